@@ -0,0 +1,317 @@
+//! Insertion, traversal, removal and defragmentation benchmarks for every tree with every storage, as well as benchmarks for iterating over siblings and for converting nodes with large payloads.
+//!
+//! Run with `cargo bench --features bench,criterion`. Enabling the `slotmap` feature adds the slot map storage to the traversal benchmarks.
+
+use core::fmt::Debug;
+use charcoal::{
+    bench_support,
+    binary_tree,
+    freeform_tree,
+    octree,
+    quadtree,
+    small_key::SmallKeyStorage,
+    storage::{Storage, SparseVec},
+    traversal::{algorithms::recursively_remove, collectors::CountNodes},
+    Traversable,
+    TraversableMut,
+    Tree,
+};
+#[cfg(feature = "slotmap")]
+use slotmap_crate::{DefaultKey, SlotMap};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use bench_support::{
+    binary_tree as generate_binary_tree,
+    octree as generate_octree,
+    quadtree as generate_quadtree,
+};
+
+const NUM_NODES: usize = 10_000;
+const SEED: u64 = 0x00C4_A8C0_A100;
+const MAX_CHILDREN: usize = 8;
+/// A payload which is expensive to move around.
+type LargePayload = [u64; 512];
+
+fn generate_freeform_tree<K, S>(
+    num_nodes: usize,
+    seed: u64,
+) -> freeform_tree::FreeformTree<u64, u64, K, S>
+where
+    S: Storage<Element = freeform_tree::Node<u64, u64, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    bench_support::freeform_tree(num_nodes, MAX_CHILDREN, seed)
+}
+
+/// Walks the tree without looking at the payloads.
+fn count_nodes<T: Traversable>(tree: &T) -> usize {
+    tree.traverse(CountNodes::<T>::new())
+}
+/// Walks the tree and reads every payload.
+fn sum_payloads<T: Tree<Branch = u64, Leaf = u64>>(tree: &T) -> u64 {
+    let mut sum = 0;
+    tree.find_map(|value| {
+        sum += *value.into_inner();
+        None::<()>
+    });
+    sum
+}
+/// Removes the first child of the root and its descendants. Since the nodes are created in random
+/// order, this leaves holes all over a sparse storage.
+fn remove_first_subtree<T>(tree: &mut T)
+where
+    T: Tree + TraversableMut<Branch = <T as Traversable>::Leaf>,
+{
+    let first_child = tree
+        .first_child_key(&tree.root_key())
+        .expect("the tree has more than one node");
+    recursively_remove(tree, first_child);
+}
+/// Removes all nodes except for the root.
+fn remove_all_children<T>(tree: &mut T)
+where
+    T: Tree + TraversableMut<Branch = <T as Traversable>::Leaf>,
+{
+    while let Some(child) = tree.first_child_key(&tree.root_key()) {
+        recursively_remove(tree, child);
+    }
+}
+
+macro_rules! bench_tree {
+    ($bench:ident, $group:literal, $module:ident, $generate:ident) => {
+        fn $bench(c: &mut Criterion) {
+            type Dense = Vec<$module::Node<u64, u64, usize>>;
+            type Sparse = SparseVec<$module::Node<u64, u64, usize>>;
+            type SmallKey = SmallKeyStorage<SparseVec<$module::Node<u64, u64, u32>>>;
+
+            let mut group = c.benchmark_group($group);
+            group.bench_function("insertion/Vec", |b| {
+                b.iter(|| $generate::<usize, Dense>(NUM_NODES, SEED))
+            });
+            group.bench_function("insertion/SparseVec", |b| {
+                b.iter(|| $generate::<usize, Sparse>(NUM_NODES, SEED))
+            });
+            group.bench_function("insertion/SmallKey", |b| {
+                b.iter(|| $generate::<u32, SmallKey>(NUM_NODES, SEED))
+            });
+
+            let dense = $generate::<usize, Dense>(NUM_NODES, SEED);
+            let sparse = $generate::<usize, Sparse>(NUM_NODES, SEED);
+            let small_key = $generate::<u32, SmallKey>(NUM_NODES, SEED);
+            group.bench_function("traversal/links/Vec", |b| b.iter(|| count_nodes(&dense)));
+            group.bench_function("traversal/links/SparseVec", |b| {
+                b.iter(|| count_nodes(&sparse))
+            });
+            group.bench_function("traversal/links/SmallKey", |b| {
+                b.iter(|| count_nodes(&small_key))
+            });
+            group.bench_function("traversal/payloads/Vec", |b| {
+                b.iter(|| sum_payloads(&dense))
+            });
+            group.bench_function("traversal/payloads/SparseVec", |b| {
+                b.iter(|| sum_payloads(&sparse))
+            });
+            group.bench_function("traversal/payloads/SmallKey", |b| {
+                b.iter(|| sum_payloads(&small_key))
+            });
+            // Slot maps check the version of the key on every access, which makes repeated lookups
+            // of the same node more noticeable than with the other storages.
+            #[cfg(feature = "slotmap")]
+            {
+                type Slots = SlotMap<DefaultKey, $module::Node<u64, u64, DefaultKey>>;
+                let slots = $generate::<DefaultKey, Slots>(NUM_NODES, SEED);
+                group.bench_function("traversal/links/SlotMap", |b| {
+                    b.iter(|| count_nodes(&slots))
+                });
+                group.bench_function("traversal/payloads/SlotMap", |b| {
+                    b.iter(|| sum_payloads(&slots))
+                });
+            }
+
+            // Removal is measured on the sparse storages, which are the ones used by default. The
+            // generic visitor is compared against the removal methods of the nodes, which are
+            // specialized for fixed-arity trees.
+            group.bench_function("removal/visitor/SparseVec", |b| {
+                b.iter_batched(
+                    || $generate::<usize, Sparse>(NUM_NODES, SEED),
+                    |mut tree| remove_all_children(&mut tree),
+                    BatchSize::LargeInput,
+                )
+            });
+            group.bench_function("removal/visitor/SmallKey", |b| {
+                b.iter_batched(
+                    || $generate::<u32, SmallKey>(NUM_NODES, SEED),
+                    |mut tree| remove_all_children(&mut tree),
+                    BatchSize::LargeInput,
+                )
+            });
+            group.bench_function("removal/node/SparseVec", |b| {
+                b.iter_batched(
+                    || $generate::<usize, Sparse>(NUM_NODES, SEED),
+                    |mut tree| {
+                        while let Some(child) = tree.first_child_key(&tree.root_key()) {
+                            $module::NodeRefMut::new_raw(&mut tree, child)
+                                .expect("the key was taken from the tree")
+                                .recursively_remove();
+                        }
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+            group.bench_function("removal/node/SmallKey", |b| {
+                b.iter_batched(
+                    || $generate::<u32, SmallKey>(NUM_NODES, SEED),
+                    |mut tree| {
+                        while let Some(child) = tree.first_child_key(&tree.root_key()) {
+                            $module::NodeRefMut::new_raw(&mut tree, child)
+                                .expect("the key was taken from the tree")
+                                .recursively_remove();
+                        }
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+
+            group.bench_function("defragmentation/SparseVec", |b| {
+                b.iter_batched(
+                    || {
+                        let mut tree = $generate::<usize, Sparse>(NUM_NODES, SEED);
+                        remove_first_subtree(&mut tree);
+                        tree
+                    },
+                    |mut tree| {
+                        tree.defragment();
+                        tree
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+            group.finish();
+        }
+    };
+}
+
+bench_tree!(
+    bench_freeform_tree,
+    "freeform_tree",
+    freeform_tree,
+    generate_freeform_tree
+);
+bench_tree!(
+    bench_binary_tree,
+    "binary_tree",
+    binary_tree,
+    generate_binary_tree
+);
+bench_tree!(bench_quadtree, "quadtree", quadtree, generate_quadtree);
+bench_tree!(bench_octree, "octree", octree, generate_octree);
+
+/// Walks a freeform tree through the children iterators of its nodes, reading every payload.
+fn sum_children<K, S>(node: freeform_tree::NodeRef<'_, u64, u64, K, S>) -> u64
+where
+    S: Storage<Element = freeform_tree::Node<u64, u64, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let children = node
+        .children()
+        .map_or(0, |children| children.map(sum_children).sum());
+    *node.value().into_inner() + children
+}
+/// Compares iterating over the siblings of freeform tree nodes with different storages.
+fn bench_sibling_iteration(c: &mut Criterion) {
+    type Sparse = SparseVec<freeform_tree::Node<u64, u64, usize>>;
+
+    let mut group = c.benchmark_group("sibling_iteration");
+    let sparse = generate_freeform_tree::<usize, Sparse>(NUM_NODES, SEED);
+    group.bench_function("freeform_tree/SparseVec", |b| {
+        b.iter(|| sum_children(sparse.root()))
+    });
+    #[cfg(feature = "slotmap")]
+    {
+        type Slots = SlotMap<DefaultKey, freeform_tree::Node<u64, u64, DefaultKey>>;
+        let slots = generate_freeform_tree::<DefaultKey, Slots>(NUM_NODES, SEED);
+        group.bench_function("freeform_tree/SlotMap", |b| {
+            b.iter(|| sum_children(slots.root()))
+        });
+    }
+    group.finish();
+}
+
+/// Compares converting a branch node with a large payload into a leaf node through an identity
+/// closure with converting it without one.
+fn bench_payload_conversion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("payload_conversion");
+    let binary_tree = || {
+        let mut tree = binary_tree::BinaryTree::<LargePayload>::new([0; 512]);
+        tree.root_mut()
+            .make_branch([1; 512], Some([2; 512]))
+            .expect("the root is a leaf");
+        tree
+    };
+    group.bench_function("binary_tree/closure", |b| {
+        b.iter_batched(
+            binary_tree,
+            |mut tree| {
+                tree.root_mut()
+                    .try_remove_children_with(|payload| payload)
+                    .expect("the children are leaves");
+                tree
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("binary_tree/keeping_payload", |b| {
+        b.iter_batched(
+            binary_tree,
+            |mut tree| {
+                tree.root_mut()
+                    .try_remove_children()
+                    .expect("the children are leaves");
+                tree
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    let freeform_tree = || {
+        let mut tree = freeform_tree::FreeformTree::<LargePayload>::new([0; 512]);
+        tree.root_mut()
+            .make_branch(vec![[1; 512], [2; 512]])
+            .expect("the root is a leaf");
+        tree
+    };
+    group.bench_function("freeform_tree/closure", |b| {
+        b.iter_batched(
+            freeform_tree,
+            |mut tree| {
+                tree.root_mut()
+                    .try_remove_children_with(|payload| payload, drop)
+                    .expect("the children are leaves");
+                tree
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("freeform_tree/keeping_payload", |b| {
+        b.iter_batched(
+            freeform_tree,
+            |mut tree| {
+                tree.root_mut()
+                    .try_remove_children(drop)
+                    .expect("the children are leaves");
+                tree
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_freeform_tree,
+    bench_binary_tree,
+    bench_quadtree,
+    bench_octree,
+    bench_sibling_iteration,
+    bench_payload_conversion
+);
+criterion_main!(benches);
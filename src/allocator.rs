@@ -0,0 +1,143 @@
+//! Storing trees in custom allocators, such as bump arenas or dedicated heaps, using the unstable [`Allocator`] trait.
+//!
+//! The storage types from Granite are created out of thin air by the [`Storage`] trait, which leaves no room for passing an allocator to them. [`SparseVecIn`] is a sparse storage like [`SparseVec`], but backed by a `Vec` in a custom allocator, which has to be supplied when it's created. Every tree has `new_in` and `with_capacity_in` constructors for using it.
+//!
+//! **Requires a nightly compiler**, since the `Allocator` trait is not stable yet (see [tracking issue for `allocator_api`]).
+//!
+//! # Example
+//! ```rust
+//! use charcoal::FreeformTree;
+//! use std::alloc::System;
+//!
+//! let mut tree = FreeformTree::new_in("root", System);
+//! tree.root_mut().make_branch(vec!["child"]).unwrap();
+//! assert_eq!(tree.num_nodes(), 2);
+//! ```
+//!
+//! [`Allocator`]: https://doc.rust-lang.org/nightly/core/alloc/trait.Allocator.html " "
+//! [`Storage`]: ../storage/trait.Storage.html " "
+//! [`SparseVecIn`]: struct.SparseVecIn.html " "
+//! [`SparseVec`]: ../storage/type.SparseVec.html " "
+//! [tracking issue for `allocator_api`]: https://github.com/rust-lang/rust/issues/32838 " "
+
+use core::{alloc::Allocator, hint, mem};
+use alloc::{alloc::Global, vec::Vec};
+use crate::storage::Storage;
+
+/// A sparse storage backed by a `Vec` in a custom allocator.
+///
+/// Removing an element leaves a hole in its place, which is filled by the next element added, so the keys of other elements stay valid, like with [`SparseVec`]. Unlike other storages, this one cannot be created without an allocator: the [`new`] and [`with_capacity`] methods of the [`Storage`] trait panic, and [`new_in`] and [`with_capacity_in`] have to be used instead, either directly or through the `new_in` and `with_capacity_in` constructors of the trees.
+///
+/// [`SparseVec`]: ../storage/type.SparseVec.html " "
+/// [`Storage`]: ../storage/trait.Storage.html " "
+/// [`new`]: ../storage/trait.Storage.html#method.new " "
+/// [`with_capacity`]: ../storage/trait.Storage.html#tymethod.with_capacity " "
+/// [`new_in`]: #method.new_in " "
+/// [`with_capacity_in`]: #method.with_capacity_in " "
+#[derive(Clone, Debug)]
+pub struct SparseVecIn<T, A: Allocator = Global> {
+    slots: Vec<Slot<T>, A>,
+    /// The index of the most recently created hole, which is the first one to be filled.
+    first_hole: Option<usize>,
+    len: usize,
+}
+#[derive(Clone, Debug)]
+enum Slot<T> {
+    Element(T),
+    /// A hole, which links to the hole created before it.
+    Hole(Option<usize>),
+}
+impl<T, A: Allocator> SparseVecIn<T, A> {
+    /// Creates an empty storage in the specified allocator. Does not allocate memory.
+    pub const fn new_in(alloc: A) -> Self {
+        Self {
+            slots: Vec::new_in(alloc),
+            first_hole: None,
+            len: 0,
+        }
+    }
+    /// Creates an empty storage in the specified allocator, with room for the specified number of elements.
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        Self {
+            slots: Vec::with_capacity_in(capacity, alloc),
+            first_hole: None,
+            len: 0,
+        }
+    }
+    /// Returns a reference to the allocator used by the storage.
+    pub fn allocator(&self) -> &A {
+        self.slots.allocator()
+    }
+    /// Returns the number of holes in the storage.
+    pub fn num_holes(&self) -> usize {
+        self.slots.len() - self.len
+    }
+}
+unsafe impl<T, A: Allocator> Storage for SparseVecIn<T, A> {
+    type Key = usize;
+    type Element = T;
+
+    fn add(&mut self, element: T) -> usize {
+        self.len += 1;
+        if let Some(hole) = self.first_hole {
+            let slot = mem::replace(&mut self.slots[hole], Slot::Element(element));
+            self.first_hole = match slot {
+                Slot::Hole(next_hole) => next_hole,
+                Slot::Element(..) => unreachable!("the list of holes points to an element"),
+            };
+            hole
+        } else {
+            self.slots.push(Slot::Element(element));
+            self.slots.len() - 1
+        }
+    }
+    #[track_caller]
+    fn remove(&mut self, key: &usize) -> T {
+        let slot = &mut self.slots[*key];
+        assert!(
+            matches!(slot, Slot::Element(..)),
+            "the element at the specified key has already been removed",
+        );
+        match mem::replace(slot, Slot::Hole(self.first_hole)) {
+            Slot::Element(element) => {
+                self.first_hole = Some(*key);
+                self.len -= 1;
+                element
+            }
+            Slot::Hole(..) => unreachable!("the slot was checked to contain an element"),
+        }
+    }
+    fn len(&self) -> usize {
+        self.len
+    }
+    #[track_caller]
+    fn with_capacity(_capacity: usize) -> Self {
+        panic!("storage in a custom allocator can only be created with new_in or with_capacity_in")
+    }
+    unsafe fn get_unchecked(&self, key: &usize) -> &T {
+        match self.slots.get_unchecked(*key) {
+            Slot::Element(element) => element,
+            // SAFETY: the caller guarantees that the key points to an element
+            Slot::Hole(..) => hint::unreachable_unchecked(),
+        }
+    }
+    unsafe fn get_unchecked_mut(&mut self, key: &usize) -> &mut T {
+        match self.slots.get_unchecked_mut(*key) {
+            Slot::Element(element) => element,
+            // SAFETY: as above
+            Slot::Hole(..) => hint::unreachable_unchecked(),
+        }
+    }
+    fn contains_key(&self, key: &usize) -> bool {
+        matches!(self.slots.get(*key), Some(Slot::Element(..)))
+    }
+    fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+    fn reserve(&mut self, additional: usize) {
+        self.slots.reserve(additional.saturating_sub(self.num_holes()));
+    }
+    fn shrink_to_fit(&mut self) {
+        self.slots.shrink_to_fit();
+    }
+}
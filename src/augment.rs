@@ -0,0 +1,608 @@
+//! Opt-in augmentations which keep derived data about the nodes of a tree up to date as it changes.
+//!
+//! The module provides the following augmentations:
+//! - [`SubtreeSizes`], which stores the number of nodes in the subtree of every branch node in its payload, wrapped in [`WithSubtreeSize`]. This makes it possible to count the nodes in a subtree without walking it, as well as to find nodes by their index in depth-first pre-order and vice versa, in time proportional to the depth of the node rather than the size of the tree.
+//! - [`Hooked`], which calls an [`OnMutate`] hook whenever children are added to or removed from a node, so that derived data of any kind, such as bounding boxes or dirty flags in branch payloads, can be maintained by the user.
+//!
+//! Augmentations are wrappers around a [`TraversableMut`] which only allow changing the structure of the tree through their own `TraversableMut` implementation, so that none of the changes can slip past them.
+//!
+//! [`SubtreeSizes`]: struct.SubtreeSizes.html " "
+//! [`WithSubtreeSize`]: struct.WithSubtreeSize.html " "
+//! [`Hooked`]: struct.Hooked.html " "
+//! [`OnMutate`]: trait.OnMutate.html " "
+//! [`TraversableMut`]: ../traversal/trait.TraversableMut.html " "
+
+use crate::{
+    traversal::{algorithms::next_in_preorder, CursorResult, VisitorDirection},
+    NodeValue,
+    Traversable,
+    TraversableMut,
+    TryRemoveLeafError,
+    TryRemoveBranchError,
+    TryRemoveChildrenError,
+    TryAddChildError,
+    MakeBranchError,
+};
+
+/// The payload of a branch node augmented with the number of nodes in its subtree, including the node itself.
+///
+/// Trees wrapped in [`SubtreeSizes`] use this as the payload type for branch nodes. The size can only be changed by `SubtreeSizes` itself; branch nodes created with [`new`] start out with a size of 1, which is corrected by `SubtreeSizes` when the node is added to the tree.
+///
+/// [`SubtreeSizes`]: struct.SubtreeSizes.html " "
+/// [`new`]: #method.new " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct WithSubtreeSize<B> {
+    /// The payload of the branch node itself.
+    pub payload: B,
+    size: usize,
+}
+impl<B> WithSubtreeSize<B> {
+    /// Wraps the payload of a branch node.
+    pub const fn new(payload: B) -> Self {
+        Self { payload, size: 1 }
+    }
+    /// Returns the number of nodes in the subtree of the node, including the node itself.
+    pub const fn subtree_size(&self) -> usize {
+        self.size
+    }
+    /// Returns the payload of the branch node, discarding the size.
+    #[allow(clippy::missing_const_for_fn)] // Clippy has no idea what a destructor is
+    pub fn into_inner(self) -> B {
+        self.payload
+    }
+}
+impl<B> From<B> for WithSubtreeSize<B> {
+    fn from(op: B) -> Self {
+        Self::new(op)
+    }
+}
+
+/// A tree which keeps track of the number of nodes in the subtree of every node.
+///
+/// The sizes are stored in the payloads of branch nodes, which is why the wrapped tree must use [`WithSubtreeSize`] as its branch payload type. The wrapper implements [`Traversable`] and [`TraversableMut`] by forwarding to the tree, hiding `WithSubtreeSize` from the payloads, and adjusts the sizes of all ancestors of a node whenever children are added or removed. Adding or removing nodes thus takes additional time proportional to the depth of the node.
+///
+/// Since the sizes would go stale if the tree was changed directly, the wrapped tree can only be accessed immutably, or taken out of the wrapper with [`into_inner`].
+///
+/// # Example
+/// ```rust
+/// use charcoal::{
+///     augment::{SubtreeSizes, WithSubtreeSize},
+///     FreeformTree,
+///     Traversable,
+///     TraversableMut,
+/// };
+///
+/// let mut tree = SubtreeSizes::new(FreeformTree::<WithSubtreeSize<u32>, u32>::new(0));
+/// let root = tree.cursor_to_root();
+/// let first = tree.try_add_child(&root, 1, |x| x).unwrap();
+/// tree.try_add_child(&first, 2, |x| x).unwrap();
+/// let last = tree.try_add_child(&root, 3, |x| x).unwrap();
+/// assert_eq!(tree.num_nodes(), 4);
+/// assert_eq!(tree.subtree_size_of(&first), 2);
+/// // Nodes can be looked up by their index in depth-first pre-order and vice versa.
+/// assert_eq!(tree.nth_in_preorder(3), Some(last));
+/// assert_eq!(tree.preorder_index_of(&first), 1);
+/// tree.try_remove_children_into(&first, |x| x, drop).unwrap();
+/// assert_eq!(tree.num_nodes(), 3);
+/// assert_eq!(tree.preorder_index_of(&last), 2);
+/// ```
+///
+/// [`WithSubtreeSize`]: struct.WithSubtreeSize.html " "
+/// [`Traversable`]: ../traversal/trait.Traversable.html " "
+/// [`TraversableMut`]: ../traversal/trait.TraversableMut.html " "
+/// [`into_inner`]: #method.into_inner " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SubtreeSizes<T>(T);
+impl<T, B> SubtreeSizes<T>
+where
+    T: TraversableMut<Branch = WithSubtreeSize<B>>,
+{
+    /// Wraps the specified tree, computing the sizes of all subtrees in it.
+    ///
+    /// The sizes stored in the tree beforehand are ignored. Computing them takes time proportional to the number of nodes multiplied by the depth of the tree.
+    pub fn new(mut traversable: T) -> Self {
+        let root = traversable.cursor_to_root();
+        let mut cursor = root.clone();
+        loop {
+            // Parents are visited before their children, so the size is reset before any of the
+            // descendants of the node add themselves to it
+            if let NodeValue::Branch(branch) = traversable.value_mut_of(&cursor) {
+                branch.size = 1;
+            }
+            let parent = traversable.parent_of(&cursor);
+            grow(&mut traversable, parent, 1);
+            match next_in_preorder(&traversable, &cursor, &root) {
+                Some(next) => cursor = next,
+                None => break,
+            }
+        }
+        Self(traversable)
+    }
+}
+impl<T> SubtreeSizes<T> {
+    /// Returns a reference to the wrapped tree.
+    pub const fn inner(&self) -> &T {
+        &self.0
+    }
+    /// Unwraps the tree, stopping the tracking of the sizes of subtrees.
+    #[allow(clippy::missing_const_for_fn)] // Clippy has no idea what a destructor is
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+impl<T, B> SubtreeSizes<T>
+where
+    T: Traversable<Branch = WithSubtreeSize<B>>,
+{
+    /// Returns the number of nodes in the tree. Unlike the number of nodes in the storage, this never includes holes left by removed nodes in sparse storage.
+    pub fn num_nodes(&self) -> usize {
+        self.subtree_size_of(&self.0.cursor_to_root())
+    }
+    /// Returns the number of nodes in the subtree of the node at the specified cursor, including the node itself.
+    ///
+    /// # Panics
+    /// Will panic if the cursor is invalid.
+    pub fn subtree_size_of(&self, cursor: &T::Cursor) -> usize {
+        match self.0.value_of(cursor) {
+            NodeValue::Branch(branch) => branch.size,
+            NodeValue::Leaf(..) => 1,
+        }
+    }
+    /// Returns the cursor to the node with the specified index in depth-first pre-order, with the root node having the index 0, or `None` if the tree has fewer nodes than that.
+    ///
+    /// The sizes of subtrees are used to skip the children which come before the node, which takes time proportional to the depth of the node multiplied by the number of children of its ancestors.
+    pub fn nth_in_preorder(&self, mut n: usize) -> Option<T::Cursor> {
+        let mut cursor = self.0.cursor_to_root();
+        'descent: while n != 0 {
+            // Skip the node we're on
+            n -= 1;
+            for child_num in 0..self.0.num_children_of(&cursor) {
+                let child = self.0.nth_child_of(&cursor, child_num)?;
+                let size = self.subtree_size_of(&child);
+                if n < size {
+                    cursor = child;
+                    continue 'descent;
+                }
+                n -= size;
+            }
+            return None;
+        }
+        Some(cursor)
+    }
+    /// Returns the index of the node at the specified cursor in depth-first pre-order, with the root node having the index 0. This is the inverse of [`nth_in_preorder`].
+    ///
+    /// # Panics
+    /// Will panic if the cursor is invalid.
+    ///
+    /// [`nth_in_preorder`]: #method.nth_in_preorder " "
+    pub fn preorder_index_of(&self, cursor: &T::Cursor) -> usize {
+        let mut index = 0;
+        let mut current = cursor.clone();
+        while let Some(parent) = self.0.parent_of(&current) {
+            // The parent itself and the subtrees of all previous siblings come before the node
+            index += 1;
+            for child_num in 0..self.0.num_children_of(&parent) {
+                let sibling = self
+                    .0
+                    .nth_child_of(&parent, child_num)
+                    .expect("failed to find node in parent's child list");
+                if sibling == current {
+                    break;
+                }
+                index += self.subtree_size_of(&sibling);
+            }
+            current = parent;
+        }
+        index
+    }
+}
+
+impl<T, B> Traversable for SubtreeSizes<T>
+where
+    T: Traversable<Branch = WithSubtreeSize<B>>,
+{
+    type Leaf = T::Leaf;
+    type Branch = B;
+    type Cursor = T::Cursor;
+
+    fn advance_cursor<V>(
+        &self,
+        cursor: Self::Cursor,
+        direction: VisitorDirection<Self::Cursor, V>,
+    ) -> CursorResult<Self::Cursor> {
+        self.0.advance_cursor(cursor, direction)
+    }
+    fn cursor_to_root(&self) -> Self::Cursor {
+        self.0.cursor_to_root()
+    }
+    fn value_of(&self, cursor: &Self::Cursor) -> NodeValue<&'_ Self::Branch, &'_ Self::Leaf> {
+        match self.0.value_of(cursor) {
+            NodeValue::Branch(branch) => NodeValue::Branch(&branch.payload),
+            NodeValue::Leaf(leaf) => NodeValue::Leaf(leaf),
+        }
+    }
+    fn parent_of(&self, cursor: &Self::Cursor) -> Option<Self::Cursor> {
+        self.0.parent_of(cursor)
+    }
+    fn num_children_of(&self, cursor: &Self::Cursor) -> usize {
+        self.0.num_children_of(cursor)
+    }
+    fn nth_child_of(&self, cursor: &Self::Cursor, child_num: usize) -> Option<Self::Cursor> {
+        self.0.nth_child_of(cursor, child_num)
+    }
+    fn contains_cursor(&self, cursor: &Self::Cursor) -> bool {
+        self.0.contains_cursor(cursor)
+    }
+}
+impl<T, B> TraversableMut for SubtreeSizes<T>
+where
+    T: TraversableMut<Branch = WithSubtreeSize<B>>,
+{
+    const CAN_REMOVE_INDIVIDUAL_CHILDREN: bool = T::CAN_REMOVE_INDIVIDUAL_CHILDREN;
+    const CAN_PACK_CHILDREN: bool = T::CAN_PACK_CHILDREN;
+    const CAN_ADD_INDIVIDUAL_CHILDREN: bool = T::CAN_ADD_INDIVIDUAL_CHILDREN;
+    type PackedChildren = T::PackedChildren;
+
+    fn value_mut_of(
+        &mut self,
+        cursor: &Self::Cursor,
+    ) -> NodeValue<&'_ mut Self::Branch, &'_ mut Self::Leaf> {
+        match self.0.value_mut_of(cursor) {
+            NodeValue::Branch(branch) => NodeValue::Branch(&mut branch.payload),
+            NodeValue::Leaf(leaf) => NodeValue::Leaf(leaf),
+        }
+    }
+    fn try_remove_leaf<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<Self::Leaf, TryRemoveLeafError> {
+        // Parents are added to the storage before their children, so removing the node does not
+        // invalidate the cursor to its parent
+        let parent = self.0.parent_of(cursor);
+        let leaf = self
+            .0
+            .try_remove_leaf(cursor, |branch| branch_to_leaf(branch.payload))?;
+        shrink(&mut self.0, parent, 1);
+        Ok(leaf)
+    }
+    fn try_remove_branch_into<BtL: FnOnce(Self::Branch) -> Self::Leaf, C: FnMut(Self::Leaf)>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+        collector: C,
+    ) -> Result<Self::Branch, TryRemoveBranchError> {
+        let parent = self.0.parent_of(cursor);
+        let num_children = self.0.num_children_of(cursor);
+        let branch = self.0.try_remove_branch_into(
+            cursor,
+            |branch| branch_to_leaf(branch.payload),
+            collector,
+        )?;
+        shrink(&mut self.0, parent, num_children + 1);
+        Ok(branch.payload)
+    }
+    fn try_remove_children_into<BtL: FnOnce(Self::Branch) -> Self::Leaf, C: FnMut(Self::Leaf)>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+        collector: C,
+    ) -> Result<(), TryRemoveChildrenError> {
+        let num_children = self.0.num_children_of(cursor);
+        self.0.try_remove_children_into(
+            cursor,
+            |branch| branch_to_leaf(branch.payload),
+            collector,
+        )?;
+        // The node itself is a leaf now and thus is skipped
+        shrink(&mut self.0, Some(cursor.clone()), num_children);
+        Ok(())
+    }
+    fn try_remove_branch<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<(Self::Branch, Self::PackedChildren), TryRemoveBranchError> {
+        let parent = self.0.parent_of(cursor);
+        let num_children = self.0.num_children_of(cursor);
+        let (branch, children) = self
+            .0
+            .try_remove_branch(cursor, |branch| branch_to_leaf(branch.payload))?;
+        shrink(&mut self.0, parent, num_children + 1);
+        Ok((branch.payload, children))
+    }
+    fn try_remove_children<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<Self::PackedChildren, TryRemoveChildrenError> {
+        let num_children = self.0.num_children_of(cursor);
+        let children = self
+            .0
+            .try_remove_children(cursor, |branch| branch_to_leaf(branch.payload))?;
+        shrink(&mut self.0, Some(cursor.clone()), num_children);
+        Ok(children)
+    }
+    fn try_add_child<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        child_payload: Self::Leaf,
+        leaf_to_branch: LtB,
+    ) -> Result<Self::Cursor, TryAddChildError<Self::Leaf>> {
+        let child = self.0.try_add_child(cursor, child_payload, |leaf| {
+            WithSubtreeSize::new(leaf_to_branch(leaf))
+        })?;
+        grow(&mut self.0, Some(cursor.clone()), 1);
+        Ok(child)
+    }
+    fn try_add_children<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        children: Self::PackedChildren,
+        leaf_to_branch: LtB,
+    ) -> Result<(), MakeBranchError<Self::Leaf, Self::PackedChildren>> {
+        self.0.try_add_children(cursor, children, |leaf| {
+            WithSubtreeSize::new(leaf_to_branch(leaf))
+        })?;
+        let num_children = self.0.num_children_of(cursor);
+        grow(&mut self.0, Some(cursor.clone()), num_children);
+        Ok(())
+    }
+}
+
+/// Hooks which are called by [`Hooked`] trees whenever children are added to or removed from a node.
+///
+/// The hook receives the tree after the change has been made, along with the cursor to the node whose children changed, and can use them to update the payload of the node, as well as those of its ancestors if the derived data depends on the whole subtree. If the node lost all of its children, it's a leaf node by the time the hook is called.
+///
+/// Both methods do nothing by default. Closures taking the tree and the cursor implement this trait by being called on both kinds of changes.
+///
+/// [`Hooked`]: struct.Hooked.html " "
+pub trait OnMutate<T: TraversableMut> {
+    /// Called after one or more children were added to the node at the specified cursor.
+    fn children_added(&mut self, _traversable: &mut T, _cursor: &T::Cursor) {}
+    /// Called after one or more children were removed from the node at the specified cursor.
+    fn children_removed(&mut self, _traversable: &mut T, _cursor: &T::Cursor) {}
+}
+impl<T, F> OnMutate<T> for F
+where
+    T: TraversableMut,
+    F: FnMut(&mut T, &T::Cursor),
+{
+    fn children_added(&mut self, traversable: &mut T, cursor: &T::Cursor) {
+        self(traversable, cursor);
+    }
+    fn children_removed(&mut self, traversable: &mut T, cursor: &T::Cursor) {
+        self(traversable, cursor);
+    }
+}
+
+/// A tree which calls an [`OnMutate`] hook whenever children are added to or removed from one of its nodes.
+///
+/// The wrapper implements [`Traversable`] and [`TraversableMut`] by forwarding to the tree and calls the hook after every successful change of the structure of the tree. Changing the payloads of nodes does not call the hook. Since the hook would not be called if the tree was changed directly, the wrapped tree can only be accessed immutably, or taken out of the wrapper with [`into_inner`].
+///
+/// # Example
+/// ```rust
+/// use charcoal::{
+///     augment::Hooked,
+///     FreeformTree,
+///     NodeValue,
+///     Traversable,
+///     TraversableMut,
+/// };
+///
+/// // Branch nodes store the number of their children, leaf nodes store an arbitrary number.
+/// let count_children = |tree: &mut FreeformTree<usize>, cursor: &usize| {
+///     let num_children = tree.num_children_of(cursor);
+///     if let NodeValue::Branch(payload) = tree.value_mut_of(cursor) {
+///         *payload = num_children;
+///     }
+/// };
+/// let mut tree = Hooked::new(FreeformTree::<usize>::new(100), count_children);
+/// let root = tree.cursor_to_root();
+/// tree.try_add_child(&root, 200, |_| 0).unwrap();
+/// let child = tree.try_add_child(&root, 300, |_| 0).unwrap();
+/// assert_eq!(tree.value_of(&root), NodeValue::Branch(&2));
+/// tree.try_remove_leaf(&child, |_| 0).unwrap();
+/// assert_eq!(tree.value_of(&root), NodeValue::Branch(&1));
+/// ```
+///
+/// [`OnMutate`]: trait.OnMutate.html " "
+/// [`Traversable`]: ../traversal/trait.Traversable.html " "
+/// [`TraversableMut`]: ../traversal/trait.TraversableMut.html " "
+/// [`into_inner`]: #method.into_inner " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Hooked<T, H> {
+    traversable: T,
+    hook: H,
+}
+impl<T, H> Hooked<T, H> {
+    /// Wraps the specified tree, calling the specified hook whenever its structure changes.
+    pub const fn new(traversable: T, hook: H) -> Self {
+        Self { traversable, hook }
+    }
+    /// Returns a reference to the wrapped tree.
+    pub const fn inner(&self) -> &T {
+        &self.traversable
+    }
+    /// Returns a reference to the hook.
+    pub const fn hook(&self) -> &H {
+        &self.hook
+    }
+    /// Returns a *mutable* reference to the hook.
+    pub fn hook_mut(&mut self) -> &mut H {
+        &mut self.hook
+    }
+    /// Unwraps the tree and the hook.
+    #[allow(clippy::missing_const_for_fn)] // Clippy has no idea what a destructor is
+    pub fn into_inner(self) -> (T, H) {
+        (self.traversable, self.hook)
+    }
+}
+impl<T, H> Traversable for Hooked<T, H>
+where
+    T: TraversableMut,
+    H: OnMutate<T>,
+{
+    type Leaf = T::Leaf;
+    type Branch = T::Branch;
+    type Cursor = T::Cursor;
+
+    fn advance_cursor<V>(
+        &self,
+        cursor: Self::Cursor,
+        direction: VisitorDirection<Self::Cursor, V>,
+    ) -> CursorResult<Self::Cursor> {
+        self.traversable.advance_cursor(cursor, direction)
+    }
+    fn cursor_to_root(&self) -> Self::Cursor {
+        self.traversable.cursor_to_root()
+    }
+    fn value_of(&self, cursor: &Self::Cursor) -> NodeValue<&'_ Self::Branch, &'_ Self::Leaf> {
+        self.traversable.value_of(cursor)
+    }
+    fn parent_of(&self, cursor: &Self::Cursor) -> Option<Self::Cursor> {
+        self.traversable.parent_of(cursor)
+    }
+    fn num_children_of(&self, cursor: &Self::Cursor) -> usize {
+        self.traversable.num_children_of(cursor)
+    }
+    fn nth_child_of(&self, cursor: &Self::Cursor, child_num: usize) -> Option<Self::Cursor> {
+        self.traversable.nth_child_of(cursor, child_num)
+    }
+    fn contains_cursor(&self, cursor: &Self::Cursor) -> bool {
+        self.traversable.contains_cursor(cursor)
+    }
+}
+impl<T, H> TraversableMut for Hooked<T, H>
+where
+    T: TraversableMut,
+    H: OnMutate<T>,
+{
+    const CAN_REMOVE_INDIVIDUAL_CHILDREN: bool = T::CAN_REMOVE_INDIVIDUAL_CHILDREN;
+    const CAN_PACK_CHILDREN: bool = T::CAN_PACK_CHILDREN;
+    const CAN_ADD_INDIVIDUAL_CHILDREN: bool = T::CAN_ADD_INDIVIDUAL_CHILDREN;
+    type PackedChildren = T::PackedChildren;
+
+    fn value_mut_of(
+        &mut self,
+        cursor: &Self::Cursor,
+    ) -> NodeValue<&'_ mut Self::Branch, &'_ mut Self::Leaf> {
+        self.traversable.value_mut_of(cursor)
+    }
+    fn try_remove_leaf<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<Self::Leaf, TryRemoveLeafError> {
+        // Parents are added to the storage before their children, so removing the node does not
+        // invalidate the cursor to its parent
+        let parent = self.traversable.parent_of(cursor);
+        let leaf = self.traversable.try_remove_leaf(cursor, branch_to_leaf)?;
+        if let Some(parent) = parent {
+            self.hook.children_removed(&mut self.traversable, &parent);
+        }
+        Ok(leaf)
+    }
+    fn try_remove_branch_into<BtL: FnOnce(Self::Branch) -> Self::Leaf, C: FnMut(Self::Leaf)>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+        collector: C,
+    ) -> Result<Self::Branch, TryRemoveBranchError> {
+        let parent = self.traversable.parent_of(cursor);
+        let branch = self
+            .traversable
+            .try_remove_branch_into(cursor, branch_to_leaf, collector)?;
+        if let Some(parent) = parent {
+            self.hook.children_removed(&mut self.traversable, &parent);
+        }
+        Ok(branch)
+    }
+    fn try_remove_children_into<BtL: FnOnce(Self::Branch) -> Self::Leaf, C: FnMut(Self::Leaf)>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+        collector: C,
+    ) -> Result<(), TryRemoveChildrenError> {
+        self.traversable
+            .try_remove_children_into(cursor, branch_to_leaf, collector)?;
+        self.hook.children_removed(&mut self.traversable, cursor);
+        Ok(())
+    }
+    fn try_remove_branch<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<(Self::Branch, Self::PackedChildren), TryRemoveBranchError> {
+        let parent = self.traversable.parent_of(cursor);
+        let removed = self.traversable.try_remove_branch(cursor, branch_to_leaf)?;
+        if let Some(parent) = parent {
+            self.hook.children_removed(&mut self.traversable, &parent);
+        }
+        Ok(removed)
+    }
+    fn try_remove_children<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<Self::PackedChildren, TryRemoveChildrenError> {
+        let children = self
+            .traversable
+            .try_remove_children(cursor, branch_to_leaf)?;
+        self.hook.children_removed(&mut self.traversable, cursor);
+        Ok(children)
+    }
+    fn try_add_child<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        child_payload: Self::Leaf,
+        leaf_to_branch: LtB,
+    ) -> Result<Self::Cursor, TryAddChildError<Self::Leaf>> {
+        let child = self
+            .traversable
+            .try_add_child(cursor, child_payload, leaf_to_branch)?;
+        self.hook.children_added(&mut self.traversable, cursor);
+        Ok(child)
+    }
+    fn try_add_children<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        children: Self::PackedChildren,
+        leaf_to_branch: LtB,
+    ) -> Result<(), MakeBranchError<Self::Leaf, Self::PackedChildren>> {
+        self.traversable
+            .try_add_children(cursor, children, leaf_to_branch)?;
+        self.hook.children_added(&mut self.traversable, cursor);
+        Ok(())
+    }
+}
+
+/// Adds the specified number of nodes to the sizes of the node at the specified cursor and all of its ancestors.
+fn grow<T, B>(traversable: &mut T, cursor: Option<T::Cursor>, num_added: usize)
+where
+    T: TraversableMut<Branch = WithSubtreeSize<B>>,
+{
+    adjust_sizes(traversable, cursor, |size| *size += num_added);
+}
+/// Subtracts the specified number of nodes from the sizes of the node at the specified cursor and all of its ancestors. Leaf nodes among them, which is what branch nodes become after losing all of their children, are skipped.
+fn shrink<T, B>(traversable: &mut T, cursor: Option<T::Cursor>, num_removed: usize)
+where
+    T: TraversableMut<Branch = WithSubtreeSize<B>>,
+{
+    adjust_sizes(traversable, cursor, |size| *size -= num_removed);
+}
+/// Calls the closure on the sizes of the node at the specified cursor and all of its ancestors, skipping leaf nodes.
+fn adjust_sizes<T, B>(
+    traversable: &mut T,
+    mut cursor: Option<T::Cursor>,
+    mut f: impl FnMut(&mut usize),
+) where
+    T: TraversableMut<Branch = WithSubtreeSize<B>>,
+{
+    while let Some(current) = cursor {
+        if let NodeValue::Branch(branch) = traversable.value_mut_of(&current) {
+            f(&mut branch.size);
+        }
+        cursor = traversable.parent_of(&current);
+    }
+}
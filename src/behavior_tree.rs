@@ -0,0 +1,241 @@
+//! Behavior trees, which describe decision making, most commonly the one of game AI, as a tree of actions combined by control flow nodes.
+//!
+//! The leaf nodes of a behavior tree are actions or conditions, which report a [`Status`] when they are run. The branch nodes are [`Composite`] nodes, which decide which of their children to run and what to report based on the statuses of the children:
+//! - a [`Sequence`] runs its children in order until one of them doesn't succeed and reports the status of that child, or success if all of them succeeded, like a logical AND;
+//! - a [`Selector`] runs its children in order until one of them doesn't fail and reports the status of that child, or failure if all of them failed, like a logical OR;
+//! - a [`Decorator`] runs its first child and transforms its status.
+//!
+//! The tree is run by the [`Tick`] visitor, which works with any [`TraversableMut`] with [`Composite`] branch payloads, or by the [`tick`] function, which is a shorthand for traversing a tree with it. The actions are run by a closure which receives a mutable reference to the payload of the leaf node, so that leaves can carry both the description of the action and its state. Ticks are stateless: every tick starts at the root node, which means that an action which reported [`Running`] is run again on the next tick, as long as the conditions before it still lead there.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{
+//!     behavior_tree::{self, BehaviorTree, Composite, Status},
+//!     repr::TreeRepr::{Branch, Leaf},
+//! };
+//!
+//! enum Action { SeeEnemy, Attack, Patrol(u32) }
+//!
+//! let mut tree = BehaviorTree::from_repr(Branch(Composite::Selector, vec![
+//!     Branch(Composite::Sequence, vec![Leaf(Action::SeeEnemy), Leaf(Action::Attack)]),
+//!     Leaf(Action::Patrol(0)),
+//! ]));
+//! let mut attacked = false;
+//! let mut run = |action: &mut Action| match action {
+//!     Action::SeeEnemy => Status::Failure,
+//!     Action::Attack => {
+//!         attacked = true;
+//!         Status::Success
+//!     }
+//!     Action::Patrol(steps) => {
+//!         *steps += 1;
+//!         Status::Running
+//!     }
+//! };
+//! assert_eq!(behavior_tree::tick(&mut tree, &mut run), Status::Running);
+//! assert_eq!(behavior_tree::tick(&mut tree, &mut run), Status::Running);
+//! assert!(!attacked);
+//! ```
+//!
+//! [`Status`]: enum.Status.html " "
+//! [`Composite`]: enum.Composite.html " "
+//! [`Sequence`]: enum.Composite.html#variant.Sequence " "
+//! [`Selector`]: enum.Composite.html#variant.Selector " "
+//! [`Decorator`]: enum.Decorator.html " "
+//! [`Tick`]: struct.Tick.html " "
+//! [`tick`]: fn.tick.html " "
+//! [`TraversableMut`]: ../traversal/trait.TraversableMut.html " "
+//! [`Running`]: enum.Status.html#variant.Running " "
+
+use core::{
+    fmt::{self, Formatter, Debug},
+    borrow::BorrowMut,
+    marker::PhantomData,
+};
+use crate::{
+    traversal::{
+        algorithms::next_sibling_of,
+        VisitorMut,
+        TraversableMut,
+        Traversable,
+        VisitorDirection,
+        CursorResult,
+        CursorDirectionError,
+    },
+    NodeValue,
+};
+#[cfg(feature = "freeform_tree")]
+use crate::freeform_tree::FreeformTree;
+
+/// A behavior tree stored in a freeform tree, with [`Composite`] nodes as branches and actions of type `L` as leaves.
+///
+/// [`Composite`]: enum.Composite.html " "
+#[cfg(feature = "freeform_tree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "freeform_tree")))]
+pub type BehaviorTree<L> = FreeformTree<Composite, L>;
+
+/// The result of running a node of a behavior tree.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Status {
+    /// The node has completed its work.
+    Success,
+    /// The node could not complete its work.
+    Failure,
+    /// The node has not completed its work yet and needs to be run again on the next tick.
+    Running,
+}
+
+/// The payload of a branch node of a behavior tree, which decides how its children are run.
+///
+/// See the [module-level documentation] for more.
+///
+/// [module-level documentation]: index.html " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Composite {
+    /// Runs the children in order until one of them doesn't succeed, reporting the status of that child, or success if all of them succeeded.
+    Sequence,
+    /// Runs the children in order until one of them doesn't fail, reporting the status of that child, or failure if all of them failed.
+    Selector,
+    /// Runs the first child and transforms its status. The rest of the children, if any, are never run.
+    Decorator(Decorator),
+}
+impl From<Decorator> for Composite {
+    fn from(op: Decorator) -> Self {
+        Self::Decorator(op)
+    }
+}
+
+/// The transformation applied by a decorator node to the status of its child. [`Running`] is always passed through unchanged.
+///
+/// [`Running`]: enum.Status.html#variant.Running " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Decorator {
+    /// Turns success into failure and vice versa.
+    Invert,
+    /// Reports success regardless of whether the child succeeded or failed.
+    AlwaysSucceed,
+    /// Reports failure regardless of whether the child succeeded or failed.
+    AlwaysFail,
+}
+impl Decorator {
+    /// Applies the transformation to the specified status.
+    #[must_use]
+    pub const fn apply(self, status: Status) -> Status {
+        match (self, status) {
+            (_, Status::Running) => Status::Running,
+            (Self::Invert, Status::Success) | (Self::AlwaysFail, _) => Status::Failure,
+            (Self::Invert, Status::Failure) | (Self::AlwaysSucceed, _) => Status::Success,
+        }
+    }
+}
+
+/// Runs one tick of a behavior tree, running the actions in the leaf nodes with the specified closure and returning the status of the root node.
+///
+/// This is a shorthand for traversing the tree with the [`Tick`] visitor. See the [module-level documentation] for an example.
+///
+/// [`Tick`]: struct.Tick.html " "
+/// [module-level documentation]: index.html " "
+pub fn tick<T, F>(tree: &mut T, action: F) -> Status
+where
+    T: TraversableMut<Branch = Composite>,
+    F: FnMut(&mut T::Leaf) -> Status,
+{
+    tree.traverse_mut(Tick::new(action))
+}
+
+/// A visitor which runs one tick of a behavior tree, running the actions in the leaf nodes with a closure and returning the status of the node it was started at.
+///
+/// Every action is run at most once per tick, since the children of composite nodes are only run for as long as the statuses of their siblings require it.
+pub struct Tick<T: Traversable, F> {
+    action: F,
+    /// The node the tick was started at, or `None` if the traversal has not started yet.
+    subtree_root: Option<T::Cursor>,
+    /// The status of the child the traversal has just returned from, or `None` if the current node is visited for the first time.
+    child_status: Option<Status>,
+    _phantom: PhantomData<fn(&mut T)>,
+}
+impl<T, F> Tick<T, F>
+where
+    T: TraversableMut<Branch = Composite>,
+    F: FnMut(&mut T::Leaf) -> Status,
+{
+    /// Creates the visitor with the specified closure for running the actions.
+    pub fn new(action: F) -> Self {
+        Self {
+            action,
+            subtree_root: None,
+            child_status: None,
+            _phantom: PhantomData,
+        }
+    }
+}
+impl<T, F> VisitorMut for Tick<T, F>
+where
+    T: TraversableMut<Branch = Composite>,
+    F: FnMut(&mut T::Leaf) -> Status,
+{
+    type Target = T;
+    type Output = Status;
+
+    fn visit_mut<C, M>(
+        &mut self,
+        mut traversable: M,
+        cursor: CursorResult<C>,
+    ) -> (VisitorDirection<C, Self::Output>, M)
+    where
+        C: From<<Self::Target as Traversable>::Cursor>
+            + Into<<Self::Target as Traversable>::Cursor>
+            + Clone
+            + Debug
+            + Eq,
+        M: BorrowMut<Self::Target>,
+    {
+        // We only ever use valid directions, so there's nothing to recover from.
+        let cursor = cursor.unwrap_or_else(CursorDirectionError::recover).into();
+        let tree = traversable.borrow_mut();
+        if self.subtree_root.is_none() {
+            self.subtree_root = Some(cursor.clone());
+        }
+        let status = match (tree.value_mut_of(&cursor), self.child_status.take()) {
+            (NodeValue::Leaf(leaf), _) => (self.action)(leaf),
+            (NodeValue::Branch(..), None) => {
+                // Visited for the first time, so the first child has to be run.
+                let first = tree
+                    .nth_child_of(&cursor, 0)
+                    .expect("branch nodes always have children");
+                return (VisitorDirection::SetTo(first.into()), traversable);
+            }
+            (NodeValue::Branch(Composite::Decorator(decorator)), Some(status)) => {
+                decorator.apply(status)
+            }
+            (NodeValue::Branch(..), Some(status)) => status,
+        };
+        if self.subtree_root.as_ref() == Some(&cursor) {
+            return (VisitorDirection::Stop(status), traversable);
+        }
+        let parent = tree
+            .parent_of(&cursor)
+            .expect("nodes below the subtree root always have a parent");
+        let keep_going = match tree.value_of(&parent) {
+            NodeValue::Branch(Composite::Sequence) => status == Status::Success,
+            NodeValue::Branch(Composite::Selector) => status == Status::Failure,
+            _ => false,
+        };
+        if keep_going {
+            if let Some(next) = next_sibling_of(tree, &cursor) {
+                return (VisitorDirection::SetTo(next.into()), traversable);
+            }
+        }
+        self.child_status = Some(status);
+        (VisitorDirection::SetTo(parent.into()), traversable)
+    }
+}
+#[allow(clippy::missing_fields_in_debug)] // Closures can't be debug-printed
+impl<T: Traversable, F> Debug for Tick<T, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tick")
+            .field("subtree_root", &self.subtree_root)
+            .field("child_status", &self.child_status)
+            .finish()
+    }
+}
@@ -0,0 +1,240 @@
+//! Deterministic tree generators for benchmarking, used by Charcoal's own benchmark suite and available for reproducing its comparisons on other workloads.
+//!
+//! Every generator takes a seed and builds the same tree for the same seed and size regardless of the storage used by the tree, so different storages can be compared on identical trees. The trees are grown by repeatedly turning a randomly chosen leaf into a branch, and the payloads of the nodes are their numbers in the order they were created, starting with 0 for the root.
+//!
+//! The benchmark suite itself lives in the `benches` directory of the repository and is run with `cargo bench --features bench,criterion`.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{bench_support, quadtree::Node, storage::SparseVec, Tree};
+//!
+//! let sparse = bench_support::quadtree::<_, SparseVec<Node<u64, u64, usize>>>(100, 42);
+//! let dense = bench_support::quadtree::<_, Vec<Node<u64, u64, usize>>>(100, 42);
+//! // The number of nodes is rounded up to fit the quadtree.
+//! assert_eq!(sparse.num_nodes(), 101);
+//! // Both trees have the same shape and payloads.
+//! assert_eq!(sparse.node_at_path(&[3, 1]).map(|node| *node.value().into_inner()),
+//!            dense.node_at_path(&[3, 1]).map(|node| *node.value().into_inner()));
+//! ```
+
+use core::fmt::Debug;
+use alloc::{vec, vec::Vec};
+use crate::storage::Storage;
+use crate::binary_tree::{self, BinaryTree};
+use crate::freeform_tree::{self, FreeformTree};
+use crate::octree::{self, Octree};
+use crate::quadtree::{self, Quadtree};
+
+/// A small and fast pseudorandom number generator which produces the same sequence of numbers for the same seed on every platform.
+///
+/// This is the `SplitMix64` algorithm, which is good enough for shaping trees but is in no way suitable for cryptography.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SplitMix64 {
+    state: u64,
+}
+impl SplitMix64 {
+    /// Creates a generator with the specified seed.
+    #[must_use]
+    pub const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+    /// Returns the next number in the sequence.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    /// Returns a number in the range `0..bound`. The numbers are very slightly biased towards the start of the range unless the bound is a power of two, which doesn't matter for benchmarking.
+    ///
+    /// # Panics
+    /// Panics if `bound` is zero.
+    #[track_caller]
+    #[allow(clippy::cast_possible_truncation)] // The result is less than a usize
+    pub fn below(&mut self, bound: usize) -> usize {
+        assert!(bound > 0, "the bound of the range must not be zero");
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+/// Removes a random key from the list of leaves.
+fn take_random_leaf<K>(leaves: &mut Vec<K>, rng: &mut SplitMix64) -> K {
+    let index = rng.below(leaves.len());
+    leaves.swap_remove(index)
+}
+
+/// Generates a freeform tree with the specified number of nodes, where every branch has between 1 and `max_children` children.
+///
+/// Trees with less than 1 node cannot exist, so a `num_nodes` of 0 produces a tree with only the root node.
+///
+/// # Panics
+/// Panics if `max_children` is zero or if the storage runs out of room for the nodes.
+#[must_use]
+pub fn freeform_tree<K, S>(
+    num_nodes: usize,
+    max_children: usize,
+    seed: u64,
+) -> FreeformTree<u64, u64, K, S>
+where
+    S: Storage<Element = freeform_tree::Node<u64, u64, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    assert!(
+        max_children > 0,
+        "branches must be allowed to have at least one child"
+    );
+    let mut rng = SplitMix64::new(seed);
+    let mut tree = FreeformTree::with_capacity(num_nodes, 0);
+    let mut leaves = vec![tree.root().into_raw_key()];
+    let mut num_created = 1;
+    while num_created < num_nodes {
+        let key = take_random_leaf(&mut leaves, &mut rng);
+        let num_children = (1 + rng.below(max_children)).min(num_nodes - num_created);
+        let mut node =
+            freeform_tree::NodeRefMut::new_raw(&mut tree, key).expect("leaves are never removed");
+        let first_payload = num_created as u64;
+        assert!(
+            node.make_branch(first_payload..first_payload + num_children as u64)
+                .is_ok(),
+            "the storage ran out of room for the nodes",
+        );
+        leaves.extend(
+            node.children_keys()
+                .expect("the node was just made a branch"),
+        );
+        num_created += num_children;
+    }
+    tree
+}
+
+/// Generates a binary tree with the specified number of nodes. All branches have two children, except for one which has only a left child if the number of nodes is even.
+///
+/// Trees with less than 1 node cannot exist, so a `num_nodes` of 0 produces a tree with only the root node.
+///
+/// # Panics
+/// Panics if the storage runs out of room for the nodes.
+#[must_use]
+pub fn binary_tree<K, S>(num_nodes: usize, seed: u64) -> BinaryTree<u64, u64, K, S>
+where
+    S: Storage<Element = binary_tree::Node<u64, u64, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let mut rng = SplitMix64::new(seed);
+    let mut tree = BinaryTree::with_capacity(num_nodes, 0);
+    let mut leaves = vec![tree.root().into_raw_key()];
+    let mut num_created = 1;
+    while num_created < num_nodes {
+        let key = take_random_leaf(&mut leaves, &mut rng);
+        let mut node =
+            binary_tree::NodeRefMut::new_raw(&mut tree, key).expect("leaves are never removed");
+        let left_payload = num_created as u64;
+        let right_payload = if num_nodes - num_created >= 2 {
+            Some(left_payload + 1)
+        } else {
+            None
+        };
+        assert!(
+            node.make_branch(left_payload, right_payload).is_ok(),
+            "the storage ran out of room for the nodes",
+        );
+        let left_key = node
+            .left_child_mut()
+            .expect("the node was just made a branch");
+        leaves.push(left_key.into_raw_key());
+        num_created += 1;
+        if let Some(right_key) = node.right_child_mut() {
+            leaves.push(right_key.into_raw_key());
+            num_created += 1;
+        }
+    }
+    tree
+}
+
+/// Generates a quadtree with at least the specified number of nodes.
+///
+/// Since every branch of a quadtree has four children, the number of nodes is always one more than a multiple of four, and is rounded up to the nearest such number.
+///
+/// # Panics
+/// Panics if the storage runs out of room for the nodes.
+#[must_use]
+pub fn quadtree<K, S>(num_nodes: usize, seed: u64) -> Quadtree<u64, u64, K, S>
+where
+    S: Storage<Element = quadtree::Node<u64, u64, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let mut rng = SplitMix64::new(seed);
+    let mut tree = Quadtree::with_capacity(num_nodes, 0);
+    let mut leaves = vec![tree.root().into_raw_key()];
+    let mut num_created = 1;
+    while num_created < num_nodes {
+        let key = take_random_leaf(&mut leaves, &mut rng);
+        let mut node =
+            quadtree::NodeRefMut::new_raw(&mut tree, key).expect("leaves are never removed");
+        let first_payload = num_created as u64;
+        let children = [
+            first_payload,
+            first_payload + 1,
+            first_payload + 2,
+            first_payload + 3,
+        ];
+        assert!(
+            node.make_branch(children).is_ok(),
+            "the storage ran out of room for the nodes",
+        );
+        for n in 0..4 {
+            let child = node
+                .nth_child_mut(n)
+                .expect("the node was just made a branch");
+            leaves.push(child.into_raw_key());
+        }
+        num_created += 4;
+    }
+    tree
+}
+
+/// Generates an octree with at least the specified number of nodes.
+///
+/// Since every branch of an octree has eight children, the number of nodes is always one more than a multiple of eight, and is rounded up to the nearest such number.
+///
+/// # Panics
+/// Panics if the storage runs out of room for the nodes.
+#[must_use]
+pub fn octree<K, S>(num_nodes: usize, seed: u64) -> Octree<u64, u64, K, S>
+where
+    S: Storage<Element = octree::Node<u64, u64, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let mut rng = SplitMix64::new(seed);
+    let mut tree = Octree::with_capacity(num_nodes, 0);
+    let mut leaves = vec![tree.root().into_raw_key()];
+    let mut num_created = 1;
+    while num_created < num_nodes {
+        let key = take_random_leaf(&mut leaves, &mut rng);
+        let mut node =
+            octree::NodeRefMut::new_raw(&mut tree, key).expect("leaves are never removed");
+        let first_payload = num_created as u64;
+        let children = [
+            first_payload,
+            first_payload + 1,
+            first_payload + 2,
+            first_payload + 3,
+            first_payload + 4,
+            first_payload + 5,
+            first_payload + 6,
+            first_payload + 7,
+        ];
+        assert!(
+            node.make_branch(children).is_ok(),
+            "the storage ran out of room for the nodes",
+        );
+        for n in 0..8 {
+            let child = node
+                .nth_child_mut(n)
+                .expect("the node was just made a branch");
+            leaves.push(child.into_raw_key());
+        }
+        num_created += 8;
+    }
+    tree
+}
@@ -0,0 +1,479 @@
+//! Priority queues shaped as implicit d-ary trees, stored in a single list without any links between the nodes.
+//!
+//! Unlike the other trees in this crate, which store the keys of the parent and the children in every node, a heap lays its nodes out in breadth-first order, which means that the position of a node in the storage is enough to find its relatives with simple index arithmetic: the children of the node at index `i` are at the indices `d * i + 1` through `d * i + d`, where `d` is the *arity* of the heap. The parent of every node is greater than or equal to the node itself, which means that the root, stored at index 0, is always the greatest element.
+//!
+//! Any [`ListStorage`] can be used to store the elements, including ones which do not require a memory allocator, such as [`ArrayVec`]. The heap also implements [`Traversable`], with the indices as cursors, so it can be inspected by the same visitors as the other trees.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::binary_heap::BinaryHeap;
+//!
+//! let mut heap = BinaryHeap::<_>::new();
+//! heap.push(3);
+//! heap.push(8);
+//! heap.push(1);
+//! assert_eq!(heap.peek(), Some(&8));
+//!
+//! // The greatest element can be modified in place, and the heap is fixed up afterwards.
+//! *heap.peek_mut().unwrap() = 0;
+//! assert_eq!(heap.pop(), Some(3));
+//! assert_eq!(heap.pop(), Some(1));
+//! assert_eq!(heap.pop(), Some(0));
+//! assert_eq!(heap.pop(), None);
+//! ```
+//!
+//! # Traversal
+//! The heap can be walked like any other tree. The last element has no next sibling, even if the place right after it would still be a child of the same parent:
+//! ```rust
+//! use charcoal::{
+//!     binary_heap::BinaryHeap,
+//!     traversal::{collectors::CountNodes, VisitorDirection},
+//!     Traversable,
+//! };
+//!
+//! let mut heap = BinaryHeap::<_>::new();
+//! heap.extend([5, 4, 3, 2].iter().copied());
+//! // The root has two children, and the first of them has a single child, which is the last element.
+//! assert_eq!(heap.num_children_of(&0), 2);
+//! assert_eq!(heap.num_children_of(&1), 1);
+//! assert!(heap
+//!     .advance_cursor(3, VisitorDirection::<_, ()>::NextSibling)
+//!     .is_err());
+//! // Every element is visited exactly once, whichever child of its parent the last element is.
+//! for arity in 2..=4 {
+//!     for len in 1..=12 {
+//!         let mut heap = BinaryHeap::<_>::with_arity(arity);
+//!         heap.extend(0..len);
+//!         assert_eq!(heap.traverse(CountNodes::new()), len);
+//!     }
+//! }
+//! ```
+//!
+//! [`ListStorage`]: ../trait.ListStorage.html " "
+//! [`ArrayVec`]: https://docs.rs/arrayvec/*/arrayvec/struct.ArrayVec.html " "
+//! [`Traversable`]: ../traversal/trait.Traversable.html " "
+
+use core::{
+    fmt::{self, Formatter, Debug},
+    ops::{Deref, DerefMut},
+    ptr,
+};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use arrayvec::ArrayVec;
+use crate::{
+    storage::ListStorage,
+    traversal::{Traversable, VisitorDirection, CursorResult, CursorDirectionError},
+    NodeValue,
+};
+
+/// The default storage type used by heaps when a storage type is not provided.
+///
+/// This is a `Vec` if the `alloc` feature is enabled and an [`ArrayVec`] *with zero-sized backing storage* otherwise, in which case [`InlineBinaryHeap`] should be used instead.
+///
+/// [`ArrayVec`]: https://docs.rs/arrayvec/*/arrayvec/struct.ArrayVec.html " "
+/// [`InlineBinaryHeap`]: type.InlineBinaryHeap.html " "
+#[cfg(feature = "alloc")]
+pub type DefaultHeapStorage<T> = Vec<T>;
+/// The default storage type used by heaps when a storage type is not provided.
+///
+/// This is a `Vec` if the `alloc` feature is enabled and an [`ArrayVec`] *with zero-sized backing storage* otherwise, in which case [`InlineBinaryHeap`] should be used instead.
+///
+/// [`ArrayVec`]: https://docs.rs/arrayvec/*/arrayvec/struct.ArrayVec.html " "
+/// [`InlineBinaryHeap`]: type.InlineBinaryHeap.html " "
+#[cfg(not(feature = "alloc"))]
+pub type DefaultHeapStorage<T> = ArrayVec<[T; 0]>;
+
+/// A max-heap with a configurable arity, which is 2 by default.
+///
+/// See the [module-level documentation] for more.
+///
+/// [module-level documentation]: index.html " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BinaryHeap<T, S = DefaultHeapStorage<T>>
+where
+    S: ListStorage<Element = T>,
+{
+    storage: S,
+    arity: usize,
+}
+impl<T, S> BinaryHeap<T, S>
+where
+    S: ListStorage<Element = T>,
+    T: Ord,
+{
+    /// Creates an empty heap in which every node has up to 2 children.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_arity(2)
+    }
+    /// Creates an empty heap in which every node has up to the specified number of children.
+    ///
+    /// Higher arities make the heap shallower, which makes `push` faster and `pop` slower, since it has to compare more children on every level.
+    ///
+    /// # Panics
+    /// Will panic if the arity is less than 2.
+    #[must_use]
+    pub fn with_arity(arity: usize) -> Self {
+        Self::from_storage(S::new(), arity)
+    }
+    /// Creates a heap with the specified arity out of the elements of the storage, rearranging them into a valid heap in linear time.
+    ///
+    /// # Panics
+    /// Will panic if the arity is less than 2.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::binary_heap::BinaryHeap;
+    ///
+    /// let mut heap = BinaryHeap::from_storage(vec![4, 9, 1, 7, 3], 3);
+    /// assert_eq!(heap.pop(), Some(9));
+    /// assert_eq!(heap.pop(), Some(7));
+    /// assert_eq!(heap.len(), 3);
+    /// ```
+    pub fn from_storage(storage: S, arity: usize) -> Self {
+        assert!(arity >= 2, "the arity of a heap must be at least 2, but was {}", arity);
+        let mut heap = Self { storage, arity };
+        let len = heap.len();
+        if len > 1 {
+            // Leaves are valid heaps on their own, so only the branches have to be sifted down,
+            // starting with the deepest ones.
+            for index in (0..=heap.parent_index(len - 1)).rev() {
+                heap.sift_down(index);
+            }
+        }
+        heap
+    }
+    /// Returns the maximum number of children of every node.
+    #[must_use]
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+    /// Returns the number of elements in the heap.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.storage.len()
+    }
+    /// Returns `true` if the heap contains no elements, `false` otherwise.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.storage.is_empty()
+    }
+    /// Returns a reference to the greatest element, or `None` if the heap is empty.
+    #[must_use]
+    pub fn peek(&self) -> Option<&T> {
+        self.storage.get(0)
+    }
+    /// Returns a mutable reference to the greatest element, or `None` if the heap is empty. The element is moved to its new place once the returned guard is dropped.
+    pub fn peek_mut(&mut self) -> Option<PeekMut<'_, T, S>> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(PeekMut { heap: self })
+        }
+    }
+    /// Adds an element to the heap.
+    ///
+    /// # Panics
+    /// Will panic if the storage has a fixed capacity and is full.
+    pub fn push(&mut self, element: T) {
+        self.storage.push(element);
+        self.sift_up(self.len() - 1);
+    }
+    /// Removes the greatest element from the heap and returns it, or returns `None` if the heap is empty.
+    pub fn pop(&mut self) -> Option<T> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+        self.swap(0, len - 1);
+        let greatest = self.storage.pop();
+        self.sift_down(0);
+        greatest
+    }
+    /// Removes all elements from the heap.
+    pub fn clear(&mut self) {
+        self.storage.truncate(0);
+    }
+    /// Returns the elements in the order in which they are stored, which is a breadth-first traversal of the tree.
+    #[must_use]
+    pub fn as_storage(&self) -> &S {
+        &self.storage
+    }
+    /// Unwraps the storage, with the elements in the order in which they were stored in the heap.
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // Clippy has no idea what a destructor is
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+
+    fn parent_index(&self, index: usize) -> usize {
+        (index - 1) / self.arity
+    }
+    fn first_child_index(&self, index: usize) -> usize {
+        index * self.arity + 1
+    }
+    fn element(&self, index: usize) -> &T {
+        debug_assert!(index < self.len(), "heap index {} out of bounds", index);
+        unsafe {
+            // SAFETY: all indices are checked against the length by the callers
+            self.storage.get_unchecked(index)
+        }
+    }
+    fn swap(&mut self, a: usize, b: usize) {
+        debug_assert!(
+            a < self.len() && b < self.len(),
+            "heap indices {} and {} out of bounds",
+            a,
+            b,
+        );
+        if a == b {
+            return;
+        }
+        unsafe {
+            // SAFETY: the indices are in bounds and different, so the pointers do not alias
+            let a: *mut T = self.storage.get_unchecked_mut(a);
+            let b: *mut T = self.storage.get_unchecked_mut(b);
+            ptr::swap(a, b);
+        }
+    }
+    /// Moves the element at the specified index up until its parent is not less than it.
+    fn sift_up(&mut self, mut index: usize) {
+        while index > 0 {
+            let parent = self.parent_index(index);
+            if self.element(parent) >= self.element(index) {
+                break;
+            }
+            self.swap(parent, index);
+            index = parent;
+        }
+    }
+    /// Moves the element at the specified index down until none of its children are greater than it.
+    fn sift_down(&mut self, mut index: usize) {
+        let len = self.len();
+        loop {
+            let first_child = self.first_child_index(index);
+            if first_child >= len {
+                break;
+            }
+            let last_child = (first_child + self.arity).min(len);
+            let greatest_child = (first_child + 1..last_child).fold(first_child, |greatest, child| {
+                if self.element(child) > self.element(greatest) {
+                    child
+                } else {
+                    greatest
+                }
+            });
+            if self.element(index) >= self.element(greatest_child) {
+                break;
+            }
+            self.swap(index, greatest_child);
+            index = greatest_child;
+        }
+    }
+}
+impl<T, S> Default for BinaryHeap<T, S>
+where
+    S: ListStorage<Element = T>,
+    T: Ord,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T, S> Extend<T> for BinaryHeap<T, S>
+where
+    S: ListStorage<Element = T>,
+    T: Ord,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for element in iter {
+            self.push(element);
+        }
+    }
+}
+
+impl<T, S> Traversable for BinaryHeap<T, S>
+where
+    S: ListStorage<Element = T>,
+    T: Ord,
+{
+    type Leaf = T;
+    type Branch = T;
+    type Cursor = usize;
+
+    fn advance_cursor<V>(
+        &self,
+        cursor: Self::Cursor,
+        direction: VisitorDirection<Self::Cursor, V>,
+    ) -> CursorResult<Self::Cursor> {
+        let error = CursorDirectionError {
+            previous_state: cursor,
+        };
+        assert!(cursor < self.len(), "invalid cursor: {}", cursor);
+        let new_cursor = match direction {
+            VisitorDirection::Parent => self.parent_of(&cursor),
+            VisitorDirection::NthAncestor(n) => {
+                (0..n).try_fold(cursor, |ancestor, _| self.parent_of(&ancestor))
+            }
+            VisitorDirection::Root => Some(0),
+            VisitorDirection::NextSibling => {
+                if cursor != 0 && cursor % self.arity != 0 && cursor + 1 < self.len() {
+                    Some(cursor + 1)
+                } else {
+                    None
+                }
+            }
+            VisitorDirection::PrevSibling => {
+                if cursor != 0 && (cursor - 1) % self.arity != 0 {
+                    Some(cursor - 1)
+                } else {
+                    None
+                }
+            }
+            VisitorDirection::Child(num) => self.nth_child_of(&cursor, num as usize),
+            VisitorDirection::SetTo(new_cursor) => Some(new_cursor),
+            VisitorDirection::Stop(..) => None,
+        };
+        // Do not allow returning invalid cursors, as those will cause panicking
+        new_cursor
+            .filter(|&new_cursor| new_cursor < self.len())
+            .ok_or(error)
+    }
+    fn cursor_to_root(&self) -> Self::Cursor {
+        0
+    }
+    #[track_caller]
+    fn value_of(&self, cursor: &Self::Cursor) -> NodeValue<&'_ Self::Branch, &'_ Self::Leaf> {
+        let element = self
+            .storage
+            .get(*cursor)
+            .unwrap_or_else(|| panic!("invalid cursor: {}", cursor));
+        if self.first_child_index(*cursor) < self.len() {
+            NodeValue::Branch(element)
+        } else {
+            NodeValue::Leaf(element)
+        }
+    }
+    #[track_caller]
+    fn parent_of(&self, cursor: &Self::Cursor) -> Option<Self::Cursor> {
+        assert!(*cursor < self.len(), "invalid cursor: {}", cursor);
+        if *cursor == 0 {
+            None
+        } else {
+            Some(self.parent_index(*cursor))
+        }
+    }
+    #[track_caller]
+    fn num_children_of(&self, cursor: &Self::Cursor) -> usize {
+        assert!(*cursor < self.len(), "invalid cursor: {}", cursor);
+        let first_child = self.first_child_index(*cursor);
+        self.len().saturating_sub(first_child).min(self.arity)
+    }
+    #[track_caller]
+    fn nth_child_of(&self, cursor: &Self::Cursor, child_num: usize) -> Option<Self::Cursor> {
+        if child_num < self.num_children_of(cursor) {
+            Some(self.first_child_index(*cursor) + child_num)
+        } else {
+            None
+        }
+    }
+    fn contains_cursor(&self, cursor: &Self::Cursor) -> bool {
+        *cursor < self.len()
+    }
+}
+
+/// A heap which stores its elements in an `ArrayVec`, i.e. doesn't require a memory allocator.
+///
+/// The capacity is specified by the backing array type, e.g. `InlineBinaryHeap<[u32; 16]>` can hold up to 16 elements.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{
+///     binary_heap::{InlineBinaryHeap, PeekMut},
+///     traversal::collectors::CountNodes,
+///     Traversable,
+/// };
+///
+/// let mut heap = InlineBinaryHeap::<[u32; 8]>::new();
+/// heap.extend([5, 2, 8, 1].iter().copied());
+/// // The heap is a tree like any other.
+/// assert_eq!(heap.traverse(CountNodes::new()), 4);
+/// assert_eq!(heap.num_children_of(&heap.cursor_to_root()), 2);
+///
+/// let greatest = heap.peek_mut().unwrap();
+/// assert_eq!(PeekMut::pop(greatest), 8);
+/// assert_eq!(heap.peek(), Some(&5));
+/// ```
+pub type InlineBinaryHeap<A> = BinaryHeap<<A as arrayvec::Array>::Item, ArrayVec<A>>;
+
+/// A mutable reference to the greatest element of a [`BinaryHeap`], returned by [`peek_mut`].
+///
+/// When the guard is dropped, the element is moved down the heap if it has been made smaller than some of the other elements.
+///
+/// [`BinaryHeap`]: struct.BinaryHeap.html " "
+/// [`peek_mut`]: struct.BinaryHeap.html#method.peek_mut " "
+pub struct PeekMut<'a, T, S>
+where
+    S: ListStorage<Element = T>,
+    T: Ord,
+{
+    heap: &'a mut BinaryHeap<T, S>,
+}
+impl<T, S> PeekMut<'_, T, S>
+where
+    S: ListStorage<Element = T>,
+    T: Ord,
+{
+    /// Removes the element from the heap and returns it.
+    #[must_use]
+    #[allow(
+        clippy::missing_panics_doc,
+        clippy::needless_pass_by_value, // Consuming the guard is the whole point
+    )]
+    pub fn pop(this: Self) -> T {
+        // The destructor still runs afterwards, but sifting down the new root of a valid heap
+        // stops right away.
+        this.heap.pop().expect("the heap was checked to be non-empty")
+    }
+}
+impl<T, S> Deref for PeekMut<'_, T, S>
+where
+    S: ListStorage<Element = T>,
+    T: Ord,
+{
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.heap.element(0)
+    }
+}
+impl<T, S> DerefMut for PeekMut<'_, T, S>
+where
+    S: ListStorage<Element = T>,
+    T: Ord,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe {
+            // SAFETY: the heap was checked to be non-empty when the guard was created
+            self.heap.storage.get_unchecked_mut(0)
+        }
+    }
+}
+impl<T, S> Drop for PeekMut<'_, T, S>
+where
+    S: ListStorage<Element = T>,
+    T: Ord,
+{
+    fn drop(&mut self) {
+        self.heap.sift_down(0);
+    }
+}
+impl<T, S> Debug for PeekMut<'_, T, S>
+where
+    S: ListStorage<Element = T>,
+    T: Ord + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PeekMut").field(&**self).finish()
+    }
+}
@@ -1,4 +1,23 @@
-use crate::storage::{Storage, ListStorage, DefaultStorage, SparseStorage, SparseStorageSlot};
+use crate::{
+    storage::{Storage, ListStorage, DefaultStorage, SparseStorage, SparseStorageSlot},
+    util::{
+        has_room_for,
+        clear_storage,
+        defragment_tree,
+        map_values_in_tree,
+        unreachable_debugchecked,
+    },
+    CapacityError,
+    StableStorage,
+    NodeValue,
+    Tree,
+};
+#[cfg(feature = "alloc")]
+use crate::util::collect_unreachable_in_tree;
+#[cfg(feature = "allocator_api")]
+use core::alloc::Allocator;
+#[cfg(feature = "allocator_api")]
+use crate::allocator::SparseVecIn;
 use super::*;
 
 /// A binary tree.
@@ -14,6 +33,8 @@ where
 {
     pub(super) storage: S,
     pub(super) root: K,
+    pub(super) removal_policy: RemovalPolicy,
+    pub(super) full_branches_only: bool,
 }
 impl<B, L, K, S> BinaryTree<B, L, K, S>
 where
@@ -40,7 +61,44 @@ where
             // SAFETY: there isn't a root there yet
             Node::root(root)
         });
-        Self { storage, root }
+        Self {
+            storage,
+            root,
+            removal_policy: RemovalPolicy::default(),
+            full_branches_only: false,
+        }
+    }
+    /// Creates a binary tree with the specified value for the root node, returning an error instead of panicking if the storage cannot fit the root node.
+    ///
+    /// # Errors
+    /// Will fail if the storage has a fixed capacity of zero. In such a case, the payload for the root node is returned back to the caller.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::binary_tree::{InlineBinaryTree, Node};
+    ///
+    /// // A storage with a capacity of zero cannot fit the root node:
+    /// let tree = InlineBinaryTree::<[Node<u32, u32, usize>; 0], u32>::try_new(87);
+    /// assert_eq!(tree.unwrap_err().payload, 87);
+    /// // Anything bigger than that works fine:
+    /// let tree = InlineBinaryTree::<[Node<u32, u32, usize>; 1], u32>::try_new(87).unwrap();
+    /// assert!(tree.root().is_leaf());
+    /// ```
+    pub fn try_new(root: L) -> Result<Self, CapacityError<L>> {
+        let mut storage = S::new();
+        if !has_room_for(&storage, 1) {
+            return Err(CapacityError { payload: root });
+        }
+        let root = storage.add(unsafe {
+            // SAFETY: as above
+            Node::root(root)
+        });
+        Ok(Self {
+            storage,
+            root,
+            removal_policy: RemovalPolicy::default(),
+            full_branches_only: false,
+        })
     }
     /// Creates a binary tree with the specified capacity for the storage.
     ///
@@ -70,7 +128,12 @@ where
             // SAFETY: as above
             Node::root(root)
         });
-        Self { storage, root }
+        Self {
+            storage,
+            root,
+            removal_policy: RemovalPolicy::default(),
+            full_branches_only: false,
+        }
     }
     /// Returns a reference to the root node of the tree.
     ///
@@ -114,6 +177,38 @@ where
             NodeRefMut::new_raw_unchecked(self, self.root.clone())
         }
     }
+    /// Consumes the tree and returns the payload of its root node, dropping all other nodes along with the storage.
+    ///
+    /// This saves the need to clone the payload out of the tree once it's no longer needed, such as when the root node holds the result of a computation which went through the whole tree. To replace the payload of the root node without consuming the tree, use [`Tree::set_root_value`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{BinaryTree, NodeValue};
+    ///
+    /// let mut tree = BinaryTree::<_>::new(0);
+    /// tree.root_mut().make_branch(1, Some(2)).unwrap();
+    /// *tree.root_mut().value_mut().into_inner() = 10;
+    /// assert_eq!(tree.into_root_value(), NodeValue::Branch(10));
+    /// ```
+    ///
+    /// [`Tree::set_root_value`]: ../tree/trait.Tree.html#method.set_root_value " "
+    pub fn into_root_value(mut self) -> NodeValue<B, L> {
+        self.storage.remove(&self.root).value.into_value()
+    }
+    /// Returns a reference to the first node, in depth-first pre-order, the value of which satisfies the specified predicate, or `None` if there's no such node.
+    ///
+    /// This is the same as [`Tree::find`], but returns a reference specific to binary trees.
+    ///
+    /// [`Tree::find`]: ../tree/trait.Tree.html#method.find " "
+    pub fn find(
+        &self,
+        predicate: impl FnMut(NodeValue<&'_ B, &'_ L>) -> bool,
+    ) -> Option<NodeRef<'_, B, L, K, S>> {
+        Tree::find(self, predicate).map(|node| unsafe {
+            // SAFETY: the key was just found in the tree
+            NodeRef::new_raw_unchecked(self, node.into_raw_key())
+        })
+    }
     /// Returns the number of nodes in the tree.
     pub fn num_nodes(&self) -> usize {
         self.storage.len()
@@ -124,22 +219,131 @@ where
     }
     /// Reserves capacity for at least additional more elements to be inserted in the given storage. The storage may reserve more space to avoid frequent reallocations. After calling `reserve`, capacity will be greater than or equal to `self.len()` + `additional`. Does nothing if capacity is already sufficient.
     pub fn reserve(&mut self, additional: usize) {
-        self.storage.reserve(additional)
+        self.storage.reserve(additional);
     }
     /// Shrinks the capacity of the storage as much as possible.
     ///
     /// It will drop down as close as possible to the current length, though dynamically allocated storages may not always reallocate exactly as much as it is needed to store all elements and none more.
     pub fn shrink_to_fit(&mut self) {
-        self.storage.shrink_to_fit()
+        self.storage.shrink_to_fit();
+    }
+    /// Returns the policy which decides what happens to the right child of a full branch node when its left child is removed.
+    ///
+    /// See [`RemovalPolicy`] for more.
+    ///
+    /// [`RemovalPolicy`]: enum.RemovalPolicy.html " "
+    pub fn removal_policy(&self) -> RemovalPolicy {
+        self.removal_policy
+    }
+    /// Sets the policy which decides what happens to the right child of a full branch node when its left child is removed.
+    ///
+    /// See [`RemovalPolicy`] for more.
+    ///
+    /// [`RemovalPolicy`]: enum.RemovalPolicy.html " "
+    pub fn set_removal_policy(&mut self, policy: RemovalPolicy) {
+        self.removal_policy = policy;
+    }
+    /// Returns `true` if the tree only allows full branch nodes, `false` otherwise.
+    ///
+    /// See [`enforce_full_branches`] for more.
+    ///
+    /// [`enforce_full_branches`]: #method.enforce_full_branches " "
+    pub fn enforces_full_branches(&self) -> bool {
+        self.full_branches_only
+    }
+    /// Makes the tree only allow full branch nodes, i.e. branch nodes with both a left and a right child, turning it into a *full binary tree*.
+    ///
+    /// Afterwards, operations which would leave a branch node with only one child fail instead, leaving the tree unchanged:
+    /// - [`try_make_branch`] fails with [`TryMakeBranchError::PartialBranch`] if no right child is provided, and [`make_branch`] panics;
+    /// - [`try_remove_leaf`] and [`try_remove_branch`] fail with `CannotRemoveIndividualChildren` for every node, and [`recursively_remove`] panics, since every node other than the root node has a sibling which would be left alone — the children of a node can only be removed together, with [`try_remove_children`];
+    /// - [`reparent_to`] fails with [`ReparentError::WouldMakePartialBranch`], unless the node is moved to its own parent, which swaps the children;
+    /// - [`replace_subtree`] panics if the replacement has partial branch nodes.
+    ///
+    /// # Errors
+    /// Will fail if the tree already has a partial branch node, returning the key of the first one in depth-first pre-order, in which case the tree keeps allowing partial branch nodes.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{BinaryTree, TryMakeBranchError, TryRemoveLeafError};
+    ///
+    /// let mut tree = BinaryTree::<_>::new("+");
+    /// tree.enforce_full_branches().unwrap();
+    /// assert!(matches!(
+    ///     tree.root_mut().try_make_branch("1", None),
+    ///     Err(TryMakeBranchError::PartialBranch { .. }),
+    /// ));
+    /// tree.root_mut().try_make_branch("1", Some("2")).unwrap();
+    /// assert_eq!(
+    ///     tree.root_mut().left_child_mut().unwrap().try_remove_leaf(),
+    ///     Err(TryRemoveLeafError::CannotRemoveIndividualChildren),
+    /// );
+    /// // Both children can still be removed at once.
+    /// tree.root_mut().try_remove_children().unwrap();
+    ///
+    /// // A tree with a partial branch node cannot become a full binary tree.
+    /// let mut tree = BinaryTree::<_>::new("-");
+    /// tree.root_mut().make_branch("1", None).unwrap();
+    /// let root_key = tree.root().into_raw_key();
+    /// assert_eq!(tree.enforce_full_branches().unwrap_err().key, root_key);
+    /// ```
+    ///
+    /// [`try_make_branch`]: struct.NodeRefMut.html#method.try_make_branch " "
+    /// [`TryMakeBranchError::PartialBranch`]: ../enum.TryMakeBranchError.html#variant.PartialBranch " "
+    /// [`make_branch`]: struct.NodeRefMut.html#method.make_branch " "
+    /// [`try_remove_leaf`]: struct.NodeRefMut.html#method.try_remove_leaf " "
+    /// [`try_remove_branch`]: struct.NodeRefMut.html#method.try_remove_branch " "
+    /// [`recursively_remove`]: struct.NodeRefMut.html#method.recursively_remove " "
+    /// [`try_remove_children`]: struct.NodeRefMut.html#method.try_remove_children " "
+    /// [`reparent_to`]: struct.NodeRefMut.html#method.reparent_to " "
+    /// [`ReparentError::WouldMakePartialBranch`]: ../enum.ReparentError.html#variant.WouldMakePartialBranch " "
+    /// [`replace_subtree`]: #method.replace_subtree " "
+    pub fn enforce_full_branches(&mut self) -> Result<(), PartialBranchError<K>> {
+        if let Some(key) = first_partial_branch(&self.storage, &self.root) {
+            return Err(PartialBranchError { key });
+        }
+        self.full_branches_only = true;
+        Ok(())
+    }
+    /// Makes the tree allow partial branch nodes again after a call to [`enforce_full_branches`]. This is the default.
+    ///
+    /// [`enforce_full_branches`]: #method.enforce_full_branches " "
+    pub fn allow_partial_branches(&mut self) {
+        self.full_branches_only = false;
     }
 }
 impl<B, L, S> BinaryTree<B, L, usize, SparseStorage<Node<B, L, usize>, S>>
 where
     S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
 {
+    /// Removes all nodes from the tree and replaces them with a new root leaf node with the specified payload, keeping the capacity of the storage, so that a tree which is rebuilt over and over again, such as once per frame, only allocates memory when it grows beyond its previous size.
+    ///
+    /// All keys to the old nodes become invalid, including the one to the old root node. The removal policy and the enforcement of full branches are kept. If the storage has holes, they are removed first, like [`defragment`] does, which makes this slower than clearing a tree which had no nodes removed.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::BinaryTree;
+    ///
+    /// let mut tree = BinaryTree::<_>::with_capacity(3, "Root");
+    /// tree.root_mut().make_branch("Left", Some("Right")).unwrap();
+    /// let capacity = tree.capacity();
+    ///
+    /// tree.clear("New root");
+    /// assert!(tree.root().is_leaf());
+    /// assert_eq!(*tree.root().value().into_inner(), "New root");
+    /// assert_eq!(tree.num_nodes(), 1);
+    /// assert_eq!(tree.capacity(), capacity);
+    /// ```
+    ///
+    /// [`defragment`]: #method.defragment " "
+    pub fn clear(&mut self, root: L) {
+        self.root = clear_storage(&mut self.storage, unsafe {
+            // SAFETY: the old root is removed along with all other nodes
+            Node::root(root)
+        });
+    }
     /// Removes all holes from the sparse storage.
     ///
-    /// Under the hood, this uses `defragment_and_fix`. It's not possible to defragment without fixing the indicies, as that might cause undefined behavior.
+    /// Nodes which are stored past the new end of the storage are moved into the holes, which changes their keys, and the links between the nodes are fixed along the way.
     ///
     /// # Example
     /// ```rust
@@ -169,7 +373,47 @@ where
     /// assert_eq!(tree.num_holes(), 0);
     /// ```
     pub fn defragment(&mut self) {
-        self.storage.defragment_and_fix()
+        self.defragment_with(|_, _| {});
+    }
+    /// Removes all holes from the sparse storage like [`defragment`] does, calling the specified closure with the old and new key of every node which was moved. This allows keys which were stored outside of the tree to be updated instead of becoming invalid.
+    ///
+    /// [`defragment`]: #method.defragment " "
+    pub fn defragment_with(&mut self, on_move: impl FnMut(usize, usize)) {
+        defragment_tree(self, |tree| (&mut tree.storage, &mut tree.root), on_move);
+    }
+    /// Removes the nodes which are in the storage but cannot be reached from the root node, returning the number of nodes which were removed.
+    ///
+    /// Such nodes never appear when the tree is only modified through its safe interface, but can be left behind by code which works with raw keys or by operations which panic halfway through adding nodes, after which they would stay in the storage forever without any way to access them. The keys of all reachable nodes stay the same, and the storage is shrunk to end after the last of them, which also removes the holes at the end of the storage.
+    ///
+    /// The tree is walked once to find the reachable nodes and, if there are any other ones, the reachable nodes are moved into a new storage, which takes *O*(*n*) time, where *n* is the number of slots in the storage, including the holes.
+    ///
+    /// # Example
+    /// See the example in [`FreeformTree::collect_unreachable`].
+    ///
+    /// [`FreeformTree::collect_unreachable`]: ../freeform_tree/struct.FreeformTree.html#method.collect_unreachable " "
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+    pub fn collect_unreachable(&mut self) -> usize {
+        collect_unreachable_in_tree(self, |tree| &mut tree.storage)
+    }
+    /// Applies the specified closures to the payloads of all nodes of the tree, the first one for branch nodes and the second one for leaf nodes, without changing the structure of the tree.
+    ///
+    /// This shadows [`Tree::map_values_mut`]: if the storage has no holes, the nodes are processed in the order in which they're stored rather than by walking the tree, which makes bulk updates of the payloads friendlier to the cache. If there are holes, the tree is walked in depth-first pre-order, as with the trait method. Either way, this takes *O*(*n*) time and doesn't allocate memory. Nodes which are in the storage but [cannot be reached from the root node] are only visited if there are no holes.
+    ///
+    /// # Example
+    /// See the example in [`FreeformTree::map_values_mut`].
+    ///
+    /// [`Tree::map_values_mut`]: ../tree/trait.Tree.html#method.map_values_mut " "
+    /// [cannot be reached from the root node]: #method.collect_unreachable " "
+    /// [`FreeformTree::map_values_mut`]: ../freeform_tree/struct.FreeformTree.html#method.map_values_mut " "
+    pub fn map_values_mut(&mut self, branch_fn: impl FnMut(&mut B), leaf_fn: impl FnMut(&mut L)) {
+        map_values_in_tree(
+            self,
+            |tree| &mut tree.storage,
+            |node| node.value.as_mut().into_value(),
+            branch_fn,
+            leaf_fn,
+        );
     }
     /// Returns the number of holes in the storage. This operation returns immediately instead of looping through the entire storage, since the sparse storage automatically tracks the number of holes it creates and destroys.
     ///
@@ -191,6 +435,57 @@ where
     }
 }
 
+#[cfg(feature = "allocator_api")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "allocator_api")))]
+impl<B, L, A> BinaryTree<B, L, usize, SparseVecIn<Node<B, L, usize>, A>>
+where
+    A: Allocator,
+{
+    /// Creates a binary tree with the specified value for the root node, storing the nodes in the specified allocator.
+    ///
+    /// See the [`allocator`] module for an example.
+    ///
+    /// [`allocator`]: ../allocator/index.html " "
+    pub fn new_in(root: L, alloc: A) -> Self {
+        Self::with_capacity_in(0, root, alloc)
+    }
+    /// Creates a binary tree with the specified value for the root node and the specified capacity for the storage, storing the nodes in the specified allocator.
+    pub fn with_capacity_in(capacity: usize, root: L, alloc: A) -> Self {
+        let mut storage = SparseVecIn::with_capacity_in(capacity, alloc);
+        let root = storage.add(unsafe {
+            // SAFETY: there isn't a root there yet
+            Node::root(root)
+        });
+        Self {
+            storage,
+            root,
+            removal_policy: RemovalPolicy::default(),
+            full_branches_only: false,
+        }
+    }
+}
+impl<B, L, K, S> BinaryTree<B, L, K, S>
+where
+    S: StableStorage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Returns a reference to the node with the specified key, or `None` if there's no such node in the tree.
+    ///
+    /// Since the storage is a [`StableStorage`], a key obtained from a node stays valid and keeps leading to that node until the node is removed, regardless of changes to other nodes.
+    ///
+    /// [`StableStorage`]: ../trait.StableStorage.html " "
+    pub fn get_by_key(&self, key: K) -> Option<NodeRef<'_, B, L, K, S>> {
+        NodeRef::new_raw(self, key)
+    }
+    /// Returns a *mutable* reference to the node with the specified key, or `None` if there's no such node in the tree.
+    ///
+    /// Since the storage is a [`StableStorage`], a key obtained from a node stays valid and keeps leading to that node until the node is removed, regardless of changes to other nodes.
+    ///
+    /// [`StableStorage`]: ../trait.StableStorage.html " "
+    pub fn get_mut_by_key(&mut self, key: K) -> Option<NodeRefMut<'_, B, L, K, S>> {
+        NodeRefMut::new_raw(self, key)
+    }
+}
 impl<B, L, K, S> Default for BinaryTree<B, L, K, S>
 where
     L: Default,
@@ -201,3 +496,50 @@ where
         Self::new(L::default())
     }
 }
+
+/// Returns the key of the first partial branch node in depth-first pre-order in the subtree of the specified node, or `None` if there are none.
+pub(super) fn first_partial_branch<B, L, K, S>(storage: &S, subtree_root: &K) -> Option<K>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let node = |key: &K| unsafe {
+        // SAFETY: keys are only taken from the links between the nodes
+        storage.get_unchecked(key)
+    };
+    let mut key = subtree_root.clone();
+    loop {
+        match &node(&key).value {
+            NodeData::Branch {
+                right_child: None, ..
+            } => return Some(key),
+            NodeData::Branch { left_child, .. } => {
+                key = left_child.clone();
+                continue;
+            }
+            NodeData::Leaf(..) => {}
+        }
+        // Climb up until we find a left child, the right sibling of which is next. Every branch
+        // node on the way has a right child, since the walk stops at the first one that doesn't.
+        loop {
+            if &key == subtree_root {
+                return None;
+            }
+            let parent = node(&key).parent.clone().unwrap_or_else(|| unsafe {
+                unreachable_debugchecked("the walk never goes above the subtree root")
+            });
+            if let NodeData::Branch {
+                left_child,
+                right_child: Some(right_child),
+                ..
+            } = &node(&parent).value
+            {
+                if *left_child == key {
+                    key = right_child.clone();
+                    break;
+                }
+            }
+            key = parent;
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Evaluation of expression trees, such as abstract syntax trees of arithmetic expressions.
+//!
+//! In an expression tree, leaf nodes hold operands and branch nodes hold operators, which are applied to the values of their children: binary operators to both children and unary operators to the left one, since that's the only child of a partial branch. The [`evaluate`] function does exactly that with the help of two closures, one for the operands and one for the operators, without using recursion, which means that it doesn't overflow the stack on deeply nested expressions.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{binary_tree::{BinaryTree, expr}, repr::BinaryTreeRepr};
+//!
+//! #[derive(Debug)]
+//! enum Op { Add, Mul, Neg }
+//! use BinaryTreeRepr::{Branch, Leaf};
+//!
+//! // -(2 + 3) * 4
+//! let repr = Branch(
+//!     Op::Mul,
+//!     Box::new(Branch(
+//!         Op::Neg,
+//!         Box::new(Branch(Op::Add, Box::new(Leaf(2)), Some(Box::new(Leaf(3))))),
+//!         None,
+//!     )),
+//!     Some(Box::new(Leaf(4))),
+//! );
+//! let tree = BinaryTree::<Op, i32>::from_repr(repr);
+//! let result = expr::evaluate(&tree, |&operand| operand, |op, left, right| match (op, right) {
+//!     (Op::Add, Some(right)) => left + right,
+//!     (Op::Mul, Some(right)) => left * right,
+//!     (Op::Neg, None) => -left,
+//!     _ => panic!("wrong number of operands for {:?}", op),
+//! });
+//! assert_eq!(result, -20);
+//! ```
+//!
+//! [`evaluate`]: fn.evaluate.html " "
+
+use core::fmt::Debug;
+use alloc::vec::Vec;
+use crate::{storage::Storage, NodeValue};
+use super::{BinaryTree, Node, NodeRef};
+
+/// Evaluates the expression represented by the tree, returning the value of the root node.
+///
+/// The value of a leaf node is computed by `eval_operand` from its payload. The value of a branch node is computed by `eval_operator` from its payload, the value of its left child and the value of its right child, or `None` if it's a partial branch. The children are always evaluated before their parent, left before right.
+///
+/// See the [module-level documentation] for an example.
+///
+/// [module-level documentation]: index.html " "
+#[allow(clippy::missing_panics_doc)] // The expects can only fail if the tree is malformed
+pub fn evaluate<B, L, K, S, R>(
+    tree: &BinaryTree<B, L, K, S>,
+    mut eval_operand: impl FnMut(&L) -> R,
+    mut eval_operator: impl FnMut(&B, R, Option<R>) -> R,
+) -> R
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    // Nodes which still have to be evaluated, paired with whether their children have already
+    // been pushed onto the stack, and the values of the nodes which have been evaluated but whose
+    // parents have not.
+    let mut stack: Vec<(NodeRef<'_, B, L, K, S>, bool)> = Vec::new();
+    let mut values = Vec::new();
+    stack.push((tree.root(), false));
+    while let Some((node, children_pushed)) = stack.pop() {
+        match node.value() {
+            NodeValue::Leaf(operand) => values.push(eval_operand(operand)),
+            NodeValue::Branch(..) if !children_pushed => {
+                let left_child = node.left_child();
+                let right_child = node.right_child();
+                stack.push((node, true));
+                // Pushed in reverse, so that the left child ends up being evaluated first
+                stack.extend(right_child.map(|child| (child, false)));
+                stack.extend(left_child.map(|child| (child, false)));
+            }
+            NodeValue::Branch(operator) => {
+                let right = if node.is_full_branch() {
+                    values.pop()
+                } else {
+                    None
+                };
+                let left = values
+                    .pop()
+                    .expect("the children were evaluated before their parent");
+                values.push(eval_operator(operator, left, right));
+            }
+        }
+    }
+    values.pop().expect("the root node was evaluated")
+}
@@ -6,6 +6,8 @@ use crate::{
     TryRemoveBranchError,
     TryRemoveLeafError,
     TryRemoveChildrenError,
+    TryAddChildError,
+    MakeBranchError,
 };
 use arrayvec::ArrayVec;
 use super::*;
@@ -31,23 +33,42 @@ where
         let node = NodeRef::new_raw(self, cursor)
             .expect("the node specified by the cursor does not exist");
         match direction {
-            VisitorDirection::Parent => node.parent().ok_or(error).map(NodeRef::into_raw_key),
+            VisitorDirection::Parent => node.parent_key().cloned().ok_or(error),
+            VisitorDirection::NthAncestor(n) => (0..n)
+                .try_fold(node, |ancestor, _| ancestor.parent())
+                .map(NodeRef::into_raw_key)
+                .ok_or(error),
+            VisitorDirection::Root => Ok(self.root.clone()),
             VisitorDirection::NextSibling => {
                 if node.is_left_child() == Some(true) {
                     node.parent()
                         .unwrap_or_else(|| unsafe {
                             unreachable_debugchecked("parent nodes cannot be leaves")
                         })
-                        .right_child()
-                        .map(NodeRef::into_raw_key)
+                        .right_child_key()
+                        .cloned()
+                        .ok_or(error)
+                } else {
+                    Err(error)
+                }
+            }
+            VisitorDirection::PrevSibling => {
+                // The left child always exists, unlike the right one
+                if node.is_left_child() == Some(false) {
+                    node.parent()
+                        .unwrap_or_else(|| unsafe {
+                            unreachable_debugchecked("parent nodes cannot be leaves")
+                        })
+                        .left_child_key()
+                        .cloned()
                         .ok_or(error)
                 } else {
                     Err(error)
                 }
             }
             VisitorDirection::Child(num) => match num {
-                0 => node.left_child().ok_or(error).map(NodeRef::into_raw_key),
-                1 => node.right_child().ok_or(error).map(NodeRef::into_raw_key),
+                0 => node.left_child_key().cloned().ok_or(error),
+                1 => node.right_child_key().cloned().ok_or(error),
                 _ => Err(error),
             },
             VisitorDirection::SetTo(new_cursor) => {
@@ -74,7 +95,7 @@ where
     fn parent_of(&self, cursor: &Self::Cursor) -> Option<Self::Cursor> {
         let node_ref = NodeRef::new_raw(self, cursor.clone())
             .unwrap_or_else(|| panic!("invalid cursor: {:?}", cursor));
-        node_ref.parent().map(NodeRef::into_raw_key)
+        node_ref.parent_key().cloned()
     }
     #[track_caller]
     fn num_children_of(&self, cursor: &Self::Cursor) -> usize {
@@ -82,10 +103,8 @@ where
             .unwrap_or_else(|| panic!("invalid cursor: {:?}", cursor));
         if node_ref.is_full_branch() {
             2
-        } else if node_ref.is_branch() {
-            1
         } else {
-            0
+            usize::from(node_ref.is_branch())
         }
     }
     #[track_caller]
@@ -93,11 +112,14 @@ where
         let node_ref = NodeRef::new_raw(self, cursor.clone())
             .unwrap_or_else(|| panic!("invalid cursor: {:?}", cursor));
         match child_num {
-            0 => node_ref.left_child().map(NodeRef::into_raw_key),
-            1 => node_ref.right_child().map(NodeRef::into_raw_key),
+            0 => node_ref.left_child_key().cloned(),
+            1 => node_ref.right_child_key().cloned(),
             _ => None,
         }
     }
+    fn contains_cursor(&self, cursor: &Self::Cursor) -> bool {
+        self.storage.contains_key(cursor)
+    }
 }
 impl<B, L, K, S> TraversableMut for BinaryTree<B, L, K, S>
 where
@@ -106,6 +128,7 @@ where
 {
     const CAN_REMOVE_INDIVIDUAL_CHILDREN: bool = true;
     const CAN_PACK_CHILDREN: bool = true;
+    const CAN_ADD_INDIVIDUAL_CHILDREN: bool = true;
     type PackedChildren = ArrayVec<[Self::Leaf; 2]>;
     fn value_mut_of(
         &mut self,
@@ -198,4 +221,80 @@ where
                 children
             })
     }
+    #[track_caller]
+    fn try_add_child<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        child_payload: Self::Leaf,
+        leaf_to_branch: LtB,
+    ) -> Result<Self::Cursor, TryAddChildError<Self::Leaf>> {
+        let full_branches_only = self.full_branches_only;
+        let mut node_ref = NodeRefMut::new_raw(self, cursor.clone())
+            .unwrap_or_else(|| panic!("invalid cursor: {:?}", cursor));
+        if node_ref.is_leaf() {
+            // A leaf node with one child would be a partial branch node
+            if full_branches_only {
+                return Err(TryAddChildError::CannotAddIndividualChildren { child_payload });
+            }
+            if node_ref
+                .make_branch_with(child_payload, None, leaf_to_branch)
+                .is_err()
+            {
+                unsafe {
+                    // SAFETY: we just did a leaf check
+                    unreachable_debugchecked("leaf node failed to become a branch")
+                }
+            }
+        } else {
+            node_ref
+                .make_full_branch(child_payload)
+                .map_err(|e| TryAddChildError::WasFullBranch {
+                    child_payload: e.right_child(),
+                })?;
+        }
+        let node = unsafe {
+            // SAFETY: we did a key check when creating the node reference
+            self.storage.get_unchecked(cursor)
+        };
+        match &node.value {
+            NodeData::Branch {
+                right_child: Some(key),
+                ..
+            }
+            | NodeData::Branch {
+                left_child: key,
+                right_child: None,
+                ..
+            } => Ok(key.clone()),
+            NodeData::Leaf(..) => unsafe {
+                // SAFETY: the node was just turned into a branch
+                unreachable_debugchecked("the node was just turned into a branch")
+            },
+        }
+    }
+    #[allow(clippy::type_complexity)]
+    #[track_caller]
+    fn try_add_children<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        children: Self::PackedChildren,
+        leaf_to_branch: LtB,
+    ) -> Result<(), MakeBranchError<Self::Leaf, Self::PackedChildren>> {
+        let mut node_ref = NodeRefMut::new_raw(self, cursor.clone())
+            .unwrap_or_else(|| panic!("invalid cursor: {:?}", cursor));
+        let mut children = children.into_iter();
+        let left_child = if let Some(x) = children.next() {
+            x
+        } else {
+            // Nothing to add, which is only an error if we were asked to add it to a branch
+            return if node_ref.is_leaf() {
+                Ok(())
+            } else {
+                Err(MakeBranchError {
+                    packed_children: ArrayVec::new(),
+                })
+            };
+        };
+        node_ref.make_branch_with(left_child, children.next(), leaf_to_branch)
+    }
 }
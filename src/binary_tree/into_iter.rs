@@ -0,0 +1,222 @@
+use core::{convert, iter::FusedIterator, ptr};
+use crate::{
+    storage::{ListStorage, SparseStorage, SparseStorageSlot, Storage},
+    tree::{PostorderRemoval, Tree},
+    util::abort_on_panic,
+    NodeValue,
+};
+use super::{BinaryTree, Node, NodeData};
+
+type SparseBinaryTree<B, L, S> = BinaryTree<B, L, usize, SparseStorage<Node<B, L, usize>, S>>;
+
+/// An owning iterator over the payloads of the nodes of a binary tree.
+///
+/// The nodes are visited in depth-first post-order: every node is yielded after all of its descendants, children are visited in order and the root node is yielded last. Created by the `IntoIterator` implementation of [`BinaryTree`], which is only available for trees which use sparse storage.
+///
+/// [`BinaryTree`]: struct.BinaryTree.html " "
+#[derive(Debug)]
+pub struct IntoIter<B, L, S>
+where
+    S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
+{
+    tree: SparseBinaryTree<B, L, S>,
+    walk: PostorderRemoval<usize>,
+}
+impl<B, L, S> Iterator for IntoIter<B, L, S>
+where
+    S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
+{
+    type Item = NodeValue<B, L>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.walk.next_key(&self.tree, |_, _| None)?;
+        Some(
+            Storage::remove(&mut self.tree.storage, &key)
+                .value
+                .into_value(),
+        )
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+impl<B, L, S> ExactSizeIterator for IntoIter<B, L, S>
+where
+    S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
+{
+    fn len(&self) -> usize {
+        self.tree.num_nodes() - self.tree.num_holes()
+    }
+}
+impl<B, L, S> FusedIterator for IntoIter<B, L, S> where
+    S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>
+{
+}
+impl<B, L, S> IntoIterator for SparseBinaryTree<B, L, S>
+where
+    S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
+{
+    type Item = NodeValue<B, L>;
+    type IntoIter = IntoIter<B, L, S>;
+
+    /// Consumes the tree, returning an iterator over the payloads of its nodes in depth-first post-order.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{BinaryTree, NodeValue};
+    ///
+    /// let mut tree = BinaryTree::<_>::new("Root");
+    /// tree.root_mut().make_branch("Left", Some("Right")).unwrap();
+    /// tree.root_mut().left_child_mut().unwrap().make_branch("Left of Left", None).unwrap();
+    /// let payloads = tree.into_iter().map(NodeValue::into_inner).collect::<Vec<_>>();
+    /// assert_eq!(payloads, ["Left of Left", "Left", "Right", "Root"]);
+    /// ```
+    fn into_iter(self) -> Self::IntoIter {
+        let walk = PostorderRemoval::new(&self, Some(self.root_key()), None);
+        IntoIter { tree: self, walk }
+    }
+}
+
+/// A draining iterator over the payloads of the descendants of the root node of a binary tree.
+///
+/// The nodes are visited in the same order as with [`IntoIter`], and the root node, which is converted into a leaf node when the iterator is created, is kept in the tree. Dropping the iterator removes the nodes which haven't been yielded yet. If the iterator is leaked instead, those nodes are left in the storage of the tree without being reachable from the root node, until the tree itself is dropped.
+///
+/// Created by the [`drain_with`] and [`drain`] methods on [`BinaryTree`].
+///
+/// [`IntoIter`]: struct.IntoIter.html " "
+/// [`drain_with`]: struct.BinaryTree.html#method.drain_with " "
+/// [`drain`]: struct.BinaryTree.html#method.drain " "
+/// [`BinaryTree`]: struct.BinaryTree.html " "
+#[derive(Debug)]
+pub struct Drain<'a, B, L, S>
+where
+    S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
+{
+    tree: &'a mut SparseBinaryTree<B, L, S>,
+    walk: PostorderRemoval<usize>,
+    right_child: Option<usize>,
+}
+impl<B, L, S> Iterator for Drain<'_, B, L, S>
+where
+    S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
+{
+    type Item = NodeValue<B, L>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let right_child = &mut self.right_child;
+        let key = self.walk.next_key(&*self.tree, |_, _| right_child.take())?;
+        Some(
+            Storage::remove(&mut self.tree.storage, &key)
+                .value
+                .into_value(),
+        )
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+impl<B, L, S> ExactSizeIterator for Drain<'_, B, L, S>
+where
+    S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
+{
+    fn len(&self) -> usize {
+        // The root node is never yielded
+        self.tree.num_nodes() - self.tree.num_holes() - 1
+    }
+}
+impl<B, L, S> FusedIterator for Drain<'_, B, L, S> where
+    S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>
+{
+}
+impl<B, L, S> Drop for Drain<'_, B, L, S>
+where
+    S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
+{
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+impl<B, L, S> SparseBinaryTree<B, L, S>
+where
+    S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
+{
+    /// Removes all nodes except for the root node from the tree, returning an iterator over their payloads in depth-first post-order. The root node is converted into a leaf node right away, using the specified closure if it was a branch node.
+    ///
+    /// See [`Drain`] for the details.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{BinaryTree, NodeValue};
+    ///
+    /// let mut tree = BinaryTree::<_, _>::new(0_usize);
+    /// tree.root_mut().make_branch_with(1, Some(2), |_| 2).unwrap();
+    /// let mut drained = tree.drain_with(|num_children| num_children * 10);
+    /// assert_eq!(drained.next(), Some(NodeValue::Leaf(1)));
+    /// // Whatever we don't consume is removed when the iterator is dropped.
+    /// drop(drained);
+    /// assert!(tree.root().is_leaf());
+    /// assert_eq!(tree.root().value(), NodeValue::Leaf(&20));
+    /// ```
+    ///
+    /// [`Drain`]: struct.Drain.html " "
+    pub fn drain_with(&mut self, branch_to_leaf: impl FnOnce(B) -> L) -> Drain<'_, B, L, S> {
+        let root = unsafe {
+            // SAFETY: the root key is always valid
+            Storage::get_unchecked_mut(&mut self.storage, &self.root)
+        };
+        let (left_child, right_child) = if let NodeData::Branch {
+            payload,
+            left_child,
+            right_child,
+        } = &root.value
+        {
+            let (left_child, right_child) = (*left_child, *right_child);
+            let old_payload = unsafe {
+                // SAFETY: the value is overwritten right after this
+                ptr::read(payload)
+            };
+            let payload = abort_on_panic(|| branch_to_leaf(old_payload));
+            unsafe {
+                // SAFETY: the old payload was moved out above and the keys are Copy
+                ptr::write(&mut root.value, NodeData::Leaf(payload));
+            }
+            (Some(left_child), right_child)
+        } else {
+            (None, None)
+        };
+        let walk = PostorderRemoval::new(&*self, left_child, Some(self.root));
+        Drain {
+            tree: self,
+            walk,
+            right_child,
+        }
+    }
+}
+impl<D, S> SparseBinaryTree<D, D, S>
+where
+    S: ListStorage<Element = SparseStorageSlot<Node<D, D, usize>>>,
+{
+    /// Removes all nodes except for the root node from the tree, returning an iterator over their payloads in depth-first post-order. The root node is converted into a leaf node right away, keeping its payload, which is why *this method is only available when the payload for leaf nodes and branch nodes is the same.*
+    ///
+    /// See [`Drain`] for the details.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{BinaryTree, NodeValue};
+    ///
+    /// let mut tree = BinaryTree::<_>::new("Root");
+    /// tree.root_mut().make_branch("Left", Some("Right")).unwrap();
+    /// let drained = tree.drain().map(NodeValue::into_inner).collect::<Vec<_>>();
+    /// assert_eq!(drained, ["Left", "Right"]);
+    /// assert!(tree.root().is_leaf());
+    /// assert_eq!(*tree.root().value().into_inner(), "Root");
+    /// ```
+    ///
+    /// [`Drain`]: struct.Drain.html " "
+    pub fn drain(&mut self) -> Drain<'_, D, D, S> {
+        self.drain_with(convert::identity)
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! The [Wikipedia article] on binary trees covers their use cases and specifics in more detail.
 //!
-//! Both *full* binary trees and non-full ones are supported. The former ones allow strictly either zero or two children, the latter ones also allow one child to exist without the other one. If there is only one, it's always treated as the left one, and removing the left child for a full branch will shift the right child into the position of the left one (implemented as a simple and very inexpensive key modification and does not actually move the elements themselves around).
+//! Both *full* binary trees and non-full ones are supported. The former ones allow strictly either zero or two children, the latter ones also allow one child to exist without the other one. If there is only one, it's always treated as the left one, and removing the left child for a full branch will shift the right child into the position of the left one (implemented as a simple and very inexpensive key modification and does not actually move the elements themselves around). Trees in which the left and right positions carry meaning can forbid the shift with a [`RemovalPolicy`], and trees which must stay full can forbid partial branch nodes altogether with [`enforce_full_branches`].
 //!
 //! # Example
 //! ```rust
@@ -34,20 +34,32 @@
 //! ```
 //!
 //! [Wikipedia article]: https://en.wikipedia.org/wiki/Binary_tree " "
+//! [`RemovalPolicy`]: enum.RemovalPolicy.html " "
+//! [`enforce_full_branches`]: struct.BinaryTree.html#method.enforce_full_branches " "
 
 use core::fmt::{self, Formatter, Debug, Display};
 
 mod base;
 mod impl_traversable;
+mod impl_tree;
+mod into_iter;
+mod migrate;
 mod node;
 mod node_ref;
 mod node_ref_mut;
+#[cfg(feature = "alloc")]
+mod repr;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub mod expr;
 
 use node::NodeData;
 pub use node::Node;
 pub use node_ref::NodeRef;
 pub use node_ref_mut::{NodeRefMut};
 pub use base::BinaryTree;
+pub use into_iter::{IntoIter, Drain};
 
 /// The error type returned by [`NodeRefMut::make_full_branch`].
 ///
@@ -89,6 +101,143 @@ impl<L> Display for MakeFullBranchError<L> {
 #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
 impl<L: Debug> std::error::Error for MakeFullBranchError<L> {}
 
+/// The error type returned by [`BinaryTree::enforce_full_branches`] when the tree already has a partial branch node.
+///
+/// [`BinaryTree::enforce_full_branches`]: struct.BinaryTree.html#method.enforce_full_branches " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PartialBranchError<K> {
+    /// The key of the first partial branch node in depth-first pre-order.
+    pub key: K,
+}
+impl<K> Display for PartialBranchError<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad("the tree has a branch node with only one child")
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl<K: Debug> std::error::Error for PartialBranchError<K> {}
+
+/// The error type returned by [`NodeRefMut::set_left_subtree`] and [`NodeRefMut::set_right_subtree`].
+///
+/// [`NodeRefMut::set_left_subtree`]: struct.NodeRefMut.html#method.set_left_subtree " "
+/// [`NodeRefMut::set_right_subtree`]: struct.NodeRefMut.html#method.set_right_subtree " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SetSubtreeError<T> {
+    /// The node was a leaf node, which cannot get a right child without having a left one first.
+    WasLeafNode {
+        /// The provided subtree, which was deemed useless when the operation failed and is returned to the caller to avoid dropping it.
+        subtree: T,
+    },
+    /// The tree [only allows full branch nodes], and either the node was a leaf node which would get only one child or the provided subtree has a partial branch node.
+    ///
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
+    WouldMakePartialBranch {
+        /// The provided subtree, which was deemed useless when the operation failed and is returned to the caller to avoid dropping it.
+        subtree: T,
+    },
+}
+impl<T> SetSubtreeError<T> {
+    /// Extracts the provided subtree, which was deemed useless when the operation failed.
+    #[allow(clippy::missing_const_for_fn)] // Clippy has no idea what a destructor is
+    pub fn subtree(self) -> T {
+        match self {
+            Self::WasLeafNode { subtree } | Self::WouldMakePartialBranch { subtree } => subtree,
+        }
+    }
+}
+impl<T> Display for SetSubtreeError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::WasLeafNode { .. } => "a leaf node cannot get a right child without a left one",
+            Self::WouldMakePartialBranch { .. } => {
+                "setting the subtree would leave a branch node with one child"
+            }
+        })
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl<T: Debug> std::error::Error for SetSubtreeError<T> {}
+
+/// The error type returned by [`NodeRefMut::take_left_subtree`] and [`NodeRefMut::take_right_subtree`].
+///
+/// [`NodeRefMut::take_left_subtree`]: struct.NodeRefMut.html#method.take_left_subtree " "
+/// [`NodeRefMut::take_right_subtree`]: struct.NodeRefMut.html#method.take_right_subtree " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TakeSubtreeError {
+    /// The node had no child in the requested position.
+    NoSuchChild,
+    /// The node was a full branch node in a tree which [keeps the right child in place], so taking the left child would shift the right child into its position.
+    ///
+    /// [keeps the right child in place]: enum.RemovalPolicy.html#variant.KeepRightInPlace " "
+    WouldShiftRightChild,
+    /// The tree [only allows full branch nodes], and taking the child would leave the node with only one.
+    ///
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
+    WouldMakePartialBranch,
+}
+impl Display for TakeSubtreeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::NoSuchChild => "the node had no child in the requested position",
+            Self::WouldShiftRightChild => {
+                "taking the left child would shift the right child into its place"
+            }
+            Self::WouldMakePartialBranch => {
+                "taking the child would leave a branch node with one child"
+            }
+        })
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl std::error::Error for TakeSubtreeError {}
+
+/// The policy which decides what happens to the right child of a full branch node when its left child is removed or moved to a different parent, set per tree with [`BinaryTree::set_removal_policy`].
+///
+/// Partial branch nodes always store their only child as the left one, so the right child of a full branch node has to either move into the slot of the left child or stay where it is, in which case the left child cannot be removed in the first place. The former is fine for trees in which the children of a node are just a sequence of up to two elements, but for trees in which the left and right positions carry meaning, such as syntax trees with binary operators, the shift silently changes what the tree means.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{binary_tree::{BinaryTree, RemovalPolicy}, TryRemoveLeafError};
+///
+/// // 10 - 3
+/// let mut tree = BinaryTree::<_>::new("-");
+/// tree.root_mut().make_branch("10", Some("3")).unwrap();
+/// tree.set_removal_policy(RemovalPolicy::KeepRightInPlace);
+///
+/// // Removing the left operand would turn the right one into the left one.
+/// let mut root = tree.root_mut();
+/// let result = root.left_child_mut().unwrap().try_remove_leaf();
+/// assert_eq!(result, Err(TryRemoveLeafError::CannotRemoveIndividualChildren));
+/// // Removing the right operand doesn't move anything, so it's still allowed.
+/// assert_eq!(root.right_child_mut().unwrap().try_remove_leaf(), Ok("3"));
+/// assert_eq!(tree.root().left_child().unwrap().value().into_inner(), &"10");
+/// ```
+///
+/// [`BinaryTree::set_removal_policy`]: struct.BinaryTree.html#method.set_removal_policy " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RemovalPolicy {
+    /// The right child is shifted into the slot of the left child, turning the parent into a partial branch node. This is the default.
+    ShiftRightToLeft,
+    /// The right child is never shifted, and operations which would shift it fail instead, leaving the tree unchanged: [`try_remove_leaf`] and [`try_remove_branch`] fail with `CannotRemoveIndividualChildren` and [`reparent_to`] fails with [`ReparentError::WouldShiftRightChild`]. The children of the parent can still be removed together with [`try_remove_children`], and the right child can be removed first.
+    ///
+    /// [`try_remove_leaf`]: struct.NodeRefMut.html#method.try_remove_leaf " "
+    /// [`try_remove_branch`]: struct.NodeRefMut.html#method.try_remove_branch " "
+    /// [`reparent_to`]: struct.NodeRefMut.html#method.reparent_to " "
+    /// [`ReparentError::WouldShiftRightChild`]: ../enum.ReparentError.html#variant.WouldShiftRightChild " "
+    /// [`try_remove_children`]: struct.NodeRefMut.html#method.try_remove_children " "
+    KeepRightInPlace,
+}
+impl Default for RemovalPolicy {
+    fn default() -> Self {
+        Self::ShiftRightToLeft
+    }
+}
+
 /// A binary tree which uses a *sparse* `Vec` as backing storage.
 ///
 /// The default `BinaryTree` type already uses this, so this is only provided for explicitness and consistency.
@@ -104,6 +253,44 @@ pub type SparseVecBinaryTree<B, L = B> =
 #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
 #[allow(unused_qualifications)]
 pub type VecBinaryTree<B, L = B> = BinaryTree<B, L, usize, alloc::vec::Vec<Node<B, L, usize>>>;
+/// A binary tree which uses a *sparse* `Vec` with 32-bit keys as backing storage, halving the size of the links between nodes on 64-bit targets.
+///
+/// See the [`small_key`] module for more.
+///
+/// [`small_key`]: ../small_key/index.html " "
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+#[allow(unused_qualifications)]
+pub type SmallKeyBinaryTree<B, L = B> = BinaryTree<
+    B,
+    L,
+    u32,
+    crate::small_key::SmallKeyStorage<crate::storage::SparseVec<Node<B, L, u32>>>,
+>;
+/// A binary tree which uses an `ArrayVec` as backing storage, keeping all of its nodes inline without the need for a memory allocator.
+///
+/// The capacity of an `ArrayVec` is specified by its backing array type, which is the first type parameter here: `[Node<B, L, usize>; 16]` makes a tree which can hold up to 16 nodes. Adding nodes beyond that with the regular methods panics, while [`try_new`] and [`try_make_branch`]/[`try_make_branch_with`] return an error instead.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{binary_tree::{InlineBinaryTree, Node}, TryMakeBranchError};
+///
+/// let mut tree = InlineBinaryTree::<[Node<u32, u32, usize>; 3], u32>::try_new(0).unwrap();
+/// tree.root_mut().try_make_branch(1, Some(2)).unwrap();
+/// // The storage is full now, so there is no room for any more children.
+/// let mut root = tree.root_mut();
+/// let mut left_child = root.left_child_mut().unwrap();
+/// assert!(matches!(
+///     left_child.try_make_branch(3, None),
+///     Err(TryMakeBranchError::OutOfCapacity { .. }),
+/// ));
+/// assert!(left_child.is_leaf());
+/// ```
+///
+/// [`try_new`]: struct.BinaryTree.html#method.try_new " "
+/// [`try_make_branch`]: struct.NodeRefMut.html#method.try_make_branch " "
+/// [`try_make_branch_with`]: struct.NodeRefMut.html#method.try_make_branch_with " "
+pub type InlineBinaryTree<A, B, L = B> = BinaryTree<B, L, usize, arrayvec::ArrayVec<A>>;
 
 /*
 /// A binary tree which uses a `LinkedList` as backing storage.
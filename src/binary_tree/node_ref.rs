@@ -1,14 +1,16 @@
-use core::fmt::Debug;
+use core::{fmt::Debug, ptr};
 use crate::{
     storage::{Storage, DefaultStorage},
+    traversal::algorithms::depth_of,
     util::unreachable_debugchecked,
+    tree,
     NodeValue,
 };
 use super::{BinaryTree, Node, NodeData};
 
 /// A reference to a node in a binary tree.
 ///
-/// Since this type does not point to the node directly, but rather the tree the node is in and the key of the node in the storage, it can be used to traverse the tree.
+/// Since this type points not only to the node itself, but also to the tree the node is in and the key of the node in the storage, it can be used to traverse the tree. The node is looked up in the storage once, when the reference is created, rather than every time it is accessed.
 #[derive(Debug)]
 pub struct NodeRef<'a, B, L, K, S = DefaultStorage<Node<B, L, K>>>
 where
@@ -17,6 +19,8 @@ where
 {
     pub(super) tree: &'a BinaryTree<B, L, K, S>,
     pub(super) key: K,
+    /// The node itself, looked up once when the reference is created, since the tree cannot change while it's borrowed.
+    node: &'a Node<B, L, K>,
 }
 impl<'a, B, L, K, S> NodeRef<'a, B, L, K, S>
 where
@@ -24,22 +28,49 @@ where
     K: Clone + Debug + Eq,
 {
     /// Creates a new `NodeRef` pointing to the specified key in the storage, or `None` if it's out of bounds.
+    ///
+    /// The node is looked up right away rather than when the reference is used. For trees backed by sparse storage, this means that the key of a removed node whose slot is still a hole makes this method panic, instead of producing a reference which panics once it's accessed. See the [`Tree`] trait for more on holes.
+    ///
+    /// # Panics
+    /// Will panic if the key points to a hole in sparse storage.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::binary_tree::{BinaryTree, NodeRef};
+    /// use std::panic::{catch_unwind, AssertUnwindSafe};
+    ///
+    /// let mut tree = BinaryTree::<_>::new(0);
+    /// tree.root_mut().make_branch(1, Some(2)).unwrap();
+    /// let right_child = tree.root().right_child().unwrap().into_raw_key();
+    /// let node = NodeRef::new_raw(&tree, right_child).unwrap();
+    /// assert_eq!(node.value().into_inner(), &2);
+    ///
+    /// tree.root_mut().right_child_mut().unwrap().try_remove_leaf().unwrap();
+    /// // The removed node left a hole, which cannot be referenced...
+    /// let result = catch_unwind(AssertUnwindSafe(|| NodeRef::new_raw(&tree, right_child).is_some()));
+    /// assert!(result.is_err());
+    /// // ...while keys past the end of the storage are reported as invalid.
+    /// assert!(NodeRef::new_raw(&tree, 3).is_none());
+    /// ```
+    ///
+    /// [`Tree`]: ../tree/trait.Tree.html#sparse-storage " "
     pub fn new_raw(tree: &'a BinaryTree<B, L, K, S>, key: K) -> Option<Self> {
-        if tree.storage.contains_key(&key) {
-            Some(unsafe {
-                // SAFETY: we just did a key check
-                Self::new_raw_unchecked(tree, key)
-            })
-        } else {
-            None
-        }
+        let node = tree.storage.get(&key)?;
+        Some(Self { tree, key, node })
     }
     /// Creates a new `NodeRef` pointing to the specified key in the storage without doing bounds checking.
     ///
     /// # Safety
     /// Causes *immediate* undefined behavior if the specified key is not present in the storage.
     pub unsafe fn new_raw_unchecked(tree: &'a BinaryTree<B, L, K, S>, key: K) -> Self {
-        Self { tree, key }
+        debug_assert!(
+            tree.storage.contains_key(&key),
+            "\
+debug key check failed: tried to reference key {:?} which is not present in the storage",
+            &key,
+        );
+        let node = tree.storage.get_unchecked(&key);
+        Self { tree, key, node }
     }
     /// Returns a reference the raw storage key for the node.
     pub fn raw_key(&self) -> &K {
@@ -62,6 +93,34 @@ where
     pub fn is_root(&self) -> bool {
         self.node().parent.is_none()
     }
+    /// Returns `true` if the node is an ancestor of the specified node, i.e. its parent, the parent of its parent and so on up to the root; `false` otherwise. A node is not an ancestor of itself, and nodes in different trees are never ancestors of each other.
+    ///
+    /// Only the parents of the specified node are visited, which takes *O*(*d*) time without allocating memory, where *d* is the depth of the specified node.
+    pub fn is_ancestor_of(&self, other: &Self) -> bool {
+        if !ptr::eq(self.tree, other.tree) {
+            return false;
+        }
+        let mut ancestor = other.parent();
+        while let Some(node) = ancestor {
+            if node.key == self.key {
+                return true;
+            }
+            ancestor = node.parent();
+        }
+        false
+    }
+    /// Returns `true` if the node is a descendant of the specified node, i.e. one of its children, one of the children of those and so on; `false` otherwise. This is the same as calling [`is_ancestor_of`] with the nodes swapped, and walks the parents of this node.
+    ///
+    /// [`is_ancestor_of`]: #method.is_ancestor_of " "
+    pub fn is_descendant_of(&self, other: &Self) -> bool {
+        other.is_ancestor_of(self)
+    }
+    /// Returns the depth of the node, i.e. the number of levels between it and the root node, with the root node being at depth 0.
+    ///
+    /// The depth is not stored in the nodes, so it's found by walking the parents of the node, which takes *O*(*d*) time, where *d* is the depth.
+    pub fn depth(&self) -> usize {
+        depth_of(self.tree, &self.key)
+    }
     /// Returns `true` if the node is a *leaf*, i.e. does not have child nodes; `false` otherwise.
     pub fn is_leaf(&self) -> bool {
         match &self.node().value {
@@ -87,20 +146,27 @@ where
     /// Returns `true` if the node is the left child of its parent, `false` if it's the right one and `None` if it's the root node.
     pub fn is_left_child(&self) -> Option<bool> {
         let parent = self.parent()?;
-        let left_child_key = &parent
-            .left_child()
-            .unwrap_or_else(|| unsafe { unreachable_debugchecked("parent nodes cannot be leaves") })
-            .key;
+        let left_child_key = parent.left_child_key().unwrap_or_else(|| unsafe {
+            unreachable_debugchecked("parent nodes cannot be leaves")
+        });
         Some(self.key == *left_child_key)
     }
     /// Returns `true` if the node is the right child of its parent, `false` if it's the left one and `None` if it's the root node.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::BinaryTree;
+    ///
+    /// let mut tree = BinaryTree::<_>::new(0);
+    /// tree.root_mut().make_branch(1, None).unwrap();
+    /// assert_eq!(tree.root().is_right_child(), None);
+    /// // The only child is the left one, even though its parent has no right child.
+    /// assert_eq!(tree.root().left_child().unwrap().is_right_child(), Some(false));
+    /// ```
     pub fn is_right_child(&self) -> Option<bool> {
         let parent = self.parent()?;
-        let right_child_key = &parent
-            .right_child()
-            .unwrap_or_else(|| unsafe { unreachable_debugchecked("parent nodes cannot be leaves") })
-            .key;
-        Some(self.key == *right_child_key)
+        // The right child may be missing, in which case the node is the left one
+        Some(parent.right_child_key() == Some(&self.key))
     }
     /// Returns references to the children, or `None` if the node is a leaf node or it only has one child. To retreive the left child even if the right one is not present, see `left_child`.
     #[allow(clippy::missing_panics_doc)]
@@ -163,8 +229,8 @@ debug key check failed: tried to reference key {:?} which is not present in the
     /// [`children`]: #method.children " "
     #[allow(clippy::missing_panics_doc)]
     pub fn right_child(&self) -> Option<Self> {
-        if let NodeData::Branch { left_child, .. } = &self.node().value {
-            Some(left_child.clone())
+        if let NodeData::Branch { right_child, .. } = &self.node().value {
+            right_child.clone()
         } else {
             None
         }
@@ -180,18 +246,29 @@ debug key check failed: tried to reference key {:?} which is not present in the
         })
     }
 
-    fn node(&self) -> &'a Node<B, L, K> {
-        debug_assert!(
-            self.tree.storage.contains_key(&self.key),
-            "\
-debug key check failed: tried to reference key {:?} which is not present in the storage",
-            &self.key,
-        );
-        unsafe {
-            // SAFETY: all existing NodeRefs are guaranteed to not be dangling
-            self.tree.storage.get_unchecked(&self.key)
+    /// Returns the key of the parent node without looking it up in the storage.
+    pub(super) fn parent_key(&self) -> Option<&'a K> {
+        self.node().parent.as_ref()
+    }
+    /// Returns the key of the left child without looking it up in the storage, or `None` if the node is a leaf node.
+    pub(super) fn left_child_key(&self) -> Option<&'a K> {
+        if let NodeData::Branch { left_child, .. } = &self.node().value {
+            Some(left_child)
+        } else {
+            None
+        }
+    }
+    /// Returns the key of the right child without looking it up in the storage, or `None` if the node is a leaf node or only has a left child.
+    pub(super) fn right_child_key(&self) -> Option<&'a K> {
+        if let NodeData::Branch { right_child, .. } = &self.node().value {
+            right_child.as_ref()
+        } else {
+            None
         }
     }
+    fn node(&self) -> &'a Node<B, L, K> {
+        self.node
+    }
 }
 impl<B, L, K, S> Copy for NodeRef<'_, B, L, K, S>
 where
@@ -208,6 +285,7 @@ where
         Self {
             tree: self.tree,
             key: self.key.clone(),
+            node: self.node,
         }
     }
 }
@@ -220,3 +298,15 @@ where
         op.value()
     }
 }
+impl<'a, B, L, K, S> From<NodeRef<'a, B, L, K, S>> for tree::NodeRef<'a, BinaryTree<B, L, K, S>>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn from(op: NodeRef<'a, B, L, K, S>) -> Self {
+        Self {
+            tree: op.tree,
+            key: op.key,
+        }
+    }
+}
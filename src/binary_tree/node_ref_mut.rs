@@ -4,19 +4,37 @@ use core::{
     fmt::Debug, // trait bounds
     hint,       // unreachable_unchecked
     convert,    // identity
+    iter,       // once, successors
 };
 use crate::{
-    storage::{Storage, DefaultStorage},
-    util::{unreachable_debugchecked, abort_on_panic},
+    storage::{Storage, ListStorage, DefaultStorage, SparseStorage, SparseStorageSlot},
+    tree::{self, PostorderRemoval, Tree},
+    traversal::algorithms::next_in_preorder,
+    util::{unreachable_debugchecked, abort_on_panic, has_room_for},
     TryRemoveLeafError,
     TryRemoveBranchError,
     TryRemoveChildrenError,
+    ReparentError,
     MakeBranchError,
-    traversal::algorithms,
+    TryMakeBranchError,
     NodeValue,
 };
 use arrayvec::ArrayVec;
-use super::{BinaryTree, MakeFullBranchError, Node, NodeData, NodeRef};
+use super::{
+    base::first_partial_branch,
+    BinaryTree,
+    MakeFullBranchError,
+    Node,
+    NodeData,
+    NodeRef,
+    RemovalPolicy,
+    SetSubtreeError,
+    TakeSubtreeError,
+};
+
+type SparseBinaryTree<B, L, S> = BinaryTree<B, L, usize, SparseStorage<Node<B, L, usize>, S>>;
+type SetSubtreeResult<B, L, S> =
+    Result<Option<SparseBinaryTree<B, L, S>>, SetSubtreeError<SparseBinaryTree<B, L, S>>>;
 
 /// A *mutable* reference to a node in a binary tree.
 ///
@@ -70,10 +88,10 @@ where
     }
     /// Returns a *mutable* reference to the parent node of the pointee, or `None` if it's the root node.
     pub fn parent_mut(&mut self) -> Option<NodeRefMut<'_, B, L, K, S>> {
-        let key = self.node().parent.as_ref().cloned();
+        let key = self.node().parent.clone();
         key.map(move |x| unsafe {
             // SAFETY: as above
-            Self::new_raw_unchecked(self.tree, x)
+            NodeRefMut::new_raw_unchecked(self.tree, x)
         })
     }
     /// Returns `true` if the node is the root node, `false` otherwise.
@@ -117,13 +135,22 @@ where
         Some(self.key == *left_child_key)
     }
     /// Returns `true` if the node is the right child of its parent, `false` if it's the left one and `None` if it's the root node.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::BinaryTree;
+    ///
+    /// let mut tree = BinaryTree::<_>::new(0);
+    /// tree.root_mut().make_branch(1, None).unwrap();
+    /// assert_eq!(tree.root_mut().is_right_child(), None);
+    /// // The only child is the left one, even though its parent has no right child.
+    /// let mut root = tree.root_mut();
+    /// assert_eq!(root.left_child_mut().unwrap().is_right_child(), Some(false));
+    /// ```
     pub fn is_right_child(&self) -> Option<bool> {
         let parent = self.parent()?;
-        let right_child_key = &parent
-            .right_child()
-            .unwrap_or_else(|| unsafe { unreachable_debugchecked("parent nodes cannot be leaves") })
-            .key;
-        Some(self.key == *right_child_key)
+        // The right child may be missing, in which case the node is the left one
+        Some(parent.right_child_key() == Some(&self.key))
     }
     /// Returns a reference to the left child, or `None` if the node is a leaf node.
     pub fn left_child(&self) -> Option<NodeRef<'_, B, L, K, S>> {
@@ -146,7 +173,7 @@ where
 debug key check failed: tried to reference key {:?} which is not present in the storage",
                 &x,
             );
-            Self::new_raw_unchecked(self.tree, x)
+            NodeRefMut::new_raw_unchecked(self.tree, x)
         })
     }
     /// Returns a reference to the right child, or `None` if the node is a leaf node.
@@ -156,8 +183,8 @@ debug key check failed: tried to reference key {:?} which is not present in the
     /// Returns a *mutable* reference to the right child, or `None` if the node is a leaf node.
     #[allow(clippy::missing_panics_doc)]
     pub fn right_child_mut(&mut self) -> Option<NodeRefMut<'_, B, L, K, S>> {
-        if let NodeData::Branch { left_child, .. } = &self.node().value {
-            Some(left_child.clone())
+        if let NodeData::Branch { right_child, .. } = &self.node().value {
+            right_child.clone()
         } else {
             None
         }
@@ -169,7 +196,7 @@ debug key check failed: tried to reference key {:?} which is not present in the
 debug key check failed: tried to reference key {:?} which is not present in the storage",
                 &x,
             );
-            Self::new_raw_unchecked(self.tree, x)
+            NodeRefMut::new_raw_unchecked(self.tree, x)
         })
     }
 
@@ -177,12 +204,22 @@ debug key check failed: tried to reference key {:?} which is not present in the
     ///
     /// # Errors
     /// Will fail if the node is already a branch node. In such a case, the provided values for the children are returned back to the caller.
+    ///
+    /// # Panics
+    /// Will panic if the tree [only allows full branch nodes] and no right child was provided. [`try_make_branch_with`] returns an error instead.
+    ///
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
+    /// [`try_make_branch_with`]: #method.try_make_branch_with " "
     pub fn make_branch_with(
         &mut self,
         left_child: L,
         right_child: Option<L>,
         f: impl FnOnce(L) -> B,
     ) -> Result<(), MakeBranchError<L, ArrayVec<[L; 2]>>> {
+        assert!(
+            right_child.is_some() || !self.tree.full_branches_only,
+            "the tree only allows full branch nodes, but no right child was provided",
+        );
         let old_val_ref = if let NodeData::Leaf(val) = &self.node().value {
             val
         } else {
@@ -202,6 +239,9 @@ debug key check failed: tried to reference key {:?} which is not present in the
             ptr::read(old_val_ref)
         };
         let new_val = f(old_val);
+        self.tree
+            .storage
+            .reserve(1 + usize::from(right_child.is_some()));
         let new_left_child_key = self.tree.storage.add(unsafe {
             // SAFETY: key validity is assumed
             Node::leaf(left_child, Some(self.raw_key().clone()))
@@ -220,10 +260,37 @@ debug key check failed: tried to reference key {:?} which is not present in the
                     left_child: new_left_child_key,
                     right_child: new_right_child_key,
                 },
-            )
+            );
         }
         Ok(())
     }
+    /// Converts a leaf node into a branch node with the specified leaf children, using the provided closure to convert the payload. Unlike [`make_branch_with`], returns an error instead of panicking if the storage cannot fit the children or if the tree [only allows full branch nodes] and no right child was provided.
+    ///
+    /// # Errors
+    /// Will fail if the node is already a branch node, if the storage has a fixed capacity and does not have enough free space for the children or if the tree only allows full branch nodes and no right child was provided. In all cases, the provided values for the children are returned back to the caller.
+    ///
+    /// [`make_branch_with`]: #method.make_branch_with " "
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
+    pub fn try_make_branch_with(
+        &mut self,
+        left_child: L,
+        right_child: Option<L>,
+        f: impl FnOnce(L) -> B,
+    ) -> Result<(), TryMakeBranchError<L, ArrayVec<[L; 2]>>> {
+        if self.is_leaf() && right_child.is_none() && self.tree.full_branches_only {
+            return Err(TryMakeBranchError::PartialBranch {
+                packed_children: iter::once(left_child).collect(),
+            });
+        }
+        let num_children = 1 + usize::from(right_child.is_some());
+        if self.is_leaf() && !has_room_for(&self.tree.storage, num_children) {
+            return Err(TryMakeBranchError::OutOfCapacity {
+                packed_children: iter::once(left_child).chain(right_child).collect(),
+            });
+        }
+        self.make_branch_with(left_child, right_child, f)
+            .map_err(Into::into)
+    }
     /// Converts a partial branch node into a full branch, giving the specified value to the right child.
     ///
     /// # Errors
@@ -256,7 +323,7 @@ debug key check failed: tried to reference key {:?} which is not present in the
             NodeData::Branch { right_child, .. } => {
                 *right_child = Some(new_right_child_key);
             }
-            _ => unsafe {
+            NodeData::Leaf(..) => unsafe {
                 // SAFETY: leaf check was performed in the beginning
                 hint::unreachable_unchecked()
             },
@@ -270,79 +337,77 @@ debug key check failed: tried to reference key {:?} which is not present in the
     /// Will fail in the following scenarios:
     /// - The node was a branch node, which would require recursion to remove, and this function explicitly does not implement recursive removal.
     /// - The node was the root node, which can never be removed.
+    /// - The tree [keeps the right child in place] and the node was the left child of a full branch node.
+    /// - The tree [only allows full branch nodes].
+    ///
+    /// [keeps the right child in place]: enum.RemovalPolicy.html#variant.KeepRightInPlace " "
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
     pub fn try_remove_leaf_with(self, f: impl FnOnce(B) -> L) -> Result<L, TryRemoveLeafError> {
+        if self.is_leaf() && self.is_pinned() {
+            return Err(TryRemoveLeafError::CannotRemoveIndividualChildren);
+        }
+        self.try_remove_leaf_ignoring_policy(|parent, payload| parent.make_leaf_with(payload, f))
+    }
+    /// Attempts to remove a branch node without using recursion. If its parent only had one child, it's replaced with a leaf node, the value for which is provided by the specified closure (the previous value is passed into the closure).
+    ///
+    /// # Errors
+    /// Will fail in the following scenarios:
+    /// - The node was a leaf node. The `try_remove_leaf`/`try_remove_leaf_with` methods exist for that.
+    /// - The node was the root node, which can never be removed.
+    /// - One or more of the node's children were a branch node, which thus would require recursion to remove.
+    /// - The tree [keeps the right child in place] and the node was the left child of a full branch node.
+    /// - The tree [only allows full branch nodes].
+    ///
+    /// [keeps the right child in place]: enum.RemovalPolicy.html#variant.KeepRightInPlace " "
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
+    pub fn try_remove_branch_with(
+        self,
+        f: impl FnOnce(B) -> L,
+    ) -> Result<(B, L, Option<L>), TryRemoveBranchError> {
+        if self.is_branch() && self.is_pinned() {
+            return Err(TryRemoveBranchError::CannotRemoveIndividualChildren);
+        }
+        self.try_remove_branch_ignoring_policy(|parent, left_child_payload| {
+            parent.make_leaf_with(left_child_payload, f)
+        })
+    }
+    /// The same as `try_remove_leaf_with`, but shifts the right child of the parent regardless of the removal policy of the tree. If the parent loses its only child, the closure converts it into a leaf node, receiving the payload of the removed node and returning the payload to give back to the caller.
+    fn try_remove_leaf_ignoring_policy(
+        mut self,
+        make_leaf: impl FnOnce(&mut NodeData<B, L, K>, L) -> L,
+    ) -> Result<L, TryRemoveLeafError> {
         if self.is_branch() {
             return Err(TryRemoveLeafError::WasBranchNode);
         }
         let parent_key = self
             .node()
             .parent
-            .as_ref()
-            .cloned()
+            .clone()
             .ok_or(TryRemoveLeafError::WasRootNode)?;
-        let (parent_left_child, parent_right_child, parent_payload) = match unsafe {
-            // SAFETY: parent key is guaranteed to be valid
-            &mut self.tree.storage.get_unchecked_mut(&parent_key).value
-        } {
-            NodeData::Branch {
-                left_child,
-                right_child,
-                payload,
-            } => (left_child, right_child, payload),
-            NodeData::Leaf(..) => unsafe {
-                unreachable_debugchecked("parent nodes cannot be leaves")
-            },
-        };
-        if &self.key == parent_left_child {
-            if let Some(right_child_ref) = parent_right_child {
-                mem::swap(parent_left_child, right_child_ref);
-                *parent_right_child = None;
-            } else {
-                let old_payload = unsafe {
-                    // SAFETY: the pointer is coerced from a reference and therefore is required to
-                    // be valid; we're also overwriting this, so no duplication
-                    ptr::read(parent_payload)
-                };
-                // Destroy the mutable references to modify parent
-                drop((parent_left_child, parent_right_child));
-                unsafe {
-                    // SAFETY: as above
-                    ptr::write(
-                        &mut self.tree.storage.get_unchecked_mut(&parent_key).value,
-                        NodeData::Leaf(abort_on_panic(|| f(old_payload))),
-                    );
-                }
-            }
-        } else if Some(&self.key) == parent_right_child.as_ref() {
-            *parent_right_child = None;
-        } else {
-            unsafe {
-                // SAFETY: a node cannot have a parent which does not list it as one
-                // of its children
-                unreachable_debugchecked(
-                    "failed to identify whether the node is the left or right child",
-                )
-            }
-        }
+        let was_only_child = self.unlink(&parent_key);
         let key = self.key.clone();
-        match self.tree.storage.remove(&key).value {
-            NodeData::Leaf(x) => Ok(x),
+        let payload = match self.tree.storage.remove(&key).value {
+            NodeData::Leaf(x) => x,
             NodeData::Branch { .. } => unsafe {
                 // SAFETY: the beggining of the function tests for self being a branch node
                 hint::unreachable_unchecked()
             },
+        };
+        if was_only_child {
+            let parent = unsafe {
+                // SAFETY: parent nodes are added to the storage before their children, so removing
+                // a child does not invalidate the key of its parent
+                self.tree.storage.get_unchecked_mut(&parent_key)
+            };
+            Ok(make_leaf(&mut parent.value, payload))
+        } else {
+            Ok(payload)
         }
     }
-    /// Attempts to remove a branch node without using recursion. If its parent only had one child, it's replaced with a leaf node, the value for which is provided by the specified closure (the previous value is passed into the closure).
-    ///
-    /// # Errors
-    /// Will fail in the following scenarios:
-    /// - The node was a leaf node. The `try_remove_leaf`/`try_remove_leaf_with` methods exist for that.
-    /// - The node was the root node, which can never be removed.
-    /// - One or more of the node's children were a branch node, which thus would require recursion to remove.
-    pub fn try_remove_branch_with(
-        self,
-        f: impl FnOnce(B) -> L,
+    /// The same as `try_remove_branch_with`, but shifts the right child of the parent regardless of the removal policy of the tree. If the parent loses its only child, the closure converts it into a leaf node, receiving the payload of the left child of the removed node and returning the payload to give back to the caller.
+    fn try_remove_branch_ignoring_policy(
+        mut self,
+        make_leaf: impl FnOnce(&mut NodeData<B, L, K>, L) -> L,
     ) -> Result<(B, L, Option<L>), TryRemoveBranchError> {
         if let NodeData::Branch {
             left_child,
@@ -361,7 +426,7 @@ debug key check failed: tried to reference key {:?} which is not present in the
             };
             if left_child_ref.is_branch() {
                 return Err(TryRemoveBranchError::HadBranchChild(0));
-            } else if right_child_ref.as_ref().map(NodeRef::is_branch) == Some(true) {
+            } else if right_child_ref.as_ref().map_or(false, NodeRef::is_branch) {
                 return Err(TryRemoveBranchError::HadBranchChild(1));
             }
         } else {
@@ -370,82 +435,55 @@ debug key check failed: tried to reference key {:?} which is not present in the
         let parent_key = self
             .node()
             .parent
-            .as_ref()
-            .cloned()
+            .clone()
             .ok_or(TryRemoveBranchError::WasRootNode)?;
-        let (parent_left_child, parent_right_child, parent_payload) = match unsafe {
-            // SAFETY: parent key is guaranteed to be valid
-            &mut self.tree.storage.get_unchecked_mut(&parent_key).value
-        } {
+        let was_only_child = self.unlink(&parent_key);
+        let (left_child_key, right_child_key) = match &self.node().value {
             NodeData::Branch {
                 left_child,
                 right_child,
-                payload,
-            } => (left_child, right_child, payload),
-            NodeData::Leaf(..) => unsafe {
-                unreachable_debugchecked("parent nodes cannot be leaves")
-            },
-        };
-        if &self.key == parent_left_child {
-            if let Some(parent_right_child_ref) = parent_right_child {
-                mem::swap(parent_left_child, parent_right_child_ref);
-                *parent_right_child = None;
-            } else {
-                let old_payload = unsafe {
-                    // SAFETY: the pointer is coerced from a reference and therefore is required to
-                    // be valid; we're also overwriting this, so no duplication
-                    ptr::read(parent_payload)
-                };
-                // Destroy the mutable references to modify parent
-                drop((parent_left_child, parent_right_child));
-                unsafe {
-                    // SAFETY: as above
-                    ptr::write(
-                        &mut self.tree.storage.get_unchecked_mut(&parent_key).value,
-                        NodeData::Leaf(abort_on_panic(|| f(old_payload))),
-                    );
-                }
-            }
-        } else if Some(&self.key) == parent_right_child.as_ref() {
-            *parent_right_child = None;
-        } else {
-            unsafe {
-                // SAFETY: a node cannot have a parent which does not list it as one
-                // of its children
-                unreachable_debugchecked(
-                    "failed to identify whether the node is the left or right child",
-                )
-            }
-        }
-        let key = self.key.clone();
-        let (payload, left_child_key, right_child_key) = match self.tree.storage.remove(&key).value
-        {
-            NodeData::Branch {
-                payload,
-                left_child: left_child_key,
-                right_child: right_child_key,
-            } => (payload, left_child_key, right_child_key),
+                ..
+            } => (left_child.clone(), right_child.clone()),
             NodeData::Leaf(..) => unsafe {
                 // SAFETY: the beggining of the function tests for self being a branch node
                 hint::unreachable_unchecked()
             },
         };
-        let left_child_payload = match self.tree.storage.remove(&left_child_key).value {
-            NodeData::Leaf(x) => x,
-            NodeData::Branch { .. } => unsafe {
-                // SAFETY: a check for branch children was made at the beginning
-                hint::unreachable_unchecked()
-            },
-        };
+        // Removing a node might shift the keys of the nodes added after it, so the right child is
+        // removed first and the node itself is removed last
         let right_child_payload = right_child_key.map(|right_child_key| {
             match self.tree.storage.remove(&right_child_key).value {
                 NodeData::Leaf(x) => x,
                 NodeData::Branch { .. } => unsafe {
-                    // SAFETY: as above
+                    // SAFETY: a check for branch children was made at the beginning
                     hint::unreachable_unchecked()
                 },
             }
         });
+        let left_child_payload = match self.tree.storage.remove(&left_child_key).value {
+            NodeData::Leaf(x) => x,
+            NodeData::Branch { .. } => unsafe {
+                // SAFETY: as above
+                hint::unreachable_unchecked()
+            },
+        };
+        let payload = match self.tree.storage.remove(&self.key).value {
+            NodeData::Branch { payload, .. } => payload,
+            NodeData::Leaf(..) => unsafe {
+                // SAFETY: as above
+                hint::unreachable_unchecked()
+            },
+        };
+        let left_child_payload = if was_only_child {
+            let parent = unsafe {
+                // SAFETY: parent nodes are added to the storage before their children, so removing
+                // a child does not invalidate the key of its parent
+                self.tree.storage.get_unchecked_mut(&parent_key)
+            };
+            make_leaf(&mut parent.value, left_child_payload)
+        } else {
+            left_child_payload
+        };
         Ok((payload, left_child_payload, right_child_payload))
     }
     /// Attempts to remove a branch node's children without using recursion, replacing it with a leaf node, the value for which is provided by the specified closure.
@@ -458,6 +496,298 @@ debug key check failed: tried to reference key {:?} which is not present in the
         &mut self,
         f: impl FnOnce(B) -> L,
     ) -> Result<(L, Option<L>), TryRemoveChildrenError> {
+        let (left_child_payload, right_child_payload) = self.remove_leaf_children()?;
+        // The payload of the left child stands in for the one of the node while the closure runs,
+        // keeping the tree valid if it panics
+        let left_child_payload = self.node_mut().value.make_leaf_with(left_child_payload, f);
+        Ok((left_child_payload, right_child_payload))
+    }
+    /// Recursively removes the specified node and all its descendants, using a closure to patch nodes which transition from having one child to having zero children.
+    ///
+    /// The descendants are removed in post-order. Instead of keeping a stack of the nodes to return to, the walk goes back up using the parent links of the nodes, without allocating memory.
+    ///
+    /// # Panics
+    /// Will panic in the following scenarios:
+    /// - The node is the root node, which can never be removed.
+    /// - The tree [keeps the right child in place] and the node is the left child of a full branch node, or the tree [only allows full branch nodes]. The descendants of the node are removed regardless, since none of them stay in the tree.
+    ///
+    /// [keeps the right child in place]: enum.RemovalPolicy.html#variant.KeepRightInPlace " "
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
+    pub fn recursively_remove_with(
+        self,
+        mut branch_to_leaf: impl FnMut(B) -> L,
+    ) -> NodeValue<B, L> {
+        assert!(!self.is_root(), "attempted to remove the root node");
+        assert!(
+            !self.would_shift_right_child(),
+            "removing the node would shift the right child of its parent into its place",
+        );
+        assert!(
+            !self.tree.full_branches_only,
+            "removing the node would leave its parent with only one child",
+        );
+        let NodeRefMut { tree, key: target } = self;
+        let mut key = target.clone();
+        loop {
+            let node = unsafe {
+                // SAFETY: the walk only visits keys taken from the tree itself
+                NodeRefMut::new_raw_unchecked(&mut *tree, key.clone())
+            };
+            // Parent nodes are added to the storage before their children, so removing the node
+            // does not invalidate the key of its parent
+            let parent_key = node.node().parent.clone().unwrap_or_else(|| unsafe {
+                unreachable_debugchecked("the walk never goes above the node being removed")
+            });
+            if node.is_leaf() {
+                let payload = node
+                    .try_remove_leaf_ignoring_policy(|parent, payload| {
+                        parent.make_leaf_with(payload, &mut branch_to_leaf)
+                    })
+                    .unwrap_or_else(|_| unsafe {
+                        unreachable_debugchecked("the node is a leaf and has a parent")
+                    });
+                if key == target {
+                    return NodeValue::Leaf(payload);
+                }
+            } else {
+                let removed = node.try_remove_branch_ignoring_policy(|parent, payload| {
+                    parent.make_leaf_with(payload, &mut branch_to_leaf)
+                });
+                match removed {
+                    Ok((payload, ..)) if key == target => return NodeValue::Branch(payload),
+                    Ok(..) => {}
+                    Err(TryRemoveBranchError::HadBranchChild(index)) => {
+                        key = match unsafe {
+                            // SAFETY: as above
+                            &tree.storage.get_unchecked(&key).value
+                        } {
+                            NodeData::Branch { left_child, .. } if index == 0 => left_child,
+                            NodeData::Branch {
+                                right_child: Some(right_child),
+                                ..
+                            } => right_child,
+                            _ => unsafe {
+                                unreachable_debugchecked("the branch child could not be found")
+                            },
+                        }
+                        .clone();
+                        continue;
+                    }
+                    Err(..) => unsafe {
+                        unreachable_debugchecked("the node is a branch and has a parent")
+                    },
+                }
+            }
+            // The parent either still has another child or has been turned into a leaf, which is
+            // removed on the next iteration
+            key = parent_key;
+        }
+    }
+    /// Moves the node and all its descendants to a different parent node in the same tree, without moving any payloads in memory: only the links between the nodes are changed, and the node keeps its key.
+    ///
+    /// Since the children of a binary tree node are not shifted to make room, the node can only be moved into a free slot: if the new parent node is a leaf node, it's converted into a branch node using the second closure and the node becomes its left child, and if it's a branch node without a right child, the node becomes its right child. If the node was the left child of its old parent, the right child of the old parent becomes its left child, and if it was its only child, the old parent is converted into a leaf node using the first closure. Moving the left child of a full branch node to the same parent thus swaps its children.
+    ///
+    /// Since sparse storage cannot tell the holes left by removed nodes apart from nodes, the new parent node is checked to be in the tree by looking for it among the nodes of the tree, which takes *O*(*n*) time, where *n* is the number of nodes in the tree.
+    ///
+    /// # Errors
+    /// Will fail in the following scenarios, leaving the tree unchanged:
+    /// - The node was the root node, which cannot have a parent.
+    /// - The key of the new parent node does not point to a node in the tree.
+    /// - The new parent node is the node itself or one of its descendants.
+    /// - The new parent node is a full branch node other than the current parent of the node.
+    /// - The tree [keeps the right child in place] and the node was the left child of a full branch node.
+    /// - The tree [only allows full branch nodes] and the new parent node is not the current parent of the node.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::binary_tree::{BinaryTree, NodeRefMut};
+    ///
+    /// let mut tree = BinaryTree::<_>::new(0);
+    /// tree.root_mut().make_branch(1, Some(2)).unwrap();
+    /// let left_key = tree.root().left_child().unwrap().into_raw_key();
+    /// let right_key = tree.root().right_child().unwrap().into_raw_key();
+    /// // Make the left child of the root the left child of the right one
+    /// NodeRefMut::new_raw(&mut tree, left_key)
+    ///     .unwrap()
+    ///     .reparent_to_with(right_key, |_| unreachable!(), |payload| payload * 10)
+    ///     .unwrap();
+    /// let new_parent = tree.root().left_child().unwrap();
+    /// assert!(tree.root().right_child().is_none());
+    /// assert_eq!(new_parent.value().into_inner(), &20);
+    /// assert_eq!(new_parent.left_child().unwrap().value().into_inner(), &1);
+    /// ```
+    ///
+    /// [keeps the right child in place]: enum.RemovalPolicy.html#variant.KeepRightInPlace " "
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
+    pub fn reparent_to_with(
+        &mut self,
+        new_parent: K,
+        branch_to_leaf: impl FnOnce(B) -> L,
+        leaf_to_branch: impl FnOnce(L) -> B,
+    ) -> Result<(), ReparentError> {
+        let old_parent = self
+            .node()
+            .parent
+            .clone()
+            .ok_or(ReparentError::WasRootNode)?;
+        // Sparse storage reports the holes left by removed nodes as valid keys and panics once
+        // they're accessed, so the new parent is looked for among the nodes of the tree instead
+        let root = self.tree.root.clone();
+        let new_parent_in_tree = self.tree.storage.contains_key(&new_parent)
+            && iter::successors(Some(root.clone()), |key| {
+                next_in_preorder(&*self.tree, key, &root)
+            })
+            .any(|key| key == new_parent);
+        if !new_parent_in_tree {
+            return Err(ReparentError::NewParentNotFound);
+        }
+        let moves_into_subtree = new_parent == self.key || {
+            let new_parent_ref = unsafe {
+                // SAFETY: the key was checked above
+                NodeRef::new_raw_unchecked(&*self.tree, new_parent.clone())
+            };
+            new_parent_ref.is_descendant_of(&NodeRef::from(&*self))
+        };
+        if moves_into_subtree {
+            return Err(ReparentError::NewParentInSubtree);
+        }
+        if new_parent != old_parent {
+            if let NodeData::Branch {
+                right_child: Some(..),
+                ..
+            } = unsafe {
+                // SAFETY: as above
+                &self.tree.storage.get_unchecked(&new_parent).value
+            } {
+                return Err(ReparentError::NewParentFull);
+            }
+        }
+        if self.would_shift_right_child() {
+            return Err(ReparentError::WouldShiftRightChild);
+        }
+        // Moving a node to its own parent only swaps the children of a full branch node
+        if self.tree.full_branches_only && new_parent != old_parent {
+            return Err(ReparentError::WouldMakePartialBranch);
+        }
+        let was_only_child = self.unlink(&old_parent);
+        if was_only_child && new_parent == old_parent {
+            // The parent still lists the node as its left child
+            return Ok(());
+        }
+        let self_key = self.key.clone();
+        let parent = unsafe {
+            // SAFETY: as above
+            self.tree.storage.get_unchecked_mut(&new_parent)
+        };
+        match &mut parent.value {
+            NodeData::Branch { right_child, .. } => *right_child = Some(self_key),
+            NodeData::Leaf(val) => {
+                let old_payload = unsafe {
+                    // SAFETY: we're overwriting the value right after this
+                    ptr::read(val)
+                };
+                // The node is detached from the tree at this point, so the tree is not valid if
+                // the closure panics
+                let new_payload = abort_on_panic(|| leaf_to_branch(old_payload));
+                unsafe {
+                    // SAFETY: as above
+                    ptr::write(
+                        &mut parent.value,
+                        NodeData::Branch {
+                            payload: new_payload,
+                            left_child: self_key,
+                            right_child: None,
+                        },
+                    );
+                }
+            }
+        }
+        self.node_mut().parent = Some(new_parent);
+        if was_only_child {
+            let parent = unsafe {
+                // SAFETY: parent keys are always valid
+                self.tree.storage.get_unchecked_mut(&old_parent)
+            };
+            let old_payload_ref = if let NodeData::Branch { payload, .. } = &parent.value {
+                payload
+            } else {
+                unsafe { unreachable_debugchecked("parent nodes cannot be leaves") }
+            };
+            let old_payload = unsafe {
+                // SAFETY: we're overwriting the value right after this
+                ptr::read(old_payload_ref)
+            };
+            // The old parent has no children left whose payload could stand in for its own
+            let new_payload = abort_on_panic(|| branch_to_leaf(old_payload));
+            unsafe {
+                // SAFETY: as above
+                ptr::write(&mut parent.value, NodeData::Leaf(new_payload));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the node has a parent but cannot be removed from it without breaking the rules set for the tree, `false` otherwise.
+    fn is_pinned(&self) -> bool {
+        !self.is_root() && (self.tree.full_branches_only || self.would_shift_right_child())
+    }
+    /// Returns `true` if the tree keeps the right child in place and the node is the left child of a full branch node, which means that the node cannot be unlinked from its parent.
+    fn would_shift_right_child(&self) -> bool {
+        if self.tree.removal_policy != RemovalPolicy::KeepRightInPlace {
+            return false;
+        }
+        self.node().parent.as_ref().map_or(false, |parent_key| {
+            match unsafe {
+                // SAFETY: parent keys are always valid
+                &self.tree.storage.get_unchecked(parent_key).value
+            } {
+                NodeData::Branch {
+                    left_child,
+                    right_child: Some(..),
+                    ..
+                } => left_child == &self.key,
+                _ => false,
+            }
+        })
+    }
+    /// Removes the link to the node from its parent, returning whether it was the only child of its parent. If the node was the left child, the right child of the parent becomes its left child. The node itself is left intact and has to be removed from the storage afterwards. If it was the only child, the parent is left as a branch node with a dangling link to its left child, which the caller has to replace with a leaf node.
+    fn unlink(&mut self, parent_key: &K) -> bool {
+        let (parent_left_child, parent_right_child) = match unsafe {
+            // SAFETY: parent key is guaranteed to be valid
+            &mut self.tree.storage.get_unchecked_mut(parent_key).value
+        } {
+            NodeData::Branch {
+                left_child,
+                right_child,
+                ..
+            } => (left_child, right_child),
+            NodeData::Leaf(..) => unsafe {
+                unreachable_debugchecked("parent nodes cannot be leaves")
+            },
+        };
+        if &self.key == parent_left_child {
+            if let Some(right_child_ref) = parent_right_child {
+                mem::swap(parent_left_child, right_child_ref);
+                *parent_right_child = None;
+                false
+            } else {
+                true
+            }
+        } else if Some(&self.key) == parent_right_child.as_ref() {
+            *parent_right_child = None;
+            false
+        } else {
+            unsafe {
+                // SAFETY: a node cannot have a parent which does not list it as one
+                // of its children
+                unreachable_debugchecked(
+                    "failed to identify whether the node is the left or right child",
+                )
+            }
+        }
+    }
+    /// Removes the children of the node, which must all be leaves, and returns their payloads. The node is left as a branch node with dangling links to its children, which the caller has to replace with a leaf node right away.
+    fn remove_leaf_children(&mut self) -> Result<(L, Option<L>), TryRemoveChildrenError> {
         let (left_child_key, right_child_key, ..) = if let NodeData::Branch {
             left_child,
             right_child,
@@ -475,55 +805,32 @@ debug key check failed: tried to reference key {:?} which is not present in the
             };
             if left_child_ref.is_branch() {
                 return Err(TryRemoveChildrenError::HadBranchChild(0));
-            } else if right_child_ref.as_ref().map(NodeRef::is_branch) == Some(true) {
+            } else if right_child_ref.as_ref().map_or(false, NodeRef::is_branch) {
                 return Err(TryRemoveChildrenError::HadBranchChild(1));
             }
             (left_child_ref.key, right_child_ref.map(|x| x.key))
         } else {
             return Err(TryRemoveChildrenError::WasLeafNode);
         };
-        let left_child_payload = match self.tree.storage.remove(&left_child_key).value {
-            NodeData::Leaf(x) => x,
-            NodeData::Branch { .. } => unsafe {
-                // SAFETY: a check for branch children was made at the beginning
-                hint::unreachable_unchecked()
-            },
-        };
+        // The right child is removed first, since removing the left child might shift its key
         let right_child_payload = right_child_key.map(|right_child_key| {
             match self.tree.storage.remove(&right_child_key).value {
                 NodeData::Leaf(x) => x,
                 NodeData::Branch { .. } => unsafe {
-                    // SAFETY: as above
+                    // SAFETY: a check for branch children was made at the beginning
                     hint::unreachable_unchecked()
                 },
             }
         });
-        let old_payload_ref = match &mut self.node_mut().value {
-            NodeData::Branch { payload, .. } => payload,
-            NodeData::Leaf(..) => unsafe {
-                // SAFETY: we checked for a leaf node in the beginning
+        let left_child_payload = match self.tree.storage.remove(&left_child_key).value {
+            NodeData::Leaf(x) => x,
+            NodeData::Branch { .. } => unsafe {
+                // SAFETY: as above
                 hint::unreachable_unchecked()
             },
         };
-        let old_payload = unsafe {
-            // SAFETY: the pointer is coerced from a reference and therefore is required to
-            // be valid; we're also overwriting this, so no duplication
-            ptr::read(old_payload_ref)
-        };
-        unsafe {
-            // SAFETY: as above
-            ptr::write(
-                &mut self.node_mut().value,
-                NodeData::Leaf(abort_on_panic(|| f(old_payload))),
-            );
-        }
         Ok((left_child_payload, right_child_payload))
     }
-    /// Recursively removes the specified node and all its descendants, using a closure to patch nodes which transition from having one child to having zero children.
-    pub fn recursively_remove_with(self, branch_to_leaf: impl FnMut(B) -> L) -> NodeValue<B, L> {
-        algorithms::recursively_remove_with(self.tree, self.key, branch_to_leaf)
-    }
-
     fn node(&self) -> &'_ Node<B, L, K> {
         debug_assert!(
             self.tree.storage.contains_key(&self.key),
@@ -549,7 +856,7 @@ debug key check failed: tried to reference key {:?} which is not present in the
         }
     }
 }
-impl<'a, D, K, S> NodeRefMut<'a, D, D, K, S>
+impl<D, K, S> NodeRefMut<'_, D, D, K, S>
 where
     S: Storage<Element = Node<D, D, K>, Key = K>,
     K: Clone + Debug + Eq,
@@ -558,6 +865,11 @@ where
     ///
     /// # Errors
     /// Will fail if the node is already a branch node. In such a case, the provided values for the children are returned back to the caller.
+    ///
+    /// # Panics
+    /// See [`make_branch_with`].
+    ///
+    /// [`make_branch_with`]: #method.make_branch_with " "
     pub fn make_branch(
         &mut self,
         left_child: D,
@@ -565,6 +877,20 @@ where
     ) -> Result<(), MakeBranchError<D, ArrayVec<[D; 2]>>> {
         self.make_branch_with(left_child, right_child, convert::identity)
     }
+    /// Converts a leaf node into a branch node with the specified leaf children, keeping its payload. Because of that, *this method is only available when the payload for leaf nodes and branch nodes is the same.* Unlike [`make_branch`], returns an error instead of panicking if the storage cannot fit the children.
+    ///
+    /// # Errors
+    /// Will fail if the node is already a branch node, if the storage has a fixed capacity and does not have enough free space for the children or if the tree [only allows full branch nodes] and no right child was provided. In all cases, the provided values for the children are returned back to the caller.
+    ///
+    /// [`make_branch`]: #method.make_branch " "
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
+    pub fn try_make_branch(
+        &mut self,
+        left_child: D,
+        right_child: Option<D>,
+    ) -> Result<(), TryMakeBranchError<D, ArrayVec<[D; 2]>>> {
+        self.try_make_branch_with(left_child, right_child, convert::identity)
+    }
 
     /// Attempts to remove the node without using recursion. If the parent only had one child, it's replaced with a leaf node, keeping its original payload, which is why *this method is only available when the payload for leaf nodes and branch nodes is the same.*
     ///
@@ -572,8 +898,19 @@ where
     /// Will fail in the following scenarios:
     /// - The node was a branch node, which would require recursion to remove, and this function explicitly does not implement recursive removal.
     /// - The node was the root node, which can never be removed.
+    /// - The tree [keeps the right child in place] and the node was the left child of a full branch node.
+    /// - The tree [only allows full branch nodes].
+    ///
+    /// [keeps the right child in place]: enum.RemovalPolicy.html#variant.KeepRightInPlace " "
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
     pub fn try_remove_leaf(self) -> Result<D, TryRemoveLeafError> {
-        self.try_remove_leaf_with(convert::identity)
+        if self.is_leaf() && self.is_pinned() {
+            return Err(TryRemoveLeafError::CannotRemoveIndividualChildren);
+        }
+        self.try_remove_leaf_ignoring_policy(|parent, payload| {
+            parent.make_leaf_keeping_payload();
+            payload
+        })
     }
     /// Attempts to remove a branch node without using recursion. If its parent only had one child, it's replaced with a leaf node, keeping its original payload, which is why *this method is only available when the payload for leaf nodes and branch nodes is the same.*
     ///
@@ -582,21 +919,398 @@ where
     /// - The node was a leaf node. The `try_remove_leaf`/`try_remove_leaf_with` methods exist for that.
     /// - The node was the root node, which can never be removed.
     /// - One or more of the node's children were a branch node, which thus would require recursion to remove.
+    /// - The tree [keeps the right child in place] and the node was the left child of a full branch node.
+    /// - The tree [only allows full branch nodes].
+    ///
+    /// [keeps the right child in place]: enum.RemovalPolicy.html#variant.KeepRightInPlace " "
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
     pub fn try_remove_branch(self) -> Result<(D, D, Option<D>), TryRemoveBranchError> {
-        self.try_remove_branch_with(convert::identity)
+        if self.is_branch() && self.is_pinned() {
+            return Err(TryRemoveBranchError::CannotRemoveIndividualChildren);
+        }
+        self.try_remove_branch_ignoring_policy(|parent, left_child_payload| {
+            parent.make_leaf_keeping_payload();
+            left_child_payload
+        })
     }
     /// Attempts to remove a branch node's children without using recursion, replacing it with a leaf node, keeping its original payload. Because of that, *this method is only available when the payload for leaf nodes and branch nodes is the same.*
     ///
+    /// Unlike with [`try_remove_children_with`], the payload is not passed through a conversion closure, so no removed child has to stand in for it while it is moved.
+    ///
     /// # Errors
     /// Will fail in the following scenarios:
     /// - The node was a leaf node, which cannot have children by definition.
     /// - One or more of the node's children were a branch node, which thus would require recursion to remove.
+    ///
+    /// [`try_remove_children_with`]: #method.try_remove_children_with " "
     pub fn try_remove_children(&mut self) -> Result<(D, Option<D>), TryRemoveChildrenError> {
-        self.try_remove_children_with(convert::identity)
+        let children = self.remove_leaf_children()?;
+        self.node_mut().value.make_leaf_keeping_payload();
+        Ok(children)
     }
     /// Recursively removes the specified node and all its descendants. Will keep the original payload of the parent node if removing this node results in a transformation of the parent into a leaf, which is why *this method is only available when the payload for leaf nodes and branch nodes is the same.*
+    ///
+    /// # Panics
+    /// See [`recursively_remove_with`].
+    ///
+    /// [`recursively_remove_with`]: #method.recursively_remove_with " "
     pub fn recursively_remove(self) -> NodeValue<D> {
-        algorithms::recursively_remove(self.tree, self.key)
+        self.recursively_remove_with(convert::identity)
+    }
+    /// Moves the node and all its descendants into a free child slot of a different parent node in the same tree, without moving any payloads in memory. The old and new parent nodes keep their payloads if they get converted between leaf and branch nodes, which is why *this method is only available when the payload for leaf nodes and branch nodes is the same.*
+    ///
+    /// See [`reparent_to_with`] for which slot the node ends up in.
+    ///
+    /// # Errors
+    /// Will fail in the following scenarios, leaving the tree unchanged:
+    /// - The node was the root node, which cannot have a parent.
+    /// - The key of the new parent node does not point to a node in the tree.
+    /// - The new parent node is the node itself or one of its descendants.
+    /// - The new parent node is a full branch node other than the current parent of the node.
+    /// - The tree [keeps the right child in place] and the node was the left child of a full branch node.
+    /// - The tree [only allows full branch nodes] and the new parent node is not the current parent of the node.
+    ///
+    /// [`reparent_to_with`]: #method.reparent_to_with " "
+    /// [keeps the right child in place]: enum.RemovalPolicy.html#variant.KeepRightInPlace " "
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
+    pub fn reparent_to(&mut self, new_parent: K) -> Result<(), ReparentError> {
+        self.reparent_to_with(new_parent, convert::identity, convert::identity)
+    }
+}
+impl<B, L, S> NodeRefMut<'_, B, L, usize, SparseStorage<Node<B, L, usize>, S>>
+where
+    S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
+{
+    /// Makes the specified tree the subtree of the left child of the node, moving its nodes into the storage of this tree. If the node already had a left child, its subtree is taken out of the tree and returned, and if the node was a leaf node, it becomes a branch node with only a left child, using the specified closure to convert the payload.
+    ///
+    /// The nodes are moved without using recursion and without moving any payloads other than from one storage to the other. Together with [`set_right_subtree`], this allows trees to be built bottom-up, as is typical for parsers, which produce the operands of an operator before the operator itself. The keys of the nodes of the provided tree become invalid, and the subtree which was replaced, if any, gets new keys in the returned tree.
+    ///
+    /// # Errors
+    /// Will fail if the tree [only allows full branch nodes] and either the node was a leaf node or the provided tree has a partial branch node. In such a case, the provided tree is returned back to the caller and this tree is left unchanged.
+    ///
+    /// # Panics
+    /// Will panic if the storage has fixed capacity and cannot fit the nodes of the provided tree once the replaced subtree is taken out. The tree is left unchanged in such a case.
+    ///
+    /// [`set_right_subtree`]: #method.set_right_subtree " "
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
+    pub fn set_left_subtree_with(
+        &mut self,
+        subtree: SparseBinaryTree<B, L, S>,
+        leaf_to_branch: impl FnOnce(L) -> B,
+    ) -> SetSubtreeResult<B, L, S> {
+        if self.tree.full_branches_only
+            && (self.is_leaf() || first_partial_branch(&subtree.storage, &subtree.root).is_some())
+        {
+            return Err(SetSubtreeError::WouldMakePartialBranch { subtree });
+        }
+        let old_left_child = match &self.node().value {
+            NodeData::Branch { left_child, .. } => Some(*left_child),
+            NodeData::Leaf(..) => None,
+        };
+        self.assert_room_for(&subtree, old_left_child);
+        let old_subtree = old_left_child.map(|old_left_child| self.split_off(old_left_child));
+        let new_left_child = self.adopt(subtree);
+        let node = self.node_mut();
+        match &mut node.value {
+            NodeData::Branch { left_child, .. } => *left_child = new_left_child,
+            NodeData::Leaf(val) => {
+                let old_payload = unsafe {
+                    // SAFETY: we're overwriting the value right after this
+                    ptr::read(val)
+                };
+                // The new left child is not linked to the node yet, so the tree is not valid if
+                // the closure panics
+                let new_payload = abort_on_panic(|| leaf_to_branch(old_payload));
+                unsafe {
+                    // SAFETY: as above
+                    ptr::write(
+                        &mut node.value,
+                        NodeData::Branch {
+                            payload: new_payload,
+                            left_child: new_left_child,
+                            right_child: None,
+                        },
+                    );
+                }
+            }
+        }
+        Ok(old_subtree)
+    }
+    /// Makes the specified tree the subtree of the right child of the node, moving its nodes into the storage of this tree. If the node already had a right child, its subtree is taken out of the tree and returned.
+    ///
+    /// See [`set_left_subtree_with`] for the details.
+    ///
+    /// # Errors
+    /// Will fail in the following scenarios, returning the provided tree back to the caller and leaving this tree unchanged:
+    /// - The node was a leaf node, which cannot get a right child without having a left one first.
+    /// - The tree [only allows full branch nodes] and the provided tree has a partial branch node.
+    ///
+    /// # Panics
+    /// Will panic if the storage has fixed capacity and cannot fit the nodes of the provided tree once the replaced subtree is taken out. The tree is left unchanged in such a case.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{BinaryTree, repr::BinaryTreeRepr};
+    ///
+    /// // Parse 1 + 2 * 3 bottom-up, starting from the operands.
+    /// let mut product = BinaryTree::<_>::new("*");
+    /// product.root_mut().set_left_subtree(BinaryTree::new("2")).unwrap();
+    /// product.root_mut().set_right_subtree(BinaryTree::new("3")).unwrap();
+    /// let mut sum = BinaryTree::<_>::new("+");
+    /// sum.root_mut().set_left_subtree(BinaryTree::new("1")).unwrap();
+    /// sum.root_mut().set_right_subtree(product).unwrap();
+    ///
+    /// let leaf = |payload| Box::new(BinaryTreeRepr::Leaf(payload));
+    /// assert_eq!(
+    ///     sum.to_repr(),
+    ///     BinaryTreeRepr::Branch("+", leaf("1"), Some(Box::new(
+    ///         BinaryTreeRepr::Branch("*", leaf("2"), Some(leaf("3"))),
+    ///     ))),
+    /// );
+    ///
+    /// // Take the product back out and put the operands into the other order.
+    /// let mut product = sum.root_mut().take_right_subtree().unwrap();
+    /// let three = product.root_mut().take_right_subtree().unwrap();
+    /// let two = product.root_mut().set_left_subtree(three).unwrap().unwrap();
+    /// product.root_mut().set_right_subtree(two).unwrap();
+    /// assert_eq!(product.to_repr(), BinaryTreeRepr::Branch("*", leaf("3"), Some(leaf("2"))));
+    /// ```
+    ///
+    /// [`set_left_subtree_with`]: #method.set_left_subtree_with " "
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
+    pub fn set_right_subtree(
+        &mut self,
+        subtree: SparseBinaryTree<B, L, S>,
+    ) -> SetSubtreeResult<B, L, S> {
+        let old_right_child = match &self.node().value {
+            NodeData::Branch { right_child, .. } => *right_child,
+            NodeData::Leaf(..) => return Err(SetSubtreeError::WasLeafNode { subtree }),
+        };
+        if self.tree.full_branches_only
+            && first_partial_branch(&subtree.storage, &subtree.root).is_some()
+        {
+            return Err(SetSubtreeError::WouldMakePartialBranch { subtree });
+        }
+        self.assert_room_for(&subtree, old_right_child);
+        let old_subtree = old_right_child.map(|old_right_child| self.split_off(old_right_child));
+        let new_right_child = self.adopt(subtree);
+        if let NodeData::Branch { right_child, .. } = &mut self.node_mut().value {
+            *right_child = Some(new_right_child);
+        }
+        Ok(old_subtree)
+    }
+    /// Takes the subtree of the left child of the node out of the tree, returning it as a separate tree. If the node was a partial branch node, it becomes a leaf node, using the specified closure to convert the payload, and if it was a full branch node, its right child becomes its left child.
+    ///
+    /// The nodes are moved into the storage of the new tree without using recursion and get new keys there. The new tree has the same removal policy as this one.
+    ///
+    /// # Errors
+    /// Will fail in the following scenarios, leaving the tree unchanged:
+    /// - The node was a leaf node, which has no children.
+    /// - The node was a full branch node and the tree [keeps the right child in place].
+    /// - The node was a full branch node and the tree [only allows full branch nodes].
+    ///
+    /// # Panics
+    /// The storage of the new tree may panic if it has fixed capacity and cannot fit the nodes of the subtree.
+    ///
+    /// [keeps the right child in place]: enum.RemovalPolicy.html#variant.KeepRightInPlace " "
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
+    pub fn take_left_subtree_with(
+        &mut self,
+        branch_to_leaf: impl FnOnce(B) -> L,
+    ) -> Result<SparseBinaryTree<B, L, S>, TakeSubtreeError> {
+        let (left_child, right_child) = match &self.node().value {
+            NodeData::Branch {
+                left_child,
+                right_child,
+                ..
+            } => (*left_child, *right_child),
+            NodeData::Leaf(..) => return Err(TakeSubtreeError::NoSuchChild),
+        };
+        if right_child.is_some() {
+            if self.tree.full_branches_only {
+                return Err(TakeSubtreeError::WouldMakePartialBranch);
+            } else if self.tree.removal_policy == RemovalPolicy::KeepRightInPlace {
+                return Err(TakeSubtreeError::WouldShiftRightChild);
+            }
+        }
+        let subtree = self.split_off(left_child);
+        let node = self.node_mut();
+        if let Some(right_child) = right_child {
+            if let NodeData::Branch {
+                left_child,
+                right_child: right_child_ref,
+                ..
+            } = &mut node.value
+            {
+                *left_child = right_child;
+                *right_child_ref = None;
+            }
+        } else {
+            let old_payload_ref = if let NodeData::Branch { payload, .. } = &node.value {
+                payload
+            } else {
+                unsafe { unreachable_debugchecked("the node was a branch node above") }
+            };
+            let old_payload = unsafe {
+                // SAFETY: we're overwriting the value right after this
+                ptr::read(old_payload_ref)
+            };
+            // The node has no children left whose payload could stand in for its own
+            let new_payload = abort_on_panic(|| branch_to_leaf(old_payload));
+            unsafe {
+                // SAFETY: as above
+                ptr::write(&mut node.value, NodeData::Leaf(new_payload));
+            }
+        }
+        Ok(subtree)
+    }
+    /// Takes the subtree of the right child of the node out of the tree, returning it as a separate tree. The node becomes a partial branch node.
+    ///
+    /// See [`take_left_subtree_with`] for the details.
+    ///
+    /// # Errors
+    /// Will fail if the node had no right child or if the tree [only allows full branch nodes], leaving the tree unchanged.
+    ///
+    /// # Panics
+    /// The storage of the new tree may panic if it has fixed capacity and cannot fit the nodes of the subtree.
+    ///
+    /// # Example
+    /// See the example in [`set_right_subtree`].
+    ///
+    /// [`take_left_subtree_with`]: #method.take_left_subtree_with " "
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
+    /// [`set_right_subtree`]: #method.set_right_subtree " "
+    pub fn take_right_subtree(&mut self) -> Result<SparseBinaryTree<B, L, S>, TakeSubtreeError> {
+        let right_child = match &self.node().value {
+            NodeData::Branch {
+                right_child: Some(right_child),
+                ..
+            } => *right_child,
+            _ => return Err(TakeSubtreeError::NoSuchChild),
+        };
+        if self.tree.full_branches_only {
+            return Err(TakeSubtreeError::WouldMakePartialBranch);
+        }
+        let subtree = self.split_off(right_child);
+        if let NodeData::Branch { right_child, .. } = &mut self.node_mut().value {
+            *right_child = None;
+        }
+        Ok(subtree)
+    }
+
+    /// Panics if the storage has fixed capacity and cannot fit the nodes of the tree once the subtree of the specified child, if any, is taken out. This has to be checked before the child is taken out, since the node would be left linking to a hole if the storage panicked while the new nodes are being moved in.
+    fn assert_room_for(&self, subtree: &SparseBinaryTree<B, L, S>, replaced_child: Option<usize>) {
+        let capacity = match S::CAPACITY {
+            Some(capacity) => capacity,
+            None => return,
+        };
+        let num_freed = replaced_child.map_or(0, |child| {
+            iter::successors(Some(child), |key| {
+                next_in_preorder(&*self.tree, key, &child)
+            })
+            .count()
+        });
+        let num_nodes = Storage::len(&self.tree.storage) - self.tree.storage.num_holes();
+        let num_added = Storage::len(&subtree.storage) - subtree.storage.num_holes();
+        assert!(
+            num_nodes - num_freed + num_added <= capacity,
+            "the storage cannot fit the nodes of the subtree",
+        );
+    }
+    /// Moves the subtree of the specified child of the node into a new tree. The link to the child from the node is left dangling, and the caller has to replace it.
+    fn split_off(&mut self, child: usize) -> SparseBinaryTree<B, L, S> {
+        let mut storage = Storage::new();
+        let root = move_subtree(&mut *self.tree, child, &mut storage);
+        BinaryTree {
+            storage,
+            root,
+            removal_policy: self.tree.removal_policy,
+            full_branches_only: self.tree.full_branches_only,
+        }
+    }
+    /// Moves the nodes of the tree into the storage of this one, making the node their parent, and returns the new key of the root of the moved tree. The node is not linked to the moved root, which the caller has to do.
+    fn adopt(&mut self, mut subtree: SparseBinaryTree<B, L, S>) -> usize {
+        let root = subtree.root;
+        let new_root = move_subtree(&mut subtree, root, &mut self.tree.storage);
+        unsafe {
+            // SAFETY: we just added it
+            Storage::get_unchecked_mut(&mut self.tree.storage, &new_root)
+        }
+        .parent = Some(self.key);
+        new_root
+    }
+}
+impl<D, S> NodeRefMut<'_, D, D, usize, SparseStorage<Node<D, D, usize>, S>>
+where
+    S: ListStorage<Element = SparseStorageSlot<Node<D, D, usize>>>,
+{
+    /// Makes the specified tree the subtree of the left child of the node, moving its nodes into the storage of this tree. If the node already had a left child, its subtree is taken out of the tree and returned, and if the node was a leaf node, it becomes a branch node with only a left child, keeping its payload. Because of that, *this method is only available when the payload for leaf nodes and branch nodes is the same.*
+    ///
+    /// See [`set_left_subtree_with`] for the details.
+    ///
+    /// # Errors
+    /// Will fail if the tree [only allows full branch nodes] and either the node was a leaf node or the provided tree has a partial branch node. In such a case, the provided tree is returned back to the caller and this tree is left unchanged.
+    ///
+    /// # Panics
+    /// Will panic if the storage has fixed capacity and cannot fit the nodes of the provided tree once the replaced subtree is taken out. The tree is left unchanged in such a case.
+    ///
+    /// # Example
+    /// See the example in [`set_right_subtree`] for building trees bottom-up. The capacity of fixed-capacity storages is checked before anything is changed:
+    /// ```rust
+    /// use charcoal::{
+    ///     binary_tree::{BinaryTree, Node},
+    ///     storage::{SparseStorage, SparseStorageSlot},
+    ///     Tree,
+    /// };
+    /// use arrayvec::ArrayVec;
+    /// use std::panic::{catch_unwind, AssertUnwindSafe};
+    ///
+    /// type Slots = ArrayVec<[SparseStorageSlot<Node<i32, i32, usize>>; 3]>;
+    /// type SmallTree = BinaryTree<i32, i32, usize, SparseStorage<Node<i32, i32, usize>, Slots>>;
+    ///
+    /// let mut tree = SmallTree::new(0);
+    /// tree.root_mut().make_branch(1, Some(2)).unwrap();
+    /// // The left child is replaced by a single node, which fits.
+    /// tree.root_mut().set_left_subtree(SmallTree::new(10)).unwrap();
+    ///
+    /// // Three nodes don't fit in place of one.
+    /// let mut subtree = SmallTree::new(20);
+    /// subtree.root_mut().make_branch(21, Some(22)).unwrap();
+    /// let result = catch_unwind(AssertUnwindSafe(|| {
+    ///     tree.root_mut().set_left_subtree(subtree)
+    /// }));
+    /// assert!(result.is_err());
+    /// assert_eq!(tree.validate(), Ok(()));
+    /// assert_eq!(tree.root().left_child().unwrap().value().into_inner(), &10);
+    /// ```
+    ///
+    /// [`set_left_subtree_with`]: #method.set_left_subtree_with " "
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
+    /// [`set_right_subtree`]: #method.set_right_subtree " "
+    pub fn set_left_subtree(
+        &mut self,
+        subtree: SparseBinaryTree<D, D, S>,
+    ) -> SetSubtreeResult<D, D, S> {
+        self.set_left_subtree_with(subtree, convert::identity)
+    }
+    /// Takes the subtree of the left child of the node out of the tree, returning it as a separate tree. If the node was a partial branch node, it becomes a leaf node, keeping its payload, which is why *this method is only available when the payload for leaf nodes and branch nodes is the same.* If it was a full branch node, its right child becomes its left child.
+    ///
+    /// See [`take_left_subtree_with`] for the details.
+    ///
+    /// # Errors
+    /// Will fail in the following scenarios, leaving the tree unchanged:
+    /// - The node was a leaf node, which has no children.
+    /// - The node was a full branch node and the tree [keeps the right child in place].
+    /// - The node was a full branch node and the tree [only allows full branch nodes].
+    ///
+    /// # Panics
+    /// The storage of the new tree may panic if it has fixed capacity and cannot fit the nodes of the subtree.
+    ///
+    /// [`take_left_subtree_with`]: #method.take_left_subtree_with " "
+    /// [keeps the right child in place]: enum.RemovalPolicy.html#variant.KeepRightInPlace " "
+    /// [only allows full branch nodes]: struct.BinaryTree.html#method.enforce_full_branches " "
+    pub fn take_left_subtree(&mut self) -> Result<SparseBinaryTree<D, D, S>, TakeSubtreeError> {
+        self.take_left_subtree_with(convert::identity)
     }
 }
 impl<'a, B, L, K, S> From<&'a NodeRefMut<'a, B, L, K, S>> for NodeValue<&'a B, &'a L>
@@ -634,9 +1348,9 @@ where
     K: Clone + Debug + Eq,
 {
     fn from(op: &'a NodeRefMut<'a, B, L, K, S>) -> Self {
-        NodeRef {
-            tree: op.tree as &'a _,
-            key: op.key.clone(),
+        unsafe {
+            // SAFETY: NodeRefMut is guaranteed to point to a valid node
+            NodeRef::new_raw_unchecked(op.tree, op.key.clone())
         }
     }
 }
@@ -646,9 +1360,9 @@ where
     K: Clone + Debug + Eq,
 {
     fn from(op: &'a mut NodeRefMut<'a, B, L, K, S>) -> Self {
-        NodeRef {
-            tree: op.tree as &'a _,
-            key: op.key.clone(),
+        unsafe {
+            // SAFETY: NodeRefMut is guaranteed to point to a valid node
+            NodeRef::new_raw_unchecked(op.tree, op.key.clone())
         }
     }
 }
@@ -658,9 +1372,87 @@ where
     K: Clone + Debug + Eq,
 {
     fn from(op: NodeRefMut<'a, B, L, K, S>) -> Self {
-        NodeRef {
-            tree: op.tree as &'a _,
+        unsafe {
+            // SAFETY: NodeRefMut is guaranteed to point to a valid node
+            NodeRef::new_raw_unchecked(op.tree, op.key)
+        }
+    }
+}
+impl<'a, B, L, K, S> From<NodeRefMut<'a, B, L, K, S>>
+    for tree::NodeRefMut<'a, BinaryTree<B, L, K, S>>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn from(op: NodeRefMut<'a, B, L, K, S>) -> Self {
+        Self {
+            tree: op.tree,
             key: op.key,
         }
     }
 }
+
+/// Moves the node with the specified key and all of its descendants from the tree into the storage, returning the new key of the node. The links to the node, both from its old parent and from its new one, are left for the caller to fix.
+///
+/// The nodes are moved in depth-first post-order, so the children of every node are already in the storage by the time the node itself is moved. Until then, the moved subtrees are kept on a stack which is threaded through the parent links of their roots, which avoids allocating memory.
+fn move_subtree<B, L, S>(
+    source: &mut SparseBinaryTree<B, L, S>,
+    subtree_root: usize,
+    destination: &mut SparseStorage<Node<B, L, usize>, S>,
+) -> usize
+where
+    S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
+{
+    let top_parent = Tree::parent_key(&*source, &subtree_root);
+    let mut walk = PostorderRemoval::new(&*source, Some(subtree_root), top_parent);
+    // The root of the most recently moved subtree which is yet to be linked to its new parent
+    let mut top = None;
+    let pop = |destination: &mut SparseStorage<Node<B, L, usize>, S>, top: &mut Option<usize>| {
+        let key = top.unwrap_or_else(|| unsafe {
+            unreachable_debugchecked("the children of a node are moved before the node itself")
+        });
+        *top = unsafe {
+            // SAFETY: the key was just added to the storage
+            Storage::get_unchecked(&*destination, &key)
+        }
+        .parent;
+        key
+    };
+    while let Some(key) = walk.next_key(&*source, |_, _| None) {
+        let value = match Storage::remove(&mut source.storage, &key).value {
+            NodeData::Branch {
+                payload,
+                right_child,
+                ..
+            } => {
+                // The right child was moved last, so it's on top of the left one
+                let right_child = right_child.map(|_| pop(destination, &mut top));
+                let left_child = pop(destination, &mut top);
+                NodeData::Branch {
+                    payload,
+                    left_child,
+                    right_child,
+                }
+            }
+            NodeData::Leaf(payload) => NodeData::Leaf(payload),
+        };
+        let (left_child, right_child) = match &value {
+            NodeData::Branch {
+                left_child,
+                right_child,
+                ..
+            } => (Some(*left_child), *right_child),
+            NodeData::Leaf(..) => (None, None),
+        };
+        let new_key = Storage::add(destination, Node { value, parent: top });
+        for child in left_child.iter().chain(right_child.iter()) {
+            unsafe {
+                // SAFETY: the children were moved before the node
+                Storage::get_unchecked_mut(destination, child)
+            }
+            .parent = Some(new_key);
+        }
+        top = Some(new_key);
+    }
+    top.unwrap_or_else(|| unsafe { unreachable_debugchecked("the subtree has at least one node") })
+}
@@ -0,0 +1,282 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::{fmt::Debug, iter};
+use crate::{storage::Storage, repr::BinaryTreeRepr, util::unreachable_debugchecked, NonShiftingStorage};
+use super::{base::first_partial_branch, BinaryTree, Node, NodeData, RemovalPolicy};
+
+impl<B, L, K, S> BinaryTree<B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Creates a binary tree from its [nested enum representation].
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{binary_tree::BinaryTree, repr::BinaryTreeRepr};
+    ///
+    /// let tree = BinaryTree::<_>::from_repr(BinaryTreeRepr::Branch(
+    ///     1,
+    ///     Box::new(BinaryTreeRepr::Leaf(2)),
+    ///     None,
+    /// ));
+    /// let root = tree.root();
+    /// assert!(!root.is_full_branch());
+    /// assert_eq!(*root.left_child().unwrap().value().into_inner(), 2);
+    /// ```
+    ///
+    /// [nested enum representation]: ../repr/index.html " "
+    pub fn from_repr(repr: BinaryTreeRepr<B, L>) -> Self {
+        let mut storage = S::new();
+        let root = add_repr(&mut storage, repr);
+        Self {
+            storage,
+            root,
+            removal_policy: RemovalPolicy::default(),
+            full_branches_only: false,
+        }
+    }
+    /// Converts the tree into its [nested enum representation], cloning the payloads of all nodes.
+    ///
+    /// [nested enum representation]: ../repr/index.html " "
+    pub fn to_repr(&self) -> BinaryTreeRepr<B, L>
+    where
+        B: Clone,
+        L: Clone,
+    {
+        node_to_repr(&self.storage, &self.root)
+    }
+}
+
+impl<B, L, K, S> BinaryTree<B, L, K, S>
+where
+    S: NonShiftingStorage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Replaces the subtree of the node with the specified key with the one described by the [nested enum representation], returning the key of the root of the new subtree, or `None` if the key does not point to a node in the tree. The new subtree takes the place of the old one as the left or right child of its parent, and replacing the subtree of the root node replaces the whole tree.
+    ///
+    /// The nodes of the old subtree are removed one by one, which invalidates keys to them. Since the keys of the other nodes have to stay valid in the meantime, the method is only available for trees backed by a [`NonShiftingStorage`].
+    ///
+    /// # Panics
+    /// Will panic if the tree [only allows full branch nodes] and the replacement has a partial branch node. The tree is left unchanged in such a case.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{binary_tree::BinaryTree, repr::BinaryTreeRepr};
+    ///
+    /// let mut tree = BinaryTree::<_>::new(0);
+    /// tree.root_mut().make_branch(1, Some(2)).unwrap();
+    /// let key = tree.root().right_child().unwrap().into_raw_key();
+    ///
+    /// tree.replace_subtree(key, BinaryTreeRepr::Branch(
+    ///     20,
+    ///     Box::new(BinaryTreeRepr::Leaf(21)),
+    ///     None,
+    /// ));
+    /// let right_child = tree.root().right_child().unwrap();
+    /// assert_eq!(right_child.value().into_inner(), &20);
+    /// assert_eq!(right_child.left_child().unwrap().value().into_inner(), &21);
+    /// ```
+    /// Trees backed by storages which shift keys upon removal, such as `Vec`, cannot replace subtrees:
+    /// ```rust,compile_fail
+    /// use charcoal::{binary_tree::{BinaryTree, Node}, repr::BinaryTreeRepr};
+    ///
+    /// let mut tree = BinaryTree::<_, _, usize, Vec<Node<_, _, usize>>>::new(0);
+    /// tree.root_mut().make_branch(1, Some(2)).unwrap();
+    /// let key = tree.root().left_child().unwrap().into_raw_key();
+    /// tree.replace_subtree(key, BinaryTreeRepr::Leaf(10));
+    /// ```
+    ///
+    /// [nested enum representation]: ../repr/index.html " "
+    /// [only allows full branch nodes]: #method.enforce_full_branches " "
+    /// [`NonShiftingStorage`]: ../trait.NonShiftingStorage.html " "
+    #[allow(clippy::needless_pass_by_value)] // Keys are taken by value everywhere else
+    pub fn replace_subtree(&mut self, key: K, replacement: BinaryTreeRepr<B, L>) -> Option<K> {
+        let parent = self.storage.get(&key)?.parent.clone();
+        // The new nodes are added first, so that the tree stays intact if building them panics.
+        let new_key = add_repr(&mut self.storage, replacement);
+        if self.full_branches_only && first_partial_branch(&self.storage, &new_key).is_some() {
+            remove_subtree(&mut self.storage, &new_key);
+            panic!("the tree only allows full branch nodes, but the replacement has a partial one");
+        }
+        remove_subtree(&mut self.storage, &key);
+        match &parent {
+            Some(parent) => {
+                if let NodeData::Branch {
+                    left_child,
+                    right_child,
+                    ..
+                } = &mut unsafe {
+                    // SAFETY: parent keys are always valid
+                    self.storage.get_unchecked_mut(parent)
+                }
+                .value
+                {
+                    if *left_child == key {
+                        *left_child = new_key.clone();
+                    } else {
+                        *right_child = Some(new_key.clone());
+                    }
+                }
+            }
+            None => self.root = new_key.clone(),
+        }
+        unsafe {
+            // SAFETY: we just added it
+            self.storage.get_unchecked_mut(&new_key)
+        }
+        .parent = parent;
+        Some(new_key)
+    }
+}
+
+/// Removes the node and all of its descendants from the storage, in post-order. The link of the parent to the node is left for the caller to fix.
+fn remove_subtree<B, L, K, S>(storage: &mut S, subtree_root: &K)
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let leftmost_leaf_of = |storage: &S, mut key: K| {
+        while let NodeData::Branch { left_child, .. } = &unsafe {
+            // SAFETY: keys are only taken from the links between the nodes
+            storage.get_unchecked(&key)
+        }
+        .value
+        {
+            key = left_child.clone();
+        }
+        key
+    };
+    let mut key = leftmost_leaf_of(storage, subtree_root.clone());
+    loop {
+        let node = storage.remove(&key);
+        if &key == subtree_root {
+            break;
+        }
+        let parent_key = node.parent.unwrap_or_else(|| unsafe {
+            unreachable_debugchecked("the walk never goes above the subtree root")
+        });
+        // A left child is followed by the subtree of its right sibling, if there is one, and a
+        // right child by its parent.
+        let right_sibling = match &unsafe {
+            // SAFETY: parents are removed after their children
+            storage.get_unchecked(&parent_key)
+        }
+        .value
+        {
+            NodeData::Branch {
+                left_child,
+                right_child: Some(right_child),
+                ..
+            } if *left_child == key => Some(right_child.clone()),
+            _ => None,
+        };
+        key = right_sibling.map_or(parent_key, |right_sibling| {
+            leftmost_leaf_of(storage, right_sibling)
+        });
+    }
+}
+
+/// Adds the node and all of its descendants to the storage, returning the key of the node. The parent link of the node itself is left for the caller to fill in.
+fn add_repr<B, L, K, S>(storage: &mut S, repr: BinaryTreeRepr<B, L>) -> K
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let (payload, left_child, right_child) = match repr {
+        BinaryTreeRepr::Leaf(payload) => {
+            return storage.add(unsafe {
+                // SAFETY: the parent link is filled in by the caller
+                Node::leaf(payload, None)
+            });
+        }
+        BinaryTreeRepr::Branch(payload, left_child, right_child) => {
+            (payload, left_child, right_child)
+        }
+    };
+    // The children are added first, since the branch node needs to know their keys
+    let left_child = add_repr(storage, *left_child);
+    let right_child = right_child.map(|right_child| add_repr(storage, *right_child));
+    let key = storage.add(Node {
+        value: NodeData::Branch {
+            payload,
+            left_child: left_child.clone(),
+            right_child: right_child.clone(),
+        },
+        parent: None,
+    });
+    for child_key in iter::once(&left_child).chain(right_child.as_ref()) {
+        unsafe {
+            // SAFETY: we just added both of those
+            storage.get_unchecked_mut(child_key)
+        }
+        .parent = Some(key.clone());
+    }
+    key
+}
+/// Converts the node and all of its descendants into the nested enum representation.
+///
+/// The children of every node are converted before the node itself, since the representation of a branch node owns the ones of its children. Instead of recursing, the branch nodes which are still missing some of their children are kept on an explicit stack.
+fn node_to_repr<B, L, K, S>(storage: &S, key: &K) -> BinaryTreeRepr<B, L>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+    B: Clone,
+    L: Clone,
+{
+    // The key and payload of every unfinished branch node and the representation of its left child
+    // once it's converted.
+    let mut stack = Vec::new();
+    let mut key = key.clone();
+    loop {
+        let node = unsafe {
+            // SAFETY: keys stored in the tree are guaranteed to be valid
+            storage.get_unchecked(&key)
+        };
+        let mut repr = match &node.value {
+            NodeData::Branch {
+                payload,
+                left_child,
+                ..
+            } => {
+                stack.push((key.clone(), payload.clone(), None));
+                key = left_child.clone();
+                continue;
+            }
+            NodeData::Leaf(payload) => BinaryTreeRepr::Leaf(payload.clone()),
+        };
+        // Hand the converted node to its parent, converting the parent as well if that was its
+        // last child, until a parent with a right child left to convert is found.
+        loop {
+            let (parent_key, _, converted_left_child) = match stack.last_mut() {
+                Some(parent) => parent,
+                None => return repr,
+            };
+            if converted_left_child.is_none() {
+                let right_child = match &unsafe {
+                    // SAFETY: as above
+                    storage.get_unchecked(parent_key)
+                }
+                .value
+                {
+                    NodeData::Branch { right_child, .. } => right_child.clone(),
+                    NodeData::Leaf(..) => unsafe {
+                        unreachable_debugchecked("only branch nodes are put on the stack")
+                    },
+                };
+                if let Some(right_child) = right_child {
+                    *converted_left_child = Some(Box::new(repr));
+                    key = right_child;
+                    break;
+                }
+            }
+            let (_, payload, converted_left_child) =
+                stack.pop().expect("the stack was checked to be non-empty");
+            repr = match converted_left_child {
+                Some(left_child) => {
+                    BinaryTreeRepr::Branch(payload, left_child, Some(Box::new(repr)))
+                }
+                None => BinaryTreeRepr::Branch(payload, Box::new(repr), None),
+            };
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! Burkhard-Keller trees, which index values by a metric for finding all values within a certain distance of a query, such as all words within a few typos of a misspelled one.
+//!
+//! A [`BkTree`] stores its values in a [`FreeformTree`], with every child labelled by its distance to its parent, which is unique among its siblings. When searching, the triangle inequality lets whole subtrees be skipped: if the query is at distance `d` from a node, only the children with labels between `d - max_distance` and `d + max_distance` can contain matches. The metric is any closure taking two values and returning a `usize` distance, as long as it satisfies the properties of a [metric]: the distance between two values is zero only if they are equal, it's the same in both directions and it satisfies the triangle inequality. If it doesn't, searches may miss some of the matches.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::bk_tree::BkTree;
+//!
+//! // The Hamming distance between words of the same length.
+//! let mut tree = BkTree::<_, _>::new(|a: &&str, b: &&str| {
+//!     a.chars().zip(b.chars()).filter(|(a, b)| a != b).count()
+//! });
+//! for word in &["book", "back", "boon", "cook", "cake", "cape"] {
+//!     tree.insert(*word);
+//! }
+//! assert_eq!(tree.len(), 6);
+//!
+//! let mut matches: Vec<_> = tree.find_within(&"bool", 1).collect();
+//! matches.sort();
+//! assert_eq!(matches, [(&"book", 1), (&"boon", 1)]);
+//! ```
+//!
+//! [`BkTree`]: struct.BkTree.html " "
+//! [`FreeformTree`]: ../freeform_tree/struct.FreeformTree.html " "
+//! [metric]: https://en.wikipedia.org/wiki/Metric_(mathematics) " "
+
+use core::{
+    fmt::{self, Formatter, Debug},
+    iter,
+};
+use alloc::vec::Vec;
+use crate::{
+    freeform_tree::{FreeformTree, Node, NodeRef, NodeRefMut},
+    storage::{Storage, DefaultStorage},
+};
+
+/// A value stored in a [`BkTree`], along with its distance to the value stored in its parent node.
+///
+/// [`BkTree`]: struct.BkTree.html " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Entry<T> {
+    /// The distance between the value and the value of the parent node, or zero for the root node.
+    pub distance: usize,
+    /// The value itself.
+    pub value: T,
+}
+
+/// A Burkhard-Keller tree with values of type `T` and a metric of type `M`.
+///
+/// See the [module-level documentation] for more.
+///
+/// [module-level documentation]: index.html " "
+pub struct BkTree<T, M, K = usize, S = DefaultStorage<Node<Entry<T>, Entry<T>, K>>>
+where
+    S: Storage<Element = Node<Entry<T>, Entry<T>, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    tree: Option<FreeformTree<Entry<T>, Entry<T>, K, S>>,
+    metric: M,
+    len: usize,
+}
+impl<T, M, K, S> BkTree<T, M, K, S>
+where
+    M: Fn(&T, &T) -> usize,
+    S: Storage<Element = Node<Entry<T>, Entry<T>, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Creates an empty tree which uses the specified metric to compute the distances between values.
+    pub fn new(metric: M) -> Self {
+        Self {
+            tree: None,
+            metric,
+            len: 0,
+        }
+    }
+    /// Returns the number of values in the tree.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Returns `true` if the tree contains no values, `false` otherwise.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Returns the underlying freeform tree, or `None` if no values have been inserted yet.
+    pub fn as_tree(&self) -> Option<&FreeformTree<Entry<T>, Entry<T>, K, S>> {
+        self.tree.as_ref()
+    }
+    /// Adds a value to the tree, returning `true` if it was added or `false` if the tree already contains a value at distance zero from it, in which case the value is dropped.
+    ///
+    /// # Panics
+    /// Will panic if the storage has a fixed capacity and is full.
+    pub fn insert(&mut self, value: T) -> bool {
+        let tree = if let Some(tree) = &mut self.tree {
+            tree
+        } else {
+            self.tree = Some(FreeformTree::new(Entry { distance: 0, value }));
+            self.len = 1;
+            return true;
+        };
+        let mut key = tree.root().into_raw_key();
+        loop {
+            let node = NodeRef::new_raw(tree, key).expect("the keys of children are always valid");
+            let distance = (self.metric)(&value, &node.value().into_inner().value);
+            if distance == 0 {
+                return false;
+            }
+            let child = node.children().and_then(|mut children| {
+                children.find(|child| child.value().into_inner().distance == distance)
+            });
+            if let Some(child) = child {
+                key = child.into_raw_key();
+            } else {
+                let parent_key = node.into_raw_key();
+                push_child(tree, parent_key, Entry { distance, value });
+                self.len += 1;
+                return true;
+            }
+        }
+    }
+    /// Returns an iterator over all values within the specified distance of the query, along with their distances to it, in no particular order.
+    pub fn find_within<'a>(&'a self, query: &'a T, max_distance: usize) -> FindWithin<'a, T, M, K, S> {
+        let stack = self
+            .tree
+            .as_ref()
+            .map(|tree| tree.root().into_raw_key())
+            .into_iter()
+            .collect();
+        FindWithin {
+            tree: self.tree.as_ref(),
+            metric: &self.metric,
+            query,
+            max_distance,
+            stack,
+        }
+    }
+    /// Returns the value closest to the query along with its distance to it, or `None` if the tree is empty.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::bk_tree::BkTree;
+    ///
+    /// let mut tree = BkTree::<_, _>::new(|a: &i32, b: &i32| (a - b).abs() as usize);
+    /// tree.insert(10);
+    /// tree.insert(20);
+    /// tree.insert(35);
+    /// assert_eq!(tree.find_closest(&31), Some((&35, 4)));
+    /// ```
+    #[allow(clippy::missing_panics_doc)] // The keys are always taken from the tree itself
+    pub fn find_closest(&self, query: &T) -> Option<(&T, usize)> {
+        let tree = self.tree.as_ref()?;
+        let mut best: Option<(K, usize)> = None;
+        let mut stack = iter::once(tree.root().into_raw_key()).collect::<Vec<_>>();
+        while let Some(key) = stack.pop() {
+            let node = NodeRef::new_raw(tree, key).expect("the keys of children are always valid");
+            let distance = (self.metric)(query, &node.value().into_inner().value);
+            if best.as_ref().map_or(true, |(_, best)| distance < *best) {
+                best = Some((node.raw_key().clone(), distance));
+            }
+            // Nothing further than the best match found so far can improve on it.
+            let radius = best.as_ref().map_or(usize::MAX, |(_, best)| *best);
+            stack.extend(node.children().into_iter().flatten().filter_map(|child| {
+                let label = child.value().into_inner().distance;
+                let difference = if label > distance {
+                    label - distance
+                } else {
+                    distance - label
+                };
+                if difference < radius {
+                    Some(child.into_raw_key())
+                } else {
+                    None
+                }
+            }));
+        }
+        best.map(|(key, distance)| {
+            let node = NodeRef::new_raw(tree, key).expect("the key was taken from the tree");
+            (&node.value().into_inner().value, distance)
+        })
+    }
+}
+#[allow(clippy::missing_fields_in_debug)] // Closures can't be debug-printed
+impl<T: Debug, M, K, S> Debug for BkTree<T, M, K, S>
+where
+    S: Storage<Element = Node<Entry<T>, Entry<T>, K>, Key = K> + Debug,
+    K: Clone + Debug + Eq,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BkTree")
+            .field("tree", &self.tree)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+/// Adds a child to the node with the specified key, turning it into a branch node if it was a leaf.
+fn push_child<T, K, S>(tree: &mut FreeformTree<Entry<T>, Entry<T>, K, S>, key: K, child: Entry<T>)
+where
+    S: Storage<Element = Node<Entry<T>, Entry<T>, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let mut node = NodeRefMut::new_raw(tree, key).expect("the parent key is always valid");
+    if node.is_leaf() {
+        node.make_branch(iter::once(child))
+            .unwrap_or_else(|_| unreachable!("the node was checked to be a leaf"));
+    } else {
+        node.try_push_back(child)
+            .unwrap_or_else(|_| unreachable!("the node was checked to be a branch"));
+    }
+}
+
+/// An iterator over the values within a certain distance of a query, created by [`BkTree::find_within`].
+///
+/// [`BkTree::find_within`]: struct.BkTree.html#method.find_within " "
+pub struct FindWithin<'a, T, M, K, S>
+where
+    S: Storage<Element = Node<Entry<T>, Entry<T>, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    tree: Option<&'a FreeformTree<Entry<T>, Entry<T>, K, S>>,
+    metric: &'a M,
+    query: &'a T,
+    max_distance: usize,
+    /// The nodes which still have to be visited.
+    stack: Vec<K>,
+}
+impl<'a, T, M, K, S> Iterator for FindWithin<'a, T, M, K, S>
+where
+    M: Fn(&T, &T) -> usize,
+    S: Storage<Element = Node<Entry<T>, Entry<T>, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    type Item = (&'a T, usize);
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = self.tree?;
+        while let Some(key) = self.stack.pop() {
+            let node = NodeRef::new_raw(tree, key).expect("the keys of children are always valid");
+            let value = &node.value().into_inner().value;
+            let distance = (self.metric)(self.query, value);
+            let min_label = distance.saturating_sub(self.max_distance);
+            let max_label = distance.saturating_add(self.max_distance);
+            self.stack
+                .extend(node.children().into_iter().flatten().filter_map(|child| {
+                    let label = child.value().into_inner().distance;
+                    if min_label <= label && label <= max_label {
+                        Some(child.into_raw_key())
+                    } else {
+                        None
+                    }
+                }));
+            if distance <= self.max_distance {
+                return Some((value, distance));
+            }
+        }
+        None
+    }
+}
+#[allow(clippy::missing_fields_in_debug)] // Closures can't be debug-printed
+impl<T, M, K, S> Debug for FindWithin<'_, T, M, K, S>
+where
+    T: Debug,
+    S: Storage<Element = Node<Entry<T>, Entry<T>, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FindWithin")
+            .field("query", self.query)
+            .field("max_distance", &self.max_distance)
+            .field("stack", &self.stack)
+            .finish()
+    }
+}
@@ -0,0 +1,895 @@
+//! A compact binary format for storing trees, for fast persistence of large trees without the overhead of serde.
+//!
+//! # Format
+//! An encoded tree consists of:
+//! - the version of the format, as a single byte, which is currently [`FORMAT_VERSION`]
+//! - the fingerprint of the payload types of the tree, as a little-endian `u64`, calculated by [combining] the [fingerprints] of the branch and leaf payload types, in that order
+//! - the nodes of the tree
+//! - the [CRC-32] checksum of everything before it, as a little-endian `u32`
+//!
+//! The nodes are stored one after another in depth-first pre-order, starting with the root node. Every node consists of:
+//! - the number of children of the node, as a [varint], which is 0 for leaf nodes
+//! - the length of the encoded payload of the node in bytes, as a varint
+//! - the payload of the node, encoded with its [`BinaryPayload`] implementation
+//!
+//! The children of a node immediately follow it, so the structure of the tree is fully described by the numbers of children, and no keys are stored. Varints are encoded in the [LEB128] format: 7 bits per byte, least significant group first, with the most significant bit of every byte except for the last one set.
+//!
+//! The version, fingerprint and checksum are checked before the decoded tree is returned, so decoding data from a newer version of the format, a tree with different payload types or a corrupted file fails with a [`DecodeError`] instead of producing a broken tree.
+//!
+//! Trees are encoded with [`Tree::to_bytes`], [`Tree::write_to_slice`] and, with the `std` feature, [`Tree::write_to`], all of which are available for all tree types. They are decoded with the `from_bytes` and `read_from` associated functions of the concrete tree types. Decoding into a binary tree, quadtree or octree fails if one of the nodes has a number of children which that type of tree does not allow.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{FreeformTree, Tree, codec::{self, DecodeError}};
+//!
+//! let mut tree = FreeformTree::<_>::new(String::from("Root"));
+//! tree.root_mut().make_branch(vec![String::from("Left"), String::from("Right")]).unwrap();
+//!
+//! let mut bytes = tree.to_bytes();
+//! assert_eq!(bytes[0], codec::FORMAT_VERSION);
+//! assert_eq!(bytes[9..16], [2, 5, 4, b'R', b'o', b'o', b't']);
+//!
+//! let decoded = FreeformTree::<String>::from_bytes(&bytes).unwrap();
+//! assert_eq!(decoded.to_repr(), tree.to_repr());
+//!
+//! // Trees with other payload types are rejected...
+//! assert!(matches!(
+//!     FreeformTree::<Vec<u8>>::from_bytes(&bytes),
+//!     Err(DecodeError::FingerprintMismatch { .. }),
+//! ));
+//! // ...and so are corrupted ones.
+//! bytes[12] = b'B';
+//! assert_eq!(
+//!     FreeformTree::<String>::from_bytes(&bytes).unwrap_err(),
+//!     DecodeError::ChecksumMismatch,
+//! );
+//! ```
+//!
+//! [`FORMAT_VERSION`]: constant.FORMAT_VERSION.html " "
+//! [combining]: fn.combine_fingerprints.html " "
+//! [fingerprints]: trait.BinaryPayload.html#associatedconstant.FINGERPRINT " "
+//! [CRC-32]: https://en.wikipedia.org/wiki/Cyclic_redundancy_check " "
+//! [varint]: fn.write_varint.html " "
+//! [`BinaryPayload`]: trait.BinaryPayload.html " "
+//! [LEB128]: https://en.wikipedia.org/wiki/LEB128 " "
+//! [`DecodeError`]: enum.DecodeError.html " "
+//! [`Tree::to_bytes`]: ../tree/trait.Tree.html#method.to_bytes " "
+//! [`Tree::write_to_slice`]: ../tree/trait.Tree.html#method.write_to_slice " "
+//! [`Tree::write_to`]: ../tree/trait.Tree.html#method.write_to " "
+
+use core::{
+    convert::TryFrom,
+    fmt::{self, Formatter, Debug, Display},
+    mem,
+};
+use alloc::{string::String, vec::Vec};
+use crate::{tree::Tree, NodeValue};
+#[cfg(any(feature = "binary_tree", feature = "octree", feature = "quadtree"))]
+use alloc::boxed::Box;
+#[cfg(any(
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+use crate::storage::Storage;
+#[cfg(feature = "freeform_tree")]
+use crate::repr::TreeRepr;
+#[cfg(feature = "binary_tree")]
+use crate::repr::BinaryTreeRepr;
+#[cfg(feature = "quadtree")]
+use crate::repr::QuadtreeRepr;
+#[cfg(feature = "octree")]
+use crate::repr::OctreeRepr;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(all(
+    feature = "std",
+    any(
+        feature = "binary_tree",
+        feature = "octree",
+        feature = "quadtree",
+        feature = "freeform_tree",
+    ),
+))]
+use std::io::Read;
+
+/// Payloads which can be stored in the [binary format].
+///
+/// The trait is implemented for integers, floating-point numbers, `bool`, `char`, `()`, `String`, as well as `Vec` and `Option` of other payloads. Integers other than `u8` and `i8` are encoded as [varints], with signed integers using [zigzag encoding] so that numbers close to zero take up few bytes; floating-point numbers and 128-bit integers are stored as little-endian bytes.
+///
+/// # Example
+/// ```rust
+/// use charcoal::codec::{self, BinaryPayload, DecodeError};
+///
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+/// impl BinaryPayload for Point {
+///     const FINGERPRINT: u64 = codec::fingerprint_of("Point");
+///     fn encode(&self, buf: &mut Vec<u8>) {
+///         self.x.encode(buf);
+///         self.y.encode(buf);
+///     }
+///     fn decode(bytes: &mut &[u8]) -> Result<Self, DecodeError> {
+///         Ok(Self {
+///             x: i32::decode(bytes)?,
+///             y: i32::decode(bytes)?,
+///         })
+///     }
+/// }
+///
+/// let mut buf = Vec::new();
+/// Point { x: -1, y: 300 }.encode(&mut buf);
+/// assert_eq!(buf, [0b0000_0001, 0b1101_1000, 0b0000_0100]);
+/// let point = Point::decode(&mut &buf[..]).unwrap();
+/// assert_eq!((point.x, point.y), (-1, 300));
+/// ```
+///
+/// [binary format]: index.html " "
+/// [varints]: fn.write_varint.html " "
+/// [zigzag encoding]: https://developers.google.com/protocol-buffers/docs/encoding#signed-ints " "
+pub trait BinaryPayload: Sized {
+    /// A number identifying the type and its encoding, used to reject trees with different payload types when decoding.
+    ///
+    /// Fingerprints are usually created from the name of the type with [`fingerprint_of`], and fingerprints of generic types are [combined] with the fingerprints of their type parameters. If the encoding of a type changes, changing its fingerprint as well, such as by adding a version number to the name, makes sure that trees encoded with the old version are not misinterpreted.
+    ///
+    /// [`fingerprint_of`]: fn.fingerprint_of.html " "
+    /// [combined]: fn.combine_fingerprints.html " "
+    const FINGERPRINT: u64;
+    /// Appends the encoded payload to the buffer.
+    fn encode(&self, buf: &mut Vec<u8>);
+    /// Decodes a payload from the start of the byte slice, advancing the slice past the bytes which were used.
+    ///
+    /// # Errors
+    /// Should fail with [`DecodeError::UnexpectedEnd`] if the slice ends in the middle of the payload and with [`DecodeError::InvalidPayload`] if the bytes do not describe a valid payload.
+    ///
+    /// [`DecodeError::UnexpectedEnd`]: enum.DecodeError.html#variant.UnexpectedEnd " "
+    /// [`DecodeError::InvalidPayload`]: enum.DecodeError.html#variant.InvalidPayload " "
+    fn decode(bytes: &mut &[u8]) -> Result<Self, DecodeError>;
+}
+
+/// The version of the [binary format] written by this version of the crate. Trees encoded with other versions of the format are rejected when decoding.
+///
+/// [binary format]: index.html " "
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Calculates a [payload fingerprint] from a string, usually the name of the type, using the 64-bit [FNV-1a] hash function.
+///
+/// [payload fingerprint]: trait.BinaryPayload.html#associatedconstant.FINGERPRINT " "
+/// [FNV-1a]: https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function " "
+#[must_use]
+pub const fn fingerprint_of(name: &str) -> u64 {
+    let bytes = name.as_bytes();
+    let mut hash = 0xCBF2_9CE4_8422_2325_u64;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+        i += 1;
+    }
+    hash
+}
+/// Combines two [payload fingerprints] into one, such as the fingerprint of a generic type with the fingerprint of its type parameter.
+///
+/// The order of the fingerprints matters, i.e. `combine_fingerprints(a, b)` is usually different from `combine_fingerprints(b, a)`.
+///
+/// [payload fingerprints]: trait.BinaryPayload.html#associatedconstant.FINGERPRINT " "
+#[must_use]
+pub const fn combine_fingerprints(first: u64, second: u64) -> u64 {
+    (first ^ second.rotate_left(29)).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+fn tree_fingerprint<B: BinaryPayload, L: BinaryPayload>() -> u64 {
+    combine_fingerprints(B::FINGERPRINT, L::FINGERPRINT)
+}
+
+/// The lookup table for the CRC-32 (IEEE 802.3) checksum, with one entry for every byte value.
+const CRC_TABLE: [u32; 256] = crc_table();
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0_u32;
+    while i < 256 {
+        let mut crc = i;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xEDB8_8320
+            };
+            bit += 1;
+        }
+        table[i as usize] = crc;
+        i += 1;
+    }
+    table
+}
+/// A running CRC-32 checksum.
+struct Crc32(u32);
+impl Crc32 {
+    const fn new() -> Self {
+        Self(0xFFFF_FFFF)
+    }
+    #[allow(clippy::cast_possible_truncation)] // Only the lowest byte is needed
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = CRC_TABLE[usize::from(self.0 as u8 ^ byte)] ^ (self.0 >> 8);
+        }
+    }
+    const fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+/// Appends an unsigned integer to the buffer as a [LEB128] varint, which takes up 1 byte for numbers below 128 and at most 10 bytes overall.
+///
+/// [LEB128]: https://en.wikipedia.org/wiki/LEB128 " "
+pub fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        #[allow(clippy::cast_possible_truncation)] // Intentional
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    #[allow(clippy::cast_possible_truncation)] // The value is below 0x80 at this point
+    buf.push(value as u8);
+}
+/// Reads an unsigned integer written with [`write_varint`] from the start of the byte slice, advancing the slice past it.
+///
+/// # Errors
+/// Will fail if the slice ends in the middle of the varint or if it does not fit into a `u64`.
+///
+/// [`write_varint`]: fn.write_varint.html " "
+pub fn read_varint(bytes: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first().ok_or(DecodeError::UnexpectedEnd)?;
+        *bytes = rest;
+        let group = u64::from(byte & 0x7F);
+        if group << shift >> shift != group {
+            return Err(DecodeError::InvalidVarint);
+        }
+        value |= group << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(DecodeError::InvalidVarint)
+}
+/// Takes the specified number of bytes from the start of the slice.
+fn take_bytes<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], DecodeError> {
+    if bytes.len() < len {
+        return Err(DecodeError::UnexpectedEnd);
+    }
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(taken)
+}
+fn read_len(bytes: &mut &[u8]) -> Result<usize, DecodeError> {
+    usize::try_from(read_varint(bytes)?).map_err(|_| DecodeError::InvalidVarint)
+}
+
+macro_rules! impl_varint {
+    ($($ty:ty),+ $(,)?) => {$(
+        impl BinaryPayload for $ty {
+            const FINGERPRINT: u64 = fingerprint_of(stringify!($ty));
+            #[allow(clippy::cast_lossless)] // usize does not implement Into<u64>
+            fn encode(&self, buf: &mut Vec<u8>) {
+                write_varint(buf, *self as u64);
+            }
+            fn decode(bytes: &mut &[u8]) -> Result<Self, DecodeError> {
+                <$ty>::try_from(read_varint(bytes)?).map_err(|_| DecodeError::InvalidPayload)
+            }
+        }
+    )+};
+}
+impl_varint!(u16, u32, u64, usize);
+macro_rules! impl_zigzag {
+    ($($ty:ty),+ $(,)?) => {$(
+        impl BinaryPayload for $ty {
+            const FINGERPRINT: u64 = fingerprint_of(stringify!($ty));
+            #[allow(clippy::cast_lossless, clippy::cast_sign_loss)] // isize does not implement Into<i64>
+            fn encode(&self, buf: &mut Vec<u8>) {
+                let value = *self as i64;
+                write_varint(buf, ((value << 1) ^ (value >> 63)) as u64);
+            }
+            #[allow(clippy::cast_possible_wrap)] // Intentional
+            fn decode(bytes: &mut &[u8]) -> Result<Self, DecodeError> {
+                let value = read_varint(bytes)?;
+                let value = (value >> 1) as i64 ^ -((value & 1) as i64);
+                <$ty>::try_from(value).map_err(|_| DecodeError::InvalidPayload)
+            }
+        }
+    )+};
+}
+impl_zigzag!(i16, i32, i64, isize);
+macro_rules! impl_le_bytes {
+    ($($ty:ty),+ $(,)?) => {$(
+        impl BinaryPayload for $ty {
+            const FINGERPRINT: u64 = fingerprint_of(stringify!($ty));
+            fn encode(&self, buf: &mut Vec<u8>) {
+                buf.extend_from_slice(&self.to_le_bytes());
+            }
+            fn decode(bytes: &mut &[u8]) -> Result<Self, DecodeError> {
+                let mut array = [0; mem::size_of::<$ty>()];
+                array.copy_from_slice(take_bytes(bytes, mem::size_of::<$ty>())?);
+                Ok(<$ty>::from_le_bytes(array))
+            }
+        }
+    )+};
+}
+impl_le_bytes!(u8, i8, u128, i128, f32, f64);
+
+impl BinaryPayload for bool {
+    const FINGERPRINT: u64 = fingerprint_of("bool");
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(u8::from(*self));
+    }
+    fn decode(bytes: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(bytes)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::InvalidPayload),
+        }
+    }
+}
+impl BinaryPayload for char {
+    const FINGERPRINT: u64 = fingerprint_of("char");
+    fn encode(&self, buf: &mut Vec<u8>) {
+        u32::from(*self).encode(buf);
+    }
+    fn decode(bytes: &mut &[u8]) -> Result<Self, DecodeError> {
+        char::try_from(u32::decode(bytes)?).map_err(|_| DecodeError::InvalidPayload)
+    }
+}
+impl BinaryPayload for () {
+    const FINGERPRINT: u64 = fingerprint_of("()");
+    fn encode(&self, _buf: &mut Vec<u8>) {}
+    fn decode(_bytes: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(())
+    }
+}
+impl BinaryPayload for String {
+    const FINGERPRINT: u64 = fingerprint_of("String");
+    fn encode(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, self.len() as u64);
+        buf.extend_from_slice(self.as_bytes());
+    }
+    fn decode(bytes: &mut &[u8]) -> Result<Self, DecodeError> {
+        let len = read_len(bytes)?;
+        let string = take_bytes(bytes, len)?;
+        String::from_utf8(string.to_vec()).map_err(|_| DecodeError::InvalidPayload)
+    }
+}
+impl<T: BinaryPayload> BinaryPayload for Vec<T> {
+    const FINGERPRINT: u64 = combine_fingerprints(fingerprint_of("Vec"), T::FINGERPRINT);
+    fn encode(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, self.len() as u64);
+        for element in self {
+            element.encode(buf);
+        }
+    }
+    fn decode(bytes: &mut &[u8]) -> Result<Self, DecodeError> {
+        let len = read_len(bytes)?;
+        // Every element takes up at least one byte, except for zero-sized ones, so this keeps a
+        // corrupted length from allocating more memory than the input itself takes up.
+        let mut vec = Vec::with_capacity(len.min(bytes.len()));
+        for _ in 0..len {
+            vec.push(T::decode(bytes)?);
+        }
+        Ok(vec)
+    }
+}
+impl<T: BinaryPayload> BinaryPayload for Option<T> {
+    const FINGERPRINT: u64 = combine_fingerprints(fingerprint_of("Option"), T::FINGERPRINT);
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Some(value) => {
+                buf.push(1);
+                value.encode(buf);
+            }
+            None => buf.push(0),
+        }
+    }
+    fn decode(bytes: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(bytes)? {
+            0 => Ok(None),
+            1 => T::decode(bytes).map(Some),
+            _ => Err(DecodeError::InvalidPayload),
+        }
+    }
+}
+
+/// Encodes the tree as described in the module-level documentation, passing the bytes to the specified closure piece by piece.
+pub(crate) fn encode_tree<T, E>(
+    tree: &T,
+    mut put: impl FnMut(&[u8]) -> Result<(), E>,
+) -> Result<(), E>
+where
+    T: Tree,
+    T::Branch: BinaryPayload,
+    T::Leaf: BinaryPayload,
+{
+    let mut crc = Crc32::new();
+    let mut put_checked = |bytes: &[u8]| {
+        crc.update(bytes);
+        put(bytes)
+    };
+    put_checked(&[FORMAT_VERSION])?;
+    put_checked(&tree_fingerprint::<T::Branch, T::Leaf>().to_le_bytes())?;
+    let mut header = Vec::new();
+    let mut payload = Vec::new();
+    let mut stack = alloc::vec![tree.root_key()];
+    while let Some(key) = stack.pop() {
+        let children = tree.children_keys(&key).collect::<Vec<_>>();
+        payload.clear();
+        match tree.node_value(&key).expect("keys are taken from the tree") {
+            NodeValue::Branch(value) => value.encode(&mut payload),
+            NodeValue::Leaf(value) => value.encode(&mut payload),
+        }
+        header.clear();
+        write_varint(&mut header, children.len() as u64);
+        write_varint(&mut header, payload.len() as u64);
+        put_checked(&header)?;
+        put_checked(&payload)?;
+        // Pushed in reverse so that the first child is encoded first.
+        stack.extend(children.into_iter().rev());
+    }
+    let checksum = crc.finish();
+    put(&checksum.to_le_bytes())
+}
+
+#[cfg(any(
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+/// A source of encoded trees, used to share the decoding logic between byte slices and readers.
+pub(crate) trait Source {
+    type Error: From<DecodeError>;
+    fn read_byte(&mut self) -> Result<u8, Self::Error>;
+    /// Replaces the contents of the buffer with the specified number of bytes.
+    fn read_bytes(&mut self, len: u64, buf: &mut Vec<u8>) -> Result<(), Self::Error>;
+}
+#[cfg(any(
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+impl Source for &[u8] {
+    type Error = DecodeError;
+    fn read_byte(&mut self) -> Result<u8, DecodeError> {
+        u8::decode(self)
+    }
+    fn read_bytes(&mut self, len: u64, buf: &mut Vec<u8>) -> Result<(), DecodeError> {
+        let len = usize::try_from(len).map_err(|_| DecodeError::UnexpectedEnd)?;
+        buf.clear();
+        buf.extend_from_slice(take_bytes(self, len)?);
+        Ok(())
+    }
+}
+#[cfg(all(
+    feature = "std",
+    any(
+        feature = "binary_tree",
+        feature = "octree",
+        feature = "quadtree",
+        feature = "freeform_tree",
+    ),
+))]
+pub(crate) struct ReaderSource<R>(pub(crate) R);
+#[cfg(all(
+    feature = "std",
+    any(
+        feature = "binary_tree",
+        feature = "octree",
+        feature = "quadtree",
+        feature = "freeform_tree",
+    ),
+))]
+impl<R: Read> Source for ReaderSource<R> {
+    type Error = ReadError;
+    fn read_byte(&mut self) -> Result<u8, ReadError> {
+        let mut byte = [0];
+        self.0.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+    fn read_bytes(&mut self, len: u64, buf: &mut Vec<u8>) -> Result<(), ReadError> {
+        buf.clear();
+        // Reading through Take instead of allocating the whole length upfront keeps a corrupted
+        // length from allocating more memory than the input itself takes up.
+        let num_read = (&mut self.0).take(len).read_to_end(buf)?;
+        if (num_read as u64) < len {
+            return Err(DecodeError::UnexpectedEnd.into());
+        }
+        Ok(())
+    }
+}
+#[cfg(any(
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+/// Wraps a source to calculate the checksum of everything read from it.
+struct Checksummed<'a, Src> {
+    source: &'a mut Src,
+    crc: Crc32,
+}
+#[cfg(any(
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+impl<Src: Source> Checksummed<'_, Src> {
+    fn read_byte(&mut self) -> Result<u8, Src::Error> {
+        let byte = self.source.read_byte()?;
+        self.crc.update(&[byte]);
+        Ok(byte)
+    }
+    fn read_bytes(&mut self, len: u64, buf: &mut Vec<u8>) -> Result<(), Src::Error> {
+        self.source.read_bytes(len, buf)?;
+        self.crc.update(buf);
+        Ok(())
+    }
+    fn read_varint(&mut self) -> Result<u64, Src::Error> {
+        // A varint is never longer than 10 bytes, so the bytes are read one by one until the last
+        // one and decoded afterwards.
+        let mut bytes = [0; 10];
+        for i in 0..bytes.len() {
+            bytes[i] = self.read_byte()?;
+            if bytes[i] & 0x80 == 0 {
+                return Ok(read_varint(&mut &bytes[..=i])?);
+            }
+        }
+        Err(DecodeError::InvalidVarint.into())
+    }
+    fn read_u64(&mut self, buf: &mut Vec<u8>) -> Result<u64, Src::Error> {
+        let mut array = [0; 8];
+        self.read_bytes(8, buf)?;
+        array.copy_from_slice(buf);
+        Ok(u64::from_le_bytes(array))
+    }
+    fn read_u32(&mut self, buf: &mut Vec<u8>) -> Result<u32, Src::Error> {
+        let mut array = [0; 4];
+        self.read_bytes(4, buf)?;
+        array.copy_from_slice(buf);
+        Ok(u32::from_le_bytes(array))
+    }
+}
+
+#[cfg(any(
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+/// Decodes a payload, requiring all of the bytes to be used.
+fn decode_exact<T: BinaryPayload>(mut bytes: &[u8]) -> Result<T, DecodeError> {
+    let value = T::decode(&mut bytes)?;
+    if bytes.is_empty() {
+        Ok(value)
+    } else {
+        Err(DecodeError::InvalidPayload)
+    }
+}
+
+#[cfg(any(
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+/// Decodes a tree as described in the module-level documentation, building it from the bottom up with a closure which receives the payload and the already built children of every node.
+pub(crate) fn decode_tree<Src, B, L, R>(
+    source: &mut Src,
+    mut make: impl FnMut(NodeValue<B, L>, Vec<R>) -> Result<R, DecodeError>,
+) -> Result<R, Src::Error>
+where
+    Src: Source,
+    B: BinaryPayload,
+    L: BinaryPayload,
+{
+    /// A branch node which is still missing some of its children.
+    struct Frame<B, R> {
+        payload: B,
+        num_missing: usize,
+        children: Vec<R>,
+    }
+    let mut source = Checksummed {
+        source,
+        crc: Crc32::new(),
+    };
+    let mut buf = Vec::new();
+    let version = source.read_byte()?;
+    if version != FORMAT_VERSION {
+        return Err(DecodeError::UnsupportedVersion(version).into());
+    }
+    let fingerprint = source.read_u64(&mut buf)?;
+    let expected = tree_fingerprint::<B, L>();
+    if fingerprint != expected {
+        return Err(DecodeError::FingerprintMismatch {
+            expected,
+            found: fingerprint,
+        }
+        .into());
+    }
+    let mut stack = Vec::<Frame<B, R>>::new();
+    let tree = 'nodes: loop {
+        let num_children =
+            usize::try_from(source.read_varint()?).map_err(|_| DecodeError::InvalidVarint)?;
+        let len = source.read_varint()?;
+        source.read_bytes(len, &mut buf)?;
+        if num_children != 0 {
+            stack.push(Frame {
+                payload: decode_exact(&buf)?,
+                num_missing: num_children,
+                children: Vec::new(),
+            });
+            continue;
+        }
+        let mut node = make(NodeValue::Leaf(decode_exact(&buf)?), Vec::new())?;
+        // Finish every branch node which got its last child.
+        loop {
+            let frame = match stack.last_mut() {
+                Some(frame) => frame,
+                None => break 'nodes node,
+            };
+            frame.children.push(node);
+            frame.num_missing -= 1;
+            if frame.num_missing != 0 {
+                break;
+            }
+            let frame = stack.pop().expect("the frame was just looked at");
+            node = make(NodeValue::Branch(frame.payload), frame.children)?;
+        }
+    };
+    let checksum = source.crc.finish();
+    if source.read_u32(&mut buf)? != checksum {
+        return Err(DecodeError::ChecksumMismatch.into());
+    }
+    Ok(tree)
+}
+
+#[cfg(any(
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+/// Decodes a tree from a byte slice, requiring all of the bytes to be used.
+pub(crate) fn decode_tree_from_slice<B, L, R>(
+    mut bytes: &[u8],
+    make: impl FnMut(NodeValue<B, L>, Vec<R>) -> Result<R, DecodeError>,
+) -> Result<R, DecodeError>
+where
+    B: BinaryPayload,
+    L: BinaryPayload,
+{
+    let tree = decode_tree(&mut bytes, make)?;
+    if bytes.is_empty() {
+        Ok(tree)
+    } else {
+        Err(DecodeError::TrailingData)
+    }
+}
+
+#[allow(clippy::unnecessary_wraps)] // Has to match the other trees
+#[cfg(feature = "freeform_tree")]
+fn make_freeform_tree<B, L>(
+    value: NodeValue<B, L>,
+    children: Vec<TreeRepr<B, L>>,
+) -> Result<TreeRepr<B, L>, DecodeError> {
+    Ok(match value {
+        NodeValue::Branch(payload) => TreeRepr::Branch(payload, children),
+        NodeValue::Leaf(payload) => TreeRepr::Leaf(payload),
+    })
+}
+#[cfg(feature = "binary_tree")]
+fn make_binary_tree<B, L>(
+    value: NodeValue<B, L>,
+    children: Vec<BinaryTreeRepr<B, L>>,
+) -> Result<BinaryTreeRepr<B, L>, DecodeError> {
+    let payload = match value {
+        NodeValue::Branch(payload) => payload,
+        NodeValue::Leaf(payload) => return Ok(BinaryTreeRepr::Leaf(payload)),
+    };
+    let num_children = children.len();
+    let mut children = children.into_iter().map(Box::new);
+    match (children.next(), children.next(), children.next()) {
+        (Some(left), right, None) => Ok(BinaryTreeRepr::Branch(payload, left, right)),
+        _ => Err(DecodeError::WrongNumberOfChildren(num_children)),
+    }
+}
+#[cfg(feature = "quadtree")]
+fn make_quadtree<B, L>(
+    value: NodeValue<B, L>,
+    children: Vec<QuadtreeRepr<B, L>>,
+) -> Result<QuadtreeRepr<B, L>, DecodeError> {
+    let payload = match value {
+        NodeValue::Branch(payload) => payload,
+        NodeValue::Leaf(payload) => return Ok(QuadtreeRepr::Leaf(payload)),
+    };
+    let num_children = children.len();
+    <Box<[_; 4]>>::try_from(children.into_boxed_slice())
+        .map(|children| QuadtreeRepr::Branch(payload, children))
+        .map_err(|_| DecodeError::WrongNumberOfChildren(num_children))
+}
+#[cfg(feature = "octree")]
+fn make_octree<B, L>(
+    value: NodeValue<B, L>,
+    children: Vec<OctreeRepr<B, L>>,
+) -> Result<OctreeRepr<B, L>, DecodeError> {
+    let payload = match value {
+        NodeValue::Branch(payload) => payload,
+        NodeValue::Leaf(payload) => return Ok(OctreeRepr::Leaf(payload)),
+    };
+    let num_children = children.len();
+    <Box<[_; 8]>>::try_from(children.into_boxed_slice())
+        .map(|children| OctreeRepr::Branch(payload, children))
+        .map_err(|_| DecodeError::WrongNumberOfChildren(num_children))
+}
+
+macro_rules! impl_decode {
+    ($feature:literal, $module:ident, $tree:ident, $make:ident) => {
+        #[cfg(feature = $feature)]
+        impl<B, L, K, S> crate::$module::$tree<B, L, K, S>
+        where
+            S: Storage<Element = crate::$module::Node<B, L, K>, Key = K>,
+            K: Clone + Debug + Eq,
+            B: BinaryPayload,
+            L: BinaryPayload,
+        {
+            /// Decodes the tree from a byte slice containing it in the [binary format], and nothing else.
+            ///
+            /// # Errors
+            /// Will fail if the bytes do not describe a tree in the binary format, one of the payloads cannot be decoded, a node has a number of children which the tree does not allow or there are bytes left over after the tree.
+            ///
+            /// [binary format]: ../codec/index.html " "
+            #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+            pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+                decode_tree_from_slice(bytes, $make).map(Self::from_repr)
+            }
+            /// Reads the tree in the [binary format] from the reader. Nothing past the end of the tree is read.
+            ///
+            /// The reader is read from in small pieces, so it should be buffered.
+            ///
+            /// # Errors
+            /// Will fail if reading fails, the bytes do not describe a tree in the binary format, one of the payloads cannot be decoded or a node has a number of children which the tree does not allow.
+            ///
+            /// [binary format]: ../codec/index.html " "
+            #[cfg(feature = "std")]
+            #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+            pub fn read_from(reader: impl Read) -> Result<Self, ReadError> {
+                decode_tree(&mut ReaderSource(reader), $make).map(Self::from_repr)
+            }
+        }
+    };
+}
+impl_decode!(
+    "freeform_tree",
+    freeform_tree,
+    FreeformTree,
+    make_freeform_tree
+);
+impl_decode!("binary_tree", binary_tree, BinaryTree, make_binary_tree);
+impl_decode!("quadtree", quadtree, Quadtree, make_quadtree);
+impl_decode!("octree", octree, Octree, make_octree);
+
+/// The error type returned by [`Tree::write_to_slice`] when the slice is too small for the encoded tree.
+///
+/// [`Tree::write_to_slice`]: ../tree/trait.Tree.html#method.write_to_slice " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BufferTooSmallError;
+impl Display for BufferTooSmallError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad("the buffer is too small for the encoded tree")
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl std::error::Error for BufferTooSmallError {}
+
+/// The error type returned when decoding trees or payloads from the [binary format] fails.
+///
+/// [binary format]: index.html " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DecodeError {
+    /// The input ended in the middle of the tree.
+    UnexpectedEnd,
+    /// A varint was too large to fit into the type it was read into.
+    InvalidVarint,
+    /// The bytes of a payload do not describe a valid value of the payload type, or not all of them were used.
+    InvalidPayload,
+    /// A node has the specified number of children, which the type of tree being decoded does not allow.
+    WrongNumberOfChildren(usize),
+    /// There were bytes left over after the end of the tree.
+    TrailingData,
+    /// The tree was encoded with the specified version of the format, which is not supported by this version of the crate.
+    UnsupportedVersion(u8),
+    /// The tree was encoded with different payload types than the ones it's being decoded into, as indicated by the payload type fingerprints.
+    FingerprintMismatch {
+        /// The fingerprint of the payload types which the tree is being decoded into.
+        expected: u64,
+        /// The fingerprint stored in the encoded tree.
+        found: u64,
+    },
+    /// The checksum stored after the tree does not match the data, meaning that the data is corrupted.
+    ChecksumMismatch,
+}
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => f.write_str("unexpected end of input"),
+            Self::InvalidVarint => f.write_str("varint out of range"),
+            Self::InvalidPayload => f.write_str("invalid payload"),
+            Self::WrongNumberOfChildren(n) => {
+                write!(
+                    f,
+                    "a node has {} children, which the tree does not allow",
+                    n
+                )
+            }
+            Self::TrailingData => f.write_str("unexpected data after the end of the tree"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported format version {}", version)
+            }
+            Self::FingerprintMismatch { expected, found } => write!(
+                f,
+                "payload type fingerprint mismatch (expected {:#018x}, found {:#018x})",
+                expected, found,
+            ),
+            Self::ChecksumMismatch => f.write_str("checksum mismatch"),
+        }
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl std::error::Error for DecodeError {}
+
+/// The error type returned when reading trees in the [binary format] from a reader fails.
+///
+/// [binary format]: index.html " "
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+#[derive(Debug)]
+pub enum ReadError {
+    /// Reading from the reader failed.
+    Io(io::Error),
+    /// The data which was read could not be decoded.
+    Decode(DecodeError),
+}
+#[cfg(feature = "std")]
+impl From<io::Error> for ReadError {
+    fn from(op: io::Error) -> Self {
+        Self::Io(op)
+    }
+}
+#[cfg(feature = "std")]
+impl From<DecodeError> for ReadError {
+    fn from(op: DecodeError) -> Self {
+        Self::Decode(op)
+    }
+}
+#[cfg(feature = "std")]
+impl Display for ReadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "reading failed: {}", error),
+            Self::Decode(error) => write!(f, "decoding failed: {}", error),
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Decode(error) => Some(error),
+        }
+    }
+}
@@ -0,0 +1,258 @@
+//! Copy-on-write payloads, making snapshots of trees cheap.
+//!
+//! The [`CowTree`] adapter wraps a tree which stores its payloads behind [`Arc`] and hides the `Arc` from the payloads when traversing the tree. Cloning the tree, which is what [`snapshot`] does, then only clones the `Arc`s, and the payloads are only cloned when they're modified through a tree which shares them with another one. This is useful for checkpointing large amounts of state, such as a simulation held in an octree, where only a small fraction of the payloads change between checkpoints.
+//!
+//! [`CowTree`]: struct.CowTree.html " "
+//! [`Arc`]: https://doc.rust-lang.org/alloc/sync/struct.Arc.html " "
+//! [`snapshot`]: struct.CowTree.html#method.snapshot " "
+
+use core::iter::Map;
+use alloc::sync::Arc;
+use crate::{
+    traversal::{CursorResult, VisitorDirection},
+    NodeValue,
+    Traversable,
+    TraversableMut,
+    TryRemoveLeafError,
+    TryRemoveBranchError,
+    TryRemoveChildrenError,
+    TryAddChildError,
+    MakeBranchError,
+};
+
+/// A wrapper around a tree with payloads stored behind `Arc`, cloning the payloads only when they're modified while being shared with another tree.
+///
+/// The wrapper implements [`Traversable`] and [`TraversableMut`] by forwarding to the tree, using the payloads themselves instead of the `Arc`s around them. Modifying a payload via `value_mut_of` clones it if it's shared. Payloads taken out of the tree by removing nodes are cloned the same way, while payloads which are put into the tree are wrapped in a new `Arc`. Packed children are passed in and out through [`CowChildren`].
+///
+/// # Example
+/// ```rust
+/// use charcoal::{octree::VecOctree, NodeValue, Traversable, TraversableMut, cow::CowTree};
+/// use std::sync::Arc;
+///
+/// let leaf = |x| Arc::new(x);
+/// let mut tree = VecOctree::<Arc<u32>>::new(Arc::new(0));
+/// tree.root_mut()
+///     .make_branch([leaf(1), leaf(2), leaf(3), leaf(4), leaf(5), leaf(6), leaf(7), leaf(8)])
+///     .unwrap();
+/// let mut state = CowTree::new(tree);
+/// // Only the structure of the tree is copied, the payloads are shared.
+/// let checkpoint = state.snapshot();
+/// let root = state.cursor_to_root();
+/// let first = state.nth_child_of(&root, 0).unwrap();
+/// if let NodeValue::Leaf(payload) = state.value_mut_of(&first) {
+///     *payload = 10;
+/// }
+/// assert_eq!(*state.value_of(&first).into_inner(), 10);
+/// assert_eq!(*checkpoint.value_of(&first).into_inner(), 1);
+/// ```
+///
+/// [`Traversable`]: ../traversal/trait.Traversable.html " "
+/// [`TraversableMut`]: ../traversal/trait.TraversableMut.html " "
+/// [`CowChildren`]: struct.CowChildren.html " "
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CowTree<T>(T);
+impl<T> CowTree<T> {
+    /// Wraps the specified tree.
+    pub const fn new(traversable: T) -> Self {
+        Self(traversable)
+    }
+    /// Returns a reference to the wrapped tree.
+    pub const fn inner(&self) -> &T {
+        &self.0
+    }
+    /// Returns a mutable reference to the wrapped tree.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+    /// Unwraps the tree.
+    #[allow(clippy::missing_const_for_fn)] // Clippy has no idea what a destructor is
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+impl<T: Clone> CowTree<T> {
+    /// Creates a copy of the tree which shares the payloads with it. The payloads are only cloned once they're modified in either of the trees.
+    ///
+    /// The copy has the same keys as the original, so cursors can be used with both. This requires the tree to be `Clone`, which sparse storage is not, so trees which are snapshotted should use a plain `Vec` or another cloneable storage instead.
+    #[must_use]
+    pub fn snapshot(&self) -> Self {
+        self.clone()
+    }
+}
+impl<T> From<T> for CowTree<T> {
+    fn from(op: T) -> Self {
+        Self(op)
+    }
+}
+
+impl<T, B, L> Traversable for CowTree<T>
+where
+    T: Traversable<Branch = Arc<B>, Leaf = Arc<L>>,
+{
+    type Leaf = L;
+    type Branch = B;
+    type Cursor = T::Cursor;
+
+    fn advance_cursor<V>(
+        &self,
+        cursor: Self::Cursor,
+        direction: VisitorDirection<Self::Cursor, V>,
+    ) -> CursorResult<Self::Cursor> {
+        self.0.advance_cursor(cursor, direction)
+    }
+    fn cursor_to_root(&self) -> Self::Cursor {
+        self.0.cursor_to_root()
+    }
+    fn value_of(&self, cursor: &Self::Cursor) -> NodeValue<&'_ Self::Branch, &'_ Self::Leaf> {
+        match self.0.value_of(cursor) {
+            NodeValue::Branch(payload) => NodeValue::Branch(payload),
+            NodeValue::Leaf(payload) => NodeValue::Leaf(payload),
+        }
+    }
+    fn parent_of(&self, cursor: &Self::Cursor) -> Option<Self::Cursor> {
+        self.0.parent_of(cursor)
+    }
+    fn num_children_of(&self, cursor: &Self::Cursor) -> usize {
+        self.0.num_children_of(cursor)
+    }
+    fn nth_child_of(&self, cursor: &Self::Cursor, child_num: usize) -> Option<Self::Cursor> {
+        self.0.nth_child_of(cursor, child_num)
+    }
+    fn contains_cursor(&self, cursor: &Self::Cursor) -> bool {
+        self.0.contains_cursor(cursor)
+    }
+}
+impl<T, B, L> TraversableMut for CowTree<T>
+where
+    T: TraversableMut<Branch = Arc<B>, Leaf = Arc<L>>,
+    B: Clone,
+    L: Clone,
+{
+    const CAN_REMOVE_INDIVIDUAL_CHILDREN: bool = T::CAN_REMOVE_INDIVIDUAL_CHILDREN;
+    const CAN_PACK_CHILDREN: bool = T::CAN_PACK_CHILDREN;
+    const CAN_ADD_INDIVIDUAL_CHILDREN: bool = T::CAN_ADD_INDIVIDUAL_CHILDREN;
+    type PackedChildren = CowChildren<T::PackedChildren>;
+
+    fn value_mut_of(
+        &mut self,
+        cursor: &Self::Cursor,
+    ) -> NodeValue<&'_ mut Self::Branch, &'_ mut Self::Leaf> {
+        match self.0.value_mut_of(cursor) {
+            NodeValue::Branch(payload) => NodeValue::Branch(Arc::make_mut(payload)),
+            NodeValue::Leaf(payload) => NodeValue::Leaf(Arc::make_mut(payload)),
+        }
+    }
+    fn try_remove_leaf<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<Self::Leaf, TryRemoveLeafError> {
+        self.0
+            .try_remove_leaf(cursor, wrap_conversion(branch_to_leaf))
+            .map(unshare)
+    }
+    fn try_remove_branch_into<BtL: FnOnce(Self::Branch) -> Self::Leaf, C: FnMut(Self::Leaf)>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+        mut collector: C,
+    ) -> Result<Self::Branch, TryRemoveBranchError> {
+        self.0
+            .try_remove_branch_into(cursor, wrap_conversion(branch_to_leaf), |child| {
+                collector(unshare(child));
+            })
+            .map(unshare)
+    }
+    fn try_remove_children_into<BtL: FnOnce(Self::Branch) -> Self::Leaf, C: FnMut(Self::Leaf)>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+        mut collector: C,
+    ) -> Result<(), TryRemoveChildrenError> {
+        self.0
+            .try_remove_children_into(cursor, wrap_conversion(branch_to_leaf), |child| {
+                collector(unshare(child));
+            })
+    }
+    fn try_remove_branch<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<(Self::Branch, Self::PackedChildren), TryRemoveBranchError> {
+        self.0
+            .try_remove_branch(cursor, wrap_conversion(branch_to_leaf))
+            .map(|(payload, children)| (unshare(payload), CowChildren(children)))
+    }
+    fn try_remove_children<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<Self::PackedChildren, TryRemoveChildrenError> {
+        self.0
+            .try_remove_children(cursor, wrap_conversion(branch_to_leaf))
+            .map(CowChildren)
+    }
+    fn try_add_child<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        child_payload: Self::Leaf,
+        leaf_to_branch: LtB,
+    ) -> Result<Self::Cursor, TryAddChildError<Self::Leaf>> {
+        self.0
+            .try_add_child(
+                cursor,
+                Arc::new(child_payload),
+                wrap_conversion(leaf_to_branch),
+            )
+            .map_err(|error| match error {
+                TryAddChildError::WasFullBranch { child_payload } => {
+                    TryAddChildError::WasFullBranch {
+                        child_payload: unshare(child_payload),
+                    }
+                }
+                TryAddChildError::CannotAddIndividualChildren { child_payload } => {
+                    TryAddChildError::CannotAddIndividualChildren {
+                        child_payload: unshare(child_payload),
+                    }
+                }
+            })
+    }
+    fn try_add_children<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        children: Self::PackedChildren,
+        leaf_to_branch: LtB,
+    ) -> Result<(), MakeBranchError<Self::Leaf, Self::PackedChildren>> {
+        self.0
+            .try_add_children(cursor, children.0, wrap_conversion(leaf_to_branch))
+            .map_err(|error| MakeBranchError {
+                packed_children: CowChildren(error.packed_children),
+            })
+    }
+}
+
+/// Packed children of a [`CowTree`], wrapping the packed children of the underlying tree and unwrapping their payloads from `Arc` when iterated over, cloning them if they're shared.
+///
+/// [`CowTree`]: struct.CowTree.html " "
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CowChildren<P>(pub P);
+impl<P, L> IntoIterator for CowChildren<P>
+where
+    P: IntoIterator<Item = Arc<L>>,
+    L: Clone,
+{
+    type Item = L;
+    type IntoIter = Map<P::IntoIter, fn(Arc<L>) -> L>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter().map(unshare)
+    }
+}
+
+/// Takes the payload out of the `Arc`, cloning it if it's shared.
+fn unshare<X: Clone>(arc: Arc<X>) -> X {
+    Arc::try_unwrap(arc).unwrap_or_else(|arc| (*arc).clone())
+}
+/// Turns a conversion between payloads into one between payloads behind `Arc`.
+fn wrap_conversion<X: Clone, Y>(f: impl FnOnce(X) -> Y) -> impl FnOnce(Arc<X>) -> Arc<Y> {
+    |x| Arc::new(f(unshare(x)))
+}
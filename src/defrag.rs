@@ -0,0 +1,371 @@
+//! Automatic defragmentation of trees which use sparse storage.
+//!
+//! Removing nodes from a tree with sparse storage leaves holes in it, which are reused by nodes added later but otherwise only go away when the tree is defragmented. Since defragmenting changes the keys of the nodes, it can't happen behind the back of code which holds cursors or keys. The [`AutoDefrag`] wrapper solves this by only defragmenting between modifications, according to a [`DefragPolicy`], and returning a [`KeyTranslation`] which can be used to remap the keys which were held while the tree was being modified.
+//!
+//! The layout of a sparse storage, including the positions of the holes, can be inspected with [`Defragment::debug_slots`].
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{defrag::{AutoDefrag, DefragPolicy}, Octree, Tree};
+//!
+//! let mut tree = AutoDefrag::new(Octree::<_>::new(0), DefragPolicy::WhenHolesExceedPercent(25));
+//! tree.modify(|tree| tree.root_mut().make_branch([1, 2, 3, 4, 5, 6, 7, 8]).unwrap());
+//! let last_key = tree.root().nth_child(7).unwrap().into_raw_key();
+//! let (_, translation) = tree.modify(|tree| {
+//!     let mut root = tree.root_mut();
+//!     let mut first = root.nth_child_mut(0).unwrap();
+//!     first.make_branch([9, 10, 11, 12, 13, 14, 15, 16]).unwrap();
+//!     first.try_remove_children().unwrap();
+//! });
+//! // 8 out of 17 slots were holes, so the tree was defragmented.
+//! let translation = translation.unwrap();
+//! assert_eq!(tree.num_holes(), 0);
+//! let last_key = translation.translate(last_key);
+//! assert_eq!(*tree.get(last_key).unwrap().value().into_inner(), 8);
+//! ```
+//!
+//! [`AutoDefrag`]: struct.AutoDefrag.html " "
+//! [`DefragPolicy`]: enum.DefragPolicy.html " "
+//! [`KeyTranslation`]: struct.KeyTranslation.html " "
+//! [`Defragment::debug_slots`]: trait.Defragment.html#method.debug_slots " "
+
+use core::{iter::FusedIterator, ops::Deref};
+use alloc::{vec, vec::Vec};
+use crate::{tree::next_in_preorder, Tree};
+
+/// Describes when [`AutoDefrag`] defragments the tree it wraps.
+///
+/// [`AutoDefrag`]: struct.AutoDefrag.html " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[allow(variant_size_differences)]
+pub enum DefragPolicy {
+    /// Never defragment automatically. This is the default policy.
+    Never,
+    /// Defragment once the number of holes exceeds the specified amount.
+    WhenHolesExceedCount(usize),
+    /// Defragment once the holes take up more than the specified percentage of the slots in the storage, counting both the nodes and the holes.
+    WhenHolesExceedPercent(u8),
+}
+impl DefragPolicy {
+    /// Returns `true` if a storage with the specified number of holes and total number of slots, including the holes, should be defragmented according to the policy, `false` otherwise.
+    #[must_use]
+    pub fn should_defragment(self, num_holes: usize, num_slots: usize) -> bool {
+        match self {
+            Self::Never => false,
+            Self::WhenHolesExceedCount(count) => num_holes > count,
+            Self::WhenHolesExceedPercent(percent) => {
+                num_holes > 0
+                    && num_holes.saturating_mul(100) > num_slots.saturating_mul(percent.into())
+            }
+        }
+    }
+}
+impl Default for DefragPolicy {
+    fn default() -> Self {
+        Self::Never
+    }
+}
+
+/// Trees with sparse storage, which can be defragmented.
+///
+/// The trait is implemented for all tree types of the crate which use sparse storage, which is the default one.
+pub trait Defragment: Tree<Cursor = usize> {
+    /// Returns the number of holes in the storage.
+    fn num_holes(&self) -> usize;
+    /// Removes all holes from the storage, calling the specified closure with the old and new key of every node which was moved.
+    fn defragment_with(&mut self, on_move: impl FnMut(usize, usize));
+    /// Removes all holes from the storage, changing the keys of the nodes which were stored after the first hole.
+    fn defragment(&mut self) {
+        self.defragment_with(|_, _| {});
+    }
+    /// Removes all holes from the storage, returning a table which maps the old keys of the nodes to their new keys.
+    fn defragment_with_translation(&mut self) -> KeyTranslation {
+        let mut moves = Vec::new();
+        self.defragment_with(|old, new| moves.push((old, new)));
+        moves.sort_unstable_by_key(|&(old, _)| old);
+        KeyTranslation { moves }
+    }
+    /// Returns an iterator over all slots of the storage, including the holes, describing whether each of them holds a node and which node is its parent. This is a diagnostic tool for inspecting and visualizing the layout of the nodes in memory, such as how fragmented the storage is and where the holes are clustered.
+    ///
+    /// The tree is walked once to find the nodes before the iterator is returned, which takes *O*(*n*) time and memory, where *n* is the number of slots in the storage. Since sparse storage only tracks the number of its holes and not their positions, holes are told apart from nodes by whether they can be reached from the root node. This means that holes cannot be told apart from nodes which are in the storage but cannot be reached from the root, if there are any, in which case neither of them are reported as holes — see [`SlotState::Unreachable`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{defrag::{Defragment, SlotState}, BinaryTree};
+    ///
+    /// let mut tree = BinaryTree::<_>::new(0);
+    /// tree.root_mut().make_branch(1, Some(2)).unwrap();
+    /// tree.root_mut().left_child_mut().unwrap().make_branch(3, None).unwrap();
+    /// tree.root_mut().right_child_mut().unwrap().try_remove_leaf().unwrap();
+    ///
+    /// let states = tree.debug_slots().map(|slot| slot.state).collect::<Vec<_>>();
+    /// assert_eq!(states, [
+    ///     SlotState::Occupied,
+    ///     SlotState::Occupied,
+    ///     SlotState::Hole,
+    ///     SlotState::Occupied,
+    /// ]);
+    /// let parents = tree.debug_slots().map(|slot| slot.parent).collect::<Vec<_>>();
+    /// assert_eq!(parents, [None, Some(0), None, Some(1)]);
+    /// ```
+    ///
+    /// [`SlotState::Unreachable`]: enum.SlotState.html#variant.Unreachable " "
+    fn debug_slots(&self) -> DebugSlots {
+        let mut slots = (0..self.num_nodes())
+            .map(|index| SlotInfo {
+                index,
+                state: SlotState::Hole,
+                parent: None,
+            })
+            .collect::<Vec<_>>();
+        let mut num_reachable = 0;
+        let mut next = Some(self.root_key());
+        while let Some(key) = next {
+            let slot = &mut slots[key];
+            slot.state = SlotState::Occupied;
+            slot.parent = self.parent_key(&key);
+            num_reachable += 1;
+            next = next_in_preorder(self, key);
+        }
+        if slots.len() - num_reachable != self.num_holes() {
+            for slot in &mut slots {
+                if slot.state == SlotState::Hole {
+                    slot.state = SlotState::Unreachable;
+                }
+            }
+        }
+        DebugSlots {
+            slots: slots.into_iter(),
+        }
+    }
+}
+
+/// A table mapping the keys which nodes had before a tree was defragmented to the keys which they have after that, returned by [`defragment_with_translation`] and [`AutoDefrag`].
+///
+/// [`defragment_with_translation`]: trait.Defragment.html#method.defragment_with_translation " "
+/// [`AutoDefrag`]: struct.AutoDefrag.html " "
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct KeyTranslation {
+    /// Pairs of old and new keys of the nodes which were moved, sorted by the old key.
+    moves: Vec<(usize, usize)>,
+}
+impl KeyTranslation {
+    /// Returns the new key of the node which had the specified key before defragmentation. Keys of nodes which were not moved are returned unchanged.
+    #[must_use]
+    pub fn translate(&self, old_key: usize) -> usize {
+        self.moves
+            .binary_search_by_key(&old_key, |&(old, _)| old)
+            .map_or(old_key, |index| self.moves[index].1)
+    }
+    /// Returns the number of nodes which were moved.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+    /// Returns `true` if no nodes were moved, `false` otherwise.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+    /// Returns an iterator over the old and new keys of the nodes which were moved, sorted by the old key.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.moves.iter().copied()
+    }
+}
+
+/// An iterator over the slots of a sparse storage, created by [`Defragment::debug_slots`].
+///
+/// [`Defragment::debug_slots`]: trait.Defragment.html#method.debug_slots " "
+#[derive(Clone, Debug)]
+pub struct DebugSlots {
+    slots: vec::IntoIter<SlotInfo>,
+}
+impl Iterator for DebugSlots {
+    type Item = SlotInfo;
+    fn next(&mut self) -> Option<SlotInfo> {
+        self.slots.next()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.slots.size_hint()
+    }
+}
+impl DoubleEndedIterator for DebugSlots {
+    fn next_back(&mut self) -> Option<SlotInfo> {
+        self.slots.next_back()
+    }
+}
+impl ExactSizeIterator for DebugSlots {}
+impl FusedIterator for DebugSlots {}
+
+/// Information about one slot of a sparse storage, returned by [`Defragment::debug_slots`].
+///
+/// [`Defragment::debug_slots`]: trait.Defragment.html#method.debug_slots " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct SlotInfo {
+    /// The index of the slot, which is the key of the node stored in it.
+    pub index: usize,
+    /// Whether the slot holds a node.
+    pub state: SlotState,
+    /// The key of the parent of the node stored in the slot, or `None` if it's the root node or the slot does not hold a node reachable from the root node.
+    pub parent: Option<usize>,
+}
+
+/// The state of a slot of a sparse storage, as reported by [`Defragment::debug_slots`].
+///
+/// [`Defragment::debug_slots`]: trait.Defragment.html#method.debug_slots " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SlotState {
+    /// The slot holds a node which can be reached from the root node.
+    Occupied,
+    /// The slot is a hole left over from a removed node.
+    Hole,
+    /// The slot is either a hole or holds a node which cannot be reached from the root node. Slots are only reported as such when the storage holds nodes which cannot be reached from the root node, since those are indistinguishable from holes. Such nodes can be removed with `collect_unreachable`.
+    Unreachable,
+}
+
+/// A wrapper around a tree with sparse storage which defragments it after modifications according to a [`DefragPolicy`].
+///
+/// The tree can be read freely, since the wrapper dereferences to it, but is only modified through [`modify`], after which the policy is checked. Keys of nodes can thus only change when `modify` returns, and since it returns a [`KeyTranslation`] when that happens, all keys which are held onto across modifications can be remapped.
+///
+/// See the [module-level documentation] for an example.
+///
+/// [`DefragPolicy`]: enum.DefragPolicy.html " "
+/// [`modify`]: #method.modify " "
+/// [`KeyTranslation`]: struct.KeyTranslation.html " "
+/// [module-level documentation]: index.html " "
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AutoDefrag<T> {
+    tree: T,
+    policy: DefragPolicy,
+}
+impl<T: Defragment> AutoDefrag<T> {
+    /// Wraps the specified tree, defragmenting it right away if the policy says so.
+    pub fn new(tree: T, policy: DefragPolicy) -> Self {
+        let mut result = Self { tree, policy };
+        result.defragment_if_needed();
+        result
+    }
+    /// Returns the policy which is used to decide whether the tree is defragmented.
+    pub const fn policy(&self) -> DefragPolicy {
+        self.policy
+    }
+    /// Changes the policy which is used to decide whether the tree is defragmented. The new policy only takes effect after the next modification.
+    pub fn set_policy(&mut self, policy: DefragPolicy) {
+        self.policy = policy;
+    }
+    /// Modifies the tree with the specified closure, then defragments it if the policy says so. Returns the value returned by the closure and, if the tree was defragmented, the table for remapping the keys of the nodes.
+    pub fn modify<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> (R, Option<KeyTranslation>) {
+        let result = f(&mut self.tree);
+        (result, self.defragment_if_needed())
+    }
+    /// Defragments the tree if the policy says so, returning the table for remapping the keys of the nodes if that happened.
+    pub fn defragment_if_needed(&mut self) -> Option<KeyTranslation> {
+        if self
+            .policy
+            .should_defragment(self.tree.num_holes(), self.tree.num_nodes())
+        {
+            Some(self.tree.defragment_with_translation())
+        } else {
+            None
+        }
+    }
+}
+impl<T> AutoDefrag<T> {
+    /// Returns a reference to the wrapped tree.
+    pub const fn inner(&self) -> &T {
+        &self.tree
+    }
+    /// Unwraps the tree.
+    #[allow(clippy::missing_const_for_fn)] // Clippy has no idea what a destructor is
+    pub fn into_inner(self) -> T {
+        self.tree
+    }
+}
+impl<T> Deref for AutoDefrag<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.tree
+    }
+}
+
+#[cfg(feature = "binary_tree")]
+mod binary_tree_impl {
+    use crate::{
+        storage::{ListStorage, SparseStorage, SparseStorageSlot},
+        binary_tree::{BinaryTree, Node},
+    };
+    use super::Defragment;
+
+    impl<B, L, S> Defragment for BinaryTree<B, L, usize, SparseStorage<Node<B, L, usize>, S>>
+    where
+        S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
+    {
+        fn num_holes(&self) -> usize {
+            self.num_holes()
+        }
+        fn defragment_with(&mut self, on_move: impl FnMut(usize, usize)) {
+            self.defragment_with(on_move);
+        }
+    }
+}
+#[cfg(feature = "octree")]
+mod octree_impl {
+    use crate::{
+        storage::{ListStorage, SparseStorage, SparseStorageSlot},
+        octree::{Octree, Node},
+    };
+    use super::Defragment;
+
+    impl<B, L, S> Defragment for Octree<B, L, usize, SparseStorage<Node<B, L, usize>, S>>
+    where
+        S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
+    {
+        fn num_holes(&self) -> usize {
+            self.num_holes()
+        }
+        fn defragment_with(&mut self, on_move: impl FnMut(usize, usize)) {
+            self.defragment_with(on_move);
+        }
+    }
+}
+#[cfg(feature = "quadtree")]
+mod quadtree_impl {
+    use crate::{
+        storage::{ListStorage, SparseStorage, SparseStorageSlot},
+        quadtree::{Quadtree, Node},
+    };
+    use super::Defragment;
+
+    impl<B, L, S> Defragment for Quadtree<B, L, usize, SparseStorage<Node<B, L, usize>, S>>
+    where
+        S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
+    {
+        fn num_holes(&self) -> usize {
+            self.num_holes()
+        }
+        fn defragment_with(&mut self, on_move: impl FnMut(usize, usize)) {
+            self.defragment_with(on_move);
+        }
+    }
+}
+#[cfg(feature = "freeform_tree")]
+mod freeform_tree_impl {
+    use crate::{
+        storage::{ListStorage, SparseStorage, SparseStorageSlot},
+        freeform_tree::{FreeformTree, Node},
+    };
+    use super::Defragment;
+
+    impl<B, L, S> Defragment for FreeformTree<B, L, usize, SparseStorage<Node<B, L, usize>, S>>
+    where
+        S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
+    {
+        fn num_holes(&self) -> usize {
+            self.num_holes()
+        }
+        fn defragment_with(&mut self, on_move: impl FnMut(usize, usize)) {
+            self.defragment_with(on_move);
+        }
+    }
+}
@@ -0,0 +1,477 @@
+//! Loading and saving documents written in a simple subset of XML, stored as freeform trees.
+//!
+//! Elements with children are stored as branch nodes with an [`Element`] payload, holding the name and the attributes of the element. Since branch nodes in freeform trees always have at least one child, elements without children, as well as text, are stored as leaf nodes with a [`Leaf`] payload.
+//!
+//! # Supported subset
+//! - Elements with attributes, the values of which can be enclosed in either double or single quotes, and self-closing elements
+//! - Text, with the `&lt;`, `&gt;`, `&amp;`, `&quot;` and `&apos;` entities, as well as numeric character references
+//! - Comments and processing instructions, including the XML declaration, which are skipped
+//!
+//! Text which only consists of whitespace is skipped as well, unless it directly follows other text, and text interrupted by comments or processing instructions is joined into a single text node. Namespaces, CDATA sections and document type declarations are not supported. When saving, no whitespace is added between the elements, so loading a saved document produces the same tree.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{dom::{self, Leaf}, NodeValue};
+//!
+//! let document = dom::parse(r#"
+//!     <?xml version="1.0"?>
+//!     <list kind="shopping">
+//!         <item>Milk &amp; cookies</item>
+//!         <item done="yes"/>
+//!     </list>
+//! "#).unwrap();
+//! let root = document.root();
+//! if let NodeValue::Branch(list) = root.value() {
+//!     assert_eq!(list.name, "list");
+//!     assert_eq!(list.attribute("kind"), Some("shopping"));
+//! } else {
+//!     panic!("the list has children, so it's a branch node");
+//! }
+//!
+//! let text = root.first_child().unwrap().first_child().unwrap();
+//! assert_eq!(text.value(), NodeValue::Leaf(&Leaf::Text("Milk & cookies".to_string())));
+//!
+//! assert_eq!(
+//!     dom::to_string(&document),
+//!     r#"<list kind="shopping"><item>Milk &amp; cookies</item><item done="yes"/></list>"#,
+//! );
+//! ```
+//!
+//! [`Element`]: struct.Element.html " "
+//! [`Leaf`]: enum.Leaf.html " "
+
+use core::{
+    iter,
+    char,
+    fmt::{self, Formatter, Debug, Display, Write},
+};
+use alloc::{string::String, vec::Vec};
+use crate::{
+    freeform_tree::{FreeformTree, Node, NodeRefMut},
+    storage::Storage,
+    tree::PreorderEvent,
+    NodeValue,
+    Tree,
+};
+
+/// A document loaded with [`parse`], stored in a freeform tree with the default storage.
+///
+/// [`parse`]: fn.parse.html " "
+pub type Document = FreeformTree<Element, Leaf>;
+
+/// The name and the attributes of an element.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Element {
+    /// The name of the element.
+    pub name: String,
+    /// The names and values of the attributes of the element, in the order in which they were specified.
+    pub attributes: Vec<(String, String)>,
+}
+impl Element {
+    /// Creates an element with the specified name and no attributes.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            attributes: Vec::new(),
+        }
+    }
+    /// Returns the value of the attribute with the specified name, or `None` if there is no such attribute.
+    #[must_use]
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// The payload of a leaf node in a [`Document`]: either an element without children or text.
+///
+/// [`Document`]: type.Document.html " "
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Leaf {
+    /// An element which has no children.
+    Element(Element),
+    /// Text, with the entities already replaced with the characters which they stand for.
+    Text(String),
+}
+
+/// Parses a document written in the supported subset of XML. See the [module-level documentation] for the details.
+///
+/// # Errors
+/// Will fail if the document is not well-formed or uses unsupported features.
+///
+/// # Example
+/// Text around a comment is loaded as a single text node, so that it stays the same after saving and loading the document again:
+/// ```rust
+/// use charcoal::{dom::{self, Leaf}, NodeValue};
+///
+/// let document = dom::parse("<r>a<!-- x -->b</r>").unwrap();
+/// assert_eq!(document.num_nodes(), 2);
+/// let text = document.root().first_child().unwrap();
+/// assert_eq!(text.value(), NodeValue::Leaf(&Leaf::Text("ab".to_string())));
+/// assert_eq!(dom::parse(&dom::to_string(&document)).unwrap().num_nodes(), 2);
+/// ```
+///
+/// [module-level documentation]: index.html " "
+pub fn parse(input: &str) -> Result<Document, ParseError> {
+    let mut parser = Parser { input, position: 0 };
+    parser.skip_misc()?;
+    if !parser.eat("<") {
+        return Err(parser.error(ParseErrorKind::NoRootElement));
+    }
+    let (root, self_closing) = parser.start_tag()?;
+    let root_name = root.name.clone();
+    let mut document = Document::new(Leaf::Element(root));
+    if !self_closing {
+        let root_key = *document.root().raw_key();
+        parser.content(&mut document, root_key, root_name)?;
+    }
+    parser.skip_misc()?;
+    if parser.rest().is_empty() {
+        Ok(document)
+    } else {
+        Err(parser.error(ParseErrorKind::TrailingContent))
+    }
+}
+
+/// Saves a document in the supported subset of XML, without any whitespace between the elements.
+#[allow(clippy::missing_panics_doc)] // Writing into a string never fails
+pub fn to_string<K, S>(document: &FreeformTree<Element, Leaf, K, S>) -> String
+where
+    S: Storage<Element = Node<Element, Leaf, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let mut result = String::new();
+    write(document, &mut result).expect("writing into a string cannot fail");
+    result
+}
+/// Saves a document in the supported subset of XML into the specified writer, without any whitespace between the elements.
+///
+/// # Errors
+/// Will fail if the writer fails.
+pub fn write<K, S>(
+    document: &FreeformTree<Element, Leaf, K, S>,
+    out: &mut impl Write,
+) -> fmt::Result
+where
+    S: Storage<Element = Node<Element, Leaf, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    // The names of the elements which have been opened but not closed yet
+    let mut open_elements = Vec::new();
+    for event in document.events() {
+        match event {
+            PreorderEvent::Enter(element) => {
+                write_start_tag(element, out)?;
+                out.write_char('>')?;
+                open_elements.push(&element.name);
+            }
+            PreorderEvent::Leaf(Leaf::Element(element)) => {
+                write_start_tag(element, out)?;
+                out.write_str("/>")?;
+            }
+            PreorderEvent::Leaf(Leaf::Text(text)) => write_escaped(text, out)?,
+            PreorderEvent::Exit => {
+                let name = open_elements
+                    .pop()
+                    .unwrap_or_else(|| unreachable!("every exit event matches an enter event"));
+                write!(out, "</{}>", name)?;
+            }
+        }
+    }
+    Ok(())
+}
+fn write_start_tag(element: &Element, out: &mut impl Write) -> fmt::Result {
+    write!(out, "<{}", element.name)?;
+    for (name, value) in &element.attributes {
+        write!(out, " {}=\"", name)?;
+        write_escaped(value, out)?;
+        out.write_char('"')?;
+    }
+    Ok(())
+}
+fn write_escaped(text: &str, out: &mut impl Write) -> fmt::Result {
+    for c in text.chars() {
+        match c {
+            '<' => out.write_str("&lt;")?,
+            '>' => out.write_str("&gt;")?,
+            '&' => out.write_str("&amp;")?,
+            '"' => out.write_str("&quot;")?,
+            c => out.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+/// The error type returned by [`parse`].
+///
+/// [`parse`]: fn.parse.html " "
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ParseError {
+    /// The byte offset in the input at which the error was detected.
+    pub position: usize,
+    /// What went wrong.
+    pub kind: ParseErrorKind,
+}
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.kind, self.position)
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl std::error::Error for ParseError {}
+
+/// The kinds of errors which can happen while parsing a document, used in [`ParseError`].
+///
+/// [`ParseError`]: struct.ParseError.html " "
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// The input ended in the middle of the document.
+    UnexpectedEnd,
+    /// A character which is not allowed at this point was encountered.
+    UnexpectedChar(char),
+    /// A closing tag does not match the element which it closes, which has the specified name.
+    MismatchedClosingTag(String),
+    /// An entity or a character reference is not recognized.
+    InvalidEntity,
+    /// The document does not have a root element.
+    NoRootElement,
+    /// The root element is followed by something other than whitespace, comments and processing instructions.
+    TrailingContent,
+}
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => f.write_str("unexpected end of input"),
+            Self::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            Self::MismatchedClosingTag(name) => {
+                write!(f, "closing tag does not match the opening tag of {:?}", name)
+            }
+            Self::InvalidEntity => f.write_str("invalid entity"),
+            Self::NoRootElement => f.write_str("no root element"),
+            Self::TrailingContent => f.write_str("unexpected content after the root element"),
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.input[self.position..]
+    }
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+    #[allow(clippy::missing_const_for_fn)] // Clippy has no idea what a destructor is
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            position: self.position,
+            kind,
+        }
+    }
+    /// Returns the error for the character at the current position, or for the end of the input.
+    fn unexpected(&self) -> ParseError {
+        self.error(
+            self.peek()
+                .map_or(ParseErrorKind::UnexpectedEnd, ParseErrorKind::UnexpectedChar),
+        )
+    }
+    fn eat(&mut self, s: &str) -> bool {
+        if self.rest().starts_with(s) {
+            self.position += s.len();
+            true
+        } else {
+            false
+        }
+    }
+    fn expect(&mut self, s: &str) -> Result<(), ParseError> {
+        if self.eat(s) {
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+    /// Skips everything up to and including the specified terminator.
+    fn skip_past(&mut self, terminator: &str) -> Result<(), ParseError> {
+        if let Some(index) = self.rest().find(terminator) {
+            self.position += index + terminator.len();
+            Ok(())
+        } else {
+            self.position = self.input.len();
+            Err(self.error(ParseErrorKind::UnexpectedEnd))
+        }
+    }
+    /// Skips whitespace, comments and processing instructions.
+    fn skip_misc(&mut self) -> Result<(), ParseError> {
+        loop {
+            self.skip_whitespace();
+            if self.eat("<!--") {
+                self.skip_past("-->")?;
+            } else if self.eat("<?") {
+                self.skip_past("?>")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+    fn name(&mut self) -> Result<String, ParseError> {
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':')))
+            .unwrap_or(rest.len());
+        if length == 0 {
+            return Err(self.unexpected());
+        }
+        let name = String::from(&rest[..length]);
+        self.position += length;
+        Ok(name)
+    }
+    /// Parses the rest of a start tag, after the opening angle bracket. Returns the element and whether the tag was self-closing.
+    fn start_tag(&mut self) -> Result<(Element, bool), ParseError> {
+        let mut element = Element::new(self.name()?);
+        loop {
+            self.skip_whitespace();
+            if self.eat("/>") {
+                return Ok((element, true));
+            } else if self.eat(">") {
+                return Ok((element, false));
+            }
+            let name = self.name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let quote = match self.peek() {
+                Some(quote @ '"') | Some(quote @ '\'') => quote,
+                _ => return Err(self.unexpected()),
+            };
+            self.position += 1;
+            let value = self.text(quote)?;
+            self.position += 1;
+            element.attributes.push((name, value));
+        }
+    }
+    /// Parses text up to the specified terminator, replacing entities, without consuming the terminator.
+    fn text(&mut self, terminator: char) -> Result<String, ParseError> {
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                Some(c) if c == terminator => return Ok(text),
+                None | Some('<') => return Err(self.unexpected()),
+                Some('&') => {
+                    self.position += 1;
+                    text.push(self.entity()?);
+                }
+                Some(c) => {
+                    self.position += c.len_utf8();
+                    text.push(c);
+                }
+            }
+        }
+    }
+    /// Parses an entity or a character reference, after the ampersand.
+    fn entity(&mut self) -> Result<char, ParseError> {
+        let rest = self.rest();
+        let end = rest
+            .find(';')
+            .ok_or_else(|| self.error(ParseErrorKind::InvalidEntity))?;
+        let entity = &rest[..end];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => {
+                let code = entity.strip_prefix("#x").map_or_else(
+                    || entity.strip_prefix('#').and_then(|decimal| decimal.parse().ok()),
+                    |hex| u32::from_str_radix(hex, 16).ok(),
+                );
+                code.and_then(char::from_u32)
+            }
+        };
+        let c = c.ok_or_else(|| self.error(ParseErrorKind::InvalidEntity))?;
+        self.position += end + 1;
+        Ok(c)
+    }
+    /// Parses the content of the element with the specified key and name, up to and including its closing tag.
+    ///
+    /// The elements which are still open are kept on a stack rather than parsed recursively, so that deeply nested documents cannot overflow the call stack.
+    fn content(
+        &mut self,
+        document: &mut Document,
+        key: usize,
+        name: String,
+    ) -> Result<(), ParseError> {
+        let mut open_elements = alloc::vec![(key, name)];
+        while let Some((key, name)) = open_elements.last() {
+            let key = *key;
+            if self.eat("</") {
+                let closing_position = self.position;
+                if &self.name()? != name {
+                    self.position = closing_position;
+                    return Err(self.error(ParseErrorKind::MismatchedClosingTag(name.clone())));
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                open_elements.pop();
+            } else if self.eat("<!--") {
+                self.skip_past("-->")?;
+            } else if self.eat("<?") {
+                self.skip_past("?>")?;
+            } else if self.eat("<") {
+                let (element, self_closing) = self.start_tag()?;
+                if self_closing {
+                    push_child(document, key, Leaf::Element(element));
+                } else {
+                    let child_name = element.name.clone();
+                    let child = push_child(document, key, Leaf::Element(element));
+                    open_elements.push((child, child_name));
+                }
+            } else {
+                let text = self.text('<')?;
+                push_text(document, key, text);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Adds a child to the element with the specified key, turning it into a branch node if it was a leaf, and returns the key of the child.
+fn push_child(document: &mut Document, key: usize, child: Leaf) -> usize {
+    let mut node = NodeRefMut::new_raw(document, key).expect("the parent key is always valid");
+    if node.is_leaf() {
+        node.make_branch_with(iter::once(child), |payload| match payload {
+            Leaf::Element(element) => element,
+            Leaf::Text(..) => unreachable!("text nodes never get children"),
+        })
+        .unwrap_or_else(|_| unreachable!("the node was checked to be a leaf"));
+    } else {
+        node.try_push_back(child)
+            .unwrap_or_else(|_| unreachable!("the node was checked to be a branch"));
+    }
+    *node.last_child().expect("a child was just added").raw_key()
+}
+
+/// Adds text to the end of the element with the specified key, joining it with the last child if that is text as well, and skipping it if it only consists of whitespace otherwise.
+fn push_text(document: &mut Document, key: usize, text: String) {
+    let mut node = NodeRefMut::new_raw(document, key).expect("the parent key is always valid");
+    if let Some(mut last_child) = node.last_child_mut() {
+        if let NodeValue::Leaf(Leaf::Text(previous)) = last_child.value_mut() {
+            previous.push_str(&text);
+            return;
+        }
+    }
+    if !text.trim().is_empty() {
+        push_child(document, key, Leaf::Text(text));
+    }
+}
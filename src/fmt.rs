@@ -0,0 +1,411 @@
+//! Human-readable rendering of the differences between two trees, for use in test assertions.
+//!
+//! The [`diff_display`] function compares two trees node by node, pairing up nodes which have the same position, and returns a [`TreeDiff`] which renders the result similarly to a unified diff: every node is printed on its own line, indented by its depth, with a prefix telling whether it's unchanged (` `), only present in the old tree (`-`) or only present in the new tree (`+`). A node which is present in both trees but has a different payload or is a leaf in one tree and a branch in the other is printed twice, first with `-` and then with `+`.
+//!
+//! The [`assert_trees_eq!`] macro uses this to print the differences between two trees when they are not equal. For comparing a tree against a pattern written out in the test itself, the [`assert_tree!`] macro can be used instead, which reports the path to the first node which doesn't match.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{fmt::diff_display, FreeformTree};
+//!
+//! let mut old = FreeformTree::<_>::new(1);
+//! old.root_mut().make_branch(vec![2, 3]).unwrap();
+//! let mut new = FreeformTree::<_>::new(1);
+//! new.root_mut().make_branch(vec![2, 4, 5]).unwrap();
+//!
+//! let diff = diff_display(&old, &new);
+//! assert!(!diff.is_empty());
+//! assert_eq!(
+//!     diff.to_string(),
+//!     "  Branch(1)
+//!     Leaf(2)
+//! -   Leaf(3)
+//! +   Leaf(4)
+//! +   Leaf(5)
+//! ",
+//! );
+//! ```
+//!
+//! [`diff_display`]: fn.diff_display.html " "
+//! [`TreeDiff`]: struct.TreeDiff.html " "
+//! [`assert_trees_eq!`]: ../macro.assert_trees_eq.html " "
+//! [`assert_tree!`]: ../macro.assert_tree.html " "
+
+use core::fmt::{self, Formatter, Display, Debug};
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+use crate::{Tree, NodeValue};
+#[cfg(feature = "alloc")]
+use crate::path::Path;
+
+/// Compares two trees with the same payload types, returning a value which renders the differences between them when displayed.
+///
+/// See the [module-level documentation] for the format.
+///
+/// [module-level documentation]: index.html " "
+pub fn diff_display<'a, T1, T2>(old: &'a T1, new: &'a T2) -> TreeDiff<'a, T1, T2>
+where
+    T1: Tree,
+    T2: Tree<Branch = T1::Branch, Leaf = T1::Leaf>,
+    T1::Branch: Debug + PartialEq,
+    T1::Leaf: Debug + PartialEq,
+{
+    TreeDiff { old, new }
+}
+
+/// The differences between two trees, created by [`diff_display`].
+///
+/// [`diff_display`]: fn.diff_display.html " "
+#[derive(Copy, Clone, Debug)]
+pub struct TreeDiff<'a, T1, T2> {
+    old: &'a T1,
+    new: &'a T2,
+}
+impl<T1, T2> TreeDiff<'_, T1, T2>
+where
+    T1: Tree,
+    T2: Tree<Branch = T1::Branch, Leaf = T1::Leaf>,
+    T1::Branch: Debug + PartialEq,
+    T1::Leaf: Debug + PartialEq,
+{
+    /// Returns `true` if the trees have the same structure and payloads, i.e. there are no differences to display, `false` otherwise.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        subtrees_equal(
+            self.old,
+            &self.old.root_key(),
+            self.new,
+            &self.new.root_key(),
+        )
+    }
+}
+impl<T1, T2> Display for TreeDiff<'_, T1, T2>
+where
+    T1: Tree,
+    T2: Tree<Branch = T1::Branch, Leaf = T1::Leaf>,
+    T1::Branch: Debug + PartialEq,
+    T1::Leaf: Debug + PartialEq,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_pair(
+            f,
+            self.old,
+            Some(self.old.root_key()),
+            self.new,
+            Some(self.new.root_key()),
+            0,
+        )
+    }
+}
+
+fn subtrees_equal<T1, T2>(old: &T1, old_key: &T1::Cursor, new: &T2, new_key: &T2::Cursor) -> bool
+where
+    T1: Tree,
+    T2: Tree<Branch = T1::Branch, Leaf = T1::Leaf>,
+    T1::Branch: PartialEq,
+    T1::Leaf: PartialEq,
+{
+    if old.node_value(old_key) != new.node_value(new_key) {
+        return false;
+    }
+    let mut old_children = old.children_keys(old_key);
+    let mut new_children = new.children_keys(new_key);
+    loop {
+        match (old_children.next(), new_children.next()) {
+            (Some(old_child), Some(new_child)) => {
+                if !subtrees_equal(old, &old_child, new, &new_child) {
+                    return false;
+                }
+            }
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Writes a pair of nodes at the same position and the pairs of their children, or the whole subtree of the node which is only present in one of the trees.
+fn write_pair<T1, T2>(
+    f: &mut Formatter<'_>,
+    old: &T1,
+    old_key: Option<T1::Cursor>,
+    new: &T2,
+    new_key: Option<T2::Cursor>,
+    depth: usize,
+) -> fmt::Result
+where
+    T1: Tree,
+    T2: Tree<Branch = T1::Branch, Leaf = T1::Leaf>,
+    T1::Branch: Debug + PartialEq,
+    T1::Leaf: Debug + PartialEq,
+{
+    match (old_key, new_key) {
+        (Some(old_key), Some(new_key)) => {
+            let old_value = value_of(old, &old_key);
+            let new_value = value_of(new, &new_key);
+            if old_value == new_value {
+                write_line(f, ' ', depth, &old_value)?;
+            } else {
+                write_line(f, '-', depth, &old_value)?;
+                write_line(f, '+', depth, &new_value)?;
+            }
+            let mut old_children = old.children_keys(&old_key);
+            let mut new_children = new.children_keys(&new_key);
+            loop {
+                let old_child = old_children.next();
+                let new_child = new_children.next();
+                if old_child.is_none() && new_child.is_none() {
+                    break Ok(());
+                }
+                write_pair(f, old, old_child, new, new_child, depth + 1)?;
+            }
+        }
+        (Some(old_key), None) => write_subtree(f, '-', old, &old_key, depth),
+        (None, Some(new_key)) => write_subtree(f, '+', new, &new_key, depth),
+        (None, None) => Ok(()),
+    }
+}
+fn write_subtree<T>(f: &mut Formatter<'_>, sign: char, tree: &T, key: &T::Cursor, depth: usize) -> fmt::Result
+where
+    T: Tree,
+    T::Branch: Debug,
+    T::Leaf: Debug,
+{
+    write_line(f, sign, depth, &value_of(tree, key))?;
+    for child in tree.children_keys(key) {
+        write_subtree(f, sign, tree, &child, depth + 1)?;
+    }
+    Ok(())
+}
+fn write_line<B: Debug, L: Debug>(f: &mut Formatter<'_>, sign: char, depth: usize, value: &NodeValue<B, L>) -> fmt::Result {
+    writeln!(f, "{} {:indent$}{:?}", sign, "", value, indent = depth * 2)
+}
+fn value_of<'a, T: Tree>(tree: &'a T, key: &T::Cursor) -> NodeValue<&'a T::Branch, &'a T::Leaf> {
+    tree.node_value(key)
+        .expect("keys taken from the tree itself are always valid")
+}
+
+/// Asserts that two trees have the same structure and payloads.
+///
+/// On failure, the macro panics with the differences between the trees, rendered by [`diff_display`]. The payloads must implement `Debug` and `PartialEq`. Like [`assert_eq!`], a custom message can be added after the trees.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{assert_trees_eq, FreeformTree};
+///
+/// let mut left = FreeformTree::<_>::new(1);
+/// left.root_mut().make_branch(vec![2, 3]).unwrap();
+/// let mut right = FreeformTree::<_>::new(1);
+/// right.root_mut().make_branch(vec![2, 3]).unwrap();
+/// assert_trees_eq!(left, right);
+/// ```
+/// ```rust,should_panic
+/// # use charcoal::{assert_trees_eq, FreeformTree};
+/// let left = FreeformTree::<_>::new(1);
+/// let right = FreeformTree::<_>::new(2);
+/// assert_trees_eq!(left, right, "the roots differ");
+/// ```
+///
+/// [`diff_display`]: fmt/fn.diff_display.html " "
+/// [`assert_eq!`]: https://doc.rust-lang.org/std/macro.assert_eq.html " "
+#[macro_export]
+macro_rules! assert_trees_eq {
+    ($left:expr, $right:expr $(,)?) => {{
+        let diff = $crate::fmt::diff_display(&$left, &$right);
+        if !diff.is_empty() {
+            panic!("assertion failed: trees are not equal (- left, + right)\n{}", diff);
+        }
+    }};
+    ($left:expr, $right:expr, $($arg:tt)+) => {{
+        let diff = $crate::fmt::diff_display(&$left, &$right);
+        if !diff.is_empty() {
+            panic!(
+                "assertion failed: trees are not equal (- left, + right): {}\n{}",
+                format_args!($($arg)+),
+                diff,
+            );
+        }
+    }};
+}
+
+/// A pattern which a tree can be matched against with [`match_pattern`], usually created by the [`assert_tree!`] macro.
+///
+/// [`match_pattern`]: fn.match_pattern.html " "
+/// [`assert_tree!`]: ../macro.assert_tree.html " "
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TreePattern<'a, B, L = B> {
+    /// Matches a branch node with the specified payload and children matching the specified patterns.
+    Branch(B, &'a [TreePattern<'a, B, L>]),
+    /// Matches a leaf node with the specified payload.
+    Leaf(L),
+}
+
+/// Checks whether the tree matches the specified pattern, returning the first node which doesn't match if it doesn't.
+///
+/// The nodes are checked in depth-first pre-order, and the children of a node are only checked if the node itself matches.
+///
+/// # Errors
+/// Will fail if a node has a different payload than the pattern, is a leaf node where the pattern expects a branch node or vice versa, or has a different number of children than the pattern.
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub fn match_pattern<T>(tree: &T, pattern: &TreePattern<'_, T::Branch, T::Leaf>) -> Result<(), PatternMismatch>
+where
+    T: Tree,
+    T::Branch: Debug + PartialEq,
+    T::Leaf: Debug + PartialEq,
+{
+    let mut path = Path::new();
+    match_node(tree, &tree.root_key(), pattern, &mut path)
+        .map_err(|kind| PatternMismatch { path, kind })
+}
+#[cfg(feature = "alloc")]
+fn match_node<T>(
+    tree: &T,
+    key: &T::Cursor,
+    pattern: &TreePattern<'_, T::Branch, T::Leaf>,
+    path: &mut Path,
+) -> Result<(), PatternMismatchKind>
+where
+    T: Tree,
+    T::Branch: Debug + PartialEq,
+    T::Leaf: Debug + PartialEq,
+{
+    let value = value_of(tree, key);
+    let (expected, children) = match pattern {
+        TreePattern::Branch(payload, children) => (NodeValue::Branch(payload), *children),
+        TreePattern::Leaf(payload) => (NodeValue::Leaf(payload), &[][..]),
+    };
+    if value != expected {
+        return Err(PatternMismatchKind::Value {
+            expected: alloc::format!("{:?}", expected),
+            found: alloc::format!("{:?}", value),
+        });
+    }
+    let num_children = tree.children_keys(key).count();
+    if num_children != children.len() {
+        return Err(PatternMismatchKind::NumChildren {
+            expected: children.len(),
+            found: num_children,
+        });
+    }
+    for (i, (child, child_pattern)) in tree.children_keys(key).zip(children).enumerate() {
+        path.push(i);
+        match_node(tree, &child, child_pattern, path)?;
+        path.pop();
+    }
+    Ok(())
+}
+
+/// The error type returned by [`match_pattern`], describing the first node which doesn't match the pattern.
+///
+/// [`match_pattern`]: fn.match_pattern.html " "
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PatternMismatch {
+    /// The path to the node which doesn't match.
+    pub path: Path,
+    /// The way in which the node doesn't match.
+    pub kind: PatternMismatchKind,
+}
+#[cfg(feature = "alloc")]
+impl Display for PatternMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str("root node: ")?;
+        } else {
+            write!(f, "node at path `{}`: ", self.path)?;
+        }
+        match &self.kind {
+            PatternMismatchKind::Value { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            PatternMismatchKind::NumChildren { expected, found } => {
+                write!(f, "expected {} children, found {}", expected, found)
+            }
+        }
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl std::error::Error for PatternMismatch {}
+/// The ways in which a node can fail to match a [`TreePattern`].
+///
+/// [`TreePattern`]: enum.TreePattern.html " "
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PatternMismatchKind {
+    /// The node has a different payload, or is a leaf node where the pattern expects a branch node or vice versa. Both values are rendered with their `Debug` implementations.
+    Value {
+        /// The node value which the pattern expects.
+        expected: String,
+        /// The node value which the tree has.
+        found: String,
+    },
+    /// The node has a different number of children than the pattern.
+    NumChildren {
+        /// The number of children which the pattern expects.
+        expected: usize,
+        /// The number of children which the node has.
+        found: usize,
+    },
+}
+
+/// Asserts that a tree matches a pattern of nested nodes.
+///
+/// The pattern is written as `leaf(payload)` for leaf nodes and `branch(payload) => [children...]` for branch nodes, where the children are comma-separated patterns. The payloads are compared using `PartialEq`, and must also implement `Debug`. On failure, the macro panics with the path to the first node which doesn't match, as reported by [`match_pattern`].
+///
+/// # Example
+/// ```rust
+/// use charcoal::{assert_tree, FreeformTree};
+///
+/// let mut tree = FreeformTree::<_>::new(1);
+/// let mut root = tree.root_mut();
+/// root.make_branch(vec![2, 3]).unwrap();
+/// root.last_child_mut().unwrap().make_branch(vec![4]).unwrap();
+///
+/// assert_tree!(tree, branch(1) => [leaf(2), branch(3) => [leaf(4)]]);
+/// ```
+/// ```rust,should_panic
+/// # use charcoal::{assert_tree, FreeformTree};
+/// # let mut tree = FreeformTree::<_>::new(1);
+/// # tree.root_mut().make_branch(vec![2, 3]).unwrap();
+/// // Panics with "node at path `1`: expected Leaf(4), found Leaf(3)".
+/// assert_tree!(tree, branch(1) => [leaf(2), leaf(4)]);
+/// ```
+///
+/// [`match_pattern`]: fmt/fn.match_pattern.html " "
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+#[macro_export]
+macro_rules! assert_tree {
+    (@pattern leaf($payload:expr)) => {
+        $crate::fmt::TreePattern::Leaf($payload)
+    };
+    (@pattern branch($payload:expr) => [$($children:tt)*]) => {
+        $crate::fmt::TreePattern::Branch($payload, &$crate::assert_tree!(@children [] $($children)*))
+    };
+    (@children [$($done:expr,)*]) => {
+        [$($done,)*]
+    };
+    (@children [$($done:expr,)*] leaf($payload:expr) $(, $($rest:tt)*)?) => {
+        $crate::assert_tree!(
+            @children [$($done,)* $crate::assert_tree!(@pattern leaf($payload)),]
+            $($($rest)*)?
+        )
+    };
+    (@children [$($done:expr,)*] branch($payload:expr) => [$($children:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::assert_tree!(
+            @children [$($done,)* $crate::assert_tree!(@pattern branch($payload) => [$($children)*]),]
+            $($($rest)*)?
+        )
+    };
+    ($tree:expr, $($pattern:tt)+) => {
+        if let Err(mismatch) = $crate::fmt::match_pattern(&$tree, &$crate::assert_tree!(@pattern $($pattern)+)) {
+            panic!("assertion failed: tree does not match the pattern: {}", mismatch);
+        }
+    };
+}
@@ -1,6 +1,25 @@
-use core::fmt::Debug;
-use crate::storage::{Storage, ListStorage, DefaultStorage, SparseStorage, SparseStorageSlot};
-use super::{NodeRef, NodeRefMut, Node};
+use core::{fmt::Debug, convert, iter};
+use crate::{
+    storage::{Storage, ListStorage, DefaultStorage, SparseStorage, SparseStorageSlot},
+    util::{
+        unreachable_debugchecked,
+        has_room_for,
+        clear_storage,
+        defragment_tree,
+        map_values_in_tree,
+    },
+    CapacityError,
+    StableStorage,
+    NodeValue,
+    Tree,
+};
+#[cfg(feature = "alloc")]
+use crate::util::collect_unreachable_in_tree;
+#[cfg(feature = "allocator_api")]
+use core::alloc::Allocator;
+#[cfg(feature = "allocator_api")]
+use crate::allocator::SparseVecIn;
+use super::{NodeRef, NodeRefMut, Node, ExtendFromError};
 
 /// A freeform tree.
 ///
@@ -43,6 +62,33 @@ where
         });
         Self { storage, root }
     }
+    /// Creates a freeform tree with the specified value for the root node, returning an error instead of panicking if the storage cannot fit the root node.
+    ///
+    /// # Errors
+    /// Will fail if the storage has a fixed capacity of zero. In such a case, the payload for the root node is returned back to the caller.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::freeform_tree::{InlineFreeformTree, Node};
+    ///
+    /// // A storage with a capacity of zero cannot fit the root node:
+    /// let tree = InlineFreeformTree::<[Node<u32, u32, usize>; 0], u32>::try_new(87);
+    /// assert_eq!(tree.unwrap_err().payload, 87);
+    /// // Anything bigger than that works fine:
+    /// let tree = InlineFreeformTree::<[Node<u32, u32, usize>; 1], u32>::try_new(87).unwrap();
+    /// assert!(tree.root().is_leaf());
+    /// ```
+    pub fn try_new(root: L) -> Result<Self, CapacityError<L>> {
+        let mut storage = S::new();
+        if !has_room_for(&storage, 1) {
+            return Err(CapacityError { payload: root });
+        }
+        let root = storage.add(unsafe {
+            // SAFETY: as above
+            Node::root(root)
+        });
+        Ok(Self { storage, root })
+    }
     /// Creates a freeform tree with the specified capacity for the storage.
     ///
     /// # Panics
@@ -118,6 +164,49 @@ where
             NodeRefMut::new_raw_unchecked(self, self.root.clone())
         }
     }
+    /// Consumes the tree and returns the payload of its root node, dropping all other nodes along with the storage.
+    ///
+    /// This saves the need to clone the payload out of the tree once it's no longer needed, such as when the root node holds the result of a computation which went through the whole tree. To replace the payload of the root node without consuming the tree, use [`Tree::set_root_value`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{FreeformTree, NodeValue};
+    ///
+    /// let mut tree = FreeformTree::<_>::new(0);
+    /// tree.root_mut().make_branch(vec![1, 2]).unwrap();
+    /// *tree.root_mut().value_mut().into_inner() = 10;
+    /// assert_eq!(tree.into_root_value(), NodeValue::Branch(10));
+    /// ```
+    ///
+    /// [`Tree::set_root_value`]: ../tree/trait.Tree.html#method.set_root_value " "
+    pub fn into_root_value(mut self) -> NodeValue<B, L> {
+        self.storage.remove(&self.root).value.into_value()
+    }
+    /// Returns a reference to the first node, in depth-first pre-order, the value of which satisfies the specified predicate, or `None` if there's no such node.
+    ///
+    /// This is the same as [`Tree::find`], but returns a reference specific to freeform trees.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::FreeformTree;
+    ///
+    /// let mut tree = FreeformTree::<_>::new(0);
+    /// tree.root_mut().make_branch(vec![1, 2, 3]).unwrap();
+    /// let found = tree.find(|value| *value.into_inner() > 1).unwrap();
+    /// // The found node is a freeform tree node, so its specific methods can be used right away.
+    /// assert_eq!(*found.prev_sibling().unwrap().value().into_inner(), 1);
+    /// ```
+    ///
+    /// [`Tree::find`]: ../tree/trait.Tree.html#method.find " "
+    pub fn find(
+        &self,
+        predicate: impl FnMut(NodeValue<&'_ B, &'_ L>) -> bool,
+    ) -> Option<NodeRef<'_, B, L, K, S>> {
+        Tree::find(self, predicate).map(|node| unsafe {
+            // SAFETY: the key was just found in the tree
+            NodeRef::new_raw_unchecked(self, node.into_raw_key())
+        })
+    }
     /// Returns the number of nodes in the tree.
     pub fn num_nodes(&self) -> usize {
         self.storage.len()
@@ -128,22 +217,138 @@ where
     }
     /// Reserves capacity for at least additional more elements to be inserted in the given storage. The storage may reserve more space to avoid frequent reallocations. After calling `reserve`, capacity will be greater than or equal to `self.len()` + `additional`. Does nothing if capacity is already sufficient.
     pub fn reserve(&mut self, additional: usize) {
-        self.storage.reserve(additional)
+        self.storage.reserve(additional);
+    }
+    /// Adds nodes to the tree in bulk, each as the last child of the node with the specified raw key, using the provided closure to convert the payloads of leaf nodes which become branch nodes. Returns the number of nodes added.
+    ///
+    /// Storage space is reserved once for the lower bound of the iterator's size hint, avoiding the repeated growth which would happen when adding many nodes one by one. Since the nodes are added in the order in which the iterator yields them, nodes added by earlier items can be used as parents by later ones; the key of the last node added to a parent can be retrieved with [`last_child`].
+    ///
+    /// # Errors
+    /// Will fail if a parent key is not present in the tree, returning it along with the payload of the child which was attempted to be added. The nodes added before that remain in the tree, and the rest of the iterator is dropped.
+    ///
+    /// [`last_child`]: struct.NodeRef.html#method.last_child " "
+    pub fn extend_from_with<I>(
+        &mut self,
+        nodes: I,
+        mut leaf_to_branch: impl FnMut(L) -> B,
+    ) -> Result<usize, ExtendFromError<K, L>>
+    where
+        I: IntoIterator<Item = (K, L)>,
+    {
+        let nodes = nodes.into_iter();
+        self.reserve(nodes.size_hint().0);
+        let mut num_added = 0;
+        for (parent_key, child_payload) in nodes {
+            let mut parent = if let Some(parent) = NodeRefMut::new_raw(self, parent_key.clone()) {
+                parent
+            } else {
+                return Err(ExtendFromError {
+                    parent_key,
+                    child_payload,
+                });
+            };
+            if parent.is_leaf() {
+                if parent
+                    .make_branch_with(iter::once(child_payload), &mut leaf_to_branch)
+                    .is_err()
+                {
+                    unsafe {
+                        // SAFETY: we just checked that the node is a leaf
+                        unreachable_debugchecked("failed to turn a leaf node into a branch")
+                    }
+                }
+            } else if parent.try_push_back(child_payload).is_err() {
+                unsafe {
+                    // SAFETY: same as above, the other way around
+                    unreachable_debugchecked("failed to add a child to a branch node")
+                }
+            }
+            num_added += 1;
+        }
+        Ok(num_added)
     }
     /// Shrinks the capacity of the storage as much as possible.
     ///
     /// It will drop down as close as possible to the current length, though dynamically allocated storages may not always reallocate exactly as much as it is needed to store all elements and none more.
     pub fn shrink_to_fit(&mut self) {
-        self.storage.shrink_to_fit()
+        self.storage.shrink_to_fit();
+    }
+}
+impl<D, K, S> FreeformTree<D, D, K, S>
+where
+    S: Storage<Element = Node<D, D, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Adds nodes to the tree in bulk, each as the last child of the node with the specified raw key, keeping the payloads of leaf nodes which become branch nodes, which is why *this method is only available when the payload for leaf nodes and branch nodes is the same.* Returns the number of nodes added.
+    ///
+    /// See [`extend_from_with`] for the details.
+    ///
+    /// # Errors
+    /// Will fail if a parent key is not present in the tree, returning it along with the payload of the child which was attempted to be added. The nodes added before that remain in the tree, and the rest of the iterator is dropped.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::FreeformTree;
+    ///
+    /// let mut tree = FreeformTree::<_>::new("/");
+    /// let root = tree.root().into_raw_key();
+    /// tree.extend_from(vec![(root, "bin"), (root, "etc"), (root, "home")]).unwrap();
+    /// // The key of the last added child of a node can be looked up to add children to it.
+    /// let home = tree.root().last_child().unwrap().into_raw_key();
+    /// tree.extend_from(vec![(home, "alice"), (home, "bob")]).unwrap();
+    /// assert_eq!(tree.num_nodes(), 6);
+    /// // Unknown parents are reported back.
+    /// let error = tree.extend_from(vec![(100, "nowhere")]).unwrap_err();
+    /// assert_eq!(error.child_payload, "nowhere");
+    /// ```
+    ///
+    /// [`extend_from_with`]: #method.extend_from_with " "
+    pub fn extend_from<I>(&mut self, nodes: I) -> Result<usize, ExtendFromError<K, D>>
+    where
+        I: IntoIterator<Item = (K, D)>,
+    {
+        self.extend_from_with(nodes, convert::identity)
     }
 }
 impl<B, L, S> FreeformTree<B, L, usize, SparseStorage<Node<B, L, usize>, S>>
 where
     S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
 {
+    /// Removes all nodes from the tree and replaces them with a new root leaf node with the specified payload, keeping the capacity of the storage, so that a tree which is rebuilt over and over again, such as once per frame, only allocates memory when it grows beyond its previous size.
+    ///
+    /// All keys to the old nodes become invalid, including the one to the old root node. If the storage has holes, they are removed first, like [`defragment`] does, which makes this slower than clearing a tree which had no nodes removed.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::FreeformTree;
+    ///
+    /// let mut tree = FreeformTree::<_>::new(0);
+    /// tree.root_mut().make_branch(vec![1, 2, 3]).unwrap();
+    /// // Removing a node leaves a hole, which is taken care of as well.
+    /// tree.root_mut().first_child_mut().unwrap().try_remove_leaf().unwrap();
+    /// let capacity = tree.capacity();
+    ///
+    /// tree.clear(4);
+    /// assert!(tree.root().is_leaf());
+    /// assert_eq!(*tree.root().value().into_inner(), 4);
+    /// assert_eq!(tree.num_nodes(), 1);
+    /// assert_eq!(tree.num_holes(), 0);
+    /// assert_eq!(tree.capacity(), capacity);
+    /// // The tree can be built up again right away.
+    /// tree.root_mut().make_branch(vec![5, 6]).unwrap();
+    /// assert_eq!(tree.num_nodes(), 3);
+    /// ```
+    ///
+    /// [`defragment`]: #method.defragment " "
+    pub fn clear(&mut self, root: L) {
+        self.root = clear_storage(&mut self.storage, unsafe {
+            // SAFETY: the old root is removed along with all other nodes
+            Node::root(root)
+        });
+    }
     /// Removes all holes from the sparse storage.
     ///
-    /// Under the hood, this uses `defragment_and_fix`. It's not possible to defragment without fixing the indicies, as that might cause undefined behavior.
+    /// Nodes which are stored past the new end of the storage are moved into the holes, which changes their keys, and the links between the nodes are fixed along the way.
     ///
     /// # Example
     /// ```rust
@@ -181,7 +386,74 @@ where
     /// assert_eq!(tree.num_holes(), 0);
     /// ```
     pub fn defragment(&mut self) {
-        self.storage.defragment_and_fix()
+        self.defragment_with(|_, _| {});
+    }
+    /// Removes all holes from the sparse storage like [`defragment`] does, calling the specified closure with the old and new key of every node which was moved. This allows keys which were stored outside of the tree to be updated instead of becoming invalid.
+    ///
+    /// [`defragment`]: #method.defragment " "
+    pub fn defragment_with(&mut self, on_move: impl FnMut(usize, usize)) {
+        defragment_tree(self, |tree| (&mut tree.storage, &mut tree.root), on_move);
+    }
+    /// Removes the nodes which are in the storage but cannot be reached from the root node, returning the number of nodes which were removed.
+    ///
+    /// Such nodes never appear when the tree is only modified through its safe interface, but can be left behind by code which works with raw keys or by operations which panic halfway through adding nodes, after which they would stay in the storage forever without any way to access them. The keys of all reachable nodes stay the same, and the storage is shrunk to end after the last of them, which also removes the holes at the end of the storage.
+    ///
+    /// The tree is walked once to find the reachable nodes and, if there are any other ones, the reachable nodes are moved into a new storage, which takes *O*(*n*) time, where *n* is the number of slots in the storage, including the holes.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::panic::{self, AssertUnwindSafe};
+    /// use charcoal::{freeform_tree::FreeformTree, repr::TreeRepr, Tree};
+    ///
+    /// let mut tree = FreeformTree::<_>::new(0);
+    /// tree.root_mut().make_branch(vec![1, 2]).unwrap();
+    /// // The empty branch node makes building the replacement panic after its children were added.
+    /// let key = tree.root().first_child().unwrap().into_raw_key();
+    /// let result = panic::catch_unwind(AssertUnwindSafe(|| {
+    ///     tree.replace_subtree(key, TreeRepr::Branch(3, vec![
+    ///         TreeRepr::Leaf(4),
+    ///         TreeRepr::Branch(5, vec![]),
+    ///     ]))
+    /// }));
+    /// assert!(result.is_err());
+    /// assert_eq!(tree.num_nodes() - tree.num_holes(), 4);
+    ///
+    /// assert_eq!(tree.collect_unreachable(), 1);
+    /// assert_eq!(tree.num_nodes() - tree.num_holes(), 3);
+    /// assert_eq!(tree.validate(), Ok(()));
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+    pub fn collect_unreachable(&mut self) -> usize {
+        collect_unreachable_in_tree(self, |tree| &mut tree.storage)
+    }
+    /// Applies the specified closures to the payloads of all nodes of the tree, the first one for branch nodes and the second one for leaf nodes, without changing the structure of the tree.
+    ///
+    /// This shadows [`Tree::map_values_mut`]: if the storage has no holes, the nodes are processed in the order in which they're stored rather than by walking the tree, which makes bulk updates of the payloads friendlier to the cache. If there are holes, the tree is walked in depth-first pre-order, as with the trait method. Either way, this takes *O*(*n*) time and doesn't allocate memory. Nodes which are in the storage but [cannot be reached from the root node] are only visited if there are no holes.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::freeform_tree::FreeformTree;
+    ///
+    /// let mut tree = FreeformTree::<_>::new((0, false));
+    /// tree.root_mut().make_branch(vec![(1, false), (2, false)]).unwrap();
+    /// tree.map_values_mut(|(_, dirty)| *dirty = true, |(value, _)| *value *= 10);
+    /// let root = tree.root();
+    /// assert_eq!(root.value().into_inner(), &(0, true));
+    /// let values = root.children().unwrap().map(|child| *child.value().into_inner()).collect::<Vec<_>>();
+    /// assert_eq!(values, [(10, false), (20, false)]);
+    /// ```
+    ///
+    /// [`Tree::map_values_mut`]: ../tree/trait.Tree.html#method.map_values_mut " "
+    /// [cannot be reached from the root node]: #method.collect_unreachable " "
+    pub fn map_values_mut(&mut self, branch_fn: impl FnMut(&mut B), leaf_fn: impl FnMut(&mut L)) {
+        map_values_in_tree(
+            self,
+            |tree| &mut tree.storage,
+            |node| node.value.as_mut().into_value(),
+            branch_fn,
+            leaf_fn,
+        );
     }
     /// Returns the number of holes in the storage. This operation returns immediately instead of looping through the entire storage, since the sparse storage automatically tracks the number of holes it creates and destroys.
     ///
@@ -203,6 +475,52 @@ where
     }
 }
 
+#[cfg(feature = "allocator_api")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "allocator_api")))]
+impl<B, L, A> FreeformTree<B, L, usize, SparseVecIn<Node<B, L, usize>, A>>
+where
+    A: Allocator,
+{
+    /// Creates a freeform tree with the specified value for the root node, storing the nodes in the specified allocator.
+    ///
+    /// See the [`allocator`] module for an example.
+    ///
+    /// [`allocator`]: ../allocator/index.html " "
+    pub fn new_in(root: L, alloc: A) -> Self {
+        Self::with_capacity_in(0, root, alloc)
+    }
+    /// Creates a freeform tree with the specified value for the root node and the specified capacity for the storage, storing the nodes in the specified allocator.
+    pub fn with_capacity_in(capacity: usize, root: L, alloc: A) -> Self {
+        let mut storage = SparseVecIn::with_capacity_in(capacity, alloc);
+        let root = storage.add(unsafe {
+            // SAFETY: there isn't a root there yet
+            Node::root(root)
+        });
+        Self { storage, root }
+    }
+}
+impl<B, L, K, S> FreeformTree<B, L, K, S>
+where
+    S: StableStorage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Returns a reference to the node with the specified key, or `None` if there's no such node in the tree.
+    ///
+    /// Since the storage is a [`StableStorage`], a key obtained from a node stays valid and keeps leading to that node until the node is removed, regardless of changes to other nodes.
+    ///
+    /// [`StableStorage`]: ../trait.StableStorage.html " "
+    pub fn get_by_key(&self, key: K) -> Option<NodeRef<'_, B, L, K, S>> {
+        NodeRef::new_raw(self, key)
+    }
+    /// Returns a *mutable* reference to the node with the specified key, or `None` if there's no such node in the tree.
+    ///
+    /// Since the storage is a [`StableStorage`], a key obtained from a node stays valid and keeps leading to that node until the node is removed, regardless of changes to other nodes.
+    ///
+    /// [`StableStorage`]: ../trait.StableStorage.html " "
+    pub fn get_mut_by_key(&mut self, key: K) -> Option<NodeRefMut<'_, B, L, K, S>> {
+        NodeRefMut::new_raw(self, key)
+    }
+}
 impl<B, L, K, S> Default for FreeformTree<B, L, K, S>
 where
     L: Default,
@@ -0,0 +1,117 @@
+use core::{cmp::Ordering, fmt::Debug};
+use alloc::vec::Vec;
+use crate::storage::Storage;
+use super::{FreeformTree, Node, NodeData, NodeRef};
+
+impl<B, L, K, S> FreeformTree<B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Sorts the children of every branch node with the specified comparator, bringing the tree into a canonical form in which the order of the children no longer depends on the order in which they were added.
+    ///
+    /// This is useful for trees in which the children of a node are a set rather than a sequence, which need to be brought into a canonical form before being hashed or compared with other trees. The sort is stable, so children which compare as equal keep their relative order.
+    ///
+    /// The nodes are processed bottom-up, in depth-first post-order, so the subtrees of the children are already canonical by the time the children themselves are sorted. This means that a comparator which looks at the whole subtrees of the nodes it compares, rather than just their payloads, produces a fully canonical form, in which trees that only differ in the order of children become identical. The tree is walked iteratively, and only one buffer for the keys of the children of a node is allocated. The payloads are not moved in memory and all keys stay valid.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::freeform_tree::FreeformTree;
+    ///
+    /// let mut first = FreeformTree::<_>::new(0);
+    /// let mut root = first.root_mut();
+    /// root.make_branch(vec![3, 1]).unwrap();
+    /// root.first_child_mut().unwrap().make_branch(vec![5, 4]).unwrap();
+    ///
+    /// let mut second = FreeformTree::<_>::new(0);
+    /// let mut root = second.root_mut();
+    /// root.make_branch(vec![1, 3]).unwrap();
+    /// root.last_child_mut().unwrap().make_branch(vec![4, 5]).unwrap();
+    /// assert_ne!(first.to_repr(), second.to_repr());
+    ///
+    /// first.canonicalize(|a, b| a.value().into_inner().cmp(b.value().into_inner()));
+    /// second.canonicalize(|a, b| a.value().into_inner().cmp(b.value().into_inner()));
+    /// assert_eq!(first.to_repr(), second.to_repr());
+    /// ```
+    pub fn canonicalize(
+        &mut self,
+        mut cmp: impl FnMut(NodeRef<'_, B, L, K, S>, NodeRef<'_, B, L, K, S>) -> Ordering,
+    ) {
+        let mut children = Vec::new();
+        let mut key = self.first_in_postorder(self.root.clone());
+        loop {
+            if let NodeData::Branch { first_child, .. } = &self.node(&key).value {
+                children.clear();
+                let mut current_child = first_child.clone();
+                loop {
+                    children.push(current_child.clone());
+                    match &self.node(&current_child).next_sibling {
+                        Some(next_sibling) => current_child.clone_from(next_sibling),
+                        None => break,
+                    }
+                }
+                let tree = &*self;
+                children.sort_by(|a, b| {
+                    let (a, b) = unsafe {
+                        // SAFETY: the keys were taken from the links between the nodes
+                        (
+                            NodeRef::new_raw_unchecked(tree, a.clone()),
+                            NodeRef::new_raw_unchecked(tree, b.clone()),
+                        )
+                    };
+                    cmp(a, b)
+                });
+                self.relink_children(&key, &children);
+            }
+            // Sorting the children of a node does not change its own links, so the walk can
+            // continue from it.
+            let node = self.node(&key);
+            key = match (&node.next_sibling, &node.parent) {
+                (Some(next_sibling), _) => self.first_in_postorder(next_sibling.clone()),
+                (None, Some(parent)) => parent.clone(),
+                (None, None) => break,
+            };
+        }
+    }
+
+    fn node(&self, key: &K) -> &Node<B, L, K> {
+        unsafe {
+            // SAFETY: keys are only taken from the links between the nodes
+            self.storage.get_unchecked(key)
+        }
+    }
+    fn node_mut(&mut self, key: &K) -> &mut Node<B, L, K> {
+        unsafe {
+            // SAFETY: as above
+            self.storage.get_unchecked_mut(key)
+        }
+    }
+    /// Returns the key of the first node in depth-first post-order in the subtree of the specified node.
+    fn first_in_postorder(&self, mut key: K) -> K {
+        while let NodeData::Branch { first_child, .. } = &self.node(&key).value {
+            key = first_child.clone();
+        }
+        key
+    }
+    /// Replaces the sibling links of the children of the specified branch node to put them in the specified order.
+    fn relink_children(&mut self, parent: &K, children: &[K]) {
+        for (i, child_key) in children.iter().enumerate() {
+            let child = self.node_mut(child_key);
+            child.prev_sibling = i.checked_sub(1).map(|i| children[i].clone());
+            child.next_sibling = children.get(i + 1).cloned();
+        }
+        if let NodeData::Branch {
+            first_child,
+            last_child,
+            ..
+        } = &mut self.node_mut(parent).value
+        {
+            if let (Some(new_first_child), Some(new_last_child)) =
+                (children.first(), children.last())
+            {
+                first_child.clone_from(new_first_child);
+                last_child.clone_from(new_last_child);
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use core::{fmt::Debug, iter::Empty};
+use core::{fmt::Debug, iter::{self, Empty}};
 use crate::{
     storage::Storage,
     traversal::{
@@ -8,12 +8,14 @@ use crate::{
         CursorResult,
         CursorDirectionError,
     },
+    util::unreachable_debugchecked,
     NodeValue,
     TryRemoveBranchError,
     TryRemoveLeafError,
     TryRemoveChildrenError,
+    TryAddChildError,
 };
-use super::{FreeformTree, Node, NodeRef, NodeRefMut};
+use super::{FreeformTree, Node, NodeData, NodeRef, NodeRefMut};
 
 impl<B, L, K, S> Traversable for FreeformTree<B, L, K, S>
 where
@@ -36,14 +38,15 @@ where
         let node = NodeRef::new_raw(self, cursor)
             .expect("the node specified by the cursor does not exist");
         match direction {
-            VisitorDirection::Parent => node.parent().map(NodeRef::into_raw_key).ok_or(error),
-            VisitorDirection::NextSibling => {
-                node.next_sibling().map(NodeRef::into_raw_key).ok_or(error)
-            }
-            VisitorDirection::Child(num) => node
-                .children_keys()
-                .and_then(|mut x| x.nth(num as usize))
+            VisitorDirection::Parent => node.parent_key().cloned().ok_or(error),
+            VisitorDirection::NthAncestor(n) => (0..n)
+                .try_fold(node, |ancestor, _| ancestor.parent())
+                .map(NodeRef::into_raw_key)
                 .ok_or(error),
+            VisitorDirection::Root => Ok(self.root.clone()),
+            VisitorDirection::NextSibling => node.next_sibling_key().cloned().ok_or(error),
+            VisitorDirection::PrevSibling => node.prev_sibling_key().cloned().ok_or(error),
+            VisitorDirection::Child(num) => node.nth_child_key(num as usize).ok_or(error),
             VisitorDirection::SetTo(new_cursor) => {
                 if self.storage.contains_key(&new_cursor) {
                     Ok(new_cursor)
@@ -68,20 +71,22 @@ where
     fn parent_of(&self, cursor: &Self::Cursor) -> Option<Self::Cursor> {
         let node_ref = NodeRef::new_raw(self, cursor.clone())
             .unwrap_or_else(|| panic!("invalid cursor: {:?}", cursor));
-        node_ref.parent().map(NodeRef::into_raw_key)
+        node_ref.parent_key().cloned()
     }
     #[track_caller]
     fn num_children_of(&self, cursor: &Self::Cursor) -> usize {
         let node_ref = NodeRef::new_raw(self, cursor.clone())
             .unwrap_or_else(|| panic!("invalid cursor: {:?}", cursor));
-        node_ref.children_keys().map_or(0, Iterator::count)
+        node_ref.num_children()
     }
     #[track_caller]
     fn nth_child_of(&self, cursor: &Self::Cursor, child_num: usize) -> Option<Self::Cursor> {
         NodeRef::new_raw(self, cursor.clone())
             .unwrap_or_else(|| panic!("invalid cursor: {:?}", cursor))
-            .children_keys()
-            .and_then(|mut x| x.nth(child_num as usize))
+            .nth_child_key(child_num)
+    }
+    fn contains_cursor(&self, cursor: &Self::Cursor) -> bool {
+        self.storage.contains_key(cursor)
     }
 }
 impl<B, L, K, S> TraversableMut for FreeformTree<B, L, K, S>
@@ -90,6 +95,7 @@ where
     K: Clone + Debug + Eq,
 {
     const CAN_REMOVE_INDIVIDUAL_CHILDREN: bool = true;
+    const CAN_ADD_INDIVIDUAL_CHILDREN: bool = true;
     type PackedChildren = Empty<L>;
 
     #[track_caller]
@@ -134,4 +140,39 @@ where
             .unwrap_or_else(|| panic!("invalid cursor: {:?}", cursor));
         node_ref.try_remove_children_with(branch_to_leaf, collector)
     }
+    #[track_caller]
+    fn try_add_child<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        child_payload: Self::Leaf,
+        leaf_to_branch: LtB,
+    ) -> Result<Self::Cursor, TryAddChildError<Self::Leaf>> {
+        let mut node_ref = NodeRefMut::new_raw(self, cursor.clone())
+            .unwrap_or_else(|| panic!("invalid cursor: {:?}", cursor));
+        let result = if node_ref.is_leaf() {
+            node_ref
+                .make_branch_with(iter::once(child_payload), leaf_to_branch)
+                .is_ok()
+        } else {
+            node_ref.try_push_back(child_payload).is_ok()
+        };
+        if !result {
+            unsafe {
+                // SAFETY: we just did a leaf check
+                unreachable_debugchecked("failed to add a child after a leaf check")
+            }
+        }
+        let node = unsafe {
+            // SAFETY: we did a key check when creating the node reference
+            self.storage.get_unchecked(cursor)
+        };
+        match &node.value {
+            // The new child is always the last one
+            NodeData::Branch { last_child, .. } => Ok(last_child.clone()),
+            NodeData::Leaf(..) => unsafe {
+                // SAFETY: the node was just turned into a branch
+                unreachable_debugchecked("the node was just turned into a branch")
+            },
+        }
+    }
 }
@@ -0,0 +1,153 @@
+use core::fmt::Debug;
+use crate::{
+    storage::Storage,
+    tree::{self, Tree, TreeStats, ValidationError, validate_with},
+    NodeValue,
+};
+use super::{FreeformTree, Node, NodeData};
+
+impl<B, L, K, S> Tree for FreeformTree<B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn num_nodes(&self) -> usize {
+        self.storage.len()
+    }
+    fn reserve_nodes(&mut self, additional: usize) {
+        self.storage.reserve(additional);
+    }
+    fn stats(&self) -> TreeStats {
+        tree::stats_with_capacity(self, self.storage.capacity())
+    }
+    fn contains_key(&self, key: &Self::Cursor) -> bool {
+        self.storage.contains_key(key)
+    }
+    fn node_value(&self, key: &Self::Cursor) -> Option<NodeValue<&'_ Self::Branch, &'_ Self::Leaf>> {
+        self.storage
+            .get(key)
+            .map(|node| node.value.as_ref().into_value())
+    }
+    fn node_value_mut(
+        &mut self,
+        key: &Self::Cursor,
+    ) -> Option<NodeValue<&'_ mut Self::Branch, &'_ mut Self::Leaf>> {
+        self.storage
+            .get_mut(key)
+            .map(|node| node.value.as_mut().into_value())
+    }
+    fn parent_key(&self, key: &Self::Cursor) -> Option<Self::Cursor> {
+        self.storage.get(key)?.parent.clone()
+    }
+    fn first_child_key(&self, key: &Self::Cursor) -> Option<Self::Cursor> {
+        match &self.storage.get(key)?.value {
+            NodeData::Branch { first_child, .. } => Some(first_child.clone()),
+            NodeData::Leaf(..) => None,
+        }
+    }
+    fn next_sibling_key(&self, key: &Self::Cursor) -> Option<Self::Cursor> {
+        self.storage.get(key)?.next_sibling.clone()
+    }
+    fn validate(&self) -> Result<(), ValidationError<Self::Cursor>> {
+        validate_with(self, |key| {
+            let node = self
+                .storage
+                .get(key)
+                .expect("validate_with only checks nodes which are present in the storage");
+            // Check that the sibling links point both ways
+            if let Some(next_sibling_key) = &node.next_sibling {
+                let next_sibling = self.storage.get(next_sibling_key).ok_or_else(|| {
+                    ValidationError::DanglingKey {
+                        node: key.clone(),
+                        key: next_sibling_key.clone(),
+                    }
+                })?;
+                if next_sibling.prev_sibling.as_ref() != Some(key) {
+                    return Err(ValidationError::SiblingMismatch {
+                        node: key.clone(),
+                        sibling: next_sibling_key.clone(),
+                    });
+                }
+            }
+            if let Some(prev_sibling_key) = &node.prev_sibling {
+                let prev_sibling = self.storage.get(prev_sibling_key).ok_or_else(|| {
+                    ValidationError::DanglingKey {
+                        node: key.clone(),
+                        key: prev_sibling_key.clone(),
+                    }
+                })?;
+                if prev_sibling.next_sibling.as_ref() != Some(key) {
+                    return Err(ValidationError::SiblingMismatch {
+                        node: key.clone(),
+                        sibling: prev_sibling_key.clone(),
+                    });
+                }
+            }
+            if let NodeData::Branch {
+                first_child,
+                last_child,
+                child_count,
+                ..
+            } = &node.value
+            {
+                let first_child_node =
+                    self.storage
+                        .get(first_child)
+                        .ok_or_else(|| ValidationError::DanglingKey {
+                            node: key.clone(),
+                            key: first_child.clone(),
+                        })?;
+                if let Some(prev_sibling) = &first_child_node.prev_sibling {
+                    return Err(ValidationError::SiblingMismatch {
+                        node: first_child.clone(),
+                        sibling: prev_sibling.clone(),
+                    });
+                }
+                if !self.storage.contains_key(last_child) {
+                    return Err(ValidationError::DanglingKey {
+                        node: key.clone(),
+                        key: last_child.clone(),
+                    });
+                }
+                // Find the end of the sibling chain, bounding the walk to avoid looping on cycles
+                let mut chain_end = first_child.clone();
+                let mut chain_len = 1;
+                for _ in 0..self.storage.len() {
+                    let next = unsafe {
+                        // SAFETY: the first child was checked above, and every next sibling is
+                        // checked below before moving to it
+                        self.storage.get_unchecked(&chain_end)
+                    }
+                    .next_sibling
+                    .clone();
+                    match next {
+                        Some(next) if !self.storage.contains_key(&next) => {
+                            return Err(ValidationError::DanglingKey {
+                                node: chain_end,
+                                key: next,
+                            });
+                        }
+                        Some(next) => chain_end = next,
+                        None => break,
+                    }
+                    chain_len += 1;
+                }
+                if &chain_end != last_child {
+                    return Err(ValidationError::LastChildMismatch {
+                        parent: key.clone(),
+                        expected_last_child: chain_end,
+                        found_last_child: last_child.clone(),
+                    });
+                }
+                if chain_len != *child_count {
+                    return Err(ValidationError::ChildCountMismatch {
+                        parent: key.clone(),
+                        expected_child_count: chain_len,
+                        found_child_count: *child_count,
+                    });
+                }
+            }
+            Ok(())
+        })
+    }
+}
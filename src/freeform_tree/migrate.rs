@@ -0,0 +1,189 @@
+use core::fmt::Debug;
+use crate::storage::Storage;
+use super::{FreeformTree, Node, NodeData};
+
+impl<B, L, K, S> FreeformTree<B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Clones the tree into a different storage, producing a tree with the same structure and payloads.
+    ///
+    /// This makes it possible to build a tree using a growable storage and then freeze it into a fixed-capacity one which does not need an allocator, such as `ArrayVec`, or to move it into a storage with different key stability guarantees. The keys of the nodes in the new tree are unrelated to the keys of the nodes in the old one.
+    ///
+    /// The tree is copied without using recursion, and no memory is allocated other than by the new storage itself.
+    ///
+    /// # Panics
+    /// Will panic if the new storage runs out of capacity.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::freeform_tree::{FreeformTree, VecFreeformTree};
+    ///
+    /// let mut tree = FreeformTree::<_>::new("Root");
+    /// tree.root_mut().make_branch(vec!["Left", "Right"]).unwrap();
+    /// // Move the tree into a plain Vec without sparse storage.
+    /// let migrated: VecFreeformTree<_> = tree.clone_into_storage();
+    /// assert_eq!(migrated.num_nodes(), 3);
+    /// assert_eq!(migrated.to_repr(), tree.to_repr());
+    /// ```
+    pub fn clone_into_storage<K2, S2>(&self) -> FreeformTree<B, L, K2, S2>
+    where
+        B: Clone,
+        L: Clone,
+        S2: Storage<Element = Node<B, L, K2>, Key = K2>,
+        K2: Clone + Debug + Eq,
+    {
+        let source = |key: &K| unsafe {
+            // SAFETY: keys stored in the tree are guaranteed to be valid
+            self.storage.get_unchecked(key)
+        };
+        let mut storage = S2::new();
+        // The root node is added first so that parent nodes come before their children in the
+        // storage, like in every other tree, which storages that shift keys upon removal rely on.
+        // Until its children are copied, it's a leaf node with a copy of the payload of the
+        // leftmost leaf, and its key is put into the links of branch nodes as a placeholder.
+        let mut leftmost_leaf = &self.root;
+        while let NodeData::Branch { first_child, .. } = &source(leftmost_leaf).value {
+            leftmost_leaf = first_child;
+        }
+        let root = storage.add(unsafe {
+            // SAFETY: the links are filled in as the tree is copied
+            Node::root(match &source(leftmost_leaf).value {
+                NodeData::Leaf(payload) => payload.clone(),
+                NodeData::Branch { .. } => unreachable!("the descent only stops at leaf nodes"),
+            })
+        });
+        let placeholder = root.clone();
+        let copy_value = |key: &K| match &source(key).value {
+            NodeData::Branch {
+                payload,
+                child_count,
+                ..
+            } => NodeData::Branch {
+                payload: payload.clone(),
+                first_child: placeholder.clone(),
+                last_child: placeholder.clone(),
+                // The same number of children is copied right after
+                child_count: *child_count,
+            },
+            NodeData::Leaf(payload) => NodeData::Leaf(payload.clone()),
+        };
+        let copy_node = |storage: &mut S2, key: &K| {
+            storage.add(Node {
+                value: copy_value(key),
+                parent: None,
+                prev_sibling: None,
+                next_sibling: None,
+            })
+        };
+        unsafe {
+            // SAFETY: we just added the root node
+            storage.get_unchecked_mut(&root)
+        }
+        .value = copy_value(&self.root);
+        // Walk both trees in lockstep, copying the children of every branch node as we visit it
+        let (mut old_key, mut new_key) = (self.root.clone(), root.clone());
+        loop {
+            if let NodeData::Branch { first_child, .. } = &source(&old_key).value {
+                let (new_first_child, new_last_child) = copy_children(
+                    &self.storage,
+                    &mut storage,
+                    first_child,
+                    &new_key,
+                    copy_node,
+                );
+                if let NodeData::Branch {
+                    first_child: new_first_child_ref,
+                    last_child: new_last_child_ref,
+                    ..
+                } = &mut unsafe {
+                    // SAFETY: the key was either just added or comes from a link we filled in
+                    storage.get_unchecked_mut(&new_key)
+                }
+                .value
+                {
+                    *new_first_child_ref = new_first_child.clone();
+                    *new_last_child_ref = new_last_child;
+                }
+                old_key = first_child.clone();
+                new_key = new_first_child;
+                continue;
+            }
+            // Climb up until we find a node with a next sibling
+            loop {
+                if old_key == self.root {
+                    return FreeformTree { storage, root };
+                }
+                let new_node = unsafe {
+                    // SAFETY: as above
+                    storage.get_unchecked(&new_key)
+                };
+                if let Some(next_sibling) = &source(&old_key).next_sibling {
+                    old_key = next_sibling.clone();
+                    new_key = new_node
+                        .next_sibling
+                        .clone()
+                        .expect("the copied node has fewer siblings than the original");
+                    break;
+                }
+                old_key = source(&old_key)
+                    .parent
+                    .clone()
+                    .expect("non-root nodes always have a parent");
+                new_key = new_node
+                    .parent
+                    .clone()
+                    .expect("non-root nodes always have a parent");
+            }
+        }
+    }
+}
+
+/// Copies the children of a node, the first of which is specified, into the new storage, linking them to each other and to the specified parent. Returns the keys of the first and last copied child.
+fn copy_children<B, L, K, S, K2, S2>(
+    source: &S,
+    storage: &mut S2,
+    old_first_child: &K,
+    new_parent: &K2,
+    copy_node: impl Fn(&mut S2, &K) -> K2,
+) -> (K2, K2)
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+    S2: Storage<Element = Node<B, L, K2>, Key = K2>,
+    K2: Clone + Debug + Eq,
+{
+    let new_first_child = copy_node(storage, old_first_child);
+    let mut old_child = old_first_child.clone();
+    let mut new_child = new_first_child.clone();
+    loop {
+        unsafe {
+            // SAFETY: we just added this one
+            storage.get_unchecked_mut(&new_child)
+        }
+        .parent = Some(new_parent.clone());
+        let next_sibling = &unsafe {
+            // SAFETY: keys stored in the tree are guaranteed to be valid
+            source.get_unchecked(&old_child)
+        }
+        .next_sibling;
+        let next_sibling = match next_sibling {
+            Some(next_sibling) => next_sibling.clone(),
+            None => return (new_first_child, new_child),
+        };
+        let new_next_sibling = copy_node(storage, &next_sibling);
+        unsafe {
+            // SAFETY: as above
+            storage.get_unchecked_mut(&new_next_sibling)
+        }
+        .prev_sibling = Some(new_child.clone());
+        unsafe {
+            // SAFETY: as above
+            storage.get_unchecked_mut(&new_child)
+        }
+        .next_sibling = Some(new_next_sibling.clone());
+        old_child = next_sibling;
+        new_child = new_next_sibling;
+    }
+}
@@ -46,10 +46,22 @@
 use core::fmt::{self, Formatter, Debug, Display};
 
 mod base;
+#[cfg(feature = "alloc")]
+mod canonical;
 mod impl_traversable;
+mod impl_tree;
+mod into_iter;
+mod migrate;
 mod node;
 mod node_ref;
 mod node_ref_mut;
+#[cfg(feature = "alloc")]
+mod repr;
+mod restructure;
+#[cfg(feature = "alloc")]
+mod transaction;
+#[cfg(feature = "alloc")]
+mod writer;
 
 use node::NodeData;
 pub use node::Node;
@@ -62,6 +74,13 @@ pub use node_ref::{
 };
 pub use node_ref_mut::NodeRefMut;
 pub use base::FreeformTree;
+pub use into_iter::{IntoIter, Drain};
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub use transaction::Transaction;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub use writer::{TreeWriter, PreorderError};
 
 /// The error type produced by [`try_push_back`] and [`try_push_front`], indicating that the node was a leaf node before.
 ///
@@ -83,6 +102,29 @@ impl<T> Display for TryPushError<T> {
         f.pad("try_push_back or try_push_front was attempted at a leaf node")
     }
 }
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl<T: Debug> std::error::Error for TryPushError<T> {}
+
+/// The error type returned by [`extend_from_with`] and [`extend_from`], indicating that one of the parent keys was not present in the tree.
+///
+/// [`extend_from_with`]: struct.FreeformTree.html#method.extend_from_with " "
+/// [`extend_from`]: struct.FreeformTree.html#method.extend_from " "
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ExtendFromError<K, L> {
+    /// The key which was not present in the tree.
+    pub parent_key: K,
+    /// The value of the child node which was attempted to be added, returned back to the caller to avoid dropping it.
+    pub child_payload: L,
+}
+impl<K, L> Display for ExtendFromError<K, L> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad("the parent key was not present in the tree")
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl<K: Debug, L: Debug> std::error::Error for ExtendFromError<K, L> {}
 
 /// A freeform tree which uses a *sparse* `Vec` as backing storage.
 ///
@@ -99,3 +141,41 @@ pub type SparseVecFreeformTree<B, L = B> =
 #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
 #[allow(unused_qualifications)]
 pub type VecFreeformTree<B, L = B> = FreeformTree<B, L, usize, alloc::vec::Vec<Node<B, L, usize>>>;
+/// A freeform tree which uses a *sparse* `Vec` with 32-bit keys as backing storage, halving the size of the links between nodes on 64-bit targets.
+///
+/// See the [`small_key`] module for more.
+///
+/// [`small_key`]: ../small_key/index.html " "
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+#[allow(unused_qualifications)]
+pub type SmallKeyFreeformTree<B, L = B> = FreeformTree<
+    B,
+    L,
+    u32,
+    crate::small_key::SmallKeyStorage<crate::storage::SparseVec<Node<B, L, u32>>>,
+>;
+/// A freeform tree which uses an `ArrayVec` as backing storage, keeping all of its nodes inline without the need for a memory allocator.
+///
+/// The capacity of an `ArrayVec` is specified by its backing array type, which is the first type parameter here: `[Node<B, L, usize>; 16]` makes a tree which can hold up to 16 nodes. Adding nodes beyond that with the regular methods panics, while [`try_new`] and [`try_make_branch`]/[`try_make_branch_with`] return an error instead.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{freeform_tree::{InlineFreeformTree, Node}, TryMakeBranchError};
+///
+/// let mut tree = InlineFreeformTree::<[Node<u32, u32, usize>; 3], u32>::try_new(0).unwrap();
+/// tree.root_mut().try_make_branch(1..3).unwrap();
+/// // The storage is full now, so there is no room for any more children.
+/// let mut root = tree.root_mut();
+/// let mut first_child = root.first_child_mut().unwrap();
+/// assert!(matches!(
+///     first_child.try_make_branch(3..4),
+///     Err(TryMakeBranchError::OutOfCapacity { .. }),
+/// ));
+/// assert!(first_child.is_leaf());
+/// ```
+///
+/// [`try_new`]: struct.FreeformTree.html#method.try_new " "
+/// [`try_make_branch`]: struct.NodeRefMut.html#method.try_make_branch " "
+/// [`try_make_branch_with`]: struct.NodeRefMut.html#method.try_make_branch_with " "
+pub type InlineFreeformTree<A, B, L = B> = FreeformTree<B, L, usize, arrayvec::ArrayVec<A>>;
@@ -1,7 +1,8 @@
-use core::{num::NonZeroIsize, fmt::Debug, hint, convert::TryFrom};
+use core::{num::NonZeroIsize, fmt::Debug, hint, mem, ptr, convert::TryFrom};
 use crate::{
     storage::{ListStorage, MoveFix},
-    util::unreachable_debugchecked,
+    small_key::ListKey,
+    util::{unreachable_debugchecked, abort_on_panic},
     NodeValue,
 };
 
@@ -42,6 +43,7 @@ where
         payload: B,
         first_child: K,
         last_child: K,
+        child_count: usize,
         prev_sibling: Option<K>,
         next_sibling: Option<K>,
         parent: Option<K>,
@@ -51,6 +53,7 @@ where
                 payload,
                 first_child,
                 last_child,
+                child_count,
             },
             parent,
             prev_sibling,
@@ -72,7 +75,7 @@ where
         }
     }
 }
-impl<B, L> MoveFix for Node<B, L, usize> {
+impl<B, L, K: ListKey> MoveFix for Node<B, L, K> {
     unsafe fn fix_shift<S>(storage: &mut S, shifted_from: usize, shifted_by: NonZeroIsize)
     where
         S: ListStorage<Element = Self>,
@@ -84,7 +87,7 @@ impl<B, L> MoveFix for Node<B, L, usize> {
         };
         if fix_starting_from >= storage.len() {
             return;
-        };
+        }
         for i in fix_starting_from..storage.len() {
             let old_index = isize::try_from(i)
                 // SAFETY: not having more than isize::MAX elements is an
@@ -104,24 +107,35 @@ impl<B, L> MoveFix for Node<B, L, usize> {
     where
         S: ListStorage<Element = Self>,
     {
-        match /*unsafe*/ {
-            // SAFETY: index validity is guaranteed for `current_index`.
-            &storage.get_unchecked(current_index).value
-        } {
+        let (previous_key, current_key) =
+            (K::from_index(previous_index), K::from_index(current_index));
+        match /*unsafe*/ &storage.get_unchecked(current_index).value {
             NodeData::Branch { first_child, .. } => {
                 let mut current_child = *first_child;
                 loop {
                     let child = /*unsafe*/ {
                         // SAFETY: index validity is guaranteed for children.
-                        storage.get_unchecked_mut(current_child)
+                        storage.get_unchecked_mut(current_child.into_index())
                     };
-                    child.parent = Some(current_index);
+                    child.parent = Some(current_key);
                     current_child = if let Some(x) = child.next_sibling
                     {x} else {break};
                 }
             },
             NodeData::Leaf {..} => {},
         }
+        if let Some(next_sibling) = /*unsafe*/ {
+            // SAFETY: index validity is guaranteed for `current_index`.
+            storage.get_unchecked(current_index).next_sibling
+        } {
+            let next_sibling = /*unsafe*/ {
+                // SAFETY: index validity is guaranteed for siblings.
+                storage.get_unchecked_mut(next_sibling.into_index())
+            };
+            if next_sibling.prev_sibling == Some(previous_key) {
+                next_sibling.prev_sibling = Some(current_key);
+            }
+        }
         let parent_index = if let Some(index) = /*unsafe*/ {
             // SAFETY: index validity is guaranteed for `current_index`.
             storage.get_unchecked(current_index).parent
@@ -130,10 +144,14 @@ impl<B, L> MoveFix for Node<B, L, usize> {
         } else {
             return;
         };
-        let parent = storage.get_unchecked_mut(parent_index);
-        let first_sibling = {
+        let parent = storage.get_unchecked_mut(parent_index.into_index());
+        let (first_sibling, last_sibling) = {
             match &mut parent.value {
-                NodeData::Branch { first_child, .. } => first_child,
+                NodeData::Branch {
+                    first_child,
+                    last_child,
+                    ..
+                } => (first_child, last_child),
                 NodeData::Leaf { .. } =>
                 /*unsafe*/
                 {
@@ -141,16 +159,19 @@ impl<B, L> MoveFix for Node<B, L, usize> {
                 }
             }
         };
-        if *first_sibling == previous_index {
-            *first_sibling = current_index;
+        if *last_sibling == previous_key {
+            *last_sibling = current_key;
+        }
+        if *first_sibling == previous_key {
+            *first_sibling = current_key;
             return;
         }
         let mut current_sibling = *first_sibling;
         loop {
-            let node = storage.get_unchecked_mut(current_sibling);
+            let node = storage.get_unchecked_mut(current_sibling.into_index());
             let next_sibling = &mut node.next_sibling;
-            if *next_sibling == Some(previous_index) {
-                *next_sibling = Some(current_index);
+            if *next_sibling == Some(previous_key) {
+                *next_sibling = Some(current_key);
                 return;
             }
             if let Some(next_sibling) = next_sibling {
@@ -176,6 +197,8 @@ where
         payload: B,
         first_child: K,
         last_child: K,
+        /// The number of children, cached to avoid walking the sibling chain to count them.
+        child_count: usize,
     },
     Leaf(L),
 }
@@ -189,10 +212,12 @@ where
                 payload,
                 first_child,
                 last_child,
+                child_count,
             } => NodeData::Branch {
                 payload,
                 first_child: first_child.clone(),
                 last_child: last_child.clone(),
+                child_count: *child_count,
             },
             Self::Leaf(x) => NodeData::Leaf(x),
         }
@@ -203,10 +228,12 @@ where
                 payload,
                 first_child,
                 last_child,
+                child_count,
             } => NodeData::Branch {
                 payload,
                 first_child: first_child.clone(),
                 last_child: last_child.clone(),
+                child_count: *child_count,
             },
             Self::Leaf(x) => NodeData::Leaf(x),
         }
@@ -218,4 +245,96 @@ where
             Self::Leaf(x) => NodeValue::Leaf(x),
         }
     }
+    /// Converts a branch node into a leaf node, using the closure to convert the payload.
+    ///
+    /// The specified leaf payload stands in for the payload of the node while the closure runs and is returned afterwards. If the closure panics, the node is left as a leaf node with the stand-in payload instead of being in an invalid state.
+    pub(super) fn make_leaf_with(&mut self, stand_in: L, f: impl FnOnce(B) -> L) -> L {
+        let payload = match mem::replace(self, Self::Leaf(stand_in)) {
+            Self::Branch { payload, .. } => payload,
+            Self::Leaf(..) => unsafe { unreachable_debugchecked("expected a branch node") },
+        };
+        match mem::replace(self, Self::Leaf(f(payload))) {
+            Self::Leaf(stand_in) => stand_in,
+            Self::Branch { .. } => unsafe {
+                unreachable_debugchecked("the node was replaced with a leaf above")
+            },
+        }
+    }
+    /// Converts a branch node into a leaf node in place, using the closure to convert the payload. The links to the children are discarded.
+    ///
+    /// Unlike with [`make_leaf_with`], no payload is available to stand in for the one of the node while the closure runs, so the process is aborted if it panics.
+    ///
+    /// [`make_leaf_with`]: #method.make_leaf_with " "
+    pub(super) fn make_leaf_or_abort(&mut self, f: impl FnOnce(B) -> L) {
+        let old_payload = match self {
+            Self::Branch { payload, .. } => unsafe {
+                // SAFETY: we're overwriting the value right after this
+                ptr::read(payload)
+            },
+            Self::Leaf(..) => unsafe { unreachable_debugchecked("expected a branch node") },
+        };
+        let new_payload = abort_on_panic(|| f(old_payload));
+        unsafe {
+            // SAFETY: the old payload was moved out above
+            ptr::write(self, Self::Leaf(new_payload));
+        }
+    }
+    /// Converts a leaf node into a branch node with one child in place, using the closure to convert the payload. Aborts the process if the closure panics, for the same reason as [`make_leaf_or_abort`].
+    ///
+    /// [`make_leaf_or_abort`]: #method.make_leaf_or_abort " "
+    pub(super) fn make_branch_or_abort(&mut self, only_child: K, f: impl FnOnce(L) -> B) {
+        let old_payload = match self {
+            Self::Leaf(val) => unsafe {
+                // SAFETY: as above
+                ptr::read(val)
+            },
+            Self::Branch { .. } => unsafe { unreachable_debugchecked("expected a leaf node") },
+        };
+        let new_payload = abort_on_panic(|| f(old_payload));
+        unsafe {
+            // SAFETY: as above
+            ptr::write(
+                self,
+                Self::Branch {
+                    payload: new_payload,
+                    first_child: only_child.clone(),
+                    last_child: only_child,
+                    child_count: 1,
+                },
+            );
+        }
+    }
+}
+impl<D, K> NodeData<D, D, K>
+where
+    K: Clone + Debug + Eq,
+{
+    /// Converts a branch node into a leaf node, keeping its payload. The links to the children are discarded.
+    ///
+    /// Unlike [`make_leaf_with`], this doesn't need a stand-in payload, since there is no conversion closure which could panic while the payload is moved out of the node.
+    ///
+    /// [`make_leaf_with`]: #method.make_leaf_with " "
+    pub(super) fn make_leaf_keeping_payload(&mut self) {
+        let (payload, links) = match self {
+            Self::Branch {
+                payload,
+                first_child,
+                last_child,
+                ..
+            } => unsafe {
+                // SAFETY: the node is overwritten right after this, without anything in between
+                // which could panic and drop the moved out values twice
+                (
+                    ptr::read(payload),
+                    (ptr::read(first_child), ptr::read(last_child)),
+                )
+            },
+            Self::Leaf(..) => unsafe { unreachable_debugchecked("expected a branch node") },
+        };
+        unsafe {
+            // SAFETY: see above
+            ptr::write(self, Self::Leaf(payload));
+        }
+        drop(links);
+    }
 }
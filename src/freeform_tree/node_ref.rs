@@ -1,13 +1,15 @@
-use core::{fmt::Debug, iter::FusedIterator};
+use core::{fmt::Debug, ptr, iter::{FusedIterator, Rev}};
 use crate::{
     storage::{Storage, DefaultStorage},
+    traversal::algorithms::depth_of,
+    tree,
     NodeValue,
 };
 use super::{FreeformTree, Node, NodeData};
 
-// A reference to a node in a freeform tree.
+/// A reference to a node in a freeform tree.
 ///
-/// Since this type does not point to the node directly, but rather the tree the node is in and the key of the node in the storage, it can be used to traverse the tree.
+/// Since this type points not only to the node itself, but also to the tree the node is in and the key of the node in the storage, it can be used to traverse the tree. The node is looked up in the storage once, when the reference is created, rather than every time it is accessed.
 #[derive(Debug)]
 pub struct NodeRef<'a, B, L = B, K = usize, S = DefaultStorage<Node<B, L, K>>>
 where
@@ -16,6 +18,8 @@ where
 {
     pub(super) tree: &'a FreeformTree<B, L, K, S>,
     pub(super) key: K,
+    /// The node itself, looked up once when the reference is created, since the tree cannot change while it's borrowed.
+    node: &'a Node<B, L, K>,
 }
 impl<'a, B, L, K, S> NodeRef<'a, B, L, K, S>
 where
@@ -23,22 +27,30 @@ where
     K: Clone + Debug + Eq,
 {
     /// Creates a new `NodeRef` pointing to the specified key in the storage, or `None` if it's out of bounds.
+    ///
+    /// The node is looked up right away rather than when the reference is used. For trees backed by sparse storage, this means that the key of a removed node whose slot is still a hole makes this method panic, instead of producing a reference which panics once it's accessed. See the [`Tree`] trait for more on holes.
+    ///
+    /// # Panics
+    /// Will panic if the key points to a hole in sparse storage.
+    ///
+    /// [`Tree`]: ../tree/trait.Tree.html#sparse-storage " "
     pub fn new_raw(tree: &'a FreeformTree<B, L, K, S>, key: K) -> Option<Self> {
-        if tree.storage.contains_key(&key) {
-            Some(unsafe {
-                // SAFETY: we just did a key check
-                Self::new_raw_unchecked(tree, key)
-            })
-        } else {
-            None
-        }
+        let node = tree.storage.get(&key)?;
+        Some(Self { tree, key, node })
     }
     /// Creates a new `NodeRef` pointing to the specified key in the storage without doing bounds checking.
     ///
     /// # Safety
     /// Causes *immediate* undefined behavior if the specified key is not present in the storage.
     pub unsafe fn new_raw_unchecked(tree: &'a FreeformTree<B, L, K, S>, key: K) -> Self {
-        Self { tree, key }
+        debug_assert!(
+            tree.storage.contains_key(&key),
+            "\
+debug key check failed: tried to reference key {:?} which is not present in the storage",
+            &key,
+        );
+        let node = tree.storage.get_unchecked(&key);
+        Self { tree, key, node }
     }
     /// Returns a reference the raw storage key for the node.
     pub fn raw_key(&self) -> &K {
@@ -97,6 +109,69 @@ where
     pub fn is_root(&self) -> bool {
         self.node().parent.is_none()
     }
+    /// Returns `true` if the node is an ancestor of the specified node, i.e. its parent, the parent of its parent and so on up to the root; `false` otherwise. A node is not an ancestor of itself, and nodes in different trees are never ancestors of each other.
+    ///
+    /// Only the parents of the specified node are visited, which takes *O*(*d*) time without allocating memory, where *d* is the depth of the specified node.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::freeform_tree::FreeformTree;
+    ///
+    /// let mut tree = FreeformTree::<_>::new("CEO");
+    /// let mut root = tree.root_mut();
+    /// root.make_branch(vec!["CTO", "CFO"]).unwrap();
+    /// root.first_child_mut().unwrap().make_branch(vec!["Dev"]).unwrap();
+    ///
+    /// let root = tree.root();
+    /// let cto = root.first_child().unwrap();
+    /// let cfo = root.last_child().unwrap();
+    /// let dev = cto.first_child().unwrap();
+    /// assert!(root.is_ancestor_of(&dev));
+    /// assert!(cto.is_ancestor_of(&dev));
+    /// assert!(!cfo.is_ancestor_of(&dev));
+    /// assert!(!dev.is_ancestor_of(&dev));
+    /// assert!(dev.is_descendant_of(&root));
+    /// ```
+    pub fn is_ancestor_of(&self, other: &Self) -> bool {
+        if !ptr::eq(self.tree, other.tree) {
+            return false;
+        }
+        let mut ancestor = other.parent();
+        while let Some(node) = ancestor {
+            if node.key == self.key {
+                return true;
+            }
+            ancestor = node.parent();
+        }
+        false
+    }
+    /// Returns `true` if the node is a descendant of the specified node, i.e. one of its children, one of the children of those and so on; `false` otherwise. This is the same as calling [`is_ancestor_of`] with the nodes swapped, and walks the parents of this node.
+    ///
+    /// [`is_ancestor_of`]: #method.is_ancestor_of " "
+    pub fn is_descendant_of(&self, other: &Self) -> bool {
+        other.is_ancestor_of(self)
+    }
+    /// Returns the depth of the node, i.e. the number of levels between it and the root node, with the root node being at depth 0.
+    ///
+    /// The depth is not stored in the nodes, so it's found by walking the parents of the node, which takes *O*(*d*) time, where *d* is the depth.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::freeform_tree::FreeformTree;
+    ///
+    /// let mut tree = FreeformTree::<_>::new("CEO");
+    /// let mut root = tree.root_mut();
+    /// root.make_branch(vec!["CTO"]).unwrap();
+    /// root.first_child_mut().unwrap().make_branch(vec!["Dev"]).unwrap();
+    ///
+    /// let root = tree.root();
+    /// let dev = root.first_child().unwrap().first_child().unwrap();
+    /// assert_eq!(root.depth(), 0);
+    /// assert_eq!(dev.depth(), 2);
+    /// ```
+    pub fn depth(&self) -> usize {
+        depth_of(self.tree, &self.key)
+    }
     /// Returns `true` if the node is a *leaf*, i.e. does not have child nodes; `false` otherwise.
     pub fn is_leaf(&self) -> bool {
         match &self.node().value {
@@ -115,13 +190,124 @@ where
     pub fn value(&self) -> NodeValue<&'a B, &'a L> {
         self.node().value.as_ref().into_value()
     }
+    /// Returns the number of children of the node, which is zero for leaf nodes.
+    ///
+    /// The number of children is stored in branch nodes, so this takes constant time.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::FreeformTree;
+    ///
+    /// let mut tree = FreeformTree::<_>::new(0);
+    /// tree.root_mut().make_branch(vec![1, 2]).unwrap();
+    /// tree.root_mut().try_push_back(3).unwrap();
+    /// assert_eq!(tree.root().num_children(), 3);
+    /// // The size hint of the children iterator is exact as well.
+    /// assert_eq!(tree.root().children().unwrap().size_hint(), (3, Some(3)));
+    /// ```
+    pub fn num_children(&self) -> usize {
+        match &self.node().value {
+            NodeData::Branch { child_count, .. } => *child_count,
+            NodeData::Leaf(..) => 0,
+        }
+    }
     /// Returns an iterator over references to the children of the node, or `None` if the node is a leaf node.
+    ///
+    /// The iterator is double-ended, so the children can be visited from the last one to the first one, which is what [`children_rev`] does.
+    ///
+    /// [`children_rev`]: #method.children_rev " "
     pub fn children(&self) -> Option<NodeChildrenIter<'_, B, L, K, S>> {
-        self.first_child().map(Self::siblings)
+        self.children_keys().map(NodeSiblingsIter)
+    }
+    /// Returns an iterator over references to the children of the node in reverse order, starting from the last child, or `None` if the node is a leaf node.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::FreeformTree;
+    ///
+    /// let mut tree = FreeformTree::<_>::new(0);
+    /// tree.root_mut().make_branch(vec![1, 2, 3]).unwrap();
+    /// let payloads = tree
+    ///     .root()
+    ///     .children_rev()
+    ///     .unwrap()
+    ///     .map(|child| *child.value().into_inner())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(payloads, [3, 2, 1]);
+    /// ```
+    pub fn children_rev(&self) -> Option<Rev<NodeChildrenIter<'_, B, L, K, S>>> {
+        self.children().map(Iterator::rev)
+    }
+    /// Returns a reference to the child of the node with the specified index, or `None` if the node is a leaf node or the index is out of bounds.
+    ///
+    /// Since the number of children is stored in branch nodes, the search starts from the last child if that's closer to the requested one, visiting at most half of the children.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::FreeformTree;
+    ///
+    /// let mut tree = FreeformTree::<_>::new(0);
+    /// tree.root_mut().make_branch(vec![1, 2, 3, 4]).unwrap();
+    /// assert_eq!(*tree.root().nth_child(3).unwrap().value().into_inner(), 4);
+    /// assert!(tree.root().nth_child(4).is_none());
+    /// ```
+    pub fn nth_child(&self, n: usize) -> Option<NodeRef<'a, B, L, K, S>> {
+        self.nth_child_key(n).map(|key| unsafe {
+            // SAFETY: child keys are always valid
+            Self::new_raw_unchecked(self.tree, key)
+        })
+    }
+    /// Returns an iterator over references to the children of the node, starting from the child with the specified index, or `None` if the node is a leaf node. The iterator is empty if the index is out of bounds.
+    ///
+    /// The first child yielded is found the same way as with [`nth_child`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::FreeformTree;
+    ///
+    /// let mut tree = FreeformTree::<_>::new(0);
+    /// tree.root_mut().make_branch(vec![1, 2, 3, 4]).unwrap();
+    /// let payloads = tree
+    ///     .root()
+    ///     .children_from(2)
+    ///     .unwrap()
+    ///     .map(|child| *child.value().into_inner())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(payloads, [3, 4]);
+    /// ```
+    ///
+    /// [`nth_child`]: #method.nth_child " "
+    pub fn children_from(&self, n: usize) -> Option<NodeChildrenIter<'_, B, L, K, S>> {
+        let mut keys = self.children_keys()?;
+        let num_children = self.num_children();
+        if n >= num_children {
+            keys.key = None;
+            keys.back_key = None;
+            keys.remaining = Some(0);
+        } else {
+            keys.key = self.nth_child(n).map(NodeRef::into_raw_key);
+            keys.remaining = Some(num_children - n);
+        }
+        Some(NodeSiblingsIter(keys))
     }
     /// Returns an iterator over the raw keys of the children of the node, or `None` if the node is a leaf node.
     pub fn children_keys(&self) -> Option<NodeChildKeysIter<'_, B, L, K, S>> {
-        self.first_child().map(Self::sibling_keys)
+        if let NodeData::Branch {
+            first_child,
+            last_child,
+            child_count,
+            ..
+        } = &self.node().value
+        {
+            Some(NodeSiblingKeysIter {
+                tree: self.tree,
+                key: Some(first_child.clone()),
+                back_key: Some(last_child.clone()),
+                remaining: Some(*child_count),
+            })
+        } else {
+            None
+        }
     }
     /// Returns an iterator over references to the siblings of the node. Does not include siblings which come before the current node. The first element yielded is always `self`.
     pub fn siblings(self) -> NodeSiblingsIter<'a, B, L, K, S> {
@@ -129,24 +315,48 @@ where
     }
     /// Returns an iterator over the raw keys of the siblings of the node. Does not include siblings which come before the current node. The first element yielded is always `self`'s key.
     pub fn sibling_keys(self) -> NodeSiblingKeysIter<'a, B, L, K, S> {
+        let back_key = self
+            .parent()
+            .and_then(|parent| parent.last_child())
+            .map_or_else(|| self.key.clone(), NodeRef::into_raw_key);
         NodeSiblingKeysIter {
             tree: self.tree,
             key: Some(self.key),
+            back_key: Some(back_key),
+            remaining: None,
         }
     }
 
-    pub(super) fn node(&self) -> &'a Node<B, L, K> {
-        debug_assert!(
-            self.tree.storage.contains_key(&self.key),
-            "\
-debug key check failed: tried to reference key {:?} which is not present in the storage",
-            &self.key,
-        );
-        unsafe {
-            // SAFETY: all existing NodeRefs are guaranteed to not be dangling
-            self.tree.storage.get_unchecked(&self.key)
+    /// Returns the key of the parent node without looking it up in the storage.
+    pub(super) fn parent_key(&self) -> Option<&'a K> {
+        self.node().parent.as_ref()
+    }
+    /// Returns the key of the previous sibling without looking it up in the storage.
+    pub(super) fn prev_sibling_key(&self) -> Option<&'a K> {
+        self.node().prev_sibling.as_ref()
+    }
+    /// Returns the key of the next sibling without looking it up in the storage.
+    pub(super) fn next_sibling_key(&self) -> Option<&'a K> {
+        self.node().next_sibling.as_ref()
+    }
+    /// Same as [`nth_child`], but only returns the key of the child, without looking the child itself up in the storage.
+    ///
+    /// [`nth_child`]: #method.nth_child " "
+    pub(super) fn nth_child_key(&self, n: usize) -> Option<K> {
+        let num_children = self.num_children();
+        if n >= num_children {
+            return None;
+        }
+        let mut keys = self.children_keys()?;
+        if n < num_children / 2 {
+            keys.nth(n)
+        } else {
+            keys.nth_back(num_children - 1 - n)
         }
     }
+    pub(super) fn node(&self) -> &'a Node<B, L, K> {
+        self.node
+    }
 }
 impl<B, L, K, S> Copy for NodeRef<'_, B, L, K, S>
 where
@@ -163,6 +373,7 @@ where
         Self {
             tree: self.tree,
             key: self.key.clone(),
+            node: self.node,
         }
     }
 }
@@ -175,6 +386,18 @@ where
         op.value()
     }
 }
+impl<'a, B, L, K, S> From<NodeRef<'a, B, L, K, S>> for tree::NodeRef<'a, FreeformTree<B, L, K, S>>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn from(op: NodeRef<'a, B, L, K, S>) -> Self {
+        Self {
+            tree: op.tree,
+            key: op.key,
+        }
+    }
+}
 
 /// An iterator over keys of the siblings of a freeform tree node.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -185,35 +408,81 @@ where
 {
     pub(super) tree: &'a FreeformTree<B, L, K, S>,
     pub(super) key: Option<K>,
+    /// The key which will be yielded from the back, which is `None` once the iterator is exhausted.
+    pub(super) back_key: Option<K>,
+    /// The number of keys left to yield, if known. Only iterators over all children of a node know it.
+    pub(super) remaining: Option<usize>,
 }
 /// An iterator over keys of the children of a freeform tree node.
 pub type NodeChildKeysIter<'a, B, L = B, K = usize, S = DefaultStorage<Node<B, L, K>>> =
     NodeSiblingKeysIter<'a, B, L, K, S>;
-impl<'a, B, L, K, S> Iterator for NodeSiblingKeysIter<'a, B, L, K, S>
+impl<'a, B, L, K, S> NodeSiblingKeysIter<'a, B, L, K, S>
 where
     S: Storage<Element = Node<B, L, K>, Key = K>,
     K: Clone + Debug + Eq,
 {
-    type Item = K;
-    fn next(&mut self) -> Option<Self::Item> {
+    /// Advances the iterator from the front, returning the key along with the node it points to, which is looked up only once for both following the sibling link and creating a `NodeRef`.
+    fn next_node(&mut self) -> Option<(K, &'a Node<B, L, K>)> {
         let current_key = self.key.take()?;
-        let next_key = unsafe {
+        let node = unsafe {
             // SAFETY: key validity guarantee
-            NodeRef::new_raw_unchecked(self.tree, current_key.clone())
-                .next_sibling()
-                .map(NodeRef::into_raw_key)
+            self.tree.storage.get_unchecked(&current_key)
         };
-        self.key = next_key;
-        Some(current_key)
+        if self.back_key.as_ref() == Some(&current_key) {
+            // The front and the back met, so this is the last key
+            self.back_key = None;
+        } else {
+            self.key = node.next_sibling.clone();
+        }
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= 1;
+        }
+        Some((current_key, node))
     }
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        if self.key.is_some() {
-            (1, None)
+    /// Same as `next_node`, but advances the iterator from the back.
+    fn next_back_node(&mut self) -> Option<(K, &'a Node<B, L, K>)> {
+        let current_key = self.back_key.take()?;
+        let node = unsafe {
+            // SAFETY: as above
+            self.tree.storage.get_unchecked(&current_key)
+        };
+        if self.key.as_ref() == Some(&current_key) {
+            self.key = None;
         } else {
-            (0, Some(0))
+            self.back_key = node.prev_sibling.clone();
+        }
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= 1;
+        }
+        Some((current_key, node))
+    }
+}
+impl<B, L, K, S> Iterator for NodeSiblingKeysIter<'_, B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    type Item = K;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_node().map(|(key, _)| key)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match (&self.key, self.remaining) {
+            (None, _) => (0, Some(0)),
+            (Some(..), Some(remaining)) => (remaining, Some(remaining)),
+            (Some(..), None) => (1, None),
         }
     }
 }
+impl<B, L, K, S> DoubleEndedIterator for NodeSiblingKeysIter<'_, B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.next_back_node().map(|(key, _)| key)
+    }
+}
 impl<B, L, K, S> FusedIterator for NodeSiblingKeysIter<'_, B, L, K, S>
 where
     S: Storage<Element = Node<B, L, K>, Key = K>,
@@ -239,15 +508,27 @@ where
 {
     type Item = NodeRef<'a, B, L, K, S>;
     fn next(&mut self) -> Option<Self::Item> {
-        self.0.next().map(|key| unsafe {
-            // SAFETY: key validity guaranteed
-            NodeRef::new_raw_unchecked(self.0.tree, key)
-        })
+        let tree = self.0.tree;
+        self.0
+            .next_node()
+            .map(|(key, node)| NodeRef { tree, key, node })
     }
     fn size_hint(&self) -> (usize, Option<usize>) {
         self.0.size_hint()
     }
 }
+impl<B, L, K, S> DoubleEndedIterator for NodeSiblingsIter<'_, B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let tree = self.0.tree;
+        self.0
+            .next_back_node()
+            .map(|(key, node)| NodeRef { tree, key, node })
+    }
+}
 impl<B, L, K, S> FusedIterator for NodeSiblingsIter<'_, B, L, K, S>
 where
     S: Storage<Element = Node<B, L, K>, Key = K>,
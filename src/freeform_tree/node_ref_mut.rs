@@ -1,13 +1,16 @@
-use core::{ptr, mem, fmt::Debug, hint, convert};
+use core::{ptr, mem, fmt::Debug, hint, convert, iter::{self, Rev}};
 use crate::{
     storage::{Storage, DefaultStorage},
-    util::{unreachable_debugchecked, abort_on_panic},
+    util::{unreachable_debugchecked, has_room_for},
     TryRemoveLeafError,
     TryRemoveBranchError,
     TryRemoveChildrenError,
+    ReparentError,
     MakeBranchError,
+    TryMakeBranchError,
     traversal::algorithms,
     NodeValue,
+    tree,
 };
 use super::{
     NodeRef,
@@ -80,10 +83,10 @@ where
     }
     /// Returns a *mutable* reference to the parent node of the pointee, or `None` if it's the root node.
     pub fn parent_mut(&mut self) -> Option<NodeRefMut<'_, B, L, K, S>> {
-        let key = self.node().parent.as_ref().cloned();
+        let key = self.node().parent.clone();
         key.map(move |x| unsafe {
             // SAFETY: as above
-            Self::new_raw_unchecked(self.tree, x)
+            NodeRefMut::new_raw_unchecked(self.tree, x)
         })
     }
     /// Returns a reference to the sibling of the pointee which comes before it in order, or `None` if it's the first child of its parent.
@@ -95,10 +98,10 @@ where
     }
     /// Returns a *mutable* reference to the sibling of the pointee which comes before it in order, or `None` if it's the first child of its parent.
     pub fn prev_sibling_mut(&mut self) -> Option<NodeRefMut<'_, B, L, K, S>> {
-        let key = self.node().prev_sibling.as_ref().cloned();
+        let key = self.node().prev_sibling.clone();
         key.map(move |x| unsafe {
             // SAFETY: as above
-            Self::new_raw_unchecked(self.tree, x)
+            NodeRefMut::new_raw_unchecked(self.tree, x)
         })
     }
     /// Returns a reference to the sibling of the pointee which comes after it in order, or `None` if it's the last child of its parent.
@@ -112,10 +115,10 @@ where
     ///
     /// This is the only way to iterate through the siblings of a node with mutable access without extra allocations. In the future, a more ergonomic interface might become available.
     pub fn next_sibling_mut(&mut self) -> Option<NodeRefMut<'_, B, L, K, S>> {
-        let key = self.node().next_sibling.as_ref().cloned();
+        let key = self.node().next_sibling.clone();
         key.map(move |x| unsafe {
             // SAFETY: as above
-            Self::new_raw_unchecked(self.tree, x)
+            NodeRefMut::new_raw_unchecked(self.tree, x)
         })
     }
     /// Returns a reference to the first child of the node, or `None` if it's a leaf node.
@@ -198,18 +201,43 @@ where
     }
     /// Returns an iterator over the raw keys of the siblings of the node. Does not include siblings which come before the current node. The first element yielded is always `self`'s key.
     pub fn sibling_keys(self) -> NodeSiblingKeysIter<'a, B, L, K, S> {
-        NodeSiblingKeysIter {
-            tree: self.tree,
-            key: Some(self.key),
+        NodeRef::from(self).sibling_keys()
+    }
+    /// Returns the number of children of the node, which is zero for leaf nodes.
+    ///
+    /// The number of children is stored in branch nodes, so this takes constant time.
+    pub fn num_children(&self) -> usize {
+        match &self.node().value {
+            NodeData::Branch { child_count, .. } => *child_count,
+            NodeData::Leaf(..) => 0,
         }
     }
     /// Returns an iterator over references to the children of the node, or `None` if the node is a leaf node.
     pub fn children(&self) -> Option<NodeChildrenIter<'_, B, L, K, S>> {
-        self.first_child().map(NodeRef::siblings)
+        self.children_keys().map(NodeSiblingsIter)
+    }
+    /// Returns an iterator over references to the children of the node in reverse order, starting from the last child, or `None` if the node is a leaf node.
+    pub fn children_rev(&self) -> Option<Rev<NodeChildrenIter<'_, B, L, K, S>>> {
+        self.children().map(Iterator::rev)
     }
     /// Returns an iterator over the raw keys of the children of the node, or `None` if the node is a leaf node.
     pub fn children_keys(&self) -> Option<NodeChildKeysIter<'_, B, L, K, S>> {
-        self.first_child().map(NodeRef::sibling_keys)
+        if let NodeData::Branch {
+            first_child,
+            last_child,
+            child_count,
+            ..
+        } = &self.node().value
+        {
+            Some(NodeSiblingKeysIter {
+                tree: self.tree,
+                key: Some(first_child.clone()),
+                back_key: Some(last_child.clone()),
+                remaining: Some(*child_count),
+            })
+        } else {
+            None
+        }
     }
 
     /// Converts a leaf node into a branch node with the specified leaf children, using the provided closure to convert the payload.
@@ -228,6 +256,7 @@ where
             });
         }
         let mut children = children.into_iter();
+        self.tree.storage.reserve(children.size_hint().0);
         let first_element = if let Some(x) = children.next() {
             self.tree.storage.add(unsafe {
                 // SAFETY: parent key validity guaranteed via own key validity guarantee
@@ -257,17 +286,18 @@ where
                     payload: new_payload,
                     first_child: first_element.clone(),
                     last_child: first_element.clone(),
+                    child_count: 1,
                 },
-            )
+            );
         }
         let mut current_element_key = first_element;
-        let mut previous_element_key = None;
+        let mut num_children = 1;
         for next_element in children {
             let next_element_key = self.tree.storage.add(unsafe {
                 // SAFETY: see safety for first_element
                 Node::leaf(
                     next_element,
-                    previous_element_key,
+                    Some(current_element_key.clone()),
                     None,
                     Some(self.key.clone()),
                 )
@@ -281,13 +311,18 @@ where
                     .next_sibling
             };
             *next_sibling_key_ref = Some(next_element_key.clone());
-            // Move the old current element to previous, put the next one into the current
-            previous_element_key = Some(mem::replace(&mut current_element_key, next_element_key));
+            current_element_key = next_element_key;
+            num_children += 1;
         }
         match &mut self.node_mut().value {
-            NodeData::Branch { last_child, .. } => {
+            NodeData::Branch {
+                last_child,
+                child_count,
+                ..
+            } => {
                 // Update the last child key to point to the last one we added.
                 *last_child = current_element_key;
+                *child_count = num_children;
             }
             NodeData::Leaf(..) => unsafe {
                 // SAFETY: the method makes numerous checks for a leaf node
@@ -296,6 +331,29 @@ where
         }
         Ok(())
     }
+    /// Converts a leaf node into a branch node with the specified leaf children, using the provided closure to convert the payload. Unlike [`make_branch_with`], returns an error instead of panicking if the storage cannot fit the children.
+    ///
+    /// # Errors
+    /// Will fail if the node is already a branch node or if the storage has a fixed capacity and does not have enough free space for the children. In both cases, the iterator over the children is returned back to the caller.
+    ///
+    /// [`make_branch_with`]: #method.make_branch_with " "
+    pub fn try_make_branch_with<I>(
+        &mut self,
+        children: I,
+        f: impl FnOnce(L) -> B,
+    ) -> Result<(), TryMakeBranchError<L, I::IntoIter>>
+    where
+        I: IntoIterator<Item = L>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let children = children.into_iter();
+        if self.is_leaf() && !has_room_for(&self.tree.storage, children.len()) {
+            return Err(TryMakeBranchError::OutOfCapacity {
+                packed_children: children,
+            });
+        }
+        self.make_branch_with(children, f).map_err(Into::into)
+    }
 
     /// Adds a child node to the node's children set after all other ones, failing if it's not a branch node.
     ///
@@ -315,7 +373,14 @@ where
             Node::leaf(child_payload, None, None, Some(self.key.clone()))
         });
         let old_last_child_key_ref = match &mut self.node_mut().value {
-            NodeData::Branch { last_child, .. } => last_child,
+            NodeData::Branch {
+                last_child,
+                child_count,
+                ..
+            } => {
+                *child_count += 1;
+                last_child
+            }
             NodeData::Leaf(..) => unsafe {
                 // SAFETY: we did a leaf check in the beginning
                 hint::unreachable_unchecked()
@@ -352,7 +417,14 @@ where
             Node::leaf(child_payload, None, None, Some(self.key.clone()))
         });
         let old_first_child_key_ref = match &mut self.node_mut().value {
-            NodeData::Branch { first_child, .. } => first_child,
+            NodeData::Branch {
+                first_child,
+                child_count,
+                ..
+            } => {
+                *child_count += 1;
+                first_child
+            }
             NodeData::Leaf(..) => unsafe {
                 // SAFETY: we did a leaf check in the beginning
                 hint::unreachable_unchecked()
@@ -372,6 +444,62 @@ where
         Ok(())
     }
 
+    /// Adds a leaf child to a branch node so that it ends up at the specified index among its children, shifting the children from that index onwards.
+    ///
+    /// # Panics
+    /// Will panic if the node is a leaf node or has fewer children than the index.
+    #[cfg(feature = "alloc")]
+    pub(super) fn insert_leaf_child(&mut self, index: usize, child_payload: L) {
+        let prev_sibling_key = if let Some(index) = index.checked_sub(1) {
+            self.children_keys()
+                .and_then(|mut children| children.nth(index))
+                .expect("the index of the child is out of bounds")
+        } else {
+            // Inserting at the front is what try_push_front does
+            self.try_push_front(child_payload)
+                .unwrap_or_else(|_| panic!("expected a branch node"));
+            return;
+        };
+        let next_sibling_key = unsafe {
+            // SAFETY: key validity guarantee
+            self.tree.storage.get_unchecked(&prev_sibling_key)
+        }
+        .next_sibling
+        .clone();
+        let child_key = self.tree.storage.add(unsafe {
+            // SAFETY: as above
+            Node::leaf(
+                child_payload,
+                Some(prev_sibling_key.clone()),
+                next_sibling_key.clone(),
+                Some(self.key.clone()),
+            )
+        });
+        unsafe {
+            // SAFETY: as above
+            self.tree.storage.get_unchecked_mut(&prev_sibling_key)
+        }
+        .next_sibling = Some(child_key.clone());
+        if let Some(next_sibling_key) = &next_sibling_key {
+            unsafe {
+                // SAFETY: as above
+                self.tree.storage.get_unchecked_mut(next_sibling_key)
+            }
+            .prev_sibling = Some(child_key.clone());
+        }
+        if let NodeData::Branch {
+            last_child,
+            child_count,
+            ..
+        } = &mut self.node_mut().value
+        {
+            if next_sibling_key.is_none() {
+                *last_child = child_key;
+            }
+            *child_count += 1;
+        }
+    }
+
     /// Attempts to remove a leaf node without using recursion. If its parent only had one child, it's replaced with a leaf node, the value for which is provided by the specified closure (the previous value is passed into the closure).
     ///
     /// # Errors
@@ -381,6 +509,15 @@ where
     pub fn try_remove_leaf_with(
         self,
         branch_to_leaf: impl FnOnce(B) -> L,
+    ) -> Result<L, TryRemoveLeafError> {
+        self.try_remove_leaf_converting(|parent, payload| {
+            parent.make_leaf_with(payload, branch_to_leaf)
+        })
+    }
+    /// The same as `try_remove_leaf_with`, but if the parent loses its only child, the closure converts it into a leaf node, receiving the payload of the removed node and returning the payload to give back to the caller.
+    fn try_remove_leaf_converting(
+        mut self,
+        make_leaf: impl FnOnce(&mut NodeData<B, L, K>, L) -> L,
     ) -> Result<L, TryRemoveLeafError> {
         if !self.is_leaf() {
             return Err(TryRemoveLeafError::WasBranchNode);
@@ -388,85 +525,26 @@ where
         let parent_key = self
             .node()
             .parent
-            .as_ref()
-            .cloned()
+            .clone()
             .ok_or(TryRemoveLeafError::WasRootNode)?;
-        let (prev_sibling_key, next_sibling_key) = (
-            self.node().prev_sibling.clone(),
-            self.node().next_sibling.clone(),
-        );
-        if let Some(prev_sibling_key) = &prev_sibling_key {
-            let prev_sibling = unsafe {
-                // SAFETY: key validity guarantee
-                self.tree.storage.get_unchecked_mut(prev_sibling_key)
-            };
-            // Will use the next sibling or None to indicate that there is no next sibling
-            prev_sibling.next_sibling = next_sibling_key.clone();
-        } else {
-            // No previous sibling key means that we're the first child of our
-            // parent, so fix the first child key value
-            let parent = unsafe {
-                // SAFETY: as above
-                self.tree.storage.get_unchecked_mut(&parent_key)
-            };
-            if let NodeData::Branch { first_child, .. } = &mut parent.value {
-                *first_child = self.key.clone();
-            } else {
-                unsafe {
-                    unreachable_debugchecked("parent nodes cannot be leaves");
-                }
-            }
-        }
-        if let Some(next_sibling_key) = &next_sibling_key {
-            let next_sibling = unsafe {
-                // SAFETY: as above
-                self.tree.storage.get_unchecked_mut(next_sibling_key)
-            };
-            // Similar thing here
-            next_sibling.prev_sibling = prev_sibling_key.clone();
+        let was_only_child = self.unlink(&parent_key);
+        let payload = if let NodeData::Leaf(val) = self.tree.storage.remove(&self.key).value {
+            val
         } else {
-            // No next sibling key means that we're the last child of our
-            // parent, so fix the last child key value
-            let parent = unsafe {
-                // SAFETY: as above
-                self.tree.storage.get_unchecked_mut(&parent_key)
-            };
-            if let NodeData::Branch { last_child, .. } = &mut parent.value {
-                *last_child = self.key.clone();
-            } else {
-                unsafe {
-                    unreachable_debugchecked("parent nodes cannot be leaves");
-                }
+            unsafe {
+                // SAFETY: we checked for a branch node in the beginning
+                hint::unreachable_unchecked()
             }
-        }
-        if prev_sibling_key.is_none() && next_sibling_key.is_none() {
+        };
+        if was_only_child {
             let parent = unsafe {
-                // SAFETY: as above
+                // SAFETY: parent nodes are added to the storage before their children, so removing
+                // a child does not invalidate the key of its parent
                 self.tree.storage.get_unchecked_mut(&parent_key)
             };
-            let parent_payload_ref = if let NodeData::Branch { payload, .. } = &parent.value {
-                payload
-            } else {
-                unsafe { unreachable_debugchecked("parent nodes cannot be leaves") }
-            };
-            let parent_payload = unsafe {
-                // SAFETY: we're overwriting this afterwards
-                ptr::read(parent_payload_ref)
-            };
-            let new_parent_payload = abort_on_panic(|| branch_to_leaf(parent_payload));
-            unsafe {
-                // SAFETY: see read()
-                ptr::write(&mut parent.value, NodeData::Leaf(new_parent_payload));
-            }
-        }
-        let val = self.tree.storage.remove(&self.key);
-        if let NodeData::Leaf(val) = val.value {
-            Ok(val)
+            Ok(make_leaf(&mut parent.value, payload))
         } else {
-            unsafe {
-                // SAFETY: we checked for a branch node in the beginning
-                hint::unreachable_unchecked()
-            }
+            Ok(payload)
         }
     }
     /// Attempts to remove a branch node without using recursion. If its parent only had one child, it's replaced with a leaf node, the value for which is provided by the specified closure (the previous value is passed into the closure). The children which this branch node had are fed into the second closure.
@@ -479,6 +557,17 @@ where
     pub fn try_remove_branch_with(
         self,
         branch_to_leaf: impl FnOnce(B) -> L,
+        collector: impl FnMut(L),
+    ) -> Result<B, TryRemoveBranchError> {
+        self.try_remove_branch_converting(
+            |parent, last_child| parent.make_leaf_with(last_child, branch_to_leaf),
+            collector,
+        )
+    }
+    /// The same as `try_remove_branch_with`, but if the parent loses its only child, the first closure converts it into a leaf node, receiving the payload of the last child of the removed node and returning the payload to feed into the collector.
+    fn try_remove_branch_converting(
+        mut self,
+        make_leaf: impl FnOnce(&mut NodeData<B, L, K>, L) -> L,
         mut collector: impl FnMut(L),
     ) -> Result<B, TryRemoveBranchError> {
         if !self.is_branch() {
@@ -489,14 +578,6 @@ where
         } else {
             return Err(TryRemoveBranchError::WasRootNode);
         };
-        let first_child_key = if let NodeData::Branch { first_child, .. } = &self.node().value {
-            first_child.clone()
-        } else {
-            unsafe {
-                // SAFETY: we checked for a leaf node in the beginning
-                hint::unreachable_unchecked()
-            }
-        };
         // FIXME this requires double iteration for checking, maybe we can
         // store the count of branch children in the parent
         let branch_child = self
@@ -510,82 +591,30 @@ where
         if let Some((_, branch_child_index)) = branch_child {
             return Err(TryRemoveBranchError::HadBranchChild(branch_child_index));
         }
-        let mut current_child_key = first_child_key;
-        loop {
-            let next_child_key = unsafe {
-                // SAFETY: key validity guarantee
-                self.tree
-                    .storage
-                    .get_unchecked(&current_child_key)
-                    .next_sibling
-                    .clone()
-            };
-            let current_child = {
-                let val = self.tree.storage.remove(&current_child_key);
-                if let NodeData::Leaf(val) = val.value {
-                    val
-                } else {
-                    unsafe {
-                        // SAFETY: we checked for branch children before
-                        hint::unreachable_unchecked()
-                    }
-                }
-            };
-            abort_on_panic(|| collector(current_child));
-            current_child_key = if let Some(next_child_key) = next_child_key {
-                next_child_key
-            } else {
-                break;
-            };
-        }
-        let parent_ref = unsafe {
-            // SAFETY: key validity guarantee
-            self.tree.storage.get_unchecked(&parent_key)
-        };
-        let is_only_sibling = if let NodeData::Branch {
-            first_child,
-            last_child,
-            ..
-        } = &parent_ref.value
+        let last_child = self.remove_leaf_children(&mut collector);
+        let was_only_child = self.unlink(&parent_key);
+        let payload = if let NodeData::Branch { payload, .. } =
+            self.tree.storage.remove(&self.key).value
         {
-            first_child == last_child
+            payload
         } else {
-            unsafe { unreachable_debugchecked("parent nodes cannot be leaves") }
+            unsafe {
+                // SAFETY: we checked for a leaf node in the beginning
+                hint::unreachable_unchecked()
+            }
         };
-        if is_only_sibling {
-            let old_parent_payload_ref = if let Some(NodeData::Branch { payload, .. }) =
-                self.parent().map(|x| &x.node().value)
-            {
-                payload
-            } else {
-                unsafe {
-                    // SAFETY: we checked for a root node before
-                    hint::unreachable_unchecked()
-                }
-            };
-            let old_parent_payload = unsafe {
-                // SAFETY: we are overwriting this afterwards
-                ptr::read(old_parent_payload_ref)
-            };
+        let last_child = if was_only_child {
             let parent = unsafe {
-                // SAFETY: key validity guarantee
+                // SAFETY: parent nodes are added to the storage before their children, so removing
+                // a child does not invalidate the key of its parent
                 self.tree.storage.get_unchecked_mut(&parent_key)
             };
-            unsafe {
-                ptr::write(
-                    &mut parent.value,
-                    NodeData::Leaf(abort_on_panic(|| branch_to_leaf(old_parent_payload))),
-                )
-            };
-        }
-        if let NodeData::Branch { payload, .. } = self.tree.storage.remove(&self.key).value {
-            Ok(payload)
+            make_leaf(&mut parent.value, last_child)
         } else {
-            unsafe {
-                // SAFETY: we checked for a leaf node in the beginning
-                hint::unreachable_unchecked()
-            }
-        }
+            last_child
+        };
+        collector(last_child);
+        Ok(payload)
     }
     /// Attempts to remove a branch node's children without using recursion, replacing it with a leaf node, the value for which is provided by the specified closure. Another closure is used to collect all removed children.
     ///
@@ -598,17 +627,268 @@ where
         branch_to_leaf: impl FnOnce(B) -> L,
         mut collector: impl FnMut(L),
     ) -> Result<(), TryRemoveChildrenError> {
-        if !self.is_branch() {
-            return Err(TryRemoveChildrenError::WasLeafNode);
+        self.check_leaf_children()?;
+        let last_child = self.remove_leaf_children(&mut collector);
+        // The payload of the last child stands in for the one of the node while the closure runs,
+        // keeping the tree valid if it panics
+        let last_child = self
+            .node_mut()
+            .value
+            .make_leaf_with(last_child, branch_to_leaf);
+        collector(last_child);
+        Ok(())
+    }
+    /// Recursively removes the specified node and all its descendants, using a closure to patch nodes which transition from having one child to having zero children.
+    pub fn recursively_remove_with(self, branch_to_leaf: impl FnMut(B) -> L) -> NodeValue<B, L> {
+        algorithms::recursively_remove_with(self.tree, self.key, branch_to_leaf)
+    }
+    /// Moves the node and all its descendants to a different parent node in the same tree, placing it at the specified position among the children of the new parent. No payloads are moved in memory: only the links between the nodes are changed, and the node keeps its key.
+    ///
+    /// The position is counted among the children of the new parent without the node itself, so moving a node within its own parent places it at that index after it has been removed from its old place. If the old parent node loses its only child, it's converted into a leaf node using the first closure, and if the new parent node is a leaf node, it's converted into a branch node using the second closure.
+    ///
+    /// Since sparse storage cannot tell the holes left by removed nodes apart from nodes, the new parent node is checked to be in the tree by looking for it among the nodes of the tree, which takes *O*(*n*) time, where *n* is the number of nodes in the tree.
+    ///
+    /// # Errors
+    /// Will fail in the following scenarios, leaving the tree unchanged:
+    /// - The node was the root node, which cannot have a parent.
+    /// - The key of the new parent node does not point to a node in the tree.
+    /// - The new parent node is the node itself or one of its descendants.
+    /// - The position is greater than the number of children of the new parent node.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{freeform_tree::{FreeformTree, NodeRefMut}, ReparentError};
+    ///
+    /// let mut tree = FreeformTree::<_>::new(0);
+    /// tree.root_mut().make_branch(vec![1, 2]).unwrap();
+    /// let first_key = tree.root().first_child().unwrap().into_raw_key();
+    /// let last_key = tree.root().last_child().unwrap().into_raw_key();
+    /// // Make the first child of the root the only child of the second one
+    /// NodeRefMut::new_raw(&mut tree, first_key)
+    ///     .unwrap()
+    ///     .reparent_to_with(last_key, 0, |_| unreachable!(), |payload| payload * 10)
+    ///     .unwrap();
+    /// assert_eq!(tree.root().num_children(), 1);
+    /// let new_parent = tree.root().first_child().unwrap();
+    /// assert_eq!(new_parent.value().into_inner(), &20);
+    /// assert_eq!(new_parent.first_child().unwrap().value().into_inner(), &1);
+    ///
+    /// // Removed nodes cannot become parents, even if their keys are still holes in the storage
+    /// let removed_key = tree.root().first_child().unwrap().first_child().unwrap().into_raw_key();
+    /// NodeRefMut::new_raw(&mut tree, removed_key)
+    ///     .unwrap()
+    ///     .try_remove_leaf_with(|payload| payload)
+    ///     .unwrap();
+    /// let node_key = tree.root().first_child().unwrap().into_raw_key();
+    /// let mut node = NodeRefMut::new_raw(&mut tree, node_key).unwrap();
+    /// assert_eq!(
+    ///     node.reparent_to_with(removed_key, 0, |payload| payload, |payload| payload),
+    ///     Err(ReparentError::NewParentNotFound),
+    /// );
+    /// ```
+    pub fn reparent_to_with(
+        &mut self,
+        new_parent: K,
+        position: usize,
+        branch_to_leaf: impl FnOnce(B) -> L,
+        leaf_to_branch: impl FnOnce(L) -> B,
+    ) -> Result<(), ReparentError> {
+        let old_parent = self
+            .node()
+            .parent
+            .clone()
+            .ok_or(ReparentError::WasRootNode)?;
+        // Sparse storage reports the holes left by removed nodes as valid keys and panics once
+        // they're accessed, so the new parent is looked for among the nodes of the tree instead
+        let root = self.tree.root.clone();
+        let new_parent_in_tree = self.tree.storage.contains_key(&new_parent)
+            && iter::successors(Some(root.clone()), |key| {
+                algorithms::next_in_preorder(&*self.tree, key, &root)
+            })
+            .any(|key| key == new_parent);
+        if !new_parent_in_tree {
+            return Err(ReparentError::NewParentNotFound);
         }
-        let first_child_key = if let NodeData::Branch { first_child, .. } = &self.node().value {
-            first_child.clone()
-        } else {
+        let moves_into_subtree = new_parent == self.key || {
+            let new_parent_ref = unsafe {
+                // SAFETY: the key was checked above
+                NodeRef::new_raw_unchecked(&*self.tree, new_parent.clone())
+            };
+            new_parent_ref.is_descendant_of(&NodeRef::from(&*self))
+        };
+        if moves_into_subtree {
+            return Err(ReparentError::NewParentInSubtree);
+        }
+        let num_siblings = match &unsafe {
+            // SAFETY: as above
+            self.tree.storage.get_unchecked(&new_parent)
+        }
+        .value
+        {
+            // The node itself does not count if it stays with the same parent
+            NodeData::Branch { child_count, .. } if new_parent == old_parent => *child_count - 1,
+            NodeData::Branch { child_count, .. } => *child_count,
+            NodeData::Leaf(..) => 0,
+        };
+        if position > num_siblings {
+            return Err(ReparentError::PositionOutOfBounds(num_siblings));
+        }
+        let same_parent = new_parent == old_parent;
+        let was_only_child = self.unlink(&old_parent);
+        self.link(new_parent, position, leaf_to_branch);
+        if was_only_child && !same_parent {
             unsafe {
-                // SAFETY: we checked for a leaf node in the beginning
-                hint::unreachable_unchecked()
+                // SAFETY: parent keys are always valid
+                self.tree.storage.get_unchecked_mut(&old_parent)
             }
+            .value
+            .make_leaf_or_abort(branch_to_leaf);
+        }
+        Ok(())
+    }
+
+    /// Links an unlinked node into the children of the specified parent node so that it ends up at the specified index among them, converting the parent into a branch node using the closure if it's a leaf node. The parent must have at least as many children as the index, not counting the node itself; if it's a branch node without children, which is what `unlink` leaves behind, its dangling links are replaced.
+    pub(super) fn link(&mut self, parent_key: K, index: usize, leaf_to_branch: impl FnOnce(L) -> B) {
+        let self_key = self.key.clone();
+        let parent = unsafe {
+            // SAFETY: key validity is checked by the caller
+            self.tree.storage.get_unchecked_mut(&parent_key)
         };
+        let (prev_sibling_key, next_sibling_key) = match &mut parent.value {
+            NodeData::Branch {
+                first_child,
+                last_child,
+                child_count,
+                ..
+            } => {
+                *child_count += 1;
+                if *child_count == 1 {
+                    *first_child = self_key.clone();
+                    *last_child = self_key.clone();
+                    (None, None)
+                } else if index == 0 {
+                    (None, Some(mem::replace(first_child, self_key.clone())))
+                } else if index == *child_count - 1 {
+                    (Some(mem::replace(last_child, self_key.clone())), None)
+                } else {
+                    let mut prev_sibling_key = first_child.clone();
+                    for _ in 1..index {
+                        prev_sibling_key = unsafe {
+                            // SAFETY: the index was checked to be in bounds by the caller
+                            self.tree.storage.get_unchecked(&prev_sibling_key)
+                        }
+                        .next_sibling
+                        .clone()
+                        .unwrap_or_else(|| unsafe {
+                            unreachable_debugchecked("the index is out of bounds")
+                        });
+                    }
+                    let next_sibling_key = unsafe {
+                        // SAFETY: key validity guarantee
+                        self.tree.storage.get_unchecked(&prev_sibling_key)
+                    }
+                    .next_sibling
+                    .clone();
+                    (Some(prev_sibling_key), next_sibling_key)
+                }
+            }
+            NodeData::Leaf(..) => {
+                parent
+                    .value
+                    .make_branch_or_abort(self_key.clone(), leaf_to_branch);
+                (None, None)
+            }
+        };
+        if let Some(prev_sibling_key) = &prev_sibling_key {
+            unsafe {
+                // SAFETY: key validity guarantee
+                self.tree.storage.get_unchecked_mut(prev_sibling_key)
+            }
+            .next_sibling = Some(self_key.clone());
+        }
+        if let Some(next_sibling_key) = &next_sibling_key {
+            unsafe {
+                // SAFETY: as above
+                self.tree.storage.get_unchecked_mut(next_sibling_key)
+            }
+            .prev_sibling = Some(self_key);
+        }
+        let node = self.node_mut();
+        node.parent = Some(parent_key);
+        node.prev_sibling = prev_sibling_key;
+        node.next_sibling = next_sibling_key;
+    }
+    /// Removes the links to the node from its siblings and parent, returning whether it was the only child of its parent. The node itself is left intact and has to be removed from the storage afterwards. If it was the only child, the parent is left as a branch node with dangling links to its children, which the caller has to replace with a leaf node.
+    pub(super) fn unlink(&mut self, parent_key: &K) -> bool {
+        let (prev_sibling_key, next_sibling_key) = (
+            self.node().prev_sibling.clone(),
+            self.node().next_sibling.clone(),
+        );
+        if let Some(prev_sibling_key) = &prev_sibling_key {
+            let prev_sibling = unsafe {
+                // SAFETY: key validity guarantee
+                self.tree.storage.get_unchecked_mut(prev_sibling_key)
+            };
+            // Will use the next sibling or None to indicate that there is no next sibling
+            prev_sibling.next_sibling.clone_from(&next_sibling_key);
+        } else {
+            // No previous sibling key means that we're the first child of our
+            // parent, so fix the first child key value
+            let parent = unsafe {
+                // SAFETY: as above
+                self.tree.storage.get_unchecked_mut(parent_key)
+            };
+            if let NodeData::Branch { first_child, .. } = &mut parent.value {
+                // If there is no next sibling either, the parent becomes a leaf below
+                if let Some(next_sibling_key) = &next_sibling_key {
+                    first_child.clone_from(next_sibling_key);
+                }
+            } else {
+                unsafe {
+                    unreachable_debugchecked("parent nodes cannot be leaves");
+                }
+            }
+        }
+        if let Some(next_sibling_key) = &next_sibling_key {
+            let next_sibling = unsafe {
+                // SAFETY: as above
+                self.tree.storage.get_unchecked_mut(next_sibling_key)
+            };
+            // Similar thing here
+            next_sibling.prev_sibling.clone_from(&prev_sibling_key);
+        } else {
+            // No next sibling key means that we're the last child of our
+            // parent, so fix the last child key value
+            let parent = unsafe {
+                // SAFETY: as above
+                self.tree.storage.get_unchecked_mut(parent_key)
+            };
+            if let NodeData::Branch { last_child, .. } = &mut parent.value {
+                // Same as above
+                if let Some(prev_sibling_key) = &prev_sibling_key {
+                    last_child.clone_from(prev_sibling_key);
+                }
+            } else {
+                unsafe {
+                    unreachable_debugchecked("parent nodes cannot be leaves");
+                }
+            }
+        }
+        if let NodeData::Branch { child_count, .. } = &mut unsafe {
+            // SAFETY: as above
+            self.tree.storage.get_unchecked_mut(parent_key)
+        }
+        .value
+        {
+            *child_count -= 1;
+        }
+        prev_sibling_key.is_none() && next_sibling_key.is_none()
+    }
+    /// Returns an error if the node is a leaf node or has branch children, i.e. if its children cannot be removed without using recursion.
+    fn check_leaf_children(&self) -> Result<(), TryRemoveChildrenError> {
+        if !self.is_branch() {
+            return Err(TryRemoveChildrenError::WasLeafNode);
+        }
         let branch_child = self
             .children()
             .unwrap_or_else(|| unsafe {
@@ -620,59 +900,55 @@ where
         if let Some((_, branch_child_index)) = branch_child {
             return Err(TryRemoveChildrenError::HadBranchChild(branch_child_index));
         }
-        let mut current_child_key = first_child_key;
+        Ok(())
+    }
+    /// Removes the children of the node, which must all be leaves, feeding the payloads of all of them except for the last one into the collector. The tree is kept valid while the collector runs, so that it's allowed to panic.
+    ///
+    /// The payload of the last child is returned instead, and the node is left as a branch node with dangling links to its children, which the caller has to replace with a leaf node or remove.
+    fn remove_leaf_children(&mut self, mut collector: impl FnMut(L)) -> L {
         loop {
-            let next_child_key = unsafe {
-                // SAFETY: key validity guarantee
-                self.tree
-                    .storage
-                    .get_unchecked(&current_child_key)
-                    .next_sibling
-                    .clone()
+            let first_child_key = if let NodeData::Branch { first_child, .. } = &self.node().value {
+                first_child.clone()
+            } else {
+                unsafe { unreachable_debugchecked("expected a branch node") }
             };
-            let current_child = {
-                let val = self.tree.storage.remove(&current_child_key);
-                if let NodeData::Leaf(val) = val.value {
+            let next_sibling_key = unsafe {
+                // SAFETY: key validity guarantee
+                self.tree.storage.get_unchecked(&first_child_key)
+            }
+            .next_sibling
+            .clone();
+            if let Some(next_sibling_key) = &next_sibling_key {
+                unsafe {
+                    // SAFETY: as above
+                    self.tree.storage.get_unchecked_mut(next_sibling_key)
+                }
+                .prev_sibling = None;
+                if let NodeData::Branch {
+                    first_child,
+                    child_count,
+                    ..
+                } = &mut self.node_mut().value
+                {
+                    first_child.clone_from(next_sibling_key);
+                    *child_count -= 1;
+                }
+            }
+            let payload =
+                if let NodeData::Leaf(val) = self.tree.storage.remove(&first_child_key).value {
                     val
                 } else {
                     unsafe {
-                        // SAFETY: we checked for branch children before
+                        // SAFETY: the callers check for branch children beforehand
                         hint::unreachable_unchecked()
                     }
-                }
-            };
-            abort_on_panic(|| collector(current_child));
-            current_child_key = if let Some(next_child_key) = next_child_key {
-                next_child_key
-            } else {
-                break;
-            };
-        }
-        let old_payload_ref = if let NodeData::Branch { payload, .. } = &self.node().value {
-            payload
-        } else {
-            unsafe {
-                // SAFETY: we checked for a leaf node before
-                hint::unreachable_unchecked()
+                };
+            if next_sibling_key.is_none() {
+                return payload;
             }
-        };
-        let old_payload = unsafe {
-            // SAFETY: we are overwriting this afterwards
-            ptr::read(old_payload_ref)
-        };
-        unsafe {
-            ptr::write(
-                &mut self.node_mut().value,
-                NodeData::Leaf(abort_on_panic(|| branch_to_leaf(old_payload))),
-            )
-        };
-        Ok(())
-    }
-    /// Recursively removes the specified node and all its descendants, using a closure to patch nodes which transition from having one child to having zero children.
-    pub fn recursively_remove_with(self, branch_to_leaf: impl FnMut(B) -> L) -> NodeValue<B, L> {
-        algorithms::recursively_remove_with(self.tree, self.key, branch_to_leaf)
+            collector(payload);
+        }
     }
-
     fn node(&self) -> &'_ Node<B, L, K> {
         debug_assert!(
             self.tree.storage.contains_key(&self.key),
@@ -698,7 +974,7 @@ debug key check failed: tried to reference key {:?} which is not present in the
         }
     }
 }
-impl<'a, D, K, S> NodeRefMut<'a, D, D, K, S>
+impl<D, K, S> NodeRefMut<'_, D, D, K, S>
 where
     S: Storage<Element = Node<D, D, K>, Key = K>,
     K: Clone + Debug + Eq,
@@ -713,6 +989,22 @@ where
     ) -> Result<(), MakeBranchError<D, I>> {
         self.make_branch_with(children, convert::identity)
     }
+    /// Converts a leaf node into a branch node with the specified leaf children, keeping its payload. Because of that, *this method is only available when the payload for leaf nodes and branch nodes is the same.* Unlike [`make_branch`], returns an error instead of panicking if the storage cannot fit the children.
+    ///
+    /// # Errors
+    /// Will fail if the node is already a branch node or if the storage has a fixed capacity and does not have enough free space for the children. In both cases, the iterator over the children is returned back to the caller.
+    ///
+    /// [`make_branch`]: #method.make_branch " "
+    pub fn try_make_branch<I>(
+        &mut self,
+        children: I,
+    ) -> Result<(), TryMakeBranchError<D, I::IntoIter>>
+    where
+        I: IntoIterator<Item = D>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.try_make_branch_with(children, convert::identity)
+    }
     /// Attempts to remove the node without using recursion. If the parent only had one child, it's replaced with a leaf node, keeping its original payload, which is why *this method is only available when the payload for leaf nodes and branch nodes is the same.*
     ///
     /// # Errors
@@ -720,7 +1012,10 @@ where
     /// - The node was a branch node, which would require recursion to remove, and this function explicitly does not implement recursive removal.
     /// - The node was the root node, which can never be removed.
     pub fn try_remove_leaf(self) -> Result<D, TryRemoveLeafError> {
-        self.try_remove_leaf_with(convert::identity)
+        self.try_remove_leaf_converting(|parent, payload| {
+            parent.make_leaf_keeping_payload();
+            payload
+        })
     }
     /// Attempts to remove a branch node without using recursion. The children of the node are fed into the specified closure. If its parent only had one child, it's replaced with a leaf node, keeping its original payload, which is why *this method is only available when the payload for leaf nodes and branch nodes is the same.*
     ///
@@ -730,24 +1025,53 @@ where
     /// - The node was the root node, which can never be removed.
     /// - One or more of the node's children were a branch node, which thus would require recursion to remove.
     pub fn try_remove_branch(self, collector: impl FnMut(D)) -> Result<D, TryRemoveBranchError> {
-        self.try_remove_branch_with(convert::identity, collector)
+        self.try_remove_branch_converting(
+            |parent, last_child| {
+                parent.make_leaf_keeping_payload();
+                last_child
+            },
+            collector,
+        )
     }
     /// Attempts to remove a branch node's children without using recursion, replacing it with a leaf node, keeping its original payload. Because of that, *this method is only available when the payload for leaf nodes and branch nodes is the same.* Removed children are fed into the specified closure.
     ///
+    /// Unlike with [`try_remove_children_with`], the payload is not passed through a conversion closure, so no removed child has to stand in for it while it is moved.
+    ///
     /// # Errors
     /// Will fail in the following scenarios:
     /// - The node was a leaf node, which cannot have children by definition.
     /// - One or more of the node's children were a branch node, which thus would require recursion to remove.
+    ///
+    /// [`try_remove_children_with`]: #method.try_remove_children_with " "
     pub fn try_remove_children(
         &mut self,
-        collector: impl FnMut(D),
+        mut collector: impl FnMut(D),
     ) -> Result<(), TryRemoveChildrenError> {
-        self.try_remove_children_with(convert::identity, collector)
+        self.check_leaf_children()?;
+        let last_child = self.remove_leaf_children(&mut collector);
+        self.node_mut().value.make_leaf_keeping_payload();
+        collector(last_child);
+        Ok(())
     }
     /// Recursively removes the specified node and all its descendants. Will keep the original payload of the parent node if removing this node results in a transformation of the parent into a leaf, which is why *this method is only available when the payload for leaf nodes and branch nodes is the same.*
     pub fn recursively_remove(self) -> NodeValue<D> {
         algorithms::recursively_remove(self.tree, self.key)
     }
+    /// Moves the node and all its descendants to a different parent node in the same tree, placing it at the specified position among the children of the new parent, without moving any payloads in memory. The old and new parent nodes keep their payloads if they get converted between leaf and branch nodes, which is why *this method is only available when the payload for leaf nodes and branch nodes is the same.*
+    ///
+    /// See [`reparent_to_with`] for how the position is counted.
+    ///
+    /// # Errors
+    /// Will fail in the following scenarios, leaving the tree unchanged:
+    /// - The node was the root node, which cannot have a parent.
+    /// - The key of the new parent node does not point to a node in the tree.
+    /// - The new parent node is the node itself or one of its descendants.
+    /// - The position is greater than the number of children of the new parent node.
+    ///
+    /// [`reparent_to_with`]: #method.reparent_to_with " "
+    pub fn reparent_to(&mut self, new_parent: K, position: usize) -> Result<(), ReparentError> {
+        self.reparent_to_with(new_parent, position, convert::identity, convert::identity)
+    }
 }
 
 impl<'a, B, L, K, S> From<&'a NodeRefMut<'a, B, L, K, S>> for NodeValue<&'a B, &'a L>
@@ -785,9 +1109,9 @@ where
     K: Clone + Debug + Eq,
 {
     fn from(op: &'a NodeRefMut<'a, B, L, K, S>) -> Self {
-        NodeRef {
-            tree: op.tree as &'a _,
-            key: op.key.clone(),
+        unsafe {
+            // SAFETY: NodeRefMut is guaranteed to point to a valid node
+            NodeRef::new_raw_unchecked(op.tree, op.key.clone())
         }
     }
 }
@@ -797,9 +1121,9 @@ where
     K: Clone + Debug + Eq,
 {
     fn from(op: &'a mut NodeRefMut<'a, B, L, K, S>) -> Self {
-        NodeRef {
-            tree: op.tree as &'a _,
-            key: op.key.clone(),
+        unsafe {
+            // SAFETY: NodeRefMut is guaranteed to point to a valid node
+            NodeRef::new_raw_unchecked(op.tree, op.key.clone())
         }
     }
 }
@@ -809,8 +1133,21 @@ where
     K: Clone + Debug + Eq,
 {
     fn from(op: NodeRefMut<'a, B, L, K, S>) -> Self {
-        NodeRef {
-            tree: op.tree as &'a _,
+        unsafe {
+            // SAFETY: NodeRefMut is guaranteed to point to a valid node
+            NodeRef::new_raw_unchecked(op.tree, op.key)
+        }
+    }
+}
+impl<'a, B, L, K, S> From<NodeRefMut<'a, B, L, K, S>>
+    for tree::NodeRefMut<'a, FreeformTree<B, L, K, S>>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn from(op: NodeRefMut<'a, B, L, K, S>) -> Self {
+        Self {
+            tree: op.tree,
             key: op.key,
         }
     }
@@ -0,0 +1,318 @@
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use crate::{storage::Storage, repr::TreeRepr, util::unreachable_debugchecked, NonShiftingStorage};
+use super::{FreeformTree, Node, NodeData};
+
+impl<B, L, K, S> FreeformTree<B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Creates a freeform tree from its [nested enum representation].
+    ///
+    /// # Panics
+    /// Panics if any of the branch nodes in the representation has no children, since those cannot exist in freeform trees.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{freeform_tree::FreeformTree, repr::TreeRepr};
+    ///
+    /// let tree = FreeformTree::<_>::from_repr(TreeRepr::Branch(1, vec![
+    ///     TreeRepr::Leaf(2),
+    ///     TreeRepr::Leaf(3),
+    /// ]));
+    /// let children = tree.root()
+    ///     .children()
+    ///     .unwrap()
+    ///     .map(|child| *child.value().into_inner())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(children, [2, 3]);
+    /// ```
+    ///
+    /// [nested enum representation]: ../repr/index.html " "
+    pub fn from_repr(repr: TreeRepr<B, L>) -> Self {
+        let mut storage = S::new();
+        let root = add_repr(&mut storage, repr);
+        Self { storage, root }
+    }
+    /// Converts the tree into its [nested enum representation], cloning the payloads of all nodes.
+    ///
+    /// [nested enum representation]: ../repr/index.html " "
+    pub fn to_repr(&self) -> TreeRepr<B, L>
+    where
+        B: Clone,
+        L: Clone,
+    {
+        node_to_repr(&self.storage, &self.root)
+    }
+}
+
+impl<B, L, K, S> FreeformTree<B, L, K, S>
+where
+    S: NonShiftingStorage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Replaces the subtree of the node with the specified key with the one described by the [nested enum representation], returning the key of the root of the new subtree, or `None` if the key does not point to a node in the tree. The new subtree takes the place of the old one among the children of its parent, and replacing the subtree of the root node replaces the whole tree.
+    ///
+    /// The nodes of the old subtree are removed one by one, which invalidates keys to them. Since the keys of the other nodes have to stay valid in the meantime, the method is only available for trees backed by a [`NonShiftingStorage`].
+    ///
+    /// # Panics
+    /// Panics if any of the branch nodes in the representation has no children, since those cannot exist in freeform trees.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{freeform_tree::FreeformTree, repr::TreeRepr, Tree};
+    ///
+    /// let mut tree = FreeformTree::<_>::new(0);
+    /// tree.root_mut().make_branch(vec![1, 2, 3]).unwrap();
+    /// let key = tree.root().nth_child(1).unwrap().into_raw_key();
+    ///
+    /// let new_key = tree
+    ///     .replace_subtree(key, TreeRepr::Branch(20, vec![TreeRepr::Leaf(21)]))
+    ///     .unwrap();
+    /// assert_eq!(tree.to_repr(), TreeRepr::Branch(0, vec![
+    ///     TreeRepr::Leaf(1),
+    ///     TreeRepr::Branch(20, vec![TreeRepr::Leaf(21)]),
+    ///     TreeRepr::Leaf(3),
+    /// ]));
+    /// assert_eq!(tree.get(new_key).unwrap().value().into_inner(), &20);
+    /// ```
+    /// Trees backed by storages which shift keys upon removal, such as `Vec`, cannot replace subtrees:
+    /// ```rust,compile_fail
+    /// use charcoal::{freeform_tree::{FreeformTree, Node}, repr::TreeRepr};
+    ///
+    /// let mut tree = FreeformTree::<_, _, usize, Vec<Node<_, _, usize>>>::new(0);
+    /// tree.root_mut().make_branch(vec![1, 2, 3]).unwrap();
+    /// let key = tree.root().nth_child(1).unwrap().into_raw_key();
+    /// tree.replace_subtree(key, TreeRepr::Leaf(20));
+    /// ```
+    ///
+    /// [nested enum representation]: ../repr/index.html " "
+    /// [`NonShiftingStorage`]: ../trait.NonShiftingStorage.html " "
+    #[allow(clippy::needless_pass_by_value)] // Keys are taken by value everywhere else
+    pub fn replace_subtree(&mut self, key: K, replacement: TreeRepr<B, L>) -> Option<K> {
+        let old_root = self.storage.get(&key)?;
+        let parent = old_root.parent.clone();
+        let prev_sibling = old_root.prev_sibling.clone();
+        let next_sibling = old_root.next_sibling.clone();
+        // The new nodes are added first, so that the tree stays intact if building them panics.
+        let new_key = add_repr(&mut self.storage, replacement);
+        remove_subtree(&mut self.storage, &key);
+        if let Some(prev_sibling) = &prev_sibling {
+            unsafe {
+                // SAFETY: sibling keys are always valid
+                self.storage.get_unchecked_mut(prev_sibling)
+            }
+            .next_sibling = Some(new_key.clone());
+        }
+        if let Some(next_sibling) = &next_sibling {
+            unsafe {
+                // SAFETY: as above
+                self.storage.get_unchecked_mut(next_sibling)
+            }
+            .prev_sibling = Some(new_key.clone());
+        }
+        match &parent {
+            Some(parent) => {
+                if let NodeData::Branch {
+                    first_child,
+                    last_child,
+                    ..
+                } = &mut unsafe {
+                    // SAFETY: parent keys are always valid
+                    self.storage.get_unchecked_mut(parent)
+                }
+                .value
+                {
+                    if prev_sibling.is_none() {
+                        *first_child = new_key.clone();
+                    }
+                    if next_sibling.is_none() {
+                        *last_child = new_key.clone();
+                    }
+                }
+            }
+            None => self.root = new_key.clone(),
+        }
+        let new_root = unsafe {
+            // SAFETY: we just added it
+            self.storage.get_unchecked_mut(&new_key)
+        };
+        new_root.parent = parent;
+        new_root.prev_sibling = prev_sibling;
+        new_root.next_sibling = next_sibling;
+        Some(new_key)
+    }
+}
+
+/// Removes the node and all of its descendants from the storage, in post-order. The links of the other nodes to the node are left for the caller to fix.
+fn remove_subtree<B, L, K, S>(storage: &mut S, subtree_root: &K)
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let first_leaf_of = |storage: &S, mut key: K| {
+        while let NodeData::Branch { first_child, .. } = &unsafe {
+            // SAFETY: keys are only taken from the links between the nodes
+            storage.get_unchecked(&key)
+        }
+        .value
+        {
+            key = first_child.clone();
+        }
+        key
+    };
+    let mut key = first_leaf_of(storage, subtree_root.clone());
+    loop {
+        let node = storage.remove(&key);
+        if &key == subtree_root {
+            break;
+        }
+        // Only the links of the nodes which have not been removed yet are followed.
+        key = match (node.next_sibling, node.parent) {
+            (Some(next_sibling), _) => first_leaf_of(storage, next_sibling),
+            (None, Some(parent)) => parent,
+            (None, None) => unsafe {
+                unreachable_debugchecked("the walk never goes above the subtree root")
+            },
+        };
+    }
+}
+
+/// Adds the node and all of its descendants to the storage, returning the key of the node. The parent and sibling links of the node itself are left for the caller to fill in.
+///
+/// The children of every node are added before the node itself, since the branch node needs to know their keys. Instead of recursing, the branch nodes which are still missing some of their children are kept on an explicit stack.
+fn add_repr<B, L, K, S>(storage: &mut S, repr: TreeRepr<B, L>) -> K
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    // The payload of every unfinished branch node, the children which are yet to be added and the
+    // keys of the ones which are added already.
+    let mut stack = Vec::new();
+    let mut repr = repr;
+    loop {
+        let mut key = match repr {
+            TreeRepr::Leaf(payload) => storage.add(unsafe {
+                // SAFETY: the links are filled in when the parent is added, or by the caller
+                Node::leaf(payload, None, None, None)
+            }),
+            TreeRepr::Branch(payload, children) => {
+                let num_children = children.len();
+                let mut children = children.into_iter();
+                repr = children
+                    .next()
+                    .expect("branch nodes of freeform trees must have at least one child");
+                stack.push((payload, children, Vec::with_capacity(num_children)));
+                continue;
+            }
+        };
+        // Hand the added node to its parent, adding the parent as well if that was its last child,
+        // until a parent with children left to add is found.
+        loop {
+            let (_, remaining, added) = match stack.last_mut() {
+                Some(parent) => parent,
+                None => return key,
+            };
+            added.push(key);
+            if let Some(next) = remaining.next() {
+                repr = next;
+                break;
+            }
+            let (payload, _, added) = stack.pop().expect("the stack was checked to be non-empty");
+            key = add_branch(storage, payload, &added);
+        }
+    }
+}
+/// Adds a branch node with the specified children, which must already be in the storage, and links the children to it. The parent and sibling links of the node itself are left for the caller to fill in.
+fn add_branch<B, L, K, S>(storage: &mut S, payload: B, children: &[K]) -> K
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let (first_child, last_child) = match (children.first(), children.last()) {
+        (Some(first_child), Some(last_child)) => (first_child.clone(), last_child.clone()),
+        _ => unsafe { unreachable_debugchecked("branch nodes have at least one child") },
+    };
+    let key = storage.add(Node {
+        value: NodeData::Branch {
+            payload,
+            first_child,
+            last_child,
+            child_count: children.len(),
+        },
+        parent: None,
+        prev_sibling: None,
+        next_sibling: None,
+    });
+    for (i, child_key) in children.iter().enumerate() {
+        let child = unsafe {
+            // SAFETY: the children were added before the node
+            storage.get_unchecked_mut(child_key)
+        };
+        child.parent = Some(key.clone());
+        child.prev_sibling = i.checked_sub(1).map(|i| children[i].clone());
+        child.next_sibling = children.get(i + 1).cloned();
+    }
+    key
+}
+/// Converts the node and all of its descendants into the nested enum representation.
+///
+/// The children of every node are converted before the node itself, since the representation of a branch node owns the ones of its children. Instead of recursing, the branch nodes which are still missing some of their children are kept on an explicit stack.
+fn node_to_repr<B, L, K, S>(storage: &S, key: &K) -> TreeRepr<B, L>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+    B: Clone,
+    L: Clone,
+{
+    // The key and payload of every unfinished branch node and the representations of the children
+    // which are converted already.
+    let mut stack = Vec::new();
+    let mut key = key.clone();
+    loop {
+        let node = unsafe {
+            // SAFETY: keys stored in the tree are guaranteed to be valid
+            storage.get_unchecked(&key)
+        };
+        let mut repr = match &node.value {
+            NodeData::Branch {
+                payload,
+                first_child,
+                child_count,
+                ..
+            } => {
+                stack.push((
+                    key.clone(),
+                    payload.clone(),
+                    Vec::with_capacity(*child_count),
+                ));
+                key = first_child.clone();
+                continue;
+            }
+            NodeData::Leaf(payload) => TreeRepr::Leaf(payload.clone()),
+        };
+        // Hand the converted node to its parent, converting the parent as well if that was its
+        // last child, until a parent with children left to convert is found.
+        loop {
+            let (_, _, converted) = match stack.last_mut() {
+                Some(parent) => parent,
+                None => return repr,
+            };
+            converted.push(repr);
+            let next_sibling = &unsafe {
+                // SAFETY: as above
+                storage.get_unchecked(&key)
+            }
+            .next_sibling;
+            if let Some(next_sibling) = next_sibling {
+                key = next_sibling.clone();
+                break;
+            }
+            let (parent_key, payload, children) =
+                stack.pop().expect("the stack was checked to be non-empty");
+            repr = TreeRepr::Branch(payload, children);
+            key = parent_key;
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use core::{fmt::Debug, convert};
+use crate::{storage::Storage, util::unreachable_debugchecked, NonShiftingStorage};
+use super::{FreeformTree, Node, NodeData, NodeRefMut};
+
+impl<B, L, K, S> FreeformTree<B, L, K, S>
+where
+    S: NonShiftingStorage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Removes the subtree of the `n`-th child of the root node from the tree and returns it as a separate tree, with that child as its root node. If the root node only had one child, it's converted into a leaf node using the closure. Returns `None` and leaves the tree unchanged if the root node has `n` children or less.
+    ///
+    /// The nodes are moved into a new storage one by one, so this takes *O*(*n*) time, where *n* is the number of nodes in the subtree, but does not allocate memory other than the storage of the new tree. Like [`split_off_with`], the method is only available for trees backed by a [`NonShiftingStorage`].
+    ///
+    /// # Panics
+    /// Will panic if the storage of the new tree cannot be created with [`Storage::new`], as is the case for storages which need an allocator to be specified.
+    ///
+    /// [`Storage::new`]: ../storage/trait.Storage.html#method.new " "
+    /// [`split_off_with`]: #method.split_off_with " "
+    /// [`NonShiftingStorage`]: ../trait.NonShiftingStorage.html " "
+    pub fn split_off_root_child_with(
+        &mut self,
+        n: usize,
+        branch_to_leaf: impl FnOnce(B) -> L,
+    ) -> Option<Self> {
+        let child_key = self.root().nth_child(n)?.into_raw_key();
+        Some(self.split_off_subtree(&child_key, branch_to_leaf))
+    }
+    /// Removes the subtree of the node with the specified key from the tree and returns it as a separate tree, with that node as its root node. If the node was the only child of its parent, the parent is converted into a leaf node using the closure. Returns `None` and leaves the tree unchanged if the key does not point to a node in the tree or points to the root node, which cannot be split off since the tree would be left without nodes.
+    ///
+    /// The nodes are moved into a new storage one by one, so this takes *O*(*n*) time, where *n* is the number of nodes in the subtree, but does not allocate memory other than the storage of the new tree. Since the keys of the other nodes have to stay valid while the nodes are removed, the method is only available for trees backed by a [`NonShiftingStorage`].
+    ///
+    /// # Panics
+    /// Will panic if the storage of the new tree cannot be created with [`Storage::new`], as is the case for storages which need an allocator to be specified.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::freeform_tree::FreeformTree;
+    ///
+    /// let mut tree = FreeformTree::<_>::new(0);
+    /// let mut root = tree.root_mut();
+    /// root.make_branch(vec![1]).unwrap();
+    /// root.first_child_mut().unwrap().make_branch(vec![10, 11]).unwrap();
+    /// let key = tree.root().first_child().unwrap().into_raw_key();
+    ///
+    /// let split_off = tree.split_off_with(key, |payload| payload + 100).unwrap();
+    /// // The root lost its only child
+    /// assert_eq!(tree.root().value().into_inner(), &100);
+    /// assert!(tree.root().is_leaf());
+    /// assert_eq!(split_off.root().value().into_inner(), &1);
+    /// assert_eq!(split_off.root().num_children(), 2);
+    /// assert!(tree.split_off_with(tree.root().into_raw_key(), |payload| payload).is_none());
+    /// ```
+    /// Trees backed by storages which shift keys upon removal, such as `Vec`, cannot split off subtrees:
+    /// ```rust,compile_fail
+    /// use charcoal::freeform_tree::{FreeformTree, Node};
+    ///
+    /// let mut tree = FreeformTree::<_, _, usize, Vec<Node<_, _, usize>>>::new(0);
+    /// tree.root_mut().make_branch(vec![1, 2, 3]).unwrap();
+    /// let key = tree.root().first_child().unwrap().into_raw_key();
+    /// tree.split_off_with(key, |payload| payload);
+    /// ```
+    ///
+    /// [`Storage::new`]: ../storage/trait.Storage.html#method.new " "
+    /// [`NonShiftingStorage`]: ../trait.NonShiftingStorage.html " "
+    #[allow(clippy::needless_pass_by_value)] // Keys are taken by value everywhere else
+    pub fn split_off_with(&mut self, key: K, branch_to_leaf: impl FnOnce(B) -> L) -> Option<Self> {
+        self.storage.get(&key)?.parent.as_ref()?;
+        Some(self.split_off_subtree(&key, branch_to_leaf))
+    }
+    /// Removes the subtree of the specified node, which must not be the root node, and returns it as a separate tree, converting the parent of the node into a leaf node using the closure if the node was its only child.
+    ///
+    /// The nodes are moved in post-order, so that the children of a node are already in the new storage when the node itself is moved. The keys of the moved children are kept in the child links of their parent in the old storage, which are not needed anymore once the walk has descended past it, and end up being the correct links for the new storage.
+    fn split_off_subtree(&mut self, subtree_root: &K, branch_to_leaf: impl FnOnce(B) -> L) -> Self {
+        let parent_key = unsafe {
+            // SAFETY: the callers only pass keys taken from the tree
+            self.storage.get_unchecked(subtree_root)
+        }
+        .parent
+        .clone()
+        .unwrap_or_else(|| unsafe { unreachable_debugchecked("cannot split off the root node") });
+        let was_only_child = unsafe {
+            // SAFETY: as above
+            NodeRefMut::new_raw_unchecked(self, subtree_root.clone())
+        }
+        .unlink(&parent_key);
+        if was_only_child {
+            unsafe {
+                // SAFETY: parent keys are always valid
+                self.storage.get_unchecked_mut(&parent_key)
+            }
+            .value
+            .make_leaf_or_abort(branch_to_leaf);
+        }
+        let mut storage = S::new();
+        let mut key = self.first_leaf_of(subtree_root.clone());
+        loop {
+            let Node {
+                value,
+                parent,
+                prev_sibling,
+                next_sibling,
+            } = self.storage.remove(&key);
+            let first_new_child = match &value {
+                NodeData::Branch { first_child, .. } => Some(first_child.clone()),
+                NodeData::Leaf(..) => None,
+            };
+            let new_key = storage.add(Node {
+                value,
+                parent: None,
+                prev_sibling: None,
+                next_sibling: None,
+            });
+            if let Some(mut child_key) = first_new_child {
+                loop {
+                    let child = unsafe {
+                        // SAFETY: the children were added to the new storage before their parent
+                        storage.get_unchecked_mut(&child_key)
+                    };
+                    child.parent = Some(new_key.clone());
+                    match &child.next_sibling {
+                        Some(next_sibling_key) => child_key.clone_from(next_sibling_key),
+                        None => break,
+                    }
+                }
+            }
+            if &key == subtree_root {
+                return Self {
+                    storage,
+                    root: new_key,
+                };
+            }
+            let parent_key = parent.unwrap_or_else(|| unsafe {
+                unreachable_debugchecked("the walk never goes above the subtree root")
+            });
+            let parent = unsafe {
+                // SAFETY: parents are moved after their children
+                self.storage.get_unchecked_mut(&parent_key)
+            };
+            if let NodeData::Branch {
+                first_child,
+                last_child,
+                ..
+            } = &mut parent.value
+            {
+                if prev_sibling.is_none() {
+                    *first_child = new_key.clone();
+                } else {
+                    let prev_sibling_key = last_child.clone();
+                    unsafe {
+                        // SAFETY: the last child link holds a key in the new storage at this point
+                        storage.get_unchecked_mut(&prev_sibling_key)
+                    }
+                    .next_sibling = Some(new_key.clone());
+                    unsafe {
+                        // SAFETY: the node was just added
+                        storage.get_unchecked_mut(&new_key)
+                    }
+                    .prev_sibling = Some(prev_sibling_key);
+                }
+                *last_child = new_key;
+            } else {
+                unsafe { unreachable_debugchecked("parent nodes cannot be leaves") }
+            }
+            key = next_sibling.map_or(parent_key, |next_sibling_key| {
+                self.first_leaf_of(next_sibling_key)
+            });
+        }
+    }
+    /// Returns the key of the first node in post-order in the subtree of the specified node, found by following the first child links.
+    fn first_leaf_of(&self, mut key: K) -> K {
+        while let NodeData::Branch { first_child, .. } = &unsafe {
+            // SAFETY: key validity guarantee
+            self.storage.get_unchecked(&key)
+        }
+        .value
+        {
+            key = first_child.clone();
+        }
+        key
+    }
+}
+impl<B, L, K, S> FreeformTree<B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Makes the node with the specified key the root node of the tree by reversing the parent links along the path between it and the current root node, without moving any payloads in memory. Returns `false` and leaves the tree unchanged if the key does not point to a node in the tree.
+    ///
+    /// Each node on the path becomes the last child of the node which was its child. If the new root node is a leaf node, it's converted into a branch node using the second closure, and if the old root node only had one child, it's converted into a leaf node using the first closure.
+    ///
+    /// The links are reversed from the new root node upwards, which takes *O*(*d*) time without allocating memory, where *d* is the depth of the new root node.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::freeform_tree::FreeformTree;
+    ///
+    /// let mut tree = FreeformTree::<_>::new("A");
+    /// let mut root = tree.root_mut();
+    /// root.make_branch(vec!["B"]).unwrap();
+    /// root.first_child_mut().unwrap().make_branch(vec!["C", "D"]).unwrap();
+    /// let c_key = tree.root().first_child().unwrap().first_child().unwrap().into_raw_key();
+    ///
+    /// assert!(tree.reroot_at_with(c_key, |payload| payload, |payload| payload));
+    /// // C is now the root, B is its only child, and A and D are the children of B
+    /// let root = tree.root();
+    /// assert_eq!(root.value().into_inner(), &"C");
+    /// let b = root.first_child().unwrap();
+    /// assert_eq!(b.value().into_inner(), &"B");
+    /// let b_children = b.children().unwrap().map(|child| *child.value().into_inner());
+    /// assert_eq!(b_children.collect::<Vec<_>>(), ["D", "A"]);
+    /// assert!(b.last_child().unwrap().is_leaf());
+    /// ```
+    pub fn reroot_at_with(
+        &mut self,
+        key: K,
+        branch_to_leaf: impl FnOnce(B) -> L,
+        leaf_to_branch: impl FnOnce(L) -> B,
+    ) -> bool {
+        let mut child_key = key.clone();
+        let mut parent_key = if let Some(node) = self.storage.get(&key) {
+            if let Some(parent_key) = &node.parent {
+                parent_key.clone()
+            } else {
+                // Already the root node
+                return true;
+            }
+        } else {
+            return false;
+        };
+        let mut parent_emptied = unsafe {
+            // SAFETY: the key was checked above
+            NodeRefMut::new_raw_unchecked(self, child_key.clone())
+        }
+        .unlink(&parent_key);
+        let mut leaf_to_branch = Some(leaf_to_branch);
+        loop {
+            let grandparent_key = unsafe {
+                // SAFETY: parent keys are always valid
+                self.storage.get_unchecked(&parent_key)
+            }
+            .parent
+            .clone();
+            let mut parent = unsafe {
+                // SAFETY: as above
+                NodeRefMut::new_raw_unchecked(self, parent_key.clone())
+            };
+            // The sibling links of the parent have to be used before they're replaced by linking
+            // the parent to its new parent
+            let grandparent_emptied = grandparent_key
+                .as_ref()
+                .map(|grandparent_key| parent.unlink(grandparent_key));
+            let position = match &unsafe {
+                // SAFETY: key validity guarantee
+                self.storage.get_unchecked(&child_key)
+            }
+            .value
+            {
+                NodeData::Branch { child_count, .. } => *child_count,
+                NodeData::Leaf(..) => 0,
+            };
+            unsafe {
+                // SAFETY: as above
+                NodeRefMut::new_raw_unchecked(self, parent_key.clone())
+            }
+            .link(child_key, position, |payload| {
+                // Nodes which used to be parents are still branch nodes, even if they have no
+                // children left, so only the new root node can be a leaf node
+                let leaf_to_branch = leaf_to_branch.take().unwrap_or_else(|| unsafe {
+                    unreachable_debugchecked("only the new root node can be a leaf node")
+                });
+                leaf_to_branch(payload)
+            });
+            if let (Some(grandparent_key), Some(grandparent_emptied)) =
+                (grandparent_key, grandparent_emptied)
+            {
+                child_key = parent_key;
+                parent_key = grandparent_key;
+                parent_emptied = grandparent_emptied;
+            } else {
+                // The parent was the old root node, which only loses a child
+                if parent_emptied {
+                    unsafe {
+                        // SAFETY: as above
+                        self.storage.get_unchecked_mut(&parent_key)
+                    }
+                    .value
+                    .make_leaf_or_abort(branch_to_leaf);
+                }
+                break;
+            }
+        }
+        let new_root = unsafe {
+            // SAFETY: the key was checked at the beginning
+            self.storage.get_unchecked_mut(&key)
+        };
+        new_root.parent = None;
+        new_root.prev_sibling = None;
+        new_root.next_sibling = None;
+        self.root = key;
+        true
+    }
+}
+impl<D, K, S> FreeformTree<D, D, K, S>
+where
+    S: NonShiftingStorage<Element = Node<D, D, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Removes the subtree of the `n`-th child of the root node from the tree and returns it as a separate tree, with that child as its root node. If the root node only had one child, it becomes a leaf node, keeping its payload, which is why *this method is only available when the payload for leaf nodes and branch nodes is the same.* Returns `None` and leaves the tree unchanged if the root node has `n` children or less.
+    ///
+    /// # Panics
+    /// Will panic if the storage of the new tree cannot be created with [`Storage::new`], as is the case for storages which need an allocator to be specified.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::freeform_tree::FreeformTree;
+    ///
+    /// let mut tree = FreeformTree::<_>::new(0);
+    /// let mut root = tree.root_mut();
+    /// root.make_branch(vec![1, 2]).unwrap();
+    /// root.last_child_mut().unwrap().make_branch(vec![20, 21]).unwrap();
+    ///
+    /// let split_off = tree.split_off_root_child(1).unwrap();
+    /// assert_eq!(tree.root().num_children(), 1);
+    /// assert_eq!(split_off.num_nodes(), 3);
+    /// assert_eq!(split_off.root().value().into_inner(), &2);
+    /// assert!(tree.split_off_root_child(1).is_none());
+    /// ```
+    ///
+    /// [`Storage::new`]: ../storage/trait.Storage.html#method.new " "
+    pub fn split_off_root_child(&mut self, n: usize) -> Option<Self> {
+        self.split_off_root_child_with(n, convert::identity)
+    }
+    /// Removes the subtree of the node with the specified key from the tree and returns it as a separate tree, with that node as its root node. If the node was the only child of its parent, the parent becomes a leaf node, keeping its payload, which is why *this method is only available when the payload for leaf nodes and branch nodes is the same.* Returns `None` and leaves the tree unchanged if the key does not point to a node in the tree or points to the root node.
+    ///
+    /// # Panics
+    /// Will panic if the storage of the new tree cannot be created with [`Storage::new`], as is the case for storages which need an allocator to be specified.
+    ///
+    /// [`Storage::new`]: ../storage/trait.Storage.html#method.new " "
+    pub fn split_off(&mut self, key: K) -> Option<Self> {
+        self.split_off_with(key, convert::identity)
+    }
+}
+impl<D, K, S> FreeformTree<D, D, K, S>
+where
+    S: Storage<Element = Node<D, D, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Makes the node with the specified key the root node of the tree by reversing the parent links along the path between it and the current root node, without moving any payloads in memory. Nodes which get converted between leaf and branch nodes keep their payloads, which is why *this method is only available when the payload for leaf nodes and branch nodes is the same.* Returns `false` and leaves the tree unchanged if the key does not point to a node in the tree.
+    ///
+    /// See [`reroot_at_with`] for where the nodes on the path end up.
+    ///
+    /// [`reroot_at_with`]: #method.reroot_at_with " "
+    pub fn reroot_at(&mut self, key: K) -> bool {
+        self.reroot_at_with(key, convert::identity, convert::identity)
+    }
+}
@@ -0,0 +1,418 @@
+use core::{
+    fmt::Debug,
+    iter::{self, Empty},
+};
+use alloc::vec::Vec;
+use crate::{
+    journal::{cursor_at, path_to},
+    storage::{Storage, DefaultStorage},
+    traversal::{CursorResult, VisitorDirection},
+    NodeValue,
+    Traversable,
+    TraversableMut,
+    TryRemoveLeafError,
+    TryRemoveBranchError,
+    TryRemoveChildrenError,
+    TryAddChildError,
+};
+use super::{FreeformTree, Node, NodeRefMut};
+
+impl<B, L, K, S> FreeformTree<B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+    B: Clone,
+    L: Clone,
+{
+    /// Starts a transaction on the tree, which records the changes made through it so that they can be reverted as a whole with [`rollback`].
+    ///
+    /// See [`Transaction`] for the details.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{FreeformTree, NodeValue, Traversable, TraversableMut};
+    ///
+    /// let mut tree = FreeformTree::<_>::new("Root");
+    /// tree.root_mut().make_branch(vec!["Left", "Right"]).unwrap();
+    /// let before = tree.to_repr();
+    ///
+    /// let mut transaction = tree.begin_transaction();
+    /// let root = transaction.cursor_to_root();
+    /// let left = transaction.nth_child_of(&root, 0).unwrap();
+    /// transaction.try_add_child(&left, "Left of Left", |x| x).unwrap();
+    /// let right = transaction.nth_child_of(&root, 1).unwrap();
+    /// transaction.try_remove_leaf(&right, |x| x).unwrap();
+    /// if let NodeValue::Branch(payload) = transaction.value_mut_of(&root) {
+    ///     *payload = "New root";
+    /// }
+    /// // Changed our mind, put everything back.
+    /// transaction.rollback();
+    /// assert_eq!(tree.to_repr(), before);
+    /// ```
+    ///
+    /// [`rollback`]: struct.Transaction.html#method.rollback " "
+    /// [`Transaction`]: struct.Transaction.html " "
+    pub fn begin_transaction(&mut self) -> Transaction<'_, B, L, K, S> {
+        Transaction {
+            tree: self,
+            undo_log: Vec::new(),
+        }
+    }
+}
+
+/// A set of changes made to a freeform tree which can be reverted as a whole, created by [`begin_transaction`].
+///
+/// The transaction borrows the tree mutably and implements [`Traversable`] and [`TraversableMut`], making the changes to the tree right away while recording how to revert them. Calling [`rollback`] reverts all of the changes in reverse order, while [`commit`] keeps them. Dropping the transaction without calling either keeps the changes as well.
+///
+/// Reverting a change requires the payloads which the change consumed, such as the payloads of removed nodes or the previous payloads of nodes converted between leaf and branch nodes, which is why the payloads have to be `Clone`. Reverting a change of the payload of a node also requires its previous payload, so every call to `value_mut_of` clones the payload, regardless of whether it's actually changed afterwards.
+///
+/// The nodes which are removed and then brought back by a rollback are added to the storage anew, so cursors to them become invalid. Since nodes are identified by their position in the tree while reverting changes, the rest of the tree is restored exactly.
+///
+/// [`begin_transaction`]: struct.FreeformTree.html#method.begin_transaction " "
+/// [`Traversable`]: ../traversal/trait.Traversable.html " "
+/// [`TraversableMut`]: ../traversal/trait.TraversableMut.html " "
+/// [`rollback`]: #method.rollback " "
+/// [`commit`]: #method.commit " "
+#[derive(Debug)]
+#[must_use = "dropping a transaction without rolling it back keeps the changes made through it"]
+pub struct Transaction<'a, B, L = B, K = usize, S = DefaultStorage<Node<B, L, K>>>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    tree: &'a mut FreeformTree<B, L, K, S>,
+    undo_log: Vec<Undo<B, L>>,
+}
+impl<B, L, K, S> Transaction<'_, B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+    B: Clone,
+    L: Clone,
+{
+    /// Returns a reference to the tree.
+    #[must_use]
+    pub fn tree(&self) -> &FreeformTree<B, L, K, S> {
+        self.tree
+    }
+    /// Returns the number of changes made through the transaction so far.
+    #[must_use]
+    pub fn num_changes(&self) -> usize {
+        self.undo_log.len()
+    }
+    /// Keeps the changes made through the transaction, ending it.
+    pub fn commit(self) {}
+    /// Reverts all changes made through the transaction, in reverse order, ending it.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn rollback(mut self) {
+        while let Some(undo) = self.undo_log.pop() {
+            undo.apply(self.tree);
+        }
+    }
+
+    /// Returns a clone of the payload of the parent of the node if it's the only child, since the parent is converted into a leaf node if the node is removed.
+    fn payload_of_parent_if_only_child(&self, cursor: &K) -> Option<B> {
+        let parent = self.tree.parent_of(cursor)?;
+        if self.tree.num_children_of(&parent) != 1 {
+            return None;
+        }
+        match self.tree.value_of(&parent) {
+            NodeValue::Branch(payload) => Some(payload.clone()),
+            NodeValue::Leaf(..) => None,
+        }
+    }
+}
+
+impl<B, L, K, S> Traversable for Transaction<'_, B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+    B: Clone,
+    L: Clone,
+{
+    type Leaf = L;
+    type Branch = B;
+    type Cursor = K;
+
+    fn advance_cursor<V>(
+        &self,
+        cursor: Self::Cursor,
+        direction: VisitorDirection<Self::Cursor, V>,
+    ) -> CursorResult<Self::Cursor> {
+        self.tree.advance_cursor(cursor, direction)
+    }
+    fn cursor_to_root(&self) -> Self::Cursor {
+        self.tree.cursor_to_root()
+    }
+    fn value_of(&self, cursor: &Self::Cursor) -> NodeValue<&'_ Self::Branch, &'_ Self::Leaf> {
+        self.tree.value_of(cursor)
+    }
+    fn parent_of(&self, cursor: &Self::Cursor) -> Option<Self::Cursor> {
+        self.tree.parent_of(cursor)
+    }
+    fn num_children_of(&self, cursor: &Self::Cursor) -> usize {
+        self.tree.num_children_of(cursor)
+    }
+    fn nth_child_of(&self, cursor: &Self::Cursor, child_num: usize) -> Option<Self::Cursor> {
+        self.tree.nth_child_of(cursor, child_num)
+    }
+    fn contains_cursor(&self, cursor: &Self::Cursor) -> bool {
+        self.tree.contains_cursor(cursor)
+    }
+}
+impl<B, L, K, S> TraversableMut for Transaction<'_, B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+    B: Clone,
+    L: Clone,
+{
+    const CAN_REMOVE_INDIVIDUAL_CHILDREN: bool = true;
+    const CAN_ADD_INDIVIDUAL_CHILDREN: bool = true;
+    type PackedChildren = Empty<L>;
+
+    fn value_mut_of(
+        &mut self,
+        cursor: &Self::Cursor,
+    ) -> NodeValue<&'_ mut Self::Branch, &'_ mut Self::Leaf> {
+        let value = match self.tree.value_of(cursor) {
+            NodeValue::Branch(payload) => NodeValue::Branch(payload.clone()),
+            NodeValue::Leaf(payload) => NodeValue::Leaf(payload.clone()),
+        };
+        self.undo_log.push(Undo::SetValue {
+            node: path_to(&*self.tree, cursor),
+            value,
+        });
+        self.tree.value_mut_of(cursor)
+    }
+    fn try_remove_leaf<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<Self::Leaf, TryRemoveLeafError> {
+        let parent_payload = self.payload_of_parent_if_only_child(cursor);
+        let mut path = path_to(&*self.tree, cursor);
+        let leaf = self.tree.try_remove_leaf(cursor, branch_to_leaf)?;
+        let index = path.pop().expect("the root node cannot be removed");
+        self.undo_log.push(Undo::InsertLeaf {
+            parent: path,
+            index,
+            payload: leaf.clone(),
+            parent_payload,
+        });
+        Ok(leaf)
+    }
+    fn try_remove_branch_into<BtL: FnOnce(Self::Branch) -> Self::Leaf, C: FnMut(Self::Leaf)>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+        mut collector: C,
+    ) -> Result<Self::Branch, TryRemoveBranchError> {
+        let parent_payload = self.payload_of_parent_if_only_child(cursor);
+        let mut path = path_to(&*self.tree, cursor);
+        let mut children = Vec::with_capacity(self.tree.num_children_of(cursor));
+        let branch = self
+            .tree
+            .try_remove_branch_into(cursor, branch_to_leaf, |child| {
+                children.push(child.clone());
+                collector(child);
+            })?;
+        let index = path.pop().expect("the root node cannot be removed");
+        self.undo_log.push(Undo::InsertBranch {
+            parent: path,
+            index,
+            payload: branch.clone(),
+            children,
+            parent_payload,
+        });
+        Ok(branch)
+    }
+    fn try_remove_children_into<BtL: FnOnce(Self::Branch) -> Self::Leaf, C: FnMut(Self::Leaf)>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+        mut collector: C,
+    ) -> Result<(), TryRemoveChildrenError> {
+        let payload = match self.tree.value_of(cursor) {
+            NodeValue::Branch(payload) => payload.clone(),
+            NodeValue::Leaf(..) => return Err(TryRemoveChildrenError::WasLeafNode),
+        };
+        let mut children = Vec::with_capacity(self.tree.num_children_of(cursor));
+        self.tree
+            .try_remove_children_into(cursor, branch_to_leaf, |child| {
+                children.push(child.clone());
+                collector(child);
+            })?;
+        self.undo_log.push(Undo::RestoreChildren {
+            node: path_to(&*self.tree, cursor),
+            payload,
+            children,
+        });
+        Ok(())
+    }
+    fn try_add_child<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        child_payload: Self::Leaf,
+        leaf_to_branch: LtB,
+    ) -> Result<Self::Cursor, TryAddChildError<Self::Leaf>> {
+        let parent_payload = match self.tree.value_of(cursor) {
+            NodeValue::Leaf(payload) => Some(payload.clone()),
+            NodeValue::Branch(..) => None,
+        };
+        let child = self
+            .tree
+            .try_add_child(cursor, child_payload, leaf_to_branch)?;
+        self.undo_log.push(Undo::RemoveLastChild {
+            parent: path_to(&*self.tree, cursor),
+            parent_payload,
+        });
+        Ok(child)
+    }
+}
+
+/// The way to revert a change made through a transaction. Nodes are identified by their path, the list of child indices which leads from the root node to the node.
+#[derive(Clone, Debug)]
+enum Undo<B, L> {
+    /// Reverts the addition of a child by removing the last child of the node, restoring the leaf payload of the node if it was a leaf node before.
+    RemoveLastChild {
+        parent: Vec<usize>,
+        parent_payload: Option<L>,
+    },
+    /// Reverts the removal of a leaf node by adding it back, restoring the branch payload of its parent if the node was its only child.
+    InsertLeaf {
+        parent: Vec<usize>,
+        index: usize,
+        payload: L,
+        parent_payload: Option<B>,
+    },
+    /// Reverts the removal of a branch node and its leaf children by adding them back, restoring the branch payload of its parent if the node was its only child.
+    InsertBranch {
+        parent: Vec<usize>,
+        index: usize,
+        payload: B,
+        children: Vec<L>,
+        parent_payload: Option<B>,
+    },
+    /// Reverts the removal of the children of a node by adding them back, along with its branch payload.
+    RestoreChildren {
+        node: Vec<usize>,
+        payload: B,
+        children: Vec<L>,
+    },
+    /// Reverts a change of the payload of a node.
+    SetValue {
+        node: Vec<usize>,
+        value: NodeValue<B, L>,
+    },
+}
+impl<B, L: Clone> Undo<B, L> {
+    fn apply<K, S>(self, tree: &mut FreeformTree<B, L, K, S>)
+    where
+        S: Storage<Element = Node<B, L, K>, Key = K>,
+        K: Clone + Debug + Eq,
+    {
+        match self {
+            Self::RemoveLastChild {
+                parent,
+                parent_payload,
+            } => {
+                let parent = key_at(tree, &parent);
+                let child = tree
+                    .num_children_of(&parent)
+                    .checked_sub(1)
+                    .and_then(|index| tree.nth_child_of(&parent, index))
+                    .expect("the node has no children");
+                tree.try_remove_leaf(&child, |_| {
+                    parent_payload.expect("the node was a branch node before")
+                })
+                .expect("the added child is not a leaf node anymore");
+            }
+            Self::InsertLeaf {
+                parent,
+                index,
+                payload,
+                parent_payload,
+            } => {
+                let parent = key_at(tree, &parent);
+                insert_leaf(tree, parent, index, payload, parent_payload);
+            }
+            Self::InsertBranch {
+                parent,
+                index,
+                payload,
+                children,
+                parent_payload,
+            } => {
+                let parent = key_at(tree, &parent);
+                // The node is added as a leaf node with a copy of the payload of its first child
+                // at first, which is then replaced by the payload of the branch node
+                let stand_in = children
+                    .first()
+                    .cloned()
+                    .expect("branch nodes always have children");
+                insert_leaf(tree, parent.clone(), index, stand_in, parent_payload);
+                let node = tree
+                    .nth_child_of(&parent, index)
+                    .expect("the node was just added");
+                node_at(tree, node)
+                    .make_branch_with(children, |_| payload)
+                    .unwrap_or_else(|_| panic!("the node was just added as a leaf node"));
+            }
+            Self::RestoreChildren {
+                node,
+                payload,
+                children,
+            } => {
+                let node = key_at(tree, &node);
+                node_at(tree, node)
+                    .make_branch_with(children, |_| payload)
+                    .unwrap_or_else(|_| panic!("the tree does not match the undo log"));
+            }
+            Self::SetValue { node, value } => {
+                let node = key_at(tree, &node);
+                match (tree.value_mut_of(&node), value) {
+                    (NodeValue::Branch(old), NodeValue::Branch(new)) => *old = new,
+                    (NodeValue::Leaf(old), NodeValue::Leaf(new)) => *old = new,
+                    _ => panic!("the tree does not match the undo log"),
+                }
+            }
+        }
+    }
+}
+
+/// Returns the key of the node at the specified path.
+fn key_at<B, L, K, S>(tree: &FreeformTree<B, L, K, S>, path: &[usize]) -> K
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    cursor_at(tree, path).expect("the tree does not match the undo log")
+}
+fn node_at<B, L, K, S>(tree: &mut FreeformTree<B, L, K, S>, key: K) -> NodeRefMut<'_, B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    NodeRefMut::new_raw(tree, key).expect("the key was just looked up")
+}
+/// Adds a leaf child to the node at the specified index, converting the node into a branch node with the specified payload if it's a leaf node.
+fn insert_leaf<B, L, K, S>(
+    tree: &mut FreeformTree<B, L, K, S>,
+    parent: K,
+    index: usize,
+    payload: L,
+    parent_payload: Option<B>,
+) where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let mut parent = node_at(tree, parent);
+    if let Some(parent_payload) = parent_payload {
+        parent
+            .make_branch_with(iter::once(payload), |_| parent_payload)
+            .unwrap_or_else(|_| panic!("the tree does not match the undo log"));
+    } else {
+        parent.insert_leaf_child(index, payload);
+    }
+}
@@ -0,0 +1,282 @@
+use core::fmt::{self, Formatter, Debug, Display};
+use alloc::vec::Vec;
+use crate::{
+    storage::{Storage, DefaultStorage},
+    tree::PreorderEvent,
+};
+use super::{FreeformTree, Node, NodeData};
+
+/// A builder which constructs a freeform tree incrementally from a stream of [pre-order events].
+///
+/// The writer is fed with [`push_preorder`], one event at a time, which makes it possible to build trees directly from event-based parsers, such as SAX-style XML or JSON parsers, without creating an intermediate nested representation. Nodes are added to the storage as soon as they are complete: leaf nodes right away and branch nodes once their `Exit` event arrives. Once the root node is complete, [`finish`] returns the tree.
+///
+/// Branch nodes of freeform trees must have at least one child, so an `Exit` event right after an `Enter` event is rejected.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{freeform_tree::{FreeformTree, TreeWriter}, tree::PreorderEvent};
+/// use charcoal::repr::TreeRepr;
+///
+/// let mut writer = TreeWriter::<_>::new();
+/// writer.push_preorder(PreorderEvent::Enter("Root")).unwrap();
+/// writer.push_preorder(PreorderEvent::Leaf("Left")).unwrap();
+/// writer.push_preorder(PreorderEvent::Enter("Right")).unwrap();
+/// writer.push_preorder(PreorderEvent::Leaf("Right of Right")).unwrap();
+/// assert_eq!(writer.depth(), 2);
+/// writer.push_preorder(PreorderEvent::Exit).unwrap();
+/// writer.push_preorder(PreorderEvent::Exit).unwrap();
+///
+/// let tree = writer.finish().unwrap();
+/// assert_eq!(
+///     tree.to_repr(),
+///     TreeRepr::Branch("Root", vec![
+///         TreeRepr::Leaf("Left"),
+///         TreeRepr::Branch("Right", vec![TreeRepr::Leaf("Right of Right")]),
+///     ]),
+/// );
+/// ```
+///
+/// [pre-order events]: ../tree/enum.PreorderEvent.html " "
+/// [`push_preorder`]: #method.push_preorder " "
+/// [`finish`]: #method.finish " "
+#[derive(Debug)]
+pub struct TreeWriter<B, L = B, K = usize, S = DefaultStorage<Node<B, L, K>>>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    storage: S,
+    open_branches: Vec<OpenBranch<B, K>>,
+    root: Option<K>,
+}
+/// A branch node which has not received its `Exit` event yet, and thus is not in the storage.
+#[derive(Debug)]
+struct OpenBranch<B, K> {
+    payload: B,
+    /// The first and last children added so far, if any.
+    children: Option<(K, K)>,
+    child_count: usize,
+}
+impl<B, L, K, S> TreeWriter<B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Creates a writer which has not received any events yet.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            storage: S::new(),
+            open_branches: Vec::new(),
+            root: None,
+        }
+    }
+    /// Creates a writer with the specified capacity for the storage of the tree.
+    ///
+    /// # Panics
+    /// The storage may panic if it has fixed capacity and the specified value does not match it.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            storage: S::with_capacity(capacity),
+            open_branches: Vec::new(),
+            root: None,
+        }
+    }
+    /// Returns the number of branch nodes which have been started with an `Enter` event but not ended with an `Exit` event yet.
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.open_branches.len()
+    }
+    /// Returns `true` if the root node is complete and the tree can be retrieved with [`finish`], `false` otherwise.
+    ///
+    /// [`finish`]: #method.finish " "
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.root.is_some()
+    }
+    /// Adds the next event to the tree being built.
+    ///
+    /// # Errors
+    /// Will fail if the event cannot follow the events pushed before it, in which case the writer is left unchanged, with the exception of the `Exit` event for a branch node without children, which also discards the matching `Enter` event. See [`PreorderError`] for the details.
+    ///
+    /// # Panics
+    /// The storage may panic if it has fixed capacity and is full.
+    ///
+    /// [`PreorderError`]: enum.PreorderError.html " "
+    pub fn push_preorder(&mut self, event: PreorderEvent<B, L>) -> Result<(), PreorderError<B, L>> {
+        if self.root.is_some() {
+            return Err(PreorderError::AfterRoot(event));
+        }
+        match event {
+            PreorderEvent::Enter(payload) => self.open_branches.push(OpenBranch {
+                payload,
+                children: None,
+                child_count: 0,
+            }),
+            PreorderEvent::Leaf(payload) => {
+                let key = self.storage.add(unsafe {
+                    // SAFETY: the links are filled in by add_to_parent
+                    Node::leaf(payload, None, None, None)
+                });
+                self.add_to_parent(key);
+            }
+            PreorderEvent::Exit => {
+                let branch = self
+                    .open_branches
+                    .pop()
+                    .ok_or(PreorderError::UnmatchedExit)?;
+                let (first_child, last_child) = match branch.children {
+                    Some(children) => children,
+                    None => return Err(PreorderError::EmptyBranch(branch.payload)),
+                };
+                let key = self.storage.add(Node {
+                    value: NodeData::Branch {
+                        payload: branch.payload,
+                        first_child: first_child.clone(),
+                        last_child,
+                        child_count: branch.child_count,
+                    },
+                    parent: None,
+                    prev_sibling: None,
+                    next_sibling: None,
+                });
+                // The children were added before their parent, so their parent links are only
+                // known now.
+                let mut current_child = first_child;
+                loop {
+                    let child = unsafe {
+                        // SAFETY: the children were added to the storage by previous events
+                        self.storage.get_unchecked_mut(&current_child)
+                    };
+                    child.parent = Some(key.clone());
+                    match &child.next_sibling {
+                        Some(next_sibling) => current_child.clone_from(next_sibling),
+                        None => break,
+                    }
+                }
+                self.add_to_parent(key);
+            }
+        }
+        Ok(())
+    }
+    /// Returns the tree built from the events pushed so far.
+    ///
+    /// # Errors
+    /// Will fail with [`PreorderError::Incomplete`] if the root node is not complete yet, i.e. if no events were pushed or if there are branch nodes which have not been ended with an `Exit` event.
+    ///
+    /// [`PreorderError::Incomplete`]: enum.PreorderError.html#variant.Incomplete " "
+    pub fn finish(self) -> Result<FreeformTree<B, L, K, S>, PreorderError<B, L>> {
+        match self.root {
+            Some(root) => Ok(FreeformTree {
+                storage: self.storage,
+                root,
+            }),
+            None => Err(PreorderError::Incomplete),
+        }
+    }
+
+    /// Makes the node with the specified key the last child of the innermost open branch node, or the root node if there are none.
+    fn add_to_parent(&mut self, key: K) {
+        let parent = if let Some(parent) = self.open_branches.last_mut() {
+            parent
+        } else {
+            self.root = Some(key);
+            return;
+        };
+        parent.child_count += 1;
+        parent.children = Some(match parent.children.take() {
+            Some((first_child, last_child)) => {
+                unsafe {
+                    // SAFETY: the last child was added to the storage by a previous event
+                    self.storage.get_unchecked_mut(&last_child)
+                }
+                .next_sibling = Some(key.clone());
+                unsafe {
+                    // SAFETY: we just added it
+                    self.storage.get_unchecked_mut(&key)
+                }
+                .prev_sibling = Some(last_child);
+                (first_child, key)
+            }
+            None => (key.clone(), key),
+        });
+    }
+}
+impl<B, L, K, S> Default for TreeWriter<B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B, L, K, S> FreeformTree<B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Creates a freeform tree from a sequence of [pre-order events], using a [`TreeWriter`].
+    ///
+    /// # Errors
+    /// Will fail if one of the events cannot follow the events before it, or if the events end before the root node is complete. Events after the error are not consumed. See [`PreorderError`] for the details.
+    ///
+    /// # Panics
+    /// The storage may panic if it has fixed capacity and cannot fit all of the nodes.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{freeform_tree::{FreeformTree, PreorderError}, tree::PreorderEvent::*};
+    ///
+    /// let tree = FreeformTree::<_>::from_preorder(vec![Enter(1), Leaf(2), Leaf(3), Exit]).unwrap();
+    /// assert_eq!(tree.root().children().unwrap().count(), 2);
+    ///
+    /// let result = FreeformTree::<_>::from_preorder(vec![Enter(1), Enter(2), Exit]);
+    /// assert!(matches!(result, Err(PreorderError::EmptyBranch(2))));
+    /// ```
+    ///
+    /// [pre-order events]: ../tree/enum.PreorderEvent.html " "
+    /// [`TreeWriter`]: struct.TreeWriter.html " "
+    /// [`PreorderError`]: enum.PreorderError.html " "
+    pub fn from_preorder(
+        events: impl IntoIterator<Item = PreorderEvent<B, L>>,
+    ) -> Result<Self, PreorderError<B, L>> {
+        let mut writer = TreeWriter::new();
+        for event in events {
+            writer.push_preorder(event)?;
+        }
+        writer.finish()
+    }
+}
+
+/// The error type returned by [`TreeWriter`] and [`FreeformTree::from_preorder`] when the pre-order events do not describe a valid freeform tree.
+///
+/// [`TreeWriter`]: struct.TreeWriter.html " "
+/// [`FreeformTree::from_preorder`]: struct.FreeformTree.html#method.from_preorder " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PreorderError<B, L = B> {
+    /// An `Exit` event came right after the `Enter` event for the same branch node, which cannot exist in freeform trees since branch nodes must have at least one child. The payload of the branch node is returned back to the caller to avoid dropping it.
+    EmptyBranch(B),
+    /// An `Exit` event came when there were no branch nodes to end.
+    UnmatchedExit,
+    /// An event came after the root node was complete. The event is returned back to the caller to avoid dropping its payload.
+    AfterRoot(PreorderEvent<B, L>),
+    /// The events ended before the root node was complete.
+    Incomplete,
+}
+impl<B, L> Display for PreorderError<B, L> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::EmptyBranch(..) => "a branch node was ended without any children",
+            Self::UnmatchedExit => "an Exit event came without a matching Enter event",
+            Self::AfterRoot(..) => "an event came after the root node was complete",
+            Self::Incomplete => "the events ended before the root node was complete",
+        })
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl<B: Debug, L: Debug> std::error::Error for PreorderError<B, L> {}
@@ -0,0 +1,112 @@
+//! Immutable trees which can be shared between threads without locking.
+//!
+//! A tree is frozen with [`Tree::freeze`], which moves it behind an [`Arc`]. The resulting [`FrozenTree`] can be cloned cheaply and sent to other threads, all of which can then read the tree and run visitors through it concurrently, since nothing can change it anymore. Once all other clones are dropped, the tree can be [thawed] to be modified again.
+//!
+//! [`Tree::freeze`]: ../tree/trait.Tree.html#method.freeze " "
+//! [`Arc`]: https://doc.rust-lang.org/alloc/sync/struct.Arc.html " "
+//! [`FrozenTree`]: struct.FrozenTree.html " "
+//! [thawed]: struct.FrozenTree.html#method.thaw " "
+
+use core::{
+    ops::Deref,
+    borrow::Borrow,
+    fmt::{self, Formatter, Debug},
+};
+use alloc::sync::Arc;
+
+/// A tree which cannot be modified, created by [`Tree::freeze`].
+///
+/// Cloning a frozen tree only clones the reference to it. The frozen tree dereferences to the tree itself, so all methods which take the tree by reference, including traversal with visitors, can be called on it directly. It's `Send` and `Sync` whenever the tree is both `Send` and `Sync`.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{Tree, FreeformTree};
+/// use std::thread;
+///
+/// let mut tree = FreeformTree::<_>::new(0);
+/// tree.root_mut().make_branch(vec![1, 2, 3]).unwrap();
+/// let frozen = tree.freeze();
+/// let threads = (0..4)
+///     .map(|_| {
+///         let frozen = frozen.clone();
+///         thread::spawn(move || frozen.children_keys(&frozen.root_key()).count())
+///     })
+///     .collect::<Vec<_>>();
+/// for thread in threads {
+///     assert_eq!(thread.join().unwrap(), 3);
+/// }
+/// // All other handles are gone, so the tree can be modified again.
+/// let mut tree = frozen.thaw().unwrap();
+/// tree.root_mut().try_push_back(4).unwrap();
+/// ```
+///
+/// [`Tree::freeze`]: ../tree/trait.Tree.html#method.freeze " "
+pub struct FrozenTree<T>(Arc<T>);
+impl<T> FrozenTree<T> {
+    /// Freezes the specified tree. [`Tree::freeze`] does the same thing.
+    ///
+    /// [`Tree::freeze`]: ../tree/trait.Tree.html#method.freeze " "
+    pub fn new(tree: T) -> Self {
+        Self(Arc::new(tree))
+    }
+    /// Returns a reference to the tree.
+    #[must_use]
+    pub fn inner(&self) -> &T {
+        &self.0
+    }
+    /// Returns the number of handles to the tree, including this one.
+    #[must_use]
+    pub fn num_handles(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+    /// Returns `true` if both handles refer to the same tree, `false` otherwise.
+    #[must_use]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+    /// Unfreezes the tree, allowing it to be modified again.
+    ///
+    /// # Errors
+    /// Will fail if there are other handles to the tree, in which case the handle is returned back to the caller.
+    pub fn thaw(self) -> Result<T, Self> {
+        Arc::try_unwrap(self.0).map_err(Self)
+    }
+}
+impl<T: Clone> FrozenTree<T> {
+    /// Unfreezes the tree, cloning it if there are other handles to it.
+    #[must_use]
+    pub fn thaw_or_clone(self) -> T {
+        Arc::try_unwrap(self.0).unwrap_or_else(|arc| (*arc).clone())
+    }
+}
+impl<T> Deref for FrozenTree<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+impl<T> AsRef<T> for FrozenTree<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+impl<T> Borrow<T> for FrozenTree<T> {
+    fn borrow(&self) -> &T {
+        &self.0
+    }
+}
+impl<T> Clone for FrozenTree<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+impl<T> From<T> for FrozenTree<T> {
+    fn from(op: T) -> Self {
+        Self::new(op)
+    }
+}
+impl<T: Debug> Debug for FrozenTree<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FrozenTree").field(&self.0).finish()
+    }
+}
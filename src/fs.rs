@@ -0,0 +1,261 @@
+//! Building trees which mirror the structure of directories in the file system.
+//!
+//! Directories with entries in them are stored as branch nodes with a [`DirMeta`] payload, while files and empty directories are stored as leaf nodes with a [`Leaf`] payload, since branch nodes in freeform trees always have at least one child. The [`AggregateSizes`] visitor sums up the sizes of the files in every directory, which is the core of any disk usage tool.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{fs, NodeValue};
+//! use std::fs as std_fs;
+//!
+//! let root = std::env::temp_dir().join("charcoal_fs_example");
+//! # let _ = std_fs::remove_dir_all(&root);
+//! std_fs::create_dir_all(root.join("nested"))?;
+//! std_fs::write(root.join("a.txt"), "hello")?;
+//! std_fs::write(root.join("nested").join("b.txt"), "world!")?;
+//!
+//! let tree = fs::scan(&root)?;
+//! if let NodeValue::Branch(dir) = tree.root().value() {
+//!     assert_eq!(dir.total_size, 11);
+//! } else {
+//!     panic!("the directory is not empty, so it's a branch node");
+//! }
+//! # std_fs::remove_dir_all(&root)?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! [`DirMeta`]: struct.DirMeta.html " "
+//! [`Leaf`]: enum.Leaf.html " "
+//! [`AggregateSizes`]: struct.AggregateSizes.html " "
+
+use core::{
+    iter,
+    fmt::Debug,
+    borrow::BorrowMut,
+    marker::PhantomData,
+};
+use std::{
+    ffi::OsString,
+    fs,
+    io,
+    path::Path,
+    time::SystemTime,
+};
+use crate::{
+    freeform_tree::{FreeformTree, NodeRefMut},
+    traversal::{
+        algorithms::{first_in_postorder, next_in_postorder},
+        VisitorMut,
+        TraversableMut,
+        Traversable,
+        VisitorDirection,
+        CursorResult,
+        CursorDirectionError,
+    },
+    NodeValue,
+};
+
+/// A tree mirroring the structure of a directory, created by [`scan`].
+///
+/// [`scan`]: fn.scan.html " "
+pub type FsTree = FreeformTree<DirMeta, Leaf>;
+
+/// The metadata of a directory with entries in it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DirMeta {
+    /// The name of the directory, without the path leading to it.
+    pub name: OsString,
+    /// The total size of all files in the directory and its subdirectories, in bytes, as computed by [`AggregateSizes`].
+    ///
+    /// [`AggregateSizes`]: struct.AggregateSizes.html " "
+    pub total_size: u64,
+}
+/// The metadata of a file.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FileMeta {
+    /// The name of the file, without the path leading to it.
+    pub name: OsString,
+    /// The size of the file in bytes.
+    pub size: u64,
+    /// The last modification time of the file, or `None` if the platform does not support it.
+    pub modified: Option<SystemTime>,
+}
+/// The payload of a leaf node in an [`FsTree`]: either a file or an empty directory.
+///
+/// [`FsTree`]: type.FsTree.html " "
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Leaf {
+    /// A file. Symbolic links are not followed, and are stored as files with the size of the link itself.
+    File(FileMeta),
+    /// A directory without entries in it.
+    EmptyDir(DirMeta),
+}
+impl Leaf {
+    /// Returns the name of the file or the directory.
+    #[must_use]
+    pub const fn name(&self) -> &OsString {
+        match self {
+            Self::File(file) => &file.name,
+            Self::EmptyDir(dir) => &dir.name,
+        }
+    }
+}
+
+/// Builds a tree mirroring the structure of the directory at the specified path, then computes the total sizes of all directories with [`AggregateSizes`].
+///
+/// The entries of every directory are sorted by their names. If the path points to a file, the tree only consists of the root node describing it.
+///
+/// # Errors
+/// Will fail if the metadata of any file or directory, or the list of entries of any directory, cannot be read.
+///
+/// [`AggregateSizes`]: struct.AggregateSizes.html " "
+pub fn scan(path: impl AsRef<Path>) -> io::Result<FsTree> {
+    let path = path.as_ref();
+    let mut tree = FsTree::new(read_leaf(path)?);
+    let root_key = *tree.root().raw_key();
+    scan_children(&mut tree, root_key, path)?;
+    tree.traverse_mut(AggregateSizes::new());
+    Ok(tree)
+}
+fn read_leaf(path: &Path) -> io::Result<Leaf> {
+    let metadata = fs::symlink_metadata(path)?;
+    let name = path
+        .file_name()
+        .map_or_else(|| path.as_os_str().to_owned(), ToOwned::to_owned);
+    Ok(if metadata.is_dir() {
+        Leaf::EmptyDir(DirMeta {
+            name,
+            total_size: 0,
+        })
+    } else {
+        Leaf::File(FileMeta {
+            name,
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    })
+}
+fn scan_children(tree: &mut FsTree, key: usize, path: &Path) -> io::Result<()> {
+    if !fs::symlink_metadata(path)?.is_dir() {
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        let child = push_child(tree, key, read_leaf(&entry)?);
+        scan_children(tree, child, &entry)?;
+    }
+    Ok(())
+}
+/// Adds a child to the directory with the specified key, turning it into a branch node if it was a leaf, and returns the key of the child.
+fn push_child(tree: &mut FsTree, key: usize, child: Leaf) -> usize {
+    let mut node = NodeRefMut::new_raw(tree, key).expect("the parent key is always valid");
+    if node.is_leaf() {
+        node.make_branch_with(iter::once(child), |payload| match payload {
+            Leaf::EmptyDir(dir) => dir,
+            Leaf::File(..) => unreachable!("files never get children"),
+        })
+        .unwrap_or_else(|_| unreachable!("the node was checked to be a leaf"));
+    } else {
+        node.try_push_back(child)
+            .unwrap_or_else(|_| unreachable!("the node was checked to be a branch"));
+    }
+    *node.last_child().expect("a child was just added").raw_key()
+}
+
+/// A `VisitorMut` which sets the [`total_size`] of every directory to the sum of the sizes of the files in it and its subdirectories, which is needed after the tree is modified.
+///
+/// The visitor performs a depth-first post-order traversal of the subtree starting at the node the cursor was initially at, so that the sizes of the subdirectories are known by the time their parent is visited.
+///
+/// [`total_size`]: struct.DirMeta.html#structfield.total_size " "
+#[derive(Copy, Clone, Debug)]
+pub struct AggregateSizes<T: Traversable> {
+    /// The root of the subtree, or `None` if the traversal has not started yet.
+    subtree_root: Option<T::Cursor>,
+    _phantom: PhantomData<fn(&mut T)>,
+}
+impl<T> AggregateSizes<T>
+where
+    T: TraversableMut<Branch = DirMeta, Leaf = Leaf>,
+{
+    /// Creates the visitor.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            subtree_root: None,
+            _phantom: PhantomData,
+        }
+    }
+}
+impl<T> Default for AggregateSizes<T>
+where
+    T: TraversableMut<Branch = DirMeta, Leaf = Leaf>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T> VisitorMut for AggregateSizes<T>
+where
+    T: TraversableMut<Branch = DirMeta, Leaf = Leaf>,
+{
+    type Target = T;
+    type Output = u64;
+
+    fn visit_mut<C, M>(
+        &mut self,
+        mut traversable: M,
+        cursor: CursorResult<C>,
+    ) -> (VisitorDirection<C, Self::Output>, M)
+    where
+        C: From<<Self::Target as Traversable>::Cursor>
+            + Into<<Self::Target as Traversable>::Cursor>
+            + Clone
+            + Debug
+            + Eq,
+        M: BorrowMut<Self::Target>,
+    {
+        // We only ever use SetTo with valid cursors, so there's nothing to recover from.
+        let cursor = cursor.unwrap_or_else(CursorDirectionError::recover).into();
+        let tree = traversable.borrow_mut();
+        let subtree_root = if let Some(subtree_root) = &self.subtree_root {
+            subtree_root.clone()
+        } else {
+            // Start from the first node in post-order rather than the subtree root itself.
+            let first = first_in_postorder(tree, &cursor);
+            self.subtree_root = Some(cursor);
+            return (VisitorDirection::SetTo(first.into()), traversable);
+        };
+        let num_children = tree.num_children_of(&cursor);
+        if num_children != 0 {
+            let total_size = (0..num_children)
+                .filter_map(|i| tree.nth_child_of(&cursor, i))
+                .map(|child| total_size_of(tree, &child))
+                .sum();
+            if let NodeValue::Branch(dir) = tree.value_mut_of(&cursor) {
+                dir.total_size = total_size;
+            }
+        }
+        let size = total_size_of(tree, &cursor);
+        let direction = if cursor == subtree_root {
+            VisitorDirection::Stop(size)
+        } else {
+            let next = next_in_postorder(tree, &cursor)
+                .expect("the subtree root is visited last, so there must be a next node");
+            VisitorDirection::SetTo(next.into())
+        };
+        (direction, traversable)
+    }
+}
+/// Returns the size of a file, the total size of a directory or zero for an empty directory.
+fn total_size_of<T>(tree: &T, cursor: &T::Cursor) -> u64
+where
+    T: Traversable<Branch = DirMeta, Leaf = Leaf>,
+{
+    match tree.value_of(cursor) {
+        NodeValue::Branch(dir) => dir.total_size,
+        NodeValue::Leaf(Leaf::File(file)) => file.size,
+        NodeValue::Leaf(Leaf::EmptyDir(..)) => 0,
+    }
+}
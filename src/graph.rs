@@ -0,0 +1,117 @@
+//! Interoperability with [petgraph], allowing its algorithm ecosystem to be used on trees.
+//!
+//! Trees are converted into directed graphs, with one graph node per tree node and one edge per parent-child relationship, directed from the parent to the child. The nodes are added to the graph in depth-first pre-order, meaning that the root node always has index 0 and that the children of a node are added in order after it.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{freeform_tree::FreeformTree, graph::to_petgraph};
+//! use petgraph::algo::has_path_connecting;
+//!
+//! let mut tree = FreeformTree::<_>::new("Root");
+//! tree.root_mut().make_branch(vec!["Left", "Right"]).unwrap();
+//!
+//! let graph = to_petgraph(&tree);
+//! assert_eq!(graph.node_count(), 3);
+//! assert_eq!(graph.edge_count(), 2);
+//! // The root is always the first node.
+//! let root = petgraph::graph::NodeIndex::new(0);
+//! assert_eq!(*graph[root].into_inner(), "Root");
+//! for child in graph.node_indices().skip(1) {
+//!     assert!(has_path_connecting(&graph, root, child, None));
+//! }
+//! ```
+//!
+//! [petgraph]: https://docs.rs/petgraph/*/petgraph/ " "
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use petgraph::graph::{Graph, NodeIndex};
+use crate::{traversal::algorithms::next_in_preorder, NodeValue, Storage, Traversable};
+
+/// Converts the traversable into a directed graph with borrowed node payloads.
+///
+/// See the [module-level documentation] for the layout of the resulting graph.
+///
+/// [module-level documentation]: index.html " "
+pub fn to_petgraph<T: Traversable>(
+    traversable: &T,
+) -> Graph<NodeValue<&'_ T::Branch, &'_ T::Leaf>, ()> {
+    to_petgraph_with(traversable, |value| value)
+}
+/// Converts the traversable into a directed graph, using a closure to produce the weights of the graph nodes from the payloads of the tree nodes.
+///
+/// See the [module-level documentation] for the layout of the resulting graph.
+///
+/// [module-level documentation]: index.html " "
+pub fn to_petgraph_with<'a, T, N, F>(traversable: &'a T, mut f: F) -> Graph<N, ()>
+where
+    T: Traversable,
+    F: FnMut(NodeValue<&'a T::Branch, &'a T::Leaf>) -> N,
+{
+    let mut graph = Graph::new();
+    let root = traversable.cursor_to_root();
+    // The path from the root to the current node, along with the graph indices of the nodes on it
+    let mut path: Vec<(T::Cursor, NodeIndex)> = Vec::new();
+    let mut cursor = Some(root.clone());
+    while let Some(current) = cursor {
+        let index = graph.add_node(f(traversable.value_of(&current)));
+        if let Some(parent) = traversable.parent_of(&current) {
+            // Pop the path until we get to the parent, which is always on it in pre-order
+            while path.last().map_or(false, |(cursor, _)| cursor != &parent) {
+                path.pop();
+            }
+            if let Some((_, parent_index)) = path.last() {
+                graph.add_edge(*parent_index, index, ());
+            }
+        }
+        cursor = next_in_preorder(traversable, &current, &root);
+        path.push((current, index));
+    }
+    graph
+}
+
+#[cfg(feature = "binary_tree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "binary_tree")))]
+impl<'a, B, L, K, S> From<&'a crate::BinaryTree<B, L, K, S>> for Graph<NodeValue<&'a B, &'a L>, ()>
+where
+    S: Storage<Element = crate::binary_tree::Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn from(op: &'a crate::BinaryTree<B, L, K, S>) -> Self {
+        to_petgraph(op)
+    }
+}
+#[cfg(feature = "octree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "octree")))]
+impl<'a, B, L, K, S> From<&'a crate::Octree<B, L, K, S>> for Graph<NodeValue<&'a B, &'a L>, ()>
+where
+    S: Storage<Element = crate::octree::Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn from(op: &'a crate::Octree<B, L, K, S>) -> Self {
+        to_petgraph(op)
+    }
+}
+#[cfg(feature = "quadtree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "quadtree")))]
+impl<'a, B, L, K, S> From<&'a crate::Quadtree<B, L, K, S>> for Graph<NodeValue<&'a B, &'a L>, ()>
+where
+    S: Storage<Element = crate::quadtree::Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn from(op: &'a crate::Quadtree<B, L, K, S>) -> Self {
+        to_petgraph(op)
+    }
+}
+#[cfg(feature = "freeform_tree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "freeform_tree")))]
+impl<'a, B, L, K, S> From<&'a crate::FreeformTree<B, L, K, S>>
+    for Graph<NodeValue<&'a B, &'a L>, ()>
+where
+    S: Storage<Element = crate::freeform_tree::Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn from(op: &'a crate::FreeformTree<B, L, K, S>) -> Self {
+        to_petgraph(op)
+    }
+}
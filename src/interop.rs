@@ -0,0 +1,249 @@
+//! Conversions between freeform trees and the trees of [indextree] and [ego-tree], for combining Charcoal with code written for those crates or migrating between them.
+//!
+//! Both crates use one payload type for all nodes, so the conversions work with freeform trees which use the same type for the payloads of branch and leaf nodes. Nodes without children become leaf nodes, and all other nodes become branch nodes. The conversions borrow the source tree and clone the payloads, and walk the trees using [pre-order events] without recursion, so they work with trees of any depth.
+//!
+//! The conversions for indextree are enabled by the `indextree` feature and the ones for ego-tree by the `ego-tree` feature. Since an indextree [`Arena`] can hold any number of trees, its trees are described by the arena and the [`NodeId`] of the root node.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::freeform_tree::FreeformTree;
+//!
+//! let mut tree = FreeformTree::<_>::new("Root");
+//! let mut root = tree.root_mut();
+//! root.make_branch(vec!["Left", "Right"]).unwrap();
+//! root.first_child_mut().unwrap().make_branch(vec!["Left of Left"]).unwrap();
+//!
+//! // To ego-tree and back.
+//! let ego = ego_tree::Tree::from(&tree);
+//! assert_eq!(ego.root().children().count(), 2);
+//! assert_eq!(FreeformTree::<_>::from(&ego).to_repr(), tree.to_repr());
+//!
+//! // To indextree and back.
+//! let (arena, root_id) = <(indextree::Arena<_>, _)>::from(&tree);
+//! assert_eq!(root_id.descendants(&arena).count(), 4);
+//! assert_eq!(FreeformTree::<_>::from((&arena, root_id)).to_repr(), tree.to_repr());
+//! ```
+//!
+//! [indextree]: https://docs.rs/indextree/*/indextree/ " "
+//! [ego-tree]: https://docs.rs/ego-tree/*/ego_tree/ " "
+//! [pre-order events]: ../tree/enum.PreorderEvent.html " "
+//! [`Arena`]: https://docs.rs/indextree/*/indextree/struct.Arena.html " "
+//! [`NodeId`]: https://docs.rs/indextree/*/indextree/struct.NodeId.html " "
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use crate::{
+    freeform_tree::{FreeformTree, Node},
+    tree::PreorderEvent,
+    Storage,
+    Tree,
+};
+
+/// Builds a foreign tree from the pre-order events of a freeform tree, using the closure to add a node with the specified payload under the specified parent, or as the root node if there is no parent. Returns the ID of the root node.
+fn build_from_events<'a, T, I, K, S>(
+    tree: &'a FreeformTree<T, T, K, S>,
+    mut add_node: impl FnMut(Option<&I>, T) -> I,
+) -> I
+where
+    T: Clone + 'a,
+    I: Clone,
+    S: Storage<Element = Node<T, T, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let mut root = None;
+    // The IDs of the branch nodes which the events are currently inside of
+    let mut open_branches: Vec<I> = Vec::new();
+    for event in tree.events() {
+        let (payload, is_branch) = match event {
+            PreorderEvent::Enter(payload) => (payload, true),
+            PreorderEvent::Leaf(payload) => (payload, false),
+            PreorderEvent::Exit => {
+                open_branches.pop();
+                continue;
+            }
+        };
+        let id = add_node(open_branches.last(), payload.clone());
+        if root.is_none() {
+            root = Some(id.clone());
+        }
+        if is_branch {
+            open_branches.push(id);
+        }
+    }
+    root.unwrap_or_else(|| unreachable!("the events of a tree always describe the root node"))
+}
+
+/// Creates a freeform tree from the pre-order events of a foreign tree, in which the nodes with children are the branch nodes.
+fn from_foreign_events<T, K, S>(
+    events: impl IntoIterator<Item = PreorderEvent<T, T>>,
+) -> FreeformTree<T, T, K, S>
+where
+    S: Storage<Element = Node<T, T, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    FreeformTree::from_preorder(events)
+        .unwrap_or_else(|_| unreachable!("only nodes with children are turned into branch nodes"))
+}
+
+/// Converts the freeform tree into an indextree [`Arena`], returning it along with the [`NodeId`] of the root node.
+///
+/// See the [module-level documentation] for more.
+///
+/// [`Arena`]: https://docs.rs/indextree/*/indextree/struct.Arena.html " "
+/// [`NodeId`]: https://docs.rs/indextree/*/indextree/struct.NodeId.html " "
+/// [module-level documentation]: index.html " "
+#[cfg(feature = "indextree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "indextree")))]
+pub fn to_indextree<T, K, S>(
+    tree: &FreeformTree<T, T, K, S>,
+) -> (indextree::Arena<T>, indextree::NodeId)
+where
+    T: Clone,
+    S: Storage<Element = Node<T, T, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let mut arena = indextree::Arena::with_capacity(tree.num_nodes());
+    let root = build_from_events(tree, |parent: Option<&indextree::NodeId>, payload| {
+        let id = arena.new_node(payload);
+        if let Some(parent) = parent {
+            parent.append(id, &mut arena);
+        }
+        id
+    });
+    (arena, root)
+}
+/// Converts the tree with the specified root node in an indextree [`Arena`] into a freeform tree.
+///
+/// See the [module-level documentation] for more.
+///
+/// # Panics
+/// Will panic if the root node is not in the arena, or if the storage has fixed capacity and cannot fit all of the nodes.
+///
+/// [`Arena`]: https://docs.rs/indextree/*/indextree/struct.Arena.html " "
+/// [module-level documentation]: index.html " "
+#[cfg(feature = "indextree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "indextree")))]
+#[must_use]
+pub fn from_indextree<T, K, S>(
+    arena: &indextree::Arena<T>,
+    root: indextree::NodeId,
+) -> FreeformTree<T, T, K, S>
+where
+    T: Clone,
+    S: Storage<Element = Node<T, T, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    use indextree::NodeEdge;
+    let has_children = |id: indextree::NodeId| id.children(arena).next().is_some();
+    let payload = |id: indextree::NodeId| arena[id].get().clone();
+    from_foreign_events(root.traverse(arena).filter_map(|edge| match edge {
+        NodeEdge::Start(id) if has_children(id) => Some(PreorderEvent::Enter(payload(id))),
+        NodeEdge::Start(id) => Some(PreorderEvent::Leaf(payload(id))),
+        NodeEdge::End(id) if has_children(id) => Some(PreorderEvent::Exit),
+        NodeEdge::End(..) => None,
+    }))
+}
+#[cfg(feature = "indextree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "indextree")))]
+impl<T, K, S> From<&FreeformTree<T, T, K, S>> for (indextree::Arena<T>, indextree::NodeId)
+where
+    T: Clone,
+    S: Storage<Element = Node<T, T, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn from(op: &FreeformTree<T, T, K, S>) -> Self {
+        to_indextree(op)
+    }
+}
+#[cfg(feature = "indextree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "indextree")))]
+impl<T, K, S> From<(&indextree::Arena<T>, indextree::NodeId)> for FreeformTree<T, T, K, S>
+where
+    T: Clone,
+    S: Storage<Element = Node<T, T, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn from(op: (&indextree::Arena<T>, indextree::NodeId)) -> Self {
+        from_indextree(op.0, op.1)
+    }
+}
+
+/// Converts the freeform tree into an ego-tree [`Tree`].
+///
+/// See the [module-level documentation] for more.
+///
+/// [`Tree`]: https://docs.rs/ego-tree/*/ego_tree/struct.Tree.html " "
+/// [module-level documentation]: index.html " "
+#[cfg(feature = "ego-tree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "ego-tree")))]
+pub fn to_ego_tree<T, K, S>(tree: &FreeformTree<T, T, K, S>) -> ego_tree::Tree<T>
+where
+    T: Clone,
+    S: Storage<Element = Node<T, T, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let mut result: Option<ego_tree::Tree<T>> = None;
+    build_from_events(tree, |parent, payload| match (&mut result, parent) {
+        (Some(result), Some(&parent)) => result
+            .get_mut(parent)
+            .unwrap_or_else(|| unreachable!("parents are added before their children"))
+            .append(payload)
+            .id(),
+        (result, _) => result
+            .get_or_insert_with(|| ego_tree::Tree::with_capacity(payload, tree.num_nodes()))
+            .root()
+            .id(),
+    });
+    result.unwrap_or_else(|| unreachable!("the events of a tree always describe the root node"))
+}
+/// Converts the subtree of an ego-tree [`Tree`] with the specified root node into a freeform tree. Use [`Tree::root`] to convert the whole tree.
+///
+/// See the [module-level documentation] for more.
+///
+/// # Panics
+/// The storage may panic if it has fixed capacity and cannot fit all of the nodes.
+///
+/// [`Tree`]: https://docs.rs/ego-tree/*/ego_tree/struct.Tree.html " "
+/// [`Tree::root`]: https://docs.rs/ego-tree/*/ego_tree/struct.Tree.html#method.root " "
+/// [module-level documentation]: index.html " "
+#[cfg(feature = "ego-tree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "ego-tree")))]
+#[must_use]
+pub fn from_ego_tree<T, K, S>(root: ego_tree::NodeRef<'_, T>) -> FreeformTree<T, T, K, S>
+where
+    T: Clone,
+    S: Storage<Element = Node<T, T, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    use ego_tree::iter::Edge;
+    from_foreign_events(root.traverse().filter_map(|edge| match edge {
+        Edge::Open(node) if node.has_children() => Some(PreorderEvent::Enter(node.value().clone())),
+        Edge::Open(node) => Some(PreorderEvent::Leaf(node.value().clone())),
+        Edge::Close(node) if node.has_children() => Some(PreorderEvent::Exit),
+        Edge::Close(..) => None,
+    }))
+}
+#[cfg(feature = "ego-tree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "ego-tree")))]
+impl<T, K, S> From<&FreeformTree<T, T, K, S>> for ego_tree::Tree<T>
+where
+    T: Clone,
+    S: Storage<Element = Node<T, T, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn from(op: &FreeformTree<T, T, K, S>) -> Self {
+        to_ego_tree(op)
+    }
+}
+#[cfg(feature = "ego-tree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "ego-tree")))]
+impl<T, K, S> From<&ego_tree::Tree<T>> for FreeformTree<T, T, K, S>
+where
+    T: Clone,
+    S: Storage<Element = Node<T, T, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn from(op: &ego_tree::Tree<T>) -> Self {
+        from_ego_tree(op.root())
+    }
+}
@@ -0,0 +1,575 @@
+//! Change tracking for trees, recording structural changes as a journal which can be replayed onto another tree.
+//!
+//! The [`TrackedTree`] wrapper records every change made to the tree it wraps as a [`Change`]. Nodes are identified in the journal by their *path*, the list of child indices which leads from the root node to the node, rather than by cursors, which would be meaningless for any other tree. This makes it possible to keep a copy of a tree in sync, e.g. over a network, by sending it the journal and applying it with [`replay`], as long as both trees started out with the same structure.
+//!
+//! Closures passed to the methods of the tree, like the ones converting between leaf and branch payloads, are called only once, and their results are recorded instead. This is why the payloads and the packed children of the tree have to be `Clone`.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{
+//!     journal::{replay, TrackedTree},
+//!     FreeformTree,
+//!     NodeValue,
+//!     Traversable,
+//!     TraversableMut,
+//! };
+//!
+//! let mut tree = TrackedTree::new(FreeformTree::<_>::new("Root"));
+//! let mut copy = FreeformTree::<_>::new("Root");
+//! let root = tree.cursor_to_root();
+//! let child = tree.try_add_child(&root, "Child", |x| x).unwrap();
+//! tree.try_add_child(&root, "Another child", |x| x).unwrap();
+//! if let NodeValue::Leaf(payload) = tree.value_mut_of(&child) {
+//!     *payload = "First child";
+//! }
+//! tree.try_remove_leaf(&child, |x| x).unwrap();
+//! // Bring the copy up to date.
+//! replay(tree.take_journal(), &mut copy).unwrap();
+//! assert_eq!(copy.to_repr(), tree.into_inner().to_repr());
+//! ```
+//!
+//! [`TrackedTree`]: struct.TrackedTree.html " "
+//! [`Change`]: enum.Change.html " "
+//! [`replay`]: fn.replay.html " "
+
+use core::{
+    fmt::{self, Formatter, Debug, Display},
+    mem,
+};
+use alloc::vec::Vec;
+use crate::{
+    traversal::{CursorResult, VisitorDirection},
+    NodeValue,
+    Traversable,
+    TraversableMut,
+    TryRemoveLeafError,
+    TryRemoveBranchError,
+    TryRemoveChildrenError,
+    TryAddChildError,
+    MakeBranchError,
+};
+
+/// A change made to a tree, as recorded by [`TrackedTree`].
+///
+/// Nodes are identified by their path, the list of child indices which leads from the root node to the node. The path of the root node is empty.
+///
+/// [`TrackedTree`]: struct.TrackedTree.html " "
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Change<B, L, P> {
+    /// A leaf child was added to a node after all of its other children.
+    AddChild {
+        /// The path to the node which the child was added to.
+        parent: Vec<usize>,
+        /// The payload of the new child.
+        child_payload: L,
+        /// The new payload of the node which the child was added to, if it was a leaf node and thus was converted into a branch node.
+        parent_payload: Option<B>,
+    },
+    /// A leaf node was converted into a branch node with the specified packed children.
+    AddChildren {
+        /// The path to the node which the children were added to.
+        parent: Vec<usize>,
+        /// The children which were added.
+        children: P,
+        /// The new payload of the node, which became a branch node.
+        parent_payload: B,
+    },
+    /// A leaf node was removed.
+    RemoveLeaf {
+        /// The path to the node which was removed.
+        node: Vec<usize>,
+        /// The new payload of the parent of the node, if it was the only child and thus the parent was converted into a leaf node.
+        parent_payload: Option<L>,
+    },
+    /// A branch node was removed along with its children, all of which were leaf nodes.
+    RemoveBranch {
+        /// The path to the node which was removed.
+        node: Vec<usize>,
+        /// The new payload of the parent of the node, if it was the only child and thus the parent was converted into a leaf node.
+        parent_payload: Option<L>,
+    },
+    /// The children of a branch node, all of which were leaf nodes, were removed.
+    RemoveChildren {
+        /// The path to the node whose children were removed.
+        node: Vec<usize>,
+        /// The new payload of the node, which became a leaf node.
+        payload: L,
+    },
+    /// The payload of a node was changed.
+    SetValue {
+        /// The path to the node whose payload was changed.
+        node: Vec<usize>,
+        /// The new payload of the node.
+        value: NodeValue<B, L>,
+    },
+}
+impl<B, L, P: IntoIterator<Item = L>> Change<B, L, P> {
+    /// Applies the change to the specified tree.
+    ///
+    /// # Errors
+    /// Will fail if the tree does not have a node at one of the paths in the change or if the tree fails to make the change. The tree is left unchanged in both cases.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn apply<T>(self, target: &mut T) -> Result<(), ReplayError>
+    where
+        T: TraversableMut<Branch = B, Leaf = L, PackedChildren = P>,
+    {
+        match self {
+            Self::AddChild {
+                parent,
+                child_payload,
+                parent_payload,
+            } => {
+                let cursor = cursor_at(target, &parent)?;
+                // Only leaf nodes need a new payload, since they're converted into branch nodes
+                if (target.num_children_of(&cursor) == 0) != parent_payload.is_some() {
+                    return Err(ReplayError::PayloadMismatch);
+                }
+                target
+                    .try_add_child(&cursor, child_payload, |_| {
+                        parent_payload.expect("the node was checked to be a leaf node")
+                    })
+                    .map(drop)
+                    .map_err(|_| ReplayError::AddChild)
+            }
+            Self::AddChildren {
+                parent,
+                children,
+                parent_payload,
+            } => {
+                let cursor = cursor_at(target, &parent)?;
+                target
+                    .try_add_children(&cursor, children, |_| parent_payload)
+                    .map_err(|_| ReplayError::AddChildren)
+            }
+            Self::RemoveLeaf {
+                node,
+                parent_payload,
+            } => {
+                let cursor = cursor_at(target, &node)?;
+                check_parent_payload(target, &cursor, parent_payload.is_some())?;
+                target
+                    .try_remove_leaf(&cursor, |_| {
+                        parent_payload.expect("the node was checked to be the only child")
+                    })
+                    .map(drop)
+                    .map_err(ReplayError::RemoveLeaf)
+            }
+            Self::RemoveBranch {
+                node,
+                parent_payload,
+            } => {
+                let cursor = cursor_at(target, &node)?;
+                check_parent_payload(target, &cursor, parent_payload.is_some())?;
+                target
+                    .try_remove_branch_into(
+                        &cursor,
+                        |_| parent_payload.expect("the node was checked to be the only child"),
+                        drop,
+                    )
+                    .map(drop)
+                    .map_err(ReplayError::RemoveBranch)
+            }
+            Self::RemoveChildren { node, payload } => {
+                let cursor = cursor_at(target, &node)?;
+                target
+                    .try_remove_children_into(&cursor, |_| payload, drop)
+                    .map_err(ReplayError::RemoveChildren)
+            }
+            Self::SetValue { node, value } => {
+                let cursor = cursor_at(target, &node)?;
+                match (target.value_mut_of(&cursor), value) {
+                    (NodeValue::Branch(old), NodeValue::Branch(new)) => *old = new,
+                    (NodeValue::Leaf(old), NodeValue::Leaf(new)) => *old = new,
+                    _ => return Err(ReplayError::PayloadMismatch),
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Applies the changes from a journal to the specified tree, in order.
+///
+/// # Errors
+/// Will fail if one of the changes could not be applied, as described in the documentation for [`Change::apply`]. The changes before it remain applied, and the rest are dropped.
+///
+/// [`Change::apply`]: enum.Change.html#method.apply " "
+pub fn replay<T, I>(journal: I, target: &mut T) -> Result<(), ReplayError>
+where
+    T: TraversableMut,
+    I: IntoIterator<Item = Change<T::Branch, T::Leaf, T::PackedChildren>>,
+{
+    journal.into_iter().try_for_each(|change| change.apply(target))
+}
+
+/// The error type returned by [`Change::apply`] and [`replay`].
+///
+/// [`Change::apply`]: enum.Change.html#method.apply " "
+/// [`replay`]: fn.replay.html " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ReplayError {
+    /// The tree does not have a node at the specified path.
+    InvalidPath,
+    /// The node is a leaf node where a branch node was expected or vice versa, which means that the tree does not have the same structure as the one the change was recorded on.
+    PayloadMismatch,
+    /// Adding a child failed.
+    AddChild,
+    /// Adding packed children failed.
+    AddChildren,
+    /// Removing a leaf node failed.
+    RemoveLeaf(TryRemoveLeafError),
+    /// Removing a branch node failed.
+    RemoveBranch(TryRemoveBranchError),
+    /// Removing the children of a node failed.
+    RemoveChildren(TryRemoveChildrenError),
+}
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::InvalidPath => "the tree does not have a node at the specified path",
+            Self::PayloadMismatch => "the node is not of the kind the change was recorded for",
+            Self::AddChild => "failed to add a child",
+            Self::AddChildren => "failed to add packed children",
+            Self::RemoveLeaf(..) => "failed to remove a leaf node",
+            Self::RemoveBranch(..) => "failed to remove a branch node",
+            Self::RemoveChildren(..) => "failed to remove the children of a node",
+        })
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl std::error::Error for ReplayError {}
+
+/// A tree which records the changes made to it as a journal.
+///
+/// The wrapper implements [`Traversable`] and [`TraversableMut`] by forwarding to the tree and records every successful change as a [`Change`]. Since changes made to the tree directly would not be recorded, the wrapped tree can only be accessed immutably, or taken out of the wrapper with [`into_inner`].
+///
+/// Payloads are changed through mutable references, so the new payload is only known after the reference is dropped. Instead of being recorded right away, such changes are recorded before the next change or when the journal is accessed, which is why the methods accessing the journal take `&mut self`.
+///
+/// See the [module-level documentation] for an example.
+///
+/// [`Traversable`]: ../traversal/trait.Traversable.html " "
+/// [`TraversableMut`]: ../traversal/trait.TraversableMut.html " "
+/// [`Change`]: enum.Change.html " "
+/// [`into_inner`]: #method.into_inner " "
+/// [module-level documentation]: index.html " "
+pub struct TrackedTree<T: TraversableMut> {
+    traversable: T,
+    journal: Vec<Change<T::Branch, T::Leaf, T::PackedChildren>>,
+    /// The cursor to the node whose payload was last borrowed mutably, if the change has not been recorded yet.
+    pending_value: Option<T::Cursor>,
+}
+impl<T> TrackedTree<T>
+where
+    T: TraversableMut,
+    T::Branch: Clone,
+    T::Leaf: Clone,
+    T::PackedChildren: Clone,
+{
+    /// Wraps the specified tree, starting with an empty journal.
+    pub fn new(traversable: T) -> Self {
+        Self {
+            traversable,
+            journal: Vec::new(),
+            pending_value: None,
+        }
+    }
+    /// Returns a reference to the wrapped tree.
+    pub fn inner(&self) -> &T {
+        &self.traversable
+    }
+    /// Returns the changes recorded since the wrapper was created or the journal was last taken.
+    pub fn journal(&mut self) -> &[Change<T::Branch, T::Leaf, T::PackedChildren>] {
+        self.record_pending_value();
+        &self.journal
+    }
+    /// Takes the recorded changes out of the journal, leaving it empty.
+    pub fn take_journal(&mut self) -> Vec<Change<T::Branch, T::Leaf, T::PackedChildren>> {
+        self.record_pending_value();
+        mem::take(&mut self.journal)
+    }
+    /// Unwraps the tree, discarding the journal.
+    #[allow(clippy::missing_const_for_fn)] // Clippy has no idea what a destructor is
+    pub fn into_inner(self) -> T {
+        self.traversable
+    }
+
+    fn record(&mut self, change: Change<T::Branch, T::Leaf, T::PackedChildren>) {
+        self.journal.push(change);
+    }
+    /// Records the change of the payload of the node which was last borrowed mutably, if there is one. Has to be called before every other change, while the node is still there.
+    fn record_pending_value(&mut self) {
+        if let Some(cursor) = self.pending_value.take() {
+            let value = match self.traversable.value_of(&cursor) {
+                NodeValue::Branch(payload) => NodeValue::Branch(payload.clone()),
+                NodeValue::Leaf(payload) => NodeValue::Leaf(payload.clone()),
+            };
+            let node = path_to(&self.traversable, &cursor);
+            self.record(Change::SetValue { node, value });
+        }
+    }
+    fn record_removed_children(&mut self, cursor: &T::Cursor) {
+        let payload = self
+            .leaf_payload_of(cursor)
+            .expect("the node was just turned into a leaf");
+        self.record(Change::RemoveChildren {
+            node: path_to(&self.traversable, cursor),
+            payload,
+        });
+    }
+    /// Returns a clone of the payload of the parent of the removed node at the specified path if the parent became a leaf node.
+    ///
+    /// Removing a node may invalidate the cursor to its parent, depending on the storage and on where the nodes ended up after reparenting or defragmentation, so the parent is found by its path instead, which removing one of its children does not change.
+    fn parent_payload_after_removal(&self, node: &[usize]) -> Option<T::Leaf> {
+        let (_, parent) = node.split_last()?;
+        let parent = cursor_at(&self.traversable, parent)
+            .unwrap_or_else(|_| unreachable!("the parent of the removed node is still in the tree"));
+        self.leaf_payload_of(&parent)
+    }
+    /// Returns a clone of the payload of the node at the specified cursor if it's a leaf node.
+    fn leaf_payload_of(&self, cursor: &T::Cursor) -> Option<T::Leaf> {
+        match self.traversable.value_of(cursor) {
+            NodeValue::Leaf(payload) => Some(payload.clone()),
+            NodeValue::Branch(..) => None,
+        }
+    }
+}
+impl<T> Debug for TrackedTree<T>
+where
+    T: TraversableMut + Debug,
+    T::Branch: Debug,
+    T::Leaf: Debug,
+    T::PackedChildren: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrackedTree")
+            .field("traversable", &self.traversable)
+            .field("journal", &self.journal)
+            .field("pending_value", &self.pending_value)
+            .finish()
+    }
+}
+impl<T> Traversable for TrackedTree<T>
+where
+    T: TraversableMut,
+    T::Branch: Clone,
+    T::Leaf: Clone,
+    T::PackedChildren: Clone,
+{
+    type Leaf = T::Leaf;
+    type Branch = T::Branch;
+    type Cursor = T::Cursor;
+
+    fn advance_cursor<V>(
+        &self,
+        cursor: Self::Cursor,
+        direction: VisitorDirection<Self::Cursor, V>,
+    ) -> CursorResult<Self::Cursor> {
+        self.traversable.advance_cursor(cursor, direction)
+    }
+    fn cursor_to_root(&self) -> Self::Cursor {
+        self.traversable.cursor_to_root()
+    }
+    fn value_of(&self, cursor: &Self::Cursor) -> NodeValue<&'_ Self::Branch, &'_ Self::Leaf> {
+        self.traversable.value_of(cursor)
+    }
+    fn parent_of(&self, cursor: &Self::Cursor) -> Option<Self::Cursor> {
+        self.traversable.parent_of(cursor)
+    }
+    fn num_children_of(&self, cursor: &Self::Cursor) -> usize {
+        self.traversable.num_children_of(cursor)
+    }
+    fn nth_child_of(&self, cursor: &Self::Cursor, child_num: usize) -> Option<Self::Cursor> {
+        self.traversable.nth_child_of(cursor, child_num)
+    }
+    fn contains_cursor(&self, cursor: &Self::Cursor) -> bool {
+        self.traversable.contains_cursor(cursor)
+    }
+}
+impl<T> TraversableMut for TrackedTree<T>
+where
+    T: TraversableMut,
+    T::Branch: Clone,
+    T::Leaf: Clone,
+    T::PackedChildren: Clone,
+{
+    const CAN_REMOVE_INDIVIDUAL_CHILDREN: bool = T::CAN_REMOVE_INDIVIDUAL_CHILDREN;
+    const CAN_PACK_CHILDREN: bool = T::CAN_PACK_CHILDREN;
+    const CAN_ADD_INDIVIDUAL_CHILDREN: bool = T::CAN_ADD_INDIVIDUAL_CHILDREN;
+    type PackedChildren = T::PackedChildren;
+
+    fn value_mut_of(
+        &mut self,
+        cursor: &Self::Cursor,
+    ) -> NodeValue<&'_ mut Self::Branch, &'_ mut Self::Leaf> {
+        if self.pending_value.as_ref() != Some(cursor) {
+            self.record_pending_value();
+            self.pending_value = Some(cursor.clone());
+        }
+        self.traversable.value_mut_of(cursor)
+    }
+    fn try_remove_leaf<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<Self::Leaf, TryRemoveLeafError> {
+        self.record_pending_value();
+        let node = path_to(&self.traversable, cursor);
+        let leaf = self.traversable.try_remove_leaf(cursor, branch_to_leaf)?;
+        let parent_payload = self.parent_payload_after_removal(&node);
+        self.record(Change::RemoveLeaf {
+            node,
+            parent_payload,
+        });
+        Ok(leaf)
+    }
+    fn try_remove_branch_into<BtL: FnOnce(Self::Branch) -> Self::Leaf, C: FnMut(Self::Leaf)>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+        collector: C,
+    ) -> Result<Self::Branch, TryRemoveBranchError> {
+        self.record_pending_value();
+        let node = path_to(&self.traversable, cursor);
+        let branch = self
+            .traversable
+            .try_remove_branch_into(cursor, branch_to_leaf, collector)?;
+        let parent_payload = self.parent_payload_after_removal(&node);
+        self.record(Change::RemoveBranch {
+            node,
+            parent_payload,
+        });
+        Ok(branch)
+    }
+    fn try_remove_children_into<BtL: FnOnce(Self::Branch) -> Self::Leaf, C: FnMut(Self::Leaf)>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+        collector: C,
+    ) -> Result<(), TryRemoveChildrenError> {
+        self.record_pending_value();
+        self.traversable
+            .try_remove_children_into(cursor, branch_to_leaf, collector)?;
+        self.record_removed_children(cursor);
+        Ok(())
+    }
+    fn try_remove_branch<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<(Self::Branch, Self::PackedChildren), TryRemoveBranchError> {
+        self.record_pending_value();
+        let node = path_to(&self.traversable, cursor);
+        let removed = self.traversable.try_remove_branch(cursor, branch_to_leaf)?;
+        let parent_payload = self.parent_payload_after_removal(&node);
+        self.record(Change::RemoveBranch {
+            node,
+            parent_payload,
+        });
+        Ok(removed)
+    }
+    fn try_remove_children<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<Self::PackedChildren, TryRemoveChildrenError> {
+        self.record_pending_value();
+        let children = self
+            .traversable
+            .try_remove_children(cursor, branch_to_leaf)?;
+        self.record_removed_children(cursor);
+        Ok(children)
+    }
+    fn try_add_child<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        child_payload: Self::Leaf,
+        leaf_to_branch: LtB,
+    ) -> Result<Self::Cursor, TryAddChildError<Self::Leaf>> {
+        self.record_pending_value();
+        let was_leaf = self.traversable.num_children_of(cursor) == 0;
+        let child = self
+            .traversable
+            .try_add_child(cursor, child_payload, leaf_to_branch)?;
+        let parent_payload = if was_leaf {
+            match self.traversable.value_of(cursor) {
+                NodeValue::Branch(payload) => Some(payload.clone()),
+                NodeValue::Leaf(..) => None,
+            }
+        } else {
+            None
+        };
+        let child_payload = self
+            .leaf_payload_of(&child)
+            .expect("the new child is always a leaf node");
+        self.record(Change::AddChild {
+            parent: path_to(&self.traversable, cursor),
+            child_payload,
+            parent_payload,
+        });
+        Ok(child)
+    }
+    fn try_add_children<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        children: Self::PackedChildren,
+        leaf_to_branch: LtB,
+    ) -> Result<(), MakeBranchError<Self::Leaf, Self::PackedChildren>> {
+        self.record_pending_value();
+        let recorded_children = children.clone();
+        self.traversable
+            .try_add_children(cursor, children, leaf_to_branch)?;
+        let parent_payload = match self.traversable.value_of(cursor) {
+            NodeValue::Branch(payload) => payload.clone(),
+            NodeValue::Leaf(..) => unreachable!("the node was just turned into a branch"),
+        };
+        self.record(Change::AddChildren {
+            parent: path_to(&self.traversable, cursor),
+            children: recorded_children,
+            parent_payload,
+        });
+        Ok(())
+    }
+}
+/// Returns the path to the node at the specified cursor, i.e. the list of child indices which leads from the root node to it.
+pub(crate) fn path_to<T: Traversable>(traversable: &T, cursor: &T::Cursor) -> Vec<usize> {
+    let mut path = Vec::new();
+    let mut current = cursor.clone();
+    while let Some(parent) = traversable.parent_of(&current) {
+        let index = (0..traversable.num_children_of(&parent))
+            .position(|i| traversable.nth_child_of(&parent, i).as_ref() == Some(&current))
+            .expect("failed to find node in parent's child list");
+        path.push(index);
+        current = parent;
+    }
+    path.reverse();
+    path
+}
+/// Returns the cursor to the node at the specified path.
+pub(crate) fn cursor_at<T: Traversable>(
+    traversable: &T,
+    path: &[usize],
+) -> Result<T::Cursor, ReplayError> {
+    path.iter().try_fold(traversable.cursor_to_root(), |cursor, &index| {
+        traversable
+            .nth_child_of(&cursor, index)
+            .ok_or(ReplayError::InvalidPath)
+    })
+}
+/// Checks whether the node at the specified cursor is the only child of its parent, which is when the recorded change has to provide a payload for the parent.
+fn check_parent_payload<T: Traversable>(
+    traversable: &T,
+    cursor: &T::Cursor,
+    has_parent_payload: bool,
+) -> Result<(), ReplayError> {
+    let is_only_child = traversable
+        .parent_of(cursor)
+        .map_or(false, |parent| traversable.num_children_of(&parent) == 1);
+    if is_only_child == has_parent_payload {
+        Ok(())
+    } else {
+        Err(ReplayError::PayloadMismatch)
+    }
+}
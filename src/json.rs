@@ -0,0 +1,219 @@
+//! JSON import and export for trees, making it possible to exchange them with programs written in other languages, such as JavaScript front-ends which render hierarchies.
+//!
+//! # Schema
+//! Every node is represented by a JSON object with the following fields:
+//! - `"payload"` — the payload of the node, serialized with its `Serialize` implementation
+//! - `"children"` — an array of the child nodes, in order, each following the same schema; omitted for leaf nodes
+//!
+//! When importing, nodes which have no `"children"` field, have it set to `null` or have an empty array in it are treated as leaf nodes, and other fields of the objects are ignored. For example, a root node with two leaf children looks like this:
+//! ```json
+//! {
+//!     "payload": "Root",
+//!     "children": [
+//!         { "payload": "Left" },
+//!         { "payload": "Right" }
+//!     ]
+//! }
+//! ```
+//!
+//! Trees are exported with [`Tree::to_json`], which is available for all tree types. Since only freeform trees can have any number of children per node, only those can be imported from arbitrary JSON, using [`FreeformTree::from_json`].
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{FreeformTree, Tree};
+//! use serde_json::json;
+//!
+//! let mut tree = FreeformTree::<_>::new(String::from("Root"));
+//! tree.root_mut().make_branch(vec![String::from("Left"), String::from("Right")]).unwrap();
+//!
+//! let json = tree.to_json().unwrap();
+//! assert_eq!(json, json!({
+//!     "payload": "Root",
+//!     "children": [
+//!         { "payload": "Left" },
+//!         { "payload": "Right" },
+//!     ],
+//! }));
+//!
+//! let imported = FreeformTree::<String>::from_json(json).unwrap();
+//! assert_eq!(imported.to_repr(), tree.to_repr());
+//! ```
+//!
+//! Neither direction recurses into the children of the nodes, so the depth of the tree is only limited by the available memory and not by the size of the call stack:
+//! ```rust
+//! use charcoal::{freeform_tree::NodeRefMut, FreeformTree, Tree};
+//! use std::thread;
+//!
+//! // A single path of 10000 nodes.
+//! let mut tree = FreeformTree::<_>::new(0);
+//! let mut key = tree.root_key();
+//! for depth in 1..10_000 {
+//!     let mut node = NodeRefMut::new_raw(&mut tree, key).unwrap();
+//!     node.make_branch(vec![depth]).unwrap();
+//!     key = node.first_child().unwrap().into_raw_key();
+//! }
+//! let imported = thread::Builder::new()
+//!     .stack_size(64 * 1024)
+//!     .spawn(move || FreeformTree::<u32>::from_json(tree.to_json().unwrap()).unwrap())
+//!     .unwrap()
+//!     .join()
+//!     .unwrap();
+//! assert_eq!(imported.num_nodes(), 10_000);
+//! ```
+//!
+//! [`Tree::to_json`]: ../tree/trait.Tree.html#method.to_json " "
+//! [`FreeformTree::from_json`]: ../freeform_tree/struct.FreeformTree.html#method.from_json " "
+
+use alloc::vec::Vec;
+use serde::Serialize;
+use serde_json::{Error, Map, Value};
+use crate::{
+    traversal::algorithms::{first_in_postorder, next_in_postorder},
+    tree::Tree,
+    NodeValue,
+};
+#[cfg(feature = "freeform_tree")]
+use serde::{de::Error as _, de::DeserializeOwned};
+#[cfg(feature = "freeform_tree")]
+use crate::repr::TreeRepr;
+
+const PAYLOAD: &str = "payload";
+const CHILDREN: &str = "children";
+
+/// Serializes the tree, as described in the module-level documentation.
+///
+/// The nodes are serialized in post-order, so that the objects of the children are ready by the time their parent is visited, without recursing into the children.
+pub(crate) fn tree_to_json<T>(tree: &T) -> Result<Value, Error>
+where
+    T: Tree,
+    T::Branch: Serialize,
+    T::Leaf: Serialize,
+{
+    // The objects of the nodes whose parents have not been visited yet. In post-order, the
+    // children of a node are always the last ones pushed before the node itself is visited.
+    let mut stack = Vec::new();
+    let root = tree.root_key();
+    let mut key = first_in_postorder(tree, &root);
+    loop {
+        let mut object = Map::new();
+        match tree
+            .node_value(&key)
+            .expect("keys of reachable nodes are valid")
+        {
+            NodeValue::Branch(payload) => {
+                object.insert(PAYLOAD.into(), serde_json::to_value(payload)?);
+                let children = stack.split_off(stack.len() - tree.num_children_of(&key));
+                object.insert(CHILDREN.into(), Value::Array(children));
+            }
+            NodeValue::Leaf(payload) => {
+                object.insert(PAYLOAD.into(), serde_json::to_value(payload)?);
+            }
+        }
+        stack.push(Value::Object(object));
+        if key == root {
+            break;
+        }
+        key = next_in_postorder(tree, &key).expect("the root node is visited last");
+    }
+    Ok(stack.pop().expect("the root node was pushed last"))
+}
+
+/// Deserializes the node and all of its descendants into a nested enum representation, as described in the module-level documentation.
+///
+/// The objects are walked depth-first with an explicit stack of the branch nodes which are still missing some of their children, so that deeply nested documents do not overflow the call stack.
+#[cfg(feature = "freeform_tree")]
+pub(crate) fn repr_from_json<B, L>(value: Value) -> Result<TreeRepr<B, L>, Error>
+where
+    B: DeserializeOwned,
+    L: DeserializeOwned,
+{
+    // The payload of every unfinished branch node, the children which are yet to be visited and
+    // the ones which are finished already.
+    let mut stack = Vec::new();
+    let mut value = value;
+    loop {
+        let (payload, children) = split_node(value)?;
+        let mut finished = if children.is_empty() {
+            TreeRepr::Leaf(serde_json::from_value(payload)?)
+        } else {
+            let num_children = children.len();
+            let mut children = children.into_iter();
+            value = children.next().expect("the list of children is not empty");
+            stack.push((
+                serde_json::from_value(payload)?,
+                children,
+                Vec::with_capacity(num_children),
+            ));
+            continue;
+        };
+        // Hand the finished node to its parent, finishing the parent as well if that was its last
+        // child, until a parent with children left to visit is found.
+        loop {
+            let (_, remaining, done) = match stack.last_mut() {
+                Some(parent) => parent,
+                None => return Ok(finished),
+            };
+            done.push(finished);
+            if let Some(next) = remaining.next() {
+                value = next;
+                break;
+            }
+            let (payload, _, done) = stack.pop().expect("the stack was checked to be non-empty");
+            finished = TreeRepr::Branch(payload, done);
+        }
+    }
+}
+/// Splits a node object into its payload and its children, as described in the module-level documentation.
+#[cfg(feature = "freeform_tree")]
+fn split_node(value: Value) -> Result<(Value, Vec<Value>), Error> {
+    let mut object = match value {
+        Value::Object(object) => object,
+        _ => return Err(Error::custom("expected a node object")),
+    };
+    let payload = object
+        .remove(PAYLOAD)
+        .ok_or_else(|| Error::missing_field(PAYLOAD))?;
+    let children = match object.remove(CHILDREN) {
+        Some(Value::Array(children)) => children,
+        None | Some(Value::Null) => Vec::new(),
+        Some(..) => {
+            return Err(Error::custom(
+                "expected the children of a node to be an array",
+            ))
+        }
+    };
+    Ok((payload, children))
+}
+
+#[cfg(feature = "freeform_tree")]
+mod freeform_tree {
+    use core::fmt::Debug;
+    use serde::de::DeserializeOwned;
+    use serde_json::{Error, Value};
+    use crate::{
+        freeform_tree::{FreeformTree, Node},
+        Storage,
+    };
+    use super::repr_from_json;
+
+    impl<B, L, K, S> FreeformTree<B, L, K, S>
+    where
+        S: Storage<Element = Node<B, L, K>, Key = K>,
+        K: Clone + Debug + Eq,
+    {
+        /// Deserializes a freeform tree from a JSON value, using the schema described in the [`json`] module.
+        ///
+        /// # Errors
+        /// Will fail if the value does not follow the schema or if deserializing one of the payloads fails.
+        ///
+        /// [`json`]: ../json/index.html " "
+        #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "json")))]
+        pub fn from_json(value: Value) -> Result<Self, Error>
+        where
+            B: DeserializeOwned,
+            L: DeserializeOwned,
+        {
+            repr_from_json(value).map(Self::from_repr)
+        }
+    }
+}
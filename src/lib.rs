@@ -1,19 +1,28 @@
 //! Implements arena-allocated tree data structures and interfaces to work with them.
 //!
 //! # Overview
-//! Charcoal implements various kinds of trees using a technique called ["arena-allocated trees"][arena tree blog post], described by Ben Lovy. The gist of it is that the trees use some sort of backing storage to store the elements, typically a [`Vec`] (or its variants, like [`SmallVec`] or [`ArrayVec`]), and instead of using pointers to link to children, indices into the storage are used instead. This significantly improves element insertion and removal performance as compared to `Rc`-based trees, and gives room for supporting configurations without a global memory allocator.
+//! Charcoal implements various kinds of trees using a technique called [“arena-allocated trees”][arena tree blog post], described by Ben Lovy. The gist of it is that the trees use some sort of backing storage to store the elements, typically a [`Vec`] (or its variants, like [`SmallVec`] or [`ArrayVec`]), and instead of using pointers to link to children, indices into the storage are used instead. This significantly improves element insertion and removal performance as compared to `Rc`-based trees, and gives room for supporting configurations without a global memory allocator.
 //!
 //! # Storage
 //! Charcoal uses [Granite] to handle arena-allocated storage. Several feature flags are used to enable various dependencies on various storage types via forwaring them to Granite.
 //!
 //! # Feature flags
 //! - `std` (**enabled by default**) — enables the full standard library, disabling `no_std` for the crate. Currently, this only adds [`Error`] trait implementations for some types.
-//! - `unwind_safety` (**enabled by default**) — **Must be enabled when using the unwinding panic implementation, otherwise using methods which accept closures is undefined behavior.** Requires `std`. Not a concern in `no_std` builds, since those do not have a panicking runtime by default.
+//! - `unwind_safety` (**enabled by default**) — **Must be enabled when using the unwinding panic implementation, otherwise using methods which accept closures is undefined behavior.** Requires `std`. Methods which remove nodes, such as `try_remove_leaf_with` and `try_remove_children_with`, keep the tree in a valid state if their closures panic; the rest abort the process instead, since there's no way for them to restore a valid state. Not a concern in `no_std` builds, since those do not have a panicking runtime by default.
 //! - `alloc` (**enabled by default**) — adds `ListStorage` trait implementations for standard library containers, except for `LinkedList`, which is temporarily unsupported. *This does not require standard library support and will only panic at runtime in `no_std` environments without an allocator.*
+//! - `binary_heap` (**enabled by default**) — adds the [`binary_heap`] module with priority queues shaped as implicit d-ary trees, which need no links between the nodes and thus work with any `ListStorage`, including ones which do not require a memory allocator.
 //! - `smallvec` — forwarded to Granite, adds a `ListStorage` trait implementation for [`SmallVec`].
 //! - `slab` — forwarded to Granite, adds a `Storage` trait implementation for [`Slab`].
-//! - `slotmap` — forwarded to Granite, adds `Storage` trait implementations for [`SlotMap`], [`HopSlotMap`] and [`DenseSlotMap`].
+//! - `slotmap` — forwarded to Granite, adds `Storage` trait implementations for [`SlotMap`], [`HopSlotMap`] and [`DenseSlotMap`]. Also implements [`StableStorage`] for them, since their keys are versioned.
+//! - `petgraph` — adds the [`graph`] module for converting trees into [petgraph] graphs. Requires `alloc`.
+//! - `indextree` and `ego-tree` — add conversions between freeform trees and the trees of [indextree] and [ego-tree], described in the [`interop`] module. Require `alloc` and `freeform_tree`.
+//! - `json` — adds JSON import and export for trees via [`serde_json`], described in the [`json`] module. Requires `alloc`.
 //! - `union_optimizations` — forwarded to Granite, adds some layout optimizations by using untagged unions, decreasing memory usage in `SparseStorage`. **Requires a nightly compiler** (see [tracking issue for RFC 2514]) and thus is disabled by default.
+//! - `allocator_api` — adds the [`allocator`] module for storing trees in custom allocators. Requires `alloc`. **Requires a nightly compiler** (see [tracking issue for `allocator_api`]) and thus is disabled by default.
+//! - `dom` — adds the [`dom`] module for loading and saving documents written in a simple subset of XML as freeform trees. Requires `alloc` and `freeform_tree`.
+//! - `raw_view` — adds the [`raw_view`] module for exporting trees as flat arrays of payloads and node indices, e.g. for uploading them to GPU buffers or passing them to C code. Requires `alloc`.
+//! - `async` — adds [`AsyncVisitor`] and the [`traverse_async`] driver for traversals which wait for I/O while processing the nodes. Does not depend on any async runtime.
+//! - `bench` — adds the [`bench_support`] module with deterministic tree generators for benchmarking. Enables `alloc` and all of the tree types. The benchmark suite of the crate additionally needs the optional `criterion` dependency, and is run with `cargo bench --features bench,criterion`.
 //!
 //! # Public dependencies
 //! - `arrayvec` (**required**) — `^0.5`
@@ -21,6 +30,11 @@
 //!     - `smallvec` (*optional*) — `^1.4`
 //!     - `slab` (*optional*) — `^0.4`
 //!     - `slotmap` (*optional*) — `^0.4`
+//! - `petgraph` (*optional*) — `^0.5`
+//! - `indextree` (*optional*) — `^4`
+//! - `ego-tree` (*optional*) — `^0.10`
+//! - `serde` (*optional*) — `^1.0`
+//! - `serde_json` (*optional*) — `^1.0`
 //!
 //! # Contributing
 //! You can help by contributing to Charcoal in those aspects:
@@ -37,8 +51,24 @@
 //! [`SlotMap`]: https://docs.rs/slotmap/*/slotmap/struct.SlotMap.html " "
 //! [`HopSlotMap`]: https://docs.rs/slotmap/*/slotmap/hop/struct.HopSlotMap.html " "
 //! [`DenseSlotMap`]: https://docs.rs/slotmap/*/slotmap/dense/struct.DenseSlotMap.html " "
+//! [`StableStorage`]: trait.StableStorage.html " "
 //! [Granite]: https://docs.rs/granite/*/granite/ " "
+//! [petgraph]: https://docs.rs/petgraph/*/petgraph/ " "
+//! [`graph`]: graph/index.html " "
+//! [indextree]: https://docs.rs/indextree/*/indextree/ " "
+//! [ego-tree]: https://docs.rs/ego-tree/*/ego_tree/ " "
+//! [`interop`]: interop/index.html " "
+//! [`serde_json`]: https://docs.rs/serde_json/*/serde_json/ " "
+//! [`json`]: json/index.html " "
 //! [tracking issue for RFC 2514]: https://github.com/rust-lang/rust/issues/55149 " "
+//! [`allocator`]: allocator/index.html " "
+//! [tracking issue for `allocator_api`]: https://github.com/rust-lang/rust/issues/32838 " "
+//! [`raw_view`]: raw_view/index.html " "
+//! [`dom`]: dom/index.html " "
+//! [`AsyncVisitor`]: traversal/trait.AsyncVisitor.html " "
+//! [`traverse_async`]: traversal/fn.traverse_async.html " "
+//! [`bench_support`]: bench_support/index.html " "
+//! [`binary_heap`]: binary_heap/index.html " "
 //! [arena tree blog post]: https://dev.to/deciduously/no-more-tears-no-more-knots-arena-allocated-trees-in-rust-44k6 " "
 
 #![warn(
@@ -58,14 +88,15 @@
 #![deny(anonymous_parameters, bare_trait_objects)]
 #![allow(
     clippy::use_self, // FIXME reenable when it gets fixed
-    clippy::clippy::wildcard_imports, // Worst lint ever
-    clippy::clippy::module_name_repetitions, // Annoying and stupid
+    clippy::wildcard_imports, // Worst lint ever
+    clippy::module_name_repetitions, // Annoying and stupid
     clippy::shadow_unrelated, // Countless false positives, very annoying
 )]
 #![cfg_attr(not(feature = "std"), no_std)]
 // TODO reimplement LinkedList
 //#![cfg_attr(feature = "linked_list_storage", feature(linked_list_cursors))]
 #![cfg_attr(feature = "doc_cfg", feature(doc_cfg))]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
@@ -73,6 +104,13 @@ extern crate alloc;
 pub extern crate granite as storage;
 #[doc(no_inline)]
 pub use storage::{Storage, ListStorage, DefaultStorage};
+mod stable_storage;
+pub use stable_storage::{StableStorage, NonShiftingStorage};
+pub mod small_key;
+
+#[cfg(feature = "allocator_api")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "allocator_api")))]
+pub mod allocator;
 
 #[cfg(feature = "binary_tree")]
 #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "binary_tree")))]
@@ -102,9 +140,106 @@ pub mod freeform_tree;
 #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "freeform_tree")))]
 pub use freeform_tree::{FreeformTree};
 
+#[cfg(all(feature = "alloc", feature = "freeform_tree"))]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(all(feature = "alloc", feature = "freeform_tree"))))]
+pub mod bk_tree;
+
+#[cfg(feature = "binary_heap")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "binary_heap")))]
+pub mod binary_heap;
+
 pub mod traversal;
 pub use traversal::{Visitor, VisitorMut, Traversable, TraversableMut};
 
+pub mod tree;
+pub use tree::Tree;
+
+pub mod augment;
+
+pub mod merkle;
+
+pub mod fmt;
+
+pub mod behavior_tree;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub mod journal;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub mod frozen;
+
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+pub mod shared;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub mod cow;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub mod defrag;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub mod path;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub mod repr;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub use repr::TreeRepr;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub mod codec;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub mod rewrite;
+
+#[cfg(feature = "json")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "json")))]
+pub mod json;
+
+#[cfg(all(feature = "std", feature = "freeform_tree"))]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(all(feature = "std", feature = "freeform_tree"))))]
+pub mod fs;
+
+#[cfg(feature = "dom")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "dom")))]
+pub mod dom;
+
+#[cfg(all(feature = "petgraph", feature = "alloc"))]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(all(feature = "petgraph", feature = "alloc"))))]
+pub mod graph;
+
+#[cfg(all(
+    any(feature = "indextree", feature = "ego-tree"),
+    feature = "alloc",
+    feature = "freeform_tree",
+))]
+#[cfg_attr(
+    feature = "doc_cfg",
+    doc(cfg(all(
+        any(feature = "indextree", feature = "ego-tree"),
+        feature = "alloc",
+        feature = "freeform_tree",
+    ))),
+)]
+pub mod interop;
+
+#[cfg(feature = "raw_view")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "raw_view")))]
+pub mod raw_view;
+
+#[cfg(feature = "bench")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "bench")))]
+pub mod bench_support;
+
 /// A prelude for using Charcoal, containing the most used types in a renamed form for safe glob-importing.
 pub mod prelude {
     #[cfg(feature = "binary_tree")]
@@ -131,7 +266,7 @@ pub mod prelude {
 
 pub(crate) mod util;
 
-use core::fmt::{self, Formatter, Display, Debug};
+use core::fmt::{Formatter, Display, Debug};
 
 /// The payload of a node of a tree.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -201,7 +336,7 @@ pub enum TryRemoveLeafError {
     CannotRemoveIndividualChildren,
 }
 impl Display for TryRemoveLeafError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         f.pad(match self {
             Self::WasRootNode => "cannot remove the root node of a tree",
             Self::WasBranchNode => "cannot remove branch nodes without recursion",
@@ -228,24 +363,16 @@ pub enum TryRemoveBranchError {
     CannotRemoveIndividualChildren,
 }
 impl Display for TryRemoveBranchError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         f.pad(match self {
             Self::WasRootNode => "cannot remove the root node of a tree",
             Self::WasLeafNode => "expected a branch node, found leaf",
             Self::HadBranchChild(index) => {
-                #[cfg(feature = "alloc")]
-                {
-                    return f.pad(&format!(
-                        "\
-node had a branch child (index {}), which cannot be removed without recursion",
-                        index,
-                    ));
-                }
-                #[cfg(not(feature = "alloc"))]
-                {
-                    "\
-node had a branch child, which cannot be removed without recursion"
-                }
+                return write!(
+                    f,
+                    "node had a branch child (index {}), which cannot be removed without recursion",
+                    index,
+                );
             }
             Self::CannotRemoveIndividualChildren => {
                 "removing individual children is not available for the tree"
@@ -266,23 +393,15 @@ pub enum TryRemoveChildrenError {
     HadBranchChild(u32),
 }
 impl Display for TryRemoveChildrenError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         f.pad(match self {
             Self::WasLeafNode => "expected a branch node, found leaf",
             Self::HadBranchChild(index) => {
-                #[cfg(feature = "alloc")]
-                {
-                    return f.pad(&format!(
-                        "\
-node had a branch child (index {}), which cannot be removed without recursion",
-                        index,
-                    ));
-                }
-                #[cfg(not(feature = "alloc"))]
-                {
-                    "\
-node had a branch child, which cannot be removed without recursion"
-                }
+                return write!(
+                    f,
+                    "node had a branch child (index {}), which cannot be removed without recursion",
+                    index,
+                );
             }
         })
     }
@@ -291,6 +410,56 @@ node had a branch child, which cannot be removed without recursion"
 #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
 impl std::error::Error for TryRemoveChildrenError {}
 
+/// The error type returned by methods on trees which move a node and its descendants to a different parent node.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReparentError {
+    /// The node was the root node, which cannot have a parent.
+    WasRootNode,
+    /// The key of the new parent node does not point to a node in the tree.
+    NewParentNotFound,
+    /// The new parent node is the node itself or one of its descendants, which would detach the node from the rest of the tree.
+    NewParentInSubtree,
+    /// The position among the children of the new parent node was out of bounds. Contains the largest allowed position, which is the number of children the new parent node has, not counting the node being moved.
+    PositionOutOfBounds(usize),
+    /// The new parent node has no free slot for another child.
+    NewParentFull,
+    /// The node was the left child of a full branch node in a binary tree which [keeps the right child in place], so moving it away would shift the right child into its slot.
+    ///
+    /// [keeps the right child in place]: binary_tree/enum.RemovalPolicy.html#variant.KeepRightInPlace " "
+    WouldShiftRightChild,
+    /// The tree [only allows full branch nodes], and moving the node would leave its old parent or its new parent with only one child.
+    ///
+    /// [only allows full branch nodes]: binary_tree/struct.BinaryTree.html#method.enforce_full_branches " "
+    WouldMakePartialBranch,
+}
+impl Display for ReparentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.pad(match self {
+            Self::WasRootNode => "the root node cannot be moved to a different parent",
+            Self::NewParentNotFound => "the new parent node does not exist",
+            Self::NewParentInSubtree => "the new parent node is inside of the subtree being moved",
+            Self::PositionOutOfBounds(max) => {
+                return write!(
+                    f,
+                    "position is out of bounds (the new parent node allows at most {})",
+                    max,
+                );
+            }
+            Self::NewParentFull => "the new parent node has no room for another child",
+            Self::WouldShiftRightChild => {
+                "moving the node would shift the right child of its parent into its place"
+            }
+            Self::WouldMakePartialBranch => {
+                "moving the node would leave a branch node with one child"
+            }
+        })
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl std::error::Error for ReparentError {}
+
 /// The error type returned by methods on trees which convert leaf nodes into branch nodes, which occurs when the node which was attempted to be converted already is a branch node.
 #[derive(Copy, Clone, Debug)]
 pub struct MakeBranchError<L, P>
@@ -304,7 +473,7 @@ impl<L, P> Display for MakeBranchError<L, P>
 where
     P: IntoIterator<Item = L>,
 {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         f.pad("the node already was a branch")
     }
 }
@@ -316,3 +485,125 @@ where
     P: IntoIterator<Item = L> + Debug,
 {
 }
+
+/// The error type returned by methods on trees which convert leaf nodes into branch nodes without panicking if the storage runs out of space.
+#[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
+pub enum TryMakeBranchError<L, P>
+where
+    P: IntoIterator<Item = L>,
+{
+    /// The node already was a branch node.
+    WasBranchNode {
+        /// The packed children which were passed to the function, returned back to the caller to avoid dropping them.
+        packed_children: P,
+    },
+    /// The storage of the tree has a fixed capacity and does not have enough free space for the children.
+    OutOfCapacity {
+        /// The packed children which were passed to the function, returned back to the caller to avoid dropping them.
+        packed_children: P,
+    },
+    /// The tree only allows branch nodes which have all of their children, but fewer children were provided.
+    PartialBranch {
+        /// The packed children which were passed to the function, returned back to the caller to avoid dropping them.
+        packed_children: P,
+    },
+}
+impl<L, P> TryMakeBranchError<L, P>
+where
+    P: IntoIterator<Item = L>,
+{
+    /// Extracts the packed children which were passed to the function and were deemed useless because the call failed.
+    #[allow(clippy::missing_const_for_fn)] // Clippy has no idea what a destructor is
+    pub fn packed_children(self) -> P {
+        match self {
+            Self::WasBranchNode { packed_children }
+            | Self::OutOfCapacity { packed_children }
+            | Self::PartialBranch { packed_children } => packed_children,
+        }
+    }
+}
+impl<L, P> From<MakeBranchError<L, P>> for TryMakeBranchError<L, P>
+where
+    P: IntoIterator<Item = L>,
+{
+    fn from(op: MakeBranchError<L, P>) -> Self {
+        Self::WasBranchNode {
+            packed_children: op.packed_children,
+        }
+    }
+}
+impl<L, P> Display for TryMakeBranchError<L, P>
+where
+    P: IntoIterator<Item = L>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.pad(match self {
+            Self::WasBranchNode { .. } => "the node already was a branch",
+            Self::OutOfCapacity { .. } => "the storage does not have enough free space for the children",
+            Self::PartialBranch { .. } => "the tree does not allow branch nodes with missing children",
+        })
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl<L, P> std::error::Error for TryMakeBranchError<L, P>
+where
+    L: Debug,
+    P: IntoIterator<Item = L> + Debug,
+{
+}
+
+/// The error type returned by methods on trees which add nodes without panicking if the storage runs out of space, which occurs when the storage has a fixed capacity and is already full.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct CapacityError<T> {
+    /// The payload of the node which was attempted to be added, returned back to the caller to avoid dropping it.
+    pub payload: T,
+}
+impl<T> Display for CapacityError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.pad("the storage does not have enough free space for the node")
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl<T: Debug> std::error::Error for CapacityError<T> {}
+
+/// The error type returned by methods on trees which add individual children to nodes.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TryAddChildError<L> {
+    /// The node already had the maximum number of children it can have.
+    WasFullBranch {
+        /// The payload of the child which was attempted to be added, returned back to the caller to avoid dropping it.
+        child_payload: L,
+    },
+    /// The tree does not support adding individual children or such support was manually disabled.
+    CannotAddIndividualChildren {
+        /// The payload of the child which was attempted to be added, returned back to the caller to avoid dropping it.
+        child_payload: L,
+    },
+}
+impl<L> TryAddChildError<L> {
+    /// Extracts the payload of the child which was attempted to be added.
+    #[allow(clippy::missing_const_for_fn)] // Clippy has no idea what a destructor is
+    pub fn child_payload(self) -> L {
+        match self {
+            Self::WasFullBranch { child_payload }
+            | Self::CannotAddIndividualChildren { child_payload } => child_payload,
+        }
+    }
+}
+impl<L> Display for TryAddChildError<L> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.pad(match self {
+            Self::WasFullBranch { .. } => "the node already had the maximum number of children",
+            Self::CannotAddIndividualChildren { .. } => {
+                "adding individual children is not available for the tree"
+            }
+        })
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl<L: Debug> std::error::Error for TryAddChildError<L> {}
@@ -0,0 +1,397 @@
+//! Merkle trees, in which every branch node stores a hash of its payload and the hashes of its children.
+//!
+//! The [`Merkle`] augmentation keeps the hashes up to date as the tree changes, recomputing only the hashes of the ancestors of the changed nodes. Hashes are produced by a [`Hasher`], which can be implemented to use any hash function, including cryptographic ones for content-addressed structures. Every [`BuildHasher`] is a `Hasher` producing `u64` hashes, which is enough for detecting changes and comparing subtrees.
+//!
+//! [`Merkle`]: struct.Merkle.html " "
+//! [`Hasher`]: trait.Hasher.html " "
+//! [`BuildHasher`]: https://doc.rust-lang.org/core/hash/trait.BuildHasher.html " "
+
+use core::hash::{Hash, BuildHasher, Hasher as _};
+use crate::{
+    traversal::{
+        algorithms::{first_in_postorder, next_in_postorder},
+        CursorResult,
+        VisitorDirection,
+    },
+    NodeValue,
+    Traversable,
+    TraversableMut,
+    TryRemoveLeafError,
+    TryRemoveBranchError,
+    TryRemoveChildrenError,
+    TryAddChildError,
+    MakeBranchError,
+};
+
+/// A hash function for the nodes of a [`Merkle`] tree.
+///
+/// The hashes of leaf nodes only depend on their payload, while the hashes of branch nodes depend on their payload and the hashes of their children, in order.
+///
+/// [`Merkle`]: struct.Merkle.html " "
+pub trait Hasher<B, L> {
+    /// The type of the hashes.
+    type Hash: Clone;
+    /// Hashes the payload of a leaf node.
+    fn hash_leaf(&self, payload: &L) -> Self::Hash;
+    /// Hashes the payload of a branch node together with the hashes of its children.
+    fn hash_branch<I: Iterator<Item = Self::Hash>>(
+        &self,
+        payload: &B,
+        child_hashes: I,
+    ) -> Self::Hash;
+}
+impl<B: Hash, L: Hash, S: BuildHasher> Hasher<B, L> for S {
+    type Hash = u64;
+    fn hash_leaf(&self, payload: &L) -> u64 {
+        let mut hasher = self.build_hasher();
+        // Keep leaf nodes from colliding with branch nodes which have the same payload
+        false.hash(&mut hasher);
+        payload.hash(&mut hasher);
+        hasher.finish()
+    }
+    fn hash_branch<I: Iterator<Item = u64>>(&self, payload: &B, child_hashes: I) -> u64 {
+        let mut hasher = self.build_hasher();
+        true.hash(&mut hasher);
+        payload.hash(&mut hasher);
+        for child_hash in child_hashes {
+            hasher.write_u64(child_hash);
+        }
+        hasher.finish()
+    }
+}
+
+/// The payload of a branch node augmented with the hash of its subtree.
+///
+/// Trees wrapped in [`Merkle`] use this as the payload type for branch nodes. The hash can only be changed by `Merkle` itself; branch nodes created with [`new`] start out without a hash, which is computed by `Merkle` when the node is added to the tree.
+///
+/// [`Merkle`]: struct.Merkle.html " "
+/// [`new`]: #method.new " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct WithHash<B, H> {
+    /// The payload of the branch node itself.
+    pub payload: B,
+    hash: Option<H>,
+}
+impl<B, H> WithHash<B, H> {
+    /// Wraps the payload of a branch node.
+    pub const fn new(payload: B) -> Self {
+        Self {
+            payload,
+            hash: None,
+        }
+    }
+    /// Returns the hash of the subtree of the node, or `None` if it hasn't been computed yet.
+    pub fn hash(&self) -> Option<&H> {
+        self.hash.as_ref()
+    }
+    /// Returns the payload of the branch node, discarding the hash.
+    #[allow(clippy::missing_const_for_fn)] // Clippy has no idea what a destructor is
+    pub fn into_inner(self) -> B {
+        self.payload
+    }
+}
+impl<B, H> From<B> for WithHash<B, H> {
+    fn from(op: B) -> Self {
+        Self::new(op)
+    }
+}
+
+/// A tree which keeps the hash of the subtree of every branch node up to date, making it a Merkle tree.
+///
+/// The hashes are stored in the payloads of branch nodes, which is why the wrapped tree must use [`WithHash`] as its branch payload type. The wrapper implements [`Traversable`] and [`TraversableMut`] by forwarding to the tree, hiding `WithHash` from the payloads, and recomputes the hashes of all ancestors of a node whenever children are added to it or removed from it. Since the payload of a node might change after `value_mut_of` returns, the hashes are recomputed after changes of payloads lazily, right before the next change or retrieval of a hash, which is why retrieving hashes requires mutable access.
+///
+/// Since the hashes would go stale if the tree was changed directly, the wrapped tree can only be accessed immutably, or taken out of the wrapper with [`into_inner`].
+///
+/// # Example
+/// ```rust
+/// use charcoal::{
+///     merkle::{Merkle, WithHash},
+///     FreeformTree,
+///     NodeValue,
+///     Traversable,
+///     TraversableMut,
+/// };
+/// use std::{collections::hash_map::DefaultHasher, hash::BuildHasherDefault};
+///
+/// let hasher = BuildHasherDefault::<DefaultHasher>::default();
+/// let mut tree = Merkle::new(FreeformTree::<WithHash<u32, u64>, u32>::new(0), hasher);
+/// let root = tree.cursor_to_root();
+/// let first = tree.try_add_child(&root, 1, |x| x).unwrap();
+/// tree.try_add_child(&root, 2, |x| x).unwrap();
+/// let original_hash = tree.root_hash();
+///
+/// if let NodeValue::Leaf(payload) = tree.value_mut_of(&first) {
+///     *payload = 10;
+/// }
+/// assert_ne!(tree.root_hash(), original_hash);
+/// // The hash only depends on the contents of the tree.
+/// if let NodeValue::Leaf(payload) = tree.value_mut_of(&first) {
+///     *payload = 1;
+/// }
+/// assert_eq!(tree.root_hash(), original_hash);
+/// ```
+///
+/// [`WithHash`]: struct.WithHash.html " "
+/// [`Traversable`]: ../traversal/trait.Traversable.html " "
+/// [`TraversableMut`]: ../traversal/trait.TraversableMut.html " "
+/// [`into_inner`]: #method.into_inner " "
+#[derive(Clone, Debug)]
+pub struct Merkle<T: Traversable, H> {
+    traversable: T,
+    hasher: H,
+    pending_value: Option<T::Cursor>,
+}
+impl<T, B, L, H> Merkle<T, H>
+where
+    T: TraversableMut<Branch = WithHash<B, H::Hash>, Leaf = L>,
+    H: Hasher<B, L>,
+{
+    /// Wraps the specified tree, computing the hashes of all branch nodes in it with the specified hasher.
+    pub fn new(traversable: T, hasher: H) -> Self {
+        let mut merkle = Self {
+            traversable,
+            hasher,
+            pending_value: None,
+        };
+        // Children come before their parents in post-order, so their hashes are always ready
+        let mut cursor =
+            first_in_postorder(&merkle.traversable, &merkle.traversable.cursor_to_root());
+        loop {
+            merkle.store_hash(&cursor);
+            cursor = match next_in_postorder(&merkle.traversable, &cursor) {
+                Some(next) => next,
+                None => break merkle,
+            };
+        }
+    }
+    /// Returns a reference to the wrapped tree.
+    ///
+    /// The hashes might be stale if the payload of a node has been changed since the last time a hash was retrieved.
+    pub fn inner(&self) -> &T {
+        &self.traversable
+    }
+    /// Returns a reference to the hasher.
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+    /// Unwraps the tree and the hasher, updating the hashes first.
+    pub fn into_inner(mut self) -> (T, H) {
+        self.rehash_pending_value();
+        (self.traversable, self.hasher)
+    }
+    /// Returns the hash of the subtree of the node at the specified cursor.
+    ///
+    /// # Panics
+    /// Will panic if the cursor is invalid.
+    pub fn hash_of(&mut self, cursor: &T::Cursor) -> H::Hash {
+        self.rehash_pending_value();
+        self.compute_hash(cursor)
+    }
+    /// Returns the hash of the whole tree.
+    pub fn root_hash(&mut self) -> H::Hash {
+        let root = self.traversable.cursor_to_root();
+        self.hash_of(&root)
+    }
+
+    /// Returns the hash of the node at the specified cursor, using the stored hashes of its children.
+    fn compute_hash(&self, cursor: &T::Cursor) -> H::Hash {
+        match self.traversable.value_of(cursor) {
+            NodeValue::Branch(branch) => branch.hash.as_ref().map_or_else(
+                || self.compute_branch_hash(cursor, &branch.payload),
+                Clone::clone,
+            ),
+            NodeValue::Leaf(payload) => self.hasher.hash_leaf(payload),
+        }
+    }
+    fn compute_branch_hash(&self, cursor: &T::Cursor, payload: &B) -> H::Hash {
+        let traversable = &self.traversable;
+        let child_hashes = (0..traversable.num_children_of(cursor)).map(|i| {
+            let child = traversable
+                .nth_child_of(cursor, i)
+                .expect("the number of children changed while hashing");
+            self.compute_hash(&child)
+        });
+        self.hasher.hash_branch(payload, child_hashes)
+    }
+    /// Computes the hash of the node at the specified cursor from the hashes of its children and stores it, if it's a branch node.
+    fn store_hash(&mut self, cursor: &T::Cursor) {
+        let hash = match self.traversable.value_of(cursor) {
+            NodeValue::Branch(branch) => self.compute_branch_hash(cursor, &branch.payload),
+            NodeValue::Leaf(..) => return,
+        };
+        if let NodeValue::Branch(branch) = self.traversable.value_mut_of(cursor) {
+            branch.hash = Some(hash);
+        }
+    }
+    /// Recomputes the hashes of the node at the specified cursor and all of its ancestors.
+    fn rehash(&mut self, mut cursor: Option<T::Cursor>) {
+        while let Some(current) = cursor {
+            self.store_hash(&current);
+            cursor = self.traversable.parent_of(&current);
+        }
+    }
+    fn rehash_pending_value(&mut self) {
+        let pending_value = self.pending_value.take();
+        self.rehash(pending_value);
+    }
+}
+impl<T, B, L, H> Traversable for Merkle<T, H>
+where
+    T: TraversableMut<Branch = WithHash<B, H::Hash>, Leaf = L>,
+    H: Hasher<B, L>,
+{
+    type Leaf = T::Leaf;
+    type Branch = B;
+    type Cursor = T::Cursor;
+
+    fn advance_cursor<V>(
+        &self,
+        cursor: Self::Cursor,
+        direction: VisitorDirection<Self::Cursor, V>,
+    ) -> CursorResult<Self::Cursor> {
+        self.traversable.advance_cursor(cursor, direction)
+    }
+    fn cursor_to_root(&self) -> Self::Cursor {
+        self.traversable.cursor_to_root()
+    }
+    fn value_of(&self, cursor: &Self::Cursor) -> NodeValue<&'_ Self::Branch, &'_ Self::Leaf> {
+        match self.traversable.value_of(cursor) {
+            NodeValue::Branch(branch) => NodeValue::Branch(&branch.payload),
+            NodeValue::Leaf(leaf) => NodeValue::Leaf(leaf),
+        }
+    }
+    fn parent_of(&self, cursor: &Self::Cursor) -> Option<Self::Cursor> {
+        self.traversable.parent_of(cursor)
+    }
+    fn num_children_of(&self, cursor: &Self::Cursor) -> usize {
+        self.traversable.num_children_of(cursor)
+    }
+    fn nth_child_of(&self, cursor: &Self::Cursor, child_num: usize) -> Option<Self::Cursor> {
+        self.traversable.nth_child_of(cursor, child_num)
+    }
+    fn contains_cursor(&self, cursor: &Self::Cursor) -> bool {
+        self.traversable.contains_cursor(cursor)
+    }
+}
+impl<T, B, L, H> TraversableMut for Merkle<T, H>
+where
+    T: TraversableMut<Branch = WithHash<B, H::Hash>, Leaf = L>,
+    H: Hasher<B, L>,
+{
+    const CAN_REMOVE_INDIVIDUAL_CHILDREN: bool = T::CAN_REMOVE_INDIVIDUAL_CHILDREN;
+    const CAN_PACK_CHILDREN: bool = T::CAN_PACK_CHILDREN;
+    const CAN_ADD_INDIVIDUAL_CHILDREN: bool = T::CAN_ADD_INDIVIDUAL_CHILDREN;
+    type PackedChildren = T::PackedChildren;
+
+    fn value_mut_of(
+        &mut self,
+        cursor: &Self::Cursor,
+    ) -> NodeValue<&'_ mut Self::Branch, &'_ mut Self::Leaf> {
+        if self.pending_value.as_ref() != Some(cursor) {
+            self.rehash_pending_value();
+            self.pending_value = Some(cursor.clone());
+        }
+        match self.traversable.value_mut_of(cursor) {
+            NodeValue::Branch(branch) => NodeValue::Branch(&mut branch.payload),
+            NodeValue::Leaf(leaf) => NodeValue::Leaf(leaf),
+        }
+    }
+    fn try_remove_leaf<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<Self::Leaf, TryRemoveLeafError> {
+        self.rehash_pending_value();
+        // Parents are added to the storage before their children, so removing the node does not
+        // invalidate the cursor to its parent
+        let parent = self.traversable.parent_of(cursor);
+        let leaf = self
+            .traversable
+            .try_remove_leaf(cursor, |branch| branch_to_leaf(branch.payload))?;
+        self.rehash(parent);
+        Ok(leaf)
+    }
+    fn try_remove_branch_into<BtL: FnOnce(Self::Branch) -> Self::Leaf, C: FnMut(Self::Leaf)>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+        collector: C,
+    ) -> Result<Self::Branch, TryRemoveBranchError> {
+        self.rehash_pending_value();
+        let parent = self.traversable.parent_of(cursor);
+        let branch = self.traversable.try_remove_branch_into(
+            cursor,
+            |branch| branch_to_leaf(branch.payload),
+            collector,
+        )?;
+        self.rehash(parent);
+        Ok(branch.payload)
+    }
+    fn try_remove_children_into<BtL: FnOnce(Self::Branch) -> Self::Leaf, C: FnMut(Self::Leaf)>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+        collector: C,
+    ) -> Result<(), TryRemoveChildrenError> {
+        self.rehash_pending_value();
+        self.traversable.try_remove_children_into(
+            cursor,
+            |branch| branch_to_leaf(branch.payload),
+            collector,
+        )?;
+        self.rehash(Some(cursor.clone()));
+        Ok(())
+    }
+    fn try_remove_branch<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<(Self::Branch, Self::PackedChildren), TryRemoveBranchError> {
+        self.rehash_pending_value();
+        let parent = self.traversable.parent_of(cursor);
+        let (branch, children) = self
+            .traversable
+            .try_remove_branch(cursor, |branch| branch_to_leaf(branch.payload))?;
+        self.rehash(parent);
+        Ok((branch.payload, children))
+    }
+    fn try_remove_children<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<Self::PackedChildren, TryRemoveChildrenError> {
+        self.rehash_pending_value();
+        let children = self
+            .traversable
+            .try_remove_children(cursor, |branch| branch_to_leaf(branch.payload))?;
+        self.rehash(Some(cursor.clone()));
+        Ok(children)
+    }
+    fn try_add_child<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        child_payload: Self::Leaf,
+        leaf_to_branch: LtB,
+    ) -> Result<Self::Cursor, TryAddChildError<Self::Leaf>> {
+        self.rehash_pending_value();
+        let child = self
+            .traversable
+            .try_add_child(cursor, child_payload, |leaf| {
+                WithHash::new(leaf_to_branch(leaf))
+            })?;
+        self.rehash(Some(cursor.clone()));
+        Ok(child)
+    }
+    fn try_add_children<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        children: Self::PackedChildren,
+        leaf_to_branch: LtB,
+    ) -> Result<(), MakeBranchError<Self::Leaf, Self::PackedChildren>> {
+        self.rehash_pending_value();
+        self.traversable
+            .try_add_children(cursor, children, |leaf| WithHash::new(leaf_to_branch(leaf)))?;
+        self.rehash(Some(cursor.clone()));
+        Ok(())
+    }
+}
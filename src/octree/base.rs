@@ -1,7 +1,18 @@
 use core::fmt::Debug;
 use crate::{
     storage::{Storage, ListStorage, DefaultStorage, SparseStorage, SparseStorageSlot},
+    util::{has_room_for, clear_storage, defragment_tree, map_values_in_tree},
+    CapacityError,
+    StableStorage,
+    NodeValue,
+    Tree,
 };
+#[cfg(feature = "alloc")]
+use crate::util::collect_unreachable_in_tree;
+#[cfg(feature = "allocator_api")]
+use core::alloc::Allocator;
+#[cfg(feature = "allocator_api")]
+use crate::allocator::SparseVecIn;
 use super::{Node, NodeRef, NodeRefMut};
 
 /// An octree.
@@ -45,6 +56,33 @@ where
         });
         Self { storage, root }
     }
+    /// Creates an octree with the specified value for the root node, returning an error instead of panicking if the storage cannot fit the root node.
+    ///
+    /// # Errors
+    /// Will fail if the storage has a fixed capacity of zero. In such a case, the payload for the root node is returned back to the caller.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::octree::{InlineOctree, Node};
+    ///
+    /// // A storage with a capacity of zero cannot fit the root node:
+    /// let tree = InlineOctree::<[Node<u32, u32, usize>; 0], u32>::try_new(87);
+    /// assert_eq!(tree.unwrap_err().payload, 87);
+    /// // Anything bigger than that works fine:
+    /// let tree = InlineOctree::<[Node<u32, u32, usize>; 1], u32>::try_new(87).unwrap();
+    /// assert!(tree.root().is_leaf());
+    /// ```
+    pub fn try_new(root: L) -> Result<Self, CapacityError<L>> {
+        let mut storage = S::new();
+        if !has_room_for(&storage, 1) {
+            return Err(CapacityError { payload: root });
+        }
+        let root = storage.add(unsafe {
+            // SAFETY: as above
+            Node::root(root)
+        });
+        Ok(Self { storage, root })
+    }
     /// Creates an octree with the specified capacity for the storage.
     ///
     /// # Panics
@@ -120,6 +158,71 @@ where
             NodeRefMut::new_raw_unchecked(self, self.root.clone())
         }
     }
+    /// Consumes the tree and returns the payload of its root node, dropping all other nodes along with the storage.
+    ///
+    /// This saves the need to clone the payload out of the tree once it's no longer needed, such as when the root node holds the result of a computation which went through the whole tree. To replace the payload of the root node without consuming the tree, use [`Tree::set_root_value`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Octree, NodeValue};
+    ///
+    /// let mut tree = Octree::<_>::new(0);
+    /// tree.root_mut().make_branch([1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+    /// *tree.root_mut().value_mut().into_inner() = 10;
+    /// assert_eq!(tree.into_root_value(), NodeValue::Branch(10));
+    /// ```
+    ///
+    /// [`Tree::set_root_value`]: ../tree/trait.Tree.html#method.set_root_value " "
+    pub fn into_root_value(mut self) -> NodeValue<B, L> {
+        self.storage.remove(&self.root).value.into_value()
+    }
+    /// Returns a reference to the first node, in depth-first pre-order, the value of which satisfies the specified predicate, or `None` if there's no such node.
+    ///
+    /// This is the same as [`Tree::find`], but returns a reference specific to octrees.
+    ///
+    /// [`Tree::find`]: ../tree/trait.Tree.html#method.find " "
+    pub fn find(
+        &self,
+        predicate: impl FnMut(NodeValue<&'_ B, &'_ L>) -> bool,
+    ) -> Option<NodeRef<'_, B, L, K, S>> {
+        Tree::find(self, predicate).map(|node| unsafe {
+            // SAFETY: the key was just found in the tree
+            NodeRef::new_raw_unchecked(self, node.into_raw_key())
+        })
+    }
+    /// Returns a reference to the node at the specified locational code, or `None` if the code is malformed or there is no such node in the tree.
+    ///
+    /// Locational codes, also known as Morton codes or Z-order codes, address nodes by the path from the root to them: every level is encoded as 3 bits holding the index of the octant to descend into, with the levels closer to the root in the more significant bits. To tell apart codes of nodes at different depths, a single sentinel bit set to 1 is placed before the first level, meaning that the root node has the code `1`, its children have the codes from `0b1_000` to `0b1_111`, and so on. This way, a code can address nodes which are up to 21 levels below the root.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use charcoal::octree::Octree;
+    /// let mut tree = Octree::<_>::new(0);
+    /// let mut root = tree.root_mut();
+    /// root.make_branch([1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+    /// root.nth_child_mut(5).unwrap().make_branch([10, 11, 12, 13, 14, 15, 16, 17]).unwrap();
+    ///
+    /// // Descend into the sixth child of the root and then into its third child:
+    /// let node = tree.node_at_locational_code(0b1_101_010).unwrap();
+    /// assert_eq!(*node.value().into_inner(), 12);
+    /// assert_eq!(node.locational_code(), Some(0b1_101_010));
+    /// // The root always has the code 1, and codes without a sentinel bit are malformed.
+    /// assert_eq!(*tree.node_at_locational_code(1).unwrap().value().into_inner(), 0);
+    /// assert!(tree.node_at_locational_code(0).is_none());
+    /// ```
+    pub fn node_at_locational_code(&self, code: u64) -> Option<NodeRef<'_, B, L, K, S>> {
+        self.root().descendant_at_locational_code(code)
+    }
+    /// Returns a *mutable* reference to the node at the specified [locational code], or `None` if the code is malformed or there is no such node in the tree.
+    ///
+    /// [locational code]: #method.node_at_locational_code " "
+    pub fn node_at_locational_code_mut(&mut self, code: u64) -> Option<NodeRefMut<'_, B, L, K, S>> {
+        let key = self.node_at_locational_code(code)?.into_raw_key();
+        Some(unsafe {
+            // SAFETY: we just got the key from a valid NodeRef
+            NodeRefMut::new_raw_unchecked(self, key)
+        })
+    }
     /// Returns the number of nodes in the tree.
     pub fn num_nodes(&self) -> usize {
         self.storage.len()
@@ -130,22 +233,48 @@ where
     }
     /// Reserves capacity for at least additional more elements to be inserted in the given storage. The storage may reserve more space to avoid frequent reallocations. After calling `reserve`, capacity will be greater than or equal to `self.len()` + `additional`. Does nothing if capacity is already sufficient.
     pub fn reserve(&mut self, additional: usize) {
-        self.storage.reserve(additional)
+        self.storage.reserve(additional);
     }
     /// Shrinks the capacity of the storage as much as possible.
     ///
     /// It will drop down as close as possible to the current length, though dynamically allocated storages may not always reallocate exactly as much as it is needed to store all elements and none more.
     pub fn shrink_to_fit(&mut self) {
-        self.storage.shrink_to_fit()
+        self.storage.shrink_to_fit();
     }
 }
 impl<B, L, S> Octree<B, L, usize, SparseStorage<Node<B, L, usize>, S>>
 where
     S: ListStorage<Element = SparseStorageSlot<Node<B, L, usize>>>,
 {
+    /// Removes all nodes from the tree and replaces them with a new root leaf node with the specified payload, keeping the capacity of the storage, so that a tree which is rebuilt over and over again, such as once per frame, only allocates memory when it grows beyond its previous size.
+    ///
+    /// All keys to the old nodes become invalid, including the one to the old root node. If the storage has holes, they are removed first, like [`defragment`] does, which makes this slower than clearing a tree which had no nodes removed.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::Octree;
+    ///
+    /// let mut tree = Octree::<_>::new(0);
+    /// tree.root_mut().make_branch([1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+    /// let capacity = tree.capacity();
+    ///
+    /// tree.clear(9);
+    /// assert!(tree.root().is_leaf());
+    /// assert_eq!(*tree.root().value().into_inner(), 9);
+    /// assert_eq!(tree.num_nodes(), 1);
+    /// assert_eq!(tree.capacity(), capacity);
+    /// ```
+    ///
+    /// [`defragment`]: #method.defragment " "
+    pub fn clear(&mut self, root: L) {
+        self.root = clear_storage(&mut self.storage, unsafe {
+            // SAFETY: the old root is removed along with all other nodes
+            Node::root(root)
+        });
+    }
     /// Removes all holes from the sparse storage.
     ///
-    /// Under the hood, this uses `defragment_and_fix`. It's not possible to defragment without fixing the indicies, as that might cause undefined behavior.
+    /// Nodes which are stored past the new end of the storage are moved into the holes, which changes their keys, and the links between the nodes are fixed along the way.
     ///
     /// # Example
     /// ```rust
@@ -183,7 +312,66 @@ where
     /// assert_eq!(tree.num_holes(), 0);
     /// ```
     pub fn defragment(&mut self) {
-        self.storage.defragment_and_fix()
+        self.defragment_with(|_, _| {});
+    }
+    /// Removes all holes from the sparse storage like [`defragment`] does, calling the specified closure with the old and new key of every node which was moved. This allows keys which were stored outside of the tree to be updated instead of becoming invalid.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Octree, Tree};
+    ///
+    /// let mut tree = Octree::<_>::new(0);
+    /// tree.root_mut().make_branch([1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+    /// let mut root = tree.root_mut();
+    /// root.nth_child_mut(0).unwrap().make_branch([9; 8]).unwrap();
+    /// root.nth_child_mut(1).unwrap().make_branch([10; 8]).unwrap();
+    /// let mut key = root.nth_child(1).unwrap().nth_child(0).unwrap().into_raw_key();
+    /// tree.root_mut().nth_child_mut(0).unwrap().try_remove_children().unwrap();
+    /// tree.defragment_with(|old, new| {
+    ///     if old == key {
+    ///         key = new;
+    ///     }
+    /// });
+    /// assert_eq!(*tree.get(key).unwrap().value().into_inner(), 10);
+    /// ```
+    ///
+    /// [`defragment`]: #method.defragment " "
+    pub fn defragment_with(&mut self, on_move: impl FnMut(usize, usize)) {
+        defragment_tree(self, |tree| (&mut tree.storage, &mut tree.root), on_move);
+    }
+    /// Removes the nodes which are in the storage but cannot be reached from the root node, returning the number of nodes which were removed.
+    ///
+    /// Such nodes never appear when the tree is only modified through its safe interface, but can be left behind by code which works with raw keys or by operations which panic halfway through adding nodes, after which they would stay in the storage forever without any way to access them. The keys of all reachable nodes stay the same, and the storage is shrunk to end after the last of them, which also removes the holes at the end of the storage.
+    ///
+    /// The tree is walked once to find the reachable nodes and, if there are any other ones, the reachable nodes are moved into a new storage, which takes *O*(*n*) time, where *n* is the number of slots in the storage, including the holes.
+    ///
+    /// # Example
+    /// See the example in [`FreeformTree::collect_unreachable`].
+    ///
+    /// [`FreeformTree::collect_unreachable`]: ../freeform_tree/struct.FreeformTree.html#method.collect_unreachable " "
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+    pub fn collect_unreachable(&mut self) -> usize {
+        collect_unreachable_in_tree(self, |tree| &mut tree.storage)
+    }
+    /// Applies the specified closures to the payloads of all nodes of the tree, the first one for branch nodes and the second one for leaf nodes, without changing the structure of the tree.
+    ///
+    /// This shadows [`Tree::map_values_mut`]: if the storage has no holes, the nodes are processed in the order in which they're stored rather than by walking the tree, which makes bulk updates of the payloads friendlier to the cache. If there are holes, the tree is walked in depth-first pre-order, as with the trait method. Either way, this takes *O*(*n*) time and doesn't allocate memory. Nodes which are in the storage but [cannot be reached from the root node] are only visited if there are no holes.
+    ///
+    /// # Example
+    /// See the example in [`FreeformTree::map_values_mut`].
+    ///
+    /// [`Tree::map_values_mut`]: ../tree/trait.Tree.html#method.map_values_mut " "
+    /// [cannot be reached from the root node]: #method.collect_unreachable " "
+    /// [`FreeformTree::map_values_mut`]: ../freeform_tree/struct.FreeformTree.html#method.map_values_mut " "
+    pub fn map_values_mut(&mut self, branch_fn: impl FnMut(&mut B), leaf_fn: impl FnMut(&mut L)) {
+        map_values_in_tree(
+            self,
+            |tree| &mut tree.storage,
+            |node| node.value.as_mut().into_value(),
+            branch_fn,
+            leaf_fn,
+        );
     }
     /// Returns the number of holes in the storage. This operation returns immediately instead of looping through the entire storage, since the sparse storage automatically tracks the number of holes it creates and destroys.
     ///
@@ -204,6 +392,52 @@ where
         self.storage.is_dense()
     }
 }
+#[cfg(feature = "allocator_api")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "allocator_api")))]
+impl<B, L, A> Octree<B, L, usize, SparseVecIn<Node<B, L, usize>, A>>
+where
+    A: Allocator,
+{
+    /// Creates an octree with the specified value for the root node, storing the nodes in the specified allocator.
+    ///
+    /// See the [`allocator`] module for an example.
+    ///
+    /// [`allocator`]: ../allocator/index.html " "
+    pub fn new_in(root: L, alloc: A) -> Self {
+        Self::with_capacity_in(0, root, alloc)
+    }
+    /// Creates an octree with the specified value for the root node and the specified capacity for the storage, storing the nodes in the specified allocator.
+    pub fn with_capacity_in(capacity: usize, root: L, alloc: A) -> Self {
+        let mut storage = SparseVecIn::with_capacity_in(capacity, alloc);
+        let root = storage.add(unsafe {
+            // SAFETY: there isn't a root there yet
+            Node::root(root)
+        });
+        Self { storage, root }
+    }
+}
+impl<B, L, K, S> Octree<B, L, K, S>
+where
+    S: StableStorage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Returns a reference to the node with the specified key, or `None` if there's no such node in the tree.
+    ///
+    /// Since the storage is a [`StableStorage`], a key obtained from a node stays valid and keeps leading to that node until the node is removed, regardless of changes to other nodes.
+    ///
+    /// [`StableStorage`]: ../trait.StableStorage.html " "
+    pub fn get_by_key(&self, key: K) -> Option<NodeRef<'_, B, L, K, S>> {
+        NodeRef::new_raw(self, key)
+    }
+    /// Returns a *mutable* reference to the node with the specified key, or `None` if there's no such node in the tree.
+    ///
+    /// Since the storage is a [`StableStorage`], a key obtained from a node stays valid and keeps leading to that node until the node is removed, regardless of changes to other nodes.
+    ///
+    /// [`StableStorage`]: ../trait.StableStorage.html " "
+    pub fn get_mut_by_key(&mut self, key: K) -> Option<NodeRefMut<'_, B, L, K, S>> {
+        NodeRefMut::new_raw(self, key)
+    }
+}
 impl<B, L, K, S> Default for Octree<B, L, K, S>
 where
     L: Default,
@@ -0,0 +1,55 @@
+use core::fmt::Debug;
+use crate::{storage::Storage, tree::{self, Tree, TreeStats}, NodeValue};
+use super::{Octree, Node, NodeData};
+
+impl<B, L, K, S> Tree for Octree<B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn num_nodes(&self) -> usize {
+        self.storage.len()
+    }
+    fn reserve_nodes(&mut self, additional: usize) {
+        self.storage.reserve(additional);
+    }
+    fn stats(&self) -> TreeStats {
+        tree::stats_with_capacity(self, self.storage.capacity())
+    }
+    fn contains_key(&self, key: &Self::Cursor) -> bool {
+        self.storage.contains_key(key)
+    }
+    fn node_value(&self, key: &Self::Cursor) -> Option<NodeValue<&'_ Self::Branch, &'_ Self::Leaf>> {
+        self.storage
+            .get(key)
+            .map(|node| node.value.as_ref().into_value())
+    }
+    fn node_value_mut(
+        &mut self,
+        key: &Self::Cursor,
+    ) -> Option<NodeValue<&'_ mut Self::Branch, &'_ mut Self::Leaf>> {
+        self.storage
+            .get_mut(key)
+            .map(|node| node.value.as_mut().into_value())
+    }
+    fn parent_key(&self, key: &Self::Cursor) -> Option<Self::Cursor> {
+        self.storage.get(key)?.parent.clone()
+    }
+    fn first_child_key(&self, key: &Self::Cursor) -> Option<Self::Cursor> {
+        match &self.storage.get(key)?.value {
+            NodeData::Branch { children, .. } => Some(children[0].clone()),
+            NodeData::Leaf(..) => None,
+        }
+    }
+    fn next_sibling_key(&self, key: &Self::Cursor) -> Option<Self::Cursor> {
+        let parent_key = self.parent_key(key)?;
+        match &self.storage.get(&parent_key)?.value {
+            NodeData::Branch { children, .. } => children
+                .iter()
+                .position(|child| child == key)
+                .and_then(|index| children.get(index + 1))
+                .cloned(),
+            NodeData::Leaf(..) => None,
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use core::fmt::{self, Formatter, Debug, Display};
+use alloc::{vec, vec::Vec};
+use crate::{storage::Storage, util::ArrayMap};
+use super::{Octree, Node, NodeData};
+
+/// An octree stored as a flat breadth-first array of payloads, without any links between the nodes, created by [`Octree::to_linear`].
+///
+/// Since every branch of an octree has exactly eight children, the nodes can be found from their positions alone, which makes this layout well-suited for uploading octrees to GPU buffers and reading them in compute shaders.
+///
+/// # Layout
+/// The octree is stored in two arrays:
+/// - the *payload array*, returned by [`payloads`], which has one element per node, listed in breadth-first order: the root node is at index 0, followed by its children, followed by their children, and so on;
+/// - the *branch bitset*, returned by [`branch_bits`], which is an array of `u32` words with one bit per node: the bit of the node at index `i` is bit `i % 32` (counting from the least significant bit) of the word at index `i / 32`, and is set if the node is a branch node. Bits past the last node are always zero, and there are exactly as many words as needed to hold all of the bits.
+///
+/// The branch nodes are numbered by the order in which they appear in the payload array, starting from 0. The *rank* of a branch node is its number, which is the number of bits set before its own bit in the bitset. The children of the branch node with rank `r` are at the indices from `1 + 8 * r` to `8 + 8 * r`, in the same order as the children of the node in the octree. Conversely, the parent of the node at index `i`, if it's not the root node, is the branch node with rank `(i - 1) / 8`. As a result, the total number of nodes is always `1 + 8 * b`, where `b` is the number of branch nodes.
+///
+/// Consumers which need to look up children often can compute the ranks of all branch nodes in one pass, by calculating a running sum of the number of bits set in every word of the bitset.
+///
+/// # Example
+/// ```rust
+/// use charcoal::octree::{Octree, LinearOctree};
+///
+/// let mut tree = Octree::<_>::new(0);
+/// let mut root = tree.root_mut();
+/// root.make_branch([1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+/// root.nth_child_mut(2).unwrap().make_branch([9, 10, 11, 12, 13, 14, 15, 16]).unwrap();
+///
+/// let linear = tree.to_linear();
+/// assert_eq!(linear.num_nodes(), 17);
+/// assert_eq!(linear.payloads()[..4], [0, 1, 2, 3]);
+/// // The root and its third child are branch nodes.
+/// assert_eq!(linear.branch_bits(), [0b1001]);
+/// // The third child of the root is the second branch node, so its children come second.
+/// assert_eq!(linear.first_child_index(3), Some(9));
+/// assert_eq!(linear.parent_index(12), Some(3));
+///
+/// let tree = Octree::<_>::from_linear(linear);
+/// assert_eq!(*tree.node_at_locational_code(0b1_010_111).unwrap().value().into_inner(), 16);
+/// ```
+///
+/// [`Octree::to_linear`]: struct.Octree.html#method.to_linear " "
+/// [`payloads`]: #method.payloads " "
+/// [`branch_bits`]: #method.branch_bits " "
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LinearOctree<T> {
+    payloads: Vec<T>,
+    branch_bits: Vec<u32>,
+}
+impl<T> LinearOctree<T> {
+    /// Creates a linear octree from its payload array and branch bitset, checking that they describe a valid octree as explained in the [layout description].
+    ///
+    /// # Errors
+    /// Will fail if the arrays do not follow the layout. See [`LinearOctreeError`] for the details.
+    ///
+    /// [layout description]: #layout " "
+    /// [`LinearOctreeError`]: enum.LinearOctreeError.html " "
+    pub fn from_parts(payloads: Vec<T>, branch_bits: Vec<u32>) -> Result<Self, LinearOctreeError> {
+        let num_nodes = payloads.len();
+        if branch_bits.len() != (num_nodes + 31) / 32 {
+            return Err(LinearOctreeError::WrongBitsetLength);
+        }
+        if let Some(&last) = branch_bits.last() {
+            let used_bits = num_nodes - (branch_bits.len() - 1) * 32;
+            if used_bits < 32 && last >> used_bits != 0 {
+                return Err(LinearOctreeError::WrongBitsetLength);
+            }
+        }
+        let mut rank = 0;
+        for index in 0..num_nodes {
+            if branch_bits[index / 32] & (1 << (index % 32)) == 0 {
+                continue;
+            }
+            if 8 * rank < index {
+                return Err(LinearOctreeError::ChildrenBeforeParent(index));
+            }
+            rank += 1;
+        }
+        if num_nodes != 1 + 8 * rank {
+            return Err(LinearOctreeError::WrongNumberOfNodes);
+        }
+        Ok(Self {
+            payloads,
+            branch_bits,
+        })
+    }
+    /// Returns the payload array and the branch bitset, in that order.
+    #[must_use]
+    pub fn into_parts(self) -> (Vec<T>, Vec<u32>) {
+        (self.payloads, self.branch_bits)
+    }
+    /// Returns the number of nodes in the octree.
+    #[must_use]
+    pub fn num_nodes(&self) -> usize {
+        self.payloads.len()
+    }
+    /// Returns the payloads of the nodes, in breadth-first order.
+    #[must_use]
+    pub fn payloads(&self) -> &[T] {
+        &self.payloads
+    }
+    /// Returns the payloads of the nodes *mutably*, in breadth-first order.
+    pub fn payloads_mut(&mut self) -> &mut [T] {
+        &mut self.payloads
+    }
+    /// Returns the branch bitset.
+    #[must_use]
+    pub fn branch_bits(&self) -> &[u32] {
+        &self.branch_bits
+    }
+    /// Returns `true` if the node at the specified index is a branch node, `false` if it's a leaf node or the index is out of bounds.
+    #[must_use]
+    pub fn is_branch(&self, index: usize) -> bool {
+        index < self.num_nodes() && self.branch_bits[index / 32] & (1 << (index % 32)) != 0
+    }
+    /// Returns the index of the first child of the node at the specified index, or `None` if it's a leaf node or the index is out of bounds. The other seven children follow the first one.
+    ///
+    /// This has to count the branch nodes before the specified one, which takes time proportional to the index.
+    #[must_use]
+    pub fn first_child_index(&self, index: usize) -> Option<usize> {
+        if !self.is_branch(index) {
+            return None;
+        }
+        let full_words = self.branch_bits[..index / 32]
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum::<usize>();
+        let partial_word = (self.branch_bits[index / 32] & ((1 << (index % 32)) - 1)).count_ones();
+        Some(1 + 8 * (full_words + partial_word as usize))
+    }
+    /// Returns the index of the parent of the node at the specified index, or `None` if it's the root node or the index is out of bounds.
+    ///
+    /// This has to find the branch node with the corresponding rank, which takes time proportional to the index.
+    #[must_use]
+    pub fn parent_index(&self, index: usize) -> Option<usize> {
+        if index == 0 || index >= self.num_nodes() {
+            return None;
+        }
+        let mut rank = (index - 1) / 8;
+        for (word_index, &word) in self.branch_bits.iter().enumerate() {
+            let num_branches = word.count_ones() as usize;
+            if rank >= num_branches {
+                rank -= num_branches;
+                continue;
+            }
+            // The branch node is in this word, so skip the bits of the ones before it.
+            let mut word = word;
+            for _ in 0..rank {
+                word &= word - 1;
+            }
+            return Some(word_index * 32 + word.trailing_zeros() as usize);
+        }
+        unreachable!("the number of nodes matches the number of branch nodes")
+    }
+}
+
+impl<T, K, S> Octree<T, T, K, S>
+where
+    S: Storage<Element = Node<T, T, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Converts the tree into a [linear octree], cloning the payloads of all nodes.
+    ///
+    /// [linear octree]: struct.LinearOctree.html " "
+    #[allow(clippy::missing_panics_doc)] // The bitset is extended before the bits are set
+    pub fn to_linear(&self) -> LinearOctree<T>
+    where
+        T: Clone,
+    {
+        let mut payloads = Vec::with_capacity(self.num_nodes());
+        let mut branch_bits = Vec::new();
+        // Serves as the queue for the breadth-first traversal, since nodes are never taken out of it.
+        let mut keys = vec![self.root.clone()];
+        let mut index = 0;
+        while let Some(key) = keys.get(index) {
+            let node = unsafe {
+                // SAFETY: keys stored in the tree are guaranteed to be valid
+                self.storage.get_unchecked(key)
+            };
+            if index % 32 == 0 {
+                branch_bits.push(0);
+            }
+            match &node.value {
+                NodeData::Branch { payload, children } => {
+                    payloads.push(payload.clone());
+                    *branch_bits.last_mut().expect("a word was pushed above") |= 1 << (index % 32);
+                    keys.extend(children.iter().cloned());
+                }
+                NodeData::Leaf(payload) => payloads.push(payload.clone()),
+            }
+            index += 1;
+        }
+        LinearOctree {
+            payloads,
+            branch_bits,
+        }
+    }
+    /// Creates an octree from a [linear octree].
+    ///
+    /// [linear octree]: struct.LinearOctree.html " "
+    #[allow(clippy::missing_panics_doc)] // Linear octrees are checked when they are created
+    pub fn from_linear(linear: LinearOctree<T>) -> Self {
+        let num_nodes = linear.num_nodes();
+        let first_children = {
+            let mut rank = 0;
+            (0..num_nodes)
+                .map(|index| {
+                    if linear.is_branch(index) {
+                        rank += 1;
+                        Some(1 + 8 * (rank - 1))
+                    } else {
+                        None
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut storage = S::new();
+        storage.reserve(num_nodes);
+        let mut keys = Vec::with_capacity(num_nodes);
+        keys.resize_with(num_nodes, || None);
+        // Children always come after their parents, so adding the nodes from the end means that
+        // the children of every branch node are already in the storage by the time it's added.
+        for (index, payload) in linear.payloads.into_iter().enumerate().rev() {
+            let key = match first_children[index] {
+                Some(first_child) => {
+                    let children = [0, 1, 2, 3, 4, 5, 6, 7].array_map(|n| {
+                        keys[first_child + n]
+                            .clone()
+                            .expect("children come after their parents")
+                    });
+                    let key = storage.add(Node {
+                        value: NodeData::Branch {
+                            payload,
+                            children: children.clone(),
+                        },
+                        parent: None,
+                    });
+                    for child_key in &children {
+                        unsafe {
+                            // SAFETY: we just added all of those
+                            storage.get_unchecked_mut(child_key)
+                        }
+                        .parent = Some(key.clone());
+                    }
+                    key
+                }
+                None => storage.add(unsafe {
+                    // SAFETY: the parent link is filled in when the parent is added
+                    Node::leaf(payload, None)
+                }),
+            };
+            keys[index] = Some(key);
+        }
+        let root = keys
+            .swap_remove(0)
+            .expect("linear octrees always have a root node");
+        Self { storage, root }
+    }
+}
+
+/// The error type returned by [`LinearOctree::from_parts`].
+///
+/// [`LinearOctree::from_parts`]: struct.LinearOctree.html#method.from_parts " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum LinearOctreeError {
+    /// The branch bitset does not have exactly as many words as needed for the nodes, or has bits set past the last node.
+    WrongBitsetLength,
+    /// The number of nodes is not one more than eight times the number of branch nodes.
+    WrongNumberOfNodes,
+    /// The children of the branch node at the specified index would be placed at or before the node itself.
+    ChildrenBeforeParent(usize),
+}
+impl Display for LinearOctreeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongBitsetLength => f.write_str("the branch bitset does not match the nodes"),
+            Self::WrongNumberOfNodes => {
+                f.write_str("the number of nodes does not match the number of branch nodes")
+            }
+            Self::ChildrenBeforeParent(index) => write!(
+                f,
+                "the children of the branch node at index {} come before it",
+                index,
+            ),
+        }
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl std::error::Error for LinearOctreeError {}
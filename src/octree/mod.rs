@@ -49,7 +49,7 @@
 //! [Wikipedia article]: https://en.wikipedia.org/wiki/Octree " "
 
 use core::{
-    fmt::Debug,
+    fmt::{self, Formatter, Debug, Display},
     iter::{DoubleEndedIterator, ExactSizeIterator, FusedIterator},
     borrow::{Borrow, BorrowMut},
 };
@@ -57,15 +57,244 @@ use arrayvec::{ArrayVec, IntoIter as ArrayVecIntoIter};
 
 mod base;
 mod impl_traversable;
+mod impl_tree;
+mod into_iter;
+#[cfg(feature = "alloc")]
+mod linear;
+mod migrate;
 mod node;
 mod node_ref;
 mod node_ref_mut;
+mod raster;
+#[cfg(feature = "alloc")]
+mod repr;
 
 use node::NodeData;
 pub use node::Node;
 pub use node_ref::NodeRef;
 pub use node_ref_mut::NodeRefMut;
 pub use base::Octree;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub use raster::rasterize;
+pub use into_iter::{IntoIter, Drain};
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub use linear::{LinearOctree, LinearOctreeError};
+
+/// A direction in which to look for a neighbor of an octree cell, used by [`NodeRef::neighbor`].
+///
+/// The children of octree nodes are assumed to be laid out in Z-order, also known as Morton order: bit 0 of the child index selects the half along the X axis, bit 1 selects the half along the Y axis and bit 2 selects the half along the Z axis, with a cleared bit meaning the half with the lower coordinates. For example, the child at index 0 has the lowest X, Y and Z coordinates among its siblings, while the child at index 7 has the highest ones.
+///
+/// [`NodeRef::neighbor`]: struct.NodeRef.html#method.neighbor " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Towards lower X coordinates.
+    NegativeX,
+    /// Towards higher X coordinates.
+    PositiveX,
+    /// Towards lower Y coordinates.
+    NegativeY,
+    /// Towards higher Y coordinates.
+    PositiveY,
+    /// Towards lower Z coordinates.
+    NegativeZ,
+    /// Towards higher Z coordinates.
+    PositiveZ,
+}
+impl Direction {
+    /// Returns the direction opposite to this one.
+    #[must_use]
+    pub const fn opposite(self) -> Self {
+        match self {
+            Self::NegativeX => Self::PositiveX,
+            Self::PositiveX => Self::NegativeX,
+            Self::NegativeY => Self::PositiveY,
+            Self::PositiveY => Self::NegativeY,
+            Self::NegativeZ => Self::PositiveZ,
+            Self::PositiveZ => Self::NegativeZ,
+        }
+    }
+    /// Returns `true` if the direction points towards higher coordinates, `false` otherwise.
+    #[must_use]
+    pub const fn is_positive(self) -> bool {
+        matches!(self, Self::PositiveX | Self::PositiveY | Self::PositiveZ)
+    }
+    /// Returns the bit of the child index which corresponds to the axis of the direction.
+    pub(crate) const fn axis_bit(self) -> u8 {
+        match self {
+            Self::NegativeX | Self::PositiveX => 1,
+            Self::NegativeY | Self::PositiveY => 2,
+            Self::NegativeZ | Self::PositiveZ => 4,
+        }
+    }
+}
+
+/// An octant of an octree cell, identifying one of the eight children of a branch node.
+///
+/// The octants follow the layout described in [`Direction`], with the X axis pointing east, the Y axis pointing north and the Z axis pointing up. Converting between octants and raw child indices is done with [`into_index`] and [`from_index`], or with the `From` implementations, which are handy for passing octants to methods which take raw indices, such as [`NodeRef::nth_child`].
+///
+/// # Example
+/// ```rust
+/// use charcoal::octree::{Octree, Octant};
+///
+/// let mut tree = Octree::<_>::new(0);
+/// tree.root_mut().make_branch([1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+/// let top = tree.root().child(Octant::TopNorthEast).unwrap();
+/// assert_eq!(*top.value().into_inner(), 8);
+/// assert_eq!(top.octant(), Some(Octant::TopNorthEast));
+/// ```
+///
+/// [`Direction`]: enum.Direction.html " "
+/// [`into_index`]: #method.into_index " "
+/// [`from_index`]: #method.from_index " "
+/// [`NodeRef::nth_child`]: struct.NodeRef.html#method.nth_child " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Octant {
+    /// The octant with the lower X, Y and Z coordinates, at index 0.
+    BottomSouthWest,
+    /// The octant with the higher X and lower Y and Z coordinates, at index 1.
+    BottomSouthEast,
+    /// The octant with the lower X and Z and higher Y coordinates, at index 2.
+    BottomNorthWest,
+    /// The octant with the higher X and Y and lower Z coordinates, at index 3.
+    BottomNorthEast,
+    /// The octant with the lower X and Y and higher Z coordinates, at index 4.
+    TopSouthWest,
+    /// The octant with the higher X and Z and lower Y coordinates, at index 5.
+    TopSouthEast,
+    /// The octant with the lower X and higher Y and Z coordinates, at index 6.
+    TopNorthWest,
+    /// The octant with the higher X, Y and Z coordinates, at index 7.
+    TopNorthEast,
+}
+impl Octant {
+    /// All octants, in the order of their indices.
+    pub const ALL: [Self; 8] = [
+        Self::BottomSouthWest,
+        Self::BottomSouthEast,
+        Self::BottomNorthWest,
+        Self::BottomNorthEast,
+        Self::TopSouthWest,
+        Self::TopSouthEast,
+        Self::TopNorthWest,
+        Self::TopNorthEast,
+    ];
+    /// Returns the index of the child which corresponds to the octant.
+    #[must_use]
+    pub const fn into_index(self) -> u8 {
+        self as u8
+    }
+    /// Returns the octant which corresponds to the child with the specified index, or `None` if the index is larger than 7.
+    #[must_use]
+    pub const fn from_index(index: u8) -> Option<Self> {
+        match index {
+            0 => Some(Self::BottomSouthWest),
+            1 => Some(Self::BottomSouthEast),
+            2 => Some(Self::BottomNorthWest),
+            3 => Some(Self::BottomNorthEast),
+            4 => Some(Self::TopSouthWest),
+            5 => Some(Self::TopSouthEast),
+            6 => Some(Self::TopNorthWest),
+            7 => Some(Self::TopNorthEast),
+            _ => None,
+        }
+    }
+    /// Returns the octant adjacent to this one in the specified direction within the same cell, or `None` if the direction points out of the cell.
+    #[must_use]
+    pub const fn towards(self, direction: Direction) -> Option<Self> {
+        let index = self.into_index();
+        let is_set = index & direction.axis_bit() != 0;
+        if is_set == direction.is_positive() {
+            None
+        } else {
+            Self::from_index(index ^ direction.axis_bit())
+        }
+    }
+}
+impl From<Octant> for u8 {
+    fn from(op: Octant) -> Self {
+        op.into_index()
+    }
+}
+impl From<u8> for Octant {
+    /// Returns the octant which corresponds to the child with the specified index.
+    ///
+    /// # Panics
+    /// Will panic if the index is larger than 7.
+    fn from(op: u8) -> Self {
+        Self::from_index(op)
+            .unwrap_or_else(|| panic!("octant index must be less than 8, but was {}", op))
+    }
+}
+
+/// An axis-aligned bounding box, used to compute the regions covered by octree nodes with [`NodeRef::bounds`].
+///
+/// The axes are oriented in the same way as described in [`Direction`] and [`Octant`].
+///
+/// [`NodeRef::bounds`]: struct.NodeRef.html#method.bounds " "
+/// [`Direction`]: enum.Direction.html " "
+/// [`Octant`]: enum.Octant.html " "
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Aabb {
+    /// The corner with the lowest X, Y and Z coordinates.
+    pub min: [f64; 3],
+    /// The corner with the highest X, Y and Z coordinates.
+    pub max: [f64; 3],
+}
+impl Aabb {
+    /// Creates a bounding box from its corners with the lowest and the highest coordinates.
+    #[must_use]
+    pub const fn new(min: [f64; 3], max: [f64; 3]) -> Self {
+        Self { min, max }
+    }
+    /// Returns the point at the center of the bounding box, which is where it is split into octants.
+    #[must_use]
+    pub fn center(&self) -> [f64; 3] {
+        [
+            (self.min[0] + self.max[0]) * 0.5,
+            (self.min[1] + self.max[1]) * 0.5,
+            (self.min[2] + self.max[2]) * 0.5,
+        ]
+    }
+    /// Returns the bounding box covered by the specified octant of this one.
+    #[must_use]
+    pub fn octant(&self, octant: Octant) -> Self {
+        let center = self.center();
+        let index = octant.into_index();
+        let mut result = *self;
+        for (axis, &center) in center.iter().enumerate() {
+            if index & 1 << axis == 0 {
+                result.max[axis] = center;
+            } else {
+                result.min[axis] = center;
+            }
+        }
+        result
+    }
+    /// Returns `true` if the point is inside the bounding box or on its boundary, `false` otherwise.
+    #[must_use]
+    pub fn contains(&self, point: [f64; 3]) -> bool {
+        point
+            .iter()
+            .enumerate()
+            .all(|(axis, &coord)| self.min[axis] <= coord && coord <= self.max[axis])
+    }
+}
+
+/// The error type returned by [`subdivide_with`], indicating that the node already was a branch node and thus could not be subdivided.
+///
+/// [`subdivide_with`]: struct.NodeRefMut.html#method.subdivide_with " "
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SubdivideError;
+impl Display for SubdivideError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad("the node already was a branch node")
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl std::error::Error for SubdivideError {}
 
 /// Packed leaf children nodes of an octree's branch node.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -109,6 +338,11 @@ impl<T> From<PackedChildren<T>> for PackedChildrenIter<T> {
         Self(ArrayVec::from(op.0).into_iter())
     }
 }
+impl<T> From<[T; 8]> for PackedChildrenIter<T> {
+    fn from(op: [T; 8]) -> Self {
+        Self(ArrayVec::from(op).into_iter())
+    }
+}
 impl<T> Iterator for PackedChildrenIter<T> {
     type Item = T;
     fn next(&mut self) -> Option<Self::Item> {
@@ -145,3 +379,41 @@ pub type SparseVecOctree<B, L = B> =
 #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
 #[allow(unused_qualifications)]
 pub type VecOctree<B, L = B> = Octree<B, L, usize, alloc::vec::Vec<Node<B, L, usize>>>;
+/// An octree which uses a *sparse* `Vec` with 32-bit keys as backing storage, halving the size of the links between nodes on 64-bit targets.
+///
+/// See the [`small_key`] module for more.
+///
+/// [`small_key`]: ../small_key/index.html " "
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+#[allow(unused_qualifications)]
+pub type SmallKeyOctree<B, L = B> = Octree<
+    B,
+    L,
+    u32,
+    crate::small_key::SmallKeyStorage<crate::storage::SparseVec<Node<B, L, u32>>>,
+>;
+/// An octree which uses an `ArrayVec` as backing storage, keeping all of its nodes inline without the need for a memory allocator.
+///
+/// The capacity of an `ArrayVec` is specified by its backing array type, which is the first type parameter here: `[Node<B, L, usize>; 16]` makes a tree which can hold up to 16 nodes. Adding nodes beyond that with the regular methods panics, while [`try_new`] and [`try_make_branch`]/[`try_make_branch_with`] return an error instead.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{octree::{InlineOctree, Node}, TryMakeBranchError};
+///
+/// let mut tree = InlineOctree::<[Node<u32, u32, usize>; 9], u32>::try_new(0).unwrap();
+/// tree.root_mut().try_make_branch([1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+/// // The storage is full now, so there is no room for any more children.
+/// let mut root = tree.root_mut();
+/// let mut first_child = root.nth_child_mut(0).unwrap();
+/// assert!(matches!(
+///     first_child.try_make_branch([9, 10, 11, 12, 13, 14, 15, 16]),
+///     Err(TryMakeBranchError::OutOfCapacity { .. }),
+/// ));
+/// assert!(first_child.is_leaf());
+/// ```
+///
+/// [`try_new`]: struct.Octree.html#method.try_new " "
+/// [`try_make_branch`]: struct.NodeRefMut.html#method.try_make_branch " "
+/// [`try_make_branch_with`]: struct.NodeRefMut.html#method.try_make_branch_with " "
+pub type InlineOctree<A, B, L = B> = Octree<B, L, usize, ArrayVec<A>>;
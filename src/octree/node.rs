@@ -1,6 +1,7 @@
-use core::{num::NonZeroIsize, fmt::Debug, hint, convert::TryFrom};
+use core::{num::NonZeroIsize, fmt::Debug, hint, mem, ptr, convert::TryFrom};
 use crate::{
     storage::{ListStorage, MoveFix},
+    small_key::ListKey,
     util::unreachable_debugchecked,
     NodeValue,
 };
@@ -52,7 +53,7 @@ where
         }
     }
 }
-impl<B, L> MoveFix for Node<B, L, usize> {
+impl<B, L, K: ListKey> MoveFix for Node<B, L, K> {
     unsafe fn fix_shift<S>(storage: &mut S, shifted_from: usize, shifted_by: NonZeroIsize)
     where
         S: ListStorage<Element = Self>,
@@ -64,7 +65,7 @@ impl<B, L> MoveFix for Node<B, L, usize> {
         };
         if fix_starting_from >= storage.len() {
             return;
-        };
+        }
         for i in fix_starting_from..storage.len() {
             let old_index = isize::try_from(i)
                 // SAFETY: not having more than isize::MAX elements is an
@@ -84,18 +85,17 @@ impl<B, L> MoveFix for Node<B, L, usize> {
     where
         S: ListStorage<Element = Self>,
     {
-        match /*unsafe*/ {
-            // SAFETY: index validity is guaranteed for `current_index`.
-            &mut storage.get_unchecked_mut(current_index).value
-        } {
+        let (previous_key, current_key) =
+            (K::from_index(previous_index), K::from_index(current_index));
+        match /*unsafe*/ &mut storage.get_unchecked_mut(current_index).value {
             NodeData::Branch { children, .. } => {
                 let children = *children;
                 let mut fix_child = |child| {
                     let child = /*unsafe*/ {
                         // SAFETY: index validity guaranteed for children
-                        storage.get_unchecked_mut(child)
+                        storage.get_unchecked_mut(K::into_index(child))
                     };
-                    child.parent = Some(current_index);
+                    child.parent = Some(current_key);
                 };
                 for &child in &children {
                     fix_child(child);
@@ -111,7 +111,7 @@ impl<B, L> MoveFix for Node<B, L, usize> {
         } else {
             return;
         };
-        let parent = storage.get_unchecked_mut(parent_index);
+        let parent = storage.get_unchecked_mut(parent_index.into_index());
         let children = match &mut parent.value {
             NodeData::Branch { children, .. } => children,
             NodeData::Leaf(..) =>
@@ -121,8 +121,8 @@ impl<B, L> MoveFix for Node<B, L, usize> {
             }
         };
         for child in children {
-            if *child == previous_index {
-                *child = current_index;
+            if *child == previous_key {
+                *child = current_key;
                 return;
             }
         }
@@ -169,4 +169,44 @@ where
             Self::Leaf(x) => NodeValue::Leaf(x),
         }
     }
+    /// Converts a branch node into a leaf node, using the closure to convert the payload.
+    ///
+    /// The specified leaf payload stands in for the payload of the node while the closure runs and is returned afterwards. If the closure panics, the node is left as a leaf node with the stand-in payload instead of being in an invalid state.
+    pub(super) fn make_leaf_with(&mut self, stand_in: L, f: impl FnOnce(B) -> L) -> L {
+        let payload = match mem::replace(self, Self::Leaf(stand_in)) {
+            Self::Branch { payload, .. } => payload,
+            Self::Leaf(..) => unsafe { unreachable_debugchecked("expected a branch node") },
+        };
+        match mem::replace(self, Self::Leaf(f(payload))) {
+            Self::Leaf(stand_in) => stand_in,
+            Self::Branch { .. } => unsafe {
+                unreachable_debugchecked("the node was replaced with a leaf above")
+            },
+        }
+    }
+}
+impl<D, K> NodeData<D, D, K>
+where
+    K: Clone + Debug + Eq,
+{
+    /// Converts a branch node into a leaf node, keeping its payload. The links to the children are discarded.
+    ///
+    /// Unlike [`make_leaf_with`], this doesn't need a stand-in payload, since there is no conversion closure which could panic while the payload is moved out of the node.
+    ///
+    /// [`make_leaf_with`]: #method.make_leaf_with " "
+    pub(super) fn make_leaf_keeping_payload(&mut self) {
+        let (payload, links) = match self {
+            Self::Branch { payload, children } => unsafe {
+                // SAFETY: the node is overwritten right after this, without anything in between
+                // which could panic and drop the moved out values twice
+                (ptr::read(payload), ptr::read(children))
+            },
+            Self::Leaf(..) => unsafe { unreachable_debugchecked("expected a branch node") },
+        };
+        unsafe {
+            // SAFETY: see above
+            ptr::write(self, Self::Leaf(payload));
+        }
+        drop(links);
+    }
 }
@@ -1,14 +1,16 @@
 use core::{fmt::Debug, ptr, convert, hint};
+use arrayvec::ArrayVec;
 use crate::{
     Storage,
     DefaultStorage,
     NodeValue,
+    tree,
     TryRemoveChildrenError,
     MakeBranchError,
-    traversal::algorithms,
-    util::{ArrayMap, abort_on_panic, unreachable_debugchecked},
+    TryMakeBranchError,
+    util::{ArrayMap, abort_on_panic, unreachable_debugchecked, has_room_for, add_many},
 };
-use super::{Octree, Node, NodeData, PackedChildren, NodeRef};
+use super::{Octree, Node, NodeData, PackedChildren, NodeRef, SubdivideError, Octant};
 
 /// A *mutable* reference to a node in an octree.
 ///
@@ -62,10 +64,10 @@ where
     }
     /// Returns a *mutable* reference to the parent node of the pointee, or `None` if it's the root node.
     pub fn parent_mut(&mut self) -> Option<NodeRefMut<'_, B, L, K, S>> {
-        let key = self.node().parent.as_ref().cloned();
+        let key = self.node().parent.clone();
         key.map(move |x| unsafe {
             // SAFETY: as above
-            Self::new_raw_unchecked(self.tree, x)
+            NodeRefMut::new_raw_unchecked(self.tree, x)
         })
     }
     /// Returns `true` if the node is the root node, `false` otherwise.
@@ -111,6 +113,13 @@ where
     pub fn value_mut(&mut self) -> NodeValue<&'_ mut B, &'_ mut L> {
         self.node_mut().value.as_mut().into_value()
     }
+    /// Returns the octant of the parent cell which the node occupies, or `None` if it's the root node. This is the same as [`child_index`], but returns an [`Octant`] instead of a raw index.
+    ///
+    /// [`child_index`]: #method.child_index " "
+    /// [`Octant`]: enum.Octant.html " "
+    pub fn octant(&self) -> Option<Octant> {
+        self.child_index().map(Octant::from)
+    }
     /// Returns references to the children, or `None` if the node is a leaf node.
     #[allow(clippy::missing_panics_doc)]
     pub fn children(&self) -> Option<[NodeRef<'_, B, L, K, S>; 8]> {
@@ -195,9 +204,17 @@ octrees have either 0 or 8 children, at indicies from 0 to 7, but child at index
 debug key check failed: tried to reference key {:?} which is not present in the storage",
                 child,
             );
-            Self::new_raw_unchecked(self.tree, child.clone())
+            NodeRefMut::new_raw_unchecked(self.tree, child.clone())
         })
     }
+    /// Returns a reference to the child which occupies the specified octant, or `None` if the node has no children.
+    pub fn child(&self, octant: Octant) -> Option<NodeRef<'_, B, L, K, S>> {
+        self.nth_child(octant.into_index())
+    }
+    /// Returns a *mutable* reference to the child which occupies the specified octant, or `None` if the node has no children.
+    pub fn child_mut(&mut self, octant: Octant) -> Option<NodeRefMut<'_, B, L, K, S>> {
+        self.nth_child_mut(octant.into_index())
+    }
 
     /// Converts a leaf node into a branch node with the specified leaf children, using the provided closure to convert the payload.
     ///
@@ -208,45 +225,162 @@ debug key check failed: tried to reference key {:?} which is not present in the
         children: [L; 8],
         leaf_to_branch: impl FnOnce(L) -> B,
     ) -> Result<(), MakeBranchError<L, PackedChildren<L>>> {
-        let old_payload_ref = if let NodeData::Leaf(val) = &self.node().value {
-            val
-        } else {
+        if self.is_branch() {
             return Err(MakeBranchError {
                 packed_children: children.into(),
             });
+        }
+        self.subdivide_leaf(|payload| (leaf_to_branch(payload), children));
+        Ok(())
+    }
+    /// Converts a leaf node into a branch node with the specified leaf children, using the provided closure to convert the payload. Unlike [`make_branch_with`], returns an error instead of panicking if the storage cannot fit the children.
+    ///
+    /// # Errors
+    /// Will fail if the node is already a branch node or if the storage has a fixed capacity and does not have enough free space for the children. In both cases, the provided values for the children are returned back to the caller.
+    ///
+    /// [`make_branch_with`]: #method.make_branch_with " "
+    pub fn try_make_branch_with(
+        &mut self,
+        children: [L; 8],
+        leaf_to_branch: impl FnOnce(L) -> B,
+    ) -> Result<(), TryMakeBranchError<L, PackedChildren<L>>> {
+        if self.is_leaf() && !has_room_for(&self.tree.storage, 8) {
+            return Err(TryMakeBranchError::OutOfCapacity {
+                packed_children: children.into(),
+            });
+        }
+        self.make_branch_with(children, leaf_to_branch)
+            .map_err(Into::into)
+    }
+    /// Splits a leaf node into eight children, using the provided closure to derive both the new payload of the node and the payloads of the children from its old payload.
+    ///
+    /// This is the higher-level counterpart of [`make_branch_with`] for level-of-detail and adaptive refinement scenarios, in which the contents of the children are computed from the contents of the node being subdivided.
+    ///
+    /// # Errors
+    /// Will fail if the node is already a branch node, without calling the closure.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::octree::Octree;
+    ///
+    /// // A voxel with a density, split into eight smaller voxels with the same density.
+    /// let mut tree = Octree::<_>::new(0.5_f32);
+    /// tree.root_mut().subdivide_with(|density| (density, [density; 8])).unwrap();
+    /// assert_eq!(tree.num_nodes(), 9);
+    /// // Subdividing the same node again is an error.
+    /// assert!(tree.root_mut().subdivide_with(|density| (density, [density; 8])).is_err());
+    /// ```
+    ///
+    /// [`make_branch_with`]: #method.make_branch_with " "
+    pub fn subdivide_with(
+        &mut self,
+        f: impl FnOnce(L) -> (B, [L; 8]),
+    ) -> Result<(), SubdivideError> {
+        if self.is_branch() {
+            return Err(SubdivideError);
+        }
+        self.subdivide_leaf(f);
+        Ok(())
+    }
+
+    /// Attempts to remove a branch node's children without using recursion, replacing it with a leaf node, the value for which is provided by the specified closure.
+    ///
+    /// # Errors
+    /// Will fail in the following scenarios:
+    /// - The node was a leaf node, which cannot have children by definition.
+    /// - One or more of the node's children were a branch node, which thus would require recursion to remove.
+    pub fn try_remove_children_with(
+        &mut self,
+        branch_to_leaf: impl FnOnce(B) -> L,
+    ) -> Result<[L; 8], TryRemoveChildrenError> {
+        let [c0, c1, c2, c3, c4, c5, c6, c7] = self.remove_leaf_children()?;
+        // The payload of the first child stands in for the one of the node while the closure runs,
+        // keeping the tree valid if it panics
+        let c0 = self.node_mut().value.make_leaf_with(c0, branch_to_leaf);
+        Ok([c0, c1, c2, c3, c4, c5, c6, c7])
+    }
+    /// Merges the eight leaf children of a branch node back into it, turning it into a leaf node, the payload for which is computed by the provided closure from the old payload of the node and the payloads of the children.
+    ///
+    /// This is the inverse of [`subdivide_with`] and the higher-level counterpart of [`try_remove_children_with`], which aggregates the contents of the children instead of discarding them.
+    ///
+    /// # Errors
+    /// Will fail in the following scenarios, without calling the closure:
+    /// - The node was a leaf node, which cannot have children by definition.
+    /// - One or more of the node's children were a branch node, which would have to be collapsed first.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::octree::Octree;
+    ///
+    /// let mut tree = Octree::<_>::new(0);
+    /// tree.root_mut().make_branch([1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+    /// // Replace the children with their sum, discarding the old payload of the branch.
+    /// tree.root_mut()
+    ///     .collapse_with(|_, children| children.iter().sum())
+    ///     .unwrap();
+    /// assert!(tree.root().is_leaf());
+    /// assert_eq!(*tree.root().value().into_inner(), 36);
+    /// ```
+    ///
+    /// [`subdivide_with`]: #method.subdivide_with " "
+    /// [`try_remove_children_with`]: #method.try_remove_children_with " "
+    pub fn collapse_with(
+        &mut self,
+        f: impl FnOnce(B, [L; 8]) -> L,
+    ) -> Result<(), TryRemoveChildrenError> {
+        let children_payloads = self.remove_leaf_children()?;
+        let old_payload_ref = if let NodeData::Branch { payload, .. } = &self.node().value {
+            payload
+        } else {
+            unsafe {
+                // SAFETY: the children were removed successfully, so this is a branch node
+                hint::unreachable_unchecked()
+            }
+        };
+        let old_payload = unsafe {
+            // SAFETY: we're overwriting the value later, and not using an invalid pointer
+            ptr::read(old_payload_ref)
+        };
+        // The closure consumes the payloads of all children, so none of them can stand in for
+        // the payload of the node if it panics
+        let new_payload = abort_on_panic(|| f(old_payload, children_payloads));
+        unsafe {
+            // SAFETY: as above
+            ptr::write(&mut self.node_mut().value, NodeData::Leaf(new_payload));
+        }
+        Ok(())
+    }
+
+    /// Converts the node, which must be a leaf, into a branch node, using the closure to produce its new payload and the payloads of the children.
+    fn subdivide_leaf(&mut self, f: impl FnOnce(L) -> (B, [L; 8])) {
+        let old_payload_ref = if let NodeData::Leaf(val) = &self.node().value {
+            val
+        } else {
+            unsafe { unreachable_debugchecked("only leaf nodes can be subdivided") }
         };
         let old_payload = unsafe {
             // SAFETY: both pointer validity and overwriting are upheld
             ptr::read(old_payload_ref)
         };
-        let payload = leaf_to_branch(old_payload);
+        let (payload, children) = abort_on_panic(|| f(old_payload));
         let self_key = self.raw_key().clone();
-        let children = children.array_map(|value| {
-            self.tree.storage.add(unsafe {
+        let children = add_many(
+            &mut self.tree.storage,
+            children.array_map(|value| unsafe {
                 // SAFETY: key validity of self is implied
                 Node::leaf(value, Some(self_key.clone()))
-            })
-        });
+            }),
+        );
         unsafe {
             // SAFETY: as above
             ptr::write(
                 &mut self.node_mut().value,
                 NodeData::Branch { children, payload },
-            )
+            );
         }
-        Ok(())
     }
-
-    /// Attempts to remove a branch node's children without using recursion, replacing it with a leaf node, the value for which is provided by the specified closure.
-    ///
-    /// # Errors
-    /// Will fail in the following scenarios:
-    /// - The node was a leaf node, which cannot have children by definition.
-    /// - One or more of the node's children were a branch node, which thus would require recursion to remove.
-    pub fn try_remove_children_with(
-        &mut self,
-        branch_to_leaf: impl FnOnce(B) -> L,
-    ) -> Result<[L; 8], TryRemoveChildrenError> {
+    /// Removes the children of the node, which must all be leaves, and returns their payloads. The node is left as a branch node with dangling links to its children, which the caller has to replace with a leaf node right away.
+    fn remove_leaf_children(&mut self) -> Result<[L; 8], TryRemoveChildrenError> {
         let children_keys = {
             let children_keys = if let NodeData::Branch { children, .. } = &self.node().value {
                 Some(children)
@@ -264,7 +398,7 @@ debug key check failed: tried to reference key {:?} which is not present in the
                         return Err(TryRemoveChildrenError::HadBranchChild(i))
                     }
                     NodeData::Leaf(..) => {}
-                };
+                }
             }
             children_keys.clone() // borrow checker got trolled
         };
@@ -278,31 +412,36 @@ debug key check failed: tried to reference key {:?} which is not present in the
                 },
             }
         });
-        let old_payload_ref = if let NodeData::Branch { payload, .. } = &self.node().value {
-            payload
-        } else {
-            unsafe {
-                // SAFETY: we checked for a leaf node in the beginning
-                hint::unreachable_unchecked()
-            }
-        };
-        let old_payload = unsafe {
-            // SAFETY: we're overwriting the value later, and not using an invalid pointer
-            ptr::read(old_payload_ref)
-        };
-        unsafe {
-            // SAFETY: as above
-            ptr::write(
-                &mut self.node_mut().value,
-                NodeData::Leaf(abort_on_panic(|| branch_to_leaf(old_payload))),
-            );
-        }
         Ok(children_payloads)
     }
 
     /// Recursively removes the specified node and all its descendants, using a closure to patch nodes which transition from eight to zero children.
-    pub fn recursively_remove_with(self, branch_to_leaf: impl FnMut(B) -> L) -> NodeValue<B, L> {
-        algorithms::recursively_remove_with(self.tree, self.key, branch_to_leaf)
+    ///
+    /// Since octrees can't have individual children removed, the siblings of the node are removed together with it, turning its parent into a leaf node. The node's own children are removed first, so the returned value is always a leaf one.
+    ///
+    /// The tree is walked using the parent links of the nodes instead of a stack, without allocating memory.
+    ///
+    /// # Panics
+    /// Will panic if the node is the root node, which can never be removed.
+    pub fn recursively_remove_with(
+        self,
+        mut branch_to_leaf: impl FnMut(B) -> L,
+    ) -> NodeValue<B, L> {
+        let parent_key = self
+            .node()
+            .parent
+            .clone()
+            .expect("attempted to remove the root node");
+        let index = unsafe {
+            // SAFETY: parent keys are guaranteed to be valid
+            child_index(self.tree.storage.get_unchecked(&parent_key), &self.key)
+        };
+        // The node's own descendants go first, then its siblings with theirs
+        if self.is_branch() {
+            remove_descendants_with(self.tree, &self.key, &mut branch_to_leaf);
+        }
+        let children = remove_descendants_with(self.tree, &parent_key, &mut branch_to_leaf);
+        NodeValue::Leaf(ArrayVec::from(children).swap_remove(index))
     }
 
     fn node(&self) -> &'_ Node<B, L, K> {
@@ -330,7 +469,7 @@ debug key check failed: tried to reference key {:?} which is not present in the
         }
     }
 }
-impl<'a, D, K, S> NodeRefMut<'a, D, D, K, S>
+impl<D, K, S> NodeRefMut<'_, D, D, K, S>
 where
     S: Storage<Element = Node<D, D, K>, Key = K>,
     K: Clone + Debug + Eq,
@@ -345,18 +484,36 @@ where
     ) -> Result<(), MakeBranchError<D, PackedChildren<D>>> {
         self.make_branch_with(children, convert::identity)
     }
+    /// Converts a leaf node into a branch node with the specified leaf children, keeping its payload. Because of that, *this method is only available when the payload for leaf nodes and branch nodes is the same.* Unlike [`make_branch`], returns an error instead of panicking if the storage cannot fit the children.
+    ///
+    /// # Errors
+    /// Will fail if the node is already a branch node or if the storage has a fixed capacity and does not have enough free space for the children. In both cases, the provided values for the children are returned back to the caller.
+    ///
+    /// [`make_branch`]: #method.make_branch " "
+    pub fn try_make_branch(
+        &mut self,
+        children: [D; 8],
+    ) -> Result<(), TryMakeBranchError<D, PackedChildren<D>>> {
+        self.try_make_branch_with(children, convert::identity)
+    }
     /// Attempts to remove a branch node's children without using recursion, replacing it with a leaf node, keeping its original payload. Because of that, *this method is only available when the payload for leaf nodes and branch nodes is the same.*
     ///
+    /// Unlike with [`try_remove_children_with`], the payload is not passed through a conversion closure, so no removed child has to stand in for it while it is moved.
+    ///
     /// # Errors
     /// Will fail in the following scenarios:
     /// - The node was a leaf node, which cannot have children by definition.
     /// - One or more of the node's children were a branch node, which thus would require recursion to remove.
+    ///
+    /// [`try_remove_children_with`]: #method.try_remove_children_with " "
     pub fn try_remove_children(&mut self) -> Result<[D; 8], TryRemoveChildrenError> {
-        self.try_remove_children_with(convert::identity)
+        let children = self.remove_leaf_children()?;
+        self.node_mut().value.make_leaf_keeping_payload();
+        Ok(children)
     }
     /// Recursively removes the specified node and all its descendants. Will keep the original payload of the parent node if removing this node results in a transformation of the parent into a leaf, which is why *this method is only available when the payload for leaf nodes and branch nodes is the same.*
     pub fn recursively_remove(self) -> NodeValue<D> {
-        algorithms::recursively_remove(self.tree, self.key)
+        self.recursively_remove_with(convert::identity)
     }
 }
 
@@ -395,9 +552,9 @@ where
     K: Clone + Debug + Eq,
 {
     fn from(op: &'a NodeRefMut<'a, B, L, K, S>) -> Self {
-        NodeRef {
-            tree: op.tree as &'a _,
-            key: op.key.clone(),
+        unsafe {
+            // SAFETY: NodeRefMut is guaranteed to point to a valid node
+            NodeRef::new_raw_unchecked(op.tree, op.key.clone())
         }
     }
 }
@@ -407,9 +564,9 @@ where
     K: Clone + Debug + Eq,
 {
     fn from(op: &'a mut NodeRefMut<'a, B, L, K, S>) -> Self {
-        NodeRef {
-            tree: op.tree as &'a _,
-            key: op.key.clone(),
+        unsafe {
+            // SAFETY: NodeRefMut is guaranteed to point to a valid node
+            NodeRef::new_raw_unchecked(op.tree, op.key.clone())
         }
     }
 }
@@ -419,9 +576,103 @@ where
     K: Clone + Debug + Eq,
 {
     fn from(op: NodeRefMut<'a, B, L, K, S>) -> Self {
-        NodeRef {
-            tree: op.tree as &'a _,
+        unsafe {
+            // SAFETY: NodeRefMut is guaranteed to point to a valid node
+            NodeRef::new_raw_unchecked(op.tree, op.key)
+        }
+    }
+}
+impl<'a, B, L, K, S> From<NodeRefMut<'a, B, L, K, S>> for tree::NodeRefMut<'a, Octree<B, L, K, S>>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn from(op: NodeRefMut<'a, B, L, K, S>) -> Self {
+        Self {
+            tree: op.tree,
             key: op.key,
         }
     }
 }
+
+/// Removes all descendants of a branch node in post-order, turning it into a leaf node, and returns the payloads of its children.
+///
+/// Instead of keeping a stack of the nodes to return to, the walk goes back up using the parent links and resumes at the child after the one it came from, since all children before it have been turned into leaves already. Parent nodes are added to the storage before their children, so removing descendants never invalidates the keys of the nodes on the way back up.
+fn remove_descendants_with<B, L, K, S>(
+    tree: &mut Octree<B, L, K, S>,
+    subtree_root: &K,
+    branch_to_leaf: &mut impl FnMut(B) -> L,
+) -> [L; 8]
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let mut key = subtree_root.clone();
+    let mut first_unchecked_child = 0;
+    loop {
+        let branch_child = match unsafe {
+            // SAFETY: the walk only visits keys taken from the tree itself
+            &tree.storage.get_unchecked(&key).value
+        } {
+            NodeData::Branch { children, .. } => children[first_unchecked_child..]
+                .iter()
+                .find(|child| {
+                    let child = unsafe {
+                        // SAFETY: child keys are guaranteed to be valid
+                        tree.storage.get_unchecked(child)
+                    };
+                    matches!(child.value, NodeData::Branch { .. })
+                })
+                .cloned(),
+            NodeData::Leaf(..) => unsafe {
+                unreachable_debugchecked("the walk only visits branch nodes")
+            },
+        };
+        if let Some(branch_child) = branch_child {
+            key = branch_child;
+            first_unchecked_child = 0;
+            continue;
+        }
+        let children = unsafe {
+            // SAFETY: as above
+            NodeRefMut::new_raw_unchecked(&mut *tree, key.clone())
+        }
+        .try_remove_children_with(&mut *branch_to_leaf)
+        .unwrap_or_else(|_| unsafe {
+            unreachable_debugchecked("all children were checked to be leaves")
+        });
+        if &key == subtree_root {
+            return children;
+        }
+        let parent_key = unsafe {
+            // SAFETY: as above
+            tree.storage.get_unchecked(&key)
+        }
+        .parent
+        .clone()
+        .unwrap_or_else(|| unsafe {
+            unreachable_debugchecked("only the root of the walk can be the root node")
+        });
+        first_unchecked_child = unsafe {
+            // SAFETY: as above
+            child_index(tree.storage.get_unchecked(&parent_key), &key)
+        } + 1;
+        key = parent_key;
+    }
+}
+/// Returns the index of the specified child in a branch node.
+///
+/// # Safety
+/// The node must be a branch node, and the key must be one of its children.
+unsafe fn child_index<B, L, K>(node: &Node<B, L, K>, child: &K) -> usize
+where
+    K: Clone + Debug + Eq,
+{
+    match &node.value {
+        NodeData::Branch { children, .. } => children
+            .iter()
+            .position(|x| x == child)
+            .unwrap_or_else(|| unreachable_debugchecked("the key is not a child of the node")),
+        NodeData::Leaf(..) => unreachable_debugchecked("parent nodes cannot be leaves"),
+    }
+}
@@ -0,0 +1,212 @@
+use core::fmt::Debug;
+use crate::{storage::Storage, util::ArrayMap};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use super::{Octree, Node, NodeData};
+#[cfg(feature = "alloc")]
+use super::{Aabb, Octant};
+
+impl<B, L, K, S> Octree<B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Builds an octree from a dense 3D grid, such as a voxel volume, by recursively subdividing it into octants until every octant can be merged into a single leaf node.
+    ///
+    /// The grid is stored layer by layer and row by row, i.e. the cell at `(x, y, z)` is at index `(z * height + y) * width + x`. The layers and rows with lower indices are the ones with lower Z and Y coordinates, so that the children of every branch node follow the layout described in [`Direction`]. An octant is merged if `is_mergeable` returns `true` for its first cell (the one with the lowest coordinates) paired with every cell in it, in which case the leaf node gets a clone of the first cell as its payload. Branch nodes get the default value of their payload type.
+    ///
+    /// The root node covers a cube with a side equal to the largest dimension of the grid rounded up to the next power of two. If the grid is not such a cube, the cells on its edges are extended to cover the rest of the cube.
+    ///
+    /// # Panics
+    /// Will panic if the grid is empty or its length does not match the specified dimensions.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{octree::Octree, NodeValue};
+    ///
+    /// let mut voxels = [false; 8];
+    /// // Fill the voxel with the highest coordinates.
+    /// voxels[7] = true;
+    /// let tree = Octree::<(), bool>::from_grid(&voxels, 2, 2, 2, |a, b| a == b);
+    /// assert_eq!(tree.num_nodes(), 9);
+    /// assert_eq!(tree.root().nth_child(7).unwrap().value(), NodeValue::Leaf(&true));
+    ///
+    /// let empty = Octree::<(), bool>::from_grid(&[false; 64], 4, 4, 4, |a, b| a == b);
+    /// assert_eq!(empty.num_nodes(), 1);
+    /// ```
+    ///
+    /// [`Direction`]: enum.Direction.html " "
+    pub fn from_grid(
+        grid: &[L],
+        width: usize,
+        height: usize,
+        depth: usize,
+        mut is_mergeable: impl FnMut(&L, &L) -> bool,
+    ) -> Self
+    where
+        L: Clone,
+        B: Default,
+    {
+        assert!(width != 0 && height != 0 && depth != 0, "the grid is empty");
+        assert_eq!(
+            grid.len(),
+            width * height * depth,
+            "the length of the grid does not match its dimensions",
+        );
+        let grid = Grid {
+            cells: grid,
+            dimensions: [width, height, depth],
+        };
+        let size = width.max(height).max(depth).next_power_of_two();
+        let mut storage = S::new();
+        let root = add_region(&mut storage, &grid, [0, 0, 0], size, &mut is_mergeable);
+        Self { storage, root }
+    }
+}
+
+struct Grid<'a, T> {
+    cells: &'a [T],
+    dimensions: [usize; 3],
+}
+impl<T> Grid<'_, T> {
+    /// Returns the cell at the specified coordinates, or the closest one on the edge of the grid if they are outside of it.
+    fn cell(&self, [x, y, z]: [usize; 3]) -> &T {
+        let [width, height, depth] = self.dimensions;
+        let x = x.min(width - 1);
+        let y = y.min(height - 1);
+        let z = z.min(depth - 1);
+        &self.cells[(z * height + y) * width + x]
+    }
+    /// Returns the range of the coordinates along the specified axis which have to be checked for a cube with the specified origin and size. The parts of the cube outside of the grid only repeat the cells on its edge, so they are skipped.
+    fn range(&self, axis: usize, origin: [usize; 3], size: usize) -> core::ops::Range<usize> {
+        let len = self.dimensions[axis];
+        let start = origin[axis].min(len - 1);
+        let end = (origin[axis] + size).min(len).max(start + 1);
+        start..end
+    }
+}
+
+/// Adds a node covering the cube with the specified origin and size, and all of its descendants, to the storage, returning the key of the node. The parent link of the node itself is left for the caller to fill in.
+fn add_region<B, L, K, S>(
+    storage: &mut S,
+    grid: &Grid<'_, L>,
+    origin: [usize; 3],
+    size: usize,
+    is_mergeable: &mut impl FnMut(&L, &L) -> bool,
+) -> K
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+    L: Clone,
+    B: Default,
+{
+    let first = grid.cell(origin);
+    let uniform = size == 1
+        || grid.range(2, origin, size).all(|z| {
+            grid.range(1, origin, size).all(|y| {
+                grid.range(0, origin, size)
+                    .all(|x| is_mergeable(first, grid.cell([x, y, z])))
+            })
+        });
+    if uniform {
+        return storage.add(unsafe {
+            // SAFETY: the parent link is filled in by the caller
+            Node::leaf(first.clone(), None)
+        });
+    }
+    let half = size / 2;
+    let children = [0, 1, 2, 3, 4, 5, 6, 7].array_map(|index: usize| {
+        let child_origin = [
+            origin[0] + (index & 1) * half,
+            origin[1] + (index >> 1 & 1) * half,
+            origin[2] + (index >> 2 & 1) * half,
+        ];
+        add_region(storage, grid, child_origin, half, is_mergeable)
+    });
+    let key = storage.add(Node {
+        value: NodeData::Branch {
+            payload: B::default(),
+            children: children.clone(),
+        },
+        parent: None,
+    });
+    for child_key in &children {
+        unsafe {
+            // SAFETY: we just added all of those
+            storage.get_unchecked_mut(child_key)
+        }
+        .parent = Some(key.clone());
+    }
+    key
+}
+
+/// Samples the payloads of the leaf nodes of an octree into a dense 3D grid, which is the inverse of [`Octree::from_grid`].
+///
+/// The grid has the specified width, height and depth and is stored layer by layer and row by row, like the one taken by `from_grid`. The cell at `(x, y, z)` gets a clone of the payload of the leaf node containing the point `(x + 0.5, y + 0.5, z + 0.5)`, where the root node covers `root_bounds`, so that the cells are one unit wide. Cells outside of the bounds of the root node are sampled from the closest leaf node on its edge.
+///
+/// # Example
+/// ```rust
+/// use charcoal::octree::{self, Octree, Aabb};
+///
+/// let mut voxels = [0; 27];
+/// voxels[13] = 1; // The voxel in the middle.
+/// let tree = Octree::<(), u8>::from_grid(&voxels, 3, 3, 3, |a, b| a == b);
+/// let root_bounds = Aabb::new([0.0; 3], [4.0; 3]);
+/// assert_eq!(octree::rasterize(&tree, root_bounds, 3, 3, 3)[..], voxels[..]);
+/// ```
+///
+/// [`Octree::from_grid`]: struct.Octree.html#method.from_grid " "
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+#[allow(clippy::cast_precision_loss)] // Grids with more than 2^52 cells on a side are not a concern
+pub fn rasterize<B, L, K, S>(
+    tree: &Octree<B, L, K, S>,
+    root_bounds: Aabb,
+    width: usize,
+    height: usize,
+    depth: usize,
+) -> Vec<L>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+    L: Clone,
+{
+    let mut grid = Vec::with_capacity(width * height * depth);
+    for z in 0..depth {
+        for y in 0..height {
+            for x in 0..width {
+                let point = [x as f64 + 0.5, y as f64 + 0.5, z as f64 + 0.5];
+                grid.push(sample(tree, root_bounds, point).clone());
+            }
+        }
+    }
+    grid
+}
+/// Returns the payload of the leaf node containing the specified point.
+#[cfg(feature = "alloc")]
+fn sample<B, L, K, S>(tree: &Octree<B, L, K, S>, root_bounds: Aabb, point: [f64; 3]) -> &L
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let mut node = tree.root();
+    let mut bounds = root_bounds;
+    loop {
+        let center = bounds.center();
+        let index = point
+            .iter()
+            .zip(center.iter())
+            .enumerate()
+            .filter(|(_, (coord, center))| coord >= center)
+            .fold(0, |index, (axis, _)| index | 1 << axis);
+        node = match node.nth_child(index) {
+            Some(child) => child,
+            None => break,
+        };
+        bounds = bounds.octant(Octant::from(index));
+    }
+    match node.value() {
+        crate::NodeValue::Leaf(payload) => payload,
+        crate::NodeValue::Branch(..) => unreachable!("only leaf nodes have no children"),
+    }
+}
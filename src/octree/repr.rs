@@ -0,0 +1,144 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::{convert::TryFrom, fmt::Debug};
+use crate::{
+    storage::Storage,
+    repr::OctreeRepr,
+    util::{ArrayMap, unreachable_debugchecked},
+};
+use super::{Octree, Node, NodeData};
+
+impl<B, L, K, S> Octree<B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Creates an octree from its [nested enum representation].
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{octree::Octree, repr::OctreeRepr};
+    ///
+    /// let tree = Octree::<_>::from_repr(OctreeRepr::Branch(0, Box::new([
+    ///     OctreeRepr::Leaf(1),
+    ///     OctreeRepr::Leaf(2),
+    ///     OctreeRepr::Leaf(3),
+    ///     OctreeRepr::Leaf(4),
+    ///     OctreeRepr::Leaf(5),
+    ///     OctreeRepr::Leaf(6),
+    ///     OctreeRepr::Leaf(7),
+    ///     OctreeRepr::Leaf(8),
+    /// ])));
+    /// assert_eq!(tree.num_nodes(), 9);
+    /// assert_eq!(*tree.root().nth_child(2).unwrap().value().into_inner(), 3);
+    /// ```
+    ///
+    /// [nested enum representation]: ../repr/index.html " "
+    pub fn from_repr(repr: OctreeRepr<B, L>) -> Self {
+        let mut storage = S::new();
+        let root = add_repr(&mut storage, repr);
+        Self { storage, root }
+    }
+    /// Converts the tree into its [nested enum representation], cloning the payloads of all nodes.
+    ///
+    /// [nested enum representation]: ../repr/index.html " "
+    pub fn to_repr(&self) -> OctreeRepr<B, L>
+    where
+        B: Clone,
+        L: Clone,
+    {
+        node_to_repr(&self.storage, &self.root)
+    }
+}
+
+/// Adds the node and all of its descendants to the storage, returning the key of the node. The parent link of the node itself is left for the caller to fill in.
+fn add_repr<B, L, K, S>(storage: &mut S, repr: OctreeRepr<B, L>) -> K
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let (payload, children) = match repr {
+        OctreeRepr::Leaf(payload) => {
+            return storage.add(unsafe {
+                // SAFETY: the parent link is filled in by the caller
+                Node::leaf(payload, None)
+            });
+        }
+        OctreeRepr::Branch(payload, children) => (payload, children),
+    };
+    // The children are added first, since the branch node needs to know their keys
+    let children = (*children).array_map(|child| add_repr(storage, child));
+    let key = storage.add(Node {
+        value: NodeData::Branch {
+            payload,
+            children: children.clone(),
+        },
+        parent: None,
+    });
+    for child_key in &children {
+        unsafe {
+            // SAFETY: we just added all of those
+            storage.get_unchecked_mut(child_key)
+        }
+        .parent = Some(key.clone());
+    }
+    key
+}
+/// Converts the node and all of its descendants into the nested enum representation.
+///
+/// The children of every node are converted before the node itself, since the representation of a branch node owns the ones of its children. Instead of recursing, the branch nodes which are still missing some of their children are kept on an explicit stack.
+fn node_to_repr<B, L, K, S>(storage: &S, key: &K) -> OctreeRepr<B, L>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+    B: Clone,
+    L: Clone,
+{
+    // The key and payload of every unfinished branch node and the representations of the children
+    // which are converted already.
+    let mut stack = Vec::new();
+    let mut key = key.clone();
+    loop {
+        let node = unsafe {
+            // SAFETY: keys stored in the tree are guaranteed to be valid
+            storage.get_unchecked(&key)
+        };
+        let mut repr = match &node.value {
+            NodeData::Branch { payload, children } => {
+                stack.push((
+                    key.clone(),
+                    payload.clone(),
+                    Vec::with_capacity(children.len()),
+                ));
+                key = children[0].clone();
+                continue;
+            }
+            NodeData::Leaf(payload) => OctreeRepr::Leaf(payload.clone()),
+        };
+        // Hand the converted node to its parent, converting the parent as well if that was its
+        // last child, until a parent with children left to convert is found.
+        loop {
+            let (parent_key, _, converted) = match stack.last_mut() {
+                Some(parent) => parent,
+                None => return repr,
+            };
+            converted.push(repr);
+            if let NodeData::Branch { children, .. } = &unsafe {
+                // SAFETY: as above
+                storage.get_unchecked(parent_key)
+            }
+            .value
+            {
+                if let Some(next_child) = children.get(converted.len()) {
+                    key = next_child.clone();
+                    break;
+                }
+            }
+            let (_, payload, children) =
+                stack.pop().expect("the stack was checked to be non-empty");
+            let children = Box::try_from(children.into_boxed_slice()).unwrap_or_else(|_| unsafe {
+                unreachable_debugchecked("all children of the node have been converted")
+            });
+            repr = OctreeRepr::Branch(payload, children);
+        }
+    }
+}
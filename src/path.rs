@@ -0,0 +1,142 @@
+//! Paths from the root node to other nodes, which can be printed and parsed.
+//!
+//! Unlike keys, which depend on the storage and are meaningless outside of the tree they came from, a [`Path`] describes a node by the indices of the children which lead to it from the root. This makes paths suitable for referring to nodes from configuration files, command line arguments and logs.
+//!
+//! Paths are displayed as the child indices separated by slashes, such as `0/3/1`. The path to the root node is empty and thus displayed as an empty string.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{path::Path, Tree, FreeformTree};
+//!
+//! let mut tree = FreeformTree::<_>::new(0);
+//! let mut root = tree.root_mut();
+//! root.make_branch(vec![1, 2, 3]).unwrap();
+//! root.last_child_mut().unwrap().make_branch(vec![4, 5]).unwrap();
+//!
+//! let path: Path = "2/1".parse().unwrap();
+//! let node = tree.node_at_path(&path).unwrap();
+//! assert_eq!(*node.value().into_inner(), 5);
+//! assert_eq!(tree.path_to(node.raw_key()).unwrap().to_string(), "2/1");
+//! ```
+//!
+//! [`Path`]: struct.Path.html " "
+
+use core::{
+    ops::Deref,
+    str::FromStr,
+    fmt::{self, Formatter, Display},
+};
+use alloc::vec::Vec;
+use crate::Tree;
+
+/// A sequence of child indices leading from the root node of a tree to one of its nodes.
+///
+/// See the [module-level documentation] for more.
+///
+/// [module-level documentation]: index.html " "
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Path(Vec<usize>);
+impl Path {
+    /// Creates an empty path, which leads to the root node.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+    /// Returns the path to the node with the specified key, or `None` if the key is invalid.
+    ///
+    /// # Panics
+    /// Will panic if a node on the way to the root is not among the children of its parent, which can only happen if the tree is malformed.
+    pub fn to_node<T: Tree>(tree: &T, key: &T::Cursor) -> Option<Self> {
+        if !tree.contains_key(key) {
+            return None;
+        }
+        let mut indices = Vec::new();
+        let mut current = key.clone();
+        while let Some(parent) = tree.parent_key(&current) {
+            let index = tree
+                .children_keys(&parent)
+                .position(|child| child == current)
+                .expect("the node is not among the children of its parent");
+            indices.push(index);
+            current = parent;
+        }
+        indices.reverse();
+        Some(Self(indices))
+    }
+    /// Appends a child index to the end of the path, making it lead to a child of the node which it led to before.
+    pub fn push(&mut self, index: usize) {
+        self.0.push(index);
+    }
+    /// Removes the last child index from the path and returns it, making the path lead to the parent of the node which it led to before, or returns `None` if the path leads to the root node.
+    pub fn pop(&mut self) -> Option<usize> {
+        self.0.pop()
+    }
+    /// Returns the child indices making up the path.
+    #[must_use]
+    pub fn as_slice(&self) -> &[usize] {
+        &self.0
+    }
+    /// Unwraps the child indices making up the path.
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // Clippy has no idea what a destructor is
+    pub fn into_inner(self) -> Vec<usize> {
+        self.0
+    }
+}
+impl Deref for Path {
+    type Target = [usize];
+    fn deref(&self) -> &[usize] {
+        &self.0
+    }
+}
+impl From<Vec<usize>> for Path {
+    fn from(op: Vec<usize>) -> Self {
+        Self(op)
+    }
+}
+impl From<&[usize]> for Path {
+    fn from(op: &[usize]) -> Self {
+        Self(op.to_vec())
+    }
+}
+impl Display for Path {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, index) in self.0.iter().enumerate() {
+            if i != 0 {
+                f.write_str("/")?;
+            }
+            write!(f, "{}", index)?;
+        }
+        Ok(())
+    }
+}
+impl FromStr for Path {
+    type Err = ParsePathError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(Self::new());
+        }
+        s.split('/')
+            .enumerate()
+            .map(|(segment, index)| index.parse().map_err(|_| ParsePathError { segment }))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self)
+    }
+}
+
+/// The error type returned when a string cannot be parsed into a [`Path`].
+///
+/// [`Path`]: struct.Path.html " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ParsePathError {
+    /// The zero-based position of the segment which is not a valid child index.
+    pub segment: usize,
+}
+impl Display for ParsePathError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "segment {} of the path is not a valid child index", self.segment)
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl std::error::Error for ParsePathError {}
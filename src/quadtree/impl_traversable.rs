@@ -1,4 +1,4 @@
-use core::{fmt::Debug, hint};
+use core::fmt::Debug;
 use crate::{
     storage::Storage,
     traversal::{
@@ -12,6 +12,7 @@ use crate::{
     TryRemoveBranchError,
     TryRemoveLeafError,
     TryRemoveChildrenError,
+    MakeBranchError,
     util::{ArrayMap, unreachable_debugchecked},
 };
 use super::{Quadtree, Node, NodeRef, NodeRefMut, PackedChildren};
@@ -36,33 +37,43 @@ where
         };
         let node = NodeRef::new_raw(self, cursor)
             .expect("the node specified by the cursor does not exist");
+        // The keys of the node and its siblings along with the index of the node among them, or
+        // `None` if it's the root node. The parent is looked up only once for both.
+        let siblings = || {
+            let siblings = node.parent()?.child_keys().unwrap_or_else(|| unsafe {
+                unreachable_debugchecked("parent nodes cannot be leaves")
+            });
+            let index = siblings
+                .iter()
+                .position(|sibling| sibling == node.raw_key())
+                .unwrap_or_else(|| unsafe {
+                    unreachable_debugchecked("failed to find node in parent's child list")
+                });
+            Some((siblings, index))
+        };
         match direction {
-            VisitorDirection::Parent => node.parent().ok_or(error).map(NodeRef::into_raw_key),
-            VisitorDirection::NextSibling => {
-                node.child_index()
-                    .map(|child_index| {
-                        let parent = node.parent().unwrap_or_else(|| unsafe {
-                            unreachable_debugchecked("parent nodes cannot be leaves")
-                        });
-                        parent
-                            .nth_child(child_index)
-                            .unwrap_or_else(|| unsafe {
-                                // SAFETY: the previous unreachable_debugchecked checked for this
-                                hint::unreachable_unchecked()
-                            })
-                            .into_raw_key()
-                    })
-                    .ok_or(error)
-            }
+            VisitorDirection::Parent => node.parent_key().cloned().ok_or(error),
+            VisitorDirection::NthAncestor(n) => (0..n)
+                .try_fold(node, |ancestor, _| ancestor.parent())
+                .map(NodeRef::into_raw_key)
+                .ok_or(error),
+            VisitorDirection::Root => Ok(self.root.clone()),
+            VisitorDirection::NextSibling => siblings()
+                .and_then(|(siblings, index)| siblings.get(index + 1))
+                .cloned()
+                .ok_or(error),
+            VisitorDirection::PrevSibling => siblings()
+                .and_then(|(siblings, index)| Some(&siblings[index.checked_sub(1)?]))
+                .cloned()
+                .ok_or(error),
             VisitorDirection::Child(num) => {
-                // False positive, we have a check here
-                #[allow(clippy::clippy::cast_possible_truncation)]
-                let num = if num <= 3 {
-                    num as u8
+                if num < 4 {
+                    node.child_keys()
+                        .map(|children| children[num as usize].clone())
+                        .ok_or(error)
                 } else {
-                    return Err(error);
-                };
-                node.nth_child(num).map(NodeRef::into_raw_key).ok_or(error)
+                    Err(error)
+                }
             }
             VisitorDirection::SetTo(new_cursor) => {
                 if self.storage.contains_key(&new_cursor) {
@@ -88,7 +99,7 @@ where
     fn parent_of(&self, cursor: &Self::Cursor) -> Option<Self::Cursor> {
         let node_ref = NodeRef::new_raw(self, cursor.clone())
             .unwrap_or_else(|| panic!("invalid cursor: {:?}", cursor));
-        node_ref.parent().map(NodeRef::into_raw_key)
+        node_ref.parent_key().cloned()
     }
     #[track_caller]
     fn num_children_of(&self, cursor: &Self::Cursor) -> usize {
@@ -102,18 +113,19 @@ where
     }
     #[track_caller]
     fn nth_child_of(&self, cursor: &Self::Cursor, child_num: usize) -> Option<Self::Cursor> {
-        // False positive, we have a check here
-        #[allow(clippy::clippy::cast_possible_truncation)]
         if child_num < 4 {
             let node_ref = NodeRef::new_raw(self, cursor.clone())
                 .unwrap_or_else(|| panic!("invalid cursor: {:?}", cursor));
             node_ref
-                .nth_child(child_num as u8)
-                .map(NodeRef::into_raw_key)
+                .child_keys()
+                .map(|children| children[child_num].clone())
         } else {
             None
         }
     }
+    fn contains_cursor(&self, cursor: &Self::Cursor) -> bool {
+        self.storage.contains_key(cursor)
+    }
 }
 impl<B, L, K, S> TraversableMut for Quadtree<B, L, K, S>
 where
@@ -156,12 +168,12 @@ where
         &mut self,
         cursor: &Self::Cursor,
         branch_to_leaf: BtL,
-        mut collector: C,
+        collector: C,
     ) -> Result<(), TryRemoveChildrenError> {
         let mut node_ref = NodeRefMut::new_raw(self, cursor.clone())
             .unwrap_or_else(|| panic!("invalid cursor: {:?}", cursor));
         node_ref.try_remove_children_with(branch_to_leaf).map(|x| {
-            x.array_map(|e| collector(e));
+            x.array_map(collector);
         })
     }
     fn try_remove_branch<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
@@ -183,4 +195,16 @@ where
             .try_remove_children_with(branch_to_leaf)
             .map(Into::into)
     }
+    #[allow(clippy::type_complexity)]
+    #[track_caller]
+    fn try_add_children<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        children: Self::PackedChildren,
+        leaf_to_branch: LtB,
+    ) -> Result<(), MakeBranchError<Self::Leaf, Self::PackedChildren>> {
+        let mut node_ref = NodeRefMut::new_raw(self, cursor.clone())
+            .unwrap_or_else(|| panic!("invalid cursor: {:?}", cursor));
+        node_ref.make_branch_with(children.into_inner(), leaf_to_branch)
+    }
 }
@@ -0,0 +1,148 @@
+use core::fmt::Debug;
+use crate::{storage::Storage, util::ArrayMap};
+use super::{Quadtree, Node, NodeData};
+
+impl<B, L, K, S> Quadtree<B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Clones the tree into a different storage, producing a tree with the same structure and payloads.
+    ///
+    /// This makes it possible to build a tree using a growable storage and then freeze it into a fixed-capacity one which does not need an allocator, such as `ArrayVec`, or to move it into a storage with different key stability guarantees. The keys of the nodes in the new tree are unrelated to the keys of the nodes in the old one.
+    ///
+    /// The tree is copied without using recursion, and no memory is allocated other than by the new storage itself.
+    ///
+    /// # Panics
+    /// Will panic if the new storage runs out of capacity.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::quadtree::{Quadtree, Node};
+    /// use arrayvec::ArrayVec;
+    ///
+    /// let mut tree = Quadtree::<_>::new(0);
+    /// tree.root_mut().make_branch([1, 2, 3, 4]).unwrap();
+    /// // Freeze the tree into a fixed-capacity storage which does not need an allocator.
+    /// let frozen: Quadtree<_, _, _, ArrayVec<[Node<_, _, _>; 16]>> = tree.clone_into_storage();
+    /// assert_eq!(frozen.num_nodes(), 5);
+    /// assert_eq!(frozen.to_repr(), tree.to_repr());
+    /// ```
+    pub fn clone_into_storage<K2, S2>(&self) -> Quadtree<B, L, K2, S2>
+    where
+        B: Clone,
+        L: Clone,
+        S2: Storage<Element = Node<B, L, K2>, Key = K2>,
+        K2: Clone + Debug + Eq,
+    {
+        let source = |key: &K| unsafe {
+            // SAFETY: keys stored in the tree are guaranteed to be valid
+            self.storage.get_unchecked(key)
+        };
+        let mut storage = S2::new();
+        // The root node is added first so that parent nodes come before their children in the
+        // storage, like in every other tree, which storages that shift keys upon removal rely on.
+        // Until its children are copied, it's a leaf node with a copy of the payload of the
+        // leftmost leaf, and its key is put into the links of branch nodes as a placeholder.
+        let mut leftmost_leaf = &self.root;
+        while let NodeData::Branch { children, .. } = &source(leftmost_leaf).value {
+            leftmost_leaf = &children[0];
+        }
+        let root = storage.add(unsafe {
+            // SAFETY: the links are filled in as the tree is copied
+            Node::root(match &source(leftmost_leaf).value {
+                NodeData::Leaf(payload) => payload.clone(),
+                NodeData::Branch { .. } => unreachable!("the descent only stops at leaf nodes"),
+            })
+        });
+        let placeholder = root.clone();
+        let copy_value = |key: &K| match &source(key).value {
+            NodeData::Branch { payload, .. } => NodeData::Branch {
+                payload: payload.clone(),
+                children: [(); 4].array_map(|()| placeholder.clone()),
+            },
+            NodeData::Leaf(payload) => NodeData::Leaf(payload.clone()),
+        };
+        let copy_node = |storage: &mut S2, key: &K, parent: Option<K2>| {
+            storage.add(Node {
+                value: copy_value(key),
+                parent,
+            })
+        };
+        unsafe {
+            // SAFETY: we just added the root node
+            storage.get_unchecked_mut(&root)
+        }
+        .value = copy_value(&self.root);
+        // Walk both trees in lockstep, copying the children of every branch node as we visit it
+        let (mut old_key, mut new_key) = (self.root.clone(), root.clone());
+        loop {
+            if let NodeData::Branch { children, .. } = &source(&old_key).value {
+                let new_children = children.array_map_by_ref(|old_child| {
+                    copy_node(&mut storage, old_child, Some(new_key.clone()))
+                });
+                if let NodeData::Branch {
+                    children: new_children_ref,
+                    ..
+                } = &mut unsafe {
+                    // SAFETY: the key was either just added or comes from a link we filled in
+                    storage.get_unchecked_mut(&new_key)
+                }
+                .value
+                {
+                    new_children_ref.clone_from(&new_children);
+                }
+                old_key = children[0].clone();
+                new_key = new_children[0].clone();
+                continue;
+            }
+            // Climb up until we find a node with a next sibling
+            loop {
+                let old_parent = match &source(&old_key).parent {
+                    Some(old_parent) => old_parent.clone(),
+                    None => return Quadtree { storage, root },
+                };
+                let new_parent = unsafe {
+                    // SAFETY: as above
+                    storage.get_unchecked(&new_key)
+                }
+                .parent
+                .clone()
+                .expect("non-root nodes always have a parent");
+                let (old_siblings, new_siblings) = match (
+                    &source(&old_parent).value,
+                    &unsafe {
+                        // SAFETY: as above
+                        storage.get_unchecked(&new_parent)
+                    }
+                    .value,
+                ) {
+                    (
+                        NodeData::Branch {
+                            children: old_siblings,
+                            ..
+                        },
+                        NodeData::Branch {
+                            children: new_siblings,
+                            ..
+                        },
+                    ) => (old_siblings, new_siblings),
+                    _ => unreachable!("parent nodes cannot be leaves"),
+                };
+                let index = old_siblings
+                    .iter()
+                    .position(|sibling| sibling == &old_key)
+                    .expect("failed to find node in parent's child list");
+                if let (Some(old_sibling), Some(new_sibling)) =
+                    (old_siblings.get(index + 1), new_siblings.get(index + 1))
+                {
+                    old_key = old_sibling.clone();
+                    new_key = new_sibling.clone();
+                    break;
+                }
+                old_key = old_parent;
+                new_key = new_parent;
+            }
+        }
+    }
+}
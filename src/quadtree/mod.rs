@@ -54,15 +54,196 @@ use arrayvec::{ArrayVec, IntoIter as ArrayVecIntoIter};
 
 mod base;
 mod impl_traversable;
+mod impl_tree;
+mod into_iter;
+mod migrate;
 mod node;
 mod node_ref;
 mod node_ref_mut;
+mod raster;
+#[cfg(feature = "alloc")]
+mod repr;
 
 use node::NodeData;
 pub use node::Node;
 pub use node_ref::NodeRef;
 pub use node_ref_mut::NodeRefMut;
 pub use base::Quadtree;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub use raster::rasterize;
+pub use into_iter::{IntoIter, Drain};
+
+/// A direction in which to look for a neighbor of a quadtree cell, used by [`NodeRef::neighbor`].
+///
+/// The children of quadtree nodes are assumed to be laid out in Z-order, also known as Morton order: bit 0 of the child index selects the half along the X axis and bit 1 selects the half along the Y axis, with a cleared bit meaning the half with the lower coordinates. For example, the child at index 0 has the lowest X and Y coordinates among its siblings, while the child at index 3 has the highest ones.
+///
+/// [`NodeRef::neighbor`]: struct.NodeRef.html#method.neighbor " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Towards lower X coordinates.
+    NegativeX,
+    /// Towards higher X coordinates.
+    PositiveX,
+    /// Towards lower Y coordinates.
+    NegativeY,
+    /// Towards higher Y coordinates.
+    PositiveY,
+}
+impl Direction {
+    /// Returns the direction opposite to this one.
+    #[must_use]
+    pub const fn opposite(self) -> Self {
+        match self {
+            Self::NegativeX => Self::PositiveX,
+            Self::PositiveX => Self::NegativeX,
+            Self::NegativeY => Self::PositiveY,
+            Self::PositiveY => Self::NegativeY,
+        }
+    }
+    /// Returns `true` if the direction points towards higher coordinates, `false` otherwise.
+    #[must_use]
+    pub const fn is_positive(self) -> bool {
+        matches!(self, Self::PositiveX | Self::PositiveY)
+    }
+    /// Returns the bit of the child index which corresponds to the axis of the direction.
+    pub(crate) const fn axis_bit(self) -> u8 {
+        match self {
+            Self::NegativeX | Self::PositiveX => 1,
+            Self::NegativeY | Self::PositiveY => 2,
+        }
+    }
+}
+
+/// A quadrant of a quadtree cell, identifying one of the four children of a branch node.
+///
+/// The quadrants follow the layout described in [`Direction`], with the X axis pointing east and the Y axis pointing north. Converting between quadrants and raw child indices is done with [`into_index`] and [`from_index`], or with the `From` implementations, which are handy for passing quadrants to methods which take raw indices, such as [`NodeRef::nth_child`].
+///
+/// # Example
+/// ```rust
+/// use charcoal::quadtree::{Quadtree, Quadrant};
+///
+/// let mut tree = Quadtree::<_>::new(0);
+/// tree.root_mut().make_branch([1, 2, 3, 4]).unwrap();
+/// let north_east = tree.root().child(Quadrant::NorthEast).unwrap();
+/// assert_eq!(*north_east.value().into_inner(), 4);
+/// assert_eq!(north_east.quadrant(), Some(Quadrant::NorthEast));
+/// ```
+///
+/// [`Direction`]: enum.Direction.html " "
+/// [`into_index`]: #method.into_index " "
+/// [`from_index`]: #method.from_index " "
+/// [`NodeRef::nth_child`]: struct.NodeRef.html#method.nth_child " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Quadrant {
+    /// The quadrant with the lower X and Y coordinates, at index 0.
+    SouthWest,
+    /// The quadrant with the higher X and lower Y coordinates, at index 1.
+    SouthEast,
+    /// The quadrant with the lower X and higher Y coordinates, at index 2.
+    NorthWest,
+    /// The quadrant with the higher X and Y coordinates, at index 3.
+    NorthEast,
+}
+impl Quadrant {
+    /// All quadrants, in the order of their indices.
+    pub const ALL: [Self; 4] = [Self::SouthWest, Self::SouthEast, Self::NorthWest, Self::NorthEast];
+    /// Returns the index of the child which corresponds to the quadrant.
+    #[must_use]
+    pub const fn into_index(self) -> u8 {
+        self as u8
+    }
+    /// Returns the quadrant which corresponds to the child with the specified index, or `None` if the index is larger than 3.
+    #[must_use]
+    pub const fn from_index(index: u8) -> Option<Self> {
+        match index {
+            0 => Some(Self::SouthWest),
+            1 => Some(Self::SouthEast),
+            2 => Some(Self::NorthWest),
+            3 => Some(Self::NorthEast),
+            _ => None,
+        }
+    }
+    /// Returns the quadrant adjacent to this one in the specified direction within the same cell, or `None` if the direction points out of the cell.
+    #[must_use]
+    pub const fn towards(self, direction: Direction) -> Option<Self> {
+        let index = self.into_index();
+        let is_set = index & direction.axis_bit() != 0;
+        if is_set == direction.is_positive() {
+            None
+        } else {
+            Self::from_index(index ^ direction.axis_bit())
+        }
+    }
+}
+impl From<Quadrant> for u8 {
+    fn from(op: Quadrant) -> Self {
+        op.into_index()
+    }
+}
+impl From<u8> for Quadrant {
+    /// Returns the quadrant which corresponds to the child with the specified index.
+    ///
+    /// # Panics
+    /// Will panic if the index is larger than 3.
+    fn from(op: u8) -> Self {
+        Self::from_index(op)
+            .unwrap_or_else(|| panic!("quadrant index must be less than 4, but was {}", op))
+    }
+}
+
+/// An axis-aligned rectangle, used to compute the regions covered by quadtree nodes with [`NodeRef::bounds`].
+///
+/// The axes are oriented in the same way as described in [`Direction`] and [`Quadrant`].
+///
+/// [`NodeRef::bounds`]: struct.NodeRef.html#method.bounds " "
+/// [`Direction`]: enum.Direction.html " "
+/// [`Quadrant`]: enum.Quadrant.html " "
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Rect {
+    /// The corner with the lowest X and Y coordinates.
+    pub min: [f64; 2],
+    /// The corner with the highest X and Y coordinates.
+    pub max: [f64; 2],
+}
+impl Rect {
+    /// Creates a rectangle from its corners with the lowest and the highest coordinates.
+    #[must_use]
+    pub const fn new(min: [f64; 2], max: [f64; 2]) -> Self {
+        Self { min, max }
+    }
+    /// Returns the point at the center of the rectangle, which is where it is split into quadrants.
+    #[must_use]
+    pub fn center(&self) -> [f64; 2] {
+        [
+            (self.min[0] + self.max[0]) * 0.5,
+            (self.min[1] + self.max[1]) * 0.5,
+        ]
+    }
+    /// Returns the rectangle covered by the specified quadrant of this one.
+    #[must_use]
+    pub fn quadrant(&self, quadrant: Quadrant) -> Self {
+        let center = self.center();
+        let index = quadrant.into_index();
+        let mut result = *self;
+        for (axis, &center) in center.iter().enumerate() {
+            if index & 1 << axis == 0 {
+                result.max[axis] = center;
+            } else {
+                result.min[axis] = center;
+            }
+        }
+        result
+    }
+    /// Returns `true` if the point is inside the rectangle or on its boundary, `false` otherwise.
+    #[must_use]
+    pub fn contains(&self, point: [f64; 2]) -> bool {
+        point
+            .iter()
+            .enumerate()
+            .all(|(axis, &coord)| self.min[axis] <= coord && coord <= self.max[axis])
+    }
+}
 
 /// Packed leaf children nodes of an quadtree's branch node.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -106,6 +287,11 @@ impl<T> From<PackedChildren<T>> for PackedChildrenIter<T> {
         Self(ArrayVec::from(op.0).into_iter())
     }
 }
+impl<T> From<[T; 4]> for PackedChildrenIter<T> {
+    fn from(op: [T; 4]) -> Self {
+        Self(ArrayVec::from(op).into_iter())
+    }
+}
 impl<T> Iterator for PackedChildrenIter<T> {
     type Item = T;
     fn next(&mut self) -> Option<Self::Item> {
@@ -142,3 +328,41 @@ pub type SparseVecQuadtree<B, L = B> =
 #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
 #[allow(unused_qualifications)]
 pub type VecQuadtree<B, L = B> = Quadtree<B, L, usize, alloc::vec::Vec<Node<B, L, usize>>>;
+/// A quadtree which uses a *sparse* `Vec` with 32-bit keys as backing storage, halving the size of the links between nodes on 64-bit targets.
+///
+/// See the [`small_key`] module for more.
+///
+/// [`small_key`]: ../small_key/index.html " "
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+#[allow(unused_qualifications)]
+pub type SmallKeyQuadtree<B, L = B> = Quadtree<
+    B,
+    L,
+    u32,
+    crate::small_key::SmallKeyStorage<crate::storage::SparseVec<Node<B, L, u32>>>,
+>;
+/// A quadtree which uses an `ArrayVec` as backing storage, keeping all of its nodes inline without the need for a memory allocator.
+///
+/// The capacity of an `ArrayVec` is specified by its backing array type, which is the first type parameter here: `[Node<B, L, usize>; 16]` makes a tree which can hold up to 16 nodes. Adding nodes beyond that with the regular methods panics, while [`try_new`] and [`try_make_branch`]/[`try_make_branch_with`] return an error instead.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{quadtree::{InlineQuadtree, Node}, TryMakeBranchError};
+///
+/// let mut tree = InlineQuadtree::<[Node<u32, u32, usize>; 5], u32>::try_new(0).unwrap();
+/// tree.root_mut().try_make_branch([1, 2, 3, 4]).unwrap();
+/// // The storage is full now, so there is no room for any more children.
+/// let mut root = tree.root_mut();
+/// let mut first_child = root.nth_child_mut(0).unwrap();
+/// assert!(matches!(
+///     first_child.try_make_branch([5, 6, 7, 8]),
+///     Err(TryMakeBranchError::OutOfCapacity { .. }),
+/// ));
+/// assert!(first_child.is_leaf());
+/// ```
+///
+/// [`try_new`]: struct.Quadtree.html#method.try_new " "
+/// [`try_make_branch`]: struct.NodeRefMut.html#method.try_make_branch " "
+/// [`try_make_branch_with`]: struct.NodeRefMut.html#method.try_make_branch_with " "
+pub type InlineQuadtree<A, B, L = B> = Quadtree<B, L, usize, ArrayVec<A>>;
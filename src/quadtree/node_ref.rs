@@ -1,15 +1,17 @@
-use core::fmt::Debug;
+use core::{fmt::Debug, iter, ptr};
 use crate::{
     Storage,
     DefaultStorage,
     NodeValue,
-    util::{ArrayMap, unreachable_debugchecked},
+    tree,
+    traversal::algorithms::depth_of,
+    util::{ArrayMap, unreachable_debugchecked, encode_locational_code, decode_locational_code},
 };
-use super::{Quadtree, Node, NodeData};
+use super::{Quadtree, Node, NodeData, PackedChildrenIter, Direction, Quadrant, Rect};
 
 /// A reference to a node in a quadtree.
 ///
-/// Since this type does not point to the node directly, but rather the tree the node is in and the key of the node in the storage, it can be used to traverse the tree.
+/// Since this type points not only to the node itself, but also to the tree the node is in and the key of the node in the storage, it can be used to traverse the tree. The node is looked up in the storage once, when the reference is created, rather than every time it is accessed.
 #[derive(Debug)]
 pub struct NodeRef<'a, B, L, K, S = DefaultStorage<Node<B, L, K>>>
 where
@@ -18,6 +20,8 @@ where
 {
     pub(super) tree: &'a Quadtree<B, L, K, S>,
     pub(super) key: K,
+    /// The node itself, looked up once when the reference is created, since the tree cannot change while it's borrowed.
+    node: &'a Node<B, L, K>,
 }
 impl<'a, B, L, K, S> NodeRef<'a, B, L, K, S>
 where
@@ -25,22 +29,30 @@ where
     K: Clone + Debug + Eq,
 {
     /// Creates a new `NodeRef` pointing to the specified key in the storage, or `None` if it's out of bounds.
+    ///
+    /// The node is looked up right away rather than when the reference is used. For trees backed by sparse storage, this means that the key of a removed node whose slot is still a hole makes this method panic, instead of producing a reference which panics once it's accessed. See the [`Tree`] trait for more on holes.
+    ///
+    /// # Panics
+    /// Will panic if the key points to a hole in sparse storage.
+    ///
+    /// [`Tree`]: ../tree/trait.Tree.html#sparse-storage " "
     pub fn new_raw(tree: &'a Quadtree<B, L, K, S>, key: K) -> Option<Self> {
-        if tree.storage.contains_key(&key) {
-            Some(unsafe {
-                // SAFETY: we just did a key check
-                Self::new_raw_unchecked(tree, key)
-            })
-        } else {
-            None
-        }
+        let node = tree.storage.get(&key)?;
+        Some(Self { tree, key, node })
     }
     /// Creates a new `NodeRef` pointing to the specified key in the storage without doing bounds checking.
     ///
     /// # Safety
     /// Causes *immediate* undefined behavior if the specified key is not present in the storage.
     pub unsafe fn new_raw_unchecked(tree: &'a Quadtree<B, L, K, S>, key: K) -> Self {
-        Self { tree, key }
+        debug_assert!(
+            tree.storage.contains_key(&key),
+            "\
+debug key check failed: tried to reference key {:?} which is not present in the storage",
+            &key,
+        );
+        let node = tree.storage.get_unchecked(&key);
+        Self { tree, key, node }
     }
     /// Returns a reference the raw storage key for the node.
     pub fn raw_key(&self) -> &K {
@@ -63,6 +75,34 @@ where
     pub fn is_root(&self) -> bool {
         self.node().parent.is_none()
     }
+    /// Returns `true` if the node is an ancestor of the specified node, i.e. its parent, the parent of its parent and so on up to the root; `false` otherwise. A node is not an ancestor of itself, and nodes in different trees are never ancestors of each other.
+    ///
+    /// Only the parents of the specified node are visited, which takes *O*(*d*) time without allocating memory, where *d* is the depth of the specified node.
+    pub fn is_ancestor_of(&self, other: &Self) -> bool {
+        if !ptr::eq(self.tree, other.tree) {
+            return false;
+        }
+        let mut ancestor = other.parent();
+        while let Some(node) = ancestor {
+            if node.key == self.key {
+                return true;
+            }
+            ancestor = node.parent();
+        }
+        false
+    }
+    /// Returns `true` if the node is a descendant of the specified node, i.e. one of its children, one of the children of those and so on; `false` otherwise. This is the same as calling [`is_ancestor_of`] with the nodes swapped, and walks the parents of this node.
+    ///
+    /// [`is_ancestor_of`]: #method.is_ancestor_of " "
+    pub fn is_descendant_of(&self, other: &Self) -> bool {
+        other.is_ancestor_of(self)
+    }
+    /// Returns the depth of the node, i.e. the number of levels between it and the root node, with the root node being at depth 0.
+    ///
+    /// The depth is not stored in the nodes, so it's found by walking the parents of the node, which takes *O*(*d*) time, where *d* is the depth.
+    pub fn depth(&self) -> usize {
+        depth_of(self.tree, &self.key)
+    }
     /// Returns `true` if the node is a *leaf*, i.e. does not have child nodes; `false` otherwise.
     pub fn is_leaf(&self) -> bool {
         match &self.node().value {
@@ -85,17 +125,24 @@ where
     pub fn child_index(&self) -> Option<u8> {
         let parent = self.parent()?;
         for (sibling, index) in parent
-            .children()
+            .child_keys()
             .unwrap_or_else(|| unsafe { unreachable_debugchecked("parent nodes cannot be leaves") })
             .iter()
             .zip(0_u8..)
         {
-            if sibling.key == self.key {
+            if *sibling == self.key {
                 return Some(index);
             }
         }
         unsafe { unreachable_debugchecked("failed to find node in parent's child list") }
     }
+    /// Returns the quadrant of the parent cell which the node occupies, or `None` if it's the root node. This is the same as [`child_index`], but returns an [`Quadrant`] instead of a raw index.
+    ///
+    /// [`child_index`]: #method.child_index " "
+    /// [`Quadrant`]: enum.Quadrant.html " "
+    pub fn quadrant(&self) -> Option<Quadrant> {
+        self.child_index().map(Quadrant::from)
+    }
     /// Returns references to the children, or `None` if the node is a leaf node.
     #[allow(clippy::missing_panics_doc)]
     pub fn children(&self) -> Option<[Self; 4]> {
@@ -120,6 +167,26 @@ debug key check failed: tried to reference key {:?} which is not present in the
             })
         })
     }
+    /// Returns an iterator over references to the children, or `None` if the node is a leaf node.
+    ///
+    /// Unlike the array returned by [`children`], the iterator implements `ExactSizeIterator` and `DoubleEndedIterator`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::Quadtree;
+    ///
+    /// let mut tree = Quadtree::<_>::new(0);
+    /// tree.root_mut().make_branch([1, 2, 3, 4]).unwrap();
+    /// let children = tree.root().children_iter().unwrap();
+    /// assert_eq!(children.len(), 4);
+    /// let last = children.rev().next().unwrap();
+    /// assert_eq!(*last.value().into_inner(), 4);
+    /// ```
+    ///
+    /// [`children`]: #method.children " "
+    pub fn children_iter(&self) -> Option<PackedChildrenIter<Self>> {
+        self.children().map(PackedChildrenIter::from)
+    }
     /// Returns a reference to the `n`-th child, or `None` if the node has no children. Indexing starts from zero, thus the value is in range from 0 to 3.
     ///
     /// # Panics
@@ -152,18 +219,148 @@ debug key check failed: tried to reference key {:?} which is not present in the
             Self::new_raw_unchecked(self.tree, child.clone())
         })
     }
+    /// Returns a reference to the child which occupies the specified quadrant, or `None` if the node has no children.
+    pub fn child(&self, quadrant: Quadrant) -> Option<Self> {
+        self.nth_child(quadrant.into_index())
+    }
 
-    fn node(&self) -> &'a Node<B, L, K> {
-        debug_assert!(
-            self.tree.storage.contains_key(&self.key),
-            "\
-debug key check failed: tried to reference key {:?} which is not present in the storage",
-            &self.key,
-        );
-        unsafe {
-            // SAFETY: all existing NodeRefs are guaranteed to not be dangling
-            self.tree.storage.get_unchecked(&self.key)
+    /// Computes the region covered by the node, given the region covered by the root node.
+    ///
+    /// The region is computed from the path from the root to the node, using the layout described in [`Direction`], which means that it doesn't have to be stored in the nodes themselves. This takes *O*(*d*) time, where *d* is the depth of the node.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::quadtree::{Quadtree, Quadrant, Rect};
+    ///
+    /// let mut tree = Quadtree::<_>::new(0);
+    /// let mut root = tree.root_mut();
+    /// root.make_branch([1, 2, 3, 4]).unwrap();
+    /// root.child_mut(Quadrant::NorthEast).unwrap().make_branch([5, 6, 7, 8]).unwrap();
+    ///
+    /// let world = Rect::new([0.0, 0.0], [8.0, 8.0]);
+    /// let node = tree.root().child(Quadrant::NorthEast).unwrap().child(Quadrant::SouthWest).unwrap();
+    /// assert_eq!(node.bounds(world), Rect::new([4.0, 4.0], [6.0, 6.0]));
+    /// ```
+    ///
+    /// [`Direction`]: enum.Direction.html " "
+    #[allow(clippy::suboptimal_flops)] // mul_add is not available without std
+    pub fn bounds(&self, root_bounds: Rect) -> Rect {
+        // Position and size of the node's cell relative to the root's cell, which is mapped to
+        // the unit square. Going up by one level halves the size and shifts the position into
+        // the half of the parent's cell which is selected by the child index.
+        let mut offset = [0.0_f64; 2];
+        let mut size = 1.0_f64;
+        for index in iter::successors(Some(self.clone()), Self::parent)
+            .filter_map(|node| node.child_index())
+        {
+            for (axis, offset) in offset.iter_mut().enumerate() {
+                let half = if index & 1 << axis == 0 { 0.0 } else { 1.0 };
+                *offset = (*offset + half) * 0.5;
+            }
+            size *= 0.5;
+        }
+        let mut result = root_bounds;
+        for (axis, &offset) in offset.iter().enumerate() {
+            let extent = root_bounds.max[axis] - root_bounds.min[axis];
+            result.min[axis] = root_bounds.min[axis] + offset * extent;
+            result.max[axis] = result.min[axis] + size * extent;
+        }
+        result
+    }
+
+    /// Returns the [locational code] of the node, or `None` if the node is more than 31 levels below the root, which is the most that fits into 64 bits.
+    ///
+    /// [locational code]: struct.Quadtree.html#method.node_at_locational_code " "
+    pub fn locational_code(&self) -> Option<u64> {
+        let path_upwards = iter::successors(Some(self.clone()), Self::parent)
+            .filter_map(|node| node.child_index());
+        encode_locational_code(path_upwards, 2)
+    }
+    /// Descends from the node along the path specified by a [locational code], which is interpreted relative to this node rather than the root. Returns `None` if the code is malformed or the path goes through a leaf node.
+    ///
+    /// [locational code]: struct.Quadtree.html#method.node_at_locational_code " "
+    pub fn descendant_at_locational_code(&self, code: u64) -> Option<Self> {
+        decode_locational_code(code, 2)?.try_fold(self.clone(), |node, index| node.nth_child(index))
+    }
+
+    /// Returns the adjacent cell in the specified direction which is of the same size as this node's cell or larger, or `None` if the cell is at the boundary of the space partitioned by the tree.
+    ///
+    /// If the neighboring cell of the same size is subdivided, the branch node corresponding to it is returned. If a larger cell is not subdivided far enough for the neighbor of the same size to exist, the leaf node corresponding to the larger cell is returned instead. See [`Direction`] for the spatial layout of the children which this method assumes.
+    ///
+    /// The neighbor is found by walking up the tree to the closest ancestor which has a sibling in the specified direction, then walking back down along the mirrored path. This takes *O*(*d*²) time without allocating memory, where *d* is the number of levels between the node and the closest common ancestor of it and the neighbor.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::quadtree::{Quadtree, Direction};
+    ///
+    /// let mut tree = Quadtree::<_>::new(-1);
+    /// let mut root = tree.root_mut();
+    /// root.make_branch([0, 1, 2, 3]).unwrap();
+    /// root.nth_child_mut(1).unwrap().make_branch([10, 11, 12, 13]).unwrap();
+    ///
+    /// let first = tree.root().nth_child(0).unwrap();
+    /// // Cells of the same size are found directly...
+    /// let second = first.neighbor(Direction::PositiveX).unwrap();
+    /// assert_eq!(*second.value().into_inner(), 1);
+    /// // ...smaller cells find larger neighbors if the space is not subdivided that far...
+    /// let small = second.nth_child(0).unwrap();
+    /// assert_eq!(*small.neighbor(Direction::NegativeX).unwrap().value().into_inner(), 0);
+    /// // ...and there is nothing beyond the boundaries of the partitioned space.
+    /// assert!(first.neighbor(Direction::NegativeY).is_none());
+    /// ```
+    ///
+    /// [`Direction`]: enum.Direction.html " "
+    pub fn neighbor(&self, direction: Direction) -> Option<Self> {
+        let axis_bit = direction.axis_bit();
+        // Climb up until we find an ancestor with a sibling in the specified direction
+        let mut current = self.clone();
+        let mut levels_climbed = 0_usize;
+        let mut neighbor = loop {
+            let index = current.child_index()?;
+            let parent = current.parent().unwrap_or_else(|| unsafe {
+                unreachable_debugchecked("non-root nodes always have a parent")
+            });
+            if (index & axis_bit != 0) != direction.is_positive() {
+                break parent.nth_child(index ^ axis_bit).unwrap_or_else(|| unsafe {
+                    unreachable_debugchecked("parent nodes cannot be leaves")
+                });
+            }
+            current = parent;
+            levels_climbed += 1;
+        };
+        // Walk back down, mirroring the path which we took on the way up, until we either get to
+        // the same depth or reach a leaf
+        for level in (0..levels_climbed).rev() {
+            if neighbor.is_leaf() {
+                break;
+            }
+            let index = iter::successors(Some(self.clone()), Self::parent)
+                .nth(level)
+                .and_then(|ancestor| ancestor.child_index())
+                .unwrap_or_else(|| unsafe {
+                    unreachable_debugchecked("failed to retrace the path to the common ancestor")
+                });
+            neighbor = neighbor.nth_child(index ^ axis_bit).unwrap_or_else(|| unsafe {
+                unreachable_debugchecked("the node was checked to be a branch")
+            });
         }
+        Some(neighbor)
+    }
+
+    /// Returns the key of the parent node without looking it up in the storage.
+    pub(super) fn parent_key(&self) -> Option<&'a K> {
+        self.node().parent.as_ref()
+    }
+    /// Returns the keys of the children without looking them up in the storage, or `None` if the node is a leaf node.
+    pub(super) fn child_keys(&self) -> Option<&'a [K; 4]> {
+        if let NodeData::Branch { children, .. } = &self.node().value {
+            Some(children)
+        } else {
+            None
+        }
+    }
+    fn node(&self) -> &'a Node<B, L, K> {
+        self.node
     }
 }
 impl<B, L, K, S> Copy for NodeRef<'_, B, L, K, S>
@@ -181,6 +378,7 @@ where
         Self {
             tree: self.tree,
             key: self.key.clone(),
+            node: self.node,
         }
     }
 }
@@ -193,3 +391,15 @@ where
         op.value()
     }
 }
+impl<'a, B, L, K, S> From<NodeRef<'a, B, L, K, S>> for tree::NodeRef<'a, Quadtree<B, L, K, S>>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    fn from(op: NodeRef<'a, B, L, K, S>) -> Self {
+        Self {
+            tree: op.tree,
+            key: op.key,
+        }
+    }
+}
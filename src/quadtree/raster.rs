@@ -0,0 +1,214 @@
+use core::fmt::Debug;
+use crate::{storage::Storage, util::ArrayMap};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use super::{Quadtree, Node, NodeData};
+#[cfg(feature = "alloc")]
+use super::{Rect, Quadrant};
+
+impl<B, L, K, S> Quadtree<B, L, K, S>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    /// Builds a quadtree from a dense 2D grid, such as a bitmap, by recursively subdividing it into quadrants until every quadrant can be merged into a single leaf node.
+    ///
+    /// The grid is stored row by row, i.e. the cell at `(x, y)` is at index `y * width + x`. The rows with lower indices are the ones with lower Y coordinates, so that the children of every branch node follow the layout described in [`Direction`]. A quadrant is merged if `is_mergeable` returns `true` for its first cell (the one with the lowest coordinates) paired with every cell in it, in which case the leaf node gets a clone of the first cell as its payload. Branch nodes get the default value of their payload type.
+    ///
+    /// The root node covers a square with a side equal to the larger dimension of the grid rounded up to the next power of two. If the grid is not such a square, the cells on its edges are extended to cover the rest of the square.
+    ///
+    /// # Panics
+    /// Will panic if the grid is empty or its length does not match the specified dimensions.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{quadtree::Quadtree, NodeValue};
+    ///
+    /// let bitmap = [
+    ///     0, 0, 1, 1,
+    ///     0, 0, 1, 1,
+    ///     0, 0, 0, 1,
+    ///     0, 0, 1, 1,
+    /// ];
+    /// let tree = Quadtree::<(), u8>::from_grid(&bitmap, 4, 4, |a, b| a == b);
+    /// // The root and the north-east quadrant are split, the rest are uniform.
+    /// assert_eq!(tree.num_nodes(), 9);
+    /// let children = tree.root().children().unwrap();
+    /// assert_eq!(children[0].value(), NodeValue::Leaf(&0));
+    /// assert_eq!(children[1].value(), NodeValue::Leaf(&1));
+    /// assert!(children[3].is_branch());
+    /// ```
+    ///
+    /// [`Direction`]: enum.Direction.html " "
+    pub fn from_grid(
+        grid: &[L],
+        width: usize,
+        height: usize,
+        mut is_mergeable: impl FnMut(&L, &L) -> bool,
+    ) -> Self
+    where
+        L: Clone,
+        B: Default,
+    {
+        assert!(width != 0 && height != 0, "the grid is empty");
+        assert_eq!(
+            grid.len(),
+            width * height,
+            "the length of the grid does not match its dimensions",
+        );
+        let grid = Grid {
+            cells: grid,
+            width,
+            height,
+        };
+        let size = width.max(height).next_power_of_two();
+        let mut storage = S::new();
+        let root = add_region(&mut storage, &grid, [0, 0], size, &mut is_mergeable);
+        Self { storage, root }
+    }
+}
+
+struct Grid<'a, T> {
+    cells: &'a [T],
+    width: usize,
+    height: usize,
+}
+impl<T> Grid<'_, T> {
+    /// Returns the cell at the specified coordinates, or the closest one on the edge of the grid if they are outside of it.
+    fn cell(&self, [x, y]: [usize; 2]) -> &T {
+        let x = x.min(self.width - 1);
+        let y = y.min(self.height - 1);
+        &self.cells[y * self.width + x]
+    }
+    /// Returns the range of the coordinates along the specified axis which have to be checked for a square with the specified origin and size. The parts of the square outside of the grid only repeat the cells on its edge, so they are skipped.
+    fn range(&self, axis: usize, origin: [usize; 2], size: usize) -> core::ops::Range<usize> {
+        let len = if axis == 0 { self.width } else { self.height };
+        let start = origin[axis].min(len - 1);
+        let end = (origin[axis] + size).min(len).max(start + 1);
+        start..end
+    }
+}
+
+/// Adds a node covering the square with the specified origin and size, and all of its descendants, to the storage, returning the key of the node. The parent link of the node itself is left for the caller to fill in.
+fn add_region<B, L, K, S>(
+    storage: &mut S,
+    grid: &Grid<'_, L>,
+    origin: [usize; 2],
+    size: usize,
+    is_mergeable: &mut impl FnMut(&L, &L) -> bool,
+) -> K
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+    L: Clone,
+    B: Default,
+{
+    let first = grid.cell(origin);
+    let uniform = size == 1
+        || grid.range(1, origin, size).all(|y| {
+            grid.range(0, origin, size)
+                .all(|x| is_mergeable(first, grid.cell([x, y])))
+        });
+    if uniform {
+        return storage.add(unsafe {
+            // SAFETY: the parent link is filled in by the caller
+            Node::leaf(first.clone(), None)
+        });
+    }
+    let half = size / 2;
+    let children = [0, 1, 2, 3].array_map(|index: usize| {
+        let child_origin = [
+            origin[0] + (index & 1) * half,
+            origin[1] + (index >> 1 & 1) * half,
+        ];
+        add_region(storage, grid, child_origin, half, is_mergeable)
+    });
+    let key = storage.add(Node {
+        value: NodeData::Branch {
+            payload: B::default(),
+            children: children.clone(),
+        },
+        parent: None,
+    });
+    for child_key in &children {
+        unsafe {
+            // SAFETY: we just added all of those
+            storage.get_unchecked_mut(child_key)
+        }
+        .parent = Some(key.clone());
+    }
+    key
+}
+
+/// Samples the payloads of the leaf nodes of a quadtree into a dense 2D grid, which is the inverse of [`Quadtree::from_grid`].
+///
+/// The grid has the specified width and height and is stored row by row, like the one taken by `from_grid`. The cell at `(x, y)` gets a clone of the payload of the leaf node containing the point `(x + 0.5, y + 0.5)`, where the root node covers `root_bounds`, so that the cells are one unit wide. Cells outside of the bounds of the root node are sampled from the closest leaf node on its edge.
+///
+/// # Example
+/// ```rust
+/// use charcoal::quadtree::{self, Quadtree, Rect};
+///
+/// // 3 by 2 pixels, which are padded to a 4 by 4 square by from_grid.
+/// let bitmap = [
+///     1, 1, 2,
+///     1, 1, 3,
+/// ];
+/// let tree = Quadtree::<(), u8>::from_grid(&bitmap, 3, 2, |a, b| a == b);
+/// let root_bounds = Rect::new([0.0, 0.0], [4.0, 4.0]);
+/// assert_eq!(quadtree::rasterize(&tree, root_bounds, 3, 2), bitmap);
+/// // Sampling at a higher resolution scales the image up.
+/// let scaled = quadtree::rasterize(&tree, Rect::new([0.0, 0.0], [8.0, 8.0]), 6, 2);
+/// assert_eq!(scaled, [1, 1, 1, 1, 2, 2, 1, 1, 1, 1, 2, 2]);
+/// ```
+///
+/// [`Quadtree::from_grid`]: struct.Quadtree.html#method.from_grid " "
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+#[allow(clippy::cast_precision_loss)] // Grids with more than 2^52 cells on a side are not a concern
+pub fn rasterize<B, L, K, S>(
+    tree: &Quadtree<B, L, K, S>,
+    root_bounds: Rect,
+    width: usize,
+    height: usize,
+) -> Vec<L>
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+    L: Clone,
+{
+    let mut grid = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            grid.push(sample(tree, root_bounds, [x as f64 + 0.5, y as f64 + 0.5]).clone());
+        }
+    }
+    grid
+}
+/// Returns the payload of the leaf node containing the specified point.
+#[cfg(feature = "alloc")]
+fn sample<B, L, K, S>(tree: &Quadtree<B, L, K, S>, root_bounds: Rect, point: [f64; 2]) -> &L
+where
+    S: Storage<Element = Node<B, L, K>, Key = K>,
+    K: Clone + Debug + Eq,
+{
+    let mut node = tree.root();
+    let mut bounds = root_bounds;
+    loop {
+        let center = bounds.center();
+        let index = point
+            .iter()
+            .zip(center.iter())
+            .enumerate()
+            .filter(|(_, (coord, center))| coord >= center)
+            .fold(0, |index, (axis, _)| index | 1 << axis);
+        node = match node.nth_child(index) {
+            Some(child) => child,
+            None => break,
+        };
+        bounds = bounds.quadrant(Quadrant::from(index));
+    }
+    match node.value() {
+        crate::NodeValue::Leaf(payload) => payload,
+        crate::NodeValue::Branch(..) => unreachable!("only leaf nodes have no children"),
+    }
+}
@@ -0,0 +1,411 @@
+//! Flat array representations of trees, for handing them over to C code, GPU buffers and other consumers which cannot follow the links of the arena-allocated trees themselves.
+//!
+//! A [`RawTreeView`] stores the nodes of a tree in four arrays of the same length, one element per node:
+//! - the payloads of the nodes
+//! - the index of the parent of every node
+//! - the index of the first child of every node
+//! - the index of the next sibling of every node
+//!
+//! Indices are 32-bit, which is what GPU buffers and most C interfaces use, and missing links are stored as [`NO_NODE`]. Views created from trees store the nodes in depth-first pre-order, so the root is always at index 0 and parents always come before their children. Since the arrays are plain slices of plain data, they can be handed over to the consumer as pointers without copying them again.
+//!
+//! The arrays can be turned back into a view with [`from_parts`], which checks that the links are consistent, and the view can be turned into one of the [nested enum representations] to recreate the tree. Nodes without children become leaf nodes, and all other nodes become branch nodes, so the payloads of branch and leaf nodes must have the same type.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{
+//!     freeform_tree::FreeformTree,
+//!     raw_view::{RawTreeView, NO_NODE},
+//! };
+//!
+//! let mut tree = FreeformTree::<_>::new(1);
+//! let mut root = tree.root_mut();
+//! root.make_branch(vec![2, 3]).unwrap();
+//! root.first_child_mut().unwrap().make_branch(vec![4]).unwrap();
+//!
+//! let view = RawTreeView::from_traversable(&tree);
+//! assert_eq!(view.payloads(), [1, 2, 4, 3]);
+//! assert_eq!(view.parents(), [NO_NODE, 0, 1, 0]);
+//! assert_eq!(view.first_children(), [1, 2, NO_NODE, NO_NODE]);
+//! assert_eq!(view.next_siblings(), [NO_NODE, 3, NO_NODE, NO_NODE]);
+//!
+//! // Pretend that the arrays went through C code and came back.
+//! let (payloads, parents, first_children, next_siblings) = view.into_parts();
+//! let view = RawTreeView::from_parts(payloads, parents, first_children, next_siblings).unwrap();
+//! let tree = FreeformTree::<_>::from_repr(view.into_repr());
+//! assert_eq!(*tree.root().last_child().unwrap().value().into_inner(), 3);
+//! ```
+//!
+//! [`RawTreeView`]: struct.RawTreeView.html " "
+//! [`NO_NODE`]: constant.NO_NODE.html " "
+//! [`from_parts`]: struct.RawTreeView.html#method.from_parts " "
+//! [nested enum representations]: ../repr/index.html " "
+
+use core::{
+    convert::Infallible,
+    fmt::{self, Formatter, Display},
+};
+#[cfg(any(feature = "octree", feature = "quadtree"))]
+use core::convert::TryFrom;
+use alloc::{vec, vec::Vec};
+#[cfg(any(feature = "binary_tree", feature = "octree", feature = "quadtree"))]
+use alloc::boxed::Box;
+use crate::{repr::TreeRepr, Traversable};
+#[cfg(feature = "binary_tree")]
+use crate::repr::BinaryTreeRepr;
+#[cfg(feature = "octree")]
+use crate::repr::OctreeRepr;
+#[cfg(feature = "quadtree")]
+use crate::repr::QuadtreeRepr;
+
+/// The index which is stored in place of a missing parent, first child or next sibling.
+pub const NO_NODE: u32 = u32::MAX;
+
+/// A tree stored as flat arrays of payloads and links between the nodes.
+///
+/// See the [module-level documentation] for more.
+///
+/// [module-level documentation]: index.html " "
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RawTreeView<T> {
+    payloads: Vec<T>,
+    parents: Vec<u32>,
+    first_children: Vec<u32>,
+    next_siblings: Vec<u32>,
+}
+impl<T> RawTreeView<T> {
+    /// Creates a view of the specified traversable, cloning the payloads of all nodes. The nodes are stored in depth-first pre-order.
+    ///
+    /// # Panics
+    /// Panics if the traversable has more nodes than can be indexed with 32-bit indices, not counting [`NO_NODE`].
+    ///
+    /// [`NO_NODE`]: constant.NO_NODE.html " "
+    pub fn from_traversable<Tr>(traversable: &Tr) -> Self
+    where
+        Tr: Traversable<Branch = T, Leaf = T>,
+        T: Clone,
+    {
+        let mut view = Self {
+            payloads: Vec::new(),
+            parents: Vec::new(),
+            first_children: Vec::new(),
+            next_siblings: Vec::new(),
+        };
+        // The last child added to every node so far, used to link up the next one.
+        let mut last_children = Vec::new();
+        let mut stack = vec![(traversable.cursor_to_root(), NO_NODE)];
+        while let Some((cursor, parent)) = stack.pop() {
+            let index = view.payloads.len();
+            assert!(
+                index < NO_NODE as usize,
+                "the tree has too many nodes for 32-bit indices",
+            );
+            #[allow(clippy::cast_possible_truncation)] // Checked above
+            let index = index as u32;
+            view.payloads
+                .push(traversable.value_of(&cursor).into_inner().clone());
+            view.parents.push(parent);
+            view.first_children.push(NO_NODE);
+            view.next_siblings.push(NO_NODE);
+            last_children.push(NO_NODE);
+            if parent != NO_NODE {
+                let parent = parent as usize;
+                match last_children[parent] {
+                    NO_NODE => view.first_children[parent] = index,
+                    previous => view.next_siblings[previous as usize] = index,
+                }
+                last_children[parent] = index;
+            }
+            // Pushed in reverse so that the first child is popped first.
+            for n in (0..traversable.num_children_of(&cursor)).rev() {
+                let child = traversable
+                    .nth_child_of(&cursor, n)
+                    .expect("the child number is less than the number of children");
+                stack.push((child, index));
+            }
+        }
+        view
+    }
+    /// Creates a view from the arrays of payloads, parent indices, first child indices and next sibling indices, checking that the links between the nodes are consistent. The nodes can be in any order, as long as the root is at index 0.
+    ///
+    /// # Errors
+    /// Will fail if the arrays are empty, have different lengths or do not describe a single tree with the root at index 0. See [`RawTreeViewError`] for the details.
+    ///
+    /// [`RawTreeViewError`]: enum.RawTreeViewError.html " "
+    pub fn from_parts(
+        payloads: Vec<T>,
+        parents: Vec<u32>,
+        first_children: Vec<u32>,
+        next_siblings: Vec<u32>,
+    ) -> Result<Self, RawTreeViewError> {
+        let num_nodes = payloads.len();
+        if parents.len() != num_nodes
+            || first_children.len() != num_nodes
+            || next_siblings.len() != num_nodes
+        {
+            return Err(RawTreeViewError::LengthMismatch);
+        }
+        if num_nodes == 0 {
+            return Err(RawTreeViewError::Empty);
+        }
+        if num_nodes > NO_NODE as usize {
+            return Err(RawTreeViewError::TooManyNodes);
+        }
+        if parents[0] != NO_NODE || next_siblings[0] != NO_NODE {
+            return Err(RawTreeViewError::InvalidLinks(0));
+        }
+        let mut visited = vec![false; num_nodes];
+        visited[0] = true;
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let mut child = first_children[node as usize];
+            while child != NO_NODE {
+                let index = child as usize;
+                if index >= num_nodes || visited[index] || parents[index] != node {
+                    return Err(RawTreeViewError::InvalidLinks(node));
+                }
+                visited[index] = true;
+                stack.push(child);
+                child = next_siblings[index];
+            }
+        }
+        if let Some(orphan) = visited.iter().position(|&visited| !visited) {
+            #[allow(clippy::cast_possible_truncation)] // The number of nodes was checked above
+            return Err(RawTreeViewError::InvalidLinks(orphan as u32));
+        }
+        Ok(Self {
+            payloads,
+            parents,
+            first_children,
+            next_siblings,
+        })
+    }
+    /// Returns the arrays of payloads, parent indices, first child indices and next sibling indices, in that order.
+    #[must_use]
+    pub fn into_parts(self) -> (Vec<T>, Vec<u32>, Vec<u32>, Vec<u32>) {
+        (
+            self.payloads,
+            self.parents,
+            self.first_children,
+            self.next_siblings,
+        )
+    }
+    /// Returns the number of nodes in the view.
+    #[must_use]
+    pub fn num_nodes(&self) -> usize {
+        self.payloads.len()
+    }
+    /// Returns the payloads of the nodes.
+    #[must_use]
+    pub fn payloads(&self) -> &[T] {
+        &self.payloads
+    }
+    /// Returns the payloads of the nodes *mutably*. The links between the nodes cannot be modified without turning the view into its parts, since that could break them.
+    pub fn payloads_mut(&mut self) -> &mut [T] {
+        &mut self.payloads
+    }
+    /// Returns the indices of the parents of the nodes, which is [`NO_NODE`] for the root node.
+    ///
+    /// [`NO_NODE`]: constant.NO_NODE.html " "
+    #[must_use]
+    pub fn parents(&self) -> &[u32] {
+        &self.parents
+    }
+    /// Returns the indices of the first children of the nodes, which is [`NO_NODE`] for leaf nodes.
+    ///
+    /// [`NO_NODE`]: constant.NO_NODE.html " "
+    #[must_use]
+    pub fn first_children(&self) -> &[u32] {
+        &self.first_children
+    }
+    /// Returns the indices of the next siblings of the nodes, which is [`NO_NODE`] for the root node and the last children of their parents.
+    ///
+    /// [`NO_NODE`]: constant.NO_NODE.html " "
+    #[must_use]
+    pub fn next_siblings(&self) -> &[u32] {
+        &self.next_siblings
+    }
+    /// Converts the view into the [nested enum representation] of a freeform tree.
+    ///
+    /// [nested enum representation]: ../repr/index.html " "
+    #[must_use]
+    pub fn into_repr(self) -> TreeRepr<T> {
+        let result = self.build_repr(|payload, children, _| {
+            Ok::<_, Infallible>(if children.is_empty() {
+                TreeRepr::Leaf(payload)
+            } else {
+                TreeRepr::Branch(payload, children)
+            })
+        });
+        match result {
+            Ok(repr) => repr,
+            Err(never) => match never {},
+        }
+    }
+    /// Converts the view into the [nested enum representation] of a binary tree.
+    ///
+    /// # Errors
+    /// Will fail if one of the nodes has more than 2 children.
+    ///
+    /// [nested enum representation]: ../repr/index.html " "
+    #[cfg(feature = "binary_tree")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "binary_tree")))]
+    pub fn into_binary_tree_repr(self) -> Result<BinaryTreeRepr<T>, RawTreeViewError> {
+        self.build_repr(|payload, children, node| {
+            let mut children = children.into_iter();
+            match (children.next(), children.next(), children.len()) {
+                (None, ..) => Ok(BinaryTreeRepr::Leaf(payload)),
+                (Some(left), right, 0) => Ok(BinaryTreeRepr::Branch(
+                    payload,
+                    Box::new(left),
+                    right.map(Box::new),
+                )),
+                (.., rest) => Err(RawTreeViewError::WrongNumberOfChildren {
+                    node,
+                    num_children: 2 + rest,
+                }),
+            }
+        })
+    }
+    /// Converts the view into the [nested enum representation] of a quadtree.
+    ///
+    /// # Errors
+    /// Will fail if one of the nodes has children but not exactly 4 of them.
+    ///
+    /// [nested enum representation]: ../repr/index.html " "
+    #[cfg(feature = "quadtree")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "quadtree")))]
+    pub fn into_quadtree_repr(self) -> Result<QuadtreeRepr<T>, RawTreeViewError> {
+        self.build_repr(|payload, children, node| {
+            if children.is_empty() {
+                return Ok(QuadtreeRepr::Leaf(payload));
+            }
+            let num_children = children.len();
+            <Box<[_; 4]>>::try_from(children.into_boxed_slice())
+                .map(|children| QuadtreeRepr::Branch(payload, children))
+                .map_err(|_| RawTreeViewError::WrongNumberOfChildren { node, num_children })
+        })
+    }
+    /// Converts the view into the [nested enum representation] of an octree.
+    ///
+    /// # Errors
+    /// Will fail if one of the nodes has children but not exactly 8 of them.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{
+    ///     freeform_tree::FreeformTree,
+    ///     octree::Octree,
+    ///     raw_view::{RawTreeView, RawTreeViewError},
+    /// };
+    ///
+    /// let mut tree = Octree::<_>::new(0);
+    /// tree.root_mut().make_branch([1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+    /// let view = RawTreeView::from_traversable(&tree);
+    /// assert_eq!(view.num_nodes(), 9);
+    /// let tree = Octree::<_>::from_repr(view.into_octree_repr().unwrap());
+    /// assert_eq!(*tree.root().nth_child(7).unwrap().value().into_inner(), 8);
+    ///
+    /// // Freeform trees can have any number of children, which octrees cannot.
+    /// let mut tree = FreeformTree::<_>::new(0);
+    /// tree.root_mut().make_branch(vec![1, 2]).unwrap();
+    /// let view = RawTreeView::from_traversable(&tree);
+    /// assert_eq!(
+    ///     view.into_octree_repr(),
+    ///     Err(RawTreeViewError::WrongNumberOfChildren { node: 0, num_children: 2 }),
+    /// );
+    /// ```
+    ///
+    /// [nested enum representation]: ../repr/index.html " "
+    #[cfg(feature = "octree")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "octree")))]
+    pub fn into_octree_repr(self) -> Result<OctreeRepr<T>, RawTreeViewError> {
+        self.build_repr(|payload, children, node| {
+            if children.is_empty() {
+                return Ok(OctreeRepr::Leaf(payload));
+            }
+            let num_children = children.len();
+            <Box<[_; 8]>>::try_from(children.into_boxed_slice())
+                .map(|children| OctreeRepr::Branch(payload, children))
+                .map_err(|_| RawTreeViewError::WrongNumberOfChildren { node, num_children })
+        })
+    }
+
+    /// Builds a nested representation of the tree from the bottom up, using a closure which receives the payload, the already built children and the index of every node.
+    fn build_repr<R, E>(self, mut f: impl FnMut(T, Vec<R>, u32) -> Result<R, E>) -> Result<R, E> {
+        let mut payloads = self.payloads.into_iter().map(Some).collect::<Vec<_>>();
+        build_node(
+            &mut payloads,
+            &self.first_children,
+            &self.next_siblings,
+            0,
+            &mut f,
+        )
+    }
+}
+fn build_node<T, R, E>(
+    payloads: &mut [Option<T>],
+    first_children: &[u32],
+    next_siblings: &[u32],
+    node: u32,
+    f: &mut impl FnMut(T, Vec<R>, u32) -> Result<R, E>,
+) -> Result<R, E> {
+    let mut children = Vec::new();
+    let mut child = first_children[node as usize];
+    while child != NO_NODE {
+        children.push(build_node(
+            payloads,
+            first_children,
+            next_siblings,
+            child,
+            f,
+        )?);
+        child = next_siblings[child as usize];
+    }
+    let payload = payloads[node as usize]
+        .take()
+        .expect("every node is only visited once");
+    f(payload, children, node)
+}
+
+/// The error type returned by [`RawTreeView::from_parts`] and the conversions of views into nested enum representations of trees with a fixed number of children.
+///
+/// [`RawTreeView::from_parts`]: struct.RawTreeView.html#method.from_parts " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RawTreeViewError {
+    /// The arrays have different lengths.
+    LengthMismatch,
+    /// The arrays are empty. Trees always have at least the root node.
+    Empty,
+    /// The arrays have more elements than can be indexed with 32-bit indices, not counting [`NO_NODE`].
+    ///
+    /// [`NO_NODE`]: constant.NO_NODE.html " "
+    TooManyNodes,
+    /// The node with the specified index has links which point out of bounds, point to a node which was already linked to elsewhere or do not agree with the parent indices of the nodes they point to, or the node cannot be reached from the root node.
+    InvalidLinks(u32),
+    /// The node with the specified index has a number of children which the tree type does not allow.
+    WrongNumberOfChildren {
+        /// The index of the node.
+        node: u32,
+        /// The number of children the node has.
+        num_children: usize,
+    },
+}
+impl Display for RawTreeViewError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LengthMismatch => f.write_str("the arrays have different lengths"),
+            Self::Empty => f.write_str("the arrays are empty"),
+            Self::TooManyNodes => f.write_str("too many nodes for 32-bit indices"),
+            Self::InvalidLinks(node) => write!(f, "invalid links at node {}", node),
+            Self::WrongNumberOfChildren { node, num_children } => write!(
+                f,
+                "node {} has {} children, which the tree does not allow",
+                node, num_children,
+            ),
+        }
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl std::error::Error for RawTreeViewError {}
@@ -0,0 +1,89 @@
+//! Nested enum representations of trees, used to import and export entire trees at once.
+//!
+//! Unlike the arena-allocated trees themselves, those representations are plain recursive enums which directly own their children. This makes them easy to write out by hand, e.g. as test fixtures, and to pass through serialization and FFI boundaries. Every tree type has a corresponding representation, which it can be created from with `from_repr` and converted back into with `to_repr`:
+//! - [`TreeRepr`] for freeform trees, storing children in a `Vec`
+//! - [`BinaryTreeRepr`] for binary trees, storing the left child and an optional right child
+//! - [`QuadtreeRepr`] for quadtrees, storing exactly 4 children in an array
+//! - [`OctreeRepr`] for octrees, storing exactly 8 children in an array
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{freeform_tree::FreeformTree, repr::TreeRepr};
+//!
+//! let repr = TreeRepr::Branch("Root", vec![
+//!     TreeRepr::Leaf("Left"),
+//!     TreeRepr::Branch("Right", vec![TreeRepr::Leaf("Right of Right")]),
+//! ]);
+//! let tree = FreeformTree::<_>::from_repr(repr.clone());
+//! assert_eq!(tree.num_nodes(), 4);
+//! assert_eq!(*tree.root().value().into_inner(), "Root");
+//! // Converting back gives us exactly what we started with.
+//! assert_eq!(tree.to_repr(), repr);
+//! ```
+//!
+//! [`TreeRepr`]: enum.TreeRepr.html " "
+//! [`BinaryTreeRepr`]: enum.BinaryTreeRepr.html " "
+//! [`QuadtreeRepr`]: enum.QuadtreeRepr.html " "
+//! [`OctreeRepr`]: enum.OctreeRepr.html " "
+
+use alloc::vec::Vec;
+#[cfg(any(feature = "binary_tree", feature = "octree", feature = "quadtree"))]
+use alloc::boxed::Box;
+
+/// The nested enum representation of a freeform tree.
+///
+/// See the [module-level documentation] for more.
+///
+/// [module-level documentation]: index.html " "
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TreeRepr<B, L = B> {
+    /// A leaf node with the specified payload.
+    Leaf(L),
+    /// A branch node with the specified payload and children, in order. *Freeform trees cannot have branch nodes without children, so the list must not be empty.*
+    Branch(B, Vec<TreeRepr<B, L>>),
+}
+
+/// The nested enum representation of a binary tree.
+///
+/// See the [module-level documentation] for more.
+///
+/// [module-level documentation]: index.html " "
+#[cfg(feature = "binary_tree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "binary_tree")))]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BinaryTreeRepr<B, L = B> {
+    /// A leaf node with the specified payload.
+    Leaf(L),
+    /// A branch node with the specified payload, left child and, if it's a full branch, right child.
+    Branch(B, Box<BinaryTreeRepr<B, L>>, Option<Box<BinaryTreeRepr<B, L>>>),
+}
+
+/// The nested enum representation of a quadtree.
+///
+/// See the [module-level documentation] for more.
+///
+/// [module-level documentation]: index.html " "
+#[cfg(feature = "quadtree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "quadtree")))]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum QuadtreeRepr<B, L = B> {
+    /// A leaf node with the specified payload.
+    Leaf(L),
+    /// A branch node with the specified payload and children, in order.
+    Branch(B, Box<[QuadtreeRepr<B, L>; 4]>),
+}
+
+/// The nested enum representation of an octree.
+///
+/// See the [module-level documentation] for more.
+///
+/// [module-level documentation]: index.html " "
+#[cfg(feature = "octree")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "octree")))]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum OctreeRepr<B, L = B> {
+    /// A leaf node with the specified payload.
+    Leaf(L),
+    /// A branch node with the specified payload and children, in order.
+    Branch(B, Box<[OctreeRepr<B, L>; 8]>),
+}
@@ -0,0 +1,286 @@
+//! Rewriting of trees with rules which replace subtrees, until none of the rules apply anymore.
+//!
+//! A *rewrite rule* looks at a node and either declines to rewrite it or returns the nested representation of a new subtree, which then takes the place of the subtree of the node. The [`rewrite`] function applies a set of rules to a tree bottom-up, repeating the process until the tree stops changing, which makes it a practical base for term rewriting and optimization passes over expression trees and other intermediate representations.
+//!
+//! Rules are either closures or [`PatternRule`]s, which combine a pattern tree, matched in the same way as with [`match_subtree`], and a closure building the replacement for the matched subtree.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{
+//!     freeform_tree::FreeformTree,
+//!     repr::TreeRepr,
+//!     rewrite::{rewrite, PatternRule, RewriteRule},
+//!     tree::NodeRef,
+//! };
+//!
+//! #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+//! enum Term {
+//!     Add,
+//!     Mul,
+//!     Num(i64),
+//!     Var(&'static str),
+//! }
+//! type Expr = FreeformTree<Term>;
+//!
+//! // (x + 1) * 0 + 2 * 3
+//! let mut tree = Expr::from_repr(TreeRepr::Branch(Term::Add, vec![
+//!     TreeRepr::Branch(Term::Mul, vec![
+//!         TreeRepr::Branch(Term::Add, vec![
+//!             TreeRepr::Leaf(Term::Var("x")),
+//!             TreeRepr::Leaf(Term::Num(1)),
+//!         ]),
+//!         TreeRepr::Leaf(Term::Num(0)),
+//!     ]),
+//!     TreeRepr::Branch(Term::Mul, vec![
+//!         TreeRepr::Leaf(Term::Num(2)),
+//!         TreeRepr::Leaf(Term::Num(3)),
+//!     ]),
+//! ]));
+//!
+//! // _ * 0 → 0, where _ matches any subtree
+//! let pattern = FreeformTree::<_>::from_repr(TreeRepr::Branch(Some(Term::Mul), vec![
+//!     TreeRepr::Leaf(None),
+//!     TreeRepr::Leaf(Some(Term::Num(0))),
+//! ]));
+//! let mut times_zero = PatternRule::new(
+//!     pattern,
+//!     |node, pattern| pattern.into_inner().map_or(true, |term| term == *node.into_inner()),
+//!     |_| TreeRepr::Leaf(Term::Num(0)),
+//! );
+//! // Folds operations on numbers into a single number.
+//! let mut fold = |node: NodeRef<'_, Expr>| {
+//!     let mut operands = node.children().map(|child| match child.value().into_inner() {
+//!         Term::Num(number) => Some(*number),
+//!         _ => None,
+//!     });
+//!     let result = match node.value().into_inner() {
+//!         Term::Add => operands.sum::<Option<i64>>(),
+//!         Term::Mul => operands.product::<Option<i64>>(),
+//!         _ => None,
+//!     };
+//!     result.map(|number| TreeRepr::Leaf(Term::Num(number)))
+//! };
+//!
+//! let rules: &mut [&mut dyn RewriteRule<Expr>] = &mut [&mut times_zero, &mut fold];
+//! assert_eq!(rewrite(&mut tree, rules), 3);
+//! assert_eq!(tree.to_repr(), TreeRepr::Leaf(Term::Num(6)));
+//! ```
+//!
+//! [`rewrite`]: fn.rewrite.html " "
+//! [`PatternRule`]: struct.PatternRule.html " "
+//! [`match_subtree`]: ../traversal/algorithms/fn.match_subtree.html " "
+
+use core::{
+    fmt::{self, Formatter, Debug},
+    marker::PhantomData,
+};
+use alloc::vec::Vec;
+use crate::{
+    traversal::{algorithms::subtree_matches, Traversable},
+    tree::{leftmost_leaf, NodeRef, Tree},
+    NodeValue,
+};
+
+/// Trees in which the subtree of any node can be replaced with a new one, built from a nested representation.
+///
+/// The trait is implemented for [freeform trees] and [binary trees], which use [`TreeRepr`] and [`BinaryTreeRepr`] respectively. Since replacing a subtree removes its nodes one by one, the implementations are only available for trees backed by a [`NonShiftingStorage`], such as the default sparse storage.
+///
+/// [`NonShiftingStorage`]: ../trait.NonShiftingStorage.html " "
+/// [freeform trees]: ../freeform_tree/struct.FreeformTree.html " "
+/// [binary trees]: ../binary_tree/struct.BinaryTree.html " "
+/// [`TreeRepr`]: ../repr/enum.TreeRepr.html " "
+/// [`BinaryTreeRepr`]: ../repr/enum.BinaryTreeRepr.html " "
+pub trait Rewritable: Tree {
+    /// The nested representation from which the new subtrees are built.
+    type Repr;
+    /// Replaces the subtree of the node with the specified key with the one described by the representation, returning the key of the root of the new subtree, or `None` if the key does not point to a node in the tree. The keys of the removed nodes become invalid.
+    fn replace_subtree(
+        &mut self,
+        key: Self::Cursor,
+        replacement: Self::Repr,
+    ) -> Option<Self::Cursor>;
+}
+
+/// A rule for [`rewrite`], which decides whether the subtree of a node is to be replaced and builds the replacement.
+///
+/// The trait is implemented for all closures which take a [`NodeRef`] and return an `Option` of the nested representation of the new subtree, as well as for [`PatternRule`].
+///
+/// [`rewrite`]: fn.rewrite.html " "
+/// [`NodeRef`]: ../tree/struct.NodeRef.html " "
+/// [`PatternRule`]: struct.PatternRule.html " "
+pub trait RewriteRule<T: Rewritable> {
+    /// Returns the nested representation of the subtree which is to replace the subtree of the specified node, or `None` if the rule does not apply to the node.
+    fn apply(&mut self, node: NodeRef<'_, T>) -> Option<T::Repr>;
+}
+impl<T, F> RewriteRule<T> for F
+where
+    T: Rewritable,
+    F: FnMut(NodeRef<'_, T>) -> Option<T::Repr>,
+{
+    fn apply(&mut self, node: NodeRef<'_, T>) -> Option<T::Repr> {
+        self(node)
+    }
+}
+
+/// A [rewrite rule] which applies to the nodes at which a pattern tree matches, building the replacement with a closure.
+///
+/// The pattern matches at a node under the same conditions as with [`match_subtree`], which the `payload_eq` closure is used for in the same way. The `builder` closure then receives the node at which the pattern matched, from which it can look up the subtrees matched by the leaf nodes of the pattern.
+///
+/// [rewrite rule]: trait.RewriteRule.html " "
+/// [`match_subtree`]: ../traversal/algorithms/fn.match_subtree.html " "
+pub struct PatternRule<T: Traversable, N: Traversable, E, F> {
+    pattern: N,
+    payload_eq: E,
+    builder: F,
+    /// The pairs of nodes which are yet to be compared, kept between matches to reuse the allocation.
+    stack: Vec<(T::Cursor, N::Cursor)>,
+    _phantom: PhantomData<fn(&T)>,
+}
+impl<T, N, E, F> PatternRule<T, N, E, F>
+where
+    T: Rewritable,
+    N: Traversable,
+    E: FnMut(NodeValue<&T::Branch, &T::Leaf>, NodeValue<&N::Branch, &N::Leaf>) -> bool,
+    F: FnMut(NodeRef<'_, T>) -> T::Repr,
+{
+    /// Creates a rule with the specified pattern tree, payload comparison closure and replacement builder closure.
+    pub fn new(pattern: N, payload_eq: E, builder: F) -> Self {
+        Self {
+            pattern,
+            payload_eq,
+            builder,
+            stack: Vec::new(),
+            _phantom: PhantomData,
+        }
+    }
+    /// Returns the pattern tree.
+    pub fn pattern(&self) -> &N {
+        &self.pattern
+    }
+}
+impl<T, N, E, F> RewriteRule<T> for PatternRule<T, N, E, F>
+where
+    T: Rewritable,
+    N: Traversable,
+    E: FnMut(NodeValue<&T::Branch, &T::Leaf>, NodeValue<&N::Branch, &N::Leaf>) -> bool,
+    F: FnMut(NodeRef<'_, T>) -> T::Repr,
+{
+    fn apply(&mut self, node: NodeRef<'_, T>) -> Option<T::Repr> {
+        let matches = subtree_matches(
+            node.tree(),
+            node.raw_key().clone(),
+            &self.pattern,
+            &mut self.payload_eq,
+            &mut self.stack,
+        );
+        if matches {
+            Some((self.builder)(node))
+        } else {
+            None
+        }
+    }
+}
+#[allow(clippy::missing_fields_in_debug)] // Closures can't be debug-printed
+impl<T, N, E, F> Debug for PatternRule<T, N, E, F>
+where
+    T: Traversable,
+    N: Traversable + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PatternRule")
+            .field("pattern", &self.pattern)
+            .finish()
+    }
+}
+
+/// Rewrites the tree with the specified rules until none of them apply anymore, returning the number of subtrees which were replaced.
+///
+/// The tree is processed in passes, each of which visits the nodes bottom-up, in depth-first post-order, and replaces the subtree of every node to which one of the rules applies, trying the rules in order and using the first one that applies. The children of a node are thus rewritten before the node itself is looked at, so rules can rely on the children already being in their simplest form. Subtrees created by the rules are not looked at again until the next pass, and the passes are repeated until one of them doesn't replace anything.
+///
+/// The rules are expected to eventually stop applying, like with any term rewriting system: a set of rules which keeps rewriting the same subtree back and forth, or which keeps growing the tree, makes the function loop forever.
+///
+/// Replacing a subtree removes its nodes from the storage, so the storage must keep the keys of the other nodes valid when nodes are removed, which is why [`Rewritable`] is only implemented for trees backed by a [`NonShiftingStorage`], such as the default sparse storage.
+///
+/// # Example
+/// See the [module-level documentation].
+///
+/// [module-level documentation]: index.html " "
+/// [`Rewritable`]: trait.Rewritable.html " "
+/// [`NonShiftingStorage`]: ../trait.NonShiftingStorage.html " "
+#[allow(clippy::missing_panics_doc)] // Keys are only taken from the tree as it's being walked
+pub fn rewrite<T: Rewritable>(tree: &mut T, rules: &mut [&mut dyn RewriteRule<T>]) -> usize {
+    let mut num_rewrites = 0;
+    loop {
+        let mut changed = false;
+        let mut key = leftmost_leaf(tree, tree.root_key());
+        loop {
+            let replacement = {
+                let tree = &*tree;
+                rules.iter_mut().find_map(|rule| {
+                    let node = tree
+                        .get(key.clone())
+                        .expect("the key was taken from the tree");
+                    rule.apply(node)
+                })
+            };
+            if let Some(replacement) = replacement {
+                key = tree
+                    .replace_subtree(key, replacement)
+                    .expect("the key was taken from the tree");
+                num_rewrites += 1;
+                changed = true;
+            }
+            key = match (tree.next_sibling_key(&key), tree.parent_key(&key)) {
+                (Some(next_sibling), _) => leftmost_leaf(tree, next_sibling),
+                (None, Some(parent)) => parent,
+                (None, None) => break,
+            };
+        }
+        if !changed {
+            return num_rewrites;
+        }
+    }
+}
+
+#[cfg(feature = "freeform_tree")]
+mod freeform_tree_impl {
+    use core::fmt::Debug;
+    use crate::{
+        freeform_tree::{FreeformTree, Node},
+        repr::TreeRepr,
+        NonShiftingStorage,
+    };
+    use super::Rewritable;
+
+    impl<B, L, K, S> Rewritable for FreeformTree<B, L, K, S>
+    where
+        S: NonShiftingStorage<Element = Node<B, L, K>, Key = K>,
+        K: Clone + Debug + Eq,
+    {
+        type Repr = TreeRepr<B, L>;
+        fn replace_subtree(&mut self, key: K, replacement: TreeRepr<B, L>) -> Option<K> {
+            self.replace_subtree(key, replacement)
+        }
+    }
+}
+#[cfg(feature = "binary_tree")]
+mod binary_tree_impl {
+    use core::fmt::Debug;
+    use crate::{
+        binary_tree::{BinaryTree, Node},
+        repr::BinaryTreeRepr,
+        NonShiftingStorage,
+    };
+    use super::Rewritable;
+
+    impl<B, L, K, S> Rewritable for BinaryTree<B, L, K, S>
+    where
+        S: NonShiftingStorage<Element = Node<B, L, K>, Key = K>,
+        K: Clone + Debug + Eq,
+    {
+        type Repr = BinaryTreeRepr<B, L>;
+        fn replace_subtree(&mut self, key: K, replacement: BinaryTreeRepr<B, L>) -> Option<K> {
+            self.replace_subtree(key, replacement)
+        }
+    }
+}
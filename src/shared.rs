@@ -0,0 +1,265 @@
+//! Sharing identical leaf payloads between nodes, which cuts the memory usage of highly redundant trees, such as tries or voxel octrees with a small palette of materials.
+//!
+//! Trees in Charcoal own their payloads, so the closest they can get to a DAG is by storing reference-counted payloads in leaf nodes. [`SharedLeaf`] is such a payload: a cheaply cloneable handle to a value, which compares and hashes by the value it points to. An [`Interner`] makes sure that equal values are only allocated once by handing out handles to the same allocation for all of them, and [`dedup_leaves`] does the same for a tree which has already been built, in one pass.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{
+//!     shared::{self, SharedLeaf},
+//!     Quadtree,
+//!     NodeValue,
+//!     Tree,
+//! };
+//!
+//! let mut tree = Quadtree::<(), _>::new(SharedLeaf::new("grass"));
+//! let leaves = [
+//!     SharedLeaf::new("grass"),
+//!     SharedLeaf::new("water"),
+//!     SharedLeaf::new("grass"),
+//!     SharedLeaf::new("grass"),
+//! ];
+//! tree.root_mut().make_branch_with(leaves, |_| ()).unwrap();
+//! let handles_to_first_leaf = |tree: &Quadtree<(), SharedLeaf<&str>>| {
+//!     tree.find_map(|value| match value {
+//!         NodeValue::Leaf(leaf) => Some(leaf.num_handles()),
+//!         NodeValue::Branch(..) => None,
+//!     })
+//! };
+//! // Every leaf has its own allocation so far.
+//! assert_eq!(handles_to_first_leaf(&tree), Some(1));
+//!
+//! assert_eq!(shared::dedup_leaves(&mut tree), 2);
+//! // Now the three grass leaves share one.
+//! assert_eq!(handles_to_first_leaf(&tree), Some(3));
+//! ```
+//!
+//! [`SharedLeaf`]: struct.SharedLeaf.html " "
+//! [`Interner`]: struct.Interner.html " "
+//! [`dedup_leaves`]: fn.dedup_leaves.html " "
+
+use core::{
+    ops::Deref,
+    borrow::Borrow,
+    hash::{Hash, Hasher, BuildHasher},
+    fmt::{self, Formatter, Debug, Display},
+};
+use alloc::sync::Arc;
+use std::collections::{hash_map::RandomState, HashSet};
+use crate::{tree::next_in_preorder, NodeValue, Tree};
+
+/// A reference-counted leaf payload which can be shared between multiple nodes.
+///
+/// Cloning a shared leaf only clones the reference to the value. Comparisons and hashing look at the value itself rather than the address of the allocation, so two shared leaves with equal values are equal even if they weren't interned.
+pub struct SharedLeaf<T>(Arc<T>);
+impl<T> SharedLeaf<T> {
+    /// Moves the value into a new allocation, which isn't shared with any other leaf yet.
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(value))
+    }
+    /// Returns a reference to the value.
+    #[must_use]
+    pub fn inner(&self) -> &T {
+        &self.0
+    }
+    /// Returns the number of handles to the value, including this one.
+    #[must_use]
+    pub fn num_handles(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+    /// Returns `true` if both handles refer to the same allocation, `false` otherwise.
+    #[must_use]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+    /// Returns the value if this is the only handle to it.
+    ///
+    /// # Errors
+    /// Will fail if there are other handles to the value, in which case the handle is returned back to the caller.
+    pub fn try_unwrap(self) -> Result<T, Self> {
+        Arc::try_unwrap(self.0).map_err(Self)
+    }
+}
+impl<T: Clone> SharedLeaf<T> {
+    /// Returns the value, cloning it if there are other handles to it.
+    #[must_use]
+    pub fn unwrap_or_clone(self) -> T {
+        Arc::try_unwrap(self.0).unwrap_or_else(|arc| (*arc).clone())
+    }
+    /// Returns a mutable reference to the value, cloning it into a new allocation first if it's shared with other handles.
+    pub fn make_mut(&mut self) -> &mut T {
+        Arc::make_mut(&mut self.0)
+    }
+}
+impl<T> From<T> for SharedLeaf<T> {
+    fn from(op: T) -> Self {
+        Self::new(op)
+    }
+}
+impl<T> From<Arc<T>> for SharedLeaf<T> {
+    fn from(op: Arc<T>) -> Self {
+        Self(op)
+    }
+}
+impl<T> From<SharedLeaf<T>> for Arc<T> {
+    fn from(op: SharedLeaf<T>) -> Self {
+        op.0
+    }
+}
+impl<T> Clone for SharedLeaf<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+impl<T> Deref for SharedLeaf<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+impl<T> AsRef<T> for SharedLeaf<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+impl<T> Borrow<T> for SharedLeaf<T> {
+    fn borrow(&self) -> &T {
+        &self.0
+    }
+}
+impl<T: PartialEq> PartialEq for SharedLeaf<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || *self.0 == *other.0
+    }
+}
+impl<T: Eq> Eq for SharedLeaf<T> {}
+impl<T: Hash> Hash for SharedLeaf<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+impl<T: Debug> Debug for SharedLeaf<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&*self.0, f)
+    }
+}
+impl<T: Display> Display for SharedLeaf<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&*self.0, f)
+    }
+}
+
+/// A set of shared leaves which hands out handles to the same allocation for equal values.
+///
+/// The interner holds a handle to every value it has seen, so the values are not freed until the interner is dropped or [cleared], even if no nodes refer to them anymore.
+///
+/// # Example
+/// ```rust
+/// use charcoal::shared::Interner;
+///
+/// let mut interner = Interner::new();
+/// let a = interner.intern(String::from("hello"));
+/// let b = interner.intern(String::from("hello"));
+/// assert!(a.ptr_eq(&b));
+/// assert_eq!(interner.len(), 1);
+/// ```
+///
+/// [cleared]: #method.clear " "
+pub struct Interner<T, H = RandomState> {
+    set: HashSet<SharedLeaf<T>, H>,
+}
+impl<T: Hash + Eq> Interner<T> {
+    /// Creates an empty interner.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            set: HashSet::new(),
+        }
+    }
+}
+impl<T: Hash + Eq, H: BuildHasher> Interner<T, H> {
+    /// Creates an empty interner which uses the specified hasher builder to hash the values.
+    pub fn with_hasher(hasher: H) -> Self {
+        Self {
+            set: HashSet::with_hasher(hasher),
+        }
+    }
+    /// Returns a handle to an interned value equal to the specified one, moving the value into a new allocation if there's none.
+    pub fn intern(&mut self, value: T) -> SharedLeaf<T> {
+        if let Some(existing) = self.set.get(&value) {
+            return existing.clone();
+        }
+        let leaf = SharedLeaf::new(value);
+        self.set.insert(leaf.clone());
+        leaf
+    }
+    /// Returns a handle to an interned value equal to the one the specified handle refers to, interning the handle itself if there's none.
+    pub fn intern_shared(&mut self, leaf: SharedLeaf<T>) -> SharedLeaf<T> {
+        if let Some(existing) = self.set.get(&leaf) {
+            return existing.clone();
+        }
+        self.set.insert(leaf.clone());
+        leaf
+    }
+    /// Returns the number of distinct values in the interner.
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+    /// Returns `true` if the interner contains no values, `false` otherwise.
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+    /// Drops the interner's handles to all values. The values which are still used by other handles stay alive, but will no longer be shared with values interned afterwards.
+    pub fn clear(&mut self) {
+        self.set.clear();
+    }
+}
+impl<T: Hash + Eq, H: BuildHasher + Default> Default for Interner<T, H> {
+    fn default() -> Self {
+        Self::with_hasher(H::default())
+    }
+}
+impl<T: Debug, H> Debug for Interner<T, H> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.set.iter()).finish()
+    }
+}
+
+/// Makes all leaf nodes with equal payloads share one allocation, returning the number of leaf nodes whose payloads were replaced with a handle to another allocation.
+///
+/// This is a one-shot pass over the whole tree. To keep sharing the payloads of leaves added afterwards, use [`dedup_leaves_with`] and intern the new payloads with the same interner.
+///
+/// See the [module-level documentation] for an example.
+///
+/// [`dedup_leaves_with`]: fn.dedup_leaves_with.html " "
+/// [module-level documentation]: index.html " "
+pub fn dedup_leaves<T, L>(tree: &mut T) -> usize
+where
+    T: Tree<Leaf = SharedLeaf<L>>,
+    L: Hash + Eq,
+{
+    dedup_leaves_with(tree, &mut Interner::new())
+}
+/// Makes all leaf nodes with equal payloads share one allocation using the specified interner, returning the number of leaf nodes whose payloads were replaced with a handle to another allocation.
+///
+/// The payloads are also shared with the values which were already in the interner, and the interner keeps all of the tree's payloads afterwards.
+#[allow(clippy::missing_panics_doc)] // The keys are always taken from the tree itself
+pub fn dedup_leaves_with<T, L, H>(tree: &mut T, interner: &mut Interner<L, H>) -> usize
+where
+    T: Tree<Leaf = SharedLeaf<L>>,
+    L: Hash + Eq,
+    H: BuildHasher,
+{
+    let mut num_replaced = 0;
+    let mut next = Some(tree.root_key());
+    while let Some(key) = next {
+        if let Some(NodeValue::Leaf(leaf)) = tree.node_value_mut(&key) {
+            let shared = interner.intern_shared(leaf.clone());
+            if !shared.ptr_eq(leaf) {
+                *leaf = shared;
+                num_replaced += 1;
+            }
+        }
+        next = next_in_preorder(tree, key);
+    }
+    num_replaced
+}
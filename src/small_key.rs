@@ -0,0 +1,164 @@
+//! Storing nodes with 32-bit keys instead of `usize` ones, which halves the size of the links between nodes on 64-bit targets.
+//!
+//! Every node stores the key of its parent and the keys of its children or siblings, so for trees with small payloads, the keys make up a large part of the memory usage. [`SmallKeyStorage`] wraps any storage with `usize` keys and converts them to `u32` and back, which is enough for trees with up to 4 billion nodes. Trees use it just like any other storage, with `u32` as the key type; each tree module has an alias for such a tree backed by a sparse `Vec`.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::freeform_tree::{Node, SmallKeyFreeformTree};
+//! use core::mem::size_of;
+//!
+//! let mut tree = SmallKeyFreeformTree::<u8>::new(1);
+//! tree.root_mut().make_branch(vec![2, 3, 4]).unwrap();
+//! assert_eq!(tree.root().children().unwrap().count(), 3);
+//! // The nodes are smaller than the ones with usize keys.
+//! assert!(size_of::<Node<u8, u8, u32>>() < size_of::<Node<u8, u8, usize>>());
+//! ```
+//!
+//! [`SmallKeyStorage`]: struct.SmallKeyStorage.html " "
+
+use core::{
+    convert::TryFrom,
+    fmt::{self, Formatter, Debug, Display},
+};
+use crate::storage::Storage;
+
+/// A wrapper around a storage with `usize` keys which uses `u32` keys instead.
+///
+/// Adding an element whose key in the underlying storage does not fit into a `u32` fails with [`try_add`], or panics with the [`add`] method of the [`Storage`] trait, which is the one used by trees. The element is not added in both cases.
+///
+/// [`try_add`]: #method.try_add " "
+/// [`add`]: ../storage/trait.Storage.html#tymethod.add " "
+/// [`Storage`]: ../storage/trait.Storage.html " "
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SmallKeyStorage<S>(S);
+impl<S: Storage<Key = usize>> SmallKeyStorage<S> {
+    /// Returns a reference to the underlying storage.
+    #[must_use]
+    pub fn inner(&self) -> &S {
+        &self.0
+    }
+    /// Unwraps the underlying storage.
+    #[must_use]
+    pub fn into_inner(self) -> S {
+        self.0
+    }
+    /// Adds an element to the storage, returning its key, or an error if the key does not fit into a `u32`.
+    ///
+    /// # Errors
+    /// Will fail if the key of the element in the underlying storage does not fit into a `u32`. In such a case, the element is removed from the underlying storage and returned back to the caller.
+    pub fn try_add(&mut self, element: S::Element) -> Result<u32, KeyOverflowError<S::Element>> {
+        let key = self.0.add(element);
+        key_to_small(key).ok_or_else(|| KeyOverflowError {
+            element: self.0.remove(&key),
+        })
+    }
+}
+unsafe impl<S: Storage<Key = usize>> Storage for SmallKeyStorage<S> {
+    type Key = u32;
+    type Element = S::Element;
+    const CAPACITY: Option<usize> = S::CAPACITY;
+
+    #[track_caller]
+    fn add(&mut self, element: Self::Element) -> u32 {
+        match self.try_add(element) {
+            Ok(key) => key,
+            Err(..) => panic!("the storage has run out of 32-bit keys"),
+        }
+    }
+    #[track_caller]
+    fn remove(&mut self, key: &u32) -> Self::Element {
+        let key = key_to_large(*key).expect("the key does not fit into a usize");
+        self.0.remove(&key)
+    }
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn with_capacity(capacity: usize) -> Self {
+        Self(S::with_capacity(capacity))
+    }
+    #[allow(clippy::cast_possible_truncation)] // Checked when the key was created
+    unsafe fn get_unchecked(&self, key: &u32) -> &Self::Element {
+        self.0.get_unchecked(&(*key as usize))
+    }
+    #[allow(clippy::cast_possible_truncation)] // Checked when the key was created
+    unsafe fn get_unchecked_mut(&mut self, key: &u32) -> &mut Self::Element {
+        self.0.get_unchecked_mut(&(*key as usize))
+    }
+    fn contains_key(&self, key: &u32) -> bool {
+        key_to_large(*key).map_or(false, |key| self.0.contains_key(&key))
+    }
+    fn new() -> Self {
+        Self(S::new())
+    }
+    fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional);
+    }
+    fn shrink_to_fit(&mut self) {
+        self.0.shrink_to_fit();
+    }
+}
+
+/// Key types which nodes can use for linking to each other in list storages.
+///
+/// List storages address their elements by `usize` indices, which nodes can store either as they are or as `u32` keys, which are used by [`SmallKeyStorage`].
+///
+/// This trait is sealed and cannot be implemented outside of Charcoal.
+///
+/// [`SmallKeyStorage`]: struct.SmallKeyStorage.html " "
+pub trait ListKey: Copy + Debug + Eq + sealed::Sealed {
+    /// Converts a list index into a key. The index is assumed to fit, since keys which don't are rejected when nodes are added.
+    fn from_index(index: usize) -> Self;
+    /// Converts the key into a list index.
+    fn into_index(self) -> usize;
+}
+impl ListKey for usize {
+    fn from_index(index: usize) -> Self {
+        index
+    }
+    fn into_index(self) -> usize {
+        self
+    }
+}
+#[allow(clippy::cast_possible_truncation)] // Checked when the keys are created
+impl ListKey for u32 {
+    fn from_index(index: usize) -> Self {
+        index as u32
+    }
+    fn into_index(self) -> usize {
+        self as usize
+    }
+}
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for usize {}
+    impl Sealed for u32 {}
+}
+
+/// Converts a key of the underlying storage into a small key, or returns `None` if it does not fit.
+fn key_to_small(key: usize) -> Option<u32> {
+    u32::try_from(key).ok()
+}
+/// Converts a small key into a key of the underlying storage, or returns `None` if it does not fit, which can only happen on 16-bit targets.
+fn key_to_large(key: u32) -> Option<usize> {
+    usize::try_from(key).ok()
+}
+
+/// The error type returned by [`SmallKeyStorage::try_add`], indicating that the key of the element does not fit into a `u32`.
+///
+/// [`SmallKeyStorage::try_add`]: struct.SmallKeyStorage.html#method.try_add " "
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct KeyOverflowError<T> {
+    /// The element which was attempted to be added, returned back to the caller to avoid dropping it.
+    pub element: T,
+}
+impl<T> Display for KeyOverflowError<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad("the storage has run out of 32-bit keys")
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl<T: Debug> std::error::Error for KeyOverflowError<T> {}
@@ -0,0 +1,73 @@
+use crate::storage::{Storage, ListStorage, SparseStorage, SparseStorageSlot, MoveFix};
+
+/// Storages which keep the keys of their elements valid when other elements are removed.
+///
+/// Operations which remove nodes one by one while holding on to the keys of other nodes, such as replacing a whole subtree, rely on this guarantee, and are thus only available for trees backed by such storages. Storages which shift their elements upon removal, such as `Vec` or `VecDeque`, do not provide it, since removing an element changes the keys of all elements after it.
+///
+/// The trait is implemented for sparse storage, which is the default one. It is also a supertrait of [`StableStorage`], so all stable storages implement it as well.
+///
+/// [`StableStorage`]: trait.StableStorage.html " "
+pub trait NonShiftingStorage: Storage {}
+impl<E, S> NonShiftingStorage for SparseStorage<E, S>
+where
+    E: MoveFix,
+    S: ListStorage<Element = SparseStorageSlot<E>>,
+{
+}
+
+/// Storages which keep the keys of their elements valid when other elements are removed, and never consider the key of a removed element valid again.
+///
+/// Trees backed by such storages guarantee that the key of a node keeps pointing to the same node for as long as the node stays in the tree, regardless of which other nodes are added or removed, and that looking up a node by its key fails once the node is removed. This makes it possible to safely store keys and look up nodes by them later with methods like `get_by_key` and `get_mut_by_key`.
+///
+/// Storages which shift their elements upon removal, such as `Vec` or `VecDeque`, do not provide the first guarantee. Sparse storage, which is the default one, keeps the keys of other nodes valid but does not provide the second guarantee: the key of a removed node may be reused for a node added later, and looking up the key of a removed node which has not been replaced yet does not fail, leading to a panic once the node is accessed.
+///
+/// If the `slotmap` feature is enabled, the trait is implemented for [`SlotMap`], [`HopSlotMap`] and [`DenseSlotMap`], the keys of which are versioned.
+///
+/// [`SlotMap`]: https://docs.rs/slotmap/*/slotmap/struct.SlotMap.html " "
+/// [`HopSlotMap`]: https://docs.rs/slotmap/*/slotmap/hop/struct.HopSlotMap.html " "
+/// [`DenseSlotMap`]: https://docs.rs/slotmap/*/slotmap/dense/struct.DenseSlotMap.html " "
+pub trait StableStorage: NonShiftingStorage {}
+
+#[cfg(feature = "slotmap")]
+mod slotmap_impl {
+    use core::fmt::Debug;
+    use slotmap_crate::{SlotMap, HopSlotMap, DenseSlotMap, Key, Slottable};
+    use super::{StableStorage, NonShiftingStorage};
+
+    impl<K, V> NonShiftingStorage for SlotMap<K, V>
+    where
+        K: Key + Debug + Eq,
+        V: Slottable,
+    {
+    }
+    impl<K, V> NonShiftingStorage for HopSlotMap<K, V>
+    where
+        K: Key + Debug + Eq,
+        V: Slottable,
+    {
+    }
+    impl<K, V> NonShiftingStorage for DenseSlotMap<K, V>
+    where
+        K: Key + Debug + Eq,
+        V: Slottable,
+    {
+    }
+    impl<K, V> StableStorage for SlotMap<K, V>
+    where
+        K: Key + Debug + Eq,
+        V: Slottable,
+    {
+    }
+    impl<K, V> StableStorage for HopSlotMap<K, V>
+    where
+        K: Key + Debug + Eq,
+        V: Slottable,
+    {
+    }
+    impl<K, V> StableStorage for DenseSlotMap<K, V>
+    where
+        K: Key + Debug + Eq,
+        V: Slottable,
+    {
+    }
+}
@@ -0,0 +1,63 @@
+use super::{depth_of, Traversable};
+
+/// Finds the lowest common ancestor of the nodes at the two specified cursors, i.e. the deepest node which has both of them as descendants, returning its cursor.
+///
+/// A node is considered to be a descendant of itself, meaning that if one of the nodes is an ancestor of the other one, its cursor is returned, and if both cursors point to the same node, that node's cursor is returned. Returns `None` only if the nodes do not share a common root, which is impossible for valid cursors of a properly implemented traversable.
+///
+/// The algorithm only uses [`parent_of`], which makes it run in *O*(*d*) time, where *d* is the depth of the deepest of the two nodes, without allocating any memory.
+///
+/// # Panics
+/// If either of the cursors is invalid.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{
+///     freeform_tree::FreeformTree,
+///     traversal::algorithms::lowest_common_ancestor,
+///     Traversable,
+/// };
+///
+/// let mut tree = FreeformTree::<_>::new("CEO");
+/// let mut root = tree.root_mut();
+/// root.make_branch(vec!["CTO", "CFO"]).unwrap();
+/// root.first_child_mut().unwrap().make_branch(vec!["Dev", "QA"]).unwrap();
+///
+/// let root = tree.cursor_to_root();
+/// let cto = tree.nth_child_of(&root, 0).unwrap();
+/// let cfo = tree.nth_child_of(&root, 1).unwrap();
+/// let dev = tree.nth_child_of(&cto, 0).unwrap();
+/// let qa = tree.nth_child_of(&cto, 1).unwrap();
+///
+/// assert_eq!(lowest_common_ancestor(&tree, &dev, &qa), Some(cto.clone()));
+/// assert_eq!(lowest_common_ancestor(&tree, &dev, &cfo), Some(root.clone()));
+/// assert_eq!(lowest_common_ancestor(&tree, &cto, &qa), Some(cto.clone()));
+/// assert_eq!(lowest_common_ancestor(&tree, &qa, &qa), Some(qa));
+/// ```
+///
+/// [`parent_of`]: ../trait.Traversable.html#tymethod.parent_of " "
+pub fn lowest_common_ancestor<T: Traversable>(
+    traversable: &T,
+    cursor_a: &T::Cursor,
+    cursor_b: &T::Cursor,
+) -> Option<T::Cursor> {
+    let (mut depth_a, mut depth_b) = (
+        depth_of(traversable, cursor_a),
+        depth_of(traversable, cursor_b),
+    );
+    let (mut a, mut b) = (cursor_a.clone(), cursor_b.clone());
+    // Bring both cursors to the same depth first, so that they meet at the ancestor when climbed
+    // in lockstep.
+    while depth_a > depth_b {
+        a = traversable.parent_of(&a)?;
+        depth_a -= 1;
+    }
+    while depth_b > depth_a {
+        b = traversable.parent_of(&b)?;
+        depth_b -= 1;
+    }
+    while a != b {
+        a = traversable.parent_of(&a)?;
+        b = traversable.parent_of(&b)?;
+    }
+    Some(a)
+}
@@ -1,11 +1,25 @@
 //! Ubiquitous algorithms for trees.
 //!
-//! For now, this only includes recursive removal.
+//! For now, this includes recursive removal, pruning, searching, pattern matching, ancestry queries and adaptive refinement.
 
+mod ancestry;
+pub use ancestry::*;
+#[cfg(feature = "alloc")]
+mod pattern;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub use pattern::*;
+mod pruning;
+pub use pruning::*;
 mod recursive_removal;
 pub use recursive_removal::*;
+mod refinement;
+pub use refinement::*;
+mod search;
+pub use search::*;
 
 use super::{
+    Visitor,
     VisitorMut,
     Traversable,
     TraversableMut,
@@ -13,3 +27,122 @@ use super::{
     CursorResult,
     CursorDirectionError,
 };
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use crate::NodeValue;
+
+/// Returns the cursor to the node which follows the specified one in a depth-first pre-order traversal of the subtree starting at `subtree_root`, or `None` if the specified node is the last one.
+pub(crate) fn next_in_preorder<T: Traversable>(
+    traversable: &T,
+    cursor: &T::Cursor,
+    subtree_root: &T::Cursor,
+) -> Option<T::Cursor> {
+    if let Some(first_child) = traversable.nth_child_of(cursor, 0) {
+        return Some(first_child);
+    }
+    next_after_subtree(traversable, cursor, subtree_root)
+}
+/// Returns the cursor to the node which follows all descendants of the specified one in a depth-first pre-order traversal of the subtree starting at `subtree_root`, or `None` if there is no such node.
+pub(crate) fn next_after_subtree<T: Traversable>(
+    traversable: &T,
+    cursor: &T::Cursor,
+    subtree_root: &T::Cursor,
+) -> Option<T::Cursor> {
+    // Climb up until we find a node with a next sibling, without leaving the subtree
+    let mut current = cursor.clone();
+    while &current != subtree_root {
+        if let Some(next_sibling) = next_sibling_of(traversable, &current) {
+            return Some(next_sibling);
+        }
+        current = traversable.parent_of(&current)?;
+    }
+    None
+}
+/// Returns the cursor to the sibling which comes after the node at the specified cursor, or `None` if the node is the last child of its parent or the root node.
+///
+/// The traversable moves the cursor by itself, which is a lot faster than looking for the node among the children of its parent, especially for trees which have to walk the list of children to find the child with a given index.
+pub(crate) fn next_sibling_of<T: Traversable>(
+    traversable: &T,
+    cursor: &T::Cursor,
+) -> Option<T::Cursor> {
+    traversable
+        .advance_cursor(cursor.clone(), VisitorDirection::<_, ()>::NextSibling)
+        .ok()
+}
+/// Returns the cursor to the first node in a depth-first post-order traversal of the subtree starting at the specified node, i.e. its leftmost leaf descendant, or the node itself if it's a leaf.
+pub(crate) fn first_in_postorder<T: Traversable>(
+    traversable: &T,
+    subtree_root: &T::Cursor,
+) -> T::Cursor {
+    let mut current = subtree_root.clone();
+    while let Some(first_child) = traversable.nth_child_of(&current, 0) {
+        current = first_child;
+    }
+    current
+}
+/// Returns the cursor to the node which follows the specified one in a depth-first post-order traversal of the whole tree, or `None` if the specified node is the root node, which is the last one.
+pub(crate) fn next_in_postorder<T: Traversable>(
+    traversable: &T,
+    cursor: &T::Cursor,
+) -> Option<T::Cursor> {
+    if let Some(next_sibling) = next_sibling_of(traversable, cursor) {
+        return Some(first_in_postorder(traversable, &next_sibling));
+    }
+    traversable.parent_of(cursor)
+}
+/// Returns the number of levels between the node at the specified cursor and the root node, i.e. the depth of the node, with the root being at depth 0.
+pub(crate) fn depth_of<T: Traversable>(traversable: &T, cursor: &T::Cursor) -> usize {
+    let mut depth = 0_usize;
+    let mut current = traversable.parent_of(cursor);
+    while let Some(parent) = current {
+        depth += 1;
+        current = traversable.parent_of(&parent);
+    }
+    depth
+}
+
+/// Returns `true` if the pattern tree `needle` matches at the node with the specified cursor, as described by [`match_subtree`]. The stack is cleared before use and is only taken as a parameter to allow reusing the allocation.
+///
+/// [`match_subtree`]: fn.match_subtree.html " "
+#[cfg(feature = "alloc")]
+pub(crate) fn subtree_matches<T, N, F>(
+    haystack: &T,
+    cursor: T::Cursor,
+    needle: &N,
+    payload_eq: &mut F,
+    stack: &mut Vec<(T::Cursor, N::Cursor)>,
+) -> bool
+where
+    T: Traversable,
+    N: Traversable,
+    F: FnMut(NodeValue<&T::Branch, &T::Leaf>, NodeValue<&N::Branch, &N::Leaf>) -> bool,
+{
+    stack.clear();
+    stack.push((cursor, needle.cursor_to_root()));
+    while let Some((node, pattern)) = stack.pop() {
+        let pattern_value = needle.value_of(&pattern);
+        let is_pattern_leaf = matches!(pattern_value, NodeValue::Leaf(..));
+        if !payload_eq(haystack.value_of(&node), pattern_value) {
+            return false;
+        }
+        if is_pattern_leaf {
+            continue;
+        }
+        let num_children = needle.num_children_of(&pattern);
+        if haystack.num_children_of(&node) != num_children {
+            return false;
+        }
+        // Pushed in reverse so that the children are compared in order.
+        for i in (0..num_children).rev() {
+            let children = (
+                haystack.nth_child_of(&node, i),
+                needle.nth_child_of(&pattern, i),
+            );
+            if let (Some(child), Some(pattern_child)) = children {
+                stack.push((child, pattern_child));
+            }
+        }
+    }
+    true
+}
@@ -0,0 +1,161 @@
+use alloc::vec::Vec;
+use core::{fmt::Debug, borrow::Borrow, mem};
+use crate::NodeValue;
+use super::{
+    next_in_preorder,
+    subtree_matches,
+    Visitor,
+    Traversable,
+    VisitorDirection,
+    CursorResult,
+    CursorDirectionError,
+};
+
+/// Searches the entire traversable for occurrences of the pattern tree `needle`, returning the cursors to the nodes at which the pattern matches, in pre-order.
+///
+/// The pattern matches at a node if the closure returns `true` for the payloads of the node and the root of the pattern, and, if the root of the pattern is a branch node, the node has the same number of children as it and the pattern matches at every child with the corresponding child of the pattern, in order. Leaf nodes of the pattern only need the closure to return `true`, which means that they can match whole subtrees: the closure receives the payloads as `NodeValue`s, so it can make a pattern leaf node match only leaf nodes, or act as a wildcard which matches any node. Matches can overlap.
+///
+/// See the [visitor documentation] for the details of the algorithm.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{freeform_tree::FreeformTree, traversal::algorithms::match_subtree, Traversable};
+///
+/// // (a * 0) + ((b + c) * 0)
+/// let mut haystack = FreeformTree::<_>::new("+");
+/// let mut root = haystack.root_mut();
+/// root.make_branch(vec!["*", "*"]).unwrap();
+/// root.first_child_mut().unwrap().make_branch(vec!["a", "0"]).unwrap();
+/// let mut second = root.last_child_mut().unwrap();
+/// second.make_branch(vec!["+", "0"]).unwrap();
+/// second.first_child_mut().unwrap().make_branch(vec!["b", "c"]).unwrap();
+///
+/// // _ * 0, where _ matches any subtree
+/// let mut needle = FreeformTree::<_>::new("*");
+/// needle.root_mut().make_branch(vec!["_", "0"]).unwrap();
+///
+/// let matches = match_subtree(&haystack, &needle, |node, pattern| {
+///     *pattern.into_inner() == "_" || node.into_inner() == pattern.into_inner()
+/// });
+/// assert_eq!(matches.len(), 2);
+/// for cursor in matches {
+///     assert_eq!(*haystack.value_of(&cursor).into_inner(), "*");
+/// }
+/// ```
+///
+/// [visitor documentation]: struct.MatchSubtree.html " "
+pub fn match_subtree<T, N, F>(haystack: &T, needle: &N, payload_eq: F) -> Vec<T::Cursor>
+where
+    T: Traversable,
+    N: Traversable,
+    F: FnMut(NodeValue<&T::Branch, &T::Leaf>, NodeValue<&N::Branch, &N::Leaf>) -> bool,
+{
+    haystack.traverse(MatchSubtree::new(needle, payload_eq))
+}
+/// Searches the subtree of the traversable starting at the specified cursor for occurrences of the pattern tree `needle`, returning the cursors to the nodes at which the pattern matches, in pre-order.
+///
+/// See [`match_subtree`] for the meaning of a match and the [visitor documentation] for the details of the algorithm.
+///
+/// [`match_subtree`]: fn.match_subtree.html " "
+/// [visitor documentation]: struct.MatchSubtree.html " "
+pub fn match_subtree_from<T, N, F>(
+    haystack: &T,
+    cursor: T::Cursor,
+    needle: &N,
+    payload_eq: F,
+) -> Vec<T::Cursor>
+where
+    T: Traversable,
+    N: Traversable,
+    F: FnMut(NodeValue<&T::Branch, &T::Leaf>, NodeValue<&N::Branch, &N::Leaf>) -> bool,
+{
+    haystack.traverse_from(cursor, MatchSubtree::new(needle, payload_eq))
+}
+
+/// A `Visitor` which finds all occurrences of a pattern tree, collecting the cursors to the nodes at which the pattern matches in pre-order.
+///
+/// See also the [`match_subtree`] and [`match_subtree_from`] functions, which create and drive the visitor to completion on a traversable, and describe when the pattern matches.
+///
+/// # Algorithm details
+/// The traversal order is the same as the one of [`FindAll`]. At every node, the pattern is compared with the subtree of the node by walking both of them in depth-first pre-order at the same time, stopping at the first mismatch. This takes *O*(*n* · *m*) time in the worst case, where *n* is the number of nodes in the searched subtree and *m* is the number of nodes in the pattern.
+///
+/// [`match_subtree`]: fn.match_subtree.html " "
+/// [`match_subtree_from`]: fn.match_subtree_from.html " "
+/// [`FindAll`]: struct.FindAll.html " "
+#[derive(Clone, Debug)]
+pub struct MatchSubtree<'a, T, N, F>
+where
+    T: Traversable,
+    N: Traversable,
+    F: FnMut(NodeValue<&T::Branch, &T::Leaf>, NodeValue<&N::Branch, &N::Leaf>) -> bool,
+{
+    needle: &'a N,
+    subtree_root: Option<T::Cursor>,
+    found: Vec<T::Cursor>,
+    /// The pairs of nodes which are yet to be compared, kept between visits to reuse the allocation.
+    stack: Vec<(T::Cursor, N::Cursor)>,
+    payload_eq: F,
+}
+impl<'a, T, N, F> MatchSubtree<'a, T, N, F>
+where
+    T: Traversable,
+    N: Traversable,
+    F: FnMut(NodeValue<&T::Branch, &T::Leaf>, NodeValue<&N::Branch, &N::Leaf>) -> bool,
+{
+    /// Creates the visitor with the specified pattern tree and payload comparison closure.
+    pub fn new(needle: &'a N, payload_eq: F) -> Self {
+        Self {
+            needle,
+            subtree_root: None,
+            found: Vec::new(),
+            stack: Vec::new(),
+            payload_eq,
+        }
+    }
+    /// Returns `true` if the pattern matches at the node with the specified cursor.
+    fn matches_at(&mut self, haystack: &T, cursor: T::Cursor) -> bool {
+        subtree_matches(
+            haystack,
+            cursor,
+            self.needle,
+            &mut self.payload_eq,
+            &mut self.stack,
+        )
+    }
+}
+impl<T, N, F> Visitor for MatchSubtree<'_, T, N, F>
+where
+    T: Traversable,
+    N: Traversable,
+    F: FnMut(NodeValue<&T::Branch, &T::Leaf>, NodeValue<&N::Branch, &N::Leaf>) -> bool,
+{
+    type Target = T;
+    type Output = Vec<T::Cursor>;
+
+    fn visit<C>(
+        &mut self,
+        traversable: impl Borrow<Self::Target>,
+        cursor: CursorResult<C>,
+    ) -> VisitorDirection<C, Self::Output>
+    where
+        C: From<<Self::Target as Traversable>::Cursor>
+            + Into<<Self::Target as Traversable>::Cursor>
+            + Clone
+            + Debug
+            + Eq,
+    {
+        // We only ever use SetTo with valid cursors, so there's nothing to recover from.
+        let cursor = cursor.unwrap_or_else(CursorDirectionError::recover).into();
+        let traversable = traversable.borrow();
+        let subtree_root = self.subtree_root.get_or_insert_with(|| cursor.clone());
+        let next = next_in_preorder(traversable, &cursor, subtree_root);
+        if self.matches_at(traversable, cursor.clone()) {
+            self.found.push(cursor);
+        }
+        let found = &mut self.found;
+        next.map_or_else(
+            || VisitorDirection::Stop(mem::take(found)),
+            |next| VisitorDirection::SetTo(next.into()),
+        )
+    }
+}
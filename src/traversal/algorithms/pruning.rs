@@ -0,0 +1,173 @@
+use core::convert;
+use crate::NodeValue;
+use super::{
+    next_after_subtree, next_in_preorder, recursively_remove_with, Traversable, TraversableMut,
+};
+
+/// Removes every subtree whose root node does not satisfy the specified predicate, returning the number of subtrees removed.
+///
+/// Branch nodes which transition from having one child to having zero children are patched using the `branch_to_leaf` closure. The tree is walked in depth-first pre-order, and the predicate is called at most once for every node which is not the root node — the root node can never be removed and thus is never checked. The descendants of a node which was removed are not checked either, and neither are the nodes which became leaves because their children were removed.
+///
+/// Trees which cannot remove individual children, such as quadtrees and octrees, remove the node along with all of its siblings, the same way [`recursively_remove_with`] does. The siblings count as one removed subtree, regardless of whether they satisfy the predicate.
+///
+/// Removing nodes might invalidate cursors to other nodes, depending on the storage being used. The walk holds on to the cursor of the next node to check while removing a subtree, and thus might skip nodes or panic if the tree uses such a storage, i.e. a `Vec` without sparse storage.
+///
+/// # Panics
+/// Will panic if the traversable does not allow removing one of the subtrees, the same way [`recursively_remove_with`] does, which happens with binary trees that [keep the right child in place] or [only allow full branch nodes].
+///
+/// # Example
+/// ```rust
+/// use charcoal::{FreeformTree, NodeValue, traversal::algorithms::prune_with};
+///
+/// // Branches store the name of the decision, leaves store the score of the outcome.
+/// let mut tree = FreeformTree::<&str, i32>::new(0);
+/// let mut root = tree.root_mut();
+/// root.make_branch_with(vec![10, -5], |_| "Root").unwrap();
+/// root.first_child_mut().unwrap().make_branch_with(vec![-1, 7], |_| "Attack").unwrap();
+/// let num_removed = prune_with(
+///     &mut tree,
+///     |node| match node {
+///         // Get rid of all moves with a negative score
+///         NodeValue::Leaf(&score) => score >= 0,
+///         NodeValue::Branch(..) => true,
+///     },
+///     |_| 0,
+/// );
+/// assert_eq!(num_removed, 2);
+/// assert_eq!(tree.num_nodes() - tree.num_holes(), 3);
+/// ```
+///
+/// [`recursively_remove_with`]: fn.recursively_remove_with.html " "
+/// [keep the right child in place]: ../../binary_tree/enum.RemovalPolicy.html#variant.KeepRightInPlace " "
+/// [only allow full branch nodes]: ../../binary_tree/struct.BinaryTree.html#method.enforce_full_branches " "
+pub fn prune_with<T, P, BtL>(traversable: &mut T, mut keep: P, branch_to_leaf: BtL) -> usize
+where
+    T: TraversableMut,
+    P: FnMut(NodeValue<&T::Branch, &T::Leaf>) -> bool,
+    BtL: FnMut(T::Branch) -> T::Leaf,
+{
+    remove_where(traversable, |node| !keep(node), branch_to_leaf)
+}
+/// Removes every subtree whose root node does not satisfy the specified predicate, returning the number of subtrees removed.
+///
+/// The payloads of branch nodes which transition from having one child to having zero children are kept. Because the payload is kept, *this function is only available when the payload for leaf nodes and branch nodes is the same.* See [`prune_with`] for the details of the algorithm.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{BinaryTree, traversal::algorithms::prune};
+///
+/// let mut tree = BinaryTree::<_>::new(1);
+/// tree.root_mut().make_branch(2, Some(3)).unwrap();
+/// tree.root_mut().left_child_mut().unwrap().make_branch(4, Some(5)).unwrap();
+/// // Removing the node with the even number also removes its children.
+/// assert_eq!(prune(&mut tree, |node| node.into_inner() % 2 != 0), 1);
+/// // The right child takes the place of the left child which was removed.
+/// assert_eq!(*tree.root().left_child().unwrap().value().into_inner(), 3);
+/// assert!(!tree.root().is_full_branch());
+/// ```
+///
+/// [`prune_with`]: fn.prune_with.html " "
+pub fn prune<T, P>(traversable: &mut T, keep: P) -> usize
+where
+    T: TraversableMut<Branch = <T as Traversable>::Leaf>,
+    P: FnMut(NodeValue<&T::Branch, &T::Leaf>) -> bool,
+{
+    prune_with(traversable, keep, convert::identity)
+}
+
+/// Removes every leaf node which does not satisfy the specified predicate, returning the number of leaves removed.
+///
+/// Branch nodes which transition from having one child to having zero children are patched using the `branch_to_leaf` closure. This works the same way as [`prune_with`] with a predicate which keeps all branch nodes: the predicate is called at most once for every leaf node, and branch nodes which became leaves because their children were removed are not checked. If the root node is a leaf, it's kept regardless of the predicate.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{FreeformTree, NodeValue, traversal::algorithms::retain_leaves_with};
+///
+/// let mut tree = FreeformTree::<&str, u32>::new(0);
+/// tree.root_mut().make_branch_with(vec![1, 2, 3, 4], |_| "Numbers").unwrap();
+/// assert_eq!(retain_leaves_with(&mut tree, |&x| x % 2 == 0, |_| 0), 2);
+/// let root = tree.root();
+/// let leaves = root.children().unwrap().map(|child| child.value()).collect::<Vec<_>>();
+/// assert_eq!(leaves, [NodeValue::Leaf(&2), NodeValue::Leaf(&4)]);
+/// ```
+///
+/// [`prune_with`]: fn.prune_with.html " "
+pub fn retain_leaves_with<T, P, BtL>(traversable: &mut T, mut keep: P, branch_to_leaf: BtL) -> usize
+where
+    T: TraversableMut,
+    P: FnMut(&T::Leaf) -> bool,
+    BtL: FnMut(T::Branch) -> T::Leaf,
+{
+    remove_where(
+        traversable,
+        |node| match node {
+            NodeValue::Leaf(payload) => !keep(payload),
+            NodeValue::Branch(..) => false,
+        },
+        branch_to_leaf,
+    )
+}
+/// Removes every leaf node which does not satisfy the specified predicate, returning the number of leaves removed.
+///
+/// The payloads of branch nodes which transition from having one child to having zero children are kept. Because the payload is kept, *this function is only available when the payload for leaf nodes and branch nodes is the same.* See [`retain_leaves_with`] for the details.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{FreeformTree, traversal::algorithms::retain_leaves};
+///
+/// let mut tree = FreeformTree::<_>::new(0);
+/// tree.root_mut().make_branch(vec![1, 2]).unwrap();
+/// assert_eq!(retain_leaves(&mut tree, |_| false), 2);
+/// // The root node is now a leaf, but it's not removed.
+/// assert!(tree.root().is_leaf());
+/// ```
+///
+/// [`retain_leaves_with`]: fn.retain_leaves_with.html " "
+pub fn retain_leaves<T, P>(traversable: &mut T, keep: P) -> usize
+where
+    T: TraversableMut<Branch = <T as Traversable>::Leaf>,
+    P: FnMut(&T::Leaf) -> bool,
+{
+    retain_leaves_with(traversable, keep, convert::identity)
+}
+
+/// Removes every subtree, except for the whole tree, whose root node satisfies the specified predicate, as described in the documentation of `prune_with`.
+fn remove_where<T, P, BtL>(
+    traversable: &mut T,
+    mut should_remove: P,
+    mut branch_to_leaf: BtL,
+) -> usize
+where
+    T: TraversableMut,
+    P: FnMut(NodeValue<&T::Branch, &T::Leaf>) -> bool,
+    BtL: FnMut(T::Branch) -> T::Leaf,
+{
+    let root = traversable.cursor_to_root();
+    let mut cursor = root.clone();
+    let mut num_removed = 0_usize;
+    loop {
+        let next = if cursor != root && should_remove(traversable.value_of(&cursor)) {
+            num_removed += 1;
+            if T::CAN_REMOVE_INDIVIDUAL_CHILDREN {
+                let next = next_after_subtree(traversable, &cursor, &root);
+                recursively_remove_with(traversable, cursor, &mut branch_to_leaf);
+                next
+            } else {
+                // The parent loses all of its children and becomes a leaf, so we continue
+                // from whatever follows it
+                let parent = traversable
+                    .parent_of(&cursor)
+                    .expect("the node is not the root node but has no parent");
+                recursively_remove_with(traversable, cursor, &mut branch_to_leaf);
+                next_after_subtree(traversable, &parent, &root)
+            }
+        } else {
+            next_in_preorder(traversable, &cursor, &root)
+        };
+        match next {
+            Some(next) => cursor = next,
+            None => break,
+        }
+    }
+    num_removed
+}
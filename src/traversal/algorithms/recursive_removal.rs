@@ -44,6 +44,7 @@ where
 /// # Panics
 /// - If the traversable which is being visited incorrectly implements `TraversableMut`, especially `CAN_REMOVE_INDIVIDUAL_CHILDREN` and `parent_of`.
 /// - If removing the root node is attempted. If all nodes in a tree need to be removed recursively, it can just be dropped instead.
+/// - If the traversable does not allow removing the target node individually, despite `CAN_REMOVE_INDIVIDUAL_CHILDREN` being true, which happens with binary trees that [keep the right child in place] when the target node is the left child of a full branch node, or that [only allow full branch nodes].
 ///
 /// # Algorithm details
 /// There are two variants of the algorithm, one which runs only if [`CAN_REMOVE_INDIVIDUAL_CHILDREN`] is true and another which runs if it's false.
@@ -58,6 +59,7 @@ where
 ///     - If the node at the cursor is a branch node:
 ///         - Try to remove the node together with its children
 ///             - If it failed because there was a branch child node, move the cursor to that node and **end traversal step, awaiting next iteration**
+///             - If it failed because the traversable does not allow removing the node individually, remove its children instead, same as in the fallback algorithm, move the cursor to the parent node and **end traversal step, awaiting next iteration**
 ///             - Otherwise, if the branch node removed was the target node, **end the traversal**, returning the value of the just-removed node
 ///             - Otherwise, if the target node was among the removed leaf nodes, **end the traversal**, returning the required leaf node
 ///             - Otherwise, move the cursor to the parent node of the branch node which has just been removed and **end traversal step, awaiting next iteration**
@@ -75,6 +77,8 @@ where
 /// [`recursively_remove_with`]: function.recursively_remove_with.html " "
 /// [`recursively_remove`]: function.recursively_remove.html " "
 /// [`CAN_REMOVE_INDIVIDUAL_CHILDREN`]: ../trait.TraversableMut.html#constant.CAN_REMOVE_INDIVIDUAL_CHILDREN " "
+/// [keep the right child in place]: ../../binary_tree/enum.RemovalPolicy.html#variant.KeepRightInPlace " "
+/// [only allow full branch nodes]: ../../binary_tree/struct.BinaryTree.html#method.enforce_full_branches " "
 #[derive(Copy, Clone, Debug)]
 pub struct RecursiveRemovalWith<T: TraversableMut, F: FnMut(T::Branch) -> T::Leaf> {
     pivot: T::Cursor,
@@ -155,12 +159,15 @@ impl<T: TraversableMut, F: FnMut(T::Branch) -> T::Leaf> VisitorMut for Recursive
                             "\
 the node was a branch node but removing it returned TryRemoveBranchError::WasLeafNode"
                         ),
-                        TryRemoveBranchError::HadBranchChild(index) => index,
-                        TryRemoveBranchError::CannotRemoveIndividualChildren => panic!(
-                            "\
-CAN_REMOVE_INDIVIDUAL_CHILDREN is true, but removing a branch node returned \
-TryRemoveBranchError::CannotRemoveIndividualChildren"
-                        ),
+                        TryRemoveBranchError::HadBranchChild(index) => Some(index),
+                        // The node is only kept in place by its siblings, which stay in the tree
+                        // only if the node is the target node
+                        TryRemoveBranchError::CannotRemoveIndividualChildren
+                            if cursor == self.pivot =>
+                        {
+                            panic!("the traversable does not allow removing the target node")
+                        }
+                        TryRemoveBranchError::CannotRemoveIndividualChildren => None,
                     });
                 match result {
                     Ok(val) => {
@@ -179,7 +186,34 @@ the removed node was not a root node but its parent node could not be found",
                         }
                         direction
                     }
-                    Err(branch_child) => VisitorDirection::Child(branch_child),
+                    Err(Some(branch_child)) => VisitorDirection::Child(branch_child),
+                    Err(None) => {
+                        // The parent gets removed later anyway, so the node can be removed
+                        // together with it after being turned into a leaf
+                        let result = traversable.try_remove_children_into(
+                            &cursor,
+                            &mut self.conversion,
+                            drop,
+                        );
+                        match result {
+                            Ok(()) => VisitorDirection::SetTo(
+                                parent
+                                    .expect(
+                                        "\
+the node was not the target node but its parent node could not be found",
+                                    )
+                                    .into(),
+                            ),
+                            Err(TryRemoveChildrenError::HadBranchChild(branch_child)) => {
+                                VisitorDirection::Child(branch_child)
+                            }
+                            Err(TryRemoveChildrenError::WasLeafNode) => panic!(
+                                "\
+the node was a branch node but removing its children returned \
+TryRemoveChildrenError::WasLeafNode"
+                            ),
+                        }
+                    }
                 }
             }
             NodeValue::Branch(..) => {
@@ -233,11 +267,9 @@ the node was a branch node but removing it returned TryRemoveChildrenError::WasL
                             "\
 the node was a leaf node but removing it returned TryRemoveLeafError::WasBranchNode"
                         ),
-                        TryRemoveLeafError::CannotRemoveIndividualChildren => panic!(
-                            "\
-CAN_REMOVE_INDIVIDUAL_CHILDREN is true, but removing a leaf node returned \
-TryRemoveLeafError::CannotRemoveIndividualChildren"
-                        ),
+                        TryRemoveLeafError::CannotRemoveIndividualChildren => {
+                            panic!("the traversable does not allow removing the target node")
+                        }
                     });
                 if cursor == self.pivot {
                     VisitorDirection::Stop(NodeValue::Leaf(payload))
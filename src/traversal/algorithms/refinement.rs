@@ -0,0 +1,124 @@
+use core::convert;
+use crate::{NodeValue, util::unreachable_debugchecked};
+use super::{depth_of, next_in_preorder, TraversableMut};
+
+/// Adaptively refines the tree by subdividing leaf nodes which are deemed too coarse, returning the number of nodes which were subdivided.
+///
+/// Leaf nodes for which the specified predicate returns `true` are subdivided until either there are no such leaves left or they are at the maximum depth. The payloads of the new children are produced by the `subdivide` closure from the payload of the leaf being subdivided, after which the payload of the leaf itself is converted using the `leaf_to_branch` closure. The new children are checked by the predicate as well, meaning that a single call refines the tree as far as needed. The root node is at depth 0, so setting `max_depth` to 0 never subdivides any nodes.
+///
+/// The algorithm walks the tree in depth-first pre-order without using recursion or allocating memory. Branch nodes which existed before the call are left untouched, though their descendants are still visited.
+///
+/// # Panics
+/// Will panic if the traversable cannot pack children, i.e. if `CAN_PACK_CHILDREN` is `false`. Of the trees provided by Charcoal, only quadtrees, octrees and binary trees can.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{quadtree::Quadtree, traversal::algorithms::refine_until_with};
+///
+/// // Every leaf stores the size of its cell, and the branches store how many times they were split.
+/// let mut tree = Quadtree::<u32, f32>::new(1.0);
+/// let num_subdivided = refine_until_with(
+///     &mut tree,
+///     8,
+///     |&size| size > 0.3,
+///     |&size| [size / 2.0; 4].into(),
+///     |_| 1,
+/// );
+/// // The root is split once, and then each of its 4 children is split once more.
+/// assert_eq!(num_subdivided, 5);
+/// assert_eq!(tree.num_nodes(), 1 + 4 + 16);
+/// ```
+pub fn refine_until_with<T, P, S, LtB>(
+    traversable: &mut T,
+    max_depth: usize,
+    mut is_too_coarse: P,
+    mut subdivide: S,
+    mut leaf_to_branch: LtB,
+) -> usize
+where
+    T: TraversableMut,
+    P: FnMut(&T::Leaf) -> bool,
+    S: FnMut(&T::Leaf) -> T::PackedChildren,
+    LtB: FnMut(T::Leaf) -> T::Branch,
+{
+    assert!(
+        T::CAN_PACK_CHILDREN,
+        "the traversable does not support packing children, which is required for refinement",
+    );
+    let root = traversable.cursor_to_root();
+    let mut cursor = root.clone();
+    let mut depth = 0_usize;
+    let mut num_subdivided = 0_usize;
+    loop {
+        let children = match traversable.value_of(&cursor) {
+            NodeValue::Leaf(payload) if depth < max_depth && is_too_coarse(payload) => {
+                Some(subdivide(payload))
+            }
+            _ => None,
+        };
+        if let Some(children) = children {
+            if traversable
+                .try_add_children(&cursor, children, &mut leaf_to_branch)
+                .is_err()
+            {
+                unsafe {
+                    // SAFETY: we just checked that the node is a leaf
+                    unreachable_debugchecked("failed to subdivide a leaf node")
+                }
+            }
+            num_subdivided += 1;
+        }
+        let next = if let Some(next) = next_in_preorder(traversable, &cursor, &root) {
+            next
+        } else {
+            break;
+        };
+        // Going down only ever happens one level at a time, so we only need to recount the depth
+        // when climbing back up
+        depth = if traversable.parent_of(&next).as_ref() == Some(&cursor) {
+            depth + 1
+        } else {
+            depth_of(traversable, &next)
+        };
+        cursor = next;
+    }
+    num_subdivided
+}
+/// Adaptively refines the tree by subdividing leaf nodes which are deemed too coarse, keeping their original payload, and returns the number of nodes which were subdivided.
+///
+/// Because the payload is kept, *this function is only available when the payload for leaf nodes and branch nodes is the same.* See [`refine_until_with`] for the details of the algorithm.
+///
+/// # Panics
+/// Will panic if the traversable cannot pack children, i.e. if `CAN_PACK_CHILDREN` is `false`. Of the trees provided by Charcoal, only quadtrees, octrees and binary trees can.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{octree::Octree, traversal::algorithms::refine_until};
+///
+/// // Split every cell into eight smaller ones until we get to the third level.
+/// let mut tree = Octree::<_>::new(1_u32);
+/// let num_subdivided = refine_until(&mut tree, 2, |_| true, |&level| [level + 1; 8].into());
+/// assert_eq!(num_subdivided, 1 + 8);
+/// assert_eq!(tree.num_nodes(), 1 + 8 + 64);
+/// ```
+///
+/// [`refine_until_with`]: fn.refine_until_with.html " "
+pub fn refine_until<T, D, P, S>(
+    traversable: &mut T,
+    max_depth: usize,
+    is_too_coarse: P,
+    subdivide: S,
+) -> usize
+where
+    T: TraversableMut<Leaf = D, Branch = D>,
+    P: FnMut(&D) -> bool,
+    S: FnMut(&D) -> T::PackedChildren,
+{
+    refine_until_with(
+        traversable,
+        max_depth,
+        is_too_coarse,
+        subdivide,
+        convert::identity,
+    )
+}
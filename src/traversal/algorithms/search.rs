@@ -0,0 +1,228 @@
+#[cfg(feature = "alloc")]
+use {alloc::vec::Vec, core::mem};
+use core::{fmt::Debug, borrow::Borrow};
+use crate::NodeValue;
+use super::{
+    next_in_preorder,
+    Visitor,
+    Traversable,
+    VisitorDirection,
+    CursorResult,
+    CursorDirectionError,
+};
+
+/// Searches the entire traversable for the first node, in pre-order, for which the specified predicate returns `true`, returning its cursor or `None` if there is no such node.
+///
+/// See the [visitor documentation] for the details of the algorithm.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{freeform_tree::FreeformTree, traversal::algorithms::find_first, Traversable};
+///
+/// let mut tree = FreeformTree::<_>::new(1);
+/// tree.root_mut().make_branch(vec![2, 3, 4]).unwrap();
+///
+/// let found = find_first(&tree, |value| *value.into_inner() > 2)
+///     .expect("there is a node with a value greater than 2");
+/// assert_eq!(*tree.value_of(&found).into_inner(), 3);
+/// assert!(find_first(&tree, |value| *value.into_inner() > 4).is_none());
+/// ```
+///
+/// [visitor documentation]: struct.FindFirst.html " "
+pub fn find_first<T, F>(traversable: &T, predicate: F) -> Option<T::Cursor>
+where
+    T: Traversable,
+    F: FnMut(NodeValue<&T::Branch, &T::Leaf>) -> bool,
+{
+    traversable.traverse(FindFirst::new(predicate))
+}
+/// Searches the subtree starting at the specified cursor for the first node, in pre-order, for which the specified predicate returns `true`, returning its cursor or `None` if there is no such node.
+///
+/// See the [visitor documentation] for the details of the algorithm.
+///
+/// [visitor documentation]: struct.FindFirst.html " "
+pub fn find_first_from<T, F>(traversable: &T, cursor: T::Cursor, predicate: F) -> Option<T::Cursor>
+where
+    T: Traversable,
+    F: FnMut(NodeValue<&T::Branch, &T::Leaf>) -> bool,
+{
+    traversable.traverse_from(cursor, FindFirst::new(predicate))
+}
+/// Searches the entire traversable for all nodes for which the specified predicate returns `true`, returning their cursors in pre-order.
+///
+/// See the [visitor documentation] for the details of the algorithm.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{freeform_tree::FreeformTree, traversal::algorithms::find_all, Traversable};
+///
+/// let mut tree = FreeformTree::<_>::new(1);
+/// tree.root_mut().make_branch(vec![2, 3, 4]).unwrap();
+///
+/// let even = find_all(&tree, |value| *value.into_inner() % 2 == 0)
+///     .into_iter()
+///     .map(|cursor| *tree.value_of(&cursor).into_inner())
+///     .collect::<Vec<_>>();
+/// assert_eq!(even, [2, 4]);
+/// ```
+///
+/// [visitor documentation]: struct.FindAll.html " "
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub fn find_all<T, F>(traversable: &T, predicate: F) -> Vec<T::Cursor>
+where
+    T: Traversable,
+    F: FnMut(NodeValue<&T::Branch, &T::Leaf>) -> bool,
+{
+    traversable.traverse(FindAll::new(predicate))
+}
+/// Searches the subtree starting at the specified cursor for all nodes for which the specified predicate returns `true`, returning their cursors in pre-order.
+///
+/// See the [visitor documentation] for the details of the algorithm.
+///
+/// [visitor documentation]: struct.FindAll.html " "
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub fn find_all_from<T, F>(traversable: &T, cursor: T::Cursor, predicate: F) -> Vec<T::Cursor>
+where
+    T: Traversable,
+    F: FnMut(NodeValue<&T::Branch, &T::Leaf>) -> bool,
+{
+    traversable.traverse_from(cursor, FindAll::new(predicate))
+}
+
+/// A `Visitor` which finds the first node, in pre-order, for which a predicate returns `true`.
+///
+/// See also the [`find_first`] and [`find_first_from`] functions, which create and drive the visitor to completion on a traversable.
+///
+/// # Algorithm details
+/// The visitor performs a depth-first pre-order traversal of the subtree starting at the node the cursor was initially at:
+/// - For every traversal step:
+///     - If the predicate returns `true` for the node at the cursor, **end the traversal**, returning its cursor
+///     - Otherwise, if the node is a branch node, move the cursor to its first child and **end traversal step, awaiting next iteration**
+///     - Otherwise, move the cursor to the next sibling of the closest node (starting with the node itself) which has one without leaving the subtree and **end traversal step, awaiting next iteration**
+///     - If there is no such node, the entire subtree has been visited, so **end the traversal**, returning `None`
+///
+/// [`find_first`]: function.find_first.html " "
+/// [`find_first_from`]: function.find_first_from.html " "
+#[derive(Copy, Clone, Debug)]
+pub struct FindFirst<T: Traversable, F: FnMut(NodeValue<&T::Branch, &T::Leaf>) -> bool> {
+    subtree_root: Option<T::Cursor>,
+    predicate: F,
+}
+impl<T, F> FindFirst<T, F>
+where
+    T: Traversable,
+    F: FnMut(NodeValue<&T::Branch, &T::Leaf>) -> bool,
+{
+    /// Creates the visitor with the specified predicate.
+    pub fn new(predicate: F) -> Self {
+        Self {
+            subtree_root: None,
+            predicate,
+        }
+    }
+}
+impl<T, F> Visitor for FindFirst<T, F>
+where
+    T: Traversable,
+    F: FnMut(NodeValue<&T::Branch, &T::Leaf>) -> bool,
+{
+    type Target = T;
+    type Output = Option<T::Cursor>;
+
+    fn visit<C>(
+        &mut self,
+        traversable: impl Borrow<Self::Target>,
+        cursor: CursorResult<C>,
+    ) -> VisitorDirection<C, Self::Output>
+    where
+        C: From<<Self::Target as Traversable>::Cursor>
+            + Into<<Self::Target as Traversable>::Cursor>
+            + Clone
+            + Debug
+            + Eq,
+    {
+        // We only ever use SetTo with valid cursors, so there's nothing to recover from.
+        let cursor = cursor.unwrap_or_else(CursorDirectionError::recover).into();
+        let traversable = traversable.borrow();
+        let subtree_root = self.subtree_root.get_or_insert_with(|| cursor.clone());
+        if (self.predicate)(traversable.value_of(&cursor)) {
+            return VisitorDirection::Stop(Some(cursor));
+        }
+        next_in_preorder(traversable, &cursor, subtree_root).map_or_else(
+            || VisitorDirection::Stop(None),
+            |next| VisitorDirection::SetTo(next.into()),
+        )
+    }
+}
+
+/// A `Visitor` which finds all nodes for which a predicate returns `true`, collecting their cursors in pre-order.
+///
+/// See also the [`find_all`] and [`find_all_from`] functions, which create and drive the visitor to completion on a traversable.
+///
+/// # Algorithm details
+/// The traversal order is the same as the one of [`FindFirst`], except the traversal does not end after the first matching node is found, and instead only ends after all nodes of the subtree have been visited.
+///
+/// [`find_all`]: function.find_all.html " "
+/// [`find_all_from`]: function.find_all_from.html " "
+/// [`FindFirst`]: struct.FindFirst.html " "
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+#[derive(Clone, Debug)]
+pub struct FindAll<T: Traversable, F: FnMut(NodeValue<&T::Branch, &T::Leaf>) -> bool> {
+    subtree_root: Option<T::Cursor>,
+    found: Vec<T::Cursor>,
+    predicate: F,
+}
+#[cfg(feature = "alloc")]
+impl<T, F> FindAll<T, F>
+where
+    T: Traversable,
+    F: FnMut(NodeValue<&T::Branch, &T::Leaf>) -> bool,
+{
+    /// Creates the visitor with the specified predicate.
+    pub fn new(predicate: F) -> Self {
+        Self {
+            subtree_root: None,
+            found: Vec::new(),
+            predicate,
+        }
+    }
+}
+#[cfg(feature = "alloc")]
+impl<T, F> Visitor for FindAll<T, F>
+where
+    T: Traversable,
+    F: FnMut(NodeValue<&T::Branch, &T::Leaf>) -> bool,
+{
+    type Target = T;
+    type Output = Vec<T::Cursor>;
+
+    fn visit<C>(
+        &mut self,
+        traversable: impl Borrow<Self::Target>,
+        cursor: CursorResult<C>,
+    ) -> VisitorDirection<C, Self::Output>
+    where
+        C: From<<Self::Target as Traversable>::Cursor>
+            + Into<<Self::Target as Traversable>::Cursor>
+            + Clone
+            + Debug
+            + Eq,
+    {
+        // Same as above.
+        let cursor = cursor.unwrap_or_else(CursorDirectionError::recover).into();
+        let traversable = traversable.borrow();
+        let subtree_root = self.subtree_root.get_or_insert_with(|| cursor.clone());
+        let next = next_in_preorder(traversable, &cursor, subtree_root);
+        if (self.predicate)(traversable.value_of(&cursor)) {
+            self.found.push(cursor);
+        }
+        let found = &mut self.found;
+        next.map_or_else(
+            || VisitorDirection::Stop(mem::take(found)),
+            |next| VisitorDirection::SetTo(next.into()),
+        )
+    }
+}
@@ -0,0 +1,151 @@
+use core::future::Future;
+use super::{Traversable, VisitorDirection, CursorResult, check_cursor};
+
+/// A version of [`Visitor`] which returns the directions for the traversal as a future.
+///
+/// This is meant for algorithms which need to wait for I/O while processing the nodes, such as fetching additional data for them from a database. The cursor protocol is the same as the one of `Visitor`: the visitor receives the cursor of the visited node, which is an error if the previous directions could not be followed, and the traversal moves the cursor according to the direction which the future resolves to, until it resolves to `Stop`. Unlike with `Visitor`, the cursor type is always the one of the target traversable.
+///
+/// The visitor is moved into the future and given back along with the directions once it resolves, the same way [`VisitorMut`] gives back the traversable, so the future can update the state of the visitor after waiting. The future cannot borrow the traversable, so everything it needs from the node has to be read by [`visit`] before it's created. The traversal is driven by [`traverse_async`] and [`traverse_async_from`].
+///
+/// # Example
+/// ```rust
+/// use core::{future::Future, pin::Pin};
+/// use charcoal::{
+///     freeform_tree::FreeformTree,
+///     traversal::{traverse_async, AsyncVisitor, CursorResult, VisitorDirection},
+///     Traversable,
+/// };
+/// # use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+/// # fn block_on<F: Future>(future: F) -> F::Output {
+/// #     fn raw_waker() -> RawWaker {
+/// #         fn clone(_: *const ()) -> RawWaker { raw_waker() }
+/// #         fn noop(_: *const ()) {}
+/// #         static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+/// #         RawWaker::new(core::ptr::null(), &VTABLE)
+/// #     }
+/// #     let waker = unsafe { Waker::from_raw(raw_waker()) };
+/// #     let mut context = Context::from_waker(&waker);
+/// #     let mut future = Box::pin(future);
+/// #     loop {
+/// #         if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+/// #             return output;
+/// #         }
+/// #     }
+/// # }
+///
+/// // Stands in for a database query.
+/// async fn fetch_size(id: u32) -> u64 {
+///     u64::from(id) * 100
+/// }
+///
+/// // Adds up the sizes of the entries with the IDs stored along the first children.
+/// struct TotalSize(u64);
+/// impl AsyncVisitor for TotalSize {
+///     type Target = FreeformTree<u32>;
+///     type Output = u64;
+///     type Future = Pin<Box<dyn Future<Output = (VisitorDirection<usize, u64>, Self)>>>;
+///
+///     fn visit(mut self, tree: &Self::Target, cursor: CursorResult<usize>) -> Self::Future {
+///         // The future cannot borrow the tree, so the payload is read right away.
+///         let id = cursor.as_ref().ok().map(|cursor| *tree.value_of(cursor).into_inner());
+///         Box::pin(async move {
+///             let direction = match id {
+///                 Some(id) => {
+///                     self.0 += fetch_size(id).await;
+///                     VisitorDirection::Child(0)
+///                 }
+///                 None => VisitorDirection::Stop(self.0),
+///             };
+///             (direction, self)
+///         })
+///     }
+/// }
+///
+/// let mut tree = FreeformTree::<u32>::new(1);
+/// let mut root = tree.root_mut();
+/// root.make_branch(vec![2, 3]).unwrap();
+/// root.first_child_mut().unwrap().make_branch(vec![4, 5]).unwrap();
+/// assert_eq!(block_on(traverse_async(&tree, TotalSize(0))), 700);
+/// ```
+///
+/// [`Visitor`]: trait.Visitor.html " "
+/// [`VisitorMut`]: trait.VisitorMut.html " "
+/// [`visit`]: #tymethod.visit " "
+/// [`traverse_async`]: fn.traverse_async.html " "
+/// [`traverse_async_from`]: fn.traverse_async_from.html " "
+pub trait AsyncVisitor: Sized {
+    /// The target type which will be traversed by the visitor.
+    type Target: Traversable;
+    /// The final value produced by the visitor.
+    type Output;
+    /// The future returned by [`visit`], which resolves to further directions for traversal and the visitor itself.
+    ///
+    /// [`visit`]: #tymethod.visit " "
+    type Future: Future<
+        Output = (
+            VisitorDirection<<Self::Target as Traversable>::Cursor, Self::Output>,
+            Self,
+        ),
+    >;
+    /// Visit the provided node, returning a future which resolves to further directions for traversal and gives back ownership of the visitor.
+    ///
+    /// # Panics
+    /// Required to panic if called after a `Stop` value has already been produced. May also panic for other reasons, as appropriate and specified by the documentation on the trait implementation.
+    fn visit(
+        self,
+        traversable: &Self::Target,
+        cursor: CursorResult<<Self::Target as Traversable>::Cursor>,
+    ) -> Self::Future;
+}
+
+/// Traverses the traversable from the root node until the end with an [`AsyncVisitor`], returning a future which resolves to the final result of the visitor.
+///
+/// # Panics
+/// The future will panic if the traversal reaches an invalid cursor.
+///
+/// [`AsyncVisitor`]: trait.AsyncVisitor.html " "
+#[allow(clippy::future_not_send)] // It's Send whenever the traversable, the visitor and its future are
+pub async fn traverse_async<T, V>(traversable: &T, visitor: V) -> V::Output
+where
+    T: Traversable,
+    V: AsyncVisitor<Target = T>,
+{
+    traverse_async_from(traversable, traversable.cursor_to_root(), visitor).await
+}
+/// Traverses the traversable from the specified starting point until the end with an [`AsyncVisitor`], returning a future which resolves to the final result of the visitor.
+///
+/// The future holds a borrow of the traversable across the points at which it waits for the visitor, so the traversable cannot be mutated until it resolves.
+///
+/// # Panics
+/// The future will panic if the traversal reaches an invalid cursor.
+///
+/// [`AsyncVisitor`]: trait.AsyncVisitor.html " "
+#[allow(clippy::future_not_send)] // It's Send whenever the traversable, the visitor and its future are
+pub async fn traverse_async_from<T, V>(
+    traversable: &T,
+    starting_cursor: T::Cursor,
+    mut visitor: V,
+) -> V::Output
+where
+    T: Traversable,
+    V: AsyncVisitor<Target = T>,
+{
+    let mut cursor = Ok(starting_cursor);
+    loop {
+        if let Ok(cursor) = &cursor {
+            check_cursor(traversable, cursor)
+                .unwrap_or_else(|error| panic!("invalid cursor: {:?}", error.cursor));
+        }
+        let (direction, returned_visitor) = visitor.visit(traversable, cursor.clone()).await;
+        visitor = returned_visitor;
+        match direction {
+            VisitorDirection::Stop(output) => return output,
+            direction => {
+                // Incorrectly driven cursors are handed to the visitor again, same as in `try_step`
+                if let Ok(current) = cursor {
+                    cursor = traversable.advance_cursor(current, direction);
+                }
+            }
+        }
+    }
+}
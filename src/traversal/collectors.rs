@@ -0,0 +1,272 @@
+//! Ready-made visitors for gathering simple information about trees.
+//!
+//! All of the visitors in this module perform a depth-first pre-order traversal of the subtree starting at the node the cursor was initially at, and work with any [`Traversable`].
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{
+//!     freeform_tree::FreeformTree,
+//!     traversal::collectors::{CollectLeaves, CountNodes, MaxDepth},
+//!     Traversable,
+//! };
+//!
+//! let mut tree = FreeformTree::<_>::new(1);
+//! let mut root = tree.root_mut();
+//! root.make_branch(vec![2, 3, 4]).unwrap();
+//! root.first_child_mut().unwrap().make_branch(vec![5, 6]).unwrap();
+//!
+//! assert_eq!(tree.traverse(CollectLeaves::new(Vec::new())), [5, 6, 3, 4]);
+//! assert_eq!(tree.traverse(CountNodes::new()), 6);
+//! assert_eq!(tree.traverse(MaxDepth::new()), 2);
+//! ```
+//!
+//! [`Traversable`]: ../trait.Traversable.html " "
+
+use core::{fmt::Debug, borrow::Borrow, marker::PhantomData};
+use crate::NodeValue;
+use super::{
+    algorithms::{next_in_preorder, depth_of},
+    Visitor,
+    Traversable,
+    VisitorDirection,
+    CursorResult,
+    CursorDirectionError,
+};
+
+/// A `Visitor` which clones the payloads of all leaf nodes into a collection, in pre-order.
+///
+/// The collection can be anything which implements `Extend`, such as a `Vec`, a `HashSet` or a `String`.
+#[derive(Clone, Debug)]
+pub struct CollectLeaves<T: Traversable, C> {
+    subtree_root: Option<T::Cursor>,
+    collection: Option<C>,
+}
+impl<T, C> CollectLeaves<T, C>
+where
+    T: Traversable,
+    T::Leaf: Clone,
+    C: Extend<T::Leaf>,
+{
+    /// Creates the visitor which will extend the specified collection.
+    pub fn new(collection: C) -> Self {
+        Self {
+            subtree_root: None,
+            collection: Some(collection),
+        }
+    }
+}
+impl<T, C> Visitor for CollectLeaves<T, C>
+where
+    T: Traversable,
+    T::Leaf: Clone,
+    C: Extend<T::Leaf>,
+{
+    type Target = T;
+    type Output = C;
+
+    fn visit<Cur>(
+        &mut self,
+        traversable: impl Borrow<Self::Target>,
+        cursor: CursorResult<Cur>,
+    ) -> VisitorDirection<Cur, Self::Output>
+    where
+        Cur: From<<Self::Target as Traversable>::Cursor>
+            + Into<<Self::Target as Traversable>::Cursor>
+            + Clone
+            + Debug
+            + Eq,
+    {
+        // We only ever use SetTo with valid cursors, so there's nothing to recover from.
+        let cursor = cursor.unwrap_or_else(CursorDirectionError::recover).into();
+        let traversable = traversable.borrow();
+        let collection = self
+            .collection
+            .as_mut()
+            .expect("the visitor has already finished its traversal");
+        if let NodeValue::Leaf(leaf) = traversable.value_of(&cursor) {
+            collection.extend(Some(leaf.clone()));
+        }
+        let subtree_root = self.subtree_root.get_or_insert_with(|| cursor.clone());
+        let collection = &mut self.collection;
+        next_in_preorder(traversable, &cursor, subtree_root).map_or_else(
+            || VisitorDirection::Stop(collection.take().unwrap_or_else(|| unreachable!())),
+            |next| VisitorDirection::SetTo(next.into()),
+        )
+    }
+}
+
+/// A `Visitor` which clones the payloads of all nodes into a collection, in pre-order.
+///
+/// The collection can be anything which implements `Extend` for [`NodeValue`]s of the payloads.
+///
+/// [`NodeValue`]: ../../enum.NodeValue.html " "
+#[derive(Clone, Debug)]
+pub struct CollectPayloads<T: Traversable, C> {
+    subtree_root: Option<T::Cursor>,
+    collection: Option<C>,
+}
+impl<T, C> CollectPayloads<T, C>
+where
+    T: Traversable,
+    T::Branch: Clone,
+    T::Leaf: Clone,
+    C: Extend<NodeValue<T::Branch, T::Leaf>>,
+{
+    /// Creates the visitor which will extend the specified collection.
+    pub fn new(collection: C) -> Self {
+        Self {
+            subtree_root: None,
+            collection: Some(collection),
+        }
+    }
+}
+impl<T, C> Visitor for CollectPayloads<T, C>
+where
+    T: Traversable,
+    T::Branch: Clone,
+    T::Leaf: Clone,
+    C: Extend<NodeValue<T::Branch, T::Leaf>>,
+{
+    type Target = T;
+    type Output = C;
+
+    fn visit<Cur>(
+        &mut self,
+        traversable: impl Borrow<Self::Target>,
+        cursor: CursorResult<Cur>,
+    ) -> VisitorDirection<Cur, Self::Output>
+    where
+        Cur: From<<Self::Target as Traversable>::Cursor>
+            + Into<<Self::Target as Traversable>::Cursor>
+            + Clone
+            + Debug
+            + Eq,
+    {
+        // Same as above.
+        let cursor = cursor.unwrap_or_else(CursorDirectionError::recover).into();
+        let traversable = traversable.borrow();
+        let collection = self
+            .collection
+            .as_mut()
+            .expect("the visitor has already finished its traversal");
+        let payload = match traversable.value_of(&cursor) {
+            NodeValue::Branch(branch) => NodeValue::Branch(branch.clone()),
+            NodeValue::Leaf(leaf) => NodeValue::Leaf(leaf.clone()),
+        };
+        collection.extend(Some(payload));
+        let subtree_root = self.subtree_root.get_or_insert_with(|| cursor.clone());
+        let collection = &mut self.collection;
+        next_in_preorder(traversable, &cursor, subtree_root).map_or_else(
+            || VisitorDirection::Stop(collection.take().unwrap_or_else(|| unreachable!())),
+            |next| VisitorDirection::SetTo(next.into()),
+        )
+    }
+}
+
+/// A `Visitor` which counts the nodes, including both branch and leaf nodes.
+#[derive(Clone, Debug)]
+pub struct CountNodes<T: Traversable> {
+    subtree_root: Option<T::Cursor>,
+    count: usize,
+}
+impl<T: Traversable> CountNodes<T> {
+    /// Creates the visitor.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            subtree_root: None,
+            count: 0,
+        }
+    }
+}
+impl<T: Traversable> Default for CountNodes<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T: Traversable> Visitor for CountNodes<T> {
+    type Target = T;
+    type Output = usize;
+
+    fn visit<C>(
+        &mut self,
+        traversable: impl Borrow<Self::Target>,
+        cursor: CursorResult<C>,
+    ) -> VisitorDirection<C, Self::Output>
+    where
+        C: From<<Self::Target as Traversable>::Cursor>
+            + Into<<Self::Target as Traversable>::Cursor>
+            + Clone
+            + Debug
+            + Eq,
+    {
+        // Same as above.
+        let cursor = cursor.unwrap_or_else(CursorDirectionError::recover).into();
+        let traversable = traversable.borrow();
+        self.count += 1;
+        let subtree_root = self.subtree_root.get_or_insert_with(|| cursor.clone());
+        next_in_preorder(traversable, &cursor, subtree_root).map_or_else(
+            || VisitorDirection::Stop(self.count),
+            |next| VisitorDirection::SetTo(next.into()),
+        )
+    }
+}
+
+/// A `Visitor` which finds the number of levels between the node the cursor was initially at and its deepest descendant, which is 0 if the node is a leaf.
+///
+/// The depth is only computed for leaf nodes, by counting their ancestors, which takes *O*(*n* × *d*) time in the worst case, where *n* is the number of nodes and *d* is the depth of the tree.
+#[derive(Clone, Debug)]
+pub struct MaxDepth<T: Traversable> {
+    subtree_root: Option<(T::Cursor, usize)>,
+    deepest: usize,
+    _phantom: PhantomData<fn(T)>,
+}
+impl<T: Traversable> MaxDepth<T> {
+    /// Creates the visitor.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            subtree_root: None,
+            deepest: 0,
+            _phantom: PhantomData,
+        }
+    }
+}
+impl<T: Traversable> Default for MaxDepth<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T: Traversable> Visitor for MaxDepth<T> {
+    type Target = T;
+    type Output = usize;
+
+    fn visit<C>(
+        &mut self,
+        traversable: impl Borrow<Self::Target>,
+        cursor: CursorResult<C>,
+    ) -> VisitorDirection<C, Self::Output>
+    where
+        C: From<<Self::Target as Traversable>::Cursor>
+            + Into<<Self::Target as Traversable>::Cursor>
+            + Clone
+            + Debug
+            + Eq,
+    {
+        // Same as above.
+        let cursor = cursor.unwrap_or_else(CursorDirectionError::recover).into();
+        let traversable = traversable.borrow();
+        let (subtree_root, root_depth) = self
+            .subtree_root
+            .get_or_insert_with(|| (cursor.clone(), depth_of(traversable, &cursor)));
+        if traversable.num_children_of(&cursor) == 0 {
+            let depth = depth_of(traversable, &cursor) - *root_depth;
+            self.deepest = self.deepest.max(depth);
+        }
+        let deepest = self.deepest;
+        next_in_preorder(traversable, &cursor, subtree_root).map_or_else(
+            || VisitorDirection::Stop(deepest),
+            |next| VisitorDirection::SetTo(next.into()),
+        )
+    }
+}
@@ -2,30 +2,84 @@
 //!
 //! The module is home to the following items:
 //! - [`Visitor`] and [`VisitorMut`] — two similar *traits for types which describe algorithms with state*
+//! - [`SimpleVisitor`] and [`SimpleVisitorMut`] — versions of those traits which are generic over the traversable, for visitors which work with any tree with certain payload types, along with the [`SimpleVisitorAdapter`] and [`SimpleVisitorMutAdapter`] wrappers which turn them into normal visitors
 //! - [`Traversable`] and its optional extension, [`TraversableMut`] — *traits for types which describe tree-like structures* which can be traversed by `Visitor` and `VisitorMut` algorithms
 //! - Implementations of ubiquitous algorithms for trees (see the [`algorithms`] module for more)
+//! - Ready-made visitors for collecting payloads and measuring trees (see the [`collectors`] module)
+//! - [`visitor_fn`] and [`visitor_fn_mut`], which wrap closures into visitors for one-off traversals
 //! - Niche [`TraverseIter`] and [`TraverseMutIter`] helpers, wrapping a [`Visitor`]/[`Traversable`] or [`VisitorMut`]/[`TraversableMut`] pair into an iterator interface
-//! - Helper types: [`Step`], [`VisitorDirection`] and [`CursorDirectionError`]
+//! - [`TraverseValuesIter`], which wraps a [`Visitor`] and a borrowed [`Traversable`] into an iterator over the nodes visited along the way, and [`TraverseMutRefIter`], a version of [`TraverseMutIter`] which borrows the [`TraversableMut`] instead of taking ownership of it
+//! - [`AsyncVisitor`], a version of [`Visitor`] which returns its directions as a future, and the [`traverse_async`] driver for it, available with the `async` feature
+//! - [`TraceVisitor`], which reports every step of another visitor for debugging and can stop traversals which run for too long
+//! - [`MultiCursor`], which keeps track of several positions in a traversable for algorithms which need more than one cursor
+//! - [`ResumableTraversal`], which runs a visitor over a tree in chunks of a limited number of steps and can be resumed from a [`Continuation`] after the tree was mutated
+//! - Helper types: [`Step`], [`VisitorDirection`], [`CursorDirectionError`], [`InvalidCursorError`] and [`TraversalTimeout`]
 //!
 //! [`algorithms`]: algorithms/index.html " "
+//! [`collectors`]: collectors/index.html " "
+//! [`visitor_fn`]: fn.visitor_fn.html " "
+//! [`visitor_fn_mut`]: fn.visitor_fn_mut.html " "
 //! [`Visitor`]: trait.Visitor.html " "
 //! [`VisitorMut`]: trait.VisitorMut.html " "
+//! [`SimpleVisitor`]: trait.SimpleVisitor.html " "
+//! [`SimpleVisitorMut`]: trait.SimpleVisitorMut.html " "
+//! [`SimpleVisitorAdapter`]: struct.SimpleVisitorAdapter.html " "
+//! [`SimpleVisitorMutAdapter`]: struct.SimpleVisitorMutAdapter.html " "
 //! [`Traversable`]: trait.Traversable.html " "
 //! [`TraversableMut`]: trait.TraversableMut.html " "
 //! [`TraverseIter`]: struct.TraverseIter.html " "
 //! [`TraverseMutIter`]: struct.TraverseMutIter.html " "
+//! [`TraverseValuesIter`]: struct.TraverseValuesIter.html " "
+//! [`TraverseMutRefIter`]: struct.TraverseMutRefIter.html " "
 //! [`Step`]: enum.Step.html " "
 //! [`VisitorDirection`]: enum.VisitorDirection.html " "
 //! [`CursorDirectionError`]: enum.CursorDirectionError.html " "
+//! [`InvalidCursorError`]: struct.InvalidCursorError.html " "
+//! [`TraceVisitor`]: struct.TraceVisitor.html " "
+//! [`AsyncVisitor`]: trait.AsyncVisitor.html " "
+//! [`traverse_async`]: fn.traverse_async.html " "
+//! [`MultiCursor`]: struct.MultiCursor.html " "
+//! [`ResumableTraversal`]: struct.ResumableTraversal.html " "
+//! [`Continuation`]: struct.Continuation.html " "
+//! [`TraversalTimeout`]: struct.TraversalTimeout.html " "
 
 pub mod algorithms;
+pub mod collectors;
+mod visitor_fn;
+pub use visitor_fn::*;
+mod trace;
+pub use trace::*;
+mod simple;
+pub use simple::*;
+#[cfg(feature = "alloc")]
+mod multi_cursor;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub use multi_cursor::*;
+#[cfg(feature = "alloc")]
+mod resumable;
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub use resumable::*;
+#[cfg(feature = "async")]
+mod asynchronous;
+#[cfg(feature = "async")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "async")))]
+pub use asynchronous::*;
 
 use core::{
     iter::FusedIterator,
     fmt::{self, Formatter, Debug, Display},
     borrow::{Borrow, BorrowMut},
 };
-use crate::{NodeValue, TryRemoveLeafError, TryRemoveBranchError, TryRemoveChildrenError};
+use crate::{
+    NodeValue,
+    TryRemoveLeafError,
+    TryRemoveBranchError,
+    TryRemoveChildrenError,
+    TryAddChildError,
+    MakeBranchError,
+};
 
 /// Iterator-like structures which control a traversable tree's cursor and use it to read information from the tree.
 ///
@@ -57,7 +111,12 @@ pub trait Visitor {
 ///
 /// Mutating visitors require exclusive mutable access to the tree they are visiting. If you only need to read data from the tree instead of mutating it or if the nodes use interior mutability, use [`Visitor`].
 ///
+/// Since the traversable is passed into the visitor by mutable borrow, visitors can also change the structure of the tree. Adding nodes via [`try_add_child`] and [`try_add_children`] never invalidates cursors, so the traversal can continue as usual afterwards. Removing nodes, on the other hand, might invalidate the cursor of the visited node, in which case the visitor should use [`SetTo`] to point the traversable to a valid node.
+///
 /// [`Visitor`]: trait.Visitor.html " "
+/// [`try_add_child`]: trait.TraversableMut.html#method.try_add_child " "
+/// [`try_add_children`]: trait.TraversableMut.html#method.try_add_children " "
+/// [`SetTo`]: enum.VisitorDirection.html#variant.SetTo " "
 pub trait VisitorMut {
     /// The target type which will be traversed by the visitor.
     type Target: TraversableMut;
@@ -81,12 +140,59 @@ pub trait VisitorMut {
         M: BorrowMut<Self::Target>;
 }
 /// The direction in which a visitor wishes to go after visiting a node.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{BinaryTree, Traversable, traversal::VisitorDirection};
+///
+/// let mut tree = BinaryTree::<_>::new(0);
+/// tree.root_mut().make_branch(1, None).unwrap();
+/// tree.root_mut().left_child_mut().unwrap().make_branch(2, None).unwrap();
+/// let root = tree.cursor_to_root();
+/// let child = tree.nth_child_of(&root, 0).unwrap();
+/// let grandchild = tree.nth_child_of(&child, 0).unwrap();
+/// let advance = |direction: VisitorDirection<_, ()>| tree.advance_cursor(grandchild, direction);
+/// assert_eq!(advance(VisitorDirection::NthAncestor(1)), Ok(child));
+/// assert_eq!(advance(VisitorDirection::NthAncestor(2)), Ok(root));
+/// // There is nothing above the root node.
+/// assert!(advance(VisitorDirection::NthAncestor(3)).is_err());
+/// assert_eq!(advance(VisitorDirection::Root), Ok(root));
+/// ```
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum VisitorDirection<C: Clone + Debug + Eq, V> {
     /// Visit the parent of the node which has been visited.
     Parent,
+    /// Visit the ancestor of the node which has been visited which is the specified number of levels above it: `NthAncestor(1)` is the parent, `NthAncestor(2)` is the parent of the parent, and so on, while `NthAncestor(0)` stays on the node itself.
+    ///
+    /// Fails without moving the cursor if the node does not have that many ancestors.
+    NthAncestor(u32),
+    /// Visit the root node.
+    ///
+    /// Unlike [`SetTo`], this does not require the visitor to know the cursor of the root node.
+    ///
+    /// [`SetTo`]: #variant.SetTo " "
+    Root,
     /// Visit the sibling of the node which has been visited.
     NextSibling,
+    /// Visit the previous sibling of the node which has been visited.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Quadtree, Traversable, traversal::VisitorDirection};
+    ///
+    /// let mut tree = Quadtree::<_>::new(0);
+    /// tree.root_mut().make_branch([1, 2, 3, 4]).unwrap();
+    /// let root = tree.cursor_to_root();
+    /// let advance = |cursor, direction: VisitorDirection<_, ()>| tree.advance_cursor(cursor, direction);
+    /// let second = tree.nth_child_of(&root, 1).unwrap();
+    /// let third = advance(second, VisitorDirection::NextSibling).unwrap();
+    /// assert_eq!(*tree.value_of(&third).into_inner(), 3);
+    /// assert_eq!(advance(third, VisitorDirection::PrevSibling), Ok(second));
+    /// // The first child has no previous sibling.
+    /// let first = tree.nth_child_of(&root, 0).unwrap();
+    /// assert!(advance(first, VisitorDirection::PrevSibling).is_err());
+    /// ```
+    PrevSibling,
     /// Visit the `n`-th child of the node which has been visited.
     Child(u32),
     /// Visit a specific cursor.
@@ -105,7 +211,10 @@ pub trait Traversable: Sized {
     type Branch;
     /// The type for the cursor which will be used for keeping track of the traversed nodes.
     ///
-    /// Must be very cheaply clonable, but not required to be `Copy`. Cursors are not guaranteed to be stable — relying on their stability for memory safety might cause undefined behavior. The only case in which those keys should actually be stored for extended periods of time is when a visitor needs to remember node locations, since it's a logic error to interleave `step`/`step_mut` calls for read-only and mutating visitors or for multiple mutating visitors; still, visitors should check for key error conditions and panic if those happen.
+    /// Must be very cheaply clonable, but not required to be `Copy`. Cursors are not guaranteed to be stable — relying on their stability for memory safety might cause undefined behavior. The only case in which those keys should actually be stored for extended periods of time is when a visitor needs to remember node locations, since it's a logic error to interleave `step`/`step_mut` calls for read-only and mutating visitors or for multiple mutating visitors; still, visitors should check for key error conditions and panic if those happen. Trees which implement [`Tree`] provide [`NodeHandle`]s for remembering nodes across modifications, which are checked before use.
+    ///
+    /// [`Tree`]: ../tree/trait.Tree.html " "
+    /// [`NodeHandle`]: ../tree/struct.NodeHandle.html " "
     type Cursor: Clone + Debug + Eq;
 
     /// Advances the specified cursor according to the specified directions from the visitor.
@@ -140,34 +249,121 @@ pub trait Traversable: Sized {
     /// # Panics
     /// Required to panic if cursor value is invalid.
     fn nth_child_of(&self, cursor: &Self::Cursor, child_num: usize) -> Option<Self::Cursor>;
+    /// Returns whether the specified cursor points to a node in the traversable.
+    ///
+    /// The non-panicking `try_` counterparts of the accessor methods use this to check the cursor beforehand. By default, every cursor is assumed to be valid, which makes those methods panic on invalid cursors just like the accessors themselves; implementors should override this if they can check the validity of a cursor.
+    ///
+    /// The trees in this crate check whether the storage contains the key. Sparse storage cannot tell a hole left by a removed node apart from a node this way, so a cursor to a removed node is still considered valid there and using it panics regardless.
+    fn contains_cursor(&self, _cursor: &Self::Cursor) -> bool {
+        true
+    }
+    /// Returns a by-reference `NodeValue` of the node at the specified cursor, or an error if the cursor is invalid.
+    ///
+    /// # Errors
+    /// Will fail if the cursor does not point to a node in the traversable.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{freeform_tree::VecFreeformTree, Traversable, TraversableMut};
+    ///
+    /// let mut tree = VecFreeformTree::<_>::new(0);
+    /// tree.root_mut().make_branch(vec![1]).unwrap();
+    /// let child = tree.nth_child_of(&tree.cursor_to_root(), 0).unwrap();
+    /// tree.try_remove_leaf(&child, |x| x).unwrap();
+    /// // The cursor to the removed child is not valid anymore.
+    /// assert!(tree.try_value_of(&child).is_err());
+    /// assert_eq!(*tree.try_value_of(&tree.cursor_to_root()).unwrap().into_inner(), 0);
+    /// ```
+    #[allow(clippy::type_complexity)] // same as in TraversableMut
+    fn try_value_of(
+        &self,
+        cursor: &Self::Cursor,
+    ) -> Result<NodeValue<&'_ Self::Branch, &'_ Self::Leaf>, InvalidCursorError<Self::Cursor>> {
+        check_cursor(self, cursor)?;
+        Ok(self.value_of(cursor))
+    }
+    /// Returns a cursor to the parent of the node at the specified cursor, `None` if that node is the root node, or an error if the cursor is invalid.
+    ///
+    /// # Errors
+    /// Will fail if the cursor does not point to a node in the traversable.
+    fn try_parent_of(
+        &self,
+        cursor: &Self::Cursor,
+    ) -> Result<Option<Self::Cursor>, InvalidCursorError<Self::Cursor>> {
+        check_cursor(self, cursor)?;
+        Ok(self.parent_of(cursor))
+    }
+    /// Returns the number of children of the node at the specified cursor, or an error if the cursor is invalid.
+    ///
+    /// # Errors
+    /// Will fail if the cursor does not point to a node in the traversable.
+    fn try_num_children_of(
+        &self,
+        cursor: &Self::Cursor,
+    ) -> Result<usize, InvalidCursorError<Self::Cursor>> {
+        check_cursor(self, cursor)?;
+        Ok(self.num_children_of(cursor))
+    }
+    /// Returns a cursor to the *`n`*th child of the node at the specified cursor, `None` if the child at that index does not exist, or an error if the cursor is invalid.
+    ///
+    /// # Errors
+    /// Will fail if the cursor does not point to a node in the traversable.
+    fn try_nth_child_of(
+        &self,
+        cursor: &Self::Cursor,
+        child_num: usize,
+    ) -> Result<Option<Self::Cursor>, InvalidCursorError<Self::Cursor>> {
+        check_cursor(self, cursor)?;
+        Ok(self.nth_child_of(cursor, child_num))
+    }
 
     /// Performs one step of the visitor from the specified cursor, returning either the cursor for the next step or the final result of the visitor if it ended.
     ///
     /// It's a logic error to interleave calls to step through a `Visitor` with equivalent calls for one or more `VisitorMut` on the same traversable. This cannot invoke undefined behavior, but may produce unexpected results, such as infinite loops or panicking.
     ///
     /// # Panics
-    /// The visitor itself may panic, but otherwise the method should not add any panics on its own.
-    fn step<V>(
+    /// Will panic if the cursor is invalid. See [`try_step`] for a version which returns an error instead. The visitor itself may panic, but otherwise the method should not add any panics on its own.
+    ///
+    /// [`try_step`]: #method.try_step " "
+    fn step<V>(&self, visitor: V, cursor: CursorResult<Self::Cursor>) -> Step<Self::Cursor, V::Output>
+    where
+        V: Visitor,
+        for<'a> &'a Self: Borrow<V::Target>,
+        Self::Cursor:
+            From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+    {
+        self.try_step(visitor, cursor)
+            .unwrap_or_else(|error| panic!("invalid cursor: {:?}", error.cursor))
+    }
+    /// Performs one step of the visitor from the specified cursor, returning either the cursor for the next step or the final result of the visitor if it ended, or an error if the cursor is invalid.
+    ///
+    /// # Errors
+    /// Will fail if the cursor does not point to a node in the traversable, in which case the visitor is not called.
+    #[allow(clippy::type_complexity)] // same as in TraversableMut
+    fn try_step<V>(
         &self,
         mut visitor: V,
         cursor: CursorResult<Self::Cursor>,
-    ) -> Step<Self::Cursor, V::Output>
+    ) -> Result<Step<Self::Cursor, V::Output>, InvalidCursorError<Self::Cursor>>
     where
         V: Visitor,
         for<'a> &'a Self: Borrow<V::Target>,
         Self::Cursor:
             From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
     {
-        match visitor.visit(self, cursor.clone()) {
+        if let Ok(cursor) = &cursor {
+            check_cursor(self, cursor)?;
+        }
+        Ok(match visitor.visit(self, cursor.clone()) {
             VisitorDirection::Stop(val) => Step::End(val),
             other => Step::NextCursor(self.advance_cursor(
                 match cursor {
                     Ok(val) => val,
-                    Err(err) => return Step::NextCursor(Err(err)),
+                    Err(err) => return Ok(Step::NextCursor(Err(err))),
                 },
                 other,
             )),
-        }
+        })
     }
     /// Traverses the traversable from the root node until the end, returning the final result of the visitor.
     fn traverse<V>(&self, visitor: V) -> V::Output
@@ -180,7 +376,49 @@ pub trait Traversable: Sized {
         self.traverse_from(self.cursor_to_root(), visitor)
     }
     /// Traverses the traversable from the specified starting point until the end, returning the final result of the visitor.
-    fn traverse_from<V>(&self, starting_cursor: Self::Cursor, mut visitor: V) -> V::Output
+    ///
+    /// # Panics
+    /// Will panic if the traversal reaches an invalid cursor. See [`try_traverse_from`] for a version which returns an error instead.
+    ///
+    /// [`try_traverse_from`]: #method.try_traverse_from " "
+    fn traverse_from<V>(&self, starting_cursor: Self::Cursor, visitor: V) -> V::Output
+    where
+        V: Visitor,
+        for<'a> &'a Self: Borrow<V::Target>,
+        Self::Cursor:
+            From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+    {
+        self.try_traverse_from(starting_cursor, visitor)
+            .unwrap_or_else(|error| panic!("invalid cursor: {:?}", error.cursor))
+    }
+    /// Traverses the traversable from the root node until the end with a [`SimpleVisitor`], returning the final result of the visitor.
+    ///
+    /// [`SimpleVisitor`]: trait.SimpleVisitor.html " "
+    fn traverse_simple<V: SimpleVisitor<Self>>(&self, visitor: V) -> V::Output {
+        self.traverse_simple_from(self.cursor_to_root(), visitor)
+    }
+    /// Traverses the traversable from the specified starting point until the end with a [`SimpleVisitor`], returning the final result of the visitor.
+    ///
+    /// # Panics
+    /// Will panic if the traversal reaches an invalid cursor.
+    ///
+    /// [`SimpleVisitor`]: trait.SimpleVisitor.html " "
+    fn traverse_simple_from<V: SimpleVisitor<Self>>(
+        &self,
+        starting_cursor: Self::Cursor,
+        visitor: V,
+    ) -> V::Output {
+        self.traverse_from(starting_cursor, SimpleVisitorAdapter::new(visitor))
+    }
+    /// Traverses the traversable from the specified starting point until the end, returning the final result of the visitor, or an error if the traversal reaches an invalid cursor.
+    ///
+    /// # Errors
+    /// Will fail if the starting cursor or a cursor which the traversal arrives at does not point to a node in the traversable. The traversal stops right away in such a case.
+    fn try_traverse_from<V>(
+        &self,
+        starting_cursor: Self::Cursor,
+        mut visitor: V,
+    ) -> Result<V::Output, InvalidCursorError<Self::Cursor>>
     where
         V: Visitor,
         for<'a> &'a Self: Borrow<V::Target>,
@@ -189,11 +427,61 @@ pub trait Traversable: Sized {
     {
         let mut cursor = Ok(starting_cursor);
         loop {
+            match self.try_step(&mut visitor, cursor.clone())? {
+                Step::NextCursor(c) => cursor = c,
+                Step::End(f) => return Ok(f),
+            }
+        }
+    }
+    /// Traverses the traversable from the specified starting point until the end, returning the final result of the visitor, or an error if the visitor does not finish within the specified number of steps.
+    ///
+    /// Visitors which never stop, for example because they keep giving directions which cannot be followed or move back and forth between the same nodes, would make [`traverse_from`] hang forever. Limiting the number of steps makes such visitors fail loudly instead. See [`TraceVisitor`] for a way to find out what the visitor is doing.
+    ///
+    /// # Errors
+    /// Will fail if the visitor has not finished after `max_steps` steps.
+    ///
+    /// # Panics
+    /// Will panic if the traversal reaches an invalid cursor.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{
+    ///     freeform_tree::FreeformTree,
+    ///     traversal::{visitor_fn, TraversalTimeout, VisitorDirection},
+    ///     Traversable,
+    /// };
+    ///
+    /// let mut tree = FreeformTree::<_>::new(1);
+    /// tree.root_mut().make_branch(vec![2, 3]).unwrap();
+    ///
+    /// // Forgets to stop when it can't go any further.
+    /// let visitor = visitor_fn(|_, _| VisitorDirection::<_, ()>::Child(0));
+    /// let result = tree.traverse_with_limit(tree.cursor_to_root(), visitor, 100);
+    /// assert_eq!(result, Err(TraversalTimeout { steps: 100 }));
+    /// ```
+    ///
+    /// [`traverse_from`]: #method.traverse_from " "
+    /// [`TraceVisitor`]: struct.TraceVisitor.html " "
+    fn traverse_with_limit<V>(
+        &self,
+        starting_cursor: Self::Cursor,
+        mut visitor: V,
+        max_steps: usize,
+    ) -> Result<V::Output, TraversalTimeout>
+    where
+        V: Visitor,
+        for<'a> &'a Self: Borrow<V::Target>,
+        Self::Cursor:
+            From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+    {
+        let mut cursor = Ok(starting_cursor);
+        for _ in 0..max_steps {
             match self.step(&mut visitor, cursor.clone()) {
                 Step::NextCursor(c) => cursor = c,
-                Step::End(f) => return f,
+                Step::End(f) => return Ok(f),
             }
         }
+        Err(TraversalTimeout { steps: max_steps })
     }
 }
 
@@ -205,6 +493,8 @@ pub trait TraversableMut: Traversable {
     ///
     /// [`Empty`]: https://doc.rust-lang.org/std/iter/struct.Empty.html " "
     const CAN_PACK_CHILDREN: bool = false;
+    /// Whether the `try_add_child` method is implemented. This is `true` for trees which have a variable number of children for branches and `false` for ones which don't.
+    const CAN_ADD_INDIVIDUAL_CHILDREN: bool = false;
     /// A container for the leaf children of a branch node.
     type PackedChildren: IntoIterator<Item = Self::Leaf>;
 
@@ -308,16 +598,119 @@ pub trait TraversableMut: Traversable {
     ) -> Result<Self::PackedChildren, TryRemoveChildrenError> {
         unimplemented!("packing children is not supported by this traversable")
     }
+    /// Attempts to add a leaf child to the node at the specified cursor, returning the cursor to the new child. If the node was a leaf node, it's converted into a branch node, the value for which is provided by the specified closure (the previous value is passed into the closure).
+    ///
+    /// Adding nodes never invalidates existing cursors, which means that this method can be freely used by a [`VisitorMut`] on the traversable it receives: the cursor of the visited node stays valid and the traversal will continue from it as usual. The returned cursor can be used to immediately visit the new child with [`SetTo`].
+    ///
+    /// By default, this method always fails with `CannotAddIndividualChildren`. Implementors should set `CAN_ADD_INDIVIDUAL_CHILDREN` to `true` if they override it.
+    ///
+    /// # Errors
+    /// Will fail in the following scenarios:
+    /// - The node already had the maximum number of children it can have.
+    /// - The tree does not allow adding individual children and only allows converting leaf nodes into branch nodes with all children at once.
+    ///
+    /// In both cases, the provided payload for the child is returned back to the caller.
+    ///
+    /// # Panics
+    /// Required to panic if cursor value is invalid.
+    ///
+    /// # Example
+    /// ```rust
+    /// use core::{borrow::BorrowMut, fmt::Debug};
+    /// use charcoal::{
+    ///     freeform_tree::FreeformTree,
+    ///     traversal::{CursorResult, VisitorDirection},
+    ///     Traversable,
+    ///     TraversableMut,
+    ///     VisitorMut,
+    /// };
+    ///
+    /// // A visitor which adds a child to every node on its way down, stopping after a certain depth.
+    /// struct Grow {
+    ///     remaining: u32,
+    /// }
+    /// impl VisitorMut for Grow {
+    ///     type Target = FreeformTree<u32>;
+    ///     type Output = ();
+    ///
+    ///     fn visit_mut<C, M>(
+    ///         &mut self,
+    ///         mut traversable: M,
+    ///         cursor: CursorResult<C>,
+    ///     ) -> (VisitorDirection<C, Self::Output>, M)
+    ///     where
+    ///         C: From<usize> + Into<usize> + Clone + Debug + Eq,
+    ///         M: BorrowMut<Self::Target>,
+    ///     {
+    ///         if self.remaining == 0 {
+    ///             return (VisitorDirection::Stop(()), traversable);
+    ///         }
+    ///         self.remaining -= 1;
+    ///         let cursor = cursor.expect("no invalid directions are ever given");
+    ///         let tree = traversable.borrow_mut();
+    ///         tree.try_add_child(&cursor.into(), self.remaining, |x| x)
+    ///             .expect("freeform trees can always add children");
+    ///         (VisitorDirection::Child(0), traversable)
+    ///     }
+    /// }
+    ///
+    /// let mut tree = FreeformTree::<_>::new(3);
+    /// tree.traverse_mut(Grow { remaining: 3 });
+    /// // The tree now is a chain of 4 nodes.
+    /// assert_eq!(tree.num_nodes(), 4);
+    /// let mut cursor = tree.cursor_to_root();
+    /// for expected in (0..=3).rev() {
+    ///     assert_eq!(*tree.value_of(&cursor).into_inner(), expected);
+    ///     cursor = tree.nth_child_of(&cursor, 0).unwrap_or(cursor);
+    /// }
+    /// ```
+    ///
+    /// [`VisitorMut`]: trait.VisitorMut.html " "
+    /// [`SetTo`]: enum.VisitorDirection.html#variant.SetTo " "
+    fn try_add_child<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        _cursor: &Self::Cursor,
+        child_payload: Self::Leaf,
+        _leaf_to_branch: LtB,
+    ) -> Result<Self::Cursor, TryAddChildError<Self::Leaf>> {
+        Err(TryAddChildError::CannotAddIndividualChildren { child_payload })
+    }
+    /// Attempts to convert a leaf node into a branch node with the specified packed children, the value for which is provided by the specified closure (the previous value is passed into the closure).
+    ///
+    /// As with [`try_add_child`], adding nodes never invalidates existing cursors, so this method can be freely used by a [`VisitorMut`].
+    ///
+    /// By default, this method is [`unimplemented!`]. If `CAN_PACK_CHILDREN` is `true`, then it is a logic error to leave it in that state, and the implementor should instead write a proper implementation of this method.
+    ///
+    /// # Errors
+    /// Will fail if the node already was a branch node. In such a case, the provided values for the children are returned back to the caller.
+    ///
+    /// # Panics
+    /// Required to panic if cursor value is invalid.
+    ///
+    /// [`try_add_child`]: #method.try_add_child " "
+    /// [`VisitorMut`]: trait.VisitorMut.html " "
+    /// [`unimplemented!`]: https://doc.rust-lang.org/std/macro.unimplemented.html " "
+    #[allow(clippy::type_complexity)] // same here
+    fn try_add_children<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        _cursor: &Self::Cursor,
+        _children: Self::PackedChildren,
+        _leaf_to_branch: LtB,
+    ) -> Result<(), MakeBranchError<Self::Leaf, Self::PackedChildren>> {
+        unimplemented!("packing children is not supported by this traversable")
+    }
 
     /// Performs one step of the mutating visitor from the specified cursor, returning either the cursor for the next step or the final result of the visitor if it ended.
     ///
     /// It's a logic error to interleave calls to step through a `VisitorMut` with equivalent calls for another `VisitorMut` or a `Visitor` on the same traversable. This cannot invoke undefined behavior, but may produce unexpected results, such as infinite loops or panicking.
     ///
     /// # Panics
-    /// The visitor itself may panic, but otherwise the method should not add any panics on its own.
+    /// Will panic if the cursor is invalid. See [`try_step_mut`] for a version which returns an error instead. The visitor itself may panic, but otherwise the method should not add any panics on its own.
+    ///
+    /// [`try_step_mut`]: #method.try_step_mut " "
     fn step_mut<V: VisitorMut>(
         &mut self,
-        mut visitor: V,
+        visitor: V,
         cursor: CursorResult<Self::Cursor>,
     ) -> Step<Self::Cursor, V::Output>
     where
@@ -325,16 +718,53 @@ pub trait TraversableMut: Traversable {
         Self::Cursor:
             From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
     {
+        self.try_step_mut(visitor, cursor)
+            .unwrap_or_else(|error| panic!("invalid cursor: {:?}", error.cursor))
+    }
+    /// Performs one step of the mutating visitor from the specified cursor, returning either the cursor for the next step or the final result of the visitor if it ended, or an error if the cursor is invalid.
+    ///
+    /// If the visitor removes the node it was on, it has to use [`SetTo`] to point the traversable to a valid node.
+    ///
+    /// # Errors
+    /// Will fail in the following scenarios:
+    /// - The cursor does not point to a node in the traversable. The visitor is not called in such a case.
+    /// - The visitor removed the node it was on and did not use `SetTo` to point the traversable to a valid node.
+    ///
+    /// [`SetTo`]: enum.VisitorDirection.html#variant.SetTo " "
+    #[allow(clippy::type_complexity)] // same here
+    fn try_step_mut<V: VisitorMut>(
+        &mut self,
+        mut visitor: V,
+        cursor: CursorResult<Self::Cursor>,
+    ) -> Result<Step<Self::Cursor, V::Output>, InvalidCursorError<Self::Cursor>>
+    where
+        for<'a> &'a mut Self: BorrowMut<V::Target>,
+        Self::Cursor:
+            From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+    {
+        if let Ok(cursor) = &cursor {
+            check_cursor(self, cursor)?;
+        }
         let (directions, borrow) = visitor.visit_mut(self, cursor.clone());
+        let directions = match directions {
+            VisitorDirection::Stop(val) => return Ok(Step::End(val)),
+            other => other,
+        };
+        let cursor = match cursor {
+            Ok(val) => val,
+            Err(err) => return Ok(Step::NextCursor(Err(err))),
+        };
+        if borrow.contains_cursor(&cursor) {
+            return Ok(Step::NextCursor(borrow.advance_cursor(cursor, directions)));
+        }
+        // The visitor removed the node it was on, so the only direction we can follow is an
+        // explicit jump to another node
         match directions {
-            VisitorDirection::Stop(val) => Step::End(val),
-            other => Step::NextCursor(borrow.advance_cursor(
-                match cursor {
-                    Ok(val) => val,
-                    Err(err) => return Step::NextCursor(Err(err)),
-                },
-                other,
-            )),
+            VisitorDirection::SetTo(new_cursor) => {
+                check_cursor(&*borrow, &new_cursor)?;
+                Ok(Step::NextCursor(Ok(new_cursor)))
+            }
+            _ => Err(InvalidCursorError { cursor }),
         }
     }
     /// *Mutably* traverses the traversable from the root node until the end, returning the final result of the visitor.
@@ -347,11 +777,52 @@ pub trait TraversableMut: Traversable {
         self.traverse_mut_from(self.cursor_to_root(), visitor)
     }
     /// *Mutably* traverses the traversable from the specified starting point until the end, returning the final result of the visitor.
+    ///
+    /// # Panics
+    /// Will panic if the traversal reaches an invalid cursor. See [`try_traverse_mut_from`] for a version which returns an error instead.
+    ///
+    /// [`try_traverse_mut_from`]: #method.try_traverse_mut_from " "
     fn traverse_mut_from<V: VisitorMut>(
         &mut self,
         starting_cursor: Self::Cursor,
-        mut visitor: V,
+        visitor: V,
     ) -> V::Output
+    where
+        for<'a> &'a mut Self: BorrowMut<V::Target>,
+        Self::Cursor:
+            From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+    {
+        self.try_traverse_mut_from(starting_cursor, visitor)
+            .unwrap_or_else(|error| panic!("invalid cursor: {:?}", error.cursor))
+    }
+    /// *Mutably* traverses the traversable from the root node until the end with a [`SimpleVisitorMut`], returning the final result of the visitor.
+    ///
+    /// [`SimpleVisitorMut`]: trait.SimpleVisitorMut.html " "
+    fn traverse_simple_mut<V: SimpleVisitorMut<Self>>(&mut self, visitor: V) -> V::Output {
+        self.traverse_simple_mut_from(self.cursor_to_root(), visitor)
+    }
+    /// *Mutably* traverses the traversable from the specified starting point until the end with a [`SimpleVisitorMut`], returning the final result of the visitor.
+    ///
+    /// # Panics
+    /// Will panic if the traversal reaches an invalid cursor.
+    ///
+    /// [`SimpleVisitorMut`]: trait.SimpleVisitorMut.html " "
+    fn traverse_simple_mut_from<V: SimpleVisitorMut<Self>>(
+        &mut self,
+        starting_cursor: Self::Cursor,
+        visitor: V,
+    ) -> V::Output {
+        self.traverse_mut_from(starting_cursor, SimpleVisitorMutAdapter::new(visitor))
+    }
+    /// *Mutably* traverses the traversable from the specified starting point until the end, returning the final result of the visitor, or an error if the traversal reaches an invalid cursor.
+    ///
+    /// # Errors
+    /// Will fail if the starting cursor or a cursor which the traversal arrives at does not point to a node in the traversable, including the case when the visitor removes the node it was on without pointing the traversable to a valid node. The traversal stops right away in such a case.
+    fn try_traverse_mut_from<V: VisitorMut>(
+        &mut self,
+        starting_cursor: Self::Cursor,
+        mut visitor: V,
+    ) -> Result<V::Output, InvalidCursorError<Self::Cursor>>
     where
         for<'a> &'a mut Self: BorrowMut<V::Target>,
         Self::Cursor:
@@ -359,11 +830,42 @@ pub trait TraversableMut: Traversable {
     {
         let mut cursor = Ok(starting_cursor);
         loop {
+            match self.try_step_mut(&mut visitor, cursor.clone())? {
+                Step::NextCursor(c) => cursor = c,
+                Step::End(f) => return Ok(f),
+            }
+        }
+    }
+    /// *Mutably* traverses the traversable from the specified starting point until the end, returning the final result of the visitor, or an error if the visitor does not finish within the specified number of steps.
+    ///
+    /// See [`traverse_with_limit`] for more on why this is useful.
+    ///
+    /// # Errors
+    /// Will fail if the visitor has not finished after `max_steps` steps.
+    ///
+    /// # Panics
+    /// Will panic if the traversal reaches an invalid cursor.
+    ///
+    /// [`traverse_with_limit`]: trait.Traversable.html#method.traverse_with_limit " "
+    fn traverse_mut_with_limit<V: VisitorMut>(
+        &mut self,
+        starting_cursor: Self::Cursor,
+        mut visitor: V,
+        max_steps: usize,
+    ) -> Result<V::Output, TraversalTimeout>
+    where
+        for<'a> &'a mut Self: BorrowMut<V::Target>,
+        Self::Cursor:
+            From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+    {
+        let mut cursor = Ok(starting_cursor);
+        for _ in 0..max_steps {
             match self.step_mut(&mut visitor, cursor.clone()) {
                 Step::NextCursor(c) => cursor = c,
-                Step::End(f) => return f,
+                Step::End(f) => return Ok(f),
             }
         }
+        Err(TraversalTimeout { steps: max_steps })
     }
 }
 
@@ -401,6 +903,81 @@ impl<C: Clone + Debug + Eq> Display for CursorDirectionError<C> {
 #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
 impl<C: Clone + Debug + Eq> std::error::Error for CursorDirectionError<C> {}
 
+/// The error returned by traversables when a cursor does not point to a node, typically because the node was removed.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct InvalidCursorError<C: Clone + Debug + Eq> {
+    /// The invalid cursor.
+    pub cursor: C,
+}
+impl<C: Clone + Debug + Eq> Display for InvalidCursorError<C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad("the cursor does not point to a node in the traversable")
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl<C: Clone + Debug + Eq> std::error::Error for InvalidCursorError<C> {}
+
+/// The error returned when a traversal does not finish within the maximum number of steps which it was given, which usually means that the visitor is stuck in a loop.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TraversalTimeout {
+    /// The number of steps which were performed before the traversal was stopped.
+    pub steps: usize,
+}
+impl Display for TraversalTimeout {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "the traversal did not finish within {} steps", self.steps)
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl std::error::Error for TraversalTimeout {}
+
+/// Converts the cursor type of a `CursorResult`, including the one of the error.
+fn convert_cursor_result<C1, C2>(cursor: CursorResult<C1>) -> CursorResult<C2>
+where
+    C1: Into<C2> + Clone + Debug + Eq,
+    C2: Clone + Debug + Eq,
+{
+    cursor.map(Into::into).map_err(|e| CursorDirectionError {
+        previous_state: e.previous_state.into(),
+    })
+}
+/// Converts the cursor of `SetTo` and the final value of `Stop` with the specified closures, leaving the other directions unchanged.
+fn map_direction<C1, C2, V1, V2>(
+    direction: VisitorDirection<C1, V1>,
+    map_cursor: impl FnOnce(C1) -> C2,
+    map_value: impl FnOnce(V1) -> V2,
+) -> VisitorDirection<C2, V2>
+where
+    C1: Clone + Debug + Eq,
+    C2: Clone + Debug + Eq,
+{
+    match direction {
+        VisitorDirection::Parent => VisitorDirection::Parent,
+        VisitorDirection::NthAncestor(n) => VisitorDirection::NthAncestor(n),
+        VisitorDirection::Root => VisitorDirection::Root,
+        VisitorDirection::NextSibling => VisitorDirection::NextSibling,
+        VisitorDirection::PrevSibling => VisitorDirection::PrevSibling,
+        VisitorDirection::Child(n) => VisitorDirection::Child(n),
+        VisitorDirection::SetTo(cursor) => VisitorDirection::SetTo(map_cursor(cursor)),
+        VisitorDirection::Stop(value) => VisitorDirection::Stop(map_value(value)),
+    }
+}
+/// Returns an error if the cursor does not point to a node in the traversable.
+fn check_cursor<T: Traversable>(
+    traversable: &T,
+    cursor: &T::Cursor,
+) -> Result<(), InvalidCursorError<T::Cursor>> {
+    if traversable.contains_cursor(cursor) {
+        Ok(())
+    } else {
+        Err(InvalidCursorError {
+            cursor: cursor.clone(),
+        })
+    }
+}
+
 /// An iterator which groups a [`Traversable`] and a [`Visitor`], performing one step with each iteration.
 ///
 /// The iterator produces values of type `Option<V::Output>`, returning `Some(None)` when calling `next` if the visitor did not stop yet and `Some(Some(...))` when it has produced a final value. After that, it will only return `None`.
@@ -611,6 +1188,248 @@ where
 {
 }
 
+/// A version of [`TraverseMutIter`] which mutably borrows the [`TraversableMut`] instead of taking ownership of it, so that the traversable can be used again once the iterator is dropped.
+///
+/// The iterator produces values of type `Option<V::Output>`, returning `Some(None)` when calling `next` if the visitor did not stop yet and `Some(Some(...))` when it has produced a final value. After that, it will only return `None`.
+///
+/// See [`Tree::traverse_iter_mut`] for a convenient way to create one.
+///
+/// [`TraverseMutIter`]: struct.TraverseMutIter.html " "
+/// [`TraversableMut`]: trait.TraversableMut.html " "
+/// [`Tree::traverse_iter_mut`]: ../trait.Tree.html#method.traverse_iter_mut " "
+pub struct TraverseMutRefIter<'a, V, T>
+where
+    V: VisitorMut,
+    T: TraversableMut,
+    for<'b> &'b mut T: BorrowMut<V::Target>,
+    T::Cursor: From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+{
+    visitor: V,
+    traversable: &'a mut T,
+    cursor: Option<CursorResult<T::Cursor>>,
+    finished: bool,
+}
+impl<'a, V, T> TraverseMutRefIter<'a, V, T>
+where
+    V: VisitorMut,
+    T: TraversableMut,
+    for<'b> &'b mut T: BorrowMut<V::Target>,
+    T::Cursor: From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+{
+    /// Creates a mutating traversal iterator with the specified visitor and borrowed traversable.
+    pub fn new(visitor: V, traversable: &'a mut T) -> Self {
+        Self {
+            visitor,
+            traversable,
+            cursor: None,
+            finished: false,
+        }
+    }
+}
+impl<'a, V, T> From<(V, &'a mut T)> for TraverseMutRefIter<'a, V, T>
+where
+    V: VisitorMut,
+    T: TraversableMut,
+    for<'b> &'b mut T: BorrowMut<V::Target>,
+    T::Cursor: From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+{
+    fn from(op: (V, &'a mut T)) -> Self {
+        Self::new(op.0, op.1)
+    }
+}
+impl<V, T> Iterator for TraverseMutRefIter<'_, V, T>
+where
+    V: VisitorMut,
+    T: TraversableMut,
+    for<'b> &'b mut T: BorrowMut<V::Target>,
+    T::Cursor: From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+{
+    type Item = Option<V::Output>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let cursor =
+            Option::take(&mut self.cursor).unwrap_or_else(|| Ok(self.traversable.cursor_to_root()));
+        match self.traversable.step_mut(&mut self.visitor, cursor) {
+            Step::NextCursor(c) => {
+                self.cursor = Some(c);
+                Some(None)
+            }
+            Step::End(f) => {
+                self.finished = true;
+                Some(Some(f))
+            }
+        }
+    }
+}
+impl<V, T> Debug for TraverseMutRefIter<'_, V, T>
+where
+    V: VisitorMut + Debug,
+    T: TraversableMut + Debug,
+    for<'b> &'b mut T: BorrowMut<V::Target>,
+    T::Cursor:
+        From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor> + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraverseMutRefIter")
+            .field("visitor", &self.visitor)
+            .field("traversable", &self.traversable)
+            .field("cursor", &self.cursor)
+            .field("finished", &self.finished)
+            .finish()
+    }
+}
+impl<V, T> FusedIterator for TraverseMutRefIter<'_, V, T>
+where
+    V: VisitorMut,
+    T: TraversableMut,
+    for<'b> &'b mut T: BorrowMut<V::Target>,
+    T::Cursor: From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+{
+}
+
+/// An iterator which groups a [`Visitor`] and a borrowed [`Traversable`], yielding the cursor and value of every node the visitor visits.
+///
+/// Unlike [`TraverseIter`], which only produces the final value of the visitor, this makes it possible to consume the nodes along the way. The iterator ends after the visitor stops, and the final value of the visitor can then be retrieved using [`output`] or [`into_output`]. Steps in which the visitor receives an incorrectly driven cursor do not visit any node and thus are skipped.
+///
+/// See [`Tree::traverse_iter`] for a convenient way to create one.
+///
+/// # Panics
+/// Iterating will panic if the traversal reaches an invalid cursor, the same way [`step`] does.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{
+///     FreeformTree,
+///     NodeValue,
+///     Traversable,
+///     traversal::{algorithms::FindFirst, TraverseValuesIter},
+/// };
+///
+/// let mut tree = FreeformTree::<_>::new(0);
+/// tree.root_mut().make_branch(vec![1, 3, 5]).unwrap();
+/// tree.root_mut().first_child_mut().unwrap().make_branch(vec![2]).unwrap();
+/// let visitor = FindFirst::<FreeformTree<_>, _>::new(|x: NodeValue<&u32, &u32>| *x.into_inner() == 3);
+/// let mut iter = TraverseValuesIter::new(visitor, &tree);
+/// let visited = iter.by_ref().map(|(_, x)| *x.into_inner()).collect::<Vec<_>>();
+/// // The search went through the nodes in pre-order until it found the one it was looking for.
+/// assert_eq!(visited, [0, 1, 2, 3]);
+/// let found = iter.into_output().flatten().unwrap();
+/// assert_eq!(*tree.value_of(&found).into_inner(), 3);
+/// ```
+///
+/// [`Visitor`]: trait.Visitor.html " "
+/// [`Traversable`]: trait.Traversable.html " "
+/// [`TraverseIter`]: struct.TraverseIter.html " "
+/// [`output`]: #method.output " "
+/// [`into_output`]: #method.into_output " "
+/// [`step`]: trait.Traversable.html#method.step " "
+/// [`Tree::traverse_iter`]: ../trait.Tree.html#method.traverse_iter " "
+pub struct TraverseValuesIter<'a, V, T>
+where
+    V: Visitor,
+    T: Traversable,
+    for<'b> &'b T: Borrow<V::Target>,
+    T::Cursor: From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+{
+    visitor: V,
+    traversable: &'a T,
+    cursor: Option<CursorResult<T::Cursor>>,
+    output: Option<V::Output>,
+}
+impl<'a, V, T> TraverseValuesIter<'a, V, T>
+where
+    V: Visitor,
+    T: Traversable,
+    for<'b> &'b T: Borrow<V::Target>,
+    T::Cursor: From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+{
+    /// Creates a value-yielding traversal iterator with the specified visitor and traversable, starting from the root node.
+    pub fn new(visitor: V, traversable: &'a T) -> Self {
+        Self::new_from(visitor, traversable, traversable.cursor_to_root())
+    }
+    /// Creates a value-yielding traversal iterator with the specified visitor and traversable, starting from the specified cursor.
+    pub fn new_from(visitor: V, traversable: &'a T, starting_cursor: T::Cursor) -> Self {
+        Self {
+            visitor,
+            traversable,
+            cursor: Some(Ok(starting_cursor)),
+            output: None,
+        }
+    }
+    /// Returns a reference to the final value produced by the visitor, or `None` if it did not stop yet.
+    pub fn output(&self) -> Option<&V::Output> {
+        self.output.as_ref()
+    }
+    /// Consumes the iterator and returns the final value produced by the visitor, or `None` if it did not stop yet.
+    #[allow(clippy::missing_const_for_fn)] // Clippy has no idea what a destructor is
+    pub fn into_output(self) -> Option<V::Output> {
+        self.output
+    }
+}
+impl<'a, V, T> From<(V, &'a T)> for TraverseValuesIter<'a, V, T>
+where
+    V: Visitor,
+    T: Traversable,
+    for<'b> &'b T: Borrow<V::Target>,
+    T::Cursor: From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+{
+    fn from(op: (V, &'a T)) -> Self {
+        Self::new(op.0, op.1)
+    }
+}
+impl<'a, V, T> Iterator for TraverseValuesIter<'a, V, T>
+where
+    V: Visitor,
+    T: Traversable,
+    for<'b> &'b T: Borrow<V::Target>,
+    T::Cursor: From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+{
+    type Item = (T::Cursor, NodeValue<&'a T::Branch, &'a T::Leaf>);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Once the visitor stops, the cursor is never put back
+            let cursor = Option::take(&mut self.cursor)?;
+            let visited = cursor.as_ref().ok().cloned();
+            match self.traversable.step(&mut self.visitor, cursor) {
+                Step::NextCursor(c) => self.cursor = Some(c),
+                Step::End(f) => self.output = Some(f),
+            }
+            if let Some(visited) = visited {
+                let value = self.traversable.value_of(&visited);
+                return Some((visited, value));
+            }
+        }
+    }
+}
+impl<V, T> Debug for TraverseValuesIter<'_, V, T>
+where
+    V: Visitor + Debug,
+    V::Output: Debug,
+    T: Traversable + Debug,
+    for<'b> &'b T: Borrow<V::Target>,
+    T::Cursor:
+        From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor> + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraverseValuesIter")
+            .field("visitor", &self.visitor)
+            .field("traversable", &self.traversable)
+            .field("cursor", &self.cursor)
+            .field("output", &self.output)
+            .finish()
+    }
+}
+impl<V, T> FusedIterator for TraverseValuesIter<'_, V, T>
+where
+    V: Visitor,
+    T: Traversable,
+    for<'b> &'b T: Borrow<V::Target>,
+    T::Cursor: From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+{
+}
+
 //───────────────────────────────────────────────────────────────────────┐
 // Implementations for pointer types and other standard library storages │
 //───────────────────────────────────────────────────────────────────────┘
@@ -678,6 +1497,35 @@ impl<T: Traversable> Traversable for &T {
     fn nth_child_of(&self, cursor: &Self::Cursor, child_num: usize) -> Option<Self::Cursor> {
         (**self).nth_child_of(cursor, child_num)
     }
+    fn contains_cursor(&self, cursor: &Self::Cursor) -> bool {
+        (**self).contains_cursor(cursor)
+    }
+    #[allow(clippy::type_complexity)]
+    fn try_value_of(
+        &self,
+        cursor: &Self::Cursor,
+    ) -> Result<NodeValue<&'_ Self::Branch, &'_ Self::Leaf>, InvalidCursorError<Self::Cursor>> {
+        (**self).try_value_of(cursor)
+    }
+    fn try_parent_of(
+        &self,
+        cursor: &Self::Cursor,
+    ) -> Result<Option<Self::Cursor>, InvalidCursorError<Self::Cursor>> {
+        (**self).try_parent_of(cursor)
+    }
+    fn try_num_children_of(
+        &self,
+        cursor: &Self::Cursor,
+    ) -> Result<usize, InvalidCursorError<Self::Cursor>> {
+        (**self).try_num_children_of(cursor)
+    }
+    fn try_nth_child_of(
+        &self,
+        cursor: &Self::Cursor,
+        child_num: usize,
+    ) -> Result<Option<Self::Cursor>, InvalidCursorError<Self::Cursor>> {
+        (**self).try_nth_child_of(cursor, child_num)
+    }
 }
 impl<T: Traversable> Traversable for &mut T {
     type Leaf = T::Leaf;
@@ -706,10 +1554,40 @@ impl<T: Traversable> Traversable for &mut T {
     fn nth_child_of(&self, cursor: &Self::Cursor, child_num: usize) -> Option<Self::Cursor> {
         (**self).nth_child_of(cursor, child_num)
     }
+    fn contains_cursor(&self, cursor: &Self::Cursor) -> bool {
+        (**self).contains_cursor(cursor)
+    }
+    #[allow(clippy::type_complexity)]
+    fn try_value_of(
+        &self,
+        cursor: &Self::Cursor,
+    ) -> Result<NodeValue<&'_ Self::Branch, &'_ Self::Leaf>, InvalidCursorError<Self::Cursor>> {
+        (**self).try_value_of(cursor)
+    }
+    fn try_parent_of(
+        &self,
+        cursor: &Self::Cursor,
+    ) -> Result<Option<Self::Cursor>, InvalidCursorError<Self::Cursor>> {
+        (**self).try_parent_of(cursor)
+    }
+    fn try_num_children_of(
+        &self,
+        cursor: &Self::Cursor,
+    ) -> Result<usize, InvalidCursorError<Self::Cursor>> {
+        (**self).try_num_children_of(cursor)
+    }
+    fn try_nth_child_of(
+        &self,
+        cursor: &Self::Cursor,
+        child_num: usize,
+    ) -> Result<Option<Self::Cursor>, InvalidCursorError<Self::Cursor>> {
+        (**self).try_nth_child_of(cursor, child_num)
+    }
 }
 impl<T: Traversable + TraversableMut> TraversableMut for &mut T {
     const CAN_REMOVE_INDIVIDUAL_CHILDREN: bool = T::CAN_REMOVE_INDIVIDUAL_CHILDREN;
     const CAN_PACK_CHILDREN: bool = T::CAN_PACK_CHILDREN;
+    const CAN_ADD_INDIVIDUAL_CHILDREN: bool = T::CAN_ADD_INDIVIDUAL_CHILDREN;
     type PackedChildren = T::PackedChildren;
     fn value_mut_of(
         &mut self,
@@ -758,4 +1636,21 @@ impl<T: Traversable + TraversableMut> TraversableMut for &mut T {
     ) -> Result<Self::PackedChildren, TryRemoveChildrenError> {
         (*self).try_remove_children(cursor, branch_to_leaf)
     }
+    fn try_add_child<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        child_payload: Self::Leaf,
+        leaf_to_branch: LtB,
+    ) -> Result<Self::Cursor, TryAddChildError<Self::Leaf>> {
+        (*self).try_add_child(cursor, child_payload, leaf_to_branch)
+    }
+    #[allow(clippy::type_complexity)]
+    fn try_add_children<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        children: Self::PackedChildren,
+        leaf_to_branch: LtB,
+    ) -> Result<(), MakeBranchError<Self::Leaf, Self::PackedChildren>> {
+        (*self).try_add_children(cursor, children, leaf_to_branch)
+    }
 }
@@ -0,0 +1,168 @@
+use core::{fmt::Debug, mem, ops::Index, slice};
+use alloc::vec::Vec;
+use super::{Traversable, VisitorDirection, CursorResult};
+
+/// A list of bookmarked cursors into a traversable, for algorithms which need to keep track of more than one position at a time.
+///
+/// Visitors only ever receive one cursor, so algorithms which need more, such as ones with slow and fast cursors, lowest common ancestor queries or iterative deepening searches, have to store the other positions themselves. The bookmarks are addressed by the index which [`push`] returns, which stays the same until the bookmark is [removed]. Moving a bookmark with [`advance`] or [`apply`] never leaves it invalid: if the movement fails, the bookmark stays where it was, just like the cursor of a traversal does.
+///
+/// Cursors can become invalid when nodes are removed from the traversable, which [`revalidate`] takes care of by moving such bookmarks back to the root node. Since sparse storage cannot tell a hole left by a removed node apart from a node, bookmarks which point to holes in it are still considered valid, as described in the documentation of [`Traversable::contains_cursor`].
+///
+/// # Example
+/// ```rust
+/// use charcoal::{
+///     freeform_tree::FreeformTree,
+///     traversal::{MultiCursor, VisitorDirection},
+///     Traversable,
+///     TraversableMut,
+/// };
+///
+/// // Build a chain of nodes with the values from 0 to 6.
+/// let mut tree = FreeformTree::<_>::new(0);
+/// let mut cursor = tree.cursor_to_root();
+/// for value in 1..=6 {
+///     cursor = tree.try_add_child(&cursor, value, |x| x).unwrap();
+/// }
+///
+/// // The fast cursor moves two levels down for every level the slow one moves, so the slow one
+/// // ends up in the middle of the chain once the fast one hits the bottom.
+/// let mut cursors = MultiCursor::new();
+/// let slow = cursors.push(tree.cursor_to_root());
+/// let fast = cursors.push(tree.cursor_to_root());
+/// let down = || VisitorDirection::<_, ()>::Child(0);
+/// while cursors.advance(&tree, fast, down()) && cursors.advance(&tree, fast, down()) {
+///     cursors.advance(&tree, slow, down());
+/// }
+/// assert_eq!(*tree.value_of(&cursors[slow]).into_inner(), 3);
+/// assert_eq!(*tree.value_of(&cursors[fast]).into_inner(), 6);
+/// ```
+///
+/// [`push`]: #method.push " "
+/// [removed]: #method.remove " "
+/// [`advance`]: #method.advance " "
+/// [`apply`]: #method.apply " "
+/// [`revalidate`]: #method.revalidate " "
+/// [`Traversable::contains_cursor`]: trait.Traversable.html#method.contains_cursor " "
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MultiCursor<C> {
+    cursors: Vec<C>,
+}
+impl<C: Clone + Debug + Eq> MultiCursor<C> {
+    /// Creates an empty list of bookmarks.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            cursors: Vec::new(),
+        }
+    }
+    /// Bookmarks the specified cursor, returning the index of the bookmark.
+    pub fn push(&mut self, cursor: C) -> usize {
+        self.cursors.push(cursor);
+        self.cursors.len() - 1
+    }
+    /// Removes the bookmark with the specified index, returning its cursor. The bookmarks after it are shifted to fill the gap, which means that their indices decrease by one.
+    ///
+    /// # Panics
+    /// Will panic if there is no bookmark with the specified index.
+    pub fn remove(&mut self, index: usize) -> C {
+        self.cursors.remove(index)
+    }
+    /// Returns the cursor of the bookmark with the specified index, or `None` if there's no such bookmark.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&C> {
+        self.cursors.get(index)
+    }
+    /// Moves the bookmark with the specified index to the specified cursor, returning the old one.
+    ///
+    /// # Panics
+    /// Will panic if there is no bookmark with the specified index.
+    pub fn set(&mut self, index: usize, cursor: C) -> C {
+        mem::replace(&mut self.cursors[index], cursor)
+    }
+    /// Returns the number of bookmarks.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.cursors.len()
+    }
+    /// Returns `true` if there are no bookmarks, `false` otherwise.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.cursors.is_empty()
+    }
+    /// Returns an iterator over the cursors of the bookmarks, in the order of their indices.
+    pub fn iter(&self) -> slice::Iter<'_, C> {
+        self.cursors.iter()
+    }
+    /// Returns the index of the first bookmark which points to the specified cursor, or `None` if there's no such bookmark. This can be used to check whether a traversal or another bookmark has reached one of the bookmarked nodes.
+    pub fn position(&self, cursor: &C) -> Option<usize> {
+        self.cursors.iter().position(|bookmark| bookmark == cursor)
+    }
+    /// Moves the bookmark with the specified index according to the result of moving a cursor, such as the one produced by [`Traversable::advance_cursor`] or passed to a visitor: if the movement succeeded, the bookmark is moved to the new cursor, and if it failed, it's moved to the last valid cursor stored in the error. Returns `true` if the movement succeeded, `false` otherwise.
+    ///
+    /// # Panics
+    /// Will panic if there is no bookmark with the specified index.
+    ///
+    /// [`Traversable::advance_cursor`]: trait.Traversable.html#tymethod.advance_cursor " "
+    pub fn apply(&mut self, index: usize, result: CursorResult<C>) -> bool {
+        let succeeded = result.is_ok();
+        self.cursors[index] = result.unwrap_or_else(|error| error.previous_state);
+        succeeded
+    }
+    /// Moves the bookmark with the specified index in the specified direction, returning `true` if it was moved or `false` if the movement failed, in which case the bookmark stays where it was.
+    ///
+    /// Unlike calling [`Traversable::advance_cursor`] directly, this does not panic if the bookmark has become invalid, and fails instead.
+    ///
+    /// # Panics
+    /// Will panic if there is no bookmark with the specified index.
+    ///
+    /// [`Traversable::advance_cursor`]: trait.Traversable.html#tymethod.advance_cursor " "
+    pub fn advance<T, V>(
+        &mut self,
+        traversable: &T,
+        index: usize,
+        direction: VisitorDirection<C, V>,
+    ) -> bool
+    where
+        T: Traversable<Cursor = C>,
+    {
+        let cursor = self.cursors[index].clone();
+        if !traversable.contains_cursor(&cursor) {
+            return false;
+        }
+        self.apply(index, traversable.advance_cursor(cursor, direction))
+    }
+    /// Moves every bookmark which no longer points to a node in the specified traversable to its root node, returning the number of bookmarks which were moved. The indices of the bookmarks stay the same.
+    pub fn revalidate<T>(&mut self, traversable: &T) -> usize
+    where
+        T: Traversable<Cursor = C>,
+    {
+        let mut num_moved = 0;
+        for cursor in &mut self.cursors {
+            if !traversable.contains_cursor(cursor) {
+                *cursor = traversable.cursor_to_root();
+                num_moved += 1;
+            }
+        }
+        num_moved
+    }
+}
+impl<C: Clone + Debug + Eq> Default for MultiCursor<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<C> Index<usize> for MultiCursor<C> {
+    type Output = C;
+
+    fn index(&self, index: usize) -> &C {
+        &self.cursors[index]
+    }
+}
+impl<'a, C> IntoIterator for &'a MultiCursor<C> {
+    type Item = &'a C;
+    type IntoIter = slice::Iter<'a, C>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cursors.iter()
+    }
+}
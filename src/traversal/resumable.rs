@@ -0,0 +1,205 @@
+use core::{
+    str::FromStr,
+    fmt::{self, Formatter, Display},
+};
+use crate::{
+    path::{Path, ParsePathError},
+    Tree,
+};
+use super::{Visitor, Step, CursorResult, CursorDirectionError};
+
+/// A traversal which performs at most a fixed number of steps of a [`Visitor`] at a time, for processing large trees across frames or event loop ticks without blocking.
+///
+/// Between the calls to [`run`], the traversal only holds on to the visitor and a [`Continuation`], which stores the position of the next step as a [path] rather than a cursor. This means that the tree is free to be mutated between the calls, and that the continuation can be stored as a string and used to [resume] the traversal later, for example after restarting the program.
+///
+/// When the traversal is resumed, the path is resolved anew. If the node at the end of it was removed in the meantime, the traversal continues from its closest ancestor which is still in the tree, and the visitor is told that its last movement failed, just like if it gave directions which could not be followed. Since paths identify nodes by their position, adding or removing an earlier sibling of a node on the path makes the traversal continue from its new occupant instead. Cursors which the visitor stores by itself are not revalidated and are subject to the usual stability caveats of the storage.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{
+///     freeform_tree::FreeformTree,
+///     traversal::{collectors::CountNodes, ResumableTraversal},
+/// };
+///
+/// let mut tree = FreeformTree::<_>::new(1);
+/// let mut root = tree.root_mut();
+/// root.make_branch(vec![2, 3, 4]).unwrap();
+/// root.first_child_mut().unwrap().make_branch(vec![5, 6]).unwrap();
+///
+/// // Visit at most two nodes per call.
+/// let mut traversal = ResumableTraversal::with_budget(CountNodes::new(), 2);
+/// assert_eq!(traversal.run(&tree), None);
+/// // The nodes with 1 and 2 have been visited, and the next one is the first child of the latter.
+/// let token = traversal.continuation().unwrap().to_string();
+/// assert_eq!(token, "0/0");
+///
+/// // The continuation can be used to resume the traversal with the same visitor later.
+/// let (visitor, _) = traversal.into_parts();
+/// let mut traversal = ResumableTraversal::resume(visitor, 2, token.parse().unwrap());
+/// assert_eq!(traversal.run(&tree), None);
+/// assert_eq!(traversal.run(&tree), Some(6));
+/// assert!(traversal.is_finished());
+/// ```
+///
+/// [`Visitor`]: trait.Visitor.html " "
+/// [`Continuation`]: struct.Continuation.html " "
+/// [path]: ../path/struct.Path.html " "
+/// [`run`]: #method.run " "
+/// [resume]: #method.resume " "
+#[derive(Clone, Debug)]
+pub struct ResumableTraversal<V> {
+    visitor: V,
+    budget: usize,
+    continuation: Option<Continuation>,
+}
+impl<V: Visitor> ResumableTraversal<V> {
+    /// Creates a traversal which starts at the root node and performs at most the specified number of steps with every call to [`run`].
+    ///
+    /// # Panics
+    /// Will panic if the budget is zero, since such a traversal would never make progress.
+    ///
+    /// [`run`]: #method.run " "
+    pub fn with_budget(visitor: V, budget: usize) -> Self {
+        Self::resume(visitor, budget, Continuation::default())
+    }
+    /// Creates a traversal which continues from the specified continuation and performs at most the specified number of steps with every call to [`run`].
+    ///
+    /// # Panics
+    /// Will panic if the budget is zero, since such a traversal would never make progress.
+    ///
+    /// [`run`]: #method.run " "
+    pub fn resume(visitor: V, budget: usize, continuation: Continuation) -> Self {
+        assert!(budget != 0, "the budget of a traversal must not be zero");
+        Self {
+            visitor,
+            budget,
+            continuation: Some(continuation),
+        }
+    }
+    /// Performs at most as many steps of the visitor on the tree as the budget allows, returning the final result of the visitor if it stopped, or `None` if it did not stop yet or has already stopped during a previous call.
+    ///
+    /// # Panics
+    /// Will panic if the traversal reaches an invalid cursor, the same way [`Traversable::step`] does.
+    ///
+    /// [`Traversable::step`]: trait.Traversable.html#method.step " "
+    pub fn run<T>(&mut self, tree: &T) -> Option<V::Output>
+    where
+        V: Visitor<Target = T>,
+        T: Tree,
+    {
+        let continuation = self.continuation.take()?;
+        let mut cursor = continuation.resolve(tree);
+        for _ in 0..self.budget {
+            match tree.step(&mut self.visitor, cursor) {
+                Step::NextCursor(c) => cursor = c,
+                Step::End(output) => return Some(output),
+            }
+        }
+        self.continuation = Some(Continuation::from_cursor(tree, cursor));
+        None
+    }
+    /// Returns the continuation from which the next call to [`run`] will resume the traversal, or `None` if the visitor has already stopped.
+    ///
+    /// [`run`]: #method.run " "
+    #[must_use]
+    pub fn continuation(&self) -> Option<&Continuation> {
+        self.continuation.as_ref()
+    }
+    /// Returns `true` if the visitor has stopped, `false` otherwise.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.continuation.is_none()
+    }
+    /// Returns the maximum number of steps performed with every call to [`run`].
+    ///
+    /// [`run`]: #method.run " "
+    #[must_use]
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+    /// Sets the maximum number of steps performed with every call to [`run`], for example to adapt to the time left until the next frame.
+    ///
+    /// # Panics
+    /// Will panic if the budget is zero, since such a traversal would never make progress.
+    ///
+    /// [`run`]: #method.run " "
+    pub fn set_budget(&mut self, budget: usize) {
+        assert!(budget != 0, "the budget of a traversal must not be zero");
+        self.budget = budget;
+    }
+    /// Returns a reference to the visitor.
+    #[must_use]
+    pub fn visitor(&self) -> &V {
+        &self.visitor
+    }
+    /// Consumes the traversal and returns the visitor and the continuation, which is `None` if the visitor has already stopped.
+    pub fn into_parts(self) -> (V, Option<Continuation>) {
+        (self.visitor, self.continuation)
+    }
+}
+
+/// The position from which a [`ResumableTraversal`] continues, in a form which survives mutations of the tree and can be stored as a string.
+///
+/// The continuation is displayed as the [path] to the node followed by an exclamation mark if the last movement of the visitor failed, such as `0/3/1` or `0/3!`, and can be parsed back from that form.
+///
+/// [`ResumableTraversal`]: struct.ResumableTraversal.html " "
+/// [path]: ../path/struct.Path.html " "
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Continuation {
+    /// The path to the node at which the next step is performed.
+    pub path: Path,
+    /// Whether the last movement of the visitor failed, in which case the visitor receives an error for the next step, containing the cursor of the node at the end of the path.
+    pub movement_failed: bool,
+}
+impl Continuation {
+    /// Creates a continuation for the specified cursor of a traversal.
+    fn from_cursor<T: Tree>(tree: &T, cursor: CursorResult<T::Cursor>) -> Self {
+        let (cursor, movement_failed) = match cursor {
+            Ok(cursor) => (cursor, false),
+            Err(error) => (error.previous_state, true),
+        };
+        Self {
+            path: Path::to_node(tree, &cursor).expect("the traversal left the tree"),
+            movement_failed,
+        }
+    }
+    /// Finds the cursor to continue from, falling back to the closest ancestor which is still in the tree.
+    fn resolve<T: Tree>(&self, tree: &T) -> CursorResult<T::Cursor> {
+        let mut cursor = tree.root_key();
+        let mut movement_failed = self.movement_failed;
+        for &index in self.path.iter() {
+            if let Some(child) = tree.children_keys(&cursor).nth(index) {
+                cursor = child;
+            } else {
+                movement_failed = true;
+                break;
+            }
+        }
+        if movement_failed {
+            Err(CursorDirectionError {
+                previous_state: cursor,
+            })
+        } else {
+            Ok(cursor)
+        }
+    }
+}
+impl Display for Continuation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.path, f)?;
+        if self.movement_failed {
+            f.write_str("!")?;
+        }
+        Ok(())
+    }
+}
+impl FromStr for Continuation {
+    type Err = ParsePathError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, movement_failed) = s.strip_suffix('!').map_or((s, false), |path| (path, true));
+        Ok(Self {
+            path: path.parse()?,
+            movement_failed,
+        })
+    }
+}
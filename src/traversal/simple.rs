@@ -0,0 +1,237 @@
+use core::{
+    fmt::{self, Formatter, Debug},
+    borrow::{Borrow, BorrowMut},
+    marker::PhantomData,
+};
+use super::{
+    Visitor,
+    VisitorMut,
+    Traversable,
+    TraversableMut,
+    VisitorDirection,
+    CursorResult,
+    convert_cursor_result,
+    map_direction,
+};
+
+/// A version of [`Visitor`] which is generic over the traversable it visits instead of fixing it with an associated type.
+///
+/// Simple visitors receive the traversable's own cursors instead of a cursor type which converts to and from them. This makes it easy to write visitors which work with any tree with certain payload types: implement the trait for every `T: Traversable<Branch = ..., Leaf = ...>` and the visitor can be used with all of them through [`traverse_simple`] and [`traverse_simple_from`], without spelling out the tree type or the conversions between cursors. Visitors which need to remember cursors between visits can be made generic over the cursor type and implement the trait for every traversable with that cursor type.
+///
+/// Simple visitors are turned into normal ones with [`SimpleVisitorAdapter`], which can be used with every API which accepts a [`Visitor`].
+///
+/// # Example
+/// ```rust
+/// use charcoal::{
+///     freeform_tree::FreeformTree,
+///     traversal::{SimpleVisitor, CursorResult, VisitorDirection},
+///     BinaryTree,
+///     Traversable,
+/// };
+///
+/// /// Sums up the payloads along the first children of the nodes, starting from the initial node.
+/// struct SumFirstChildren(i32);
+/// impl<T: Traversable<Branch = i32, Leaf = i32>> SimpleVisitor<T> for SumFirstChildren {
+///     type Output = i32;
+///     fn visit(
+///         &mut self,
+///         traversable: &T,
+///         cursor: CursorResult<T::Cursor>,
+///     ) -> VisitorDirection<T::Cursor, i32> {
+///         match cursor {
+///             Ok(cursor) => {
+///                 self.0 += *traversable.value_of(&cursor).into_inner();
+///                 VisitorDirection::Child(0)
+///             }
+///             Err(..) => VisitorDirection::Stop(self.0),
+///         }
+///     }
+/// }
+///
+/// let mut freeform = FreeformTree::<_>::new(1);
+/// freeform.root_mut().make_branch(vec![2, 3]).unwrap();
+/// let mut binary = BinaryTree::<_>::new(1);
+/// binary.root_mut().make_branch(10, Some(20)).unwrap();
+///
+/// assert_eq!(freeform.traverse_simple(SumFirstChildren(0)), 3);
+/// assert_eq!(binary.traverse_simple(SumFirstChildren(0)), 11);
+/// ```
+///
+/// [`Visitor`]: trait.Visitor.html " "
+/// [`traverse_simple`]: trait.Traversable.html#method.traverse_simple " "
+/// [`traverse_simple_from`]: trait.Traversable.html#method.traverse_simple_from " "
+/// [`SimpleVisitorAdapter`]: struct.SimpleVisitorAdapter.html " "
+pub trait SimpleVisitor<T: Traversable> {
+    /// The final value produced by the visitor.
+    type Output;
+    /// Visit the provided node, returning further directions for traversal.
+    ///
+    /// # Panics
+    /// Required to panic if called after a `Stop` value has already been produced. May also panic for other reasons, as appropriate and specified by the documentation on the trait implementation.
+    fn visit(
+        &mut self,
+        traversable: &T,
+        cursor: CursorResult<T::Cursor>,
+    ) -> VisitorDirection<T::Cursor, Self::Output>;
+}
+/// A version of [`SimpleVisitor`] with an added ability to acquire mutable access to the tree's nodes, in the same way as [`VisitorMut`].
+///
+/// Simple visitors are turned into normal ones with [`SimpleVisitorMutAdapter`], which can be used with every API which accepts a [`VisitorMut`].
+///
+/// [`SimpleVisitor`]: trait.SimpleVisitor.html " "
+/// [`VisitorMut`]: trait.VisitorMut.html " "
+/// [`SimpleVisitorMutAdapter`]: struct.SimpleVisitorMutAdapter.html " "
+pub trait SimpleVisitorMut<T: TraversableMut> {
+    /// The final value produced by the visitor.
+    type Output;
+    /// Visit the provided node with a mutable reference, returning further directions for traversal.
+    ///
+    /// # Panics
+    /// Required to panic if called after a `Stop` value has already been produced. May also panic for other reasons, as appropriate and specified by the documentation on the trait implementation.
+    fn visit_mut(
+        &mut self,
+        traversable: &mut T,
+        cursor: CursorResult<T::Cursor>,
+    ) -> VisitorDirection<T::Cursor, Self::Output>;
+}
+impl<T: Traversable, V: SimpleVisitor<T>> SimpleVisitor<T> for &mut V {
+    type Output = V::Output;
+    fn visit(
+        &mut self,
+        traversable: &T,
+        cursor: CursorResult<T::Cursor>,
+    ) -> VisitorDirection<T::Cursor, Self::Output> {
+        (*self).visit(traversable, cursor)
+    }
+}
+impl<T: TraversableMut, V: SimpleVisitorMut<T>> SimpleVisitorMut<T> for &mut V {
+    type Output = V::Output;
+    fn visit_mut(
+        &mut self,
+        traversable: &mut T,
+        cursor: CursorResult<T::Cursor>,
+    ) -> VisitorDirection<T::Cursor, Self::Output> {
+        (*self).visit_mut(traversable, cursor)
+    }
+}
+
+/// A [`Visitor`] which visits the traversable `T` with a [`SimpleVisitor`].
+///
+/// [`Visitor`]: trait.Visitor.html " "
+/// [`SimpleVisitor`]: trait.SimpleVisitor.html " "
+#[derive(Copy, Clone)]
+pub struct SimpleVisitorAdapter<V, T> {
+    visitor: V,
+    _phantom: PhantomData<fn(&T)>,
+}
+impl<V, T> SimpleVisitorAdapter<V, T>
+where
+    V: SimpleVisitor<T>,
+    T: Traversable,
+{
+    /// Wraps the specified simple visitor.
+    pub fn new(visitor: V) -> Self {
+        Self {
+            visitor,
+            _phantom: PhantomData,
+        }
+    }
+    /// Returns the wrapped simple visitor.
+    pub fn into_inner(self) -> V {
+        self.visitor
+    }
+}
+impl<V, T> Visitor for SimpleVisitorAdapter<V, T>
+where
+    V: SimpleVisitor<T>,
+    T: Traversable,
+{
+    type Target = T;
+    type Output = V::Output;
+
+    fn visit<C>(
+        &mut self,
+        traversable: impl Borrow<Self::Target>,
+        cursor: CursorResult<C>,
+    ) -> VisitorDirection<C, Self::Output>
+    where
+        C: From<<Self::Target as Traversable>::Cursor>
+            + Into<<Self::Target as Traversable>::Cursor>
+            + Clone
+            + Debug
+            + Eq,
+    {
+        let direction = self
+            .visitor
+            .visit(traversable.borrow(), convert_cursor_result(cursor));
+        map_direction(direction, Into::into, |value| value)
+    }
+}
+impl<V: Debug, T> Debug for SimpleVisitorAdapter<V, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimpleVisitorAdapter")
+            .field("visitor", &self.visitor)
+            .finish()
+    }
+}
+
+/// A [`VisitorMut`] which visits the traversable `T` with a [`SimpleVisitorMut`].
+///
+/// [`VisitorMut`]: trait.VisitorMut.html " "
+/// [`SimpleVisitorMut`]: trait.SimpleVisitorMut.html " "
+#[derive(Copy, Clone)]
+pub struct SimpleVisitorMutAdapter<V, T> {
+    visitor: V,
+    _phantom: PhantomData<fn(&mut T)>,
+}
+impl<V, T> SimpleVisitorMutAdapter<V, T>
+where
+    V: SimpleVisitorMut<T>,
+    T: TraversableMut,
+{
+    /// Wraps the specified simple visitor.
+    pub fn new(visitor: V) -> Self {
+        Self {
+            visitor,
+            _phantom: PhantomData,
+        }
+    }
+    /// Returns the wrapped simple visitor.
+    pub fn into_inner(self) -> V {
+        self.visitor
+    }
+}
+impl<V, T> VisitorMut for SimpleVisitorMutAdapter<V, T>
+where
+    V: SimpleVisitorMut<T>,
+    T: TraversableMut,
+{
+    type Target = T;
+    type Output = V::Output;
+
+    fn visit_mut<C, M>(
+        &mut self,
+        mut traversable: M,
+        cursor: CursorResult<C>,
+    ) -> (VisitorDirection<C, Self::Output>, M)
+    where
+        C: From<<Self::Target as Traversable>::Cursor>
+            + Into<<Self::Target as Traversable>::Cursor>
+            + Clone
+            + Debug
+            + Eq,
+        M: BorrowMut<Self::Target>,
+    {
+        let direction = self
+            .visitor
+            .visit_mut(traversable.borrow_mut(), convert_cursor_result(cursor));
+        (map_direction(direction, Into::into, |value| value), traversable)
+    }
+}
+impl<V: Debug, T> Debug for SimpleVisitorMutAdapter<V, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimpleVisitorMutAdapter")
+            .field("visitor", &self.visitor)
+            .finish()
+    }
+}
@@ -0,0 +1,161 @@
+use core::{
+    fmt::{self, Formatter, Debug},
+    borrow::Borrow,
+};
+use crate::NodeValue;
+use super::{
+    Visitor,
+    Traversable,
+    VisitorDirection,
+    CursorResult,
+    CursorDirectionError,
+    TraversalTimeout,
+    convert_cursor_result,
+    map_direction,
+};
+
+/// A `Visitor` which wraps another visitor and reports every step it takes to a callback, for diagnosing visitors which give incorrect directions.
+///
+/// Optionally, the traversal can be limited to a maximum number of steps, after which it stops with a [`TraversalTimeout`] error instead of running forever if the wrapped visitor is stuck in a loop.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{
+///     freeform_tree::FreeformTree,
+///     traversal::{visitor_fn, TraceVisitor, TraversalTimeout, VisitorDirection},
+///     Traversable,
+///     NodeValue,
+/// };
+///
+/// let mut tree = FreeformTree::<_>::new(1);
+/// tree.root_mut().make_branch(vec![2, 3]).unwrap();
+///
+/// // This visitor never stops, bouncing between the root and its first child forever.
+/// let buggy = visitor_fn(|_, value| match value {
+///     NodeValue::Branch(..) => VisitorDirection::Child(0),
+///     NodeValue::Leaf(..) => VisitorDirection::<_, ()>::Parent,
+/// });
+/// let mut leaves_visited = 0;
+/// let traced = TraceVisitor::new(buggy, |event| {
+///     if event.is_leaf {
+///         leaves_visited += 1;
+///     }
+/// })
+/// .with_step_limit(10);
+/// assert_eq!(tree.traverse(traced), Err(TraversalTimeout { steps: 10 }));
+/// assert_eq!(leaves_visited, 5);
+/// ```
+///
+/// [`TraversalTimeout`]: struct.TraversalTimeout.html " "
+pub struct TraceVisitor<V: Visitor, F> {
+    inner: V,
+    callback: F,
+    steps: usize,
+    max_steps: Option<usize>,
+}
+impl<V, F> TraceVisitor<V, F>
+where
+    V: Visitor,
+    F: FnMut(TraceEvent<<V::Target as Traversable>::Cursor>),
+{
+    /// Wraps the specified visitor, calling the specified closure after every step it takes. The number of steps is not limited.
+    pub fn new(inner: V, callback: F) -> Self {
+        Self {
+            inner,
+            callback,
+            steps: 0,
+            max_steps: None,
+        }
+    }
+    /// Sets the maximum number of steps after which the traversal is stopped with a [`TraversalTimeout`] error.
+    ///
+    /// [`TraversalTimeout`]: struct.TraversalTimeout.html " "
+    #[must_use]
+    pub fn with_step_limit(mut self, max_steps: usize) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+    /// Returns the number of steps which have been performed so far.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+    /// Unwraps the visitor.
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+}
+impl<V, F> Visitor for TraceVisitor<V, F>
+where
+    V: Visitor,
+    F: FnMut(TraceEvent<<V::Target as Traversable>::Cursor>),
+{
+    type Target = V::Target;
+    type Output = Result<V::Output, TraversalTimeout>;
+
+    fn visit<C>(
+        &mut self,
+        traversable: impl Borrow<Self::Target>,
+        cursor: CursorResult<C>,
+    ) -> VisitorDirection<C, Self::Output>
+    where
+        C: From<<Self::Target as Traversable>::Cursor>
+            + Into<<Self::Target as Traversable>::Cursor>
+            + Clone
+            + Debug
+            + Eq,
+    {
+        if self.max_steps.map_or(false, |max_steps| self.steps >= max_steps) {
+            return VisitorDirection::Stop(Err(TraversalTimeout { steps: self.steps }));
+        }
+        let traversable = traversable.borrow();
+        let traced_cursor = convert_cursor_result(cursor.clone());
+        let current = traced_cursor
+            .clone()
+            .unwrap_or_else(CursorDirectionError::recover);
+        let is_leaf = matches!(traversable.value_of(&current), NodeValue::Leaf(..));
+        let direction = self.inner.visit(traversable, cursor);
+        (self.callback)(TraceEvent {
+            step: self.steps,
+            cursor: traced_cursor,
+            is_leaf,
+            direction: match &direction {
+                VisitorDirection::Parent => VisitorDirection::Parent,
+                VisitorDirection::NthAncestor(n) => VisitorDirection::NthAncestor(*n),
+                VisitorDirection::Root => VisitorDirection::Root,
+                VisitorDirection::NextSibling => VisitorDirection::NextSibling,
+                VisitorDirection::PrevSibling => VisitorDirection::PrevSibling,
+                VisitorDirection::Child(n) => VisitorDirection::Child(*n),
+                VisitorDirection::SetTo(cursor) => VisitorDirection::SetTo(cursor.clone().into()),
+                VisitorDirection::Stop(..) => VisitorDirection::Stop(()),
+            },
+        });
+        self.steps += 1;
+        map_direction(direction, |cursor| cursor, Ok)
+    }
+}
+#[allow(clippy::missing_fields_in_debug)] // Closures can't be debug-printed
+impl<V: Visitor + Debug, F> Debug for TraceVisitor<V, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraceVisitor")
+            .field("inner", &self.inner)
+            .field("steps", &self.steps)
+            .field("max_steps", &self.max_steps)
+            .finish()
+    }
+}
+
+/// A single step of a visitor, reported by [`TraceVisitor`].
+///
+/// [`TraceVisitor`]: struct.TraceVisitor.html " "
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TraceEvent<C: Clone + Debug + Eq> {
+    /// The number of steps which were performed before this one.
+    pub step: usize,
+    /// The cursor which was passed to the visitor, which is an error if the directions it gave in the previous step could not be followed.
+    pub cursor: CursorResult<C>,
+    /// Whether the visited node is a leaf node. If the cursor is an error, this refers to the node at its previous state.
+    pub is_leaf: bool,
+    /// The directions which the visitor gave, with the final value of `Stop` stripped.
+    pub direction: VisitorDirection<C, ()>,
+}
@@ -0,0 +1,193 @@
+use core::{
+    fmt::{self, Formatter, Debug},
+    borrow::{Borrow, BorrowMut},
+    marker::PhantomData,
+};
+use crate::NodeValue;
+use super::{
+    Visitor,
+    VisitorMut,
+    Traversable,
+    TraversableMut,
+    VisitorDirection,
+    CursorResult,
+    CursorDirectionError,
+    convert_cursor_result,
+    map_direction,
+};
+
+/// Wraps a closure into a [`Visitor`], for one-off traversals which don't warrant a dedicated visitor type.
+///
+/// The closure receives the cursor of the visited node, which is an error if the previous directions could not be followed, and the payload of the node. In the latter case, the error contains the cursor of the node which was visited previously, and the payload is the one of that node.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{
+///     freeform_tree::FreeformTree,
+///     traversal::{visitor_fn, VisitorDirection},
+///     Traversable,
+/// };
+///
+/// let mut tree = FreeformTree::<_>::new(1);
+/// let mut root = tree.root_mut();
+/// root.make_branch(vec![2, 3]).unwrap();
+/// root.first_child_mut().unwrap().make_branch(vec![4, 5]).unwrap();
+///
+/// // Descend along the first children until there are none left.
+/// let leftmost = tree.traverse(visitor_fn(|cursor, value| match cursor {
+///     Ok(..) => VisitorDirection::Child(0),
+///     Err(..) => VisitorDirection::Stop(*value.into_inner()),
+/// }));
+/// assert_eq!(leftmost, 4);
+/// ```
+///
+/// [`Visitor`]: trait.Visitor.html " "
+pub fn visitor_fn<T, O, F>(f: F) -> VisitorFn<T, F>
+where
+    T: Traversable,
+    F: FnMut(
+        CursorResult<T::Cursor>,
+        NodeValue<&T::Branch, &T::Leaf>,
+    ) -> VisitorDirection<T::Cursor, O>,
+{
+    VisitorFn {
+        f,
+        _phantom: PhantomData,
+    }
+}
+/// Wraps a closure into a [`VisitorMut`], for one-off traversals which don't warrant a dedicated visitor type.
+///
+/// The closure receives the same arguments as with [`visitor_fn`], except that the payload is borrowed mutably.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{
+///     freeform_tree::FreeformTree,
+///     traversal::{visitor_fn_mut, VisitorDirection},
+///     TraversableMut,
+/// };
+///
+/// let mut tree = FreeformTree::<_>::new(1);
+/// tree.root_mut().make_branch(vec![2, 3]).unwrap();
+///
+/// // Double the payloads of the nodes along the first children.
+/// tree.traverse_mut(visitor_fn_mut(|cursor, value| {
+///     let value = value.into_inner();
+///     match cursor {
+///         Ok(..) => {
+///             *value *= 2;
+///             VisitorDirection::Child(0)
+///         }
+///         Err(..) => VisitorDirection::Stop(()),
+///     }
+/// }));
+/// let root = tree.root();
+/// assert_eq!(*root.value().into_inner(), 2);
+/// assert_eq!(*root.first_child().unwrap().value().into_inner(), 4);
+/// assert_eq!(*root.last_child().unwrap().value().into_inner(), 3);
+/// ```
+///
+/// [`VisitorMut`]: trait.VisitorMut.html " "
+/// [`visitor_fn`]: fn.visitor_fn.html " "
+pub fn visitor_fn_mut<T, O, F>(f: F) -> VisitorFnMut<T, F>
+where
+    T: TraversableMut,
+    F: FnMut(
+        CursorResult<T::Cursor>,
+        NodeValue<&mut T::Branch, &mut T::Leaf>,
+    ) -> VisitorDirection<T::Cursor, O>,
+{
+    VisitorFnMut {
+        f,
+        _phantom: PhantomData,
+    }
+}
+
+/// A [`Visitor`] which calls a closure for every visited node, created by [`visitor_fn`].
+///
+/// [`Visitor`]: trait.Visitor.html " "
+/// [`visitor_fn`]: fn.visitor_fn.html " "
+#[derive(Copy, Clone)]
+pub struct VisitorFn<T, F> {
+    f: F,
+    _phantom: PhantomData<fn(&T)>,
+}
+impl<T, O, F> Visitor for VisitorFn<T, F>
+where
+    T: Traversable,
+    F: FnMut(
+        CursorResult<T::Cursor>,
+        NodeValue<&T::Branch, &T::Leaf>,
+    ) -> VisitorDirection<T::Cursor, O>,
+{
+    type Target = T;
+    type Output = O;
+
+    fn visit<C>(
+        &mut self,
+        traversable: impl Borrow<Self::Target>,
+        cursor: CursorResult<C>,
+    ) -> VisitorDirection<C, Self::Output>
+    where
+        C: From<<Self::Target as Traversable>::Cursor>
+            + Into<<Self::Target as Traversable>::Cursor>
+            + Clone
+            + Debug
+            + Eq,
+    {
+        let cursor = convert_cursor_result(cursor);
+        let current = cursor.clone().unwrap_or_else(CursorDirectionError::recover);
+        let direction = (self.f)(cursor, traversable.borrow().value_of(&current));
+        map_direction(direction, Into::into, |value| value)
+    }
+}
+impl<T, F> Debug for VisitorFn<T, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VisitorFn").finish()
+    }
+}
+
+/// A [`VisitorMut`] which calls a closure for every visited node, created by [`visitor_fn_mut`].
+///
+/// [`VisitorMut`]: trait.VisitorMut.html " "
+/// [`visitor_fn_mut`]: fn.visitor_fn_mut.html " "
+#[derive(Copy, Clone)]
+pub struct VisitorFnMut<T, F> {
+    f: F,
+    _phantom: PhantomData<fn(&mut T)>,
+}
+impl<T, O, F> VisitorMut for VisitorFnMut<T, F>
+where
+    T: TraversableMut,
+    F: FnMut(
+        CursorResult<T::Cursor>,
+        NodeValue<&mut T::Branch, &mut T::Leaf>,
+    ) -> VisitorDirection<T::Cursor, O>,
+{
+    type Target = T;
+    type Output = O;
+
+    fn visit_mut<C, M>(
+        &mut self,
+        mut traversable: M,
+        cursor: CursorResult<C>,
+    ) -> (VisitorDirection<C, Self::Output>, M)
+    where
+        C: From<<Self::Target as Traversable>::Cursor>
+            + Into<<Self::Target as Traversable>::Cursor>
+            + Clone
+            + Debug
+            + Eq,
+        M: BorrowMut<Self::Target>,
+    {
+        let cursor = convert_cursor_result(cursor);
+        let current = cursor.clone().unwrap_or_else(CursorDirectionError::recover);
+        let direction = (self.f)(cursor, traversable.borrow_mut().value_mut_of(&current));
+        (map_direction(direction, Into::into, |value| value), traversable)
+    }
+}
+impl<T, F> Debug for VisitorFnMut<T, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VisitorFnMut").finish()
+    }
+}
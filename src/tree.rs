@@ -0,0 +1,2050 @@
+//! The [`Tree`] trait, unifying the concrete tree types provided by Charcoal.
+//!
+//! While [`Traversable`] describes anything which a [`Visitor`] can walk through, it's deliberately kept minimal and panics on invalid cursors. The `Tree` trait builds on top of it to provide fallible key-based node access, child enumeration and a [`NodeRef`]/[`NodeRefMut`] pair which works for any tree type, allowing downstream crates to write algorithms which are generic over the kind of tree they operate on.
+//!
+//! # Example
+//! ```rust
+//! use charcoal::{Tree, BinaryTree, Octree};
+//!
+//! // Counts the leaves under a node without knowing what kind of tree it's in.
+//! fn count_leaves<T: Tree>(tree: &T, key: T::Cursor) -> usize {
+//!     let node = tree.get(key).expect("invalid key");
+//!     if node.is_leaf() {
+//!         1
+//!     } else {
+//!         node.children().map(|child| count_leaves(tree, child.into_raw_key())).sum()
+//!     }
+//! }
+//!
+//! let mut binary_tree = BinaryTree::<_>::new(0);
+//! binary_tree.root_mut().make_branch(1, Some(2)).unwrap();
+//! assert_eq!(count_leaves(&binary_tree, binary_tree.root_key()), 2);
+//!
+//! let mut octree = Octree::<_>::new(0);
+//! octree.root_mut().make_branch([1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+//! assert_eq!(count_leaves(&octree, octree.root_key()), 8);
+//! ```
+//!
+//! # Iteration order
+//! Every API which goes through all nodes of a tree does so in an order defined by the structure of the tree alone, visiting the children of every node in the order of their child indices. The order never depends on where the nodes are placed in the storage, so the holes left by removed nodes, the order in which the nodes were added and [defragmentation] do not affect it, and trees with the same structure and payloads are always walked the same way. Specifically:
+//! - [`find`], [`find_map`], [`events`], [`validate`] and [`stats`] go through the nodes in depth-first pre-order: every node comes before its descendants and after the descendants of its previous sibling. The [binary format], [JSON export], [raw views] and [petgraph export] store the nodes in the same order.
+//! - The consuming iterators and the `drain` methods of all tree types, as well as [`dedup_subtrees`], go through the nodes in depth-first post-order: every node comes after its descendants and after the descendants of its previous sibling, and the root node comes last.
+//! - [Linear octrees] store the nodes in breadth-first order: every node comes after all nodes which are closer to the root and after its previous sibling.
+//!
+//! ```rust
+//! use charcoal::{Tree, FreeformTree, NodeValue, tree::PreorderEvent};
+//!
+//! let mut tree = FreeformTree::<_>::new(0);
+//! let mut root = tree.root_mut();
+//! root.make_branch(vec![1, 2, 3]).unwrap();
+//! // Removing the first child leaves a hole in the storage, which the next added node fills, so
+//! // the node added last is stored before its siblings and the node added first is not first.
+//! root.first_child_mut().unwrap().try_remove_leaf().unwrap();
+//! root.try_push_back(4).unwrap();
+//! root.try_push_front(5).unwrap();
+//! assert_eq!(tree.children_keys(&tree.root_key()).collect::<Vec<_>>(), [4, 2, 3, 1]);
+//!
+//! // Yet the nodes are always visited by their position in the tree.
+//! let leaves = tree
+//!     .events()
+//!     .filter_map(|event| match event {
+//!         PreorderEvent::Leaf(&leaf) => Some(leaf),
+//!         _ => None,
+//!     })
+//!     .collect::<Vec<_>>();
+//! assert_eq!(leaves, [5, 2, 3, 4]);
+//! assert_eq!(tree.find_map(|value| Some(*value.into_inner())), Some(0));
+//! assert_eq!(
+//!     tree.into_iter().map(NodeValue::into_inner).collect::<Vec<_>>(),
+//!     [5, 2, 3, 4, 0],
+//! );
+//! ```
+//!
+//! [`Tree`]: trait.Tree.html " "
+//! [`NodeRef`]: struct.NodeRef.html " "
+//! [`NodeRefMut`]: struct.NodeRefMut.html " "
+//! [`Traversable`]: ../traversal/trait.Traversable.html " "
+//! [`Visitor`]: ../traversal/trait.Visitor.html " "
+//! [defragmentation]: ../defrag/index.html " "
+//! [`find`]: trait.Tree.html#method.find " "
+//! [`find_map`]: trait.Tree.html#method.find_map " "
+//! [`events`]: trait.Tree.html#method.events " "
+//! [`validate`]: trait.Tree.html#method.validate " "
+//! [`stats`]: trait.Tree.html#method.stats " "
+//! [binary format]: ../codec/index.html " "
+//! [JSON export]: ../json/index.html " "
+//! [raw views]: ../raw_view/index.html " "
+//! [petgraph export]: ../graph/index.html " "
+//! [`dedup_subtrees`]: trait.Tree.html#method.dedup_subtrees " "
+//! [Linear octrees]: ../octree/struct.LinearOctree.html " "
+
+use core::{
+    iter::FusedIterator,
+    mem,
+    fmt::{self, Formatter, Debug, Display},
+    borrow::{Borrow, BorrowMut},
+};
+use crate::{
+    traversal::{
+        TraverseMutRefIter,
+        TraverseValuesIter,
+        VisitorDirection,
+        CursorResult,
+        CursorDirectionError,
+    },
+    NodeValue,
+    Traversable,
+    TraversableMut,
+    Visitor,
+    VisitorMut,
+    TryRemoveLeafError,
+    TryRemoveBranchError,
+    TryRemoveChildrenError,
+    TryAddChildError,
+    MakeBranchError,
+};
+#[cfg(feature = "alloc")]
+use core::convert::Infallible;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use crate::{
+    codec::{BinaryPayload, BufferTooSmallError},
+    frozen::FrozenTree,
+    path::Path,
+};
+#[cfg(feature = "std")]
+use core::hash::Hash;
+#[cfg(feature = "std")]
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+#[cfg(feature = "std")]
+use crate::traversal::algorithms::{first_in_postorder, next_in_postorder};
+
+/// Trees with nodes which can be addressed by keys.
+///
+/// The keys are the same as the [cursors] of the [`Traversable`] implementation, which means that they are subject to the same stability caveats: removing nodes from a tree might invalidate keys to other nodes, depending on the storage being used. Unlike the methods of `Traversable`, all methods of this trait which accept keys return `None` instead of panicking if the key does not point into the storage, with one exception described [below].
+///
+/// All methods which go through every node of the tree do so in a guaranteed order which only depends on the structure of the tree, as described in the [module-level documentation].
+///
+/// Since generic associated types are not available, there is no way to return the tree-specific `NodeRef` types from a trait method. Instead, the [`get`] and [`get_mut`] methods return the generic [`NodeRef`] and [`NodeRefMut`] wrappers, which are implemented in terms of the methods of this trait. The tree-specific functionality, such as adding new nodes, is still available on the concrete tree types.
+///
+/// # Sparse storage
+/// Sparse storage, which is the default one, is the exception mentioned above. Removing a node from it leaves a hole in its place until a new node is added there, and the storage provides no way of telling a hole apart from a node other than accessing it, which panics. The key of a removed node thus keeps being reported as valid by [`contains_key`] and [`get`] while its slot is a hole, and methods which access the node through it, such as [`node_value`], panic. The holes are also counted by [`num_nodes`], as they are by the `num_nodes` methods of the trees themselves. Trees backed by sparse storage report the number of holes with their `num_holes` method, and can get rid of them with `defragment`. Storages which never consider the key of a removed node valid are marked with the [`StableStorage`] trait.
+///
+/// ```rust
+/// use charcoal::{binary_tree::BinaryTree, Tree};
+///
+/// let mut tree = BinaryTree::<_>::new(0);
+/// tree.root_mut().make_branch(1, Some(2)).unwrap();
+/// let right_child = tree.root().right_child().unwrap().into_raw_key();
+/// tree.root_mut().right_child_mut().unwrap().try_remove_leaf().unwrap();
+///
+/// // The removed node left a hole, which is still counted...
+/// assert_eq!(tree.num_holes(), 1);
+/// assert_eq!(Tree::num_nodes(&tree), 3);
+/// assert_eq!(Tree::num_nodes(&tree) - tree.num_holes(), 2);
+/// // ...and its key still points into the storage, so it must not be used anymore.
+/// assert!(tree.contains_key(&right_child));
+/// // Once the storage is defragmented, the hole is gone:
+/// tree.defragment();
+/// assert_eq!(Tree::num_nodes(&tree), 2);
+/// assert!(!tree.contains_key(&right_child));
+/// ```
+///
+/// [cursors]: ../traversal/trait.Traversable.html#associatedtype.Cursor " "
+/// [`Traversable`]: ../traversal/trait.Traversable.html " "
+/// [module-level documentation]: index.html#iteration-order " "
+/// [`get`]: #method.get " "
+/// [`get_mut`]: #method.get_mut " "
+/// [`NodeRef`]: struct.NodeRef.html " "
+/// [`NodeRefMut`]: struct.NodeRefMut.html " "
+/// [below]: #sparse-storage " "
+/// [`contains_key`]: #tymethod.contains_key " "
+/// [`num_nodes`]: #tymethod.num_nodes " "
+/// [`node_value`]: #tymethod.node_value " "
+/// [`StableStorage`]: ../trait.StableStorage.html " "
+pub trait Tree: Traversable {
+    /// Returns the number of nodes in the tree. For trees backed by sparse storage, this includes the holes left behind by removed nodes, as described in the [trait-level documentation].
+    ///
+    /// [trait-level documentation]: #sparse-storage " "
+    fn num_nodes(&self) -> usize;
+    /// Returns `true` if the specified key points to a node in the tree, `false` otherwise. For trees backed by sparse storage, this is also `true` for the key of a removed node as long as its slot is a hole, as described in the [trait-level documentation].
+    ///
+    /// [trait-level documentation]: #sparse-storage " "
+    fn contains_key(&self, key: &Self::Cursor) -> bool;
+    /// Returns a by-reference `NodeValue` of the node with the specified key, or `None` if the key is invalid.
+    fn node_value(&self, key: &Self::Cursor) -> Option<NodeValue<&'_ Self::Branch, &'_ Self::Leaf>>;
+    /// Returns a *mutable* by-reference `NodeValue` of the node with the specified key, or `None` if the key is invalid.
+    fn node_value_mut(
+        &mut self,
+        key: &Self::Cursor,
+    ) -> Option<NodeValue<&'_ mut Self::Branch, &'_ mut Self::Leaf>>;
+    /// Returns the key of the parent of the node with the specified key, or `None` if the node is the root node or the key is invalid.
+    fn parent_key(&self, key: &Self::Cursor) -> Option<Self::Cursor>;
+    /// Returns the key of the first child of the node with the specified key, or `None` if the node is a leaf or the key is invalid.
+    fn first_child_key(&self, key: &Self::Cursor) -> Option<Self::Cursor>;
+    /// Returns the key of the sibling which directly follows the node with the specified key, or `None` if the node is the last child of its parent, is the root node or the key is invalid.
+    fn next_sibling_key(&self, key: &Self::Cursor) -> Option<Self::Cursor>;
+
+    /// Returns the key of the root node.
+    fn root_key(&self) -> Self::Cursor {
+        self.cursor_to_root()
+    }
+    /// Returns an iterator over the keys of the children of the node with the specified key. The iterator is empty if the node is a leaf or the key is invalid.
+    fn children_keys(&self, key: &Self::Cursor) -> ChildKeys<'_, Self> {
+        ChildKeys {
+            tree: self,
+            next: self.first_child_key(key),
+        }
+    }
+    /// Returns a reference to the node with the specified key, or `None` if the key is invalid.
+    fn get(&self, key: Self::Cursor) -> Option<NodeRef<'_, Self>> {
+        if self.contains_key(&key) {
+            Some(NodeRef { tree: self, key })
+        } else {
+            None
+        }
+    }
+    /// Returns a *mutable* reference to the node with the specified key, or `None` if the key is invalid.
+    fn get_mut(&mut self, key: Self::Cursor) -> Option<NodeRefMut<'_, Self>> {
+        if self.contains_key(&key) {
+            Some(NodeRefMut { tree: self, key })
+        } else {
+            None
+        }
+    }
+    /// Returns a reference to the node at the end of the specified path of child indices, which starts at the root node, or `None` if there is no such node. The [`Path`] type can be used to print and parse such paths.
+    ///
+    /// [`Path`]: ../path/struct.Path.html " "
+    fn node_at_path(&self, path: &[usize]) -> Option<NodeRef<'_, Self>> {
+        let key = path.iter().try_fold(self.root_key(), |key, &index| {
+            self.children_keys(&key).nth(index)
+        })?;
+        Some(NodeRef { tree: self, key })
+    }
+    /// Returns a *mutable* reference to the node at the end of the specified path of child indices, which starts at the root node, or `None` if there is no such node.
+    fn node_at_path_mut(&mut self, path: &[usize]) -> Option<NodeRefMut<'_, Self>> {
+        let key = self.node_at_path(path)?.into_raw_key();
+        Some(NodeRefMut { tree: self, key })
+    }
+    /// Replaces the payload of the root node, returning the old one. Since the root node can be either a leaf or a branch node, *this method is only available when the payload for leaf nodes and branch nodes is the same.*
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, BinaryTree};
+    ///
+    /// let mut tree = BinaryTree::<_>::new("Old");
+    /// tree.root_mut().make_branch("Left", None).unwrap();
+    /// assert_eq!(tree.set_root_value("New"), "Old");
+    /// assert_eq!(*tree.root().value().into_inner(), "New");
+    /// ```
+    fn set_root_value(&mut self, value: Self::Leaf) -> Self::Leaf
+    where
+        Self: Traversable<Branch = <Self as Traversable>::Leaf>,
+    {
+        let root_key = self.root_key();
+        match self
+            .node_value_mut(&root_key)
+            .expect("the root node is always present")
+        {
+            NodeValue::Branch(payload) | NodeValue::Leaf(payload) => mem::replace(payload, value),
+        }
+    }
+    /// Returns the path of child indices from the root node to the node with the specified key, or `None` if the key is invalid. See [`Path`] for more.
+    ///
+    /// [`Path`]: ../path/struct.Path.html " "
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+    fn path_to(&self, key: &Self::Cursor) -> Option<Path> {
+        Path::to_node(self, key)
+    }
+    /// Returns a reference to the node which the specified handle was created for, or `None` if the handle is no longer valid.
+    ///
+    /// See [`NodeHandle`] for the circumstances under which handles stay valid.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, FreeformTree};
+    ///
+    /// let mut tree = FreeformTree::<_>::new(0);
+    /// tree.root_mut().make_branch(vec![1, 2]).unwrap();
+    /// let first_child = tree.first_child_key(&tree.root_key()).unwrap();
+    /// let handle = tree.get(first_child).unwrap().handle();
+    /// // Handles are not tied to the lifetime of the tree, which can be modified in the meantime.
+    /// tree.root_mut().try_push_back(3).unwrap();
+    /// assert_eq!(*tree.upgrade(&handle).unwrap().value().into_inner(), 1);
+    /// ```
+    ///
+    /// [`NodeHandle`]: struct.NodeHandle.html " "
+    fn upgrade(&self, handle: &NodeHandle<Self::Cursor>) -> Option<NodeRef<'_, Self>> {
+        self.get(handle.key.clone())
+    }
+    /// Returns a *mutable* reference to the node which the specified handle was created for, or `None` if the handle is no longer valid.
+    ///
+    /// See [`NodeHandle`] for the circumstances under which handles stay valid.
+    ///
+    /// [`NodeHandle`]: struct.NodeHandle.html " "
+    fn upgrade_mut(&mut self, handle: &NodeHandle<Self::Cursor>) -> Option<NodeRefMut<'_, Self>> {
+        self.get_mut(handle.key.clone())
+    }
+    /// Reserves capacity for at least the specified number of additional nodes, so that adding them, as happens when a large amount of leaves is subdivided, does not repeatedly reallocate the storage.
+    ///
+    /// The default implementation does nothing. The trees in this crate forward it to the `reserve` method of their storage, which means that it panics if the storage has a fixed capacity which cannot fit the nodes.
+    fn reserve_nodes(&mut self, additional: usize) {
+        let _ = additional;
+    }
+    /// Applies the specified closures to the payloads of all nodes of the tree, the first one for branch nodes and the second one for leaf nodes, without changing the structure of the tree.
+    ///
+    /// This is meant for bulk updates of the payloads, such as marking every node as dirty, and is faster than driving a mutating visitor through the tree, since it doesn't need to keep track of a traversal. The default implementation walks the tree in depth-first pre-order without allocating memory, which takes *O*(*n*) time. The trees in this crate which use sparse storage, which is the default one, shadow it with an inherent method which processes the nodes in the order in which they're stored instead if the storage has no holes, which is the case for trees which had no nodes removed or were [defragmented]. The closures thus shouldn't rely on the order in which the nodes are visited.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, BinaryTree};
+    ///
+    /// let mut tree = BinaryTree::<_>::new(1);
+    /// tree.root_mut().make_branch(2, Some(3)).unwrap();
+    /// // Calling the trait method makes the nodes get visited in pre-order.
+    /// let mut order = Vec::new();
+    /// Tree::map_values_mut(&mut tree, |x| order.push(*x), |x| *x *= 10);
+    /// assert_eq!(order, [1]);
+    /// assert_eq!(tree.root().children().unwrap().0.value().into_inner(), &20);
+    /// ```
+    ///
+    /// [defragmented]: ../defrag/trait.Defragment.html#method.defragment " "
+    fn map_values_mut(
+        &mut self,
+        mut branch_fn: impl FnMut(&mut Self::Branch),
+        mut leaf_fn: impl FnMut(&mut Self::Leaf),
+    ) {
+        let mut next = Some(self.root_key());
+        while let Some(key) = next {
+            match self
+                .node_value_mut(&key)
+                .expect("keys of reachable nodes are valid")
+            {
+                NodeValue::Branch(payload) => branch_fn(payload),
+                NodeValue::Leaf(payload) => leaf_fn(payload),
+            }
+            next = next_in_preorder(self, key);
+        }
+    }
+    /// Returns an iterator which drives the specified visitor through the tree from the root node, yielding the cursor and value of every node it visits.
+    ///
+    /// Unlike [`TraverseIter`], the iterator only borrows the tree, so the tree can keep being used after the iteration. The final value of the visitor can be retrieved from the iterator once it's exhausted.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, BinaryTree, NodeValue, traversal::algorithms::FindFirst};
+    ///
+    /// let mut tree = BinaryTree::<_>::new(0);
+    /// tree.root_mut().make_branch(1, Some(2)).unwrap();
+    /// let visitor = FindFirst::<BinaryTree<_>, _>::new(|x: NodeValue<&u32, &u32>| *x.into_inner() == 2);
+    /// let mut iter = tree.traverse_iter(visitor);
+    /// assert_eq!(iter.by_ref().count(), 3);
+    /// let found = iter.into_output().flatten().unwrap();
+    /// // The tree is still available after the iteration.
+    /// assert_eq!(tree.parent_key(&found), Some(tree.root_key()));
+    /// ```
+    ///
+    /// [`TraverseIter`]: ../traversal/struct.TraverseIter.html " "
+    fn traverse_iter<V>(&self, visitor: V) -> TraverseValuesIter<'_, V, Self>
+    where
+        V: Visitor,
+        for<'a> &'a Self: Borrow<V::Target>,
+        Self::Cursor:
+            From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+    {
+        TraverseValuesIter::new(visitor, self)
+    }
+    /// Returns an iterator which drives the specified mutating visitor through the tree from the root node, performing one step with each iteration.
+    ///
+    /// Unlike [`TraverseMutIter`], the iterator only borrows the tree, so the tree can keep being used after the iteration.
+    ///
+    /// [`TraverseMutIter`]: ../traversal/struct.TraverseMutIter.html " "
+    fn traverse_iter_mut<V>(&mut self, visitor: V) -> TraverseMutRefIter<'_, V, Self>
+    where
+        Self: TraversableMut,
+        V: VisitorMut,
+        for<'a> &'a mut Self: BorrowMut<V::Target>,
+        Self::Cursor:
+            From<<V::Target as Traversable>::Cursor> + Into<<V::Target as Traversable>::Cursor>,
+    {
+        TraverseMutRefIter::new(visitor, self)
+    }
+    /// Walks the entire tree and checks whether the links between its nodes are consistent, returning the first inconsistency found.
+    ///
+    /// This is a debugging tool which is primarily useful when working with raw keys or implementing custom storages — trees which were only modified via the safe interfaces provided by Charcoal should always pass validation. The check runs in *O*(*n*) time and does not allocate memory.
+    ///
+    /// The following invariants are checked:
+    /// - The root node does not have a parent
+    /// - All keys stored in the nodes point to nodes which are present in the storage
+    /// - Every child of a node has that node as its parent
+    /// - The tree does not have cycles, i.e. the walk does not visit more nodes than there are in the storage
+    ///
+    /// Trees may check additional invariants specific to their structure, such as the consistency of sibling links in freeform trees.
+    ///
+    /// Whether a key points to a node which is present in the storage is checked with [`contains_key`], so a key to a removed node whose slot in a sparse storage is still a hole cannot be told apart from a valid one, as described in the [trait-level documentation], and makes the check panic once the hole is accessed. Other storages, including all [`StableStorage`]s, report such a key as a dangling one.
+    ///
+    /// # Errors
+    /// Returns the first inconsistency found, checking the nodes in depth-first pre-order, with the keys of the nodes involved.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, Octree};
+    ///
+    /// let mut tree = Octree::<_>::new(0);
+    /// tree.root_mut().make_branch([1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+    /// // Trees modified only through the safe interface are always valid.
+    /// assert_eq!(tree.validate(), Ok(()));
+    /// ```
+    /// A link to a node which has been removed from the storage behind the back of the tree, as faulty code working with raw keys could leave behind, is reported as a dangling key. This uses a storage which can be told to lose a node to simulate that:
+    /// ```rust
+    /// use charcoal::{
+    ///     binary_tree::{BinaryTree, Node as BinaryNode},
+    ///     freeform_tree::{FreeformTree, Node as FreeformNode},
+    ///     storage::Storage,
+    ///     tree::ValidationError,
+    ///     Tree,
+    /// };
+    /// use std::cell::Cell;
+    ///
+    /// thread_local! {
+    ///     static LOST_KEY: Cell<Option<usize>> = Cell::new(None);
+    /// }
+    /// /// A storage which pretends that the element at `LOST_KEY` was removed.
+    /// struct Lossy<T>(Vec<T>);
+    /// unsafe impl<T> Storage for Lossy<T> {
+    ///     type Key = usize;
+    ///     type Element = T;
+    ///     fn add(&mut self, element: T) -> usize {
+    ///         self.0.push(element);
+    ///         self.0.len() - 1
+    ///     }
+    ///     fn remove(&mut self, key: &usize) -> T {
+    ///         self.0.remove(*key)
+    ///     }
+    ///     fn len(&self) -> usize {
+    ///         self.0.len()
+    ///     }
+    ///     fn with_capacity(capacity: usize) -> Self {
+    ///         Self(Vec::with_capacity(capacity))
+    ///     }
+    ///     unsafe fn get_unchecked(&self, key: &usize) -> &T {
+    ///         self.0.get_unchecked(*key)
+    ///     }
+    ///     unsafe fn get_unchecked_mut(&mut self, key: &usize) -> &mut T {
+    ///         self.0.get_unchecked_mut(*key)
+    ///     }
+    ///     fn contains_key(&self, key: &usize) -> bool {
+    ///         *key < self.0.len() && LOST_KEY.with(|lost_key| lost_key.get() != Some(*key))
+    ///     }
+    ///     fn capacity(&self) -> usize {
+    ///         self.0.capacity()
+    ///     }
+    ///     fn reserve(&mut self, additional: usize) {
+    ///         self.0.reserve(additional);
+    ///     }
+    /// }
+    ///
+    /// let mut binary_tree = BinaryTree::<_, _, usize, Lossy<BinaryNode<_, _, usize>>>::new(0);
+    /// binary_tree.root_mut().make_branch(1, Some(2)).unwrap();
+    /// let root = binary_tree.root_key();
+    /// let left_child = binary_tree.root().left_child().unwrap().into_raw_key();
+    /// LOST_KEY.with(|lost_key| lost_key.set(Some(left_child)));
+    /// assert_eq!(
+    ///     binary_tree.validate(),
+    ///     Err(ValidationError::DanglingKey { node: root, key: left_child }),
+    /// );
+    ///
+    /// LOST_KEY.with(|lost_key| lost_key.set(None));
+    /// let mut freeform_tree = FreeformTree::<_, _, usize, Lossy<FreeformNode<_, _, usize>>>::new(0);
+    /// freeform_tree.root_mut().make_branch(vec![1, 2, 3]).unwrap();
+    /// assert_eq!(freeform_tree.validate(), Ok(()));
+    /// let first_child = freeform_tree.root().nth_child(0).unwrap().into_raw_key();
+    /// let second_child = freeform_tree.root().nth_child(1).unwrap().into_raw_key();
+    /// LOST_KEY.with(|lost_key| lost_key.set(Some(second_child)));
+    /// assert_eq!(
+    ///     freeform_tree.validate(),
+    ///     Err(ValidationError::DanglingKey { node: first_child, key: second_child }),
+    /// );
+    /// ```
+    ///
+    /// [`contains_key`]: #tymethod.contains_key " "
+    /// [trait-level documentation]: #sparse-storage " "
+    /// [`StableStorage`]: ../trait.StableStorage.html " "
+    fn validate(&self) -> Result<(), ValidationError<Self::Cursor>> {
+        validate_with(self, |_| Ok(()))
+    }
+    /// Collects statistics about the nodes of the tree and the storage holding them in one pass over the tree, useful for tuning the memory usage of large trees.
+    ///
+    /// The default implementation cannot see the storage and thus reports a capacity equal to [`num_nodes`]. The trees in this crate report the capacity of their storage instead.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, Quadtree};
+    ///
+    /// let mut tree = Quadtree::<_>::new(0);
+    /// tree.root_mut().make_branch([1, 2, 3, 4]).unwrap();
+    /// tree.root_mut().nth_child_mut(0).unwrap().make_branch([5, 6, 7, 8]).unwrap();
+    /// tree.root_mut().nth_child_mut(0).unwrap().try_remove_children().unwrap();
+    /// let stats = tree.stats();
+    /// assert_eq!(stats.num_nodes, 5);
+    /// assert_eq!((stats.num_branches, stats.num_leaves), (1, 4));
+    /// // The removed children left holes in the sparse storage.
+    /// assert_eq!(stats.num_holes, 4);
+    /// assert_eq!(stats.max_depth, 1);
+    /// assert!(stats.capacity >= 9);
+    /// ```
+    ///
+    /// [`num_nodes`]: #tymethod.num_nodes " "
+    fn stats(&self) -> TreeStats {
+        stats_with_capacity(self, self.num_nodes())
+    }
+    /// Returns a reference to the first node, in depth-first pre-order, the value of which satisfies the specified predicate, or `None` if there's no such node.
+    ///
+    /// Unlike searching with a visitor, this does not involve the [`Traversable`] trait, so the types of the closure's arguments are always inferred.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, BinaryTree};
+    ///
+    /// let mut tree = BinaryTree::<_>::new(0);
+    /// tree.root_mut().make_branch(1, Some(2)).unwrap();
+    /// let found = tree.find(|value| *value.into_inner() == 2).unwrap();
+    /// assert!(found.is_leaf());
+    /// assert!(tree.find(|value| *value.into_inner() > 2).is_none());
+    /// ```
+    ///
+    /// [`Traversable`]: ../traversal/trait.Traversable.html " "
+    fn find(
+        &self,
+        mut predicate: impl FnMut(NodeValue<&'_ Self::Branch, &'_ Self::Leaf>) -> bool,
+    ) -> Option<NodeRef<'_, Self>> {
+        let mut next = Some(self.root_key());
+        while let Some(key) = next {
+            let value = self.node_value(&key).expect("keys of reachable nodes are valid");
+            if predicate(value) {
+                return Some(NodeRef { tree: self, key });
+            }
+            next = next_in_preorder(self, key);
+        }
+        None
+    }
+    /// Calls the specified closure on the values of the nodes in depth-first pre-order, returning the first result which is `Some`, or `None` if the closure returned `None` for all nodes.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, FreeformTree, NodeValue};
+    ///
+    /// let mut tree = FreeformTree::<_, &str>::new("leaf");
+    /// tree.root_mut().make_branch_with(vec!["first", "second"], |_| 2_usize).unwrap();
+    /// let first_leaf_len = tree.find_map(|value| match value {
+    ///     NodeValue::Leaf(leaf) => Some(leaf.len()),
+    ///     NodeValue::Branch(..) => None,
+    /// });
+    /// assert_eq!(first_leaf_len, Some(5));
+    /// ```
+    fn find_map<R>(
+        &self,
+        mut f: impl FnMut(NodeValue<&'_ Self::Branch, &'_ Self::Leaf>) -> Option<R>,
+    ) -> Option<R> {
+        let mut next = Some(self.root_key());
+        while let Some(key) = next {
+            let value = self.node_value(&key).expect("keys of reachable nodes are valid");
+            if let Some(result) = f(value) {
+                return Some(result);
+            }
+            next = next_in_preorder(self, key);
+        }
+        None
+    }
+    /// Returns `true` if the value of at least one node satisfies the specified predicate, `false` otherwise. The nodes are checked in depth-first pre-order, and the search stops at the first match.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, Octree};
+    ///
+    /// let mut tree = Octree::<_>::new(0);
+    /// tree.root_mut().make_branch([1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+    /// assert!(tree.any(|value| *value.into_inner() == 8));
+    /// assert!(!tree.any(|value| *value.into_inner() > 8));
+    /// ```
+    fn any(
+        &self,
+        predicate: impl FnMut(NodeValue<&'_ Self::Branch, &'_ Self::Leaf>) -> bool,
+    ) -> bool {
+        self.find(predicate).is_some()
+    }
+    /// Returns `true` if the values of all nodes satisfy the specified predicate, `false` otherwise. The nodes are checked in depth-first pre-order, and the search stops at the first node which doesn't satisfy the predicate.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, Quadtree, NodeValue};
+    ///
+    /// let mut tree = Quadtree::<_>::new(0);
+    /// tree.root_mut().make_branch([1, 2, 3, 4]).unwrap();
+    /// // Only the leaves have to be positive.
+    /// assert!(tree.all(|value| match value {
+    ///     NodeValue::Branch(..) => true,
+    ///     NodeValue::Leaf(leaf) => *leaf > 0,
+    /// }));
+    /// assert!(!tree.all(|value| *value.into_inner() > 0));
+    /// ```
+    fn all(
+        &self,
+        mut predicate: impl FnMut(NodeValue<&'_ Self::Branch, &'_ Self::Leaf>) -> bool,
+    ) -> bool {
+        self.find(|value| !predicate(value)).is_none()
+    }
+    /// Returns the path of child indices from the root node to the first node, in depth-first pre-order, the value of which satisfies the specified predicate, or `None` if there's no such node. See [`Path`] for more.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, BinaryTree};
+    ///
+    /// let mut tree = BinaryTree::<_>::new(0);
+    /// tree.root_mut().make_branch(1, Some(2)).unwrap();
+    /// tree.root_mut().right_child_mut().unwrap().make_branch(3, Some(4)).unwrap();
+    /// let path = tree.position(|value| *value.into_inner() == 4).unwrap();
+    /// assert_eq!(path.as_slice(), [1, 1]);
+    /// assert_eq!(tree.node_at_path(&path).unwrap().value().into_inner(), &4);
+    /// assert!(tree.position(|value| *value.into_inner() > 4).is_none());
+    /// ```
+    ///
+    /// [`Path`]: ../path/struct.Path.html " "
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+    fn position(
+        &self,
+        predicate: impl FnMut(NodeValue<&'_ Self::Branch, &'_ Self::Leaf>) -> bool,
+    ) -> Option<Path> {
+        let found = self.find(predicate)?;
+        Some(Path::to_node(self, &found.key).expect("keys of reachable nodes are valid"))
+    }
+    /// Returns an iterator over the nodes of the tree as a stream of [pre-order events]: `Enter` for every branch node, followed by the events for its children and a matching `Exit`, and `Leaf` for every leaf node.
+    ///
+    /// This is the most convenient shape for writing serializers, pretty-printers and converters without recursion, and is the reverse of [`TreeWriter`], which builds freeform trees from such events. The iterator walks the tree using the parent links of the nodes and does not allocate.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, BinaryTree, tree::PreorderEvent};
+    ///
+    /// let mut tree = BinaryTree::<_>::new("Root");
+    /// tree.root_mut().make_branch("Left", Some("Right")).unwrap();
+    ///
+    /// let mut xml = String::new();
+    /// for event in tree.events() {
+    ///     match event {
+    ///         PreorderEvent::Enter(name) => xml.push_str(&format!("<{}>", name)),
+    ///         PreorderEvent::Leaf(name) => xml.push_str(&format!("<{}/>", name)),
+    ///         PreorderEvent::Exit => xml.push_str("</>"),
+    ///     }
+    /// }
+    /// assert_eq!(xml, "<Root><Left/><Right/></>");
+    /// ```
+    ///
+    /// [pre-order events]: enum.PreorderEvent.html " "
+    /// [`TreeWriter`]: ../freeform_tree/struct.TreeWriter.html " "
+    fn events(&self) -> Events<'_, Self> {
+        Events {
+            tree: self,
+            next: Some(EventStep::Visit(self.root_key())),
+        }
+    }
+    /// Returns an iterator over the levels of the tree in breadth-first order, yielding the nodes at every depth as a `Vec`, starting with the one containing only the root node. The nodes within a level are ordered as in depth-first pre-order, so siblings stay adjacent and ordered.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, BinaryTree};
+    ///
+    /// let mut tree = BinaryTree::<_>::new(0);
+    /// tree.root_mut().make_branch(1, Some(2)).unwrap();
+    /// tree.root_mut().left_child_mut().unwrap().make_branch(3, Some(4)).unwrap();
+    /// let widths = tree.iter_levels().map(|level| level.len()).collect::<Vec<_>>();
+    /// assert_eq!(widths, [1, 2, 2]);
+    /// let last = tree.iter_levels().last().unwrap();
+    /// let values = last.iter().map(|node| *node.value().into_inner()).collect::<Vec<_>>();
+    /// assert_eq!(values, [3, 4]);
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+    fn iter_levels(&self) -> Levels<'_, Self> {
+        Levels {
+            tree: self,
+            level: alloc::vec![self.root_key()],
+        }
+    }
+    /// Returns an iterator over the leaf nodes of the tree in depth-first pre-order, yielding every leaf along with the path of child indices from the root node to it. See [`Path`] for more.
+    ///
+    /// The path is maintained while walking the tree instead of being found separately for every leaf, which makes this the cheapest way to generate prefix codes or flatten a hierarchy into a list of entries. The payloads on the way to a leaf can be reached by walking up from it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, BinaryTree};
+    ///
+    /// // A Huffman tree for three symbols.
+    /// let mut tree = BinaryTree::<_>::new('*');
+    /// let mut root = tree.root_mut();
+    /// root.make_branch('a', Some('*')).unwrap();
+    /// root.right_child_mut().unwrap().make_branch('b', Some('c')).unwrap();
+    ///
+    /// let codes = tree
+    ///     .leaf_paths()
+    ///     .map(|(path, leaf)| {
+    ///         let code = path.iter().map(|index| index.to_string()).collect::<String>();
+    ///         (*leaf.value().into_inner(), code)
+    ///     })
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(codes, [('a', "0".to_string()), ('b', "10".to_string()), ('c', "11".to_string())]);
+    /// ```
+    ///
+    /// [`Path`]: ../path/struct.Path.html " "
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+    fn leaf_paths(&self) -> LeafPaths<'_, Self> {
+        LeafPaths {
+            tree: self,
+            next: Some(self.root_key()),
+            indices: Vec::new(),
+        }
+    }
+    /// Serializes the tree into a JSON value, using the schema described in the [`json`] module.
+    ///
+    /// # Errors
+    /// Will fail if serializing one of the payloads fails.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, BinaryTree};
+    /// use serde_json::json;
+    ///
+    /// let mut tree = BinaryTree::<_>::new(1);
+    /// tree.root_mut().make_branch(2, None).unwrap();
+    /// assert_eq!(
+    ///     tree.to_json().unwrap(),
+    ///     json!({ "payload": 1, "children": [{ "payload": 2 }] }),
+    /// );
+    /// ```
+    ///
+    /// [`json`]: ../json/index.html " "
+    #[cfg(feature = "json")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "json")))]
+    fn to_json(&self) -> Result<serde_json::Value, serde_json::Error>
+    where
+        Self::Branch: serde::Serialize,
+        Self::Leaf: serde::Serialize,
+    {
+        crate::json::tree_to_json(self)
+    }
+    /// Encodes the tree in the [binary format].
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, Octree};
+    ///
+    /// let mut tree = Octree::<_>::new(0_u32);
+    /// tree.root_mut().make_branch([1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+    /// let bytes = tree.to_bytes();
+    /// // Every node takes up 3 bytes: the number of children, the length of the payload and the
+    /// // payload itself, all of which are below 128. The header takes up 9 more bytes and the
+    /// // checksum takes up 4.
+    /// assert_eq!(bytes.len(), 9 + 9 * 3 + 4);
+    /// assert_eq!(Octree::<u32>::from_bytes(&bytes).unwrap().to_repr(), tree.to_repr());
+    /// ```
+    ///
+    /// [binary format]: ../codec/index.html " "
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+    fn to_bytes(&self) -> Vec<u8>
+    where
+        Self::Branch: BinaryPayload,
+        Self::Leaf: BinaryPayload,
+    {
+        let mut bytes = Vec::new();
+        let result = crate::codec::encode_tree(self, |piece| {
+            bytes.extend_from_slice(piece);
+            Ok::<_, Infallible>(())
+        });
+        match result {
+            Ok(()) => bytes,
+            Err(never) => match never {},
+        }
+    }
+    /// Encodes the tree in the [binary format] into the specified slice, returning the number of bytes written.
+    ///
+    /// # Errors
+    /// Will fail if the slice is too small for the encoded tree, in which case the contents of the slice are unspecified.
+    ///
+    /// [binary format]: ../codec/index.html " "
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+    fn write_to_slice(&self, buf: &mut [u8]) -> Result<usize, BufferTooSmallError>
+    where
+        Self::Branch: BinaryPayload,
+        Self::Leaf: BinaryPayload,
+    {
+        let mut num_written = 0;
+        crate::codec::encode_tree(self, |piece| {
+            let end = num_written + piece.len();
+            buf.get_mut(num_written..end)
+                .ok_or(BufferTooSmallError)?
+                .copy_from_slice(piece);
+            num_written = end;
+            Ok(())
+        })?;
+        Ok(num_written)
+    }
+    /// Writes the tree in the [binary format] to the writer.
+    ///
+    /// The writer is written to in small pieces, so it should be buffered.
+    ///
+    /// # Errors
+    /// Will fail if writing fails.
+    ///
+    /// [binary format]: ../codec/index.html " "
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+    fn write_to(&self, mut writer: impl Write) -> io::Result<()>
+    where
+        Self::Branch: BinaryPayload,
+        Self::Leaf: BinaryPayload,
+    {
+        crate::codec::encode_tree(self, |piece| writer.write_all(piece))
+    }
+    /// Freezes the tree, making it immutable and allowing it to be shared between threads without locking. See [`FrozenTree`] for more.
+    ///
+    /// [`FrozenTree`]: ../frozen/struct.FrozenTree.html " "
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+    fn freeze(self) -> FrozenTree<Self>
+    where
+        Self: Sized,
+    {
+        FrozenTree::new(self)
+    }
+    /// Finds the subtrees which are structurally identical, i.e. have equal payloads in the same shape, using [hash-consing].
+    ///
+    /// Every subtree gets a number which only depends on the payload of its root and the numbers of its children, so that identical subtrees get the same number. Since every node has exactly one parent, the tree itself cannot share the duplicates, but the result reports them and counts the distinct subtrees, which is the number of nodes the tree would have if it was a DAG with the duplicates merged. This is especially useful for [frozen] trees, which are read-only anyway and can thus use the result as a DAG view of themselves, and for quadtrees of images, which tend to have massive duplication.
+    ///
+    /// # Example
+    /// ```rust
+    /// use charcoal::{Tree, Quadtree};
+    ///
+    /// let mut tree = Quadtree::<_>::new(0);
+    /// tree.root_mut().make_branch([1, 0, 1, 0]).unwrap();
+    /// tree.root_mut().nth_child_mut(0).unwrap().make_branch([2, 2, 3, 3]).unwrap();
+    /// tree.root_mut().nth_child_mut(2).unwrap().make_branch([2, 2, 3, 3]).unwrap();
+    /// let duplicates = tree.dedup_subtrees();
+    /// // The two identical branches, the two leaves with 0, and the leaves with 2 and with 3
+    /// // inside of the identical branches.
+    /// assert_eq!(duplicates.groups().len(), 4);
+    /// assert_eq!(duplicates.num_nodes(), 13);
+    /// assert_eq!(duplicates.num_distinct(), 5);
+    /// ```
+    ///
+    /// [hash-consing]: https://en.wikipedia.org/wiki/Hash_consing " "
+    /// [frozen]: ../frozen/struct.FrozenTree.html " "
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+    fn dedup_subtrees(&self) -> SubtreeDuplicates<Self::Cursor>
+    where
+        Self::Branch: Hash + Eq,
+        Self::Leaf: Hash + Eq,
+    {
+        find_duplicate_subtrees(self)
+    }
+}
+
+/// Statistics about the nodes of a tree and the storage holding them, returned by [`Tree::stats`].
+///
+/// [`Tree::stats`]: trait.Tree.html#method.stats " "
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct TreeStats {
+    /// The number of nodes in the tree, including the root node.
+    pub num_nodes: usize,
+    /// The number of branch nodes in the tree.
+    pub num_branches: usize,
+    /// The number of leaf nodes in the tree.
+    pub num_leaves: usize,
+    /// The number of slots in the storage which are left over from removed nodes and will be reused by nodes added later. Only sparse storage has holes, and they can be eliminated by defragmenting it.
+    pub num_holes: usize,
+    /// The number of nodes which the storage can hold without reallocating, including the ones it holds already.
+    pub capacity: usize,
+    /// The number of edges on the longest path from the root node to a leaf, which is `0` for a tree which only consists of the root node.
+    pub max_depth: usize,
+}
+
+/// The structurally identical subtrees of a tree, returned by [`Tree::dedup_subtrees`].
+///
+/// [`Tree::dedup_subtrees`]: trait.Tree.html#method.dedup_subtrees " "
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SubtreeDuplicates<K> {
+    groups: Vec<Vec<K>>,
+    num_nodes: usize,
+    num_distinct: usize,
+}
+#[cfg(feature = "std")]
+impl<K> SubtreeDuplicates<K> {
+    /// Returns the groups of keys to the roots of identical subtrees. Every group has at least two keys, and both the groups and the keys within them are ordered by the depth-first post-order position of the subtree roots.
+    ///
+    /// The subtrees within a duplicated subtree are duplicated as well, so every group of branch nodes is accompanied by groups for their descendants.
+    #[must_use]
+    pub fn groups(&self) -> &[Vec<K>] {
+        &self.groups
+    }
+    /// Returns the groups of keys to the roots of identical subtrees, consuming the result. See [`groups`] for the order of the groups.
+    ///
+    /// [`groups`]: #method.groups " "
+    #[must_use]
+    pub fn into_groups(self) -> Vec<Vec<K>> {
+        self.groups
+    }
+    /// Returns `true` if there are no identical subtrees, `false` otherwise.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+    /// Returns the number of nodes reachable from the root node.
+    #[must_use]
+    pub const fn num_nodes(&self) -> usize {
+        self.num_nodes
+    }
+    /// Returns the number of distinct subtrees, which is the number of nodes the tree would have if all identical subtrees were merged into one, turning it into a DAG.
+    #[must_use]
+    pub const fn num_distinct(&self) -> usize {
+        self.num_distinct
+    }
+}
+
+/// Walks the tree in post-order, numbering every subtree by the payload of its root and the numbers of its children, and groups the keys by those numbers.
+#[cfg(feature = "std")]
+pub(crate) fn find_duplicate_subtrees<T>(tree: &T) -> SubtreeDuplicates<T::Cursor>
+where
+    T: Tree,
+    T::Branch: Hash + Eq,
+    T::Leaf: Hash + Eq,
+{
+    let mut numbers = HashMap::new();
+    // The keys of the nodes with each number, indexed by the number.
+    let mut members: Vec<Vec<T::Cursor>> = Vec::new();
+    // The numbers of the nodes whose parents have not been visited yet. In post-order, the
+    // children of a node are always the last ones pushed before the node itself is visited.
+    let mut stack = Vec::new();
+    let root = tree.root_key();
+    let mut key = first_in_postorder(tree, &root);
+    loop {
+        let num_children = tree.num_children_of(&key);
+        let children = stack.split_off(stack.len() - num_children);
+        let value = tree.node_value(&key).expect("keys of reachable nodes are valid");
+        let next_number = members.len();
+        let number = *numbers.entry((value, children)).or_insert(next_number);
+        if number == next_number {
+            members.push(Vec::new());
+        }
+        members[number].push(key.clone());
+        stack.push(number);
+        if key == root {
+            break;
+        }
+        key = next_in_postorder(tree, &key).expect("the root node is visited last");
+    }
+    let num_distinct = members.len();
+    let num_nodes = members.iter().map(Vec::len).sum();
+    SubtreeDuplicates {
+        groups: members.into_iter().filter(|group| group.len() > 1).collect(),
+        num_nodes,
+        num_distinct,
+    }
+}
+
+/// Returns the key of the node which comes after the specified one in depth-first pre-order, or `None` if the node is the last one.
+pub(crate) fn next_in_preorder<T: Tree>(tree: &T, key: T::Cursor) -> Option<T::Cursor> {
+    if let Some(first_child) = tree.first_child_key(&key) {
+        return Some(first_child);
+    }
+    let root = tree.root_key();
+    let mut key = key;
+    loop {
+        if key == root {
+            return None;
+        }
+        if let Some(next_sibling) = tree.next_sibling_key(&key) {
+            return Some(next_sibling);
+        }
+        key = tree
+            .parent_key(&key)
+            .expect("a non-root node always has a parent");
+    }
+}
+
+/// Walks the tree in pre-order, counting the nodes and the depth. The holes are the difference between the number of nodes reported by the tree and the number of nodes actually reachable from the root.
+pub(crate) fn stats_with_capacity<T: Tree>(tree: &T, capacity: usize) -> TreeStats {
+    let root = tree.root_key();
+    let mut stats = TreeStats {
+        capacity,
+        ..TreeStats::default()
+    };
+    let mut current = root.clone();
+    let mut depth = 0;
+    loop {
+        stats.num_nodes += 1;
+        stats.max_depth = stats.max_depth.max(depth);
+        if let Some(first_child) = tree.first_child_key(&current) {
+            stats.num_branches += 1;
+            current = first_child;
+            depth += 1;
+            continue;
+        }
+        stats.num_leaves += 1;
+        loop {
+            if current == root {
+                stats.num_holes = tree.num_nodes().saturating_sub(stats.num_nodes);
+                return stats;
+            }
+            if let Some(next_sibling) = tree.next_sibling_key(&current) {
+                current = next_sibling;
+                break;
+            }
+            current = tree
+                .parent_key(&current)
+                .expect("a non-root node always has a parent");
+            depth -= 1;
+        }
+    }
+}
+
+/// Walks the tree in pre-order, checking the invariants listed in the documentation of `Tree::validate` and running the specified closure on every node before checking its children.
+///
+/// The closure is only ever called with keys which are present in the storage.
+pub(crate) fn validate_with<T: Tree>(
+    tree: &T,
+    mut check_node: impl FnMut(&T::Cursor) -> Result<(), ValidationError<T::Cursor>>,
+) -> Result<(), ValidationError<T::Cursor>> {
+    let root = tree.root_key();
+    if !tree.contains_key(&root) {
+        return Err(ValidationError::DanglingKey {
+            node: root.clone(),
+            key: root,
+        });
+    }
+    if let Some(parent) = tree.parent_key(&root) {
+        return Err(ValidationError::RootHasParent { parent });
+    }
+    let num_nodes = tree.num_nodes();
+    let mut num_visited = 0_usize;
+    let mut current = root.clone();
+    loop {
+        num_visited += 1;
+        if num_visited > num_nodes {
+            return Err(ValidationError::Cycle { node: current });
+        }
+        check_node(&current)?;
+        // Bound the number of children so that a cycle in the sibling links cannot make us loop
+        // forever.
+        for (num_children, child) in tree.children_keys(&current).enumerate() {
+            if num_children >= num_nodes {
+                return Err(ValidationError::Cycle { node: current });
+            }
+            if !tree.contains_key(&child) {
+                return Err(ValidationError::DanglingKey {
+                    node: current,
+                    key: child,
+                });
+            }
+            let child_parent = tree.parent_key(&child);
+            if child_parent.as_ref() != Some(&current) {
+                return Err(ValidationError::ParentMismatch {
+                    child,
+                    expected_parent: current,
+                    found_parent: child_parent,
+                });
+            }
+        }
+        // All keys we're moving to from now on have been checked above.
+        if let Some(first_child) = tree.first_child_key(&current) {
+            current = first_child;
+            continue;
+        }
+        loop {
+            if current == root {
+                return Ok(());
+            }
+            if let Some(next_sibling) = tree.next_sibling_key(&current) {
+                current = next_sibling;
+                break;
+            }
+            current = tree
+                .parent_key(&current)
+                .expect("parent of a non-root node was already checked");
+        }
+    }
+}
+
+#[cfg(any(
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+/// A depth-first post-order walk which allows the caller to remove every node right after it's been visited, used by the consuming iterators of all tree types.
+///
+/// The walk goes over a sequence of sibling subtrees, called the top-level subtrees. Since every node is visited after all of its descendants, the only links which are ever followed are those of nodes which are still present: the parent and next sibling links of the node which is being visited, the child links of the nodes which haven't been visited yet and the child links of the parent of the node being visited, which are only compared against, never dereferenced. *This only holds if removing a node does not move any of the other ones around*, i.e. for sparse storages.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct PostorderRemoval<K> {
+    next: Option<K>,
+    top_parent: Option<K>,
+}
+#[cfg(any(
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+impl<K: Clone + Eq> PostorderRemoval<K> {
+    /// Starts the walk at the top-level subtree with the specified root. `top_parent` is the key of the parent of the top-level subtrees, or `None` if the walk goes over the entire tree.
+    pub(crate) fn new<T: Tree<Cursor = K>>(
+        tree: &T,
+        first_subtree: Option<K>,
+        top_parent: Option<K>,
+    ) -> Self {
+        Self {
+            next: first_subtree.map(|key| leftmost_leaf(tree, key)),
+            top_parent,
+        }
+    }
+    /// Returns the key of the next node to be visited, which the caller is expected to remove from the tree before calling this again.
+    ///
+    /// Once a top-level subtree is finished, `next_subtree` is called with the key of its root to find the root of the next one.
+    pub(crate) fn next_key<T: Tree<Cursor = K>>(
+        &mut self,
+        tree: &T,
+        next_subtree: impl FnOnce(&T, &K) -> Option<K>,
+    ) -> Option<K> {
+        let current = self.next.take()?;
+        let parent = tree.parent_key(&current);
+        self.next = if parent == self.top_parent {
+            next_subtree(tree, &current).map(|key| leftmost_leaf(tree, key))
+        } else if let Some(next_sibling) = tree.next_sibling_key(&current) {
+            Some(leftmost_leaf(tree, next_sibling))
+        } else {
+            parent
+        };
+        Some(current)
+    }
+}
+#[cfg(any(
+    feature = "alloc",
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+/// Descends from the specified node to its first child, then to the first child of that, and so on, returning the key of the leaf node at which the descent ends.
+pub(crate) fn leftmost_leaf<T: Tree>(tree: &T, mut key: T::Cursor) -> T::Cursor {
+    while let Some(first_child) = tree.first_child_key(&key) {
+        key = first_child;
+    }
+    key
+}
+
+/// The error type returned by [`Tree::validate`], describing an inconsistency in the links between the nodes of a tree.
+///
+/// [`Tree::validate`]: trait.Tree.html#method.validate " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ValidationError<K> {
+    /// The root node has a parent.
+    RootHasParent {
+        /// The key of the parent which the root node has.
+        parent: K,
+    },
+    /// A node contains a key which does not point to a node in the storage.
+    DanglingKey {
+        /// The key of the node which contains the dangling key.
+        node: K,
+        /// The dangling key itself.
+        key: K,
+    },
+    /// A child node does not have the node it's a child of as its parent.
+    ParentMismatch {
+        /// The key of the child node.
+        child: K,
+        /// The key of the node which has the child among its children.
+        expected_parent: K,
+        /// The parent which the child node actually has.
+        found_parent: Option<K>,
+    },
+    /// The previous and next sibling links of two nodes do not point to each other.
+    SiblingMismatch {
+        /// The key of the node which has the other one as its sibling.
+        node: K,
+        /// The key of the sibling which does not link back to the node.
+        sibling: K,
+    },
+    /// The last child of a branch node is not the last node in the chain of siblings which starts from its first child.
+    LastChildMismatch {
+        /// The key of the branch node.
+        parent: K,
+        /// The key of the last node in the chain of siblings.
+        expected_last_child: K,
+        /// The key which the branch node stores as its last child.
+        found_last_child: K,
+    },
+    /// The number of children which a branch node stores does not match the length of the chain of siblings which starts from its first child.
+    ChildCountMismatch {
+        /// The key of the branch node.
+        parent: K,
+        /// The length of the chain of siblings.
+        expected_child_count: usize,
+        /// The number of children which the branch node stores.
+        found_child_count: usize,
+    },
+    /// The walk through the tree visited more nodes than there are in the storage, meaning that there is a cycle.
+    Cycle {
+        /// The key of the node at which the walk was stopped.
+        node: K,
+    },
+}
+impl<K> Display for ValidationError<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::RootHasParent { .. } => "the root node has a parent",
+            Self::DanglingKey { .. } => "a node contains a key which is not present in the storage",
+            Self::ParentMismatch { .. } => "a child node does not link back to its parent",
+            Self::SiblingMismatch { .. } => "two sibling nodes do not link to each other",
+            Self::LastChildMismatch { .. } => "the last child of a node is not the last sibling",
+            Self::ChildCountMismatch { .. } => {
+                "the number of children of a node does not match its stored child count"
+            }
+            Self::Cycle { .. } => "the tree contains a cycle",
+        })
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "std")))]
+impl<K: Debug> std::error::Error for ValidationError<K> {}
+
+/// A handle to a node in any [`Tree`], produced by the `handle` method of [`NodeRef`] and [`NodeRefMut`].
+///
+/// Unlike node references, handles do not borrow the tree, which means that they can be stored for as long as needed and [upgraded] back into references later, which checks whether the node still exists. A handle is simply a key which is checked before use, which means that it stays valid for as long as the key does:
+/// - With storages which never reuse keys of removed nodes or version them, such as [`SlotMap`], the handle either leads to the same node or fails to upgrade once the node has been removed.
+/// - With sparse storage, which is the default one, the key of a removed node may be reused for a node added later, in which case the handle leads to that node instead. If the node has not been replaced yet, the storage cannot tell that the key points to a hole, and using the upgraded reference panics.
+/// - With storages which shift elements when one is removed, such as `Vec`, removing any node can make the handle lead to a different node.
+///
+/// [`Tree`]: trait.Tree.html " "
+/// [`NodeRef`]: struct.NodeRef.html " "
+/// [`NodeRefMut`]: struct.NodeRefMut.html " "
+/// [upgraded]: trait.Tree.html#method.upgrade " "
+/// [`SlotMap`]: https://docs.rs/slotmap/*/slotmap/struct.SlotMap.html " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NodeHandle<K> {
+    key: K,
+}
+impl<K> NodeHandle<K> {
+    /// Creates a handle from a raw storage key.
+    pub const fn from_raw_key(key: K) -> Self {
+        Self { key }
+    }
+    /// Returns a reference the raw storage key for the node.
+    pub const fn raw_key(&self) -> &K {
+        &self.key
+    }
+    /// Consumes the handle and returns the underlying raw storage key for the node.
+    #[allow(clippy::missing_const_for_fn)] // Clippy has no idea what a destructor is
+    pub fn into_raw_key(self) -> K {
+        self.key
+    }
+}
+
+/// A reference to a node in any [`Tree`], produced by its [`get`] method. The node references of the trees in this crate can be converted into it with `From`.
+///
+/// # Traversal
+/// A node reference is also a [`Traversable`] view of the subtree which starts at the node, with the node as its root, so that any visitor or algorithm can be run on a part of a tree without copying it. The cursors are the keys of the nodes in the tree, so they can be used with the tree itself afterwards.
+///
+/// The node has no parent and no siblings as far as the view is concerned, so moving to them fails like it does for the root node of a tree, and [`contains_cursor`] only accepts cursors to nodes in the subtree, which takes *O*(*d*) time, where *d* is the depth of the node below the root of the view. The other methods do not check whether a cursor is in the subtree, since that would have to be done on every step.
+///
+/// ```rust
+/// use charcoal::{
+///     traversal::algorithms::find_all,
+///     tree::NodeRef,
+///     FreeformTree,
+///     Traversable,
+///     Tree,
+/// };
+///
+/// let mut tree = FreeformTree::<_>::new(0);
+/// tree.root_mut().make_branch(vec![1, 2]).unwrap();
+/// tree.root_mut().first_child_mut().unwrap().make_branch(vec![3, 4]).unwrap();
+///
+/// let first_child = NodeRef::from(tree.root().first_child().unwrap());
+/// let found = find_all(&first_child, |value| *value.into_inner() > 0)
+///     .into_iter()
+///     .map(|key| *tree.node_value(&key).unwrap().into_inner())
+///     .collect::<Vec<_>>();
+/// // The second child of the root node is not a part of the subtree.
+/// assert_eq!(found, [1, 3, 4]);
+/// assert!(first_child.parent_of(&first_child.cursor_to_root()).is_none());
+/// assert!(!first_child.contains_cursor(&tree.root_key()));
+/// ```
+///
+/// [`Tree`]: trait.Tree.html " "
+/// [`get`]: trait.Tree.html#method.get " "
+/// [`Traversable`]: ../traversal/trait.Traversable.html " "
+/// [`contains_cursor`]: ../traversal/trait.Traversable.html#method.contains_cursor " "
+pub struct NodeRef<'a, T: Tree> {
+    pub(crate) tree: &'a T,
+    pub(crate) key: T::Cursor,
+}
+impl<'a, T: Tree> NodeRef<'a, T> {
+    /// Returns the tree which the node belongs to.
+    pub fn tree(&self) -> &'a T {
+        self.tree
+    }
+    /// Returns a reference the raw storage key for the node.
+    pub fn raw_key(&self) -> &T::Cursor {
+        &self.key
+    }
+    /// Consumes the reference and returns the underlying raw storage key for the node.
+    pub fn into_raw_key(self) -> T::Cursor {
+        self.key
+    }
+    /// Returns a handle to the node, which can be stored without borrowing the tree and [upgraded] back into a reference later.
+    ///
+    /// [upgraded]: trait.Tree.html#method.upgrade " "
+    pub fn handle(&self) -> NodeHandle<T::Cursor> {
+        NodeHandle {
+            key: self.key.clone(),
+        }
+    }
+    /// Returns a reference to the data stored in the node.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn value(&self) -> NodeValue<&'a T::Branch, &'a T::Leaf> {
+        self.tree
+            .node_value(&self.key)
+            .expect("key was invalidated while a node reference existed")
+    }
+    /// Returns a reference to the parent node of the pointee, or `None` if it's the root node.
+    pub fn parent(&self) -> Option<Self> {
+        self.tree.parent_key(&self.key).map(|key| Self {
+            tree: self.tree,
+            key,
+        })
+    }
+    /// Returns `true` if the node is the root node, `false` otherwise.
+    pub fn is_root(&self) -> bool {
+        self.tree.parent_key(&self.key).is_none()
+    }
+    /// Returns `true` if the node is a *leaf*, i.e. does not have child nodes; `false` otherwise.
+    pub fn is_leaf(&self) -> bool {
+        self.tree.first_child_key(&self.key).is_none()
+    }
+    /// Returns `true` if the node is a *branch*, i.e. has child nodes; `false` otherwise.
+    pub fn is_branch(&self) -> bool {
+        !self.is_leaf()
+    }
+    /// Returns a reference to the first child of the node, or `None` if it's a leaf node.
+    pub fn first_child(&self) -> Option<Self> {
+        self.tree.first_child_key(&self.key).map(|key| Self {
+            tree: self.tree,
+            key,
+        })
+    }
+    /// Returns a reference to the next sibling of the node, or `None` if it's the last child of its parent or the root node.
+    pub fn next_sibling(&self) -> Option<Self> {
+        self.tree.next_sibling_key(&self.key).map(|key| Self {
+            tree: self.tree,
+            key,
+        })
+    }
+    /// Returns an iterator over references to the children of the node. The iterator is empty if the node is a leaf node.
+    pub fn children(&self) -> Children<'a, T> {
+        Children {
+            inner: self.tree.children_keys(&self.key),
+        }
+    }
+    /// Returns an iterator over the keys of the children of the node. The iterator is empty if the node is a leaf node.
+    pub fn children_keys(&self) -> ChildKeys<'a, T> {
+        self.tree.children_keys(&self.key)
+    }
+}
+impl<T: Tree> Clone for NodeRef<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree,
+            key: self.key.clone(),
+        }
+    }
+}
+impl<T: Tree + Debug> Debug for NodeRef<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NodeRef")
+            .field("tree", &self.tree)
+            .field("key", &self.key)
+            .finish()
+    }
+}
+impl<'a, T: Tree> From<NodeRefMut<'a, T>> for NodeRef<'a, T> {
+    fn from(op: NodeRefMut<'a, T>) -> Self {
+        op.downgrade()
+    }
+}
+impl<T: Tree> Traversable for NodeRef<'_, T> {
+    type Leaf = T::Leaf;
+    type Branch = T::Branch;
+    type Cursor = T::Cursor;
+
+    fn advance_cursor<V>(
+        &self,
+        cursor: Self::Cursor,
+        direction: VisitorDirection<Self::Cursor, V>,
+    ) -> CursorResult<Self::Cursor> {
+        advance_in_subtree(self.tree, &self.key, cursor, direction)
+    }
+    fn cursor_to_root(&self) -> Self::Cursor {
+        self.key.clone()
+    }
+    fn value_of(&self, cursor: &Self::Cursor) -> NodeValue<&'_ Self::Branch, &'_ Self::Leaf> {
+        self.tree.value_of(cursor)
+    }
+    fn parent_of(&self, cursor: &Self::Cursor) -> Option<Self::Cursor> {
+        parent_in_subtree(self.tree, &self.key, cursor)
+    }
+    fn num_children_of(&self, cursor: &Self::Cursor) -> usize {
+        self.tree.num_children_of(cursor)
+    }
+    fn nth_child_of(&self, cursor: &Self::Cursor, child_num: usize) -> Option<Self::Cursor> {
+        self.tree.nth_child_of(cursor, child_num)
+    }
+    fn contains_cursor(&self, cursor: &Self::Cursor) -> bool {
+        self.tree.contains_cursor(cursor) && is_in_subtree(self.tree, &self.key, cursor)
+    }
+}
+
+/// A *mutable* reference to a node in any [`Tree`], produced by its [`get_mut`] method. The mutable node references of the trees in this crate can be converted into it with `From`.
+///
+/// [`Tree`]: trait.Tree.html " "
+/// [`get_mut`]: trait.Tree.html#method.get_mut " "
+pub struct NodeRefMut<'a, T: Tree> {
+    pub(crate) tree: &'a mut T,
+    pub(crate) key: T::Cursor,
+}
+impl<'a, T: Tree> NodeRefMut<'a, T> {
+    /// Returns a reference the raw storage key for the node.
+    pub fn raw_key(&self) -> &T::Cursor {
+        &self.key
+    }
+    /// Consumes the reference and returns the underlying raw storage key for the node.
+    pub fn into_raw_key(self) -> T::Cursor {
+        self.key
+    }
+    /// Returns a handle to the node, which can be stored without borrowing the tree and [upgraded] back into a reference later.
+    ///
+    /// [upgraded]: trait.Tree.html#method.upgrade " "
+    pub fn handle(&self) -> NodeHandle<T::Cursor> {
+        NodeHandle {
+            key: self.key.clone(),
+        }
+    }
+    /// Converts the mutable reference into an immutable one.
+    pub fn downgrade(self) -> NodeRef<'a, T> {
+        NodeRef {
+            tree: self.tree,
+            key: self.key,
+        }
+    }
+    /// Returns an immutable reference to the same node, borrowing the mutable one.
+    pub fn as_ref(&self) -> NodeRef<'_, T> {
+        NodeRef {
+            tree: self.tree,
+            key: self.key.clone(),
+        }
+    }
+    /// Returns a reference to the data stored in the node.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn value(&self) -> NodeValue<&'_ T::Branch, &'_ T::Leaf> {
+        self.tree
+            .node_value(&self.key)
+            .expect("key was invalidated while a node reference existed")
+    }
+    /// Returns a *mutable* reference to the data stored in the node.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn value_mut(&mut self) -> NodeValue<&'_ mut T::Branch, &'_ mut T::Leaf> {
+        self.tree
+            .node_value_mut(&self.key)
+            .expect("key was invalidated while a node reference existed")
+    }
+    /// Returns a *mutable* reference to the parent node of the pointee, or `None` if it's the root node.
+    pub fn parent_mut(&mut self) -> Option<NodeRefMut<'_, T>> {
+        let key = self.tree.parent_key(&self.key)?;
+        Some(NodeRefMut {
+            tree: self.tree,
+            key,
+        })
+    }
+    /// Returns a *mutable* reference to the first child of the node, or `None` if it's a leaf node.
+    pub fn first_child_mut(&mut self) -> Option<NodeRefMut<'_, T>> {
+        let key = self.tree.first_child_key(&self.key)?;
+        Some(NodeRefMut {
+            tree: self.tree,
+            key,
+        })
+    }
+    /// Returns a *mutable* reference to the next sibling of the node, or `None` if it's the last child of its parent or the root node.
+    pub fn next_sibling_mut(&mut self) -> Option<NodeRefMut<'_, T>> {
+        let key = self.tree.next_sibling_key(&self.key)?;
+        Some(NodeRefMut {
+            tree: self.tree,
+            key,
+        })
+    }
+    /// Consumes the reference and returns a *mutable* reference to the parent node of the pointee, or the original reference back if it's the root node.
+    ///
+    /// # Errors
+    /// Returns the original reference if the node is the root node.
+    pub fn into_parent(self) -> Result<Self, Self> {
+        match self.tree.parent_key(&self.key) {
+            Some(key) => Ok(Self {
+                tree: self.tree,
+                key,
+            }),
+            None => Err(self),
+        }
+    }
+    /// Returns a *mutable* view of the subtree which starts at the node, with the node as its root node. See [`SubtreeMut`] for more.
+    ///
+    /// [`SubtreeMut`]: struct.SubtreeMut.html " "
+    pub fn subtree_mut(&mut self) -> SubtreeMut<'_, T> {
+        SubtreeMut {
+            tree: self.tree,
+            root: self.key.clone(),
+        }
+    }
+}
+impl<T: Tree + Debug> Debug for NodeRefMut<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NodeRefMut")
+            .field("tree", &self.tree)
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+/// A *mutable* view of the subtree which starts at a node in any [`Tree`], with the node as its root node. Created by the [`subtree_mut`] method on [`NodeRefMut`].
+///
+/// The view implements [`Traversable`] the same way as [`NodeRef`] does, as well as [`TraversableMut`], so that mutating visitors and algorithms can be confined to a part of a larger tree: moving above the node or to its siblings fails, and the node itself cannot be removed, just like the root node of a tree. Everything else is forwarded to the tree.
+///
+/// The view remembers the key of the node, so it can only be used with storages which do not move the nodes around when other ones are removed, such as sparse storage, which is the default one.
+///
+/// # Example
+/// ```rust
+/// use charcoal::{
+///     traversal::algorithms::prune,
+///     tree::NodeRefMut,
+///     FreeformTree,
+///     Tree,
+///     NodeValue,
+/// };
+///
+/// let mut tree = FreeformTree::<_>::new(0);
+/// tree.root_mut().make_branch(vec![1, 2, 3]).unwrap();
+/// tree.root_mut().first_child_mut().unwrap().make_branch(vec![4, 5, 6]).unwrap();
+///
+/// // Remove the odd nodes, but only under the first child of the root node.
+/// let mut root = tree.root_mut();
+/// let mut first_child = NodeRefMut::from(root.first_child_mut().unwrap());
+/// prune(&mut first_child.subtree_mut(), |value| *value.into_inner() % 2 == 0);
+///
+/// let values = tree.into_iter().map(NodeValue::into_inner).collect::<Vec<_>>();
+/// assert_eq!(values, [4, 6, 1, 2, 3, 0]);
+/// ```
+///
+/// [`Tree`]: trait.Tree.html " "
+/// [`subtree_mut`]: struct.NodeRefMut.html#method.subtree_mut " "
+/// [`NodeRefMut`]: struct.NodeRefMut.html " "
+/// [`Traversable`]: ../traversal/trait.Traversable.html " "
+/// [`NodeRef`]: struct.NodeRef.html#traversal " "
+/// [`TraversableMut`]: ../traversal/trait.TraversableMut.html " "
+pub struct SubtreeMut<'a, T: Tree> {
+    tree: &'a mut T,
+    root: T::Cursor,
+}
+impl<T: Tree> Traversable for SubtreeMut<'_, T> {
+    type Leaf = T::Leaf;
+    type Branch = T::Branch;
+    type Cursor = T::Cursor;
+
+    fn advance_cursor<V>(
+        &self,
+        cursor: Self::Cursor,
+        direction: VisitorDirection<Self::Cursor, V>,
+    ) -> CursorResult<Self::Cursor> {
+        advance_in_subtree(&*self.tree, &self.root, cursor, direction)
+    }
+    fn cursor_to_root(&self) -> Self::Cursor {
+        self.root.clone()
+    }
+    fn value_of(&self, cursor: &Self::Cursor) -> NodeValue<&'_ Self::Branch, &'_ Self::Leaf> {
+        self.tree.value_of(cursor)
+    }
+    fn parent_of(&self, cursor: &Self::Cursor) -> Option<Self::Cursor> {
+        parent_in_subtree(&*self.tree, &self.root, cursor)
+    }
+    fn num_children_of(&self, cursor: &Self::Cursor) -> usize {
+        self.tree.num_children_of(cursor)
+    }
+    fn nth_child_of(&self, cursor: &Self::Cursor, child_num: usize) -> Option<Self::Cursor> {
+        self.tree.nth_child_of(cursor, child_num)
+    }
+    fn contains_cursor(&self, cursor: &Self::Cursor) -> bool {
+        self.tree.contains_cursor(cursor) && is_in_subtree(&*self.tree, &self.root, cursor)
+    }
+}
+impl<T: Tree + TraversableMut> TraversableMut for SubtreeMut<'_, T> {
+    const CAN_REMOVE_INDIVIDUAL_CHILDREN: bool = T::CAN_REMOVE_INDIVIDUAL_CHILDREN;
+    const CAN_PACK_CHILDREN: bool = T::CAN_PACK_CHILDREN;
+    const CAN_ADD_INDIVIDUAL_CHILDREN: bool = T::CAN_ADD_INDIVIDUAL_CHILDREN;
+    type PackedChildren = T::PackedChildren;
+
+    fn value_mut_of(
+        &mut self,
+        cursor: &Self::Cursor,
+    ) -> NodeValue<&'_ mut Self::Branch, &'_ mut Self::Leaf> {
+        self.tree.value_mut_of(cursor)
+    }
+    fn try_remove_leaf<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<Self::Leaf, TryRemoveLeafError> {
+        if *cursor == self.root {
+            return Err(TryRemoveLeafError::WasRootNode);
+        }
+        self.tree.try_remove_leaf(cursor, branch_to_leaf)
+    }
+    #[allow(clippy::type_complexity)]
+    fn try_remove_branch_into<BtL: FnOnce(Self::Branch) -> Self::Leaf, C: FnMut(Self::Leaf)>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+        collector: C,
+    ) -> Result<Self::Branch, TryRemoveBranchError> {
+        if *cursor == self.root {
+            return Err(TryRemoveBranchError::WasRootNode);
+        }
+        self.tree
+            .try_remove_branch_into(cursor, branch_to_leaf, collector)
+    }
+    #[allow(clippy::type_complexity)]
+    fn try_remove_children_into<BtL: FnOnce(Self::Branch) -> Self::Leaf, C: FnMut(Self::Leaf)>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+        collector: C,
+    ) -> Result<(), TryRemoveChildrenError> {
+        self.tree
+            .try_remove_children_into(cursor, branch_to_leaf, collector)
+    }
+    #[allow(clippy::type_complexity)]
+    fn try_remove_branch<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<(Self::Branch, Self::PackedChildren), TryRemoveBranchError> {
+        if *cursor == self.root {
+            return Err(TryRemoveBranchError::WasRootNode);
+        }
+        self.tree.try_remove_branch(cursor, branch_to_leaf)
+    }
+    #[allow(clippy::type_complexity)]
+    fn try_remove_children<BtL: FnOnce(Self::Branch) -> Self::Leaf>(
+        &mut self,
+        cursor: &Self::Cursor,
+        branch_to_leaf: BtL,
+    ) -> Result<Self::PackedChildren, TryRemoveChildrenError> {
+        self.tree.try_remove_children(cursor, branch_to_leaf)
+    }
+    fn try_add_child<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        child_payload: Self::Leaf,
+        leaf_to_branch: LtB,
+    ) -> Result<Self::Cursor, TryAddChildError<Self::Leaf>> {
+        self.tree
+            .try_add_child(cursor, child_payload, leaf_to_branch)
+    }
+    #[allow(clippy::type_complexity)]
+    fn try_add_children<LtB: FnOnce(Self::Leaf) -> Self::Branch>(
+        &mut self,
+        cursor: &Self::Cursor,
+        children: Self::PackedChildren,
+        leaf_to_branch: LtB,
+    ) -> Result<(), MakeBranchError<Self::Leaf, Self::PackedChildren>> {
+        self.tree.try_add_children(cursor, children, leaf_to_branch)
+    }
+}
+impl<T: Tree + Debug> Debug for SubtreeMut<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubtreeMut")
+            .field("tree", &self.tree)
+            .field("root", &self.root)
+            .finish()
+    }
+}
+
+/// Moves a cursor within the subtree of a tree which starts at `root`, as if it were a tree of its own: the parent and the siblings of `root` are out of reach, and `root` is the root node.
+fn advance_in_subtree<T: Tree, V>(
+    tree: &T,
+    root: &T::Cursor,
+    cursor: T::Cursor,
+    direction: VisitorDirection<T::Cursor, V>,
+) -> CursorResult<T::Cursor> {
+    let error = CursorDirectionError {
+        previous_state: cursor.clone(),
+    };
+    match direction {
+        VisitorDirection::Parent
+        | VisitorDirection::NextSibling
+        | VisitorDirection::PrevSibling
+            if cursor == *root =>
+        {
+            Err(error)
+        }
+        VisitorDirection::NthAncestor(n) => (0..n)
+            .try_fold(cursor, |ancestor, _| {
+                parent_in_subtree(tree, root, &ancestor)
+            })
+            .ok_or(error),
+        VisitorDirection::Root => Ok(root.clone()),
+        VisitorDirection::SetTo(new_cursor) => {
+            if tree.contains_cursor(&new_cursor) && is_in_subtree(tree, root, &new_cursor) {
+                Ok(new_cursor)
+            } else {
+                Err(error)
+            }
+        }
+        direction => tree.advance_cursor(cursor, direction),
+    }
+}
+/// Returns the parent of the specified node within the subtree of a tree which starts at `root`.
+fn parent_in_subtree<T: Tree>(tree: &T, root: &T::Cursor, cursor: &T::Cursor) -> Option<T::Cursor> {
+    if cursor == root {
+        None
+    } else {
+        tree.parent_of(cursor)
+    }
+}
+/// Returns `true` if the node with the specified key is `root` itself or one of its descendants, `false` otherwise.
+fn is_in_subtree<T: Tree>(tree: &T, root: &T::Cursor, key: &T::Cursor) -> bool {
+    let mut current = key.clone();
+    loop {
+        if current == *root {
+            return true;
+        }
+        match tree.parent_key(&current) {
+            Some(parent) => current = parent,
+            None => return false,
+        }
+    }
+}
+
+/// An iterator over the keys of the children of a node in a [`Tree`].
+///
+/// Created by the [`children_keys`] method on `Tree`.
+///
+/// [`Tree`]: trait.Tree.html " "
+/// [`children_keys`]: trait.Tree.html#method.children_keys " "
+pub struct ChildKeys<'a, T: Tree> {
+    tree: &'a T,
+    next: Option<T::Cursor>,
+}
+impl<T: Tree> Iterator for ChildKeys<'_, T> {
+    type Item = T::Cursor;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+        self.next = self.tree.next_sibling_key(&current);
+        Some(current)
+    }
+}
+impl<T: Tree> FusedIterator for ChildKeys<'_, T> {}
+impl<T: Tree> Clone for ChildKeys<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree,
+            next: self.next.clone(),
+        }
+    }
+}
+impl<T: Tree> Debug for ChildKeys<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChildKeys")
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+/// An iterator over references to the children of a node in a [`Tree`].
+///
+/// Created by the [`children`] method on `NodeRef`.
+///
+/// [`Tree`]: trait.Tree.html " "
+/// [`children`]: struct.NodeRef.html#method.children " "
+pub struct Children<'a, T: Tree> {
+    inner: ChildKeys<'a, T>,
+}
+impl<'a, T: Tree> Iterator for Children<'a, T> {
+    type Item = NodeRef<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = self.inner.tree;
+        self.inner.next().map(|key| NodeRef { tree, key })
+    }
+}
+impl<T: Tree> FusedIterator for Children<'_, T> {}
+impl<T: Tree> Clone for Children<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+impl<T: Tree> Debug for Children<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Children")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+/// An event in a depth-first pre-order description of a tree, produced by [`Tree::events`] and consumed by [`TreeWriter`].
+///
+/// [`Tree::events`]: trait.Tree.html#method.events " "
+/// [`TreeWriter`]: ../freeform_tree/struct.TreeWriter.html " "
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PreorderEvent<B, L = B> {
+    /// Starts a branch node with the specified payload. The events which follow describe its children, up to the matching `Exit` event.
+    Enter(B),
+    /// Adds a leaf node with the specified payload.
+    Leaf(L),
+    /// Ends the branch node started by the last unmatched `Enter` event.
+    Exit,
+}
+
+/// An iterator over the nodes of a [`Tree`] as a stream of [pre-order events].
+///
+/// Created by the [`events`] method on `Tree`.
+///
+/// [`Tree`]: trait.Tree.html " "
+/// [pre-order events]: enum.PreorderEvent.html " "
+/// [`events`]: trait.Tree.html#method.events " "
+pub struct Events<'a, T: Tree> {
+    tree: &'a T,
+    next: Option<EventStep<T::Cursor>>,
+}
+/// The next thing to do for an `Events` iterator.
+#[derive(Copy, Clone, Debug)]
+enum EventStep<K> {
+    /// Emit the `Enter` or `Leaf` event for the node.
+    Visit(K),
+    /// Emit the `Exit` event for the branch node.
+    Exit(K),
+}
+impl<T: Tree> Events<'_, T> {
+    /// Returns the step which follows the events for the node and its descendants.
+    fn step_after(&self, key: &T::Cursor) -> Option<EventStep<T::Cursor>> {
+        if *key == self.tree.root_key() {
+            return None;
+        }
+        let step = self.tree.next_sibling_key(key).map_or_else(
+            || {
+                EventStep::Exit(
+                    self.tree
+                        .parent_key(key)
+                        .expect("a non-root node always has a parent"),
+                )
+            },
+            EventStep::Visit,
+        );
+        Some(step)
+    }
+}
+impl<'a, T: Tree> Iterator for Events<'a, T> {
+    type Item = PreorderEvent<&'a T::Branch, &'a T::Leaf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = self.tree;
+        match self.next.take()? {
+            EventStep::Visit(key) => {
+                let value = tree.node_value(&key).expect("keys of reachable nodes are valid");
+                match value {
+                    NodeValue::Branch(payload) => {
+                        self.next = tree.first_child_key(&key).map(EventStep::Visit);
+                        Some(PreorderEvent::Enter(payload))
+                    }
+                    NodeValue::Leaf(payload) => {
+                        self.next = self.step_after(&key);
+                        Some(PreorderEvent::Leaf(payload))
+                    }
+                }
+            }
+            EventStep::Exit(key) => {
+                self.next = self.step_after(&key);
+                Some(PreorderEvent::Exit)
+            }
+        }
+    }
+}
+impl<T: Tree> FusedIterator for Events<'_, T> {}
+impl<T: Tree> Clone for Events<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree,
+            next: self.next.clone(),
+        }
+    }
+}
+impl<T: Tree> Debug for Events<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Events")
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+/// An iterator over the levels of a [`Tree`] in breadth-first order.
+///
+/// Created by the [`iter_levels`] method on `Tree`.
+///
+/// [`Tree`]: trait.Tree.html " "
+/// [`iter_levels`]: trait.Tree.html#method.iter_levels " "
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub struct Levels<'a, T: Tree> {
+    tree: &'a T,
+    level: Vec<T::Cursor>,
+}
+#[cfg(feature = "alloc")]
+impl<'a, T: Tree> Iterator for Levels<'a, T> {
+    type Item = Vec<NodeRef<'a, T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.level.is_empty() {
+            return None;
+        }
+        let tree = self.tree;
+        let next_level = self
+            .level
+            .iter()
+            .flat_map(|key| tree.children_keys(key))
+            .collect();
+        let level = mem::replace(&mut self.level, next_level);
+        Some(level.into_iter().map(|key| NodeRef { tree, key }).collect())
+    }
+}
+#[cfg(feature = "alloc")]
+impl<T: Tree> FusedIterator for Levels<'_, T> {}
+#[cfg(feature = "alloc")]
+impl<T: Tree> Clone for Levels<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree,
+            level: self.level.clone(),
+        }
+    }
+}
+#[cfg(feature = "alloc")]
+impl<T: Tree> Debug for Levels<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Levels")
+            .field("level", &self.level)
+            .finish()
+    }
+}
+
+/// An iterator over the leaf nodes of a [`Tree`] and the paths leading to them.
+///
+/// Created by the [`leaf_paths`] method on `Tree`.
+///
+/// [`Tree`]: trait.Tree.html " "
+/// [`leaf_paths`]: trait.Tree.html#method.leaf_paths " "
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "doc_cfg", doc(cfg(feature = "alloc")))]
+pub struct LeafPaths<'a, T: Tree> {
+    tree: &'a T,
+    next: Option<T::Cursor>,
+    /// The child indices leading to the `next` node.
+    indices: Vec<usize>,
+}
+#[cfg(feature = "alloc")]
+impl<T: Tree> LeafPaths<'_, T> {
+    /// Returns the node which follows the specified one and its descendants in pre-order, updating the indices accordingly.
+    fn step_after(&mut self, mut key: T::Cursor) -> Option<T::Cursor> {
+        let root = self.tree.root_key();
+        while key != root {
+            if let Some(sibling) = self.tree.next_sibling_key(&key) {
+                *self.indices.last_mut().expect("a non-root node has a path") += 1;
+                return Some(sibling);
+            }
+            self.indices.pop();
+            key = self
+                .tree
+                .parent_key(&key)
+                .expect("a non-root node always has a parent");
+        }
+        None
+    }
+}
+#[cfg(feature = "alloc")]
+impl<'a, T: Tree> Iterator for LeafPaths<'a, T> {
+    type Item = (Path, NodeRef<'a, T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = self.tree;
+        loop {
+            let key = self.next.take()?;
+            let value = tree
+                .node_value(&key)
+                .expect("keys of reachable nodes are valid");
+            if let NodeValue::Leaf(..) = value {
+                let path = Path::from(self.indices.clone());
+                self.next = self.step_after(key.clone());
+                return Some((path, NodeRef { tree, key }));
+            }
+            // Branch nodes without children are skipped along with their empty subtrees.
+            self.next = match tree.first_child_key(&key) {
+                Some(child) => {
+                    self.indices.push(0);
+                    Some(child)
+                }
+                None => self.step_after(key),
+            };
+        }
+    }
+}
+#[cfg(feature = "alloc")]
+impl<T: Tree> FusedIterator for LeafPaths<'_, T> {}
+#[cfg(feature = "alloc")]
+impl<T: Tree> Clone for LeafPaths<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree,
+            next: self.next.clone(),
+            indices: self.indices.clone(),
+        }
+    }
+}
+#[cfg(feature = "alloc")]
+impl<T: Tree> Debug for LeafPaths<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LeafPaths")
+            .field("next", &self.next)
+            .field("indices", &self.indices)
+            .finish()
+    }
+}
@@ -1,3 +1,15 @@
+#[cfg(any(
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+use crate::{
+    storage::{Storage, ListStorage, SparseStorage, SparseStorageSlot, MoveFix},
+    tree::{Tree, next_in_preorder},
+    NodeValue,
+};
+
 /*
 const INLINE_STACK_SIZE: usize = 128;
 
@@ -11,13 +23,17 @@ pub(crate) type Stack<T> = alloc::vec::Vec<T>;
 ))]
 pub(crate) type Stack<T> = arrayvec::ArrayVec<[T; INLINE_STACK_SIZE]>;*/
 
+#[cfg(any(feature = "octree", feature = "quadtree"))]
 pub trait ArrayMap<T, U> {
     type Output;
+    const LEN: usize;
     fn array_map(self, f: impl FnMut(T) -> U) -> Self::Output;
     fn array_map_by_ref(&self, f: impl FnMut(&T) -> U) -> Self::Output;
 }
+#[cfg(any(feature = "octree", feature = "quadtree"))]
 impl<T, U> ArrayMap<T, U> for [T; 4] {
     type Output = [U; 4];
+    const LEN: usize = 4;
     fn array_map(self, mut f: impl FnMut(T) -> U) -> Self::Output {
         let [e0, e1, e2, e3] = self;
         [f(e0), f(e1), f(e2), f(e3)]
@@ -27,8 +43,10 @@ impl<T, U> ArrayMap<T, U> for [T; 4] {
         [f(e0), f(e1), f(e2), f(e3)]
     }
 }
+#[cfg(any(feature = "octree", feature = "quadtree"))]
 impl<T, U> ArrayMap<T, U> for [T; 8] {
     type Output = [U; 8];
+    const LEN: usize = 8;
     fn array_map(self, mut f: impl FnMut(T) -> U) -> Self::Output {
         let [e0, e1, e2, e3, e4, e5, e6, e7] = self;
         [f(e0), f(e1), f(e2), f(e3), f(e4), f(e5), f(e6), f(e7)]
@@ -39,13 +57,188 @@ impl<T, U> ArrayMap<T, U> for [T; 8] {
     }
 }
 
+#[cfg(any(feature = "octree", feature = "quadtree"))]
+// Reserves space for all elements at once instead of letting every addition reallocate
+pub fn add_many<S: Storage, A: ArrayMap<S::Element, S::Key>>(storage: &mut S, elements: A) -> A::Output {
+    storage.reserve(A::LEN);
+    elements.array_map(|element| storage.add(element))
+}
+
+#[cfg(any(
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+/// Removes all holes from the sparse storage of a tree, using the specified function to access the storage and the key of the root node and calling `on_move` with the old and new key of every node which was moved.
+///
+/// `defragment_and_fix` fills a hole by rotating all elements after it while only fixing the links of one of them, so it's only used for removing the holes once they're all at the end. To get them there, every node which is stored past the point where the storage will end is removed and added back, which puts it into the oldest hole, until it ends up before that point. Since the holes which are created along the way are newer than all of the ones which were there before, all holes before that point get filled.
+pub fn defragment_tree<T, E, S>(
+    tree: &mut T,
+    parts: for<'a> fn(&'a mut T) -> (&'a mut SparseStorage<E, S>, &'a mut usize),
+    mut on_move: impl FnMut(usize, usize),
+) where
+    T: Tree<Cursor = usize>,
+    E: MoveFix,
+    S: ListStorage<Element = SparseStorageSlot<E>>,
+{
+    let num_holes = parts(tree).0.num_holes();
+    if num_holes == 0 {
+        return;
+    }
+    let new_len = tree.num_nodes() - num_holes;
+    let mut next = Some(tree.root_key());
+    while let Some(current) = next {
+        // Moving a node does not change the keys of any other ones, so the next node in
+        // pre-order can be found before moving the current one.
+        next = next_in_preorder(tree, current);
+        let (storage, root) = parts(tree);
+        let mut key = current;
+        while key >= new_len {
+            let node = storage.remove_and_shiftfix(key);
+            let new_key = ListStorage::add(storage, node);
+            unsafe {
+                // SAFETY: the node was just moved from the old key to the new one
+                E::fix_move(storage, key, new_key);
+            }
+            if *root == key {
+                *root = new_key;
+            }
+            key = new_key;
+        }
+        if key != current {
+            on_move(current, key);
+        }
+    }
+    parts(tree).0.defragment_and_fix();
+}
+
+/// Removes the nodes which cannot be reached from the root node from the sparse storage of a tree, using the specified function to access the storage, and returns the number of nodes which were removed. The keys of the reachable nodes do not change.
+///
+/// Since the sparse storage does not say which of its slots are holes, the reachable nodes are moved into a new storage at the same indices, with holes punched in place of everything else. The node which is stored last is used to fill the gaps before it's put into its own slot, since a gap can only be turned into a hole by removing an element from it. Whatever remains in the old storage is then dropped along with it.
+#[cfg(all(
+    feature = "alloc",
+    any(
+        feature = "binary_tree",
+        feature = "octree",
+        feature = "quadtree",
+        feature = "freeform_tree",
+    ),
+))]
+pub fn collect_unreachable_in_tree<T, E, S>(
+    tree: &mut T,
+    storage: for<'a> fn(&'a mut T) -> &'a mut SparseStorage<E, S>,
+) -> usize
+where
+    T: Tree<Cursor = usize>,
+    E: MoveFix,
+    S: ListStorage<Element = SparseStorageSlot<E>>,
+{
+    let num_slots = tree.num_nodes();
+    let mut reachable = alloc::vec![false; num_slots];
+    let mut num_reachable = 0;
+    let mut last_reachable = 0;
+    let mut next = Some(tree.root_key());
+    while let Some(key) = next {
+        reachable[key] = true;
+        num_reachable += 1;
+        last_reachable = last_reachable.max(key);
+        next = next_in_preorder(tree, key);
+    }
+    let old_storage = storage(tree);
+    let num_unreachable = num_slots - old_storage.num_holes() - num_reachable;
+    if num_unreachable == 0 {
+        return 0;
+    }
+    let mut new_storage: SparseStorage<E, S> = ListStorage::with_capacity(last_reachable + 1);
+    let mut last_node = Storage::remove(old_storage, &last_reachable);
+    for (index, &is_reachable) in reachable[..last_reachable].iter().enumerate() {
+        if is_reachable {
+            ListStorage::push(&mut new_storage, Storage::remove(old_storage, &index));
+        } else {
+            ListStorage::push(&mut new_storage, last_node);
+            last_node = Storage::remove(&mut new_storage, &index);
+        }
+    }
+    ListStorage::push(&mut new_storage, last_node);
+    *old_storage = new_storage;
+    num_unreachable
+}
+
+#[cfg(any(
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+/// Applies the closures to the payloads of all nodes of a tree with sparse storage, using the specified functions to access the storage and the payload of a node.
+///
+/// If the storage has no holes, every slot holds a node, so the storage is simply processed in order, which is a lot friendlier to the cache than following the links between the nodes. Otherwise, there's no way to tell the holes apart without hitting them, so the tree is walked instead.
+pub fn map_values_in_tree<T, E, S>(
+    tree: &mut T,
+    storage: for<'a> fn(&'a mut T) -> &'a mut SparseStorage<E, S>,
+    value: for<'a> fn(&'a mut E) -> NodeValue<&'a mut T::Branch, &'a mut T::Leaf>,
+    mut branch_fn: impl FnMut(&mut T::Branch),
+    mut leaf_fn: impl FnMut(&mut T::Leaf),
+) where
+    T: Tree<Cursor = usize>,
+    S: ListStorage<Element = SparseStorageSlot<E>>,
+{
+    if !storage(tree).is_dense() {
+        tree.map_values_mut(branch_fn, leaf_fn);
+        return;
+    }
+    let storage = storage(tree);
+    for index in 0..ListStorage::len(storage) {
+        let element = unsafe {
+            // SAFETY: the index is in bounds and there are no holes
+            ListStorage::get_unchecked_mut(storage, index)
+        };
+        match value(element) {
+            NodeValue::Branch(payload) => branch_fn(payload),
+            NodeValue::Leaf(payload) => leaf_fn(payload),
+        }
+    }
+}
+
+#[cfg(any(
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+/// Removes all elements from a sparse storage without giving up its capacity, adds the specified root node and returns its key.
+///
+/// Truncating the sparse storage only truncates the storage inside it, leaving its list of holes pointing past the end, so the holes are removed beforehand. If dropping one of the payloads panics, the tree is left without a root node, so the process is aborted instead.
+pub fn clear_storage<E, S>(storage: &mut SparseStorage<E, S>, root: E) -> usize
+where
+    S: ListStorage<Element = SparseStorageSlot<E>>,
+{
+    if !storage.is_dense() {
+        storage.defragment();
+    }
+    abort_on_panic(|| ListStorage::truncate(storage, 0));
+    ListStorage::add(storage, root)
+}
+
+#[cfg(any(
+    feature = "binary_tree",
+    feature = "octree",
+    feature = "quadtree",
+    feature = "freeform_tree",
+))]
+// Storages which don't have a fixed capacity are assumed to always have enough space
+pub fn has_room_for<S: Storage>(storage: &S, additional: usize) -> bool {
+    S::CAPACITY.map_or(true, |capacity| storage.len() + additional <= capacity)
+}
+
 #[cfg_attr(debug_assertions, track_caller)]
 pub unsafe fn unreachable_debugchecked(msg: &str) -> ! {
     #[cfg(debug_assertions)]
     {
         // Most of those panics are in a tree corrupton context, so we should
         // just abort the process to prevent unwinders from collecting corrupted data
-        abort_on_panic(|| unreachable!(msg))
+        abort_on_panic(|| unreachable!("{}", msg))
     }
     #[cfg(not(debug_assertions))]
     {
@@ -65,3 +258,38 @@ pub fn abort_on_panic<R>(f: impl FnOnce() -> R) -> R {
         f()
     }
 }
+
+/// Encodes a path of child indices, listed from the node up to the root, into a locational code with the specified number of bits per level. Returns `None` if the path is too long for the code to fit into 64 bits.
+#[cfg(any(feature = "octree", feature = "quadtree"))]
+pub fn encode_locational_code(
+    path_upwards: impl IntoIterator<Item = u8>,
+    bits_per_level: u32,
+) -> Option<u64> {
+    let mut code = 0_u64;
+    let mut shift = 0_u32;
+    for index in path_upwards {
+        // The sentinel bit has to fit after all of the levels
+        if shift + bits_per_level > 63 {
+            return None;
+        }
+        code |= u64::from(index) << shift;
+        shift += bits_per_level;
+    }
+    Some(code | 1 << shift)
+}
+/// Decodes a locational code with the specified number of bits per level into a path of child indices, listed from the root down to the node. Returns `None` if the code is zero or its sentinel bit is not at a level boundary.
+#[cfg(any(feature = "octree", feature = "quadtree"))]
+pub fn decode_locational_code(code: u64, bits_per_level: u32) -> Option<impl Iterator<Item = u8>> {
+    let significant_bits = 63_u32.checked_sub(code.leading_zeros())?;
+    if significant_bits % bits_per_level != 0 {
+        return None;
+    }
+    let depth = significant_bits / bits_per_level;
+    let mask = (1 << bits_per_level) - 1;
+    #[allow(clippy::cast_possible_truncation)] // It's masked, you dummy
+    Some(
+        (0..depth)
+            .rev()
+            .map(move |level| ((code >> (level * bits_per_level)) & mask) as u8),
+    )
+}